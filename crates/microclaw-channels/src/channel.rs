@@ -1,6 +1,7 @@
+use std::future::Future;
 use std::sync::Arc;
 
-use crate::channel_adapter::ChannelRegistry;
+use crate::channel_adapter::{ChannelRegistry, OutboundDecision};
 use microclaw_storage::db::{call_blocking, Database, StoredMessage};
 
 #[derive(Clone, Debug)]
@@ -214,14 +215,109 @@ pub async fn enforce_channel_policy(
     Ok(())
 }
 
+/// Runs the registered outbound filter over bot text. Returns the text to
+/// deliver, or an error when a hook blocked it.
+async fn apply_outbound_filter(
+    registry: &ChannelRegistry,
+    channel: &str,
+    chat_id: i64,
+    text: &str,
+) -> Result<String, String> {
+    match registry.outbound_filter() {
+        Some(filter) => match filter.filter_outbound(channel, chat_id, text).await {
+            OutboundDecision::Send(text) => Ok(text),
+            OutboundDecision::Block(reason) => Err(format!("Outbound message blocked: {reason}")),
+        },
+        None => Ok(text.to_string()),
+    }
+}
+
+/// Whether a registered outbound filter would inspect bot text right now.
+pub async fn outbound_filter_active(registry: &ChannelRegistry) -> bool {
+    match registry.outbound_filter() {
+        Some(filter) => filter.is_active().await,
+        None => false,
+    }
+}
+
+/// Send and store an agent's final reply through a channel's own transport
+/// (threads, chunking, rich formatting). The outbound filter runs first; a
+/// blocked reply is neither sent nor stored.
+pub async fn send_and_store_channel_reply<F, Fut>(
+    registry: &ChannelRegistry,
+    db: Arc<Database>,
+    channel: &str,
+    bot_username: &str,
+    chat_id: i64,
+    text: &str,
+    send: F,
+) -> Result<(), String>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let text = apply_outbound_filter(registry, channel, chat_id, text).await?;
+    send(text.clone()).await;
+    let msg = StoredMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        chat_id,
+        sender_name: bot_username.to_string(),
+        content: text,
+        is_from_bot: true,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+    call_blocking(db, move |d| d.store_message(&msg))
+        .await
+        .map_err(|e| format!("Failed to store sent message: {e}"))
+}
+
+/// What `deliver_and_store_bot_message` did with bot text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BotDelivery {
+    /// Sent and stored; holds the text after the outbound filter.
+    Sent(String),
+    /// Blocked by the outbound filter with this reason; nothing was sent or stored.
+    Blocked(String),
+}
+
+impl BotDelivery {
+    /// The text the chat actually saw, or a marker in place of a blocked message.
+    pub fn into_visible_text(self) -> String {
+        match self {
+            BotDelivery::Sent(text) => text,
+            BotDelivery::Blocked(reason) => format!("[Message blocked: {reason}]"),
+        }
+    }
+
+    /// Treat a blocked message as a delivery failure.
+    pub fn into_result(self) -> Result<String, String> {
+        match self {
+            BotDelivery::Sent(text) => Ok(text),
+            BotDelivery::Blocked(reason) => Err(format!("Outbound message blocked: {reason}")),
+        }
+    }
+}
+
+/// Filter, send and store bot text. Callers that show the reply anywhere else
+/// must use the returned text, not their own copy.
 pub async fn deliver_and_store_bot_message(
     registry: &ChannelRegistry,
     db: Arc<Database>,
     bot_username: &str,
     chat_id: i64,
     text: &str,
-) -> Result<(), String> {
+) -> Result<BotDelivery, String> {
     let routing = get_required_chat_routing(registry, db.clone(), chat_id).await?;
+    let text = match registry.outbound_filter() {
+        Some(filter) => match filter
+            .filter_outbound(&routing.channel_name, chat_id, text)
+            .await
+        {
+            OutboundDecision::Send(text) => text,
+            OutboundDecision::Block(reason) => return Ok(BotDelivery::Blocked(reason)),
+        },
+        None => text.to_string(),
+    };
     let external_chat_id = call_blocking(db.clone(), move |d| d.get_chat_external_id(chat_id))
         .await
         .map_err(|e| format!("Failed to read external chat id for chat {chat_id}: {e}"))?
@@ -229,7 +325,7 @@ pub async fn deliver_and_store_bot_message(
 
     if let Some(adapter) = registry.get(&routing.channel_name) {
         if !adapter.is_local_only() {
-            adapter.send_text(&external_chat_id, text).await?;
+            adapter.send_text(&external_chat_id, &text).await?;
         }
     } else {
         return Err(format!(
@@ -242,13 +338,14 @@ pub async fn deliver_and_store_bot_message(
         id: uuid::Uuid::new_v4().to_string(),
         chat_id,
         sender_name: bot_username.to_string(),
-        content: text.to_string(),
+        content: text.clone(),
         is_from_bot: true,
         timestamp: chrono::Utc::now().to_rfc3339(),
     };
     call_blocking(db.clone(), move |d| d.store_message(&msg))
         .await
-        .map_err(|e| format!("Failed to store sent message: {e}"))
+        .map_err(|e| format!("Failed to store sent message: {e}"))?;
+    Ok(BotDelivery::Sent(text))
 }
 
 #[cfg(test)]
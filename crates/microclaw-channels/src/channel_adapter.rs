@@ -38,6 +38,28 @@ pub trait ChannelAdapter: Send + Sync {
     }
 }
 
+/// Decision returned by an [`OutboundMessageFilter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutboundDecision {
+    /// Deliver this text (possibly rewritten by the filter).
+    Send(String),
+    /// Do not deliver; the reason is returned to the caller.
+    Block(String),
+}
+
+/// Inspects bot text right before `deliver_and_store_bot_message` or
+/// `send_and_store_channel_reply` sends it.
+#[async_trait]
+pub trait OutboundMessageFilter: Send + Sync {
+    async fn filter_outbound(&self, channel: &str, chat_id: i64, text: &str) -> OutboundDecision;
+
+    /// Whether the filter can currently change or block anything. Channels
+    /// that stream drafts only do so while this is false.
+    async fn is_active(&self) -> bool {
+        true
+    }
+}
+
 #[derive(Default)]
 pub struct ChannelRegistry {
     adapters: HashMap<String, Arc<dyn ChannelAdapter>>,
@@ -45,6 +67,7 @@ pub struct ChannelRegistry {
     type_to_channel: HashMap<String, String>,
     /// "slack_dm" -> Private, "group" -> Group, etc.
     type_to_conversation: HashMap<String, ConversationKind>,
+    outbound_filter: Option<Arc<dyn OutboundMessageFilter>>,
 }
 
 impl ChannelRegistry {
@@ -67,6 +90,14 @@ impl ChannelRegistry {
         self.adapters.get(name)
     }
 
+    pub fn set_outbound_filter(&mut self, filter: Arc<dyn OutboundMessageFilter>) {
+        self.outbound_filter = Some(filter);
+    }
+
+    pub fn outbound_filter(&self) -> Option<&Arc<dyn OutboundMessageFilter>> {
+        self.outbound_filter.as_ref()
+    }
+
     /// Resolve a DB chat_type string to the adapter and conversation kind.
     pub fn resolve(
         &self,
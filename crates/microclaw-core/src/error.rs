@@ -1,11 +1,39 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+/// How an upstream LLM failure should be handled, classified from the HTTP status
+/// or the provider's structured error code rather than from the message text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmErrorKind {
+    /// Rate limited or out of quota.
+    RateLimited,
+    /// Overloaded, unavailable, timed out or failed server-side; may succeed later.
+    Transient,
+    /// Rejected as sent (bad request, auth, policy); retrying will not help.
+    Permanent,
+}
+
+impl LlmErrorKind {
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            429 => LlmErrorKind::RateLimited,
+            408 | 500..=599 => LlmErrorKind::Transient,
+            _ => LlmErrorKind::Permanent,
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 #[allow(dead_code)]
 pub enum MicroClawError {
     #[error("LLM API error: {0}")]
     LlmApi(String),
 
+    /// An error reported by the LLM provider, with its classification.
+    #[error("LLM API error: {message}")]
+    LlmUpstream { kind: LlmErrorKind, message: String },
+
     #[error("Rate limited, retry after backoff")]
     RateLimited,
 
@@ -31,6 +59,44 @@ pub enum MicroClawError {
     MaxIterations(usize),
 }
 
+impl MicroClawError {
+    /// An error for a non-success HTTP response, classified by its status.
+    pub fn llm_status(status: u16, message: impl Into<String>) -> Self {
+        MicroClawError::LlmUpstream {
+            kind: LlmErrorKind::from_status(status),
+            message: message.into(),
+        }
+    }
+
+    /// Whether the error is transient (rate limits, timeouts, upstream 5xx/overload)
+    /// and the same request may succeed on retry or on another provider.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            MicroClawError::RateLimited => true,
+            MicroClawError::Http(e) => {
+                e.is_timeout()
+                    || e.is_connect()
+                    || e.is_request()
+                    || e.status()
+                        .is_some_and(|s| s.is_server_error() || s.as_u16() == 429)
+            }
+            MicroClawError::LlmUpstream { kind, .. } => *kind != LlmErrorKind::Permanent,
+            _ => false,
+        }
+    }
+
+    /// Whether the error is a rate limit. The HTTP providers already back off and
+    /// retry 429s themselves before giving up with one of these.
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            MicroClawError::RateLimited => true,
+            MicroClawError::Http(e) => e.status().is_some_and(|s| s.as_u16() == 429),
+            MicroClawError::LlmUpstream { kind, .. } => *kind == LlmErrorKind::RateLimited,
+            _ => false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,6 +133,33 @@ mod tests {
         assert!(e.to_string().contains("JSON error"));
     }
 
+    #[test]
+    fn test_is_retryable_classification() {
+        assert!(MicroClawError::RateLimited.is_retryable());
+        assert!(MicroClawError::llm_status(503, "busy").is_retryable());
+        assert!(MicroClawError::llm_status(529, "overloaded_error: Overloaded").is_retryable());
+        assert!(MicroClawError::llm_status(429, "slow down").is_retryable());
+        assert!(!MicroClawError::llm_status(400, "invalid_request_error: too long").is_retryable());
+        assert!(!MicroClawError::llm_status(401, "bad key").is_retryable());
+        // The wording of an unclassified error never triggers failover.
+        assert!(!MicroClawError::LlmApi("HTTP 503: overloaded".into()).is_retryable());
+        assert!(!MicroClawError::Config("missing key".into()).is_retryable());
+        assert!(!MicroClawError::MaxIterations(3).is_retryable());
+    }
+
+    #[test]
+    fn test_is_rate_limited_classification() {
+        assert!(MicroClawError::RateLimited.is_rate_limited());
+        assert!(MicroClawError::llm_status(429, "slow down").is_rate_limited());
+        assert!(MicroClawError::LlmUpstream {
+            kind: LlmErrorKind::RateLimited,
+            message: "RESOURCE_EXHAUSTED: quota".into(),
+        }
+        .is_rate_limited());
+        assert!(!MicroClawError::llm_status(529, "overloaded_error: Overloaded").is_rate_limited());
+        assert!(!MicroClawError::LlmApi("rate_limit_error: slow down".into()).is_rate_limited());
+    }
+
     #[test]
     fn test_error_debug() {
         let e = MicroClawError::RateLimited;
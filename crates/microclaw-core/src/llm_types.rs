@@ -37,6 +37,17 @@ pub enum ContentBlock {
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
     },
+    /// Extended-thinking block. Anthropic requires these to be sent back unchanged
+    /// (with their signature) in the assistant turn that precedes a tool result.
+    #[serde(rename = "thinking")]
+    Thinking {
+        thinking: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+    },
+    /// Encrypted thinking the provider withheld; opaque, but must be round-tripped.
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,7 +75,7 @@ pub struct MessagesRequest {
     pub stream: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct MessagesResponse {
     pub content: Vec<ResponseContentBlock>,
@@ -72,7 +83,7 @@ pub struct MessagesResponse {
     pub usage: Option<Usage>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum ResponseContentBlock {
     #[serde(rename = "text")]
@@ -85,16 +96,31 @@ pub enum ResponseContentBlock {
         #[serde(default)]
         thought_signature: Option<String>,
     },
-    /// Catch-all for unknown block types (e.g. "thinking" from MiniMax M2.5)
+    #[serde(rename = "thinking")]
+    Thinking {
+        thinking: String,
+        #[serde(default)]
+        signature: Option<String>,
+    },
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
+    /// Catch-all for unknown block types
     #[serde(other)]
     Other,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Usage {
+    /// Uncached input tokens. For Anthropic this excludes cache reads/writes.
     pub input_tokens: u32,
     pub output_tokens: u32,
+    /// Input tokens written to the provider's prompt cache.
+    #[serde(default)]
+    pub cache_creation_input_tokens: u32,
+    /// Input tokens served from the provider's prompt cache.
+    #[serde(default)]
+    pub cache_read_input_tokens: u32,
 }
 
 #[cfg(test)]
@@ -199,6 +225,64 @@ mod tests {
         assert_eq!(resp.usage.as_ref().unwrap().output_tokens, 5);
     }
 
+    #[test]
+    fn test_thinking_blocks_round_trip() {
+        let resp: MessagesResponse = serde_json::from_value(json!({
+            "content": [
+                {"type": "thinking", "thinking": "let me see", "signature": "sig1"},
+                {"type": "redacted_thinking", "data": "opaque"},
+                {"type": "text", "text": "Done"}
+            ],
+            "stop_reason": "end_turn",
+            "usage": null
+        }))
+        .unwrap();
+        match &resp.content[0] {
+            ResponseContentBlock::Thinking {
+                thinking,
+                signature,
+            } => {
+                assert_eq!(thinking, "let me see");
+                assert_eq!(signature.as_deref(), Some("sig1"));
+            }
+            _ => panic!("Expected Thinking block"),
+        }
+        assert!(matches!(
+            &resp.content[1],
+            ResponseContentBlock::RedactedThinking { data } if data == "opaque"
+        ));
+
+        let block = ContentBlock::Thinking {
+            thinking: "hmm".into(),
+            signature: None,
+        };
+        let v = serde_json::to_value(&block).unwrap();
+        assert_eq!(v, json!({"type": "thinking", "thinking": "hmm"}));
+        let v = serde_json::to_value(ContentBlock::RedactedThinking {
+            data: "opaque".into(),
+        })
+        .unwrap();
+        assert_eq!(v, json!({"type": "redacted_thinking", "data": "opaque"}));
+    }
+
+    #[test]
+    fn test_usage_cache_token_deserialization() {
+        let usage: Usage = serde_json::from_value(json!({
+            "input_tokens": 12,
+            "output_tokens": 3,
+            "cache_creation_input_tokens": 2048,
+            "cache_read_input_tokens": 4096
+        }))
+        .unwrap();
+        assert_eq!(usage.cache_creation_input_tokens, 2048);
+        assert_eq!(usage.cache_read_input_tokens, 4096);
+
+        let usage: Usage =
+            serde_json::from_value(json!({"input_tokens": 1, "output_tokens": 2})).unwrap();
+        assert_eq!(usage.cache_creation_input_tokens, 0);
+        assert_eq!(usage.cache_read_input_tokens, 0);
+    }
+
     #[test]
     fn test_response_content_block_tool_use_deserialization() {
         let json = json!({
@@ -1,14 +1,30 @@
 use rusqlite::OptionalExtension;
 use rusqlite::{params, Connection};
-use std::path::Path;
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 #[cfg(feature = "sqlite-vec")]
 use std::sync::Once;
 use std::sync::{Mutex, MutexGuard};
 
 use microclaw_core::error::MicroClawError;
 
+use crate::memory_bundle::{
+    BundleEdge, BundleEmbedding, BundleHeader, BundleMemory, BundleRecord, BundleScope,
+    ExportOptions, ImportReport, MergeStrategy, BUNDLE_FORMAT, BUNDLE_VERSION,
+};
+use crate::memory_quality::memory_topic_key;
+use crate::vector_index::{PersistentHnsw, VectorBackend};
+
 pub struct Database {
     conn: Mutex<Connection>,
+    vectors: Mutex<VectorState>,
+}
+
+/// The selected vector index and, for HNSW, the open index.
+struct VectorState {
+    backend: VectorBackend,
+    hnsw_path: PathBuf,
+    hnsw: Option<PersistentHnsw>,
 }
 
 #[cfg(feature = "sqlite-vec")]
@@ -70,6 +86,8 @@ pub struct LlmUsageSummary {
     pub output_tokens: i64,
     pub total_tokens: i64,
     pub last_request_at: Option<String>,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
 }
 
 #[derive(Debug, Clone)]
@@ -79,6 +97,25 @@ pub struct LlmModelUsageSummary {
     pub input_tokens: i64,
     pub output_tokens: i64,
     pub total_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+}
+
+/// One `llm_usage_logs` row. `input_tokens` excludes prompt-cache reads/writes,
+/// which providers such as Anthropic report separately.
+#[derive(Debug, Clone, Default)]
+pub struct LlmUsageEntry<'a> {
+    pub chat_id: i64,
+    pub caller_channel: &'a str,
+    pub provider: &'a str,
+    pub model: &'a str,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+    pub request_kind: &'a str,
+    /// Primary provider alias when a failover profile served the call.
+    pub fallback_from: Option<&'a str>,
 }
 
 #[derive(Debug, Clone)]
@@ -108,6 +145,11 @@ pub struct MemoryObservabilitySummary {
     pub reflector_inserted_24h: i64,
     pub reflector_updated_24h: i64,
     pub reflector_skipped_24h: i64,
+    pub reflector_queued_24h: i64,
+    pub reflector_approved_24h: i64,
+    pub reflector_rejected_24h: i64,
+    /// Reflector facts currently waiting for review (not limited to 24h).
+    pub review_pending: i64,
     pub injection_events_24h: i64,
     pub injection_selected_24h: i64,
     pub injection_candidates_24h: i64,
@@ -123,11 +165,43 @@ pub struct MemoryReflectorRun {
     pub inserted_count: i64,
     pub updated_count: i64,
     pub skipped_count: i64,
+    /// Facts held in the review queue instead of being written.
+    pub queued_count: i64,
+    /// Facts written directly because they met the auto-approve threshold.
+    pub auto_approved_count: i64,
+    /// Queued facts a reviewer approved as-is.
+    pub approved_count: i64,
+    /// Queued facts a reviewer approved after editing.
+    pub edited_count: i64,
+    pub rejected_count: i64,
     pub dedup_method: String,
     pub parse_ok: bool,
     pub error_text: Option<String>,
 }
 
+/// A reflector-extracted fact held back for human review (`memory_reviews` row).
+#[derive(Debug, Clone)]
+pub struct MemoryReview {
+    pub id: i64,
+    pub chat_id: i64,
+    /// Reflector run that proposed the fact, once the run has been logged.
+    pub run_id: Option<i64>,
+    /// `insert`, `update` or `supersede`.
+    pub action: String,
+    /// Existing memory the fact would update or supersede.
+    pub target_memory_id: Option<i64>,
+    pub content: String,
+    pub category: String,
+    pub confidence: f64,
+    /// `pending`, `approved`, `edited` or `rejected`.
+    pub status: String,
+    /// Memory written when the review was approved.
+    pub memory_id: Option<i64>,
+    pub created_at: String,
+    pub decided_at: Option<String>,
+    pub decided_by: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct MemoryInjectionLog {
     pub id: i64,
@@ -180,10 +254,35 @@ pub struct AuditLogRecord {
     pub created_at: String,
 }
 
+/// A snapshot of a chat's working directory, taken before a turn changed files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointRecord {
+    pub id: String,
+    pub chat_id: i64,
+    pub kind: String,      // "git" or "store"
+    pub reference: String, // commit id or manifest hash
+    pub working_dir: String,
+    /// Messages in the chat when the turn started, so a fork point maps to a checkpoint.
+    pub message_count: i64,
+    pub created_at: String,
+    pub restored_at: Option<String>,
+}
+
+/// The file state a fork point maps to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForkCheckpoint {
+    /// The first checkpoint taken at or after the fork point.
+    Exact(CheckpointRecord),
+    /// No files changed after the fork point, so the current files match it.
+    Current,
+    /// The checkpoint for the fork point was pruned; no recorded state matches it.
+    Pruned,
+}
+
 pub type SessionMetaRow = (String, String, Option<String>, Option<i64>);
 pub type SessionTreeRow = (i64, Option<String>, Option<i64>, String);
 
-const SCHEMA_VERSION_CURRENT: i64 = 18;
+const SCHEMA_VERSION_CURRENT: i64 = 25;
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -198,6 +297,7 @@ pub struct ScheduledTask {
     pub last_run: Option<String>,
     pub status: String, // "active", "paused", "completed", "cancelled"
     pub created_at: String,
+    pub response_schema: Option<String>, // JSON schema for structured final answers
 }
 
 #[derive(Debug, Clone)]
@@ -299,6 +399,36 @@ pub struct SubagentEventRecord {
     pub created_at: String,
 }
 
+const MEMORY_REVIEW_COLUMNS: &str = "id, chat_id, run_id, action, target_memory_id, content, category, confidence, status, memory_id, created_at, decided_at, decided_by";
+
+fn map_memory_review(row: &rusqlite::Row<'_>) -> rusqlite::Result<MemoryReview> {
+    Ok(MemoryReview {
+        id: row.get(0)?,
+        chat_id: row.get(1)?,
+        run_id: row.get(2)?,
+        action: row.get(3)?,
+        target_memory_id: row.get(4)?,
+        content: row.get(5)?,
+        category: row.get(6)?,
+        confidence: row.get(7)?,
+        status: row.get(8)?,
+        memory_id: row.get(9)?,
+        created_at: row.get(10)?,
+        decided_at: row.get(11)?,
+        decided_by: row.get(12)?,
+    })
+}
+
+/// `memory_reflector_runs` counter credited by a review decision.
+fn review_counter_column(status: &str) -> Option<&'static str> {
+    match status {
+        "approved" => Some("approved_count"),
+        "edited" => Some("edited_count"),
+        "rejected" => Some("rejected_count"),
+        _ => None,
+    }
+}
+
 fn table_has_column(conn: &Connection, table: &str, column: &str) -> Result<bool, MicroClawError> {
     // Validate table name to prevent SQL injection via PRAGMA
     if !table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
@@ -828,12 +958,295 @@ fn apply_schema_migrations(conn: &Connection) -> Result<(), MicroClawError> {
         set_schema_version(conn, 18)?;
         version = 18;
     }
+    if version < 19 {
+        if !table_has_column(conn, "llm_usage_logs", "fallback_from")? {
+            conn.execute(
+                "ALTER TABLE llm_usage_logs ADD COLUMN fallback_from TEXT",
+                [],
+            )?;
+        }
+        set_schema_version(conn, 19)?;
+        version = 19;
+    }
+    if version < 20 {
+        for column in ["cache_read_tokens", "cache_write_tokens"] {
+            if !table_has_column(conn, "llm_usage_logs", column)? {
+                conn.execute(
+                    &format!(
+                        "ALTER TABLE llm_usage_logs ADD COLUMN {column} INTEGER NOT NULL DEFAULT 0"
+                    ),
+                    [],
+                )?;
+            }
+        }
+        set_schema_version(conn, 20)?;
+        version = 20;
+    }
+    if version < 21 {
+        if !table_has_column(conn, "scheduled_tasks", "response_schema")? {
+            conn.execute(
+                "ALTER TABLE scheduled_tasks ADD COLUMN response_schema TEXT",
+                [],
+            )?;
+        }
+        set_schema_version(conn, 21)?;
+        version = 21;
+    }
+    if version < 22 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checkpoints (
+                id TEXT PRIMARY KEY,
+                chat_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                reference TEXT NOT NULL,
+                working_dir TEXT NOT NULL,
+                message_count INTEGER NOT NULL,
+                created_at TEXT NOT NULL,
+                restored_at TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_checkpoints_chat_created
+                ON checkpoints(chat_id, created_at);",
+        )?;
+        set_schema_version(conn, 22)?;
+        version = 22;
+    }
+    if version < 23 {
+        // External-content FTS5 index over memories.content, kept in sync by triggers.
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS memories_fts USING fts5(
+                content,
+                content='memories',
+                content_rowid='id',
+                tokenize='porter unicode61 remove_diacritics 2'
+            );
+            CREATE TRIGGER IF NOT EXISTS memories_fts_ai AFTER INSERT ON memories BEGIN
+                INSERT INTO memories_fts(rowid, content) VALUES (new.id, new.content);
+            END;
+            CREATE TRIGGER IF NOT EXISTS memories_fts_ad AFTER DELETE ON memories BEGIN
+                INSERT INTO memories_fts(memories_fts, rowid, content)
+                    VALUES ('delete', old.id, old.content);
+            END;
+            CREATE TRIGGER IF NOT EXISTS memories_fts_au AFTER UPDATE OF content ON memories BEGIN
+                INSERT INTO memories_fts(memories_fts, rowid, content)
+                    VALUES ('delete', old.id, old.content);
+                INSERT INTO memories_fts(rowid, content) VALUES (new.id, new.content);
+            END;
+            INSERT INTO memories_fts(memories_fts) VALUES ('rebuild');",
+        )?;
+        set_schema_version(conn, 23)?;
+        version = 23;
+    }
+    if version < 24 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS memory_reviews (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_id INTEGER NOT NULL,
+                run_id INTEGER,
+                action TEXT NOT NULL,
+                target_memory_id INTEGER,
+                content TEXT NOT NULL,
+                category TEXT NOT NULL,
+                confidence REAL NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                memory_id INTEGER,
+                created_at TEXT NOT NULL,
+                decided_at TEXT,
+                decided_by TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_memory_reviews_status_chat
+                ON memory_reviews(status, chat_id);",
+        )?;
+        for column in [
+            "queued_count",
+            "auto_approved_count",
+            "approved_count",
+            "edited_count",
+            "rejected_count",
+        ] {
+            if !table_has_column(conn, "memory_reflector_runs", column)? {
+                conn.execute(
+                    &format!(
+                        "ALTER TABLE memory_reflector_runs ADD COLUMN {column} INTEGER NOT NULL DEFAULT 0"
+                    ),
+                    [],
+                )?;
+            }
+        }
+        set_schema_version(conn, 24)?;
+        version = 24;
+    }
+    if version < 25 {
+        // Highest message_count among a chat's pruned checkpoints, so a fork point at
+        // or below it is known to have lost its exact checkpoint.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checkpoint_prune_marks (
+                chat_id INTEGER PRIMARY KEY,
+                pruned_message_count INTEGER NOT NULL
+            );",
+        )?;
+        set_schema_version(conn, 25)?;
+        version = 25;
+    }
     if version != SCHEMA_VERSION_CURRENT {
         set_schema_version(conn, SCHEMA_VERSION_CURRENT)?;
     }
     Ok(())
 }
 
+#[cfg(feature = "sqlite-vec")]
+fn prepare_sqlite_vec_index(conn: &Connection, dimension: usize) -> Result<(), MicroClawError> {
+    let current_dim: Option<String> = conn
+        .query_row(
+            "SELECT value FROM db_meta WHERE key = 'embedding_dim'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(existing) = current_dim {
+        if existing != dimension.to_string() {
+            conn.execute("DROP TABLE IF EXISTS memories_vec", [])?;
+            conn.execute("UPDATE memories SET embedding_model = NULL", [])?;
+        }
+    }
+
+    conn.execute(
+        &format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS memories_vec USING vec0(
+                embedding float[{dimension}] distance_metric=cosine
+            )"
+        ),
+        [],
+    )?;
+    conn.execute(
+        "INSERT INTO db_meta(key, value) VALUES('embedding_dim', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![dimension.to_string()],
+    )?;
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite-vec"))]
+fn prepare_sqlite_vec_index(_conn: &Connection, _dimension: usize) -> Result<(), MicroClawError> {
+    Err(MicroClawError::Config(
+        "vector_index 'sqlite-vec' needs a build with --features sqlite-vec".into(),
+    ))
+}
+
+#[cfg(not(feature = "sqlite-vec"))]
+fn vector_index_not_ready() -> MicroClawError {
+    MicroClawError::Config("vector index not prepared".into())
+}
+
+/// Whether RFC 3339 timestamp `a` is later than `b`; falls back to string order.
+fn is_newer(a: &str, b: &str) -> bool {
+    match (
+        chrono::DateTime::parse_from_rfc3339(a),
+        chrono::DateTime::parse_from_rfc3339(b),
+    ) {
+        (Ok(a), Ok(b)) => a > b,
+        _ => a > b,
+    }
+}
+
+/// Turn free text into an FTS5 query matching any of its words, or `None` if it has none.
+/// Words are quoted so FTS5 operators and punctuation in the text are taken literally.
+fn fts_match_query(text: &str) -> Option<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|w| w.chars().count() > 1)
+    {
+        let term = format!("\"{word}\"");
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+    (!terms.is_empty()).then(|| terms.join(" OR "))
+}
+
+fn bundle_chat_id<'a>(
+    conn: &Connection,
+    cache: &mut HashMap<(&'a str, &'a str), i64>,
+    channel: &'a str,
+    external_chat_id: &'a str,
+) -> Result<i64, MicroClawError> {
+    if let Some(chat_id) = cache.get(&(channel, external_chat_id)) {
+        return Ok(*chat_id);
+    }
+    let chat_id = resolve_or_create_chat_id_on(conn, channel, external_chat_id, None, "private")?;
+    cache.insert((channel, external_chat_id), chat_id);
+    Ok(chat_id)
+}
+
+fn resolve_or_create_chat_id_on(
+    conn: &Connection,
+    channel: &str,
+    external_chat_id: &str,
+    chat_title: Option<&str>,
+    chat_type: &str,
+) -> Result<i64, MicroClawError> {
+    let now = chrono::Utc::now().to_rfc3339();
+    if let Some(chat_id) = conn
+        .query_row(
+            "SELECT chat_id FROM chats WHERE channel = ?1 AND external_chat_id = ?2 LIMIT 1",
+            params![channel, external_chat_id],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()?
+    {
+        conn.execute(
+            "UPDATE chats
+             SET chat_title = COALESCE(?2, chat_title),
+                 chat_type = ?3,
+                 last_message_time = ?4
+             WHERE chat_id = ?1",
+            params![chat_id, chat_title, chat_type, now],
+        )?;
+        return Ok(chat_id);
+    }
+
+    let preferred_chat_id = external_chat_id.parse::<i64>().ok();
+    if let Some(cid) = preferred_chat_id {
+        let occupied = conn
+            .query_row(
+                "SELECT 1 FROM chats WHERE chat_id = ?1 LIMIT 1",
+                params![cid],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if !occupied {
+            conn.execute(
+                "INSERT INTO chats(chat_id, chat_title, chat_type, last_message_time, channel, external_chat_id)
+                 VALUES(?1, ?2, ?3, ?4, ?5, ?6)",
+                params![cid, chat_title, chat_type, now, channel, external_chat_id],
+            )?;
+            return Ok(cid);
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO chats(chat_title, chat_type, last_message_time, channel, external_chat_id)
+         VALUES(?1, ?2, ?3, ?4, ?5)",
+        params![chat_title, chat_type, now, channel, external_chat_id],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn checkpoint_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<CheckpointRecord> {
+    Ok(CheckpointRecord {
+        id: row.get(0)?,
+        chat_id: row.get(1)?,
+        kind: row.get(2)?,
+        reference: row.get(3)?,
+        working_dir: row.get(4)?,
+        message_count: row.get(5)?,
+        created_at: row.get(6)?,
+        restored_at: row.get(7)?,
+    })
+}
+
 impl Database {
     fn lock_conn(&self) -> MutexGuard<'_, Connection> {
         match self.conn.lock() {
@@ -842,6 +1255,13 @@ impl Database {
         }
     }
 
+    fn lock_vectors(&self) -> MutexGuard<'_, VectorState> {
+        match self.vectors.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
     pub fn new(data_dir: &str) -> Result<Self, MicroClawError> {
         let db_path = Path::new(data_dir).join("microclaw.db");
         std::fs::create_dir_all(data_dir)?;
@@ -944,7 +1364,10 @@ impl Database {
                 output_tokens INTEGER NOT NULL,
                 total_tokens INTEGER NOT NULL,
                 request_kind TEXT NOT NULL DEFAULT 'agent_loop',
-                created_at TEXT NOT NULL
+                created_at TEXT NOT NULL,
+                fallback_from TEXT,
+                cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+                cache_write_tokens INTEGER NOT NULL DEFAULT 0
             );
 
             CREATE INDEX IF NOT EXISTS idx_llm_usage_chat_created
@@ -1090,6 +1513,11 @@ impl Database {
 
         Ok(Database {
             conn: Mutex::new(conn),
+            vectors: Mutex::new(VectorState {
+                backend: VectorBackend::Auto,
+                hnsw_path: Path::new(data_dir).join("memory_vectors.hnsw"),
+                hnsw: None,
+            }),
         })
     }
 
@@ -1130,53 +1558,23 @@ impl Database {
         chat_type: &str,
     ) -> Result<i64, MicroClawError> {
         let conn = self.lock_conn();
-        let now = chrono::Utc::now().to_rfc3339();
+        resolve_or_create_chat_id_on(&conn, channel, external_chat_id, chat_title, chat_type)
+    }
 
-        if let Some(chat_id) = conn
+    pub fn get_chat_id_by_channel_and_external_id(
+        &self,
+        channel: &str,
+        external_chat_id: &str,
+    ) -> Result<Option<i64>, MicroClawError> {
+        let conn = self.lock_conn();
+        let chat_id = conn
             .query_row(
                 "SELECT chat_id FROM chats WHERE channel = ?1 AND external_chat_id = ?2 LIMIT 1",
                 params![channel, external_chat_id],
                 |row| row.get::<_, i64>(0),
             )
-            .optional()?
-        {
-            conn.execute(
-                "UPDATE chats
-                 SET chat_title = COALESCE(?2, chat_title),
-                     chat_type = ?3,
-                     last_message_time = ?4
-                 WHERE chat_id = ?1",
-                params![chat_id, chat_title, chat_type, now],
-            )?;
-            return Ok(chat_id);
-        }
-
-        let preferred_chat_id = external_chat_id.parse::<i64>().ok();
-        if let Some(cid) = preferred_chat_id {
-            let occupied = conn
-                .query_row(
-                    "SELECT 1 FROM chats WHERE chat_id = ?1 LIMIT 1",
-                    params![cid],
-                    |_| Ok(()),
-                )
-                .optional()?
-                .is_some();
-            if !occupied {
-                conn.execute(
-                    "INSERT INTO chats(chat_id, chat_title, chat_type, last_message_time, channel, external_chat_id)
-                     VALUES(?1, ?2, ?3, ?4, ?5, ?6)",
-                    params![cid, chat_title, chat_type, now, channel, external_chat_id],
-                )?;
-                return Ok(cid);
-            }
-        }
-
-        conn.execute(
-            "INSERT INTO chats(chat_title, chat_type, last_message_time, channel, external_chat_id)
-             VALUES(?1, ?2, ?3, ?4, ?5)",
-            params![chat_title, chat_type, now, channel, external_chat_id],
-        )?;
-        Ok(conn.last_insert_rowid())
+            .optional()?;
+        Ok(chat_id)
     }
 
     pub fn store_message(&self, msg: &StoredMessage) -> Result<(), MicroClawError> {
@@ -1226,6 +1624,16 @@ impl Database {
         Ok(exists)
     }
 
+    /// Delete one stored message. Returns whether it existed.
+    pub fn delete_message(&self, chat_id: i64, message_id: &str) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let affected = conn.execute(
+            "DELETE FROM messages WHERE chat_id = ?1 AND id = ?2",
+            params![chat_id, message_id],
+        )?;
+        Ok(affected > 0)
+    }
+
     pub fn get_recent_messages(
         &self,
         chat_id: i64,
@@ -1536,7 +1944,7 @@ impl Database {
     pub fn get_due_tasks(&self, now: &str) -> Result<Vec<ScheduledTask>, MicroClawError> {
         let conn = self.lock_conn();
         let mut stmt = conn.prepare(
-            "SELECT id, chat_id, prompt, schedule_type, schedule_value, timezone, next_run, last_run, status, created_at
+            "SELECT id, chat_id, prompt, schedule_type, schedule_value, timezone, next_run, last_run, status, created_at, response_schema
              FROM scheduled_tasks
              WHERE status = 'active' AND next_run <= ?1",
         )?;
@@ -1553,6 +1961,7 @@ impl Database {
                     last_run: row.get(7)?,
                     status: row.get(8)?,
                     created_at: row.get(9)?,
+                    response_schema: row.get(10)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
@@ -1568,7 +1977,7 @@ impl Database {
         let tx = conn.unchecked_transaction()?;
 
         let mut stmt = tx.prepare(
-            "SELECT id, chat_id, prompt, schedule_type, schedule_value, timezone, next_run, last_run, status, created_at
+            "SELECT id, chat_id, prompt, schedule_type, schedule_value, timezone, next_run, last_run, status, created_at, response_schema
              FROM scheduled_tasks
              WHERE status = 'active' AND next_run <= ?1
              ORDER BY next_run ASC, id ASC
@@ -1587,6 +1996,7 @@ impl Database {
                     last_run: row.get(7)?,
                     status: row.get(8)?,
                     created_at: row.get(9)?,
+                    response_schema: row.get(10)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
@@ -1614,7 +2024,7 @@ impl Database {
     pub fn get_tasks_for_chat(&self, chat_id: i64) -> Result<Vec<ScheduledTask>, MicroClawError> {
         let conn = self.lock_conn();
         let mut stmt = conn.prepare(
-            "SELECT id, chat_id, prompt, schedule_type, schedule_value, timezone, next_run, last_run, status, created_at
+            "SELECT id, chat_id, prompt, schedule_type, schedule_value, timezone, next_run, last_run, status, created_at, response_schema
              FROM scheduled_tasks
              WHERE chat_id = ?1 AND status IN ('active', 'paused')
              ORDER BY id",
@@ -1632,6 +2042,7 @@ impl Database {
                     last_run: row.get(7)?,
                     status: row.get(8)?,
                     created_at: row.get(9)?,
+                    response_schema: row.get(10)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
@@ -1641,7 +2052,7 @@ impl Database {
     pub fn get_task_by_id(&self, task_id: i64) -> Result<Option<ScheduledTask>, MicroClawError> {
         let conn = self.lock_conn();
         let result = conn.query_row(
-            "SELECT id, chat_id, prompt, schedule_type, schedule_value, timezone, next_run, last_run, status, created_at
+            "SELECT id, chat_id, prompt, schedule_type, schedule_value, timezone, next_run, last_run, status, created_at, response_schema
              FROM scheduled_tasks
              WHERE id = ?1",
             params![task_id],
@@ -1657,6 +2068,7 @@ impl Database {
                     last_run: row.get(7)?,
                     status: row.get(8)?,
                     created_at: row.get(9)?,
+                    response_schema: row.get(10)?,
                 })
             },
         );
@@ -1667,6 +2079,19 @@ impl Database {
         }
     }
 
+    pub fn set_task_response_schema(
+        &self,
+        task_id: i64,
+        response_schema: Option<&str>,
+    ) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let rows = conn.execute(
+            "UPDATE scheduled_tasks SET response_schema = ?1 WHERE id = ?2",
+            params![response_schema, task_id],
+        )?;
+        Ok(rows > 0)
+    }
+
     pub fn update_task_status(&self, task_id: i64, status: &str) -> Result<bool, MicroClawError> {
         let conn = self.lock_conn();
         let rows = conn.execute(
@@ -2170,61 +2595,253 @@ impl Database {
             params![chat_id],
         )?;
         affected += tx.execute("DELETE FROM memories WHERE chat_id = ?1", params![chat_id])?;
+        affected += tx.execute(
+            "DELETE FROM checkpoints WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        tx.execute(
+            "DELETE FROM checkpoint_prune_marks WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
         affected += tx.execute("DELETE FROM chats WHERE chat_id = ?1", params![chat_id])?;
 
         tx.commit()?;
         Ok(affected > 0)
     }
 
-    // --- Auth: password/session/api-key ---
+    // --- Working-dir checkpoints ---
 
-    pub fn upsert_auth_password_hash(&self, password_hash: &str) -> Result<(), MicroClawError> {
+    pub fn count_messages(&self, chat_id: i64) -> Result<i64, MicroClawError> {
+        let conn = self.lock_conn();
+        let count = conn.query_row(
+            "SELECT COUNT(*) FROM messages WHERE chat_id = ?1",
+            params![chat_id],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    pub fn insert_checkpoint(&self, record: &CheckpointRecord) -> Result<(), MicroClawError> {
         let conn = self.lock_conn();
-        let now = chrono::Utc::now().to_rfc3339();
         conn.execute(
-            "INSERT INTO auth_passwords(id, password_hash, created_at, updated_at)
-             VALUES(1, ?1, ?2, ?2)
-             ON CONFLICT(id) DO UPDATE SET
-                password_hash = excluded.password_hash,
-                updated_at = excluded.updated_at",
-            params![password_hash, now],
+            "INSERT INTO checkpoints
+                (id, chat_id, kind, reference, working_dir, message_count, created_at, restored_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                record.id,
+                record.chat_id,
+                record.kind,
+                record.reference,
+                record.working_dir,
+                record.message_count,
+                record.created_at,
+                record.restored_at,
+            ],
         )?;
         Ok(())
     }
 
-    pub fn get_auth_password_hash(&self) -> Result<Option<String>, MicroClawError> {
+    /// Newest first.
+    pub fn list_checkpoints(
+        &self,
+        chat_id: i64,
+        limit: usize,
+    ) -> Result<Vec<CheckpointRecord>, MicroClawError> {
         let conn = self.lock_conn();
-        let value = conn
+        let mut stmt = conn.prepare(
+            "SELECT id, chat_id, kind, reference, working_dir, message_count, created_at, restored_at
+             FROM checkpoints
+             WHERE chat_id = ?1
+             ORDER BY created_at DESC, rowid DESC
+             LIMIT ?2",
+        )?;
+        let rows = stmt
+            .query_map(params![chat_id, limit as i64], checkpoint_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    pub fn get_checkpoint(
+        &self,
+        chat_id: i64,
+        id: &str,
+    ) -> Result<Option<CheckpointRecord>, MicroClawError> {
+        let conn = self.lock_conn();
+        let row = conn
             .query_row(
-                "SELECT password_hash FROM auth_passwords WHERE id = 1",
-                [],
-                |row| row.get::<_, String>(0),
+                "SELECT id, chat_id, kind, reference, working_dir, message_count, created_at, restored_at
+                 FROM checkpoints
+                 WHERE chat_id = ?1 AND id = ?2",
+                params![chat_id, id],
+                checkpoint_from_row,
             )
             .optional()?;
-        Ok(value)
+        Ok(row)
     }
 
-    pub fn clear_auth_password_hash(&self) -> Result<bool, MicroClawError> {
+    /// The newest checkpoint `/undo` has not rolled back to yet.
+    pub fn latest_unrestored_checkpoint(
+        &self,
+        chat_id: i64,
+    ) -> Result<Option<CheckpointRecord>, MicroClawError> {
         let conn = self.lock_conn();
-        let rows = conn.execute("DELETE FROM auth_passwords WHERE id = 1", [])?;
-        Ok(rows > 0)
+        let row = conn
+            .query_row(
+                "SELECT id, chat_id, kind, reference, working_dir, message_count, created_at, restored_at
+                 FROM checkpoints
+                 WHERE chat_id = ?1 AND restored_at IS NULL
+                 ORDER BY created_at DESC, rowid DESC
+                 LIMIT 1",
+                params![chat_id],
+                checkpoint_from_row,
+            )
+            .optional()?;
+        Ok(row)
     }
 
-    pub fn create_auth_session(
+    /// The file state matching a conversation cut after `fork_point` messages: the
+    /// first checkpoint taken once the chat had at least that many messages. If a
+    /// pruned checkpoint could have been that one, the next surviving checkpoint
+    /// holds later files and the result is [`ForkCheckpoint::Pruned`].
+    pub fn checkpoint_for_fork_point(
         &self,
-        session_id: &str,
-        label: Option<&str>,
-        expires_at: &str,
-    ) -> Result<(), MicroClawError> {
+        chat_id: i64,
+        fork_point: i64,
+    ) -> Result<ForkCheckpoint, MicroClawError> {
         let conn = self.lock_conn();
-        let now = chrono::Utc::now().to_rfc3339();
-        conn.execute(
-            "INSERT INTO auth_sessions(session_id, label, created_at, expires_at, last_seen_at, revoked_at)
-             VALUES(?1, ?2, ?3, ?4, ?3, NULL)",
-            params![session_id, label, now, expires_at],
-        )?;
-        Ok(())
-    }
+        let pruned_through: Option<i64> = conn
+            .query_row(
+                "SELECT pruned_message_count FROM checkpoint_prune_marks WHERE chat_id = ?1",
+                params![chat_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let row = conn
+            .query_row(
+                "SELECT id, chat_id, kind, reference, working_dir, message_count, created_at, restored_at
+                 FROM checkpoints
+                 WHERE chat_id = ?1 AND message_count >= ?2
+                 ORDER BY created_at ASC, rowid ASC
+                 LIMIT 1",
+                params![chat_id, fork_point],
+                checkpoint_from_row,
+            )
+            .optional()?;
+        Ok(match row {
+            None => ForkCheckpoint::Current,
+            Some(_) if pruned_through.is_some_and(|pruned| pruned >= fork_point) => {
+                ForkCheckpoint::Pruned
+            }
+            Some(record) => ForkCheckpoint::Exact(record),
+        })
+    }
+
+    pub fn mark_checkpoint_restored(&self, id: &str) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let rows = conn.execute(
+            "UPDATE checkpoints SET restored_at = ?1 WHERE id = ?2",
+            params![chrono::Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Delete all but the newest `keep` checkpoints of a chat, returning the deleted ones.
+    pub fn prune_checkpoints(
+        &self,
+        chat_id: i64,
+        keep: usize,
+    ) -> Result<Vec<CheckpointRecord>, MicroClawError> {
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        let stale = {
+            let mut stmt = tx.prepare(
+                "SELECT id, chat_id, kind, reference, working_dir, message_count, created_at, restored_at
+                 FROM checkpoints
+                 WHERE chat_id = ?1
+                 ORDER BY created_at DESC, rowid DESC
+                 LIMIT -1 OFFSET ?2",
+            )?;
+            let rows = stmt
+                .query_map(params![chat_id, keep as i64], checkpoint_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        for record in &stale {
+            tx.execute("DELETE FROM checkpoints WHERE id = ?1", params![record.id])?;
+        }
+        if let Some(pruned_through) = stale.iter().map(|r| r.message_count).max() {
+            tx.execute(
+                "INSERT INTO checkpoint_prune_marks (chat_id, pruned_message_count)
+                 VALUES (?1, ?2)
+                 ON CONFLICT(chat_id) DO UPDATE SET
+                    pruned_message_count = MAX(pruned_message_count, excluded.pruned_message_count)",
+                params![chat_id, pruned_through],
+            )?;
+        }
+        tx.commit()?;
+        Ok(stale)
+    }
+
+    /// References of every stored checkpoint of `kind`, across chats.
+    pub fn checkpoint_references(&self, kind: &str) -> Result<Vec<String>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt =
+            conn.prepare("SELECT DISTINCT reference FROM checkpoints WHERE kind = ?1")?;
+        let rows = stmt
+            .query_map(params![kind], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    // --- Auth: password/session/api-key ---
+
+    pub fn upsert_auth_password_hash(&self, password_hash: &str) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO auth_passwords(id, password_hash, created_at, updated_at)
+             VALUES(1, ?1, ?2, ?2)
+             ON CONFLICT(id) DO UPDATE SET
+                password_hash = excluded.password_hash,
+                updated_at = excluded.updated_at",
+            params![password_hash, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_auth_password_hash(&self) -> Result<Option<String>, MicroClawError> {
+        let conn = self.lock_conn();
+        let value = conn
+            .query_row(
+                "SELECT password_hash FROM auth_passwords WHERE id = 1",
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    pub fn clear_auth_password_hash(&self) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let rows = conn.execute("DELETE FROM auth_passwords WHERE id = 1", [])?;
+        Ok(rows > 0)
+    }
+
+    pub fn create_auth_session(
+        &self,
+        session_id: &str,
+        label: Option<&str>,
+        expires_at: &str,
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO auth_sessions(session_id, label, created_at, expires_at, last_seen_at, revoked_at)
+             VALUES(?1, ?2, ?3, ?4, ?3, NULL)",
+            params![session_id, label, now, expires_at],
+        )?;
+        Ok(())
+    }
 
     pub fn validate_auth_session(&self, session_id: &str) -> Result<bool, MicroClawError> {
         let conn = self.lock_conn();
@@ -2648,23 +3265,39 @@ impl Database {
         output_tokens: i64,
         request_kind: &str,
     ) -> Result<i64, MicroClawError> {
+        self.log_llm_usage_entry(&LlmUsageEntry {
+            chat_id,
+            caller_channel,
+            provider,
+            model,
+            input_tokens,
+            output_tokens,
+            request_kind,
+            ..Default::default()
+        })
+    }
+
+    pub fn log_llm_usage_entry(&self, entry: &LlmUsageEntry<'_>) -> Result<i64, MicroClawError> {
         let conn = self.lock_conn();
         let now = chrono::Utc::now().to_rfc3339();
-        let total_tokens = input_tokens.saturating_add(output_tokens);
+        let total_tokens = entry.input_tokens.saturating_add(entry.output_tokens);
         conn.execute(
             "INSERT INTO llm_usage_logs
-                (chat_id, caller_channel, provider, model, input_tokens, output_tokens, total_tokens, request_kind, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                (chat_id, caller_channel, provider, model, input_tokens, output_tokens, total_tokens, request_kind, created_at, fallback_from, cache_read_tokens, cache_write_tokens)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             params![
-                chat_id,
-                caller_channel,
-                provider,
-                model,
-                input_tokens,
-                output_tokens,
+                entry.chat_id,
+                entry.caller_channel,
+                entry.provider,
+                entry.model,
+                entry.input_tokens,
+                entry.output_tokens,
                 total_tokens,
-                request_kind,
+                entry.request_kind,
                 now,
+                entry.fallback_from,
+                entry.cache_read_tokens,
+                entry.cache_write_tokens,
             ],
         )?;
         Ok(conn.last_insert_rowid())
@@ -2683,96 +3316,43 @@ impl Database {
         since: Option<&str>,
     ) -> Result<LlmUsageSummary, MicroClawError> {
         let conn = self.lock_conn();
-        let (requests, input_tokens, output_tokens, total_tokens, last_request_at) =
-            match (chat_id, since) {
-                (Some(id), Some(since_ts)) => conn.query_row(
-                    "SELECT
-                    COUNT(*),
-                    COALESCE(SUM(input_tokens), 0),
-                    COALESCE(SUM(output_tokens), 0),
-                    COALESCE(SUM(total_tokens), 0),
-                    MAX(created_at)
-                 FROM llm_usage_logs
-                 WHERE chat_id = ?1 AND created_at >= ?2",
-                    params![id, since_ts],
-                    |row| {
-                        Ok((
-                            row.get::<_, i64>(0)?,
-                            row.get::<_, i64>(1)?,
-                            row.get::<_, i64>(2)?,
-                            row.get::<_, i64>(3)?,
-                            row.get::<_, Option<String>>(4)?,
-                        ))
-                    },
-                )?,
-                (Some(id), None) => conn.query_row(
-                    "SELECT
-                    COUNT(*),
-                    COALESCE(SUM(input_tokens), 0),
-                    COALESCE(SUM(output_tokens), 0),
-                    COALESCE(SUM(total_tokens), 0),
-                    MAX(created_at)
-                 FROM llm_usage_logs
-                 WHERE chat_id = ?1",
-                    params![id],
-                    |row| {
-                        Ok((
-                            row.get::<_, i64>(0)?,
-                            row.get::<_, i64>(1)?,
-                            row.get::<_, i64>(2)?,
-                            row.get::<_, i64>(3)?,
-                            row.get::<_, Option<String>>(4)?,
-                        ))
-                    },
-                )?,
-                (None, Some(since_ts)) => conn.query_row(
-                    "SELECT
-                    COUNT(*),
-                    COALESCE(SUM(input_tokens), 0),
-                    COALESCE(SUM(output_tokens), 0),
-                    COALESCE(SUM(total_tokens), 0),
-                    MAX(created_at)
-                 FROM llm_usage_logs
-                 WHERE created_at >= ?1",
-                    params![since_ts],
-                    |row| {
-                        Ok((
-                            row.get::<_, i64>(0)?,
-                            row.get::<_, i64>(1)?,
-                            row.get::<_, i64>(2)?,
-                            row.get::<_, i64>(3)?,
-                            row.get::<_, Option<String>>(4)?,
-                        ))
-                    },
-                )?,
-                (None, None) => conn.query_row(
-                    "SELECT
+        let select = "SELECT
                     COUNT(*),
                     COALESCE(SUM(input_tokens), 0),
                     COALESCE(SUM(output_tokens), 0),
                     COALESCE(SUM(total_tokens), 0),
-                    MAX(created_at)
-                 FROM llm_usage_logs",
-                    [],
-                    |row| {
-                        Ok((
-                            row.get::<_, i64>(0)?,
-                            row.get::<_, i64>(1)?,
-                            row.get::<_, i64>(2)?,
-                            row.get::<_, i64>(3)?,
-                            row.get::<_, Option<String>>(4)?,
-                        ))
-                    },
-                )?,
-            };
-
-        Ok(LlmUsageSummary {
-            requests,
-            input_tokens,
-            output_tokens,
-            total_tokens,
-            last_request_at,
-        })
+                    MAX(created_at),
+                    COALESCE(SUM(cache_read_tokens), 0),
+                    COALESCE(SUM(cache_write_tokens), 0)
+                 FROM llm_usage_logs";
+        let mapper = |row: &rusqlite::Row<'_>| {
+            Ok(LlmUsageSummary {
+                requests: row.get(0)?,
+                input_tokens: row.get(1)?,
+                output_tokens: row.get(2)?,
+                total_tokens: row.get(3)?,
+                last_request_at: row.get(4)?,
+                cache_read_tokens: row.get(5)?,
+                cache_write_tokens: row.get(6)?,
+            })
+        };
+        let summary = match (chat_id, since) {
+            (Some(id), Some(since_ts)) => conn.query_row(
+                &format!("{select} WHERE chat_id = ?1 AND created_at >= ?2"),
+                params![id, since_ts],
+                mapper,
+            )?,
+            (Some(id), None) => {
+                conn.query_row(&format!("{select} WHERE chat_id = ?1"), params![id], mapper)?
+            }
+            (None, Some(since_ts)) => conn.query_row(
+                &format!("{select} WHERE created_at >= ?1"),
+                params![since_ts],
+                mapper,
+            )?,
+            (None, None) => conn.query_row(select, [], mapper)?,
+        };
+        Ok(summary)
     }
 
     pub fn get_llm_usage_by_model(
@@ -2788,7 +3368,9 @@ impl Database {
                 COUNT(*) AS requests,
                 COALESCE(SUM(input_tokens), 0) AS input_tokens,
                 COALESCE(SUM(output_tokens), 0) AS output_tokens,
-                COALESCE(SUM(total_tokens), 0) AS total_tokens
+                COALESCE(SUM(total_tokens), 0) AS total_tokens,
+                COALESCE(SUM(cache_read_tokens), 0) AS cache_read_tokens,
+                COALESCE(SUM(cache_write_tokens), 0) AS cache_write_tokens
              FROM llm_usage_logs",
         );
 
@@ -2825,6 +3407,8 @@ impl Database {
                 input_tokens: row.get(2)?,
                 output_tokens: row.get(3)?,
                 total_tokens: row.get(4)?,
+                cache_read_tokens: row.get(5)?,
+                cache_write_tokens: row.get(6)?,
             })
         };
 
@@ -2941,6 +3525,55 @@ impl Database {
         Ok(memories)
     }
 
+    /// Full-text search (FTS5, BM25) in memories visible to chat_id (own + global),
+    /// best match first. Scores are SQLite's `bm25()`, where lower is better.
+    pub fn search_memories_fts(
+        &self,
+        chat_id: i64,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<(Memory, f64)>, MicroClawError> {
+        let Some(match_query) = fts_match_query(query) else {
+            return Ok(Vec::new());
+        };
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.chat_id, m.content, m.category, m.created_at, m.updated_at,
+                    m.embedding_model, m.confidence, m.source, m.last_seen_at, m.is_archived,
+                    m.archived_at, bm25(memories_fts) AS score
+             FROM memories_fts
+             JOIN memories m ON m.id = memories_fts.rowid
+             WHERE memories_fts MATCH ?1
+               AND (m.chat_id = ?2 OR m.chat_id IS NULL)
+               AND m.is_archived = 0
+               AND m.confidence >= 0.45
+             ORDER BY score ASC
+             LIMIT ?3",
+        )?;
+        let hits = stmt
+            .query_map(params![match_query, chat_id, limit as i64], |row| {
+                Ok((
+                    Memory {
+                        id: row.get(0)?,
+                        chat_id: row.get(1)?,
+                        content: row.get(2)?,
+                        category: row.get(3)?,
+                        created_at: row.get(4)?,
+                        updated_at: row.get(5)?,
+                        embedding_model: row.get(6)?,
+                        confidence: row.get(7)?,
+                        source: row.get(8)?,
+                        last_seen_at: row.get(9)?,
+                        is_archived: row.get::<_, i64>(10)? != 0,
+                        archived_at: row.get(11)?,
+                    },
+                    row.get::<_, f64>(12)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(hits)
+    }
+
     pub fn get_all_memories_for_chat(
         &self,
         chat_id: Option<i64>,
@@ -3042,8 +3675,13 @@ impl Database {
 
     /// Delete a memory row by id. Returns true if a row was deleted.
     pub fn delete_memory(&self, id: i64) -> Result<bool, MicroClawError> {
-        let conn = self.lock_conn();
-        let rows = conn.execute("DELETE FROM memories WHERE id = ?1", params![id])?;
+        let rows = {
+            let conn = self.lock_conn();
+            conn.execute("DELETE FROM memories WHERE id = ?1", params![id])?
+        };
+        if let Some(store) = self.lock_vectors().hnsw.as_mut() {
+            store.remove(id)?;
+        }
         Ok(rows > 0)
     }
 
@@ -3164,51 +3802,84 @@ impl Database {
         rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
-    #[cfg(feature = "sqlite-vec")]
+    /// Choose the index used by the vector methods below. Takes effect on the next
+    /// [`Database::prepare_vector_index`].
+    pub fn set_vector_backend(&self, backend: VectorBackend) {
+        self.lock_vectors().backend = backend;
+    }
+
+    /// The index the vector methods use, with `Auto` resolved for this build.
+    pub fn vector_backend(&self) -> VectorBackend {
+        self.lock_vectors().backend.resolve()
+    }
+
+    /// Set up the vector index for `dimension`-sized embeddings. Memories whose embedding
+    /// is not in the index (after a dimension or backend change, or a lost HNSW file) have
+    /// `embedding_model` cleared, so `get_memories_without_embedding` hands them out again.
     pub fn prepare_vector_index(&self, dimension: usize) -> Result<(), MicroClawError> {
-        let conn = self.lock_conn();
         let dimension = dimension.max(1);
+        let mut vectors = self.lock_vectors();
+        let backend = vectors.backend.resolve();
+        let conn = self.lock_conn();
         conn.execute(
             "CREATE TABLE IF NOT EXISTS db_meta (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
             [],
         )?;
-
-        let current_dim: Option<String> = conn
+        let previous_backend: Option<String> = conn
             .query_row(
-                "SELECT value FROM db_meta WHERE key = 'embedding_dim'",
+                "SELECT value FROM db_meta WHERE key = 'vector_backend'",
                 [],
                 |row| row.get(0),
             )
             .optional()?;
-        if let Some(existing) = current_dim {
-            if existing != dimension.to_string() {
-                conn.execute("DROP TABLE IF EXISTS memories_vec", [])?;
-                conn.execute("UPDATE memories SET embedding_model = NULL", [])?;
-            }
+        if previous_backend.is_some_and(|b| b != backend.as_str()) {
+            conn.execute("UPDATE memories SET embedding_model = NULL", [])?;
         }
-
         conn.execute(
-            &format!(
-                "CREATE VIRTUAL TABLE IF NOT EXISTS memories_vec USING vec0(
-                    embedding float[{dimension}] distance_metric=cosine
-                )"
-            ),
-            [],
-        )?;
-        conn.execute(
-            "INSERT INTO db_meta(key, value) VALUES('embedding_dim', ?1)
+            "INSERT INTO db_meta(key, value) VALUES('vector_backend', ?1)
              ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-            params![dimension.to_string()],
+            params![backend.as_str()],
         )?;
-        Ok(())
+
+        match backend {
+            VectorBackend::Hnsw => {
+                vectors.hnsw = None;
+                let (store, reused) = PersistentHnsw::open(vectors.hnsw_path.clone(), dimension);
+                let mut stmt =
+                    conn.prepare("SELECT id FROM memories WHERE embedding_model IS NOT NULL")?;
+                let embedded = stmt
+                    .query_map([], |row| row.get::<_, i64>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                for id in embedded {
+                    if !reused || !store.index().contains(id) {
+                        conn.execute(
+                            "UPDATE memories SET embedding_model = NULL WHERE id = ?1",
+                            params![id],
+                        )?;
+                    }
+                }
+                vectors.hnsw = Some(store);
+                Ok(())
+            }
+            _ => prepare_sqlite_vec_index(&conn, dimension),
+        }
     }
 
-    #[cfg(feature = "sqlite-vec")]
     pub fn upsert_memory_vec(
         &self,
         memory_id: i64,
         embedding: &[f32],
     ) -> Result<(), MicroClawError> {
+        let mut vectors = self.lock_vectors();
+        if let Some(store) = vectors.hnsw.as_mut() {
+            return store.upsert(memory_id, embedding);
+        }
+        drop(vectors);
+        self.upsert_sqlite_vec(memory_id, embedding)
+    }
+
+    #[cfg(feature = "sqlite-vec")]
+    fn upsert_sqlite_vec(&self, memory_id: i64, embedding: &[f32]) -> Result<(), MicroClawError> {
         let conn = self.lock_conn();
         let vector_json = serde_json::to_string(embedding)?;
         conn.execute(
@@ -3218,6 +3889,19 @@ impl Database {
         Ok(())
     }
 
+    #[cfg(not(feature = "sqlite-vec"))]
+    fn upsert_sqlite_vec(&self, _memory_id: i64, _embedding: &[f32]) -> Result<(), MicroClawError> {
+        Err(vector_index_not_ready())
+    }
+
+    /// Write pending HNSW changes to disk. A no-op for sqlite-vec.
+    pub fn flush_vector_index(&self) -> Result<(), MicroClawError> {
+        match self.lock_vectors().hnsw.as_mut() {
+            Some(store) => store.flush(),
+            None => Ok(()),
+        }
+    }
+
     pub fn get_all_active_memories(&self) -> Result<Vec<(i64, String)>, MicroClawError> {
         let conn = self.lock_conn();
         let mut stmt =
@@ -3228,12 +3912,44 @@ impl Database {
         rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
-    #[cfg(feature = "sqlite-vec")]
+    /// The `k` memories visible to chat_id whose embeddings are nearest to `query_vec`,
+    /// as `(id, cosine distance)`, nearest first.
     pub fn knn_memories(
         &self,
         chat_id: i64,
         query_vec: &[f32],
         k: usize,
+    ) -> Result<Vec<(i64, f32)>, MicroClawError> {
+        let vectors = self.lock_vectors();
+        let Some(store) = vectors.hnsw.as_ref() else {
+            drop(vectors);
+            return self.knn_sqlite_vec(chat_id, query_vec, k);
+        };
+        // Search wider than k, since hits from other chats are dropped below.
+        let hits = store.index().search(query_vec, k.saturating_mul(4).max(k));
+        drop(vectors);
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT 1 FROM memories WHERE id = ?1 AND (chat_id = ?2 OR chat_id IS NULL)",
+        )?;
+        let mut visible = Vec::with_capacity(k);
+        for (id, distance) in hits {
+            if visible.len() >= k {
+                break;
+            }
+            if stmt.exists(params![id, chat_id])? {
+                visible.push((id, distance));
+            }
+        }
+        Ok(visible)
+    }
+
+    #[cfg(feature = "sqlite-vec")]
+    fn knn_sqlite_vec(
+        &self,
+        chat_id: i64,
+        query_vec: &[f32],
+        k: usize,
     ) -> Result<Vec<(i64, f32)>, MicroClawError> {
         let conn = self.lock_conn();
         let vector_json = serde_json::to_string(query_vec)?;
@@ -3254,6 +3970,16 @@ impl Database {
         rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
+    #[cfg(not(feature = "sqlite-vec"))]
+    fn knn_sqlite_vec(
+        &self,
+        _chat_id: i64,
+        _query_vec: &[f32],
+        _k: usize,
+    ) -> Result<Vec<(i64, f32)>, MicroClawError> {
+        Err(vector_index_not_ready())
+    }
+
     /// Get a single memory by id.
     pub fn get_memory_by_id(&self, id: i64) -> Result<Option<Memory>, MicroClawError> {
         let conn = self.lock_conn();
@@ -3392,6 +4118,389 @@ impl Database {
         Ok(to_memory_id)
     }
 
+    /// The stored embedding of a memory, if the vector index has one.
+    pub fn get_memory_vec(&self, memory_id: i64) -> Result<Option<Vec<f32>>, MicroClawError> {
+        let vectors = self.lock_vectors();
+        if let Some(store) = vectors.hnsw.as_ref() {
+            return Ok(store.index().vector(memory_id).map(<[f32]>::to_vec));
+        }
+        drop(vectors);
+        self.get_sqlite_vec(memory_id)
+    }
+
+    #[cfg(feature = "sqlite-vec")]
+    fn get_sqlite_vec(&self, memory_id: i64) -> Result<Option<Vec<f32>>, MicroClawError> {
+        let conn = self.lock_conn();
+        let json: Option<String> = conn
+            .query_row(
+                "SELECT vec_to_json(embedding) FROM memories_vec WHERE rowid = ?1",
+                params![memory_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        json.map(|j| serde_json::from_str(&j).map_err(Into::into))
+            .transpose()
+    }
+
+    #[cfg(not(feature = "sqlite-vec"))]
+    fn get_sqlite_vec(&self, _memory_id: i64) -> Result<Option<Vec<f32>>, MicroClawError> {
+        Err(vector_index_not_ready())
+    }
+
+    /// Header, memories and the supersede edges between them, ready for
+    /// [`crate::memory_bundle::write_bundle`]. Chat memories whose chat has no channel
+    /// and external id cannot be mapped on import and are left out.
+    pub fn export_memory_bundle(
+        &self,
+        options: &ExportOptions,
+    ) -> Result<Vec<BundleRecord>, MicroClawError> {
+        let mut memories = Vec::new();
+        let mut edges = Vec::new();
+        {
+            let conn = self.lock_conn();
+            let mut sql = String::from(
+                "SELECT m.id, m.chat_id, COALESCE(m.chat_channel, c.channel),
+                        COALESCE(m.external_chat_id, c.external_chat_id),
+                        m.content, m.category, m.confidence, m.source, m.created_at,
+                        m.updated_at, m.last_seen_at, m.is_archived, m.archived_at,
+                        m.embedding_model
+                 FROM memories m
+                 LEFT JOIN chats c ON c.chat_id = m.chat_id
+                 WHERE 1 = 1",
+            );
+            if options.chat_id.is_some() {
+                sql.push_str(" AND m.chat_id = ?1");
+            } else if options.global_only {
+                sql.push_str(" AND m.chat_id IS NULL");
+            }
+            if !options.include_archived {
+                sql.push_str(" AND m.is_archived = 0");
+            }
+            sql.push_str(" ORDER BY m.id");
+            let mut stmt = conn.prepare(&sql)?;
+            let mapper = |row: &rusqlite::Row<'_>| {
+                let chat_id: Option<i64> = row.get(1)?;
+                let channel: Option<String> = row.get(2)?;
+                let external_chat_id: Option<String> = row.get(3)?;
+                let scope = match (chat_id, channel, external_chat_id) {
+                    (None, _, _) => Some(BundleScope::Global),
+                    (Some(_), Some(channel), Some(external_chat_id)) => Some(BundleScope::Chat {
+                        channel,
+                        external_chat_id,
+                    }),
+                    _ => None,
+                };
+                Ok(scope.map(|scope| {
+                    let memory = BundleMemory {
+                        id: row.get(0)?,
+                        scope,
+                        content: row.get(4)?,
+                        category: row.get(5)?,
+                        confidence: row.get(6)?,
+                        source: row.get(7)?,
+                        created_at: row.get(8)?,
+                        updated_at: row.get(9)?,
+                        last_seen_at: row.get(10)?,
+                        is_archived: row.get::<_, i64>(11)? != 0,
+                        archived_at: row.get(12)?,
+                        embedding: None,
+                    };
+                    Ok::<_, rusqlite::Error>((memory, row.get::<_, Option<String>>(13)?))
+                }))
+            };
+            let rows = match options.chat_id {
+                Some(cid) => stmt.query_map(params![cid], mapper)?,
+                None => stmt.query_map([], mapper)?,
+            };
+            for row in rows {
+                if let Some(entry) = row?.transpose()? {
+                    memories.push(entry);
+                }
+            }
+
+            let exported: HashSet<i64> = memories.iter().map(|(m, _)| m.id).collect();
+            let mut stmt = conn.prepare(
+                "SELECT from_memory_id, to_memory_id, reason, created_at
+                 FROM memory_supersede_edges
+                 ORDER BY id",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(BundleEdge {
+                    from: row.get(0)?,
+                    to: row.get(1)?,
+                    reason: row.get(2)?,
+                    created_at: row.get(3)?,
+                })
+            })?;
+            for edge in rows {
+                let edge = edge?;
+                if exported.contains(&edge.from) && exported.contains(&edge.to) {
+                    edges.push(edge);
+                }
+            }
+        }
+
+        let mut records = Vec::with_capacity(memories.len() + edges.len() + 1);
+        records.push(BundleRecord::Header(BundleHeader {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            memory_count: memories.len(),
+            edge_count: edges.len(),
+            agents_file_count: 0,
+        }));
+        for (mut memory, embedding_model) in memories {
+            if options.include_embeddings {
+                if let Some(model) = embedding_model {
+                    if let Ok(Some(vector)) = self.get_memory_vec(memory.id) {
+                        memory.embedding = Some(BundleEmbedding { model, vector });
+                    }
+                }
+            }
+            records.push(BundleRecord::Memory(Box::new(memory)));
+        }
+        records.extend(edges.into_iter().map(BundleRecord::Supersede));
+        Ok(records)
+    }
+
+    /// Merge a parsed bundle into this database. Embeddings are kept only when made by
+    /// `embedding_model` and accepted by the prepared vector index; other memories are
+    /// left for `get_memories_without_embedding` to pick up.
+    pub fn import_memory_bundle(
+        &self,
+        records: &[BundleRecord],
+        strategy: MergeStrategy,
+        embedding_model: Option<&str>,
+    ) -> Result<ImportReport, MicroClawError> {
+        let mut report = ImportReport::default();
+        // Chats are created inside the transaction so a failed import leaves none behind.
+        let mut chat_ids: HashMap<(&str, &str), i64> = HashMap::new();
+        let mut id_map: HashMap<i64, i64> = HashMap::new();
+        let mut pending_vectors: Vec<(i64, &[f32])> = Vec::new();
+        // Active (id, topic key, updated_at) per scope, loaded on first use by `Dedupe`.
+        let mut topics: HashMap<Option<i64>, Vec<(i64, String, String)>> = HashMap::new();
+        {
+            let mut conn = self.lock_conn();
+            let tx = conn.transaction()?;
+            for record in records {
+                // Chat-scoped AGENTS.md files are written by the caller after commit and
+                // need their chat to exist.
+                if let BundleRecord::AgentsFile(file) = record {
+                    if let BundleScope::Chat {
+                        channel,
+                        external_chat_id,
+                    } = &file.scope
+                    {
+                        if !channel.is_empty() {
+                            bundle_chat_id(&tx, &mut chat_ids, channel, external_chat_id)?;
+                        }
+                    }
+                }
+            }
+            for record in records {
+                let BundleRecord::Memory(memory) = record else {
+                    continue;
+                };
+                let (chat_id, chat_channel, external_chat_id) = match &memory.scope {
+                    BundleScope::Global => (None, None, None),
+                    BundleScope::Chat {
+                        channel,
+                        external_chat_id,
+                    } if !channel.is_empty() => {
+                        let cid = bundle_chat_id(&tx, &mut chat_ids, channel, external_chat_id)?;
+                        (Some(cid), Some(channel), Some(external_chat_id))
+                    }
+                    BundleScope::Chat { .. } | BundleScope::Bot { .. } => {
+                        report.unmapped += 1;
+                        continue;
+                    }
+                };
+                let content = memory.content.trim();
+                if content.is_empty() {
+                    report.skipped += 1;
+                    continue;
+                }
+                let confidence = memory.confidence.clamp(0.0, 1.0);
+
+                let topic_match = if strategy == MergeStrategy::Dedupe && !memory.is_archived {
+                    if let Entry::Vacant(slot) = topics.entry(chat_id) {
+                        let mut stmt = tx.prepare(
+                            "SELECT id, content, updated_at FROM memories
+                             WHERE chat_id IS ?1 AND is_archived = 0",
+                        )?;
+                        let rows = stmt
+                            .query_map(params![chat_id], |row| {
+                                Ok((
+                                    row.get::<_, i64>(0)?,
+                                    memory_topic_key(&row.get::<_, String>(1)?),
+                                    row.get::<_, String>(2)?,
+                                ))
+                            })?
+                            .collect::<Result<Vec<_>, _>>()?;
+                        slot.insert(rows);
+                    }
+                    let key = memory_topic_key(content);
+                    topics[&chat_id]
+                        .iter()
+                        .find(|(_, k, _)| *k == key)
+                        .map(|(id, _, updated_at)| (*id, updated_at.clone()))
+                } else {
+                    None
+                };
+                let exact_match: Option<i64> = if topic_match.is_none() {
+                    tx.query_row(
+                        "SELECT id FROM memories WHERE chat_id IS ?1 AND content = ?2
+                         ORDER BY is_archived ASC, id DESC LIMIT 1",
+                        params![chat_id, content],
+                        |row| row.get(0),
+                    )
+                    .optional()?
+                } else {
+                    None
+                };
+
+                let accepted_vector = memory
+                    .embedding
+                    .as_ref()
+                    .filter(|e| Some(e.model.as_str()) == embedding_model)
+                    .map(|e| e.vector.as_slice());
+                let local_id = match (topic_match, exact_match) {
+                    (Some((existing, existing_updated)), _) => {
+                        if is_newer(&memory.updated_at, &existing_updated) {
+                            tx.execute(
+                                "UPDATE memories
+                                 SET content = ?2, category = ?3, confidence = ?4, source = ?5,
+                                     updated_at = ?6, last_seen_at = ?7, embedding_model = NULL
+                                 WHERE id = ?1",
+                                params![
+                                    existing,
+                                    content,
+                                    memory.category,
+                                    confidence,
+                                    memory.source,
+                                    memory.updated_at,
+                                    memory.last_seen_at
+                                ],
+                            )?;
+                            if let Some(entry) = topics
+                                .get_mut(&chat_id)
+                                .and_then(|t| t.iter_mut().find(|(id, _, _)| *id == existing))
+                            {
+                                entry.2 = memory.updated_at.clone();
+                            }
+                            report.updated += 1;
+                            pending_vectors.extend(accepted_vector.map(|v| (existing, v)));
+                        } else {
+                            report.skipped += 1;
+                        }
+                        existing
+                    }
+                    (None, Some(existing)) if strategy == MergeStrategy::Overwrite => {
+                        tx.execute(
+                            "UPDATE memories
+                             SET category = ?2, confidence = ?3, source = ?4, updated_at = ?5,
+                                 last_seen_at = ?6, is_archived = ?7, archived_at = ?8
+                             WHERE id = ?1",
+                            params![
+                                existing,
+                                memory.category,
+                                confidence,
+                                memory.source,
+                                memory.updated_at,
+                                memory.last_seen_at,
+                                memory.is_archived as i64,
+                                memory.archived_at
+                            ],
+                        )?;
+                        report.updated += 1;
+                        existing
+                    }
+                    (None, Some(existing)) => {
+                        report.skipped += 1;
+                        existing
+                    }
+                    (None, None) => {
+                        tx.execute(
+                            "INSERT INTO memories (
+                                chat_id, content, category, created_at, updated_at,
+                                embedding_model, confidence, source, last_seen_at, is_archived,
+                                archived_at, chat_channel, external_chat_id
+                            ) VALUES (?1, ?2, ?3, ?4, ?5, NULL, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                            params![
+                                chat_id,
+                                content,
+                                memory.category,
+                                memory.created_at,
+                                memory.updated_at,
+                                confidence,
+                                memory.source,
+                                memory.last_seen_at,
+                                memory.is_archived as i64,
+                                memory.archived_at,
+                                chat_channel,
+                                external_chat_id
+                            ],
+                        )?;
+                        let id = tx.last_insert_rowid();
+                        if let Some(scope_topics) = topics.get_mut(&chat_id) {
+                            if !memory.is_archived {
+                                scope_topics.push((
+                                    id,
+                                    memory_topic_key(content),
+                                    memory.updated_at.clone(),
+                                ));
+                            }
+                        }
+                        report.inserted += 1;
+                        pending_vectors.extend(accepted_vector.map(|v| (id, v)));
+                        id
+                    }
+                };
+                id_map.insert(memory.id, local_id);
+            }
+
+            for record in records {
+                let BundleRecord::Supersede(edge) = record else {
+                    continue;
+                };
+                let (Some(&from), Some(&to)) = (id_map.get(&edge.from), id_map.get(&edge.to))
+                else {
+                    continue;
+                };
+                if from == to {
+                    continue;
+                }
+                let exists: bool = tx.query_row(
+                    "SELECT EXISTS(
+                        SELECT 1 FROM memory_supersede_edges
+                        WHERE from_memory_id = ?1 AND to_memory_id = ?2
+                    )",
+                    params![from, to],
+                    |row| row.get(0),
+                )?;
+                if !exists {
+                    tx.execute(
+                        "INSERT INTO memory_supersede_edges(from_memory_id, to_memory_id, reason, created_at)
+                         VALUES(?1, ?2, ?3, ?4)",
+                        params![from, to, edge.reason, edge.created_at],
+                    )?;
+                    report.edges += 1;
+                }
+            }
+            tx.commit()?;
+        }
+
+        if let Some(model) = embedding_model {
+            for (id, vector) in pending_vectors {
+                if self.upsert_memory_vec(id, vector).is_ok() {
+                    self.update_memory_embedding_model(id, model)?;
+                    report.embeddings += 1;
+                }
+            }
+        }
+        Ok(report)
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn log_reflector_run(
         &self,
@@ -3510,13 +4619,19 @@ impl Database {
             reflector_inserted_24h,
             reflector_updated_24h,
             reflector_skipped_24h,
+            reflector_queued_24h,
+            reflector_approved_24h,
+            reflector_rejected_24h,
         ) = if let Some(cid) = chat_id {
             conn.query_row(
                 "SELECT
                         COUNT(*),
                         COALESCE(SUM(inserted_count), 0),
                         COALESCE(SUM(updated_count), 0),
-                        COALESCE(SUM(skipped_count), 0)
+                        COALESCE(SUM(skipped_count), 0),
+                        COALESCE(SUM(queued_count), 0),
+                        COALESCE(SUM(approved_count + edited_count), 0),
+                        COALESCE(SUM(rejected_count), 0)
                      FROM memory_reflector_runs
                      WHERE chat_id = ?1 AND unixepoch(started_at) >= unixepoch(?2)",
                 params![cid, &since_24h],
@@ -3526,6 +4641,9 @@ impl Database {
                         row.get::<_, i64>(1)?,
                         row.get::<_, i64>(2)?,
                         row.get::<_, i64>(3)?,
+                        row.get::<_, i64>(4)?,
+                        row.get::<_, i64>(5)?,
+                        row.get::<_, i64>(6)?,
                     ))
                 },
             )?
@@ -3535,7 +4653,10 @@ impl Database {
                         COUNT(*),
                         COALESCE(SUM(inserted_count), 0),
                         COALESCE(SUM(updated_count), 0),
-                        COALESCE(SUM(skipped_count), 0)
+                        COALESCE(SUM(skipped_count), 0),
+                        COALESCE(SUM(queued_count), 0),
+                        COALESCE(SUM(approved_count + edited_count), 0),
+                        COALESCE(SUM(rejected_count), 0)
                      FROM memory_reflector_runs
                      WHERE unixepoch(started_at) >= unixepoch(?1)",
                 params![&since_24h],
@@ -3545,11 +4666,28 @@ impl Database {
                         row.get::<_, i64>(1)?,
                         row.get::<_, i64>(2)?,
                         row.get::<_, i64>(3)?,
+                        row.get::<_, i64>(4)?,
+                        row.get::<_, i64>(5)?,
+                        row.get::<_, i64>(6)?,
                     ))
                 },
             )?
         };
 
+        let review_pending: i64 = if let Some(cid) = chat_id {
+            conn.query_row(
+                "SELECT COUNT(*) FROM memory_reviews WHERE status = 'pending' AND chat_id = ?1",
+                params![cid],
+                |row| row.get(0),
+            )?
+        } else {
+            conn.query_row(
+                "SELECT COUNT(*) FROM memory_reviews WHERE status = 'pending'",
+                [],
+                |row| row.get(0),
+            )?
+        };
+
         let (injection_events_24h, injection_selected_24h, injection_candidates_24h) =
             if let Some(cid) = chat_id {
                 conn.query_row(
@@ -3597,6 +4735,10 @@ impl Database {
             reflector_inserted_24h,
             reflector_updated_24h,
             reflector_skipped_24h,
+            reflector_queued_24h,
+            reflector_approved_24h,
+            reflector_rejected_24h,
+            review_pending,
             injection_events_24h,
             injection_selected_24h,
             injection_candidates_24h,
@@ -3612,7 +4754,8 @@ impl Database {
     ) -> Result<Vec<MemoryReflectorRun>, MicroClawError> {
         let conn = self.lock_conn();
         let mut query = String::from(
-            "SELECT id, chat_id, started_at, finished_at, extracted_count, inserted_count, updated_count, skipped_count, dedup_method, parse_ok, error_text
+            "SELECT id, chat_id, started_at, finished_at, extracted_count, inserted_count, updated_count, skipped_count, dedup_method, parse_ok, error_text,
+                    queued_count, auto_approved_count, approved_count, edited_count, rejected_count
              FROM memory_reflector_runs",
         );
         let mut where_parts: Vec<&str> = Vec::new();
@@ -3635,29 +4778,235 @@ impl Database {
         query.push_str(" OFFSET ");
         query.push_str(&offset.to_string());
 
-        let mut stmt = conn.prepare(&query)?;
-        let mapper = |row: &rusqlite::Row<'_>| {
-            Ok(MemoryReflectorRun {
-                id: row.get(0)?,
-                chat_id: row.get(1)?,
-                started_at: row.get(2)?,
-                finished_at: row.get(3)?,
-                extracted_count: row.get(4)?,
-                inserted_count: row.get(5)?,
-                updated_count: row.get(6)?,
-                skipped_count: row.get(7)?,
-                dedup_method: row.get(8)?,
-                parse_ok: row.get::<_, i64>(9)? != 0,
-                error_text: row.get(10)?,
-            })
+        let mut stmt = conn.prepare(&query)?;
+        let mapper = |row: &rusqlite::Row<'_>| {
+            Ok(MemoryReflectorRun {
+                id: row.get(0)?,
+                chat_id: row.get(1)?,
+                started_at: row.get(2)?,
+                finished_at: row.get(3)?,
+                extracted_count: row.get(4)?,
+                inserted_count: row.get(5)?,
+                updated_count: row.get(6)?,
+                skipped_count: row.get(7)?,
+                queued_count: row.get(11)?,
+                auto_approved_count: row.get(12)?,
+                approved_count: row.get(13)?,
+                edited_count: row.get(14)?,
+                rejected_count: row.get(15)?,
+                dedup_method: row.get(8)?,
+                parse_ok: row.get::<_, i64>(9)? != 0,
+                error_text: row.get(10)?,
+            })
+        };
+        let rows = match (chat_id, since) {
+            (Some(cid), Some(ts)) => stmt.query_map(params![cid, ts], mapper)?,
+            (Some(cid), None) => stmt.query_map(params![cid], mapper)?,
+            (None, Some(ts)) => stmt.query_map(params![ts], mapper)?,
+            (None, None) => stmt.query_map([], mapper)?,
+        };
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn enqueue_memory_review(
+        &self,
+        chat_id: i64,
+        action: &str,
+        target_memory_id: Option<i64>,
+        content: &str,
+        category: &str,
+        confidence: f64,
+    ) -> Result<i64, MicroClawError> {
+        let conn = self.lock_conn();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO memory_reviews (
+                chat_id, action, target_memory_id, content, category, confidence, status, created_at
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'pending', ?7)",
+            params![
+                chat_id,
+                action,
+                target_memory_id,
+                content,
+                category,
+                confidence.clamp(0.0, 1.0),
+                now
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Attach queued reviews to the reflector run that proposed them and record the
+    /// run's queued / auto-approved counts, so later decisions can be credited to it.
+    pub fn link_memory_reviews_to_run(
+        &self,
+        run_id: i64,
+        review_ids: &[i64],
+        auto_approved_count: usize,
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        for review_id in review_ids {
+            tx.execute(
+                "UPDATE memory_reviews SET run_id = ?1 WHERE id = ?2",
+                params![run_id, review_id],
+            )?;
+        }
+        tx.execute(
+            "UPDATE memory_reflector_runs
+             SET queued_count = ?2, auto_approved_count = ?3
+             WHERE id = ?1",
+            params![run_id, review_ids.len() as i64, auto_approved_count as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Reviews ordered oldest first. `status` filters on `pending`, `approved`,
+    /// `edited` or `rejected`.
+    pub fn get_memory_reviews(
+        &self,
+        chat_id: Option<i64>,
+        status: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<MemoryReview>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {MEMORY_REVIEW_COLUMNS}
+             FROM memory_reviews
+             WHERE (?1 IS NULL OR chat_id = ?1) AND (?2 IS NULL OR status = ?2)
+             ORDER BY id ASC
+             LIMIT ?3"
+        ))?;
+        let rows = stmt.query_map(
+            params![chat_id, status, limit.max(1) as i64],
+            map_memory_review,
+        )?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// Content of every pending or rejected review for `chat_id`, newest first. The
+    /// reflector must not propose these facts again, so the list is not capped.
+    pub fn get_unapproved_memory_review_contents(
+        &self,
+        chat_id: i64,
+    ) -> Result<Vec<String>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT content FROM memory_reviews
+             WHERE chat_id = ?1 AND status IN ('pending', 'rejected')
+             ORDER BY id DESC",
+        )?;
+        let rows = stmt.query_map(params![chat_id], |row| row.get(0))?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn get_memory_review(&self, id: i64) -> Result<Option<MemoryReview>, MicroClawError> {
+        let conn = self.lock_conn();
+        conn.query_row(
+            &format!("SELECT {MEMORY_REVIEW_COLUMNS} FROM memory_reviews WHERE id = ?1"),
+            params![id],
+            map_memory_review,
+        )
+        .optional()
+        .map_err(Into::into)
+    }
+
+    /// Move a pending review to `status` (`approved`, `edited` or `rejected`) and credit
+    /// the decision to its reflector run. `content`/`category` replace the proposed fact
+    /// when given. Returns `None` when the review does not exist or was already decided,
+    /// so two reviewers cannot apply the same fact twice.
+    pub fn decide_memory_review(
+        &self,
+        id: i64,
+        status: &str,
+        content: Option<&str>,
+        category: Option<&str>,
+        decided_by: &str,
+    ) -> Result<Option<MemoryReview>, MicroClawError> {
+        let counter = review_counter_column(status).ok_or_else(|| {
+            MicroClawError::Config(format!("invalid memory review status '{status}'"))
+        })?;
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        let now = chrono::Utc::now().to_rfc3339();
+        let changed = tx.execute(
+            "UPDATE memory_reviews
+             SET status = ?2,
+                 content = COALESCE(?3, content),
+                 category = COALESCE(?4, category),
+                 decided_at = ?5,
+                 decided_by = ?6
+             WHERE id = ?1 AND status = 'pending'",
+            params![id, status, content, category, now, decided_by],
+        )?;
+        if changed == 0 {
+            return Ok(None);
+        }
+        let review = tx.query_row(
+            &format!("SELECT {MEMORY_REVIEW_COLUMNS} FROM memory_reviews WHERE id = ?1"),
+            params![id],
+            map_memory_review,
+        )?;
+        if let Some(run_id) = review.run_id {
+            tx.execute(
+                &format!(
+                    "UPDATE memory_reflector_runs SET {counter} = {counter} + 1 WHERE id = ?1"
+                ),
+                params![run_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(Some(review))
+    }
+
+    pub fn set_memory_review_memory_id(
+        &self,
+        id: i64,
+        memory_id: i64,
+    ) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let rows = conn.execute(
+            "UPDATE memory_reviews SET memory_id = ?2 WHERE id = ?1",
+            params![id, memory_id],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Undo a decision whose follow-up write failed: the review becomes pending again
+    /// and the run counter credited by `decide_memory_review` is taken back.
+    pub fn reopen_memory_review(&self, id: i64) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        let current: Option<(String, Option<i64>)> = tx
+            .query_row(
+                "SELECT status, run_id FROM memory_reviews WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((status, run_id)) = current else {
+            return Ok(false);
         };
-        let rows = match (chat_id, since) {
-            (Some(cid), Some(ts)) => stmt.query_map(params![cid, ts], mapper)?,
-            (Some(cid), None) => stmt.query_map(params![cid], mapper)?,
-            (None, Some(ts)) => stmt.query_map(params![ts], mapper)?,
-            (None, None) => stmt.query_map([], mapper)?,
+        let Some(counter) = review_counter_column(&status) else {
+            return Ok(false);
         };
-        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+        tx.execute(
+            "UPDATE memory_reviews
+             SET status = 'pending', memory_id = NULL, decided_at = NULL, decided_by = NULL
+             WHERE id = ?1",
+            params![id],
+        )?;
+        if let Some(run_id) = run_id {
+            tx.execute(
+                &format!(
+                    "UPDATE memory_reflector_runs SET {counter} = MAX({counter} - 1, 0) WHERE id = ?1"
+                ),
+                params![run_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(true)
     }
 
     pub fn get_memory_injection_logs(
@@ -4351,6 +5700,7 @@ impl Database {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::memory_bundle::BundleAgentsFile;
 
     fn test_db() -> (Database, std::path::PathBuf) {
         let dir = std::env::temp_dir().join(format!("microclaw_test_{}", uuid::Uuid::new_v4()));
@@ -4735,6 +6085,28 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_delete_message() {
+        let (db, dir) = test_db();
+        for chat_id in [100, 200] {
+            db.store_message(&StoredMessage {
+                id: "msg1".into(),
+                chat_id,
+                sender_name: "alice".into(),
+                content: "hello".into(),
+                is_from_bot: false,
+                timestamp: "2024-01-01T00:00:00Z".into(),
+            })
+            .unwrap();
+        }
+
+        assert!(db.delete_message(100, "msg1").unwrap());
+        assert!(!db.delete_message(100, "msg1").unwrap());
+        assert!(!db.message_exists(100, "msg1").unwrap());
+        assert!(db.message_exists(200, "msg1").unwrap());
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_store_message_if_new() {
         let (db, dir) = test_db();
@@ -4885,6 +6257,96 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_checkpoints_lifecycle() {
+        let (db, dir) = test_db();
+        let record = |id: &str, message_count: i64, created_at: &str| CheckpointRecord {
+            id: id.to_string(),
+            chat_id: 7,
+            kind: "store".to_string(),
+            reference: format!("ref-{id}"),
+            working_dir: "/work".to_string(),
+            message_count,
+            created_at: created_at.to_string(),
+            restored_at: None,
+        };
+        db.insert_checkpoint(&record("a", 1, "2024-01-01T00:00:01Z"))
+            .unwrap();
+        db.insert_checkpoint(&record("b", 3, "2024-01-01T00:00:02Z"))
+            .unwrap();
+        db.insert_checkpoint(&record("c", 5, "2024-01-01T00:00:03Z"))
+            .unwrap();
+
+        let listed = db.list_checkpoints(7, 10).unwrap();
+        let ids: Vec<_> = listed.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "b", "a"]);
+        assert_eq!(
+            db.get_checkpoint(7, "b").unwrap(),
+            Some(record("b", 3, "2024-01-01T00:00:02Z"))
+        );
+        assert!(db.get_checkpoint(8, "b").unwrap().is_none());
+
+        let exact = |fork_point| match db.checkpoint_for_fork_point(7, fork_point).unwrap() {
+            ForkCheckpoint::Exact(record) => Some(record.id),
+            _ => None,
+        };
+        assert_eq!(exact(2).as_deref(), Some("b"));
+        assert_eq!(exact(3).as_deref(), Some("b"));
+        assert_eq!(exact(4).as_deref(), Some("c"));
+        assert_eq!(
+            db.checkpoint_for_fork_point(7, 6).unwrap(),
+            ForkCheckpoint::Current
+        );
+
+        assert_eq!(db.latest_unrestored_checkpoint(7).unwrap().unwrap().id, "c");
+        assert!(db.mark_checkpoint_restored("c").unwrap());
+        assert_eq!(db.latest_unrestored_checkpoint(7).unwrap().unwrap().id, "b");
+
+        let pruned = db.prune_checkpoints(7, 2).unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].id, "a");
+        assert_eq!(db.checkpoint_references("store").unwrap().len(), 2);
+        // Fork points past the prune window no longer map to "b", whose files are
+        // newer than the ones "a" held; later fork points are unaffected.
+        assert_eq!(
+            db.checkpoint_for_fork_point(7, 1).unwrap(),
+            ForkCheckpoint::Pruned
+        );
+        assert_eq!(exact(2).as_deref(), Some("b"));
+
+        db.delete_chat_data(7).unwrap();
+        assert!(db.list_checkpoints(7, 10).unwrap().is_empty());
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_set_task_response_schema() {
+        let (db, dir) = test_db();
+        let id = db
+            .create_scheduled_task(100, "report", "cron", "0 * * * * *", "2024-01-01T00:00:00Z")
+            .unwrap();
+        assert!(db
+            .get_task_by_id(id)
+            .unwrap()
+            .unwrap()
+            .response_schema
+            .is_none());
+
+        assert!(db
+            .set_task_response_schema(id, Some(r#"{"type":"object"}"#))
+            .unwrap());
+        let task = db.get_task_by_id(id).unwrap().unwrap();
+        assert_eq!(
+            task.response_schema.as_deref(),
+            Some(r#"{"type":"object"}"#)
+        );
+        let due = db.claim_due_tasks("2024-06-01T00:00:00Z", 10).unwrap();
+        assert_eq!(due[0].response_schema, task.response_schema);
+
+        assert!(!db.set_task_response_schema(9999, None).unwrap());
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_get_due_tasks() {
         let (db, dir) = test_db();
@@ -5844,6 +7306,53 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_log_llm_usage_entry_records_fallback_and_cache_tokens() {
+        let (db, dir) = test_db();
+        let primary = db
+            .log_llm_usage(1, "web", "anthropic", "claude-test", 10, 5, "agent_loop")
+            .unwrap();
+        let routed = db
+            .log_llm_usage_entry(&LlmUsageEntry {
+                chat_id: 1,
+                caller_channel: "web",
+                provider: "backup",
+                model: "gpt-test",
+                input_tokens: 10,
+                output_tokens: 5,
+                cache_read_tokens: 4000,
+                cache_write_tokens: 600,
+                request_kind: "agent_loop",
+                fallback_from: Some("anthropic"),
+            })
+            .unwrap();
+
+        let summary = db.get_llm_usage_summary(Some(1)).unwrap();
+        assert_eq!(summary.requests, 2);
+        assert_eq!(summary.input_tokens, 20);
+        assert_eq!(summary.total_tokens, 30);
+        assert_eq!(summary.cache_read_tokens, 4000);
+        assert_eq!(summary.cache_write_tokens, 600);
+        let by_model = db.get_llm_usage_by_model(Some(1), None, None).unwrap();
+        let gpt = by_model.iter().find(|r| r.model == "gpt-test").unwrap();
+        assert_eq!(gpt.cache_read_tokens, 4000);
+
+        let conn = db.lock_conn();
+        let fallback_from = |id: i64| -> Option<String> {
+            conn.query_row(
+                "SELECT fallback_from FROM llm_usage_logs WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(fallback_from(primary), None);
+        assert_eq!(fallback_from(routed).as_deref(), Some("anthropic"));
+        drop(conn);
+
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_delete_chat_data_cleans_llm_usage() {
         let (db, dir) = test_db();
@@ -6056,6 +7565,58 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_search_memories_fts_ranks_and_tracks_changes() {
+        let (db, dir) = test_db();
+        let deploy_id = db
+            .insert_memory(
+                Some(100),
+                "Deploying runs from the staging branch",
+                "KNOWLEDGE",
+            )
+            .unwrap();
+        let global_id = db
+            .insert_memory(None, "The staging server deploys nightly", "KNOWLEDGE")
+            .unwrap();
+        db.insert_memory(Some(200), "Other chat deploys on fridays", "KNOWLEDGE")
+            .unwrap();
+
+        // Porter stemming matches "deployed" against "Deploying" and "deploys".
+        let hits = db.search_memories_fts(100, "deployed?", 10).unwrap();
+        let ids: Vec<i64> = hits.iter().map(|(m, _)| m.id).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&deploy_id) && ids.contains(&global_id));
+        assert!(hits[0].1 <= hits[1].1);
+
+        // Triggers keep the index in sync with updates, archiving and deletes.
+        db.update_memory_content(deploy_id, "Releases are cut on mondays", "KNOWLEDGE")
+            .unwrap();
+        let hits = db.search_memories_fts(100, "deploy", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(db.search_memories_fts(100, "mondays", 10).unwrap().len(), 1);
+        db.archive_memory(global_id).unwrap();
+        assert!(db
+            .search_memories_fts(100, "deploy", 10)
+            .unwrap()
+            .is_empty());
+        db.delete_memory(deploy_id).unwrap();
+        assert!(db
+            .search_memories_fts(100, "mondays", 10)
+            .unwrap()
+            .is_empty());
+
+        assert!(db
+            .search_memories_fts(100, "? * \"", 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            fts_match_query("Deploy \"staging\" NEAR(x) deploy"),
+            Some("\"deploy\" OR \"staging\" OR \"near\"".to_string())
+        );
+
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_archive_memory_hides_from_search_and_context() {
         let (db, dir) = test_db();
@@ -6115,6 +7676,101 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_memory_review_queue_decisions_update_run_counts() {
+        let (db, dir) = test_db();
+        let now = chrono::Utc::now().to_rfc3339();
+        let first = db
+            .enqueue_memory_review(100, "insert", None, "User runs Arch", "PROFILE", 0.6)
+            .unwrap();
+        let second = db
+            .enqueue_memory_review(100, "update", Some(7), "Deploys on Fridays", "EVENT", 0.5)
+            .unwrap();
+        db.enqueue_memory_review(200, "insert", None, "Other chat fact", "KNOWLEDGE", 0.5)
+            .unwrap();
+        let run_id = db
+            .log_reflector_run(100, &now, &now, 3, 1, 0, 0, "jaccard", true, None)
+            .unwrap();
+        db.link_memory_reviews_to_run(run_id, &[first, second], 1)
+            .unwrap();
+
+        let pending = db
+            .get_memory_reviews(Some(100), Some("pending"), 10)
+            .unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[1].target_memory_id, Some(7));
+        assert_eq!(pending[1].run_id, Some(run_id));
+        assert_eq!(
+            db.get_memory_reviews(None, Some("pending"), 10)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let edited = db
+            .decide_memory_review(first, "edited", Some("User runs Arch Linux"), None, "web")
+            .unwrap()
+            .unwrap();
+        assert_eq!(edited.content, "User runs Arch Linux");
+        assert_eq!(edited.category, "PROFILE");
+        assert_eq!(edited.decided_by.as_deref(), Some("web"));
+        // A decided review cannot be claimed again.
+        assert!(db
+            .decide_memory_review(first, "rejected", None, None, "web")
+            .unwrap()
+            .is_none());
+        db.decide_memory_review(second, "rejected", None, None, "chat:100")
+            .unwrap()
+            .unwrap();
+        assert!(db
+            .decide_memory_review(second, "pending", None, None, "web")
+            .is_err());
+
+        let run = &db
+            .get_memory_reflector_runs(Some(100), None, 10, 0)
+            .unwrap()[0];
+        assert_eq!(run.queued_count, 2);
+        assert_eq!(run.auto_approved_count, 1);
+        assert_eq!(run.edited_count, 1);
+        assert_eq!(run.rejected_count, 1);
+
+        assert!(db.reopen_memory_review(second).unwrap());
+        let reopened = db.get_memory_review(second).unwrap().unwrap();
+        assert_eq!(reopened.status, "pending");
+        assert!(reopened.decided_at.is_none());
+        let run = &db
+            .get_memory_reflector_runs(Some(100), None, 10, 0)
+            .unwrap()[0];
+        assert_eq!(run.rejected_count, 0);
+
+        let summary = db.get_memory_observability_summary(Some(100)).unwrap();
+        assert_eq!(summary.review_pending, 1);
+        assert_eq!(summary.reflector_queued_24h, 2);
+        assert_eq!(summary.reflector_approved_24h, 1);
+        assert_eq!(
+            db.get_memory_observability_summary(None)
+                .unwrap()
+                .review_pending,
+            2
+        );
+
+        // Edited facts were accepted; pending and rejected ones must not come back,
+        // however many there are.
+        db.decide_memory_review(second, "rejected", None, None, "web")
+            .unwrap()
+            .unwrap();
+        for i in 0..600 {
+            db.enqueue_memory_review(100, "insert", None, &format!("fact {i}"), "KNOWLEDGE", 0.5)
+                .unwrap();
+        }
+        let contents = db.get_unapproved_memory_review_contents(100).unwrap();
+        assert_eq!(contents.len(), 601);
+        assert_eq!(contents[0], "fact 599");
+        assert_eq!(contents[600], "Deploys on Fridays");
+
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_supersede_memory_creates_edge_and_archives_old() {
         let (db, dir) = test_db();
@@ -6293,6 +7949,245 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_hnsw_vector_index_knn_and_rebuild() {
+        let (db, dir) = test_db();
+        db.set_vector_backend(VectorBackend::Hnsw);
+        assert_eq!(db.vector_backend(), VectorBackend::Hnsw);
+        db.prepare_vector_index(3).unwrap();
+        let own = db
+            .insert_memory(Some(100), "vector own", "KNOWLEDGE")
+            .unwrap();
+        let global = db
+            .insert_memory(None, "vector global", "KNOWLEDGE")
+            .unwrap();
+        let other = db
+            .insert_memory(Some(200), "vector other", "KNOWLEDGE")
+            .unwrap();
+        for (id, v) in [
+            (own, [1.0, 0.0, 0.0]),
+            (global, [0.0, 1.0, 0.0]),
+            (other, [0.99, 0.01, 0.0]),
+        ] {
+            db.upsert_memory_vec(id, &v).unwrap();
+            db.update_memory_embedding_model(id, "test-model").unwrap();
+        }
+
+        let nearest = db.knn_memories(100, &[0.95, 0.05, 0.0], 2).unwrap();
+        assert_eq!(
+            nearest.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            vec![own, global]
+        );
+        assert!(db.upsert_memory_vec(own, &[1.0, 0.0]).is_err());
+        db.flush_vector_index().unwrap();
+        drop(db);
+
+        // Reopening reuses the persisted index; nothing needs re-embedding.
+        let db = Database::new(dir.to_str().unwrap()).unwrap();
+        db.set_vector_backend(VectorBackend::Hnsw);
+        db.prepare_vector_index(3).unwrap();
+        assert!(db
+            .get_memories_without_embedding(None, 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            db.knn_memories(200, &[1.0, 0.0, 0.0], 1).unwrap()[0].0,
+            other
+        );
+        db.delete_memory(other).unwrap();
+        assert_eq!(
+            db.knn_memories(200, &[1.0, 0.0, 0.0], 1).unwrap()[0].0,
+            global
+        );
+        drop(db);
+
+        // A lost index file hands every embedded memory back for re-embedding.
+        std::fs::remove_file(dir.join("memory_vectors.hnsw")).unwrap();
+        let db = Database::new(dir.to_str().unwrap()).unwrap();
+        db.set_vector_backend(VectorBackend::Hnsw);
+        db.prepare_vector_index(3).unwrap();
+        assert_eq!(
+            db.get_memories_without_embedding(None, 10).unwrap().len(),
+            2
+        );
+        assert!(db
+            .knn_memories(100, &[1.0, 0.0, 0.0], 1)
+            .unwrap()
+            .is_empty());
+
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_memory_bundle_export_import_merge() {
+        let (src, src_dir) = test_db();
+        src.set_vector_backend(VectorBackend::Hnsw);
+        src.prepare_vector_index(3).unwrap();
+        let chat = src
+            .resolve_or_create_chat_id("telegram", "42", Some("ops"), "group")
+            .unwrap();
+        src.insert_memory(None, "Production database port is 5433", "KNOWLEDGE")
+            .unwrap();
+        let old = src
+            .insert_memory(Some(chat), "Standup is at 9am", "EVENT")
+            .unwrap();
+        let new = src
+            .supersede_memory(
+                old,
+                "Standup is at 10am",
+                "EVENT",
+                "tool",
+                0.9,
+                Some("moved"),
+            )
+            .unwrap();
+        src.upsert_memory_vec(new, &[1.0, 0.0, 0.0]).unwrap();
+        src.update_memory_embedding_model(new, "test-model")
+            .unwrap();
+
+        let records = src
+            .export_memory_bundle(&ExportOptions {
+                include_archived: true,
+                include_embeddings: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(matches!(
+            &records[0],
+            BundleRecord::Header(h) if h.memory_count == 3 && h.edge_count == 1
+        ));
+        let active_only = src.export_memory_bundle(&ExportOptions::default()).unwrap();
+        assert_eq!(active_only.len(), 3);
+
+        let (dst, dst_dir) = test_db();
+        dst.set_vector_backend(VectorBackend::Hnsw);
+        dst.prepare_vector_index(3).unwrap();
+        let report = dst
+            .import_memory_bundle(&records, MergeStrategy::Skip, Some("test-model"))
+            .unwrap();
+        assert_eq!(
+            (report.inserted, report.edges, report.embeddings),
+            (3, 1, 1)
+        );
+        let dst_chat = dst
+            .resolve_or_create_chat_id("telegram", "42", None, "private")
+            .unwrap();
+        let chat_memories = dst.get_all_memories_for_chat(Some(dst_chat)).unwrap();
+        let imported_new = chat_memories
+            .iter()
+            .find(|m| m.content == "Standup is at 10am")
+            .unwrap();
+        assert_eq!(imported_new.embedding_model.as_deref(), Some("test-model"));
+        assert_eq!(
+            dst.knn_memories(dst_chat, &[1.0, 0.0, 0.0], 1).unwrap()[0].0,
+            imported_new.id
+        );
+
+        // Importing the same bundle again changes nothing.
+        let report = dst
+            .import_memory_bundle(&records, MergeStrategy::Skip, Some("test-model"))
+            .unwrap();
+        assert_eq!((report.inserted, report.skipped, report.edges), (0, 3, 0));
+
+        // Overwrite refreshes metadata of memories with identical content.
+        let mut tweaked = records.clone();
+        for record in &mut tweaked {
+            if let BundleRecord::Memory(m) = record {
+                m.confidence = 0.99;
+                if m.content.contains("5433") {
+                    m.content = "Production database port is 5434".into();
+                    m.updated_at = "2099-01-01T00:00:00+00:00".into();
+                }
+            }
+        }
+        let report = dst
+            .import_memory_bundle(&tweaked, MergeStrategy::Overwrite, None)
+            .unwrap();
+        assert_eq!((report.inserted, report.updated), (1, 2));
+
+        // Dedupe matches on topic key and keeps the newer content.
+        let (dedupe, dedupe_dir) = test_db();
+        dedupe
+            .import_memory_bundle(&records, MergeStrategy::Skip, None)
+            .unwrap();
+        let report = dedupe
+            .import_memory_bundle(&tweaked, MergeStrategy::Dedupe, None)
+            .unwrap();
+        assert_eq!((report.inserted, report.updated), (0, 1));
+        let globals = dedupe.get_all_memories_for_chat(None).unwrap();
+        assert_eq!(globals.len(), 1);
+        assert_eq!(globals[0].content, "Production database port is 5434");
+        assert!(globals[0].embedding_model.is_none());
+
+        cleanup(&src_dir);
+        cleanup(&dst_dir);
+        cleanup(&dedupe_dir);
+    }
+
+    #[test]
+    fn test_memory_bundle_import_failure_leaves_no_chats() {
+        let (db, dir) = test_db();
+        let memory = BundleMemory {
+            id: 1,
+            scope: BundleScope::Chat {
+                channel: "telegram".into(),
+                external_chat_id: "42".into(),
+            },
+            content: "Standup is at 9am".into(),
+            category: "EVENT".into(),
+            confidence: 0.8,
+            source: "tool".into(),
+            created_at: "2024-01-01T00:00:00+00:00".into(),
+            updated_at: "2024-01-01T00:00:00+00:00".into(),
+            last_seen_at: "2024-01-01T00:00:00+00:00".into(),
+            is_archived: false,
+            archived_at: None,
+            embedding: None,
+        };
+        let records = vec![
+            BundleRecord::AgentsFile(BundleAgentsFile {
+                scope: BundleScope::Chat {
+                    channel: "discord".into(),
+                    external_chat_id: "7".into(),
+                },
+                content: "notes".into(),
+            }),
+            BundleRecord::Memory(Box::new(memory)),
+        ];
+        db.lock_conn()
+            .execute_batch(
+                "CREATE TRIGGER fail_import BEFORE INSERT ON memories
+                 BEGIN SELECT RAISE(ABORT, 'import failed'); END;",
+            )
+            .unwrap();
+        assert!(db
+            .import_memory_bundle(&records, MergeStrategy::Skip, None)
+            .is_err());
+        assert_eq!(
+            db.get_chat_id_by_channel_and_external_id("telegram", "42")
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            db.get_chat_id_by_channel_and_external_id("discord", "7")
+                .unwrap(),
+            None
+        );
+
+        db.lock_conn()
+            .execute_batch("DROP TRIGGER fail_import;")
+            .unwrap();
+        let report = db
+            .import_memory_bundle(&records, MergeStrategy::Skip, None)
+            .unwrap();
+        assert_eq!(report.inserted, 1);
+        assert!(db
+            .get_chat_id_by_channel_and_external_id("discord", "7")
+            .unwrap()
+            .is_some());
+        cleanup(&dir);
+    }
+
     #[cfg(feature = "sqlite-vec")]
     #[test]
     fn test_sqlite_vec_prepare_and_knn() {
@@ -6311,6 +8206,7 @@ mod tests {
         assert_eq!(nearest.len(), 1);
         assert_eq!(nearest[0].0, id1);
         assert!(nearest[0].1 >= 0.0);
+        assert_eq!(db.get_memory_vec(id2).unwrap(), Some(vec![0.0, 1.0, 0.0]));
 
         cleanup(&dir);
     }
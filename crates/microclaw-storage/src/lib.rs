@@ -2,5 +2,7 @@
 
 pub mod db;
 pub mod memory;
+pub mod memory_bundle;
 pub mod memory_quality;
 pub mod usage;
+pub mod vector_index;
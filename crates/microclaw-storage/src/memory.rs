@@ -1,5 +1,15 @@
 use std::path::{Path, PathBuf};
 
+use crate::memory_bundle::MergeStrategy;
+
+/// Which AGENTS.md file under `groups/` a piece of content belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentsFileScope {
+    Global,
+    Bot { channel: String },
+    Chat { channel: String, chat_id: i64 },
+}
+
 pub struct MemoryManager {
     data_dir: PathBuf,
 }
@@ -103,6 +113,109 @@ impl MemoryManager {
         context
     }
 
+    fn agents_file_path(&self, scope: &AgentsFileScope) -> PathBuf {
+        match scope {
+            AgentsFileScope::Global => self.global_memory_path(),
+            AgentsFileScope::Bot { channel } => self.bot_memory_path(channel),
+            AgentsFileScope::Chat { channel, chat_id } => self.chat_memory_path(channel, *chat_id),
+        }
+    }
+
+    /// Every non-blank AGENTS.md file: global first, then per channel its bot file and
+    /// the files of numeric chat directories, in path order.
+    pub fn list_agents_files(&self) -> Vec<(AgentsFileScope, String)> {
+        fn sorted_dirs(dir: &Path) -> Vec<PathBuf> {
+            let mut dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect();
+            dirs.sort();
+            dirs
+        }
+
+        let mut scopes = vec![AgentsFileScope::Global];
+        for channel_dir in sorted_dirs(&self.data_dir) {
+            let Some(channel) = channel_dir.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            scopes.push(AgentsFileScope::Bot {
+                channel: channel.to_string(),
+            });
+            for chat_dir in sorted_dirs(&channel_dir) {
+                let chat_id = chat_dir
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.parse::<i64>().ok());
+                if let Some(chat_id) = chat_id {
+                    scopes.push(AgentsFileScope::Chat {
+                        channel: channel.to_string(),
+                        chat_id,
+                    });
+                }
+            }
+        }
+        scopes
+            .into_iter()
+            .filter_map(|scope| {
+                let content = std::fs::read_to_string(self.agents_file_path(&scope)).ok()?;
+                (!content.trim().is_empty()).then_some((scope, content))
+            })
+            .collect()
+    }
+
+    /// Merge imported AGENTS.md content into the file for `scope`, mirroring the memory
+    /// import strategies: `Skip` only fills a missing or blank file, `Overwrite` replaces
+    /// it, and `Dedupe` appends the imported lines the file does not already contain.
+    /// Returns whether the file changed.
+    pub fn merge_agents_file(
+        &self,
+        scope: &AgentsFileScope,
+        content: &str,
+        strategy: MergeStrategy,
+    ) -> std::io::Result<bool> {
+        let path = self.agents_file_path(scope);
+        let existing = std::fs::read_to_string(&path).unwrap_or_default();
+        let merged = if existing.trim().is_empty() {
+            content.to_string()
+        } else {
+            match strategy {
+                MergeStrategy::Skip => return Ok(false),
+                MergeStrategy::Overwrite => content.to_string(),
+                MergeStrategy::Dedupe => {
+                    let known: std::collections::HashSet<&str> =
+                        existing.lines().map(str::trim).collect();
+                    let missing: Vec<&str> = content
+                        .lines()
+                        .filter(|line| !line.trim().is_empty() && !known.contains(line.trim()))
+                        .collect();
+                    if missing.is_empty() {
+                        return Ok(false);
+                    }
+                    let mut merged = existing.clone();
+                    if !merged.ends_with('\n') {
+                        merged.push('\n');
+                    }
+                    for line in missing {
+                        merged.push_str(line);
+                        merged.push('\n');
+                    }
+                    merged
+                }
+            }
+        };
+        if content.trim().is_empty() || merged == existing {
+            return Ok(false);
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, merged)?;
+        Ok(true)
+    }
+
     #[allow(dead_code)]
     pub fn groups_dir(&self) -> &Path {
         &self.data_dir
@@ -241,6 +354,78 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_list_agents_files_finds_every_scope() {
+        let (mm, dir) = test_memory_manager();
+        mm.write_global_memory("global").unwrap();
+        mm.write_bot_memory("telegram", "bot").unwrap();
+        mm.write_chat_memory("telegram", 42, "chat").unwrap();
+        mm.write_chat_memory("web", 7, "   ").unwrap();
+        std::fs::create_dir_all(dir.join("groups").join("telegram").join("skills")).unwrap();
+
+        let files = mm.list_agents_files();
+        assert_eq!(
+            files,
+            vec![
+                (AgentsFileScope::Global, "global".to_string()),
+                (
+                    AgentsFileScope::Bot {
+                        channel: "telegram".into()
+                    },
+                    "bot".to_string()
+                ),
+                (
+                    AgentsFileScope::Chat {
+                        channel: "telegram".into(),
+                        chat_id: 42
+                    },
+                    "chat".to_string()
+                ),
+            ]
+        );
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_merge_agents_file_strategies() {
+        let (mm, dir) = test_memory_manager();
+        let scope = AgentsFileScope::Chat {
+            channel: "telegram".into(),
+            chat_id: 42,
+        };
+        assert!(mm
+            .merge_agents_file(&scope, "- likes tea\n", MergeStrategy::Skip)
+            .unwrap());
+        assert!(!mm
+            .merge_agents_file(&scope, "- likes coffee\n", MergeStrategy::Skip)
+            .unwrap());
+        assert_eq!(
+            mm.read_chat_memory("telegram", 42).unwrap(),
+            "- likes tea\n"
+        );
+
+        assert!(mm
+            .merge_agents_file(
+                &scope,
+                "- likes tea\n- lives in Paris\n",
+                MergeStrategy::Dedupe
+            )
+            .unwrap());
+        assert!(!mm
+            .merge_agents_file(&scope, "- lives in Paris", MergeStrategy::Dedupe)
+            .unwrap());
+        assert_eq!(
+            mm.read_chat_memory("telegram", 42).unwrap(),
+            "- likes tea\n- lives in Paris\n"
+        );
+
+        assert!(mm
+            .merge_agents_file(&scope, "- replaced\n", MergeStrategy::Overwrite)
+            .unwrap());
+        assert_eq!(mm.read_chat_memory("telegram", 42).unwrap(), "- replaced\n");
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_groups_dir() {
         let (mm, dir) = test_memory_manager();
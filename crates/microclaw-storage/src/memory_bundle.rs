@@ -0,0 +1,271 @@
+//! Portable memory bundles: versioned JSONL that moves structured memories and the
+//! free-form `AGENTS.md` memory files between instances.
+//!
+//! The first line is a [`BundleHeader`]; every further line is a memory, a supersede
+//! edge or an `AGENTS.md` file. Chats are referenced by `(channel, external_chat_id)`
+//! rather than by the local `chat_id`, so a bundle can be imported into another
+//! database.
+
+use std::io::{BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use microclaw_core::error::MicroClawError;
+
+pub const BUNDLE_FORMAT: &str = "microclaw-memory-bundle";
+/// Version 2 added `agents_file` records; version 1 bundles still import.
+pub const BUNDLE_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BundleRecord {
+    Header(BundleHeader),
+    Memory(Box<BundleMemory>),
+    Supersede(BundleEdge),
+    AgentsFile(BundleAgentsFile),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleHeader {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    pub memory_count: usize,
+    pub edge_count: usize,
+    #[serde(default)]
+    pub agents_file_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BundleScope {
+    Global,
+    /// A channel's bot-wide `AGENTS.md`; memories never use this scope.
+    Bot {
+        channel: String,
+    },
+    Chat {
+        channel: String,
+        external_chat_id: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleMemory {
+    /// Id in the exporting database; only used to resolve supersede edges.
+    pub id: i64,
+    pub scope: BundleScope,
+    pub content: String,
+    pub category: String,
+    pub confidence: f64,
+    pub source: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub last_seen_at: String,
+    #[serde(default)]
+    pub is_archived: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<BundleEmbedding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleEmbedding {
+    pub model: String,
+    pub vector: Vec<f32>,
+}
+
+/// `from` was superseded by `to`; both are exporter-side memory ids.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleEdge {
+    pub from: i64,
+    pub to: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub created_at: String,
+}
+
+/// The contents of one `AGENTS.md` memory file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleAgentsFile {
+    pub scope: BundleScope,
+    pub content: String,
+}
+
+/// What to export.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Only memories of this chat; `None` exports every scope.
+    pub chat_id: Option<i64>,
+    /// Only global memories. Ignored when `chat_id` is set.
+    pub global_only: bool,
+    pub include_archived: bool,
+    pub include_embeddings: bool,
+}
+
+/// How an imported memory is merged with memories already in the same scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// Keep the existing memory when one with the same content exists.
+    #[default]
+    Skip,
+    /// Replace the metadata of an existing memory with the same content.
+    Overwrite,
+    /// Match active memories on `memory_topic_key`; the more recently updated one wins.
+    Dedupe,
+}
+
+impl MergeStrategy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "skip" => Some(MergeStrategy::Skip),
+            "overwrite" => Some(MergeStrategy::Overwrite),
+            "dedupe" | "dedup" => Some(MergeStrategy::Dedupe),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MergeStrategy::Skip => "skip",
+            MergeStrategy::Overwrite => "overwrite",
+            MergeStrategy::Dedupe => "dedupe",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImportReport {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    /// Chat-scoped memories without a channel and external chat id to map them by.
+    pub unmapped: usize,
+    pub edges: usize,
+    pub embeddings: usize,
+    /// `AGENTS.md` files written; files left unchanged count as `skipped`.
+    pub agents_files: usize,
+}
+
+/// Write `records` as JSONL, one record per line.
+pub fn write_bundle<W: Write>(records: &[BundleRecord], mut out: W) -> Result<(), MicroClawError> {
+    for record in records {
+        serde_json::to_writer(&mut out, record)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Parse a JSONL bundle, checking its header. Blank lines are ignored.
+pub fn read_bundle<R: BufRead>(input: R) -> Result<Vec<BundleRecord>, MicroClawError> {
+    let mut records = Vec::new();
+    for (idx, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: BundleRecord = serde_json::from_str(&line)
+            .map_err(|e| MicroClawError::Config(format!("memory bundle line {}: {e}", idx + 1)))?;
+        match (&record, records.is_empty()) {
+            (BundleRecord::Header(header), true) => {
+                if header.format != BUNDLE_FORMAT {
+                    return Err(MicroClawError::Config(format!(
+                        "not a memory bundle (format '{}')",
+                        header.format
+                    )));
+                }
+                if header.version > BUNDLE_VERSION {
+                    return Err(MicroClawError::Config(format!(
+                        "memory bundle version {} is newer than supported version {}",
+                        header.version, BUNDLE_VERSION
+                    )));
+                }
+            }
+            (BundleRecord::Header(_), false) => {
+                return Err(MicroClawError::Config(format!(
+                    "memory bundle line {}: unexpected second header",
+                    idx + 1
+                )));
+            }
+            (_, true) => {
+                return Err(MicroClawError::Config(
+                    "memory bundle must start with a header line".into(),
+                ));
+            }
+            _ => {}
+        }
+        records.push(record);
+    }
+    if records.is_empty() {
+        return Err(MicroClawError::Config("memory bundle is empty".into()));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_round_trip_and_header_checks() {
+        let records = vec![
+            BundleRecord::Header(BundleHeader {
+                format: BUNDLE_FORMAT.into(),
+                version: BUNDLE_VERSION,
+                exported_at: "2026-01-01T00:00:00Z".into(),
+                memory_count: 1,
+                edge_count: 0,
+                agents_file_count: 1,
+            }),
+            BundleRecord::Memory(Box::new(BundleMemory {
+                id: 7,
+                scope: BundleScope::Chat {
+                    channel: "telegram".into(),
+                    external_chat_id: "42".into(),
+                },
+                content: "User prefers tea".into(),
+                category: "PROFILE".into(),
+                confidence: 0.9,
+                source: "tool".into(),
+                created_at: "2026-01-01T00:00:00Z".into(),
+                updated_at: "2026-01-01T00:00:00Z".into(),
+                last_seen_at: "2026-01-01T00:00:00Z".into(),
+                is_archived: false,
+                archived_at: None,
+                embedding: Some(BundleEmbedding {
+                    model: "hashing-v1".into(),
+                    vector: vec![0.5, -0.5],
+                }),
+            })),
+            BundleRecord::AgentsFile(BundleAgentsFile {
+                scope: BundleScope::Bot {
+                    channel: "telegram".into(),
+                },
+                content: "# Notes\n".into(),
+            }),
+        ];
+        let mut buf = Vec::new();
+        write_bundle(&records, &mut buf).unwrap();
+        let text = String::from_utf8(buf.clone()).unwrap();
+        assert!(text.starts_with("{\"kind\":\"header\""));
+        assert!(text.contains("\"scope\":{\"type\":\"chat\""));
+        assert_eq!(read_bundle(buf.as_slice()).unwrap(), records);
+
+        assert!(text.contains("{\"kind\":\"agents_file\",\"scope\":{\"type\":\"bot\""));
+
+        let v1 = text.replacen("\"version\":2", "\"version\":1", 1).replacen(
+            ",\"agents_file_count\":1",
+            "",
+            1,
+        );
+        assert_eq!(read_bundle(v1.as_bytes()).unwrap().len(), records.len());
+
+        let newer = text.replacen("\"version\":2", "\"version\":99", 1);
+        assert!(read_bundle(newer.as_bytes()).is_err());
+        let headless = text.lines().nth(1).unwrap().to_string();
+        assert!(read_bundle(headless.as_bytes()).is_err());
+        assert!(read_bundle("".as_bytes()).is_err());
+    }
+}
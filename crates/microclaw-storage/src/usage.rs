@@ -26,17 +26,51 @@ fn fmt_int(v: i64) -> String {
     }
 }
 
-fn fmt_summary_line(name: &str, s: &LlmUsageSummary) -> String {
+/// Estimates the USD cost of one model's usage, cache reads and writes included;
+/// `None` when the model has no price.
+pub type CostEstimator<'a> = dyn Fn(&LlmModelUsageSummary) -> Option<f64> + Send + Sync + 'a;
+
+// `tok` counts uncached input plus output; cache tokens are billed at their own
+// rates and reported next to it rather than folded in.
+fn fmt_cache_suffix(cache_read: i64, cache_write: i64) -> String {
+    if cache_read == 0 && cache_write == 0 {
+        return String::new();
+    }
+    format!(
+        "  +cache read {} / write {} (not in tok)",
+        fmt_int(cache_read),
+        fmt_int(cache_write)
+    )
+}
+
+fn fmt_cost_suffix(cost: Option<f64>) -> String {
+    cost.map(|usd| format!("  ~${usd:.4}")).unwrap_or_default()
+}
+
+/// Sum of the priced rows, or `None` when no row has a price.
+fn total_cost(rows: &[LlmModelUsageSummary], estimate_cost: &CostEstimator<'_>) -> Option<f64> {
+    rows.iter()
+        .filter_map(estimate_cost)
+        .fold(None, |acc, usd| Some(acc.unwrap_or(0.0) + usd))
+}
+
+fn fmt_summary_line(name: &str, s: &LlmUsageSummary, cost: Option<f64>) -> String {
     format!(
-        "{name:<8} req={:>4}  tok={} (in {} / out {})",
+        "{name:<8} req={:>4}  tok={} (in {} / out {}){}{}",
         fmt_int(s.requests),
         fmt_int(s.total_tokens),
         fmt_int(s.input_tokens),
-        fmt_int(s.output_tokens)
+        fmt_int(s.output_tokens),
+        fmt_cache_suffix(s.cache_read_tokens, s.cache_write_tokens),
+        fmt_cost_suffix(cost)
     )
 }
 
-fn format_model_rows(rows: &[LlmModelUsageSummary], max_rows: usize) -> Vec<String> {
+fn format_model_rows(
+    rows: &[LlmModelUsageSummary],
+    max_rows: usize,
+    estimate_cost: &CostEstimator<'_>,
+) -> Vec<String> {
     if rows.is_empty() {
         return vec!["    - (no data)".to_string()];
     }
@@ -46,43 +80,82 @@ fn format_model_rows(rows: &[LlmModelUsageSummary], max_rows: usize) -> Vec<Stri
         .enumerate()
         .map(|(idx, row)| {
             format!(
-                "    {}. {}  tok={}  req={}  in {} / out {}",
+                "    {}. {}  tok={}  req={}  in {} / out {}{}{}",
                 idx + 1,
                 row.model,
                 fmt_int(row.total_tokens),
                 fmt_int(row.requests),
                 fmt_int(row.input_tokens),
-                fmt_int(row.output_tokens)
+                fmt_int(row.output_tokens),
+                fmt_cache_suffix(row.cache_read_tokens, row.cache_write_tokens),
+                fmt_cost_suffix(estimate_cost(row))
             )
         })
         .collect()
 }
 
-fn block_lines(
-    title: &str,
-    all: &LlmUsageSummary,
-    d24: &LlmUsageSummary,
-    d7: &LlmUsageSummary,
-    models_24h: &[LlmModelUsageSummary],
-    models_7d: &[LlmModelUsageSummary],
-) -> Vec<String> {
+struct UsageBlock {
+    all: LlmUsageSummary,
+    d24: LlmUsageSummary,
+    d7: LlmUsageSummary,
+    models_all: Vec<LlmModelUsageSummary>,
+    models_24h: Vec<LlmModelUsageSummary>,
+    models_7d: Vec<LlmModelUsageSummary>,
+}
+
+fn block_lines(title: &str, block: &UsageBlock, estimate_cost: &CostEstimator<'_>) -> Vec<String> {
     let mut lines = vec![
         title.to_string(),
         "".to_string(),
-        format!("  🧮 {}", fmt_summary_line("All-time", all)),
-        format!("  🕓 {}", fmt_summary_line("Last 24h", d24)),
-        format!("  📆 {}", fmt_summary_line("Last 7d", d7)),
+        format!(
+            "  🧮 {}",
+            fmt_summary_line(
+                "All-time",
+                &block.all,
+                total_cost(&block.models_all, estimate_cost)
+            )
+        ),
+        format!(
+            "  🕓 {}",
+            fmt_summary_line(
+                "Last 24h",
+                &block.d24,
+                total_cost(&block.models_24h, estimate_cost)
+            )
+        ),
+        format!(
+            "  📆 {}",
+            fmt_summary_line(
+                "Last 7d",
+                &block.d7,
+                total_cost(&block.models_7d, estimate_cost)
+            )
+        ),
         "".to_string(),
         "  🤖 Top models (24h)".to_string(),
     ];
-    lines.extend(format_model_rows(models_24h, 4));
+    lines.extend(format_model_rows(&block.models_24h, 4, estimate_cost));
     lines.push("".to_string());
     lines.push("  🤖 Top models (7d)".to_string());
-    lines.extend(format_model_rows(models_7d, 4));
+    lines.extend(format_model_rows(&block.models_7d, 4, estimate_cost));
 
     lines
 }
 
+async fn query_block(db: Arc<Database>, chat_id: Option<i64>) -> Result<UsageBlock, String> {
+    let now = chrono::Utc::now();
+    let since_24h = (now - chrono::Duration::hours(24)).to_rfc3339();
+    let since_7d = (now - chrono::Duration::days(7)).to_rfc3339();
+    Ok(UsageBlock {
+        all: query_summary(db.clone(), chat_id, None).await?,
+        d24: query_summary(db.clone(), chat_id, Some(since_24h.clone())).await?,
+        d7: query_summary(db.clone(), chat_id, Some(since_7d.clone())).await?,
+        models_all: query_by_model(db.clone(), chat_id, None).await?,
+        models_24h: query_by_model(db.clone(), chat_id, Some(since_24h)).await?,
+        models_7d: query_by_model(db, chat_id, Some(since_7d)).await?,
+    })
+}
+
 async fn query_summary(
     db: Arc<Database>,
     chat_id: Option<i64>,
@@ -116,42 +189,15 @@ async fn query_memory_summary(
         .map_err(|e| e.to_string())
 }
 
-pub async fn build_usage_report(db: Arc<Database>, chat_id: i64) -> Result<String, String> {
+/// `/usage` report for a chat and globally, priced with `estimate_cost`.
+pub async fn build_usage_report(
+    db: Arc<Database>,
+    chat_id: i64,
+    estimate_cost: &CostEstimator<'_>,
+) -> Result<String, String> {
     let now = chrono::Utc::now();
-    let since_24h = (now - chrono::Duration::hours(24)).to_rfc3339();
-    let since_7d = (now - chrono::Duration::days(7)).to_rfc3339();
-
-    let chat_all = query_summary(db.clone(), Some(chat_id), None).await?;
-    let chat_24h = query_summary(db.clone(), Some(chat_id), Some(since_24h.clone())).await?;
-    let chat_7d = query_summary(db.clone(), Some(chat_id), Some(since_7d.clone())).await?;
-    let chat_models_24h = query_by_model(db.clone(), Some(chat_id), Some(since_24h)).await?;
-    let chat_models_7d = query_by_model(db.clone(), Some(chat_id), Some(since_7d)).await?;
-
-    let global_all = query_summary(db.clone(), None, None).await?;
-    let global_24h = query_summary(
-        db.clone(),
-        None,
-        Some((now - chrono::Duration::hours(24)).to_rfc3339()),
-    )
-    .await?;
-    let global_7d = query_summary(
-        db.clone(),
-        None,
-        Some((now - chrono::Duration::days(7)).to_rfc3339()),
-    )
-    .await?;
-    let global_models_24h = query_by_model(
-        db.clone(),
-        None,
-        Some((now - chrono::Duration::hours(24)).to_rfc3339()),
-    )
-    .await?;
-    let global_models_7d = query_by_model(
-        db.clone(),
-        None,
-        Some((now - chrono::Duration::days(7)).to_rfc3339()),
-    )
-    .await?;
+    let chat_block = query_block(db.clone(), Some(chat_id)).await?;
+    let global_block = query_block(db.clone(), None).await?;
     let chat_mem = query_memory_summary(db.clone(), Some(chat_id)).await?;
     let global_mem = query_memory_summary(db.clone(), None).await?;
 
@@ -164,25 +210,11 @@ pub async fn build_usage_report(db: Arc<Database>, chat_id: i64) -> Result<Strin
         "".to_string(),
     ];
 
-    lines.extend(block_lines(
-        "🔹 This chat",
-        &chat_all,
-        &chat_24h,
-        &chat_7d,
-        &chat_models_24h,
-        &chat_models_7d,
-    ));
+    lines.extend(block_lines("🔹 This chat", &chat_block, estimate_cost));
 
     lines.push("".to_string());
 
-    lines.extend(block_lines(
-        "🌍 Global",
-        &global_all,
-        &global_24h,
-        &global_7d,
-        &global_models_24h,
-        &global_models_7d,
-    ));
+    lines.extend(block_lines("🌍 Global", &global_block, estimate_cost));
 
     lines.push("".to_string());
     lines.push("🧠 Memory Observability".to_string());
@@ -202,6 +234,15 @@ pub async fn build_usage_report(db: Arc<Database>, chat_id: i64) -> Result<Strin
         fmt_int(chat_mem.reflector_updated_24h),
         fmt_int(chat_mem.reflector_skipped_24h)
     ));
+    if chat_mem.review_pending > 0 || chat_mem.reflector_queued_24h > 0 {
+        lines.push(format!(
+            "  Review: pending={} queued_24h={} approved_24h={} rejected_24h={}",
+            fmt_int(chat_mem.review_pending),
+            fmt_int(chat_mem.reflector_queued_24h),
+            fmt_int(chat_mem.reflector_approved_24h),
+            fmt_int(chat_mem.reflector_rejected_24h)
+        ));
+    }
     lines.push(format!(
         "  Injection 24h: events={} selected/candidates={}/{}",
         fmt_int(chat_mem.injection_events_24h),
@@ -224,6 +265,15 @@ pub async fn build_usage_report(db: Arc<Database>, chat_id: i64) -> Result<Strin
         fmt_int(global_mem.reflector_updated_24h),
         fmt_int(global_mem.reflector_skipped_24h)
     ));
+    if global_mem.review_pending > 0 || global_mem.reflector_queued_24h > 0 {
+        lines.push(format!(
+            "  Global review: pending={} queued_24h={} approved_24h={} rejected_24h={}",
+            fmt_int(global_mem.review_pending),
+            fmt_int(global_mem.reflector_queued_24h),
+            fmt_int(global_mem.reflector_approved_24h),
+            fmt_int(global_mem.reflector_rejected_24h)
+        ));
+    }
     lines.push(format!(
         "  Global injection 24h: events={} selected/candidates={}/{}",
         fmt_int(global_mem.injection_events_24h),
@@ -233,3 +283,36 @@ pub async fn build_usage_report(db: Arc<Database>, chat_id: i64) -> Result<Strin
 
     Ok(lines.join("\n"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(model: &str, input: i64, cache_read: i64) -> LlmModelUsageSummary {
+        LlmModelUsageSummary {
+            model: model.to_string(),
+            requests: 1,
+            input_tokens: input,
+            output_tokens: 0,
+            total_tokens: input,
+            cache_read_tokens: cache_read,
+            cache_write_tokens: 0,
+        }
+    }
+
+    #[test]
+    fn test_model_rows_show_cache_and_cost() {
+        let estimate = |r: &LlmModelUsageSummary| {
+            (r.model == "priced").then(|| {
+                (r.input_tokens as f64 * 3.0 + r.cache_read_tokens as f64 * 0.3) / 1_000_000.0
+            })
+        };
+        let rows = vec![row("priced", 1_000_000, 1_000_000), row("free", 10, 0)];
+        let lines = format_model_rows(&rows, 4, &estimate);
+        assert!(lines[0].contains("+cache read 1,000,000 / write 0 (not in tok)"));
+        assert!(lines[0].ends_with("~$3.3000"), "{}", lines[0]);
+        assert!(!lines[1].contains('$'), "{}", lines[1]);
+        assert_eq!(total_cost(&rows, &estimate), Some(3.3));
+        assert_eq!(total_cost(&rows[1..], &estimate), None);
+    }
+}
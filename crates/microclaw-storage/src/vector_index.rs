@@ -0,0 +1,690 @@
+//! In-process vector index for memory embeddings.
+//!
+//! [`HnswIndex`] is a pure-Rust HNSW graph (cosine distance) used when the sqlite-vec
+//! extension is not compiled in or not selected. [`PersistentHnsw`] keeps it in a file
+//! under the data directory. Entries are replaced and removed by tombstoning; the graph
+//! is rebuilt once more than half of it is tombstones.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use microclaw_core::error::MicroClawError;
+
+/// Which index backs semantic memory search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VectorBackend {
+    /// sqlite-vec when compiled in, HNSW otherwise.
+    #[default]
+    Auto,
+    SqliteVec,
+    Hnsw,
+}
+
+impl VectorBackend {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "auto" => Some(VectorBackend::Auto),
+            "sqlite-vec" | "sqlite_vec" => Some(VectorBackend::SqliteVec),
+            "hnsw" => Some(VectorBackend::Hnsw),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VectorBackend::Auto => "auto",
+            VectorBackend::SqliteVec => "sqlite-vec",
+            VectorBackend::Hnsw => "hnsw",
+        }
+    }
+
+    /// The backend `Auto` stands for in this build.
+    pub fn resolve(self) -> Self {
+        match self {
+            VectorBackend::Auto if cfg!(feature = "sqlite-vec") => VectorBackend::SqliteVec,
+            VectorBackend::Auto => VectorBackend::Hnsw,
+            other => other,
+        }
+    }
+}
+
+/// Links per node above layer 0; layer 0 keeps twice as many.
+const M: usize = 16;
+const EF_CONSTRUCTION: usize = 100;
+const EF_SEARCH: usize = 64;
+const MAX_LEVEL: usize = 12;
+const FILE_MAGIC: &[u8; 8] = b"MCHNSW01";
+/// Magic, dimension, rng state, entry point and node count.
+const FILE_HEADER_LEN: u64 = 32;
+/// Larger than any embedding model produces; anything above it is a corrupt file.
+const MAX_DIM: usize = 1 << 16;
+
+struct Node {
+    id: i64,
+    deleted: bool,
+    vector: Vec<f32>,
+    /// Neighbours per layer, from layer 0 up to the node's level.
+    links: Vec<Vec<u32>>,
+}
+
+/// Candidate in a layer search, ordered by distance.
+#[derive(Clone, Copy, PartialEq)]
+struct Scored(f32, u32);
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .total_cmp(&other.0)
+            .then_with(|| self.1.cmp(&other.1))
+    }
+}
+
+/// HNSW graph over unit-normalised vectors, keyed by memory id.
+pub struct HnswIndex {
+    dim: usize,
+    nodes: Vec<Node>,
+    by_id: HashMap<i64, u32>,
+    entry: Option<u32>,
+    deleted: usize,
+    rng: u64,
+}
+
+impl HnswIndex {
+    pub fn new(dim: usize) -> Self {
+        Self {
+            dim: dim.max(1),
+            nodes: Vec::new(),
+            by_id: HashMap::new(),
+            entry: None,
+            deleted: 0,
+            rng: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.dim
+    }
+
+    /// Number of live entries.
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    pub fn contains(&self, id: i64) -> bool {
+        self.by_id.contains_key(&id)
+    }
+
+    /// The stored (unit-normalised) vector for `id`.
+    pub fn vector(&self, id: i64) -> Option<&[f32]> {
+        self.by_id
+            .get(&id)
+            .map(|&idx| self.nodes[idx as usize].vector.as_slice())
+    }
+
+    /// Insert or replace the vector for `id`.
+    pub fn upsert(&mut self, id: i64, vector: &[f32]) -> Result<(), MicroClawError> {
+        if vector.len() != self.dim {
+            return Err(MicroClawError::Config(format!(
+                "embedding has {} dimensions, the vector index expects {}",
+                vector.len(),
+                self.dim
+            )));
+        }
+        self.tombstone(id);
+        let vector = normalize(vector);
+        let level = self.random_level();
+        let idx = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            id,
+            deleted: false,
+            vector,
+            links: vec![Vec::new(); level + 1],
+        });
+        self.by_id.insert(id, idx);
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(idx);
+            return Ok(());
+        };
+        let top = self.level_of(entry);
+        let query = self.nodes[idx as usize].vector.clone();
+        let mut eps = vec![entry];
+        for layer in (level + 1..=top).rev() {
+            eps = vec![self.search_layer(&query, &eps, 1, layer)[0].1];
+        }
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &eps, EF_CONSTRUCTION, layer);
+            let max_links = max_links(layer);
+            let neighbours: Vec<u32> = found.iter().take(max_links).map(|s| s.1).collect();
+            for &n in &neighbours {
+                self.link(n, idx, layer);
+            }
+            self.nodes[idx as usize].links[layer] = neighbours;
+            eps = found.into_iter().map(|s| s.1).collect();
+        }
+        if level > top {
+            self.entry = Some(idx);
+        }
+        if self.deleted > 64 && self.deleted * 2 > self.nodes.len() {
+            self.compact();
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: i64) -> bool {
+        self.tombstone(id)
+    }
+
+    /// The `k` nearest live entries to `query` as `(id, cosine distance)`, nearest first.
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(i64, f32)> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        if query.len() != self.dim || k == 0 {
+            return Vec::new();
+        }
+        let query = normalize(query);
+        let mut eps = vec![entry];
+        for layer in (1..=self.level_of(entry)).rev() {
+            eps = vec![self.search_layer(&query, &eps, 1, layer)[0].1];
+        }
+        self.search_layer(&query, &eps, EF_SEARCH.max(k), 0)
+            .into_iter()
+            .filter(|s| !self.nodes[s.1 as usize].deleted)
+            .take(k)
+            .map(|s| (self.nodes[s.1 as usize].id, s.0))
+            .collect()
+    }
+
+    fn tombstone(&mut self, id: i64) -> bool {
+        match self.by_id.remove(&id) {
+            Some(idx) => {
+                self.nodes[idx as usize].deleted = true;
+                self.deleted += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn level_of(&self, idx: u32) -> usize {
+        self.nodes[idx as usize].links.len() - 1
+    }
+
+    fn distance(&self, query: &[f32], idx: u32) -> f32 {
+        let v = &self.nodes[idx as usize].vector;
+        1.0 - query.iter().zip(v).map(|(a, b)| a * b).sum::<f32>()
+    }
+
+    /// Best-first search of one layer; returns up to `ef` candidates, nearest first.
+    fn search_layer(&self, query: &[f32], eps: &[u32], ef: usize, layer: usize) -> Vec<Scored> {
+        let mut visited: HashSet<u32> = eps.iter().copied().collect();
+        let mut candidates: BinaryHeap<std::cmp::Reverse<Scored>> = BinaryHeap::new();
+        let mut best: BinaryHeap<Scored> = BinaryHeap::new();
+        for &ep in eps {
+            let scored = Scored(self.distance(query, ep), ep);
+            candidates.push(std::cmp::Reverse(scored));
+            best.push(scored);
+        }
+        while best.len() > ef {
+            best.pop();
+        }
+        while let Some(std::cmp::Reverse(current)) = candidates.pop() {
+            if let Some(worst) = best.peek() {
+                if best.len() >= ef && current.0 > worst.0 {
+                    break;
+                }
+            }
+            let Some(links) = self.nodes[current.1 as usize].links.get(layer) else {
+                continue;
+            };
+            for &n in links {
+                if !visited.insert(n) {
+                    continue;
+                }
+                let scored = Scored(self.distance(query, n), n);
+                if best.len() < ef || best.peek().is_some_and(|w| scored.0 < w.0) {
+                    candidates.push(std::cmp::Reverse(scored));
+                    best.push(scored);
+                    if best.len() > ef {
+                        best.pop();
+                    }
+                }
+            }
+        }
+        best.into_sorted_vec()
+    }
+
+    /// Add `to` to `from`'s links on `layer`, keeping only the nearest when over the limit.
+    fn link(&mut self, from: u32, to: u32, layer: usize) {
+        let max_links = max_links(layer);
+        let Some(links) = self.nodes[from as usize].links.get(layer) else {
+            return;
+        };
+        let mut links = links.clone();
+        links.push(to);
+        if links.len() > max_links {
+            let base = self.nodes[from as usize].vector.clone();
+            let mut scored: Vec<Scored> = links
+                .iter()
+                .map(|&n| Scored(self.distance(&base, n), n))
+                .collect();
+            scored.sort();
+            links = scored.into_iter().take(max_links).map(|s| s.1).collect();
+        }
+        self.nodes[from as usize].links[layer] = links;
+    }
+
+    fn random_level(&mut self) -> usize {
+        // xorshift64*, seeded deterministically so rebuilds are reproducible.
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let bits = self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11;
+        let uniform = (bits as f64 + 1.0) / (1u64 << 53) as f64;
+        let level = (-uniform.ln() / (M as f64).ln()).floor() as usize;
+        level.min(MAX_LEVEL)
+    }
+
+    /// Rebuild the graph from the live entries, dropping tombstones.
+    fn compact(&mut self) {
+        let live: Vec<(i64, Vec<f32>)> = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .filter(|n| !n.deleted)
+            .map(|n| (n.id, n.vector))
+            .collect();
+        self.by_id.clear();
+        self.entry = None;
+        self.deleted = 0;
+        for (id, vector) in live {
+            // Stored vectors already have the index's dimension.
+            let _ = self.upsert(id, &vector);
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), MicroClawError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        {
+            let mut w = BufWriter::new(std::fs::File::create(&tmp)?);
+            w.write_all(FILE_MAGIC)?;
+            w.write_all(&(self.dim as u32).to_le_bytes())?;
+            w.write_all(&self.rng.to_le_bytes())?;
+            w.write_all(&self.entry.map(i64::from).unwrap_or(-1).to_le_bytes())?;
+            w.write_all(&(self.nodes.len() as u32).to_le_bytes())?;
+            for node in &self.nodes {
+                w.write_all(&node.id.to_le_bytes())?;
+                w.write_all(&[u8::from(node.deleted), node.links.len() as u8])?;
+                for x in &node.vector {
+                    w.write_all(&x.to_le_bytes())?;
+                }
+                for links in &node.links {
+                    w.write_all(&(links.len() as u32).to_le_bytes())?;
+                    for n in links {
+                        w.write_all(&n.to_le_bytes())?;
+                    }
+                }
+            }
+            w.flush()?;
+        }
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Load an index written by [`HnswIndex::save`]. `Ok(None)` if the file does not exist.
+    pub fn load(path: &Path) -> Result<Option<Self>, MicroClawError> {
+        let file = match std::fs::File::open(path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let file_len = file.metadata()?.len();
+        let corrupt =
+            |what: String| MicroClawError::Config(format!("{} is corrupt: {what}", path.display()));
+        let mut r = BufReader::new(file);
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != FILE_MAGIC {
+            return Err(MicroClawError::Config(format!(
+                "{} is not a vector index file",
+                path.display()
+            )));
+        }
+        let dim = read_u32(&mut r)? as usize;
+        if dim == 0 || dim > MAX_DIM {
+            return Err(corrupt(format!("dimension {dim}")));
+        }
+        let mut index = HnswIndex::new(dim);
+        index.rng = u64::from_le_bytes(read_array(&mut r)?);
+        let entry = i64::from_le_bytes(read_array(&mut r)?);
+        let count = read_u32(&mut r)?;
+        // Every node takes at least its id, flags and vector; check before allocating.
+        let min_node_len = 10 + 4 * dim as u64;
+        if u64::from(count) * min_node_len > file_len.saturating_sub(FILE_HEADER_LEN) {
+            return Err(corrupt(format!(
+                "{count} nodes of dimension {dim} do not fit in {file_len} bytes"
+            )));
+        }
+        for idx in 0..count {
+            let id = i64::from_le_bytes(read_array(&mut r)?);
+            let [deleted, layers] = read_array(&mut r)?;
+            if layers as usize > MAX_LEVEL + 1 {
+                return Err(corrupt(format!("node {idx} has {layers} layers")));
+            }
+            let mut vector = Vec::with_capacity(dim);
+            for _ in 0..dim {
+                vector.push(f32::from_le_bytes(read_array(&mut r)?));
+            }
+            let mut links = Vec::with_capacity(layers as usize);
+            for layer in 0..layers as usize {
+                let n = read_u32(&mut r)?;
+                if n as usize > max_links(layer) {
+                    return Err(corrupt(format!(
+                        "node {idx} has {n} links on layer {layer}"
+                    )));
+                }
+                let mut layer = Vec::with_capacity(n as usize);
+                for _ in 0..n {
+                    let link = read_u32(&mut r)?;
+                    if link >= count {
+                        return Err(corrupt(format!("link to node {link} of {count}")));
+                    }
+                    layer.push(link);
+                }
+                links.push(layer);
+            }
+            if links.is_empty() {
+                links.push(Vec::new());
+            }
+            if deleted != 0 {
+                index.deleted += 1;
+            } else {
+                index.by_id.insert(id, idx);
+            }
+            index.nodes.push(Node {
+                id,
+                deleted: deleted != 0,
+                vector,
+                links,
+            });
+        }
+        index.entry = u32::try_from(entry).ok().filter(|e| *e < count);
+        Ok(Some(index))
+    }
+}
+
+fn max_links(layer: usize) -> usize {
+    if layer == 0 {
+        M * 2
+    } else {
+        M
+    }
+}
+
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter().map(|x| x / norm).collect()
+    } else {
+        vector.to_vec()
+    }
+}
+
+fn read_array<const N: usize>(r: &mut impl Read) -> Result<[u8; N], MicroClawError> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u32(r: &mut impl Read) -> Result<u32, MicroClawError> {
+    Ok(u32::from_le_bytes(read_array(r)?))
+}
+
+/// An [`HnswIndex`] backed by a file. Changes are written at most every few seconds
+/// and on drop; entries lost in between are re-embedded, see
+/// [`crate::db::Database::prepare_vector_index`].
+pub struct PersistentHnsw {
+    index: HnswIndex,
+    path: PathBuf,
+    dirty: bool,
+    last_saved: Instant,
+}
+
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+impl PersistentHnsw {
+    /// Open the index at `path`. Starts empty when the file is missing, unreadable or
+    /// built for another dimension; the second value is `false` in that case.
+    pub fn open(path: PathBuf, dim: usize) -> (Self, bool) {
+        let loaded = match HnswIndex::load(&path) {
+            Ok(Some(index)) if index.dimension() == dim.max(1) => Some(index),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Discarding vector index {}: {e}", path.display());
+                None
+            }
+        };
+        let reused = loaded.is_some();
+        let index = loaded.unwrap_or_else(|| HnswIndex::new(dim));
+        (
+            Self {
+                index,
+                path,
+                dirty: !reused,
+                last_saved: Instant::now(),
+            },
+            reused,
+        )
+    }
+
+    pub fn index(&self) -> &HnswIndex {
+        &self.index
+    }
+
+    pub fn upsert(&mut self, id: i64, vector: &[f32]) -> Result<(), MicroClawError> {
+        self.index.upsert(id, vector)?;
+        self.touched()
+    }
+
+    pub fn remove(&mut self, id: i64) -> Result<(), MicroClawError> {
+        if self.index.remove(id) {
+            self.touched()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), MicroClawError> {
+        if self.dirty {
+            self.index.save(&self.path)?;
+            self.dirty = false;
+            self.last_saved = Instant::now();
+        }
+        Ok(())
+    }
+
+    fn touched(&mut self) -> Result<(), MicroClawError> {
+        self.dirty = true;
+        if self.last_saved.elapsed() >= SAVE_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PersistentHnsw {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::warn!("Failed to save vector index {}: {e}", self.path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(seed: u64, dim: usize) -> Vec<f32> {
+        let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (0..dim)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                ((state >> 33) as f32 / u32::MAX as f32) - 0.25
+            })
+            .collect()
+    }
+
+    fn brute_force(vectors: &[(i64, Vec<f32>)], query: &[f32], k: usize) -> Vec<i64> {
+        let q = normalize(query);
+        let mut scored: Vec<(f32, i64)> = vectors
+            .iter()
+            .map(|(id, v)| {
+                let v = normalize(v);
+                (1.0 - q.iter().zip(&v).map(|(a, b)| a * b).sum::<f32>(), *id)
+            })
+            .collect();
+        scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+        scored.into_iter().take(k).map(|(_, id)| id).collect()
+    }
+
+    #[test]
+    fn test_hnsw_recall_matches_brute_force() {
+        let dim = 32;
+        let vectors: Vec<(i64, Vec<f32>)> = (0..600).map(|i| (i, vector(i as u64, dim))).collect();
+        let mut index = HnswIndex::new(dim);
+        for (id, v) in &vectors {
+            index.upsert(*id, v).unwrap();
+        }
+        assert_eq!(index.len(), 600);
+
+        let mut found = 0;
+        for q in 0..20u64 {
+            let query = vector(10_000 + q, dim);
+            let expected = brute_force(&vectors, &query, 10);
+            let got: Vec<i64> = index.search(&query, 10).into_iter().map(|h| h.0).collect();
+            found += got.iter().filter(|id| expected.contains(id)).count();
+        }
+        assert!(found >= 180, "recall@10 too low: {found}/200");
+
+        let exact = index.search(&vectors[42].1, 1);
+        assert_eq!(exact[0].0, 42);
+        assert!(exact[0].1.abs() < 1e-5);
+        assert!(index.upsert(1, &[1.0]).is_err());
+    }
+
+    #[test]
+    fn test_hnsw_replace_remove_and_compact() {
+        let dim = 8;
+        let mut index = HnswIndex::new(dim);
+        for i in 0..200 {
+            index.upsert(i, &vector(i as u64, dim)).unwrap();
+        }
+        let target = vector(9_999, dim);
+        index.upsert(7, &target).unwrap();
+        assert_eq!(index.len(), 200);
+        assert_eq!(index.search(&target, 1)[0].0, 7);
+
+        assert!(index.remove(7));
+        assert!(!index.remove(7));
+        assert!(index.search(&target, 5).iter().all(|h| h.0 != 7));
+
+        for i in 0..150 {
+            index.remove(i);
+        }
+        // Tombstones past half the graph trigger a rebuild on the next insert.
+        index.upsert(1_000, &target).unwrap();
+        assert_eq!(index.nodes.len(), 51);
+        assert_eq!(index.search(&target, 1)[0].0, 1_000);
+    }
+
+    #[test]
+    fn test_persistent_hnsw_round_trip() {
+        let dir = std::env::temp_dir().join(format!("mc_hnsw_{}", uuid::Uuid::new_v4()));
+        let path = dir.join("memory_vectors.hnsw");
+        let dim = 16;
+        {
+            let (mut store, reused) = PersistentHnsw::open(path.clone(), dim);
+            assert!(!reused);
+            for i in 0..50 {
+                store.upsert(i, &vector(i as u64, dim)).unwrap();
+            }
+            store.remove(3).unwrap();
+        }
+
+        let (store, reused) = PersistentHnsw::open(path.clone(), dim);
+        assert!(reused);
+        assert_eq!(store.index().len(), 49);
+        assert!(!store.index().contains(3));
+        assert_eq!(store.index().search(&vector(20, dim), 1)[0].0, 20);
+        drop(store);
+
+        let (store, reused) = PersistentHnsw::open(path.clone(), dim * 2);
+        assert!(!reused);
+        assert!(store.index().is_empty());
+
+        assert_eq!(
+            VectorBackend::parse("SQLITE_VEC"),
+            Some(VectorBackend::SqliteVec)
+        );
+        assert_eq!(VectorBackend::parse("faiss"), None);
+        assert_ne!(VectorBackend::Auto.resolve(), VectorBackend::Auto);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_hnsw_load_rejects_oversized_headers() {
+        let dir = std::env::temp_dir().join(format!("mc_hnsw_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("memory_vectors.hnsw");
+        let file = |dim: u32, count: u32, node: &[u8]| {
+            let mut bytes = FILE_MAGIC.to_vec();
+            bytes.extend_from_slice(&dim.to_le_bytes());
+            bytes.extend_from_slice(&0u64.to_le_bytes());
+            bytes.extend_from_slice(&(-1i64).to_le_bytes());
+            bytes.extend_from_slice(&count.to_le_bytes());
+            bytes.extend_from_slice(node);
+            bytes
+        };
+
+        std::fs::write(&path, file(u32::MAX, 0, &[])).unwrap();
+        assert!(HnswIndex::load(&path).is_err());
+        std::fs::write(&path, file(4, u32::MAX, &[])).unwrap();
+        assert!(HnswIndex::load(&path).is_err());
+
+        // One node whose only layer claims four billion links.
+        let mut node = 7i64.to_le_bytes().to_vec();
+        node.extend_from_slice(&[0, 1]);
+        node.extend_from_slice(&[0u8; 16]);
+        node.extend_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, file(4, 1, &node)).unwrap();
+        let err = HnswIndex::load(&path).err().unwrap().to_string();
+        assert!(err.contains("links on layer 0"), "{err}");
+
+        // Corrupt files are discarded, not fatal.
+        let (store, reused) = PersistentHnsw::open(path.clone(), 4);
+        assert!(!reused);
+        assert!(store.index().is_empty());
+        drop(store);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,946 @@
+//! Working-directory checkpoints taken before the agent changes files.
+//!
+//! Directories that are git work trees are captured as detached commits kept alive by
+//! `refs/microclaw/checkpoints/*`. Anything else goes to a content-addressed store
+//! under `<data_dir>/checkpoints`: file contents in `objects/`, one JSON manifest per
+//! snapshot in `manifests/`, both named by their SHA-256.
+//!
+//! The agent can write to the working dir, `.git` included, so git runs with hooks,
+//! fsmonitor, system/global config and filter drivers switched off. When tools run in
+//! a sandbox the store is used even for git work trees (see
+//! [`CheckpointStore::with_git_snapshots`]), so nothing host-side interprets
+//! repository config the sandboxed agent wrote.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_max_per_chat() -> usize {
+    20
+}
+
+fn default_max_file_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_exclude() -> Vec<String> {
+    vec![
+        ".git".into(),
+        "node_modules".into(),
+        "target".into(),
+        ".tool_results".into(),
+    ]
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CheckpointConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Checkpoints kept per chat; older ones are discarded.
+    #[serde(default = "default_max_per_chat")]
+    pub max_per_chat: usize,
+    /// Larger files are left out of snapshots (and left alone on restore).
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// File or directory names skipped at any depth by content-addressed snapshots.
+    #[serde(default = "default_exclude")]
+    pub exclude: Vec<String>,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            max_per_chat: default_max_per_chat(),
+            max_file_bytes: default_max_file_bytes(),
+            exclude: default_exclude(),
+        }
+    }
+}
+
+impl CheckpointConfig {
+    pub fn normalize(&mut self) {
+        if self.max_per_chat == 0 {
+            self.max_per_chat = default_max_per_chat();
+        }
+        if self.max_file_bytes == 0 {
+            self.max_file_bytes = default_max_file_bytes();
+        }
+        self.exclude = self
+            .exclude
+            .iter()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckpointKind {
+    Git,
+    Store,
+}
+
+impl CheckpointKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckpointKind::Git => "git",
+            CheckpointKind::Store => "store",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "git" => Some(CheckpointKind::Git),
+            "store" => Some(CheckpointKind::Store),
+            _ => None,
+        }
+    }
+}
+
+/// Where a checkpoint's content lives: a commit id for [`CheckpointKind::Git`], a
+/// manifest hash for [`CheckpointKind::Store`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub kind: CheckpointKind,
+    pub reference: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RestoreSummary {
+    pub written: usize,
+    pub removed: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Entry {
+    Dir,
+    File {
+        hash: String,
+        size: u64,
+        mode: u32,
+        mtime_ns: u64,
+    },
+    Symlink {
+        target: String,
+    },
+    /// Present but too large to store; restore leaves it untouched.
+    Skipped,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    entries: BTreeMap<String, Entry>,
+}
+
+pub struct CheckpointStore {
+    root: PathBuf,
+    config: CheckpointConfig,
+    git_snapshots: bool,
+}
+
+impl CheckpointStore {
+    pub fn new(data_dir: &Path, config: CheckpointConfig) -> Self {
+        Self {
+            root: data_dir.join("checkpoints"),
+            config,
+            git_snapshots: true,
+        }
+    }
+
+    /// Whether git work trees are captured as git commits. Turned off when tools run
+    /// sandboxed; existing git checkpoints can still be restored.
+    pub fn with_git_snapshots(mut self, enabled: bool) -> Self {
+        self.git_snapshots = enabled;
+        self
+    }
+
+    /// Capture `dir`. `previous` (an earlier snapshot of the same directory) lets
+    /// unchanged files skip re-hashing.
+    pub fn snapshot(&self, dir: &Path, previous: Option<&Snapshot>) -> Result<Snapshot> {
+        if self.git_snapshots && is_git_work_tree(dir) {
+            return Ok(Snapshot {
+                kind: CheckpointKind::Git,
+                reference: self.snapshot_git(dir)?,
+            });
+        }
+        let previous = previous
+            .filter(|s| s.kind == CheckpointKind::Store)
+            .and_then(|s| self.load_manifest(&s.reference).ok())
+            .unwrap_or_default();
+        Ok(Snapshot {
+            kind: CheckpointKind::Store,
+            reference: self.snapshot_store(dir, &previous)?,
+        })
+    }
+
+    /// Bring `target_dir` back to the snapshot taken of `source_dir`. They are the same
+    /// directory except when restoring into a forked session's own working dir.
+    pub fn restore(
+        &self,
+        snapshot: &Snapshot,
+        source_dir: &Path,
+        target_dir: &Path,
+    ) -> Result<RestoreSummary> {
+        std::fs::create_dir_all(target_dir)
+            .with_context(|| format!("failed to create '{}'", target_dir.display()))?;
+        match snapshot.kind {
+            CheckpointKind::Git => self.restore_git(&snapshot.reference, source_dir, target_dir),
+            CheckpointKind::Store => self.restore_store(&snapshot.reference, target_dir),
+        }
+    }
+
+    /// Drop what keeps a discarded snapshot alive. Store content is only reclaimed by
+    /// [`CheckpointStore::collect_garbage`], since manifests can be shared.
+    pub fn discard(&self, snapshot: &Snapshot, source_dir: &Path) -> Result<()> {
+        if snapshot.kind == CheckpointKind::Git && is_git_work_tree(source_dir) {
+            git(source_dir)
+                .args(["update-ref", "-d", &checkpoint_ref(&snapshot.reference)])
+                .run()?;
+        }
+        Ok(())
+    }
+
+    /// Delete manifests not in `live` and objects no remaining manifest uses.
+    /// Returns how many files were removed.
+    pub fn collect_garbage(&self, live: &HashSet<String>) -> Result<usize> {
+        let mut removed = 0;
+        let mut live_objects = HashSet::new();
+        for (name, path) in list_dir(&self.root.join("manifests")) {
+            let Some(reference) = name.strip_suffix(".json") else {
+                continue;
+            };
+            if live.contains(reference) {
+                if let Ok(manifest) = self.load_manifest(reference) {
+                    live_objects.extend(manifest.entries.into_values().filter_map(|e| match e {
+                        Entry::File { hash, .. } => Some(hash),
+                        _ => None,
+                    }));
+                }
+            } else if std::fs::remove_file(&path).is_ok() {
+                removed += 1;
+            }
+        }
+        for (_, shard) in list_dir(&self.root.join("objects")) {
+            for (name, path) in list_dir(&shard) {
+                let hash = format!(
+                    "{}{name}",
+                    shard.file_name().unwrap_or_default().to_string_lossy()
+                );
+                if !live_objects.contains(&hash) && std::fs::remove_file(&path).is_ok() {
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.root.join("objects").join(&hash[..2]).join(&hash[2..])
+    }
+
+    fn manifest_path(&self, reference: &str) -> PathBuf {
+        self.root
+            .join("manifests")
+            .join(format!("{reference}.json"))
+    }
+
+    fn load_manifest(&self, reference: &str) -> Result<Manifest> {
+        if reference.len() != 64 || !reference.bytes().all(|b| b.is_ascii_hexdigit()) {
+            bail!("invalid checkpoint reference '{reference}'");
+        }
+        let raw = std::fs::read(self.manifest_path(reference))
+            .with_context(|| format!("checkpoint '{reference}' is missing from the store"))?;
+        Ok(serde_json::from_slice(&raw)?)
+    }
+
+    fn is_oversized(&self, path: &Path) -> bool {
+        std::fs::symlink_metadata(path)
+            .is_ok_and(|meta| meta.is_file() && meta.len() > self.config.max_file_bytes)
+    }
+
+    fn is_excluded(&self, name: &str) -> bool {
+        self.config.exclude.iter().any(|e| e == name)
+    }
+
+    /// Everything under `dir` the store tracks, keyed by `/`-separated relative path.
+    fn walk(&self, dir: &Path) -> Result<BTreeMap<String, (PathBuf, std::fs::Metadata)>> {
+        let mut out = BTreeMap::new();
+        let mut pending = vec![(dir.to_path_buf(), String::new())];
+        while let Some((abs, rel)) = pending.pop() {
+            let entries = std::fs::read_dir(&abs)
+                .with_context(|| format!("failed to read '{}'", abs.display()))?;
+            for entry in entries {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if self.is_excluded(&name) {
+                    continue;
+                }
+                let path = entry.path();
+                let meta = std::fs::symlink_metadata(&path)?;
+                let rel = if rel.is_empty() {
+                    name
+                } else {
+                    format!("{rel}/{name}")
+                };
+                if meta.is_dir() {
+                    pending.push((path.clone(), rel.clone()));
+                }
+                out.insert(rel, (path, meta));
+            }
+        }
+        Ok(out)
+    }
+
+    fn snapshot_store(&self, dir: &Path, previous: &Manifest) -> Result<String> {
+        let mut manifest = Manifest::default();
+        for (rel, (path, meta)) in self.walk(dir)? {
+            let entry = if meta.is_dir() {
+                Entry::Dir
+            } else if meta.file_type().is_symlink() {
+                Entry::Symlink {
+                    target: std::fs::read_link(&path)?.to_string_lossy().into_owned(),
+                }
+            } else if !meta.is_file() {
+                continue;
+            } else if meta.len() > self.config.max_file_bytes {
+                Entry::Skipped
+            } else {
+                let size = meta.len();
+                let mtime_ns = mtime_ns(&meta);
+                let hash = match previous.entries.get(&rel) {
+                    Some(Entry::File {
+                        hash,
+                        size: prev_size,
+                        mtime_ns: prev_mtime,
+                        ..
+                    }) if *prev_size == size
+                        && *prev_mtime == mtime_ns
+                        && self.object_path(hash).exists() =>
+                    {
+                        hash.clone()
+                    }
+                    _ => self.store_object(&path)?,
+                };
+                Entry::File {
+                    hash,
+                    size,
+                    mode: file_mode(&meta),
+                    mtime_ns,
+                }
+            };
+            manifest.entries.insert(rel, entry);
+        }
+        let raw = serde_json::to_vec(&manifest)?;
+        let reference = sha256_hex(&raw);
+        let path = self.manifest_path(&reference);
+        if !path.exists() {
+            write_atomic(&path, &raw)?;
+        }
+        Ok(reference)
+    }
+
+    fn store_object(&self, path: &Path) -> Result<String> {
+        let data =
+            std::fs::read(path).with_context(|| format!("failed to read '{}'", path.display()))?;
+        let hash = sha256_hex(&data);
+        let object = self.object_path(&hash);
+        if !object.exists() {
+            write_atomic(&object, &data)?;
+        }
+        Ok(hash)
+    }
+
+    fn restore_store(&self, reference: &str, dir: &Path) -> Result<RestoreSummary> {
+        let manifest = self.load_manifest(reference)?;
+        let mut summary = RestoreSummary::default();
+
+        // Remove what the snapshot does not have, deepest paths first.
+        let current = self.walk(dir)?;
+        for (rel, (path, meta)) in current.iter().rev() {
+            let keep = match manifest.entries.get(rel) {
+                Some(Entry::Dir) => meta.is_dir(),
+                Some(Entry::Symlink { .. }) => meta.file_type().is_symlink(),
+                Some(Entry::File { .. } | Entry::Skipped) => meta.is_file(),
+                None => false,
+            };
+            if keep {
+                continue;
+            }
+            if meta.is_dir() {
+                // May still hold excluded entries; those stay.
+                if std::fs::remove_dir(path).is_ok() {
+                    summary.removed += 1;
+                }
+            } else {
+                std::fs::remove_file(path)
+                    .with_context(|| format!("failed to remove '{}'", path.display()))?;
+                summary.removed += 1;
+            }
+        }
+
+        for (rel, entry) in &manifest.entries {
+            let path = dir.join(rel);
+            match entry {
+                Entry::Dir => std::fs::create_dir_all(&path)?,
+                Entry::Skipped => {}
+                Entry::Symlink { target } => {
+                    if std::fs::read_link(&path).ok().as_deref() == Some(Path::new(target)) {
+                        continue;
+                    }
+                    let _ = std::fs::remove_file(&path);
+                    make_symlink(Path::new(target), &path)?;
+                    summary.written += 1;
+                }
+                Entry::File { hash, mode, .. } => {
+                    if path.is_file()
+                        && std::fs::read(&path).is_ok_and(|data| sha256_hex(&data) == *hash)
+                    {
+                        continue;
+                    }
+                    let data = std::fs::read(self.object_path(hash))
+                        .with_context(|| format!("object for '{rel}' is missing"))?;
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::write(&path, data)
+                        .with_context(|| format!("failed to write '{}'", path.display()))?;
+                    set_file_mode(&path, *mode);
+                    summary.written += 1;
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    fn scratch_index(&self) -> Result<PathBuf> {
+        let dir = self.root.join("tmp");
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir.join(format!("index-{}-{}", std::process::id(), unique_suffix())))
+    }
+
+    fn snapshot_git(&self, dir: &Path) -> Result<String> {
+        let index = self.scratch_index()?;
+        let _cleanup = RemoveOnDrop(index.clone());
+        // Start from the real index so `git add` can reuse its stat cache.
+        let real_index = git(dir).args(["rev-parse", "--git-path", "index"]).run()?;
+        let real_index = dir.join(real_index.trim());
+        if real_index.exists() {
+            std::fs::copy(&real_index, &index)?;
+        }
+        // Oversized new or modified files keep whatever the index already had.
+        let changed = git(dir)
+            .index(&index)
+            .args(["ls-files", "-z", "-o", "-m", "--exclude-standard"])
+            .run()?;
+        let oversized = changed
+            .split('\0')
+            .filter(|rel| !rel.is_empty() && self.is_oversized(&dir.join(rel)))
+            .map(|rel| format!(":(exclude,literal){rel}"));
+        git(dir)
+            .index(&index)
+            .args(["add", "-A", "--", "."])
+            .args(oversized)
+            .run()?;
+        let tree = git(dir).index(&index).args(["write-tree"]).run()?;
+        let mut commit = git(dir);
+        commit.args(["commit-tree", tree.trim(), "-m", "microclaw checkpoint"]);
+        if let Ok(head) = git(dir).args(["rev-parse", "--verify", "-q", "HEAD"]).run() {
+            commit.args(["-p", head.trim()]);
+        }
+        let commit = commit.run()?.trim().to_string();
+        git(dir)
+            .args(["update-ref", &checkpoint_ref(&commit), &commit])
+            .run()?;
+        Ok(commit)
+    }
+
+    fn restore_git(
+        &self,
+        commit: &str,
+        source_dir: &Path,
+        target_dir: &Path,
+    ) -> Result<RestoreSummary> {
+        if !commit.bytes().all(|b| b.is_ascii_hexdigit()) {
+            bail!("invalid checkpoint reference '{commit}'");
+        }
+        if !is_git_work_tree(source_dir) {
+            bail!(
+                "'{}' is no longer a git repository; checkpoint {commit} is gone",
+                source_dir.display()
+            );
+        }
+        let git_dir = git(source_dir)
+            .args(["rev-parse", "--absolute-git-dir"])
+            .run()?;
+        let git_dir = PathBuf::from(git_dir.trim());
+        let index = self.scratch_index()?;
+        let _cleanup = RemoveOnDrop(index.clone());
+        let in_target = || {
+            let mut cmd = git_with_config_of(target_dir, source_dir);
+            cmd.env("GIT_DIR", &git_dir)
+                .env("GIT_WORK_TREE", target_dir);
+            cmd.index(&index);
+            cmd
+        };
+
+        let wanted: HashSet<String> = in_target()
+            .args(["ls-tree", "-r", "-z", "--name-only", commit])
+            .run()?
+            .split('\0')
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect();
+        // With the scratch index still empty, `-o` lists every file that is not ignored.
+        let present = in_target()
+            .args(["ls-files", "-z", "-o", "--exclude-standard"])
+            .run()?;
+        let mut summary = RestoreSummary::default();
+        for rel in present.split('\0').filter(|p| !p.is_empty()) {
+            let path = target_dir.join(rel);
+            if !wanted.contains(rel) && !self.is_oversized(&path) {
+                std::fs::remove_file(&path).with_context(|| format!("failed to remove '{rel}'"))?;
+                summary.removed += 1;
+            }
+        }
+        in_target().args(["read-tree", commit]).run()?;
+        // Like store snapshots, files over `max_file_bytes` are left untouched.
+        let mut paths = Vec::new();
+        for rel in &wanted {
+            if !self.is_oversized(&target_dir.join(rel)) {
+                paths.extend_from_slice(rel.as_bytes());
+                paths.push(0);
+                summary.written += 1;
+            }
+        }
+        in_target()
+            .args(["checkout-index", "-f", "-z", "--stdin"])
+            .run_with_input(&paths)?;
+        Ok(summary)
+    }
+}
+
+fn checkpoint_ref(commit: &str) -> String {
+    format!("refs/microclaw/checkpoints/{commit}")
+}
+
+/// Only the top of a work tree counts, so a repo cloned into a subdirectory does not
+/// switch the whole working dir to git snapshots.
+fn is_git_work_tree(dir: &Path) -> bool {
+    dir.join(".git").exists()
+        && git(dir)
+            .args(["rev-parse", "--show-prefix"])
+            .run()
+            .is_ok_and(|prefix| prefix.trim().is_empty())
+}
+
+struct GitCommand(Command);
+
+/// `git -C dir` that runs nothing the repository can configure: no hooks, no
+/// fsmonitor, no system or global config, no attributes file and every filter driver
+/// defined in the repository's config replaced by a no-op.
+fn git(dir: &Path) -> GitCommand {
+    git_with_config_of(dir, dir)
+}
+
+/// Like [`git`], with filter drivers read from the repository at `repo` (for commands
+/// that point `GIT_DIR` at another directory's repository).
+fn git_with_config_of(dir: &Path, repo: &Path) -> GitCommand {
+    let mut cmd = base_git(dir);
+    cmd.args([
+        "-c",
+        "core.fsmonitor=",
+        "-c",
+        "core.hooksPath=/dev/null",
+        "-c",
+        "core.attributesFile=/dev/null",
+    ]);
+    for driver in filter_drivers(repo) {
+        for key in ["clean", "smudge", "process"] {
+            cmd.arg("-c").arg(format!("filter.{driver}.{key}="));
+        }
+        cmd.arg("-c").arg(format!("filter.{driver}.required=false"));
+    }
+    cmd.arg("-C").arg(dir);
+    git_identity(&mut cmd);
+    GitCommand(cmd)
+}
+
+fn base_git(dir: &Path) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_ATTR_NOSYSTEM", "1")
+        .env_remove("GIT_CONFIG_PARAMETERS")
+        .env_remove("GIT_CONFIG_COUNT")
+        .current_dir(dir);
+    cmd
+}
+
+/// Names of filter drivers the repository config defines. Reading config runs nothing.
+fn filter_drivers(dir: &Path) -> Vec<String> {
+    let Ok(output) = base_git(dir)
+        .arg("-C")
+        .arg(dir)
+        .args(["config", "-z", "--name-only", "--get-regexp", r"^filter\."])
+        .output()
+    else {
+        return Vec::new();
+    };
+    let mut drivers: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter_map(|key| key.strip_prefix("filter.")?.rsplit_once('.'))
+        .map(|(driver, _)| driver.to_string())
+        .collect();
+    drivers.sort();
+    drivers.dedup();
+    drivers
+}
+
+fn git_identity(cmd: &mut Command) {
+    cmd.env("GIT_AUTHOR_NAME", "microclaw")
+        .env("GIT_AUTHOR_EMAIL", "microclaw@localhost")
+        .env("GIT_COMMITTER_NAME", "microclaw")
+        .env("GIT_COMMITTER_EMAIL", "microclaw@localhost")
+        .env_remove("GIT_INDEX_FILE");
+}
+
+impl GitCommand {
+    fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        self.0.args(args);
+        self
+    }
+
+    fn env(&mut self, key: &str, value: &Path) -> &mut Self {
+        self.0.env(key, value);
+        self
+    }
+
+    fn index(&mut self, index: &Path) -> &mut Self {
+        self.env("GIT_INDEX_FILE", index)
+    }
+
+    fn run(&mut self) -> Result<String> {
+        let output = self.0.output().context("failed to run git")?;
+        Self::finish(output)
+    }
+
+    fn run_with_input(&mut self, input: &[u8]) -> Result<String> {
+        let mut child = self
+            .0
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .context("failed to run git")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input)?;
+        }
+        Self::finish(child.wait_with_output()?)
+    }
+
+    fn finish(output: std::process::Output) -> Result<String> {
+        if !output.status.success() {
+            return Err(anyhow!(
+                "git failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+struct RemoveOnDrop(PathBuf);
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn list_dir(dir: &Path) -> Vec<(String, PathBuf)> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| (e.file_name().to_string_lossy().into_owned(), e.path()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn unique_suffix() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default()
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent)?;
+    let tmp = parent.join(format!(".tmp-{}-{}", std::process::id(), unique_suffix()));
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(data)?;
+    drop(file);
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn mtime_ns(meta: &std::fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(unix)]
+fn file_mode(meta: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(_meta: &std::fs::Metadata) -> u32 {
+    0o644
+}
+
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+    let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode));
+}
+
+#[cfg(not(unix))]
+fn set_file_mode(_path: &Path, _mode: u32) {}
+
+#[cfg(unix)]
+fn make_symlink(target: &Path, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, link)
+        .with_context(|| format!("failed to create symlink '{}'", link.display()))
+}
+
+#[cfg(windows)]
+fn make_symlink(target: &Path, link: &Path) -> Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+        .with_context(|| format!("failed to create symlink '{}'", link.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mc_ckpt_{label}_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_store_snapshot_restores_edits_deletions_and_new_files() {
+        let data = temp_dir("data");
+        let work = temp_dir("work");
+        std::fs::create_dir_all(work.join("src")).unwrap();
+        std::fs::write(work.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(work.join("notes.txt"), "keep me").unwrap();
+        std::fs::create_dir_all(work.join("node_modules/pkg")).unwrap();
+        std::fs::write(work.join("node_modules/pkg/index.js"), "dep").unwrap();
+
+        let store = CheckpointStore::new(&data, CheckpointConfig::default());
+        let snap = store.snapshot(&work, None).unwrap();
+        assert_eq!(snap.kind, CheckpointKind::Store);
+        // Same content with a warm stat cache yields the same manifest.
+        assert_eq!(store.snapshot(&work, Some(&snap)).unwrap(), snap);
+
+        std::fs::write(work.join("src/main.rs"), "broken").unwrap();
+        std::fs::remove_file(work.join("notes.txt")).unwrap();
+        std::fs::create_dir_all(work.join("junk/deep")).unwrap();
+        std::fs::write(work.join("junk/deep/x.txt"), "x").unwrap();
+        std::fs::write(work.join("node_modules/pkg/index.js"), "dep v2").unwrap();
+
+        let summary = store.restore(&snap, &work, &work).unwrap();
+        assert_eq!(read(&work.join("src/main.rs")), "fn main() {}\n");
+        assert_eq!(read(&work.join("notes.txt")), "keep me");
+        assert!(!work.join("junk").exists());
+        // Excluded directories are neither captured nor touched.
+        assert_eq!(read(&work.join("node_modules/pkg/index.js")), "dep v2");
+        assert_eq!(summary.written, 2);
+        assert_eq!(summary.removed, 3);
+
+        let _ = std::fs::remove_dir_all(&data);
+        let _ = std::fs::remove_dir_all(&work);
+    }
+
+    #[test]
+    fn test_store_restores_into_another_dir_and_collects_garbage() {
+        let data = temp_dir("data");
+        let work = temp_dir("work");
+        let fork = temp_dir("fork");
+        std::fs::write(work.join("a.txt"), "one").unwrap();
+        let store = CheckpointStore::new(&data, CheckpointConfig::default());
+        let first = store.snapshot(&work, None).unwrap();
+        std::fs::write(work.join("a.txt"), "two").unwrap();
+        let second = store.snapshot(&work, Some(&first)).unwrap();
+        assert_ne!(first, second);
+
+        store.restore(&first, &work, &fork).unwrap();
+        assert_eq!(read(&fork.join("a.txt")), "one");
+
+        let live = HashSet::from([second.reference.clone()]);
+        // One manifest and the object only it referenced.
+        assert_eq!(store.collect_garbage(&live).unwrap(), 2);
+        assert!(store.restore(&first, &work, &fork).is_err());
+        std::fs::write(work.join("a.txt"), "three").unwrap();
+        store.restore(&second, &work, &work).unwrap();
+        assert_eq!(read(&work.join("a.txt")), "two");
+
+        let _ = std::fs::remove_dir_all(&data);
+        let _ = std::fs::remove_dir_all(&work);
+        let _ = std::fs::remove_dir_all(&fork);
+    }
+
+    #[test]
+    fn test_git_snapshot_leaves_head_and_index_alone() {
+        let data = temp_dir("data");
+        let work = temp_dir("repo");
+        if git(&work).args(["init", "-q"]).run().is_err() {
+            eprintln!("skipping: git unavailable");
+            return;
+        }
+        std::fs::write(work.join(".gitignore"), "build/\n").unwrap();
+        std::fs::write(work.join("tracked.txt"), "v1").unwrap();
+        git(&work).args(["add", "-A"]).run().unwrap();
+        git(&work)
+            .args(["commit", "-q", "-m", "init"])
+            .run()
+            .unwrap();
+        std::fs::write(work.join("untracked.txt"), "draft").unwrap();
+        std::fs::create_dir_all(work.join("build")).unwrap();
+        std::fs::write(work.join("build/out.bin"), "artifact").unwrap();
+        let head = git(&work).args(["rev-parse", "HEAD"]).run().unwrap();
+        let status = git(&work).args(["status", "--porcelain"]).run().unwrap();
+
+        let store = CheckpointStore::new(&data, CheckpointConfig::default());
+        let snap = store.snapshot(&work, None).unwrap();
+        assert_eq!(snap.kind, CheckpointKind::Git);
+        assert_eq!(git(&work).args(["rev-parse", "HEAD"]).run().unwrap(), head);
+        assert_eq!(
+            git(&work).args(["status", "--porcelain"]).run().unwrap(),
+            status
+        );
+
+        std::fs::write(work.join("tracked.txt"), "v2").unwrap();
+        std::fs::remove_file(work.join("untracked.txt")).unwrap();
+        std::fs::write(work.join("new.txt"), "agent output").unwrap();
+        std::fs::write(work.join("build/out.bin"), "rebuilt").unwrap();
+
+        let summary = store.restore(&snap, &work, &work).unwrap();
+        assert_eq!(summary.removed, 1);
+        assert_eq!(read(&work.join("tracked.txt")), "v1");
+        assert_eq!(read(&work.join("untracked.txt")), "draft");
+        assert!(!work.join("new.txt").exists());
+        assert_eq!(read(&work.join("build/out.bin")), "rebuilt");
+        assert_eq!(
+            git(&work).args(["status", "--porcelain"]).run().unwrap(),
+            status
+        );
+
+        let fork = temp_dir("fork");
+        store.restore(&snap, &work, &fork).unwrap();
+        assert_eq!(read(&fork.join("tracked.txt")), "v1");
+        assert_eq!(read(&fork.join("untracked.txt")), "draft");
+
+        store.discard(&snap, &work).unwrap();
+        assert!(git(&work)
+            .args([
+                "rev-parse",
+                "--verify",
+                "-q",
+                &checkpoint_ref(&snap.reference)
+            ])
+            .run()
+            .is_err());
+
+        let _ = std::fs::remove_dir_all(&data);
+        let _ = std::fs::remove_dir_all(&work);
+        let _ = std::fs::remove_dir_all(&fork);
+    }
+
+    #[test]
+    fn test_git_snapshot_ignores_repo_config_and_caps_file_size() {
+        let data = temp_dir("data");
+        let work = temp_dir("repo");
+        if git(&work).args(["init", "-q"]).run().is_err() {
+            eprintln!("skipping: git unavailable");
+            return;
+        }
+        let marker = work.join("pwned");
+        let hook = format!("touch '{}'", marker.display());
+        for (key, value) in [
+            ("filter.evil.clean", hook.as_str()),
+            ("filter.evil.process", hook.as_str()),
+            ("filter.evil.required", "true"),
+            ("core.fsmonitor", hook.as_str()),
+        ] {
+            git(&work).args(["config", key, value]).run().unwrap();
+        }
+        std::fs::write(work.join(".gitignore"), "pwned\n").unwrap();
+        std::fs::write(work.join(".gitattributes"), "* filter=evil\n").unwrap();
+        std::fs::write(work.join("small.txt"), "v1").unwrap();
+        std::fs::write(work.join("big.bin"), "0123456789").unwrap();
+
+        let config = CheckpointConfig {
+            max_file_bytes: 4,
+            ..CheckpointConfig::default()
+        };
+        let store = CheckpointStore::new(&data, config.clone());
+        let snap = store.snapshot(&work, None).unwrap();
+        assert_eq!(snap.kind, CheckpointKind::Git);
+        let files = git(&work)
+            .args(["ls-tree", "-r", "--name-only", &snap.reference])
+            .run()
+            .unwrap();
+        assert!(files.contains("small.txt"));
+        assert!(!files.contains("big.bin"));
+
+        std::fs::write(work.join("small.txt"), "v2").unwrap();
+        std::fs::write(work.join("big.bin"), "rebuilt-big").unwrap();
+        store.restore(&snap, &work, &work).unwrap();
+        assert_eq!(read(&work.join("small.txt")), "v1");
+        assert_eq!(read(&work.join("big.bin")), "rebuilt-big");
+        assert!(!marker.exists(), "repository config ran a command");
+
+        let sandboxed = CheckpointStore::new(&data, config).with_git_snapshots(false);
+        assert_eq!(
+            sandboxed.snapshot(&work, None).unwrap().kind,
+            CheckpointKind::Store
+        );
+
+        let _ = std::fs::remove_dir_all(&data);
+        let _ = std::fs::remove_dir_all(&work);
+    }
+}
@@ -0,0 +1,174 @@
+//! A small JSONPath subset for narrowing fetched JSON documents.
+//!
+//! Supported syntax: `$` (root, optional), `.key`, `['key']` / `["key"]`,
+//! `[n]` (negative counts from the end), `[*]` / `.*` and `..key` / `..*`
+//! (recursive descent). Filters, slices and unions are not supported.
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Key(String),
+    Index(i64),
+    Wildcard,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    recursive: bool,
+    selector: Selector,
+}
+
+fn parse(path: &str) -> Result<Vec<Segment>, String> {
+    let chars: Vec<char> = path.trim().chars().collect();
+    let mut pos = 0;
+    if chars.first() == Some(&'$') {
+        pos = 1;
+    }
+    let mut segments = Vec::new();
+    while pos < chars.len() {
+        let mut recursive = false;
+        match chars[pos] {
+            '.' if chars.get(pos + 1) == Some(&'.') => {
+                recursive = true;
+                pos += 2;
+            }
+            '.' => pos += 1,
+            '[' => {}
+            // A bare leading key, as in `data.items`.
+            _ if segments.is_empty() && pos == 0 => {}
+            c => return Err(format!("unexpected '{c}' at position {pos}")),
+        }
+        let selector = if chars.get(pos) == Some(&'[') {
+            let close = chars[pos..]
+                .iter()
+                .position(|c| *c == ']')
+                .map(|i| pos + i)
+                .ok_or_else(|| "unclosed '['".to_string())?;
+            let inner: String = chars[pos + 1..close].iter().collect();
+            pos = close + 1;
+            let inner = inner.trim();
+            if inner == "*" {
+                Selector::Wildcard
+            } else if let Some(key) = inner
+                .strip_prefix('\'')
+                .and_then(|s| s.strip_suffix('\''))
+                .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')))
+            {
+                Selector::Key(key.to_string())
+            } else {
+                Selector::Index(
+                    inner
+                        .parse()
+                        .map_err(|_| format!("unsupported bracket selector '[{inner}]'"))?,
+                )
+            }
+        } else {
+            let start = pos;
+            while pos < chars.len() && chars[pos] != '.' && chars[pos] != '[' {
+                pos += 1;
+            }
+            let name: String = chars[start..pos].iter().collect();
+            match name.as_str() {
+                "" => return Err(format!("missing key at position {start}")),
+                "*" => Selector::Wildcard,
+                _ => Selector::Key(name),
+            }
+        };
+        segments.push(Segment {
+            recursive,
+            selector,
+        });
+    }
+    Ok(segments)
+}
+
+fn descendants<'a>(value: &'a Value, out: &mut Vec<&'a Value>) {
+    out.push(value);
+    match value {
+        Value::Array(items) => items.iter().for_each(|v| descendants(v, out)),
+        Value::Object(map) => map.values().for_each(|v| descendants(v, out)),
+        _ => {}
+    }
+}
+
+fn apply<'a>(value: &'a Value, selector: &Selector, out: &mut Vec<&'a Value>) {
+    match (selector, value) {
+        (Selector::Key(key), Value::Object(map)) => out.extend(map.get(key)),
+        (Selector::Index(i), Value::Array(items)) => {
+            let idx = if *i < 0 {
+                items.len().checked_sub(i.unsigned_abs() as usize)
+            } else {
+                Some(*i as usize)
+            };
+            out.extend(idx.and_then(|idx| items.get(idx)));
+        }
+        (Selector::Wildcard, Value::Array(items)) => out.extend(items.iter()),
+        (Selector::Wildcard, Value::Object(map)) => out.extend(map.values()),
+        _ => {}
+    }
+}
+
+/// Every value in `root` matched by `path`, in document order.
+pub fn select<'a>(root: &'a Value, path: &str) -> Result<Vec<&'a Value>, String> {
+    let segments = parse(path).map_err(|e| format!("invalid JSONPath '{path}': {e}"))?;
+    let mut current = vec![root];
+    for segment in &segments {
+        let mut scope = Vec::new();
+        if segment.recursive {
+            current.iter().for_each(|v| descendants(v, &mut scope));
+        } else {
+            scope = current;
+        }
+        let mut next = Vec::new();
+        for value in scope {
+            apply(value, &segment.selector, &mut next);
+        }
+        current = next;
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::select;
+
+    #[test]
+    fn selects_keys_indexes_and_wildcards() {
+        let doc = json!({
+            "data": {"items": [{"id": 1, "name": "a"}, {"id": 2, "name": "b"}]},
+            "odd key": true
+        });
+        assert_eq!(
+            select(&doc, "$.data.items[0].name").unwrap(),
+            vec![&json!("a")]
+        );
+        assert_eq!(select(&doc, "data.items[-1].id").unwrap(), vec![&json!(2)]);
+        assert_eq!(
+            select(&doc, "$.data.items[*].id").unwrap(),
+            vec![&json!(1), &json!(2)]
+        );
+        assert_eq!(select(&doc, "$['odd key']").unwrap(), vec![&json!(true)]);
+        assert_eq!(select(&doc, "$").unwrap(), vec![&doc]);
+        assert!(select(&doc, "$.missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn recursive_descent_finds_nested_keys() {
+        let doc = json!({"a": {"name": "x", "b": [{"name": "y"}]}});
+        assert_eq!(
+            select(&doc, "$..name").unwrap(),
+            vec![&json!("x"), &json!("y")]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_syntax() {
+        let doc = json!({});
+        assert!(select(&doc, "$.items[?(@.id)]").is_err());
+        assert!(select(&doc, "$.items[0").is_err());
+        assert!(select(&doc, "$.").is_err());
+    }
+}
@@ -1,10 +1,18 @@
 //! Tool runtime and built-in tool implementations for MicroClaw.
 
+pub mod checkpoint_store;
 pub mod command_runner;
 pub mod env_file;
+pub mod json_path;
+pub mod patch;
 pub mod path_guard;
+pub mod pdf_text;
 pub mod runtime;
 pub mod sandbox;
+pub mod sandbox_egress;
+pub mod sandbox_fs;
+pub mod sandbox_native;
+pub mod shell_session;
 pub mod todo_store;
 pub mod types;
 pub mod web_content_validation;
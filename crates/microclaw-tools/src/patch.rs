@@ -0,0 +1,659 @@
+//! Multi-file patches for the `apply_patch` tool.
+//!
+//! Two input formats are accepted: ordinary unified diffs (`diff -u` / `git diff`
+//! output, several files per patch) and an envelope format that needs no line numbers:
+//!
+//! ```text
+//! *** Begin Patch
+//! *** Update File: src/lib.rs
+//! @@ fn main
+//!  context
+//! -old line
+//! +new line
+//! *** Add File: notes.txt
+//! +hello
+//! *** Delete File: old.txt
+//! *** End Patch
+//! ```
+//!
+//! Hunks are matched against the current file content (line numbers are only a hint),
+//! and every file is planned in memory before anything is written, so a patch that
+//! does not fit leaves the tree untouched.
+
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Hunk {
+    /// 0-based index in the original file where the hunk's old lines start, from a
+    /// unified `@@ -l,n` header.
+    pub position_hint: Option<usize>,
+    /// Envelope `@@ <line>` header: the hunk starts after the next line equal to this.
+    pub anchor: Option<String>,
+    /// Envelope `*** End of File`: the hunk's old lines end the file.
+    pub at_eof: bool,
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Remove(s) => Some(s.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect()
+    }
+
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Add(s) => Some(s.as_str()),
+                HunkLine::Remove(_) => None,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChange {
+    Add {
+        content: String,
+    },
+    Delete,
+    Update {
+        hunks: Vec<Hunk>,
+        move_to: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+    pub path: String,
+    pub change: FileChange,
+}
+
+/// Parse a unified diff or an envelope patch, optionally wrapped in a Markdown fence.
+pub fn parse_patch(text: &str) -> Result<Vec<FilePatch>, String> {
+    let text = strip_fence(text);
+    let patches = if text.trim_start().starts_with("*** Begin Patch") {
+        parse_envelope(text)?
+    } else {
+        parse_unified(text)?
+    };
+    if patches.is_empty() {
+        return Err("patch contains no file changes".into());
+    }
+    let mut seen = std::collections::HashSet::new();
+    for p in &patches {
+        if !seen.insert(p.path.as_str()) {
+            return Err(format!("patch changes '{}' more than once", p.path));
+        }
+    }
+    Ok(patches)
+}
+
+fn strip_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return text;
+    };
+    let Some(body_start) = rest.find('\n') else {
+        return text;
+    };
+    rest[body_start + 1..]
+        .trim_end()
+        .strip_suffix("```")
+        .unwrap_or(&rest[body_start + 1..])
+}
+
+fn parse_envelope(text: &str) -> Result<Vec<FilePatch>, String> {
+    let mut lines = text.trim().lines().peekable();
+    lines.next(); // *** Begin Patch
+    let mut patches = Vec::new();
+    let mut ended = false;
+    while let Some(line) = lines.next() {
+        if line.trim() == "*** End Patch" {
+            ended = true;
+            break;
+        }
+        if let Some(path) = line.strip_prefix("*** Add File: ") {
+            let mut content = String::new();
+            while let Some(next) = lines.peek() {
+                if next.starts_with("*** ") {
+                    break;
+                }
+                let body = next.strip_prefix('+').ok_or_else(|| {
+                    format!("line '{next}' in added file '{path}' must start with '+'")
+                })?;
+                content.push_str(body);
+                content.push('\n');
+                lines.next();
+            }
+            patches.push(FilePatch {
+                path: path.trim().to_string(),
+                change: FileChange::Add { content },
+            });
+        } else if let Some(path) = line.strip_prefix("*** Delete File: ") {
+            patches.push(FilePatch {
+                path: path.trim().to_string(),
+                change: FileChange::Delete,
+            });
+        } else if let Some(path) = line.strip_prefix("*** Update File: ") {
+            let path = path.trim().to_string();
+            let mut move_to = None;
+            if let Some(target) = lines.peek().and_then(|l| l.strip_prefix("*** Move to: ")) {
+                move_to = Some(target.trim().to_string());
+                lines.next();
+            }
+            let mut hunks: Vec<Hunk> = Vec::new();
+            while let Some(next) = lines.peek().copied() {
+                if next == "*** End of File" {
+                    hunks
+                        .last_mut()
+                        .ok_or_else(|| format!("'*** End of File' before any hunk in '{path}'"))?
+                        .at_eof = true;
+                    lines.next();
+                    continue;
+                }
+                if next.starts_with("*** ") {
+                    break;
+                }
+                lines.next();
+                if let Some(header) = next.strip_prefix("@@") {
+                    let anchor = header.trim();
+                    hunks.push(Hunk {
+                        anchor: (!anchor.is_empty()).then(|| anchor.to_string()),
+                        ..Hunk::default()
+                    });
+                    continue;
+                }
+                if hunks.is_empty() {
+                    hunks.push(Hunk::default());
+                }
+                let hunk = hunks.last_mut().expect("hunk pushed above");
+                hunk.lines
+                    .push(parse_hunk_line(next).ok_or_else(|| {
+                        format!("unexpected line '{next}' in update of '{path}'")
+                    })?);
+            }
+            hunks.retain(|h| !h.lines.is_empty());
+            if hunks.is_empty() && move_to.is_none() {
+                return Err(format!("update of '{path}' has no hunks"));
+            }
+            patches.push(FilePatch {
+                path,
+                change: FileChange::Update { hunks, move_to },
+            });
+        } else if !line.trim().is_empty() {
+            return Err(format!("unexpected line '{line}' in patch envelope"));
+        }
+    }
+    if !ended {
+        return Err("patch envelope is missing '*** End Patch'".into());
+    }
+    Ok(patches)
+}
+
+fn parse_hunk_line(line: &str) -> Option<HunkLine> {
+    match line.chars().next() {
+        Some(' ') => Some(HunkLine::Context(line[1..].to_string())),
+        Some('-') => Some(HunkLine::Remove(line[1..].to_string())),
+        Some('+') => Some(HunkLine::Add(line[1..].to_string())),
+        // Editors and models often drop the single space of an empty context line.
+        None => Some(HunkLine::Context(String::new())),
+        _ => None,
+    }
+}
+
+fn parse_unified(text: &str) -> Result<Vec<FilePatch>, String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut patches = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.starts_with("diff --git ") {
+            // `rename from` / `rename to` of this block.
+            let mut rename: (Option<String>, Option<String>) = (None, None);
+            // A pure rename has no ---/+++ lines.
+            let (from, to) = git_header_paths(line);
+            let has_body = lines[i + 1..]
+                .iter()
+                .take_while(|l| !l.starts_with("diff --git "))
+                .any(|l| l.starts_with("--- "));
+            i += 1;
+            while i < lines.len() && !lines[i].starts_with("--- ") {
+                if lines[i].starts_with("diff --git ") {
+                    break;
+                }
+                if let Some(p) = lines[i].strip_prefix("rename from ") {
+                    rename.0 = Some(p.to_string());
+                } else if let Some(p) = lines[i].strip_prefix("rename to ") {
+                    rename.1 = Some(p.to_string());
+                }
+                i += 1;
+            }
+            if !has_body {
+                if let (Some(from), Some(to)) = (rename.0.take().or(from), rename.1.take().or(to)) {
+                    if from != to {
+                        patches.push(FilePatch {
+                            path: from,
+                            change: FileChange::Update {
+                                hunks: Vec::new(),
+                                move_to: Some(to),
+                            },
+                        });
+                    }
+                }
+            }
+            continue;
+        }
+        let Some(old_raw) = line.strip_prefix("--- ") else {
+            i += 1;
+            continue;
+        };
+        let new_raw = lines
+            .get(i + 1)
+            .and_then(|l| l.strip_prefix("+++ "))
+            .ok_or_else(|| format!("'--- {old_raw}' is not followed by a '+++' line"))?;
+        let old_path = diff_path(old_raw);
+        let new_path = diff_path(new_raw);
+        i += 2;
+
+        let mut hunks = Vec::new();
+        while i < lines.len() && lines[i].starts_with("@@") {
+            let (old_start, old_count, new_count) = parse_range_header(lines[i])?;
+            i += 1;
+            let mut hunk = Hunk {
+                position_hint: Some(if old_count == 0 {
+                    old_start
+                } else {
+                    old_start.saturating_sub(1)
+                }),
+                ..Hunk::default()
+            };
+            let (mut old_seen, mut new_seen) = (0, 0);
+            while old_seen < old_count || new_seen < new_count {
+                let Some(raw) = lines.get(i) else {
+                    return Err(format!("hunk for '{new_raw}' ends early"));
+                };
+                i += 1;
+                if raw.starts_with('\\') {
+                    continue;
+                }
+                let parsed = parse_hunk_line(raw)
+                    .ok_or_else(|| format!("unexpected line '{raw}' in hunk for '{new_raw}'"))?;
+                match &parsed {
+                    HunkLine::Context(_) => {
+                        old_seen += 1;
+                        new_seen += 1;
+                    }
+                    HunkLine::Remove(_) => old_seen += 1,
+                    HunkLine::Add(_) => new_seen += 1,
+                }
+                hunk.lines.push(parsed);
+            }
+            while lines.get(i).is_some_and(|l| l.starts_with('\\')) {
+                i += 1;
+            }
+            hunks.push(hunk);
+        }
+
+        let patch = match (old_path, new_path) {
+            (None, None) => return Err("diff with /dev/null on both sides".into()),
+            (None, Some(path)) => FilePatch {
+                path,
+                change: FileChange::Add {
+                    content: added_content(&hunks),
+                },
+            },
+            (Some(path), None) => FilePatch {
+                path,
+                change: FileChange::Delete,
+            },
+            (Some(old), Some(new)) => {
+                let move_to = (old != new).then_some(new);
+                if hunks.is_empty() && move_to.is_none() {
+                    return Err(format!("diff for '{old}' has no hunks"));
+                }
+                FilePatch {
+                    path: old,
+                    change: FileChange::Update { hunks, move_to },
+                }
+            }
+        };
+        patches.push(patch);
+    }
+    Ok(patches)
+}
+
+fn added_content(hunks: &[Hunk]) -> String {
+    let mut content = String::new();
+    for line in hunks.iter().flat_map(|h| h.new_lines()) {
+        content.push_str(line);
+        content.push('\n');
+    }
+    content
+}
+
+/// `a/x` and `b/x` from `diff --git a/x b/x` when the split is unambiguous.
+fn git_header_paths(line: &str) -> (Option<String>, Option<String>) {
+    let rest = line.trim_start_matches("diff --git ");
+    let parts: Vec<&str> = rest.split(' ').collect();
+    if parts.len() != 2 {
+        return (None, None);
+    }
+    (diff_path(parts[0]), diff_path(parts[1]))
+}
+
+/// The path of a `---`/`+++` line: `None` for `/dev/null`, `a/`/`b/` prefixes and
+/// trailing timestamps removed.
+fn diff_path(raw: &str) -> Option<String> {
+    let path = raw.split('\t').next().unwrap_or(raw).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// `@@ -l[,n] +l[,n] @@` into (old start, old count, new count).
+fn parse_range_header(line: &str) -> Result<(usize, usize, usize), String> {
+    let bad = || format!("malformed hunk header '{line}'");
+    let inner = line
+        .strip_prefix("@@ ")
+        .and_then(|rest| rest.split(" @@").next())
+        .ok_or_else(bad)?;
+    let mut parts = inner.split_whitespace();
+    let old = parts
+        .next()
+        .and_then(|p| p.strip_prefix('-'))
+        .ok_or_else(bad)?;
+    let new = parts
+        .next()
+        .and_then(|p| p.strip_prefix('+'))
+        .ok_or_else(bad)?;
+    let range = |r: &str| -> Option<(usize, usize)> {
+        match r.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((r.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_count) = range(old).ok_or_else(bad)?;
+    let (_, new_count) = range(new).ok_or_else(bad)?;
+    Ok((old_start, old_count, new_count))
+}
+
+/// A file's content after its hunks were applied, with a unified diff of the change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedHunks {
+    pub content: String,
+    /// `@@` sections of the unified diff, without the `---`/`+++` header.
+    pub diff: String,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Apply `hunks` to `original`. Fails naming the first hunk that does not match.
+pub fn apply_hunks(original: &str, hunks: &[Hunk]) -> Result<AppliedHunks, String> {
+    let trailing_newline = original.is_empty() || original.ends_with('\n');
+    let src: Vec<&str> = if original.is_empty() {
+        Vec::new()
+    } else {
+        original
+            .strip_suffix('\n')
+            .unwrap_or(original)
+            .split('\n')
+            .collect()
+    };
+    let mut out: Vec<String> = Vec::with_capacity(src.len());
+    let mut diff = String::new();
+    let (mut added, mut removed) = (0, 0);
+    let mut cursor = 0;
+    for (n, hunk) in hunks.iter().enumerate() {
+        let mut from = cursor;
+        if let Some(anchor) = &hunk.anchor {
+            if let Some(found) = (cursor..src.len()).find(|&i| src[i].trim() == anchor.trim()) {
+                from = found + 1;
+            }
+        }
+        let old = hunk.old_lines();
+        let at = locate(&src, &old, from, hunk)
+            .or_else(|| {
+                hunk.anchor
+                    .as_ref()
+                    .and_then(|_| locate(&src, &old, cursor, hunk))
+            })
+            .ok_or_else(|| {
+                let preview: Vec<&str> = old.iter().take(3).copied().collect();
+                format!(
+                    "hunk {} does not match the current file (expected lines starting with {:?})",
+                    n + 1,
+                    preview
+                )
+            })?;
+        out.extend(src[cursor..at].iter().map(|s| s.to_string()));
+        let new_start = out.len();
+        let _ = writeln!(
+            diff,
+            "@@ -{} +{} @@",
+            range_label(at, old.len()),
+            range_label(new_start, hunk.new_lines().len())
+        );
+        // Keep the file's own text for context lines (they may differ in trailing space).
+        let mut src_line = at;
+        for line in &hunk.lines {
+            match line {
+                HunkLine::Context(_) => {
+                    let _ = writeln!(diff, " {}", src[src_line]);
+                    out.push(src[src_line].to_string());
+                    src_line += 1;
+                }
+                HunkLine::Remove(_) => {
+                    let _ = writeln!(diff, "-{}", src[src_line]);
+                    removed += 1;
+                    src_line += 1;
+                }
+                HunkLine::Add(text) => {
+                    let _ = writeln!(diff, "+{text}");
+                    out.push(text.clone());
+                    added += 1;
+                }
+            }
+        }
+        cursor = at + old.len();
+    }
+    out.extend(src[cursor..].iter().map(|s| s.to_string()));
+    let mut content = out.join("\n");
+    if !out.is_empty() && trailing_newline {
+        content.push('\n');
+    }
+    Ok(AppliedHunks {
+        content,
+        diff,
+        added,
+        removed,
+    })
+}
+
+/// Where `old` occurs in `src` at or after `from`: exactly if possible, else ignoring
+/// trailing whitespace. With a position hint the closest match wins, otherwise the first.
+fn locate(src: &[&str], old: &[&str], from: usize, hunk: &Hunk) -> Option<usize> {
+    if old.is_empty() {
+        return Some(if hunk.at_eof {
+            src.len()
+        } else {
+            hunk.position_hint
+                .unwrap_or(src.len())
+                .clamp(from, src.len())
+        });
+    }
+    if old.len() > src.len() {
+        return None;
+    }
+    let last = src.len() - old.len();
+    let matches_at = |i: usize, loose: bool| {
+        old.iter().enumerate().all(|(k, line)| {
+            if loose {
+                src[i + k].trim_end() == line.trim_end()
+            } else {
+                src[i + k] == *line
+            }
+        })
+    };
+    for loose in [false, true] {
+        if hunk.at_eof {
+            if last >= from && matches_at(last, loose) {
+                return Some(last);
+            }
+            continue;
+        }
+        let candidates = (from..=last).filter(|&i| matches_at(i, loose));
+        let found = match hunk.position_hint {
+            Some(hint) => candidates.min_by_key(|&i| i.abs_diff(hint)),
+            None => candidates.into_iter().next(),
+        };
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+fn range_label(start: usize, count: usize) -> String {
+    // Unified diffs number from 1, and an empty range names the line before it.
+    let first = if count == 0 { start } else { start + 1 };
+    if count == 1 {
+        first.to_string()
+    } else {
+        format!("{first},{count}")
+    }
+}
+
+/// Unified diff of a whole file being added (`old` empty) or deleted (`new` empty).
+pub fn whole_file_diff(content: &str, added: bool) -> (String, usize) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut diff = if added {
+        format!("@@ -0,0 +{} @@\n", range_label(0, lines.len()))
+    } else {
+        format!("@@ -{} +0,0 @@\n", range_label(0, lines.len()))
+    };
+    let sign = if added { '+' } else { '-' };
+    for line in &lines {
+        let _ = writeln!(diff, "{sign}{line}");
+    }
+    (diff, lines.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_multi_file_parse_and_apply() {
+        let patch = "\
+diff --git a/src/a.txt b/src/a.txt
+index 111..222 100644
+--- a/src/a.txt
++++ b/src/a.txt
+@@ -1,3 +1,3 @@
+ one
+-two
++TWO
+ three
+@@ -6,2 +6,3 @@ fn tail
+ six
+ seven
++eight
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1,2 @@
++hello
++world
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+        let patches = parse_patch(patch).unwrap();
+        assert_eq!(patches.len(), 3);
+        assert_eq!(patches[0].path, "src/a.txt");
+        assert_eq!(
+            patches[1].change,
+            FileChange::Add {
+                content: "hello\nworld\n".into()
+            }
+        );
+        assert_eq!(patches[2].change, FileChange::Delete);
+
+        let FileChange::Update { hunks, move_to } = &patches[0].change else {
+            panic!("expected update");
+        };
+        assert!(move_to.is_none());
+        // Two extra lines at the top: hunks still land by content.
+        let original = "zero\nzero\none\ntwo\nthree\nfour\nfive\nsix\nseven\n";
+        let applied = apply_hunks(original, hunks).unwrap();
+        assert_eq!(
+            applied.content,
+            "zero\nzero\none\nTWO\nthree\nfour\nfive\nsix\nseven\neight\n"
+        );
+        assert_eq!((applied.added, applied.removed), (2, 1));
+        assert!(applied
+            .diff
+            .starts_with("@@ -3,3 +3,3 @@\n one\n-two\n+TWO\n"));
+    }
+
+    #[test]
+    fn test_envelope_update_move_add_delete() {
+        let patch = "```\n*** Begin Patch\n*** Update File: lib.rs\n*** Move to: core.rs\n@@ fn second\n-    b()\n+    c()\n*** Add File: README\n+# hi\n*** Delete File: old.rs\n*** End Patch\n```";
+        let patches = parse_patch(patch).unwrap();
+        assert_eq!(patches.len(), 3);
+        let FileChange::Update { hunks, move_to } = &patches[0].change else {
+            panic!("expected update");
+        };
+        assert_eq!(move_to.as_deref(), Some("core.rs"));
+        let original = "fn first\n    b()\nfn second\n    b()\n";
+        let applied = apply_hunks(original, hunks).unwrap();
+        assert_eq!(applied.content, "fn first\n    b()\nfn second\n    c()\n");
+    }
+
+    #[test]
+    fn test_mismatched_hunk_is_reported() {
+        let patch = "--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n keep\n-missing\n+new\n";
+        let patches = parse_patch(patch).unwrap();
+        let FileChange::Update { hunks, .. } = &patches[0].change else {
+            panic!("expected update");
+        };
+        let err = apply_hunks("keep\nother\n", hunks).unwrap_err();
+        assert!(err.contains("hunk 1 does not match"), "{err}");
+    }
+
+    #[test]
+    fn test_apply_preserves_missing_trailing_newline_and_loose_whitespace() {
+        let patch = "--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n\\ No newline at end of file\n";
+        let patches = parse_patch(patch).unwrap();
+        let FileChange::Update { hunks, .. } = &patches[0].change else {
+            panic!("expected update");
+        };
+        let applied = apply_hunks("a  \nb", hunks).unwrap();
+        assert_eq!(applied.content, "a  \nc");
+    }
+
+    #[test]
+    fn test_duplicate_file_and_empty_patch_are_rejected() {
+        assert!(parse_patch("nothing here").is_err());
+        let dup = "--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n--- a/x\n+++ b/x\n@@ -1 +1 @@\n-b\n+c\n";
+        assert!(parse_patch(dup).unwrap_err().contains("more than once"));
+    }
+}
@@ -1,5 +1,18 @@
+//! Path checks for the file tools.
+//!
+//! Every path goes through symlink validation, the optional allowlist file and a
+//! built-in list of credential locations. On top of that, `path_policy` in the config
+//! adds deny globs, read-only zones, write-only directories and size limits, for all
+//! chats and per chat. A refusal is a [`PathDenied`] naming the [`DenyReason`] and the
+//! rule that matched.
+
+use std::collections::HashMap;
+use std::fmt;
 use std::path::{Component, Path, PathBuf};
 
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
 /// Directory components that are always blocked.
 const BLOCKED_DIRS: &[&str] = &[".ssh", ".aws", ".gnupg", ".kube"];
 
@@ -39,27 +52,193 @@ fn default_path_allowlist_path() -> Option<std::path::PathBuf> {
     Some(home.join(".microclaw/sandbox-path-allowlist.txt"))
 }
 
+/// How a tool is about to use a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathAccess {
+    Read,
+    Write,
+    /// Read and rewrite in place, as `edit_file` does.
+    ReadWrite,
+}
+
+impl PathAccess {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PathAccess::Read => "read",
+            PathAccess::Write => "write",
+            PathAccess::ReadWrite => "read_write",
+        }
+    }
+
+    fn reads(self) -> bool {
+        matches!(self, PathAccess::Read | PathAccess::ReadWrite)
+    }
+
+    fn writes(self) -> bool {
+        matches!(self, PathAccess::Write | PathAccess::ReadWrite)
+    }
+}
+
+/// Why the path guard refused a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DenyReason {
+    /// A component of the path is a symlink, or it resolves somewhere else in the sandbox.
+    Symlink,
+    /// Outside the roots listed in the path allowlist file.
+    OutsideAllowlist,
+    /// On the built-in list of credential and key locations.
+    Sensitive,
+    /// Matches one of `path_policy` deny globs.
+    DenyGlob,
+    /// A write inside a read-only zone.
+    ReadOnly,
+    /// A read inside a write-only directory.
+    WriteOnly,
+    /// Over `max_read_bytes` or `max_write_bytes`.
+    TooLarge,
+}
+
+impl DenyReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DenyReason::Symlink => "symlink",
+            DenyReason::OutsideAllowlist => "outside_allowlist",
+            DenyReason::Sensitive => "sensitive_path",
+            DenyReason::DenyGlob => "deny_glob",
+            DenyReason::ReadOnly => "read_only",
+            DenyReason::WriteOnly => "write_only",
+            DenyReason::TooLarge => "too_large",
+        }
+    }
+
+    /// The `ToolResult::error_type` a tool reports for this denial.
+    pub fn error_type(self) -> &'static str {
+        match self {
+            DenyReason::Symlink => "path_symlink",
+            DenyReason::OutsideAllowlist => "path_outside_allowlist",
+            DenyReason::Sensitive => "path_sensitive",
+            DenyReason::DenyGlob => "path_deny_glob",
+            DenyReason::ReadOnly => "path_read_only",
+            DenyReason::WriteOnly => "path_write_only",
+            DenyReason::TooLarge => "path_too_large",
+        }
+    }
+}
+
+/// A path the guard refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathDenied {
+    pub path: String,
+    pub reason: DenyReason,
+    /// The blocked entry, glob, zone or limit that matched, when there is one.
+    pub rule: Option<String>,
+    pub message: String,
+}
+
+impl PathDenied {
+    fn new(path: &str, reason: DenyReason, rule: Option<String>, message: String) -> Self {
+        Self {
+            path: path.to_string(),
+            reason,
+            rule,
+            message,
+        }
+    }
+
+    fn sensitive(path: &str, entry: String) -> Self {
+        Self::new(
+            path,
+            DenyReason::Sensitive,
+            Some(entry),
+            format!("Access denied: '{path}' is a sensitive path and cannot be accessed."),
+        )
+    }
+}
+
+impl fmt::Display for PathDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for PathDenied {}
+
+impl From<PathDenied> for String {
+    fn from(denied: PathDenied) -> Self {
+        denied.message
+    }
+}
+
 /// Check if a path is blocked. Returns Err(message) if blocked.
 pub fn check_path(path: &str) -> Result<(), String> {
+    check_builtin(path).map(|_| ()).map_err(String::from)
+}
+
+/// Symlink, allowlist and built-in checks for a host path. Returns the resolved path.
+fn check_builtin(path: &str) -> Result<PathBuf, PathDenied> {
     let candidate = Path::new(path);
     if let Err(err) = validate_symlink_safety(candidate) {
-        return Err(format!(
-            "Access denied: '{path}' symlink validation failed: {err}"
+        return Err(PathDenied::new(
+            path,
+            DenyReason::Symlink,
+            None,
+            format!("Access denied: '{path}' symlink validation failed: {err}"),
         ));
     }
     if let Err(err) = validate_allowlist(candidate) {
-        return Err(format!("Access denied: {err}"));
+        return Err(PathDenied::new(
+            path,
+            DenyReason::OutsideAllowlist,
+            None,
+            format!("Access denied: {err}"),
+        ));
     }
-    if is_blocked(candidate) {
-        Err(format!(
-            "Access denied: '{}' is a sensitive path and cannot be accessed.",
-            path
-        ))
-    } else {
-        Ok(())
+    let resolved = resolve_host_path(candidate);
+    match blocked_entry(candidate, &resolved) {
+        Some(entry) => Err(PathDenied::sensitive(path, entry)),
+        None => Ok(resolved),
     }
 }
 
+/// Check a path inside a sandbox, where `resolved` is the sandbox's own
+/// symlink-resolved form of `path`. Nothing on the host filesystem is consulted.
+pub fn check_sandbox_path(path: &str, resolved: &str) -> Result<(), String> {
+    check_sandbox_builtin(path, resolved).map_err(String::from)
+}
+
+fn check_sandbox_builtin(path: &str, resolved: &str) -> Result<(), PathDenied> {
+    let requested = normalize_path(Path::new(path));
+    let resolved = Path::new(resolved);
+    if requested != resolved {
+        return Err(PathDenied::new(
+            path,
+            DenyReason::Symlink,
+            None,
+            format!(
+                "Access denied: '{path}' symlink validation failed: resolves to '{}' inside the sandbox",
+                resolved.display()
+            ),
+        ));
+    }
+    if let Err(err) = validate_allowlist_target(resolved) {
+        return Err(PathDenied::new(
+            path,
+            DenyReason::OutsideAllowlist,
+            None,
+            format!("Access denied: {err}"),
+        ));
+    }
+    match blocked_entry(Path::new(path), resolved) {
+        Some(entry) => Err(PathDenied::sensitive(path, entry)),
+        None => Ok(()),
+    }
+}
+
+/// [`is_blocked`] for a path listed inside a sandbox, checked by name only.
+pub fn is_sandbox_path_blocked(path: &Path) -> bool {
+    is_blocked_resolved(path, &normalize_path(path))
+}
+
 /// Logically normalize a path by resolving `.` and `..` components without
 /// requiring the path to exist on the filesystem.
 fn normalize_path(path: &Path) -> PathBuf {
@@ -85,11 +264,15 @@ fn normalize_path(path: &Path) -> PathBuf {
 
 /// Check if a file path should be blocked.
 pub fn is_blocked(path: &Path) -> bool {
+    is_blocked_resolved(path, &resolve_host_path(path))
+}
+
+fn resolve_host_path(path: &Path) -> PathBuf {
     // Try to resolve symlinks; if the file doesn't exist, fall back to
     // logical normalization so that `..` components are still resolved.
     // For relative paths, prepend the working directory first so `..`
     // at the start can be resolved against the absolute prefix.
-    let resolved = std::fs::canonicalize(path).unwrap_or_else(|_| {
+    std::fs::canonicalize(path).unwrap_or_else(|_| {
         let abs = if path.is_relative() {
             if let Ok(cwd) = std::env::current_dir() {
                 cwd.join(path)
@@ -100,14 +283,21 @@ pub fn is_blocked(path: &Path) -> bool {
             path.to_path_buf()
         };
         normalize_path(&abs)
-    });
+    })
+}
 
+fn is_blocked_resolved(path: &Path, resolved: &Path) -> bool {
+    blocked_entry(path, resolved).is_some()
+}
+
+/// The built-in entry that blocks `path`, if any.
+fn blocked_entry(path: &Path, resolved: &Path) -> Option<String> {
     // Check against blocked absolute paths (both original and resolved)
     let original_str = path.to_string_lossy();
     let resolved_str = resolved.to_string_lossy();
     for blocked in BLOCKED_ABSOLUTE {
         if original_str == *blocked || resolved_str == *blocked {
-            return true;
+            return Some(blocked.to_string());
         }
     }
 
@@ -123,10 +313,10 @@ pub fn is_blocked(path: &Path) -> bool {
     // Check each component against blocked dirs and files
     for component in &components {
         if BLOCKED_DIRS.contains(&component.as_str()) {
-            return true;
+            return Some(component.clone());
         }
         if BLOCKED_FILES.contains(&component.as_str()) {
-            return true;
+            return Some(component.clone());
         }
     }
 
@@ -139,13 +329,13 @@ pub fn is_blocked(path: &Path) -> bool {
                     .zip(subpath.iter())
                     .all(|(a, b)| a.as_str() == *b);
                 if matches {
-                    return true;
+                    return Some(subpath.join("/"));
                 }
             }
         }
     }
 
-    false
+    None
 }
 
 fn validate_symlink_safety(path: &Path) -> Result<(), String> {
@@ -180,22 +370,40 @@ fn validate_symlink_safety(path: &Path) -> Result<(), String> {
 }
 
 fn validate_allowlist(path: &Path) -> Result<(), String> {
-    if cfg!(test) {
-        let _ = path;
+    let Some((allowlist, content)) = load_allowlist()? else {
         return Ok(());
+    };
+    let canonical_target = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    check_allowlist(&allowlist, &content, &canonical_target)
+}
+
+/// Allowlist check against a target already resolved inside a sandbox.
+fn validate_allowlist_target(resolved: &Path) -> Result<(), String> {
+    let Some((allowlist, content)) = load_allowlist()? else {
+        return Ok(());
+    };
+    check_allowlist(&allowlist, &content, resolved)
+}
+
+fn load_allowlist() -> Result<Option<(PathBuf, String)>, String> {
+    if cfg!(test) {
+        return Ok(None);
     }
     let allowlist_path = std::env::var_os("MICROCLAW_PATH_ALLOWLIST")
         .map(std::path::PathBuf::from)
         .or_else(default_path_allowlist_path);
     let Some(allowlist) = allowlist_path else {
-        return Ok(());
+        return Ok(None);
     };
     if !allowlist.exists() {
-        return Ok(());
+        return Ok(None);
     }
     let content = std::fs::read_to_string(&allowlist)
         .map_err(|e| format!("failed reading allowlist '{}': {e}", allowlist.display()))?;
-    let canonical_target = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    Ok(Some((allowlist, content)))
+}
+
+fn check_allowlist(allowlist: &Path, content: &str, canonical_target: &Path) -> Result<(), String> {
     let mut allowed_roots = Vec::new();
     for raw in content.lines() {
         let line = raw.trim();
@@ -233,6 +441,324 @@ pub fn filter_paths(paths: Vec<String>) -> Vec<String> {
         .collect()
 }
 
+/// Path rules added to the built-in ones, for every chat or for one chat.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathRules {
+    /// Paths no file tool may touch. A glob without `/` matches any path component
+    /// (`*.pem`); one with `/` matches absolute paths and everything below them
+    /// (`~/work/private`, `/srv/**/secrets`). `*` and `?` stay within a component,
+    /// `**` spans components.
+    #[serde(default)]
+    pub deny_globs: Vec<String>,
+    /// Directories file tools may read but not change.
+    #[serde(default)]
+    pub read_only: Vec<String>,
+    /// Directories file tools may write into but not read back.
+    #[serde(default)]
+    pub write_only: Vec<String>,
+    /// Largest file a tool may read, in bytes. Unset or `0` means no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_read_bytes: Option<u64>,
+    /// Largest file a tool may write, in bytes. Unset or `0` means no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_write_bytes: Option<u64>,
+}
+
+impl PathRules {
+    fn normalize(&mut self) {
+        let clean = |entries: &mut Vec<String>, zone: bool| {
+            *entries = entries
+                .iter()
+                .map(|e| e.trim())
+                .filter(|e| !e.is_empty())
+                .map(|e| {
+                    let expanded = expand_home(e);
+                    if zone && expanded.len() > 1 {
+                        expanded.trim_end_matches('/').to_string()
+                    } else {
+                        expanded
+                    }
+                })
+                .collect();
+        };
+        clean(&mut self.deny_globs, false);
+        clean(&mut self.read_only, true);
+        clean(&mut self.write_only, true);
+        self.max_read_bytes = self.max_read_bytes.filter(|n| *n > 0);
+        self.max_write_bytes = self.max_write_bytes.filter(|n| *n > 0);
+    }
+
+    fn validate(&self, scope: &str) -> Result<(), String> {
+        for glob in &self.deny_globs {
+            glob_regex(glob).map_err(|e| {
+                format!("path_policy.{scope}.deny_globs: '{glob}' is not a valid glob: {e}")
+            })?;
+        }
+        for (field, zones) in [
+            ("read_only", &self.read_only),
+            ("write_only", &self.write_only),
+        ] {
+            if let Some(zone) = zones.iter().find(|z| !Path::new(z).is_absolute()) {
+                return Err(format!(
+                    "path_policy.{scope}.{field}: '{zone}' must be an absolute path or start with ~/"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `path_policy` in the config.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathPolicyConfig {
+    /// Rules for every chat.
+    #[serde(default)]
+    pub default: PathRules,
+    /// Rules keyed by chat session (`<channel>-<chat_id>`, e.g. `telegram-123`),
+    /// applied on top of `default`. They can only narrow access.
+    #[serde(default)]
+    pub chats: HashMap<String, PathRules>,
+}
+
+impl PathPolicyConfig {
+    pub fn normalize(&mut self) {
+        self.default.normalize();
+        self.chats.values_mut().for_each(PathRules::normalize);
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.default.validate("default")?;
+        for (key, rules) in &self.chats {
+            rules.validate(&format!("chats.{key}"))?;
+        }
+        Ok(())
+    }
+
+    /// The policy for `session_key`: the default rules plus that chat's own.
+    pub fn resolve(&self, session_key: &str) -> PathPolicy {
+        let mut policy = PathPolicy::default();
+        policy.add(&self.default);
+        if let Some(rules) = self.chats.get(session_key) {
+            policy.add(rules);
+        }
+        policy
+    }
+}
+
+/// The path rules in effect for one chat, ready to check paths against.
+/// The default value applies only the built-in checks.
+#[derive(Debug, Clone, Default)]
+pub struct PathPolicy {
+    deny: Vec<(String, Regex)>,
+    read_only: Vec<(String, Vec<PathBuf>)>,
+    write_only: Vec<(String, Vec<PathBuf>)>,
+    max_read_bytes: Option<u64>,
+    max_write_bytes: Option<u64>,
+}
+
+impl PathPolicy {
+    fn add(&mut self, rules: &PathRules) {
+        for glob in &rules.deny_globs {
+            match glob_regex(glob) {
+                Ok(re) => self.deny.push((glob.clone(), re)),
+                Err(e) => tracing::warn!("Ignoring invalid path_policy deny glob '{glob}': {e}"),
+            }
+        }
+        self.read_only
+            .extend(rules.read_only.iter().map(|z| (z.clone(), zone_roots(z))));
+        self.write_only
+            .extend(rules.write_only.iter().map(|z| (z.clone(), zone_roots(z))));
+        let tighter = |a: Option<u64>, b: Option<u64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.max_read_bytes = tighter(self.max_read_bytes, rules.max_read_bytes);
+        self.max_write_bytes = tighter(self.max_write_bytes, rules.max_write_bytes);
+    }
+
+    /// Check a host path for `access`: the built-in checks, this policy's rules and,
+    /// for reads of an existing file, `max_read_bytes`.
+    pub fn check(&self, path: &str, access: PathAccess) -> Result<(), PathDenied> {
+        let resolved = check_builtin(path)?;
+        self.check_rules(path, &resolved, access)?;
+        if access.reads() {
+            if let Ok(meta) = std::fs::metadata(&resolved) {
+                if meta.is_file() {
+                    self.check_read_size(path, meta.len())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// [`PathPolicy::check`] for a path inside a sandbox. `resolved` is the sandbox's
+    /// symlink-resolved form of `path`, `size` the file's size there if it is a file.
+    pub fn check_sandbox(
+        &self,
+        path: &str,
+        resolved: &str,
+        size: Option<u64>,
+        access: PathAccess,
+    ) -> Result<(), PathDenied> {
+        check_sandbox_builtin(path, resolved)?;
+        self.check_rules(path, Path::new(resolved), access)?;
+        match size {
+            Some(size) if access.reads() => self.check_read_size(path, size),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_read_size(&self, path: &str, bytes: u64) -> Result<(), PathDenied> {
+        check_size(path, bytes, self.max_read_bytes, "read")
+    }
+
+    pub fn check_write_size(&self, path: &str, bytes: u64) -> Result<(), PathDenied> {
+        check_size(path, bytes, self.max_write_bytes, "write")
+    }
+
+    /// Whether a host path met while walking a directory may be listed or searched.
+    /// Like [`is_blocked`], this skips the symlink, allowlist and size checks.
+    pub fn permits(&self, path: &Path, access: PathAccess) -> bool {
+        let resolved = resolve_host_path(path);
+        !is_blocked_resolved(path, &resolved) && self.check_rules("", &resolved, access).is_ok()
+    }
+
+    /// [`PathPolicy::permits`] for a path listed inside a sandbox, checked by name only.
+    pub fn permits_sandbox(&self, path: &Path, access: PathAccess) -> bool {
+        let resolved = normalize_path(path);
+        !is_blocked_resolved(path, &resolved) && self.check_rules("", &resolved, access).is_ok()
+    }
+
+    /// [`filter_paths`] with this policy's rules applied as well.
+    pub fn filter_paths(&self, paths: Vec<String>) -> Vec<String> {
+        paths
+            .into_iter()
+            .filter(|p| self.permits(Path::new(p), PathAccess::Read))
+            .collect()
+    }
+
+    fn check_rules(
+        &self,
+        path: &str,
+        resolved: &Path,
+        access: PathAccess,
+    ) -> Result<(), PathDenied> {
+        for (glob, re) in &self.deny {
+            let matched = if glob.contains('/') {
+                // An ancestor matching covers everything below a denied directory.
+                resolved
+                    .ancestors()
+                    .any(|a| re.is_match(&a.to_string_lossy()))
+            } else {
+                resolved.components().any(|c| match c {
+                    Component::Normal(name) => re.is_match(&name.to_string_lossy()),
+                    _ => false,
+                })
+            };
+            if matched {
+                return Err(PathDenied::new(
+                    path,
+                    DenyReason::DenyGlob,
+                    Some(glob.clone()),
+                    format!("Access denied: '{path}' matches path_policy deny glob '{glob}'."),
+                ));
+            }
+        }
+        if access.writes() {
+            if let Some(zone) = zone_containing(&self.read_only, resolved) {
+                return Err(PathDenied::new(
+                    path,
+                    DenyReason::ReadOnly,
+                    Some(zone.to_string()),
+                    format!("Access denied: '{path}' is in read-only zone '{zone}' and cannot be changed."),
+                ));
+            }
+        }
+        if access.reads() {
+            if let Some(zone) = zone_containing(&self.write_only, resolved) {
+                return Err(PathDenied::new(
+                    path,
+                    DenyReason::WriteOnly,
+                    Some(zone.to_string()),
+                    format!("Access denied: '{path}' is in write-only directory '{zone}' and cannot be read."),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_size(path: &str, bytes: u64, limit: Option<u64>, kind: &str) -> Result<(), PathDenied> {
+    match limit {
+        Some(limit) if bytes > limit => Err(PathDenied::new(
+            path,
+            DenyReason::TooLarge,
+            Some(format!("max_{kind}_bytes: {limit}")),
+            format!(
+                "Access denied: '{path}' is {bytes} bytes, over the {limit}-byte {kind} limit (path_policy max_{kind}_bytes)."
+            ),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A zone as written and, when different, with symlinks resolved, so it matches
+/// canonicalized paths too.
+fn zone_roots(zone: &str) -> Vec<PathBuf> {
+    let root = PathBuf::from(zone);
+    let mut roots = vec![root.clone()];
+    if let Ok(canonical) = std::fs::canonicalize(&root) {
+        if canonical != root {
+            roots.push(canonical);
+        }
+    }
+    roots
+}
+
+fn zone_containing<'a>(zones: &'a [(String, Vec<PathBuf>)], path: &Path) -> Option<&'a str> {
+    zones
+        .iter()
+        .find(|(_, roots)| roots.iter().any(|root| path.starts_with(root)))
+        .map(|(zone, _)| zone.as_str())
+}
+
+fn expand_home(entry: &str) -> String {
+    let Some(rest) = entry.strip_prefix("~/").or((entry == "~").then_some("")) else {
+        return entry.to_string();
+    };
+    match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        Some(home) => PathBuf::from(home)
+            .join(rest)
+            .to_string_lossy()
+            .into_owned(),
+        None => entry.to_string(),
+    }
+}
+
+/// Compile a deny glob into an anchored regex over `/`-separated paths.
+fn glob_regex(glob: &str) -> Result<Regex, String> {
+    let mut re = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    re.push_str("(?:.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re).map_err(|e| e.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -378,4 +904,142 @@ mod tests {
         assert!(err.contains("symlink"));
         let _ = std::fs::remove_dir_all(&dir);
     }
+
+    #[test]
+    fn test_check_sandbox_path_uses_sandbox_resolution() {
+        assert!(check_sandbox_path("/work/src/main.rs", "/work/src/main.rs").is_ok());
+        assert!(check_sandbox_path("/work/src/../README.md", "/work/README.md").is_ok());
+
+        let err = check_sandbox_path("/work/link", "/root/.ssh/id_rsa").unwrap_err();
+        assert!(err.contains("symlink validation failed"));
+        let err = check_sandbox_path("/work/.env", "/work/.env").unwrap_err();
+        assert!(err.contains("sensitive path"));
+        assert!(check_sandbox_path("/etc/shadow", "/etc/shadow").is_err());
+
+        assert!(is_sandbox_path_blocked(Path::new("/home/user/.aws/config")));
+        assert!(!is_sandbox_path_blocked(Path::new("/work/src/lib.rs")));
+    }
+
+    fn rules(json: &str) -> PathRules {
+        let mut rules: PathRules = serde_json::from_str(json).unwrap();
+        rules.normalize();
+        rules
+    }
+
+    fn policy(default: PathRules) -> PathPolicy {
+        PathPolicyConfig {
+            default,
+            chats: HashMap::new(),
+        }
+        .resolve("web-1")
+    }
+
+    #[test]
+    fn test_policy_deny_globs_match_names_and_subtrees() {
+        let policy = policy(rules(
+            r#"{"deny_globs": ["*.pem", "/srv/app/private", "/srv/**/secrets/*.txt"]}"#,
+        ));
+        let err = policy
+            .check("/srv/app/certs/server.pem", PathAccess::Read)
+            .unwrap_err();
+        assert_eq!(err.reason, DenyReason::DenyGlob);
+        assert_eq!(err.rule.as_deref(), Some("*.pem"));
+        assert_eq!(err.reason.error_type(), "path_deny_glob");
+
+        let err = policy
+            .check("/srv/app/private/notes.md", PathAccess::Write)
+            .unwrap_err();
+        assert_eq!(err.rule.as_deref(), Some("/srv/app/private"));
+        assert!(policy
+            .check("/srv/a/b/secrets/key.txt", PathAccess::Read)
+            .is_err());
+        assert!(policy
+            .check("/srv/app/private-ish/notes.md", PathAccess::Read)
+            .is_ok());
+        assert!(policy
+            .check("/srv/app/src/main.rs", PathAccess::Read)
+            .is_ok());
+
+        // Built-in checks still come first.
+        let err = policy
+            .check("/home/user/.ssh/id_rsa", PathAccess::Read)
+            .unwrap_err();
+        assert_eq!(err.reason, DenyReason::Sensitive);
+        assert_eq!(err.rule.as_deref(), Some(".ssh"));
+    }
+
+    #[test]
+    fn test_policy_read_only_and_write_only_zones() {
+        let policy = policy(rules(
+            r#"{"read_only": ["/srv/docs/"], "write_only": ["/srv/drop"]}"#,
+        ));
+        assert!(policy.check("/srv/docs/a.md", PathAccess::Read).is_ok());
+        let err = policy
+            .check("/srv/docs/a.md", PathAccess::ReadWrite)
+            .unwrap_err();
+        assert_eq!(err.reason, DenyReason::ReadOnly);
+        assert_eq!(err.rule.as_deref(), Some("/srv/docs"));
+
+        assert!(policy.check("/srv/drop/out.txt", PathAccess::Write).is_ok());
+        let err = policy
+            .check("/srv/drop/out.txt", PathAccess::Read)
+            .unwrap_err();
+        assert_eq!(err.reason, DenyReason::WriteOnly);
+        assert!(!policy.permits(Path::new("/srv/drop/out.txt"), PathAccess::Read));
+        assert!(policy.permits_sandbox(Path::new("/srv/docs/a.md"), PathAccess::Read));
+
+        let err = policy
+            .check_sandbox("/srv/docs/b.md", "/srv/docs/b.md", None, PathAccess::Write)
+            .unwrap_err();
+        assert_eq!(err.reason, DenyReason::ReadOnly);
+    }
+
+    #[test]
+    fn test_policy_size_limits_and_chat_rules() {
+        let mut config = PathPolicyConfig {
+            default: rules(r#"{"max_read_bytes": 100, "max_write_bytes": 0}"#),
+            chats: HashMap::from([(
+                "telegram-42".to_string(),
+                rules(
+                    r#"{"max_read_bytes": 1000, "max_write_bytes": 10, "deny_globs": ["*.log"]}"#,
+                ),
+            )]),
+        };
+        config.normalize();
+        assert_eq!(config.default.max_write_bytes, None);
+
+        let shared = config.resolve("web-1");
+        assert!(shared.check_write_size("/w/a", 1_000_000).is_ok());
+        let err = shared
+            .check_sandbox("/w/a", "/w/a", Some(101), PathAccess::Read)
+            .unwrap_err();
+        assert_eq!(err.reason, DenyReason::TooLarge);
+        assert_eq!(err.rule.as_deref(), Some("max_read_bytes: 100"));
+        assert!(shared
+            .check_sandbox("/w/a", "/w/a", Some(101), PathAccess::Write)
+            .is_ok());
+        assert!(shared.check("/w/app.log", PathAccess::Read).is_ok());
+
+        // A chat's rules add to the default ones and cannot raise its limits.
+        let chat = config.resolve("telegram-42");
+        assert!(chat.check_read_size("/w/a", 101).is_err());
+        assert!(chat.check_write_size("/w/a", 11).is_err());
+        assert!(chat.check("/w/app.log", PathAccess::Read).is_err());
+    }
+
+    #[test]
+    fn test_policy_config_validation() {
+        let mut config = PathPolicyConfig {
+            default: rules(r#"{"read_only": ["relative/dir"]}"#),
+            chats: HashMap::new(),
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.contains("path_policy.default.read_only"));
+
+        config.default = rules(r#"{"read_only": ["~/docs", "  "], "deny_globs": [" *.key "]}"#);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.default.deny_globs, vec!["*.key".to_string()]);
+        assert_eq!(config.default.read_only.len(), 1);
+        assert!(Path::new(&config.default.read_only[0]).is_absolute());
+    }
 }
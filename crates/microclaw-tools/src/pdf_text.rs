@@ -0,0 +1,228 @@
+//! Text extraction from PDF documents.
+//!
+//! Parsing is delegated to `pdf-extract` (built on `lopdf`). PDFs arrive from
+//! arbitrary URLs, so every parse runs on the blocking pool under a size cap and
+//! a wall-clock budget, and a panic inside the parser is reported as an error
+//! instead of taking the worker down. Scanned pages yield little or no text.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::Duration;
+
+/// PDFs larger than this are refused before parsing.
+pub const MAX_PDF_BYTES: usize = 10 * 1024 * 1024;
+/// Wall-clock budget for one extraction.
+pub const PDF_PARSE_TIMEOUT: Duration = Duration::from_secs(20);
+
+pub fn is_pdf(data: &[u8]) -> bool {
+    data.len() >= 5
+        && data[..data.len().min(1024)]
+            .windows(5)
+            .any(|w| w == b"%PDF-")
+}
+
+/// Extract readable text from `data` off the async executor, within
+/// [`MAX_PDF_BYTES`] and [`PDF_PARSE_TIMEOUT`].
+pub async fn extract_pdf_text(data: Vec<u8>) -> Result<String, String> {
+    extract_with_budget(
+        data,
+        MAX_PDF_BYTES,
+        PDF_PARSE_TIMEOUT,
+        extract_pdf_text_sync,
+    )
+    .await
+}
+
+async fn extract_with_budget<F>(
+    data: Vec<u8>,
+    max_bytes: usize,
+    timeout: Duration,
+    extract: F,
+) -> Result<String, String>
+where
+    F: FnOnce(&[u8]) -> Result<String, String> + Send + 'static,
+{
+    if data.len() > max_bytes {
+        return Err(format!(
+            "PDF is {} bytes; the limit is {} MB",
+            data.len(),
+            max_bytes / (1024 * 1024)
+        ));
+    }
+    // The blocking thread cannot be cancelled; on timeout it finishes in the
+    // background and its result is dropped.
+    let task = tokio::task::spawn_blocking(move || extract(&data));
+    match tokio::time::timeout(timeout, task).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => Err(format!("PDF extraction failed: {e}")),
+        Err(_) => Err(format!(
+            "PDF extraction timed out after {}s",
+            timeout.as_secs()
+        )),
+    }
+}
+
+/// Synchronous extraction. Fails when the input is not a PDF, cannot be parsed,
+/// or no text could be recovered from it.
+pub fn extract_pdf_text_sync(data: &[u8]) -> Result<String, String> {
+    if !is_pdf(data) {
+        return Err("not a PDF document".to_string());
+    }
+    let raw = match catch_unwind(AssertUnwindSafe(|| {
+        pdf_extract::extract_text_from_mem(data)
+    })) {
+        Ok(Ok(text)) => text,
+        Ok(Err(e)) => return Err(format!("failed to parse PDF: {e}")),
+        Err(_) => return Err("failed to parse PDF: malformed document".to_string()),
+    };
+    let text = tidy(&raw);
+    if text.is_empty() {
+        return Err(
+            "PDF contains no extractable text (it may be scanned or use embedded font encodings)"
+                .to_string(),
+        );
+    }
+    Ok(text)
+}
+
+/// Trim trailing whitespace per line and collapse runs of blank lines.
+fn tidy(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut blank_run = 0;
+    for line in raw.lines().map(str::trim_end) {
+        if line.is_empty() {
+            blank_run += 1;
+            if blank_run > 1 || out.is_empty() {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.trim().to_string()
+}
+
+/// A well-formed single-page PDF drawing `content` with Helvetica as `/F1`.
+#[cfg(test)]
+pub(crate) fn single_page_pdf(content: &[u8]) -> Vec<u8> {
+    let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+    stream.extend_from_slice(content);
+    stream.extend_from_slice(b"\nendstream");
+    pdf_document(&[
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        b"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] \
+           /Resources << /Font << /F1 5 0 R >> >> /Contents 4 0 R >>"
+            .to_vec(),
+        stream,
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_vec(),
+    ])
+}
+
+/// Objects numbered from 1 with a correct cross-reference table.
+#[cfg(test)]
+fn pdf_document(objects: &[Vec<u8>]) -> Vec<u8> {
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, body) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(body);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{
+        extract_pdf_text, extract_pdf_text_sync, extract_with_budget, is_pdf, single_page_pdf,
+    };
+
+    #[test]
+    fn extracts_page_text() {
+        let pdf = single_page_pdf(
+            b"BT /F1 12 Tf 72 720 Td (Hello \\(PDF\\) world) Tj 0 -14 Td (Second line) Tj ET",
+        );
+        assert!(is_pdf(&pdf));
+        let text = extract_pdf_text_sync(&pdf).unwrap();
+        assert!(text.contains("Hello (PDF) world"), "{text:?}");
+        assert!(text.contains("Second line"), "{text:?}");
+    }
+
+    #[test]
+    fn rejects_non_pdf_and_textless_pdf() {
+        assert!(extract_pdf_text_sync(b"<html></html>").is_err());
+        let pdf = single_page_pdf(b"0 0 m 10 10 l S");
+        let err = extract_pdf_text_sync(&pdf).unwrap_err();
+        assert!(err.contains("no extractable text"), "{err}");
+    }
+
+    #[test]
+    fn malformed_input_is_an_error_not_a_panic() {
+        let valid = single_page_pdf(b"BT /F1 12 Tf 72 720 Td (Fuzz target) Tj ET");
+        let mut cases: Vec<Vec<u8>> = vec![
+            b"%PDF-".to_vec(),
+            b"%PDF-1.7\n1 0 obj\n<< /Length 99999999 >>\nstream\nBT".to_vec(),
+            b"%PDF-1.4\ntrailer\n<< /Root 1 0 R >>\nstartxref\n999999\n%%EOF".to_vec(),
+            b"%PDF-1.4\n1 0 obj\n<< /Type /Catalog /Pages 1 0 R >>\nendobj\n".to_vec(),
+        ];
+        // Truncate at every few bytes, then flip bytes throughout with a fixed
+        // xorshift sequence so failures are reproducible.
+        cases.extend((0..valid.len()).step_by(7).map(|n| valid[..n].to_vec()));
+        let mut seed: u64 = 0x9E37_79B9_7F4A_7C15;
+        for _ in 0..200 {
+            let mut mutated = valid.clone();
+            for _ in 0..4 {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let at = 9 + (seed as usize) % (mutated.len() - 9);
+                mutated[at] = (seed >> 32) as u8;
+            }
+            cases.push(mutated);
+        }
+        for case in cases {
+            // Any outcome is fine as long as the call returns.
+            let _ = extract_pdf_text_sync(&case);
+        }
+    }
+
+    #[tokio::test]
+    async fn async_extraction_enforces_size_and_time_budget() {
+        let pdf = single_page_pdf(b"BT /F1 12 Tf 72 720 Td (Budgeted) Tj ET");
+        assert_eq!(extract_pdf_text(pdf.clone()).await.unwrap(), "Budgeted");
+
+        let err = extract_with_budget(pdf.clone(), 16, Duration::from_secs(5), |_| {
+            Ok(String::new())
+        })
+        .await
+        .unwrap_err();
+        assert!(err.contains("limit"), "{err}");
+
+        let err = extract_with_budget(pdf, usize::MAX, Duration::from_millis(50), |_| {
+            std::thread::sleep(Duration::from_millis(500));
+            Ok(String::new())
+        })
+        .await
+        .unwrap_err();
+        assert!(err.contains("timed out"), "{err}");
+    }
+}
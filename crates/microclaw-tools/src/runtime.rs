@@ -1,7 +1,7 @@
 use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
-use microclaw_core::llm_types::ToolDefinition;
+use microclaw_core::llm_types::{ImageSource, ToolDefinition};
 use serde_json::json;
 
 use crate::sandbox::SandboxMode;
@@ -15,6 +15,8 @@ pub struct ToolResult {
     pub duration_ms: Option<u128>,
     pub error_type: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    /// Images shown to the model alongside the text result.
+    pub images: Vec<ImageSource>,
 }
 
 impl ToolResult {
@@ -28,6 +30,7 @@ impl ToolResult {
             duration_ms: None,
             error_type: None,
             metadata: None,
+            images: Vec::new(),
         }
     }
 
@@ -41,6 +44,7 @@ impl ToolResult {
             duration_ms: None,
             error_type: Some("tool_error".to_string()),
             metadata: None,
+            images: Vec::new(),
         }
     }
 
@@ -58,6 +62,11 @@ impl ToolResult {
         self.metadata = Some(metadata);
         self
     }
+
+    pub fn with_image(mut self, image: ImageSource) -> Self {
+        self.images.push(image);
+        self
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -96,8 +105,9 @@ impl ToolExecutionPolicy {
 
 pub fn tool_risk(name: &str) -> ToolRisk {
     match name {
-        "bash" => ToolRisk::High,
-        "write_file"
+        "bash" | "bash_session_start" | "bash_session_exec" | "apply_patch" => ToolRisk::High,
+        "bash_session_kill"
+        | "write_file"
         | "edit_file"
         | "write_memory"
         | "a2a_send"
@@ -114,10 +124,41 @@ pub fn tool_risk(name: &str) -> ToolRisk {
     }
 }
 
+/// Whether a tool call may run concurrently with neighbouring calls from the same turn.
+/// Only low-risk tools qualify, minus the ones that mutate per-turn or per-chat state
+/// (skill env, todo list, browser and shell sessions, sub-agents) and MCP tools, whose side effects
+/// are unknown.
+pub fn tool_parallel_safe(name: &str) -> bool {
+    if tool_risk(name) != ToolRisk::Low {
+        return false;
+    }
+    !matches!(
+        name,
+        "activate_skill"
+            | "todo_write"
+            | "browser"
+            | "sessions_spawn"
+            | "export_chat"
+            | "bash_session_read_output"
+    ) && !name.starts_with("subagents_")
+        && !name.starts_with("mcp_")
+}
+
+/// Tools that can change files in the chat's working dir, so a checkpoint is taken
+/// before the first of them runs in a turn.
+pub fn tool_mutates_working_dir(name: &str) -> bool {
+    matches!(
+        name,
+        "bash" | "bash_session_exec" | "write_file" | "edit_file" | "apply_patch"
+    )
+}
+
 pub fn tool_execution_policy(name: &str) -> ToolExecutionPolicy {
     match name {
-        "bash" => ToolExecutionPolicy::Dual,
-        "write_file" | "edit_file" => ToolExecutionPolicy::HostOnly,
+        "bash" | "bash_session_start" | "bash_session_exec" => ToolExecutionPolicy::Dual,
+        "read_file" | "write_file" | "edit_file" | "apply_patch" | "glob" | "grep" => {
+            ToolExecutionPolicy::Dual
+        }
         _ => ToolExecutionPolicy::HostOnly,
     }
 }
@@ -238,6 +279,11 @@ pub trait Tool: Send + Sync {
     fn name(&self) -> &str;
     fn definition(&self) -> ToolDefinition;
     async fn execute(&self, input: serde_json::Value) -> ToolResult;
+
+    /// What the call would do, shown to the user when it is held for high-risk approval.
+    async fn approval_preview(&self, _input: serde_json::Value) -> Option<String> {
+        None
+    }
 }
 
 pub fn resolve_tool_path(working_dir: &Path, path: &str) -> PathBuf {
@@ -294,11 +340,42 @@ pub fn resolve_tool_working_dir(
     resolved
 }
 
+/// The working dir tools use for a chat, without creating it.
+pub fn working_dir_for_chat(
+    base_working_dir: &Path,
+    isolation: WorkingDirIsolation,
+    channel: &str,
+    chat_id: i64,
+) -> PathBuf {
+    match isolation {
+        WorkingDirIsolation::Shared => base_working_dir.join("shared"),
+        WorkingDirIsolation::Chat => chat_working_dir(base_working_dir, channel, chat_id),
+    }
+}
+
 fn requires_high_risk_approval(name: &str, auth: &ToolAuthContext) -> bool {
     tool_risk(name) == ToolRisk::High && (auth.caller_channel == "web" || auth.is_control_chat())
 }
 
 const HIGH_RISK_APPROVED_KEY: &str = "__microclaw_high_risk_approved";
+/// Input key carrying the digest of the preview an operator approved; a tool that
+/// finds its current state no longer matches refuses to run.
+pub const APPROVAL_DIGEST_KEY: &str = "__microclaw_approval_digest";
+const APPROVAL_DIGEST_LABEL: &str = "Approval digest: ";
+
+/// `preview` with a trailing line naming `digest`, readable by [`approval_digest`].
+pub fn with_approval_digest(preview: String, digest: &str) -> String {
+    format!("{preview}\n\n{APPROVAL_DIGEST_LABEL}{digest}")
+}
+
+/// The digest an approval preview was rendered with, if it has one.
+pub fn approval_digest(preview: &str) -> Option<&str> {
+    let line = preview
+        .lines()
+        .rev()
+        .find(|l| l.starts_with(APPROVAL_DIGEST_LABEL))?;
+    Some(line[APPROVAL_DIGEST_LABEL.len()..].trim()).filter(|d| !d.is_empty())
+}
 
 pub fn require_high_risk_approval(
     name: &str,
@@ -349,10 +426,11 @@ mod tests {
         assert_eq!(tool_execution_policy("bash"), ToolExecutionPolicy::Dual);
         assert_eq!(
             tool_execution_policy("write_file"),
-            ToolExecutionPolicy::HostOnly
+            ToolExecutionPolicy::Dual
         );
+        assert_eq!(tool_execution_policy("grep"), ToolExecutionPolicy::Dual);
         assert_eq!(
-            tool_execution_policy("read_file"),
+            tool_execution_policy("web_fetch"),
             ToolExecutionPolicy::HostOnly
         );
     }
@@ -1,13 +1,18 @@
 use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::{Component, Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 
 use crate::command_runner::{build_command, shell_command};
+use crate::sandbox_egress::{
+    EgressAuditSink, EgressMode, EgressProfile, EgressProxy, SandboxEgressConfig,
+};
+use crate::sandbox_native::{native_available, NativeSandbox};
 use serde::{Deserialize, Serialize};
 
 fn default_sandbox_mode() -> SandboxMode {
@@ -59,6 +64,8 @@ pub enum SandboxBackend {
     Auto,
     Docker,
     Podman,
+    /// Rootless Linux namespaces + seccomp, no container runtime needed.
+    Native,
 }
 
 /// Container security profile controlling Linux capabilities and privilege escalation.
@@ -110,6 +117,8 @@ pub struct SandboxConfig {
     pub cpu_quota: Option<f64>,
     #[serde(default)]
     pub pids_limit: Option<u32>,
+    #[serde(default)]
+    pub egress: SandboxEgressConfig,
 }
 
 impl Default for SandboxConfig {
@@ -127,10 +136,18 @@ impl Default for SandboxConfig {
             memory_limit: None,
             cpu_quota: None,
             pids_limit: None,
+            egress: SandboxEgressConfig::default(),
         }
     }
 }
 
+impl SandboxConfig {
+    /// Egress profile for a session, given the env files of its active skills.
+    pub fn egress_profile(&self, session_key: &str, env_files: &[PathBuf]) -> EgressProfile {
+        self.egress.resolve(session_key, self.no_network, env_files)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SandboxExecOptions {
     pub timeout: Duration,
@@ -147,19 +164,52 @@ pub struct SandboxExecResult {
     pub exit_code: i32,
 }
 
+/// How to start a long-lived interactive shell for a persistent shell session.
+/// The caller attaches the process to a terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellLaunch {
+    pub program: String,
+    pub args: Vec<String>,
+    pub envs: Vec<(String, String)>,
+    pub working_dir: Option<PathBuf>,
+    /// Whether the shell runs inside a sandbox container.
+    pub sandboxed: bool,
+}
+
+/// Starts bash without rc files or line editing when it is installed, else `sh`.
+const INTERACTIVE_SHELL_SCRIPT: &str =
+    "if command -v bash >/dev/null 2>&1; then exec bash --noprofile --norc --noediting -i; else exec sh -i; fi";
+
 #[async_trait]
 pub trait Sandbox: Send + Sync {
     fn backend_name(&self) -> &'static str;
     fn is_real(&self) -> bool {
         true
     }
-    async fn ensure_ready(&self, session_key: &str) -> Result<()>;
+    async fn ensure_ready(&self, session_key: &str, egress: &EgressProfile) -> Result<()>;
     async fn exec(
         &self,
         session_key: &str,
         command: &str,
         opts: &SandboxExecOptions,
+    ) -> Result<SandboxExecResult> {
+        self.exec_with_input(session_key, command, &[], opts).await
+    }
+    /// Like [`Sandbox::exec`], with `input` written to the command's stdin.
+    async fn exec_with_input(
+        &self,
+        session_key: &str,
+        command: &str,
+        input: &[u8],
+        opts: &SandboxExecOptions,
     ) -> Result<SandboxExecResult>;
+    fn interactive_shell(
+        &self,
+        _session_key: &str,
+        opts: &SandboxExecOptions,
+    ) -> Result<ShellLaunch> {
+        Ok(host_interactive_shell(opts))
+    }
 }
 
 pub struct NoSandbox;
@@ -174,17 +224,18 @@ impl Sandbox for NoSandbox {
         false
     }
 
-    async fn ensure_ready(&self, _session_key: &str) -> Result<()> {
+    async fn ensure_ready(&self, _session_key: &str, _egress: &EgressProfile) -> Result<()> {
         Ok(())
     }
 
-    async fn exec(
+    async fn exec_with_input(
         &self,
         _session_key: &str,
         command: &str,
+        input: &[u8],
         opts: &SandboxExecOptions,
     ) -> Result<SandboxExecResult> {
-        exec_host_command(command, opts).await
+        exec_host_command_with_input(command, input, opts).await
     }
 }
 
@@ -226,10 +277,14 @@ fn pick_runtime(
         SandboxBackend::Auto => docker_available.then_some(ContainerRuntime::Docker),
         SandboxBackend::Docker => docker_available.then_some(ContainerRuntime::Docker),
         SandboxBackend::Podman => podman_available.then_some(ContainerRuntime::Podman),
+        SandboxBackend::Native => None,
     }
 }
 
 fn resolve_runtime(backend: SandboxBackend) -> Option<ContainerRuntime> {
+    if backend == SandboxBackend::Native {
+        return None;
+    }
     pick_runtime(
         backend,
         runtime_available("docker"),
@@ -238,18 +293,284 @@ fn resolve_runtime(backend: SandboxBackend) -> Option<ContainerRuntime> {
 }
 
 pub fn runtime_available_for_backend(backend: SandboxBackend) -> bool {
-    resolve_runtime(backend).is_some()
+    match backend {
+        SandboxBackend::Native => native_available(),
+        _ => resolve_runtime(backend).is_some(),
+    }
 }
 
 pub fn selected_runtime_cli(backend: SandboxBackend) -> Option<&'static str> {
     resolve_runtime(backend).map(ContainerRuntime::cli)
 }
 
+/// Container label recording which egress mode a sandbox container was created with.
+const EGRESS_LABEL: &str = "microclaw.egress";
+
+/// How a running container created with egress `current` can serve a call needing
+/// `requested`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContainerReuse {
+    /// The container's network already matches the call.
+    Reuse,
+    /// The container is on the allowlist network, so the proxy can hold it to the
+    /// call's profile, narrower ones included.
+    ThroughProxy,
+    /// Nothing inside the container limits it to the call's profile.
+    Recreate,
+}
+
+fn container_reuse(current: EgressMode, requested: EgressMode) -> ContainerReuse {
+    match (current, requested) {
+        (EgressMode::Allowlist, EgressMode::Allowlist | EgressMode::None) => {
+            ContainerReuse::ThroughProxy
+        }
+        (current, requested) if current == requested => ContainerReuse::Reuse,
+        _ => ContainerReuse::Recreate,
+    }
+}
+
+/// The internal network allowlisted containers join.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EgressNetwork {
+    id: String,
+    subnet: String,
+    gateway: Ipv4Addr,
+    /// IPv6 subnets of the same network. The proxy only listens on IPv4, so
+    /// these get no route to the host at all.
+    subnets_v6: Vec<String>,
+}
+
+/// Parses `<id> <subnet> <gateway> ...` from `network inspect`, keeping the first
+/// IPv4 subnet and every IPv6 one.
+fn parse_egress_network(inspect: &str) -> Option<EgressNetwork> {
+    let mut fields = inspect.split_whitespace();
+    let id = fields.next()?.to_string();
+    let fields: Vec<&str> = fields.collect();
+    let mut ipv4 = None;
+    let mut subnets_v6 = Vec::new();
+    for pair in fields.chunks(2) {
+        let subnet = pair[0];
+        if subnet.contains(':') {
+            subnets_v6.push(subnet.to_string());
+        } else if ipv4.is_none() {
+            ipv4 = pair
+                .get(1)
+                .and_then(|gateway| gateway.parse::<Ipv4Addr>().ok())
+                .map(|gateway| (subnet.to_string(), gateway));
+        }
+    }
+    let (subnet, gateway) = ipv4?;
+    Some(EgressNetwork {
+        id,
+        subnet,
+        gateway,
+        subnets_v6,
+    })
+}
+
+/// The chain guarding the host from one egress network (same name in both families).
+fn host_firewall_chain(network: &EgressNetwork) -> String {
+    let id: String = network.id.chars().take(12).collect();
+    format!("MICROCLAW-{id}")
+}
+
+/// One `iptables` or `ip6tables` chain that `INPUT` sends the egress network's
+/// packets to. `--internal` already keeps these containers off other networks;
+/// this closes the host itself, whose services listening on the gateway or
+/// `0.0.0.0` would otherwise be reachable without the proxy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HostFirewall {
+    cli: &'static str,
+    chain: String,
+    /// Rules appended to the chain after it is flushed.
+    rules: Vec<Vec<String>>,
+    /// Source subnets `INPUT` jumps to the chain for.
+    subnets: Vec<String>,
+}
+
+fn args(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|a| a.to_string()).collect()
+}
+
+/// The IPv4 chain accepts the proxy port on the gateway and rejects the rest; the
+/// IPv6 chain, present when the network has IPv6 subnets, rejects everything.
+fn host_firewalls(network: &EgressNetwork, proxy: SocketAddr) -> Vec<HostFirewall> {
+    let chain = host_firewall_chain(network);
+    let mut firewalls = vec![HostFirewall {
+        cli: "iptables",
+        chain: chain.clone(),
+        rules: vec![
+            args(&[
+                "-d",
+                &network.gateway.to_string(),
+                "-p",
+                "tcp",
+                "--dport",
+                &proxy.port().to_string(),
+                "-j",
+                "ACCEPT",
+            ]),
+            args(&["-j", "REJECT"]),
+        ],
+        subnets: vec![network.subnet.clone()],
+    }];
+    if !network.subnets_v6.is_empty() {
+        firewalls.push(HostFirewall {
+            cli: "ip6tables",
+            chain,
+            rules: vec![args(&["-j", "REJECT"])],
+            subnets: network.subnets_v6.clone(),
+        });
+    }
+    firewalls
+}
+
+impl HostFirewall {
+    /// Flushes the chain and appends its rules.
+    fn chain_commands(&self) -> Vec<Vec<String>> {
+        let mut commands = vec![args(&["-w", "-F", &self.chain])];
+        commands.extend(self.rules.iter().map(|rule| {
+            let mut command = args(&["-w", "-A", &self.chain]);
+            command.extend(rule.iter().cloned());
+            command
+        }));
+        commands
+    }
+
+    fn jump(&self, op: &str, subnet: &str) -> Vec<String> {
+        args(&["-w", op, "INPUT", "-s", subnet, "-j", &self.chain])
+    }
+
+    /// Undoes [`HostFirewall::install`]: the `INPUT` jumps, then the chain.
+    fn removal_commands(&self) -> Vec<Vec<String>> {
+        let mut commands: Vec<Vec<String>> = self
+            .subnets
+            .iter()
+            .map(|subnet| self.jump("-D", subnet))
+            .collect();
+        commands.push(args(&["-w", "-F", &self.chain]));
+        commands.push(args(&["-w", "-X", &self.chain]));
+        commands
+    }
+
+    async fn run(&self, args: &[String]) -> Result<std::process::Output> {
+        tokio::process::Command::new(self.cli)
+            .args(args)
+            .output()
+            .await
+            .with_context(|| format!("failed to run {}", self.cli))
+    }
+
+    async fn run_checked(&self, args: &[String]) -> Result<()> {
+        let out = self.run(args).await?;
+        if !out.status.success() {
+            bail!(
+                "{} {} failed: {}",
+                self.cli,
+                args.join(" "),
+                String::from_utf8_lossy(&out.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    async fn install(&self) -> Result<()> {
+        // Fails harmlessly when the chain is left over from an earlier run.
+        self.run(&args(&["-w", "-N", &self.chain])).await?;
+        for command in self.chain_commands() {
+            self.run_checked(&command).await?;
+        }
+        for subnet in &self.subnets {
+            if !self.run(&self.jump("-C", subnet)).await?.status.success() {
+                self.run_checked(&self.jump("-I", subnet)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn remove(&self) {
+        for command in self.removal_commands() {
+            // Keep going: a missing jump must not leave the chain behind.
+            if let Err(e) = self.run_checked(&command).await {
+                tracing::debug!("sandbox firewall teardown: {e:#}");
+            }
+        }
+    }
+}
+
+/// Chains installed by this process, removed by [`remove_host_firewalls`].
+static HOST_FIREWALLS: std::sync::Mutex<Vec<HostFirewall>> = std::sync::Mutex::new(Vec::new());
+
+/// Removes the host firewall chains and `INPUT` jumps installed for allowlist
+/// egress. Call on shutdown.
+pub async fn remove_host_firewalls() {
+    let firewalls = std::mem::take(&mut *HOST_FIREWALLS.lock().unwrap_or_else(|e| e.into_inner()));
+    for firewall in firewalls {
+        firewall.remove().await;
+        tracing::info!(
+            chain = firewall.chain.as_str(),
+            cli = firewall.cli,
+            "removed sandbox egress firewall"
+        );
+    }
+}
+
+/// Installs the host firewall for `network`. Returns the failures; any failure
+/// leaves allowlisted containers able to reach host services besides the proxy.
+async fn install_host_firewall(network: &EgressNetwork, proxy: SocketAddr) -> Vec<String> {
+    let mut failures = Vec::new();
+    for firewall in host_firewalls(network, proxy) {
+        {
+            // Recorded first so a partial install is still torn down.
+            let mut installed = HOST_FIREWALLS.lock().unwrap_or_else(|e| e.into_inner());
+            if !installed.contains(&firewall) {
+                installed.push(firewall.clone());
+            }
+        }
+        match firewall.install().await {
+            Ok(()) => tracing::info!(
+                chain = firewall.chain.as_str(),
+                cli = firewall.cli,
+                subnets = firewall.subnets.join(","),
+                proxy = %proxy,
+                "sandbox egress network limited to the proxy port on the host"
+            ),
+            Err(e) => failures.push(format!("{e:#}")),
+        }
+    }
+    failures
+}
+
+/// Whether this process may manage the host firewall (`iptables -S INPUT`
+/// succeeds), which allowlist egress needs to keep containers off host services.
+pub fn host_firewall_available() -> Result<(), String> {
+    let out = std::process::Command::new("iptables")
+        .args(["-w", "-S", "INPUT"])
+        .output()
+        .map_err(|e| format!("failed to run iptables: {e}"))?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&out.stderr).trim().to_string())
+    }
+}
+
+/// Egress proxy state shared by the router and the container backend, so an audit
+/// sink installed on the router reaches the proxy.
+#[derive(Default)]
+struct EgressState {
+    audit: OnceLock<Arc<dyn EgressAuditSink>>,
+    proxy: tokio::sync::OnceCell<Arc<EgressProxy>>,
+    /// Set when the host firewall could not be installed.
+    firewall_degraded: AtomicBool,
+}
+
 pub struct DockerSandbox {
     runtime: ContainerRuntime,
     config: SandboxConfig,
     mount_dir: PathBuf,
     extra_mounts: Vec<ExtraMount>,
+    egress: Arc<EgressState>,
 }
 
 impl DockerSandbox {
@@ -258,13 +579,117 @@ impl DockerSandbox {
         config: SandboxConfig,
         mount_dir: PathBuf,
         extra_mounts: Vec<ExtraMount>,
+        egress: Arc<EgressState>,
     ) -> Self {
         Self {
             runtime,
             config,
             mount_dir,
             extra_mounts,
+            egress,
+        }
+    }
+
+    fn egress_network(&self) -> String {
+        format!("{}-egress", self.config.container_prefix)
+    }
+
+    async fn egress_network_info(&self) -> Result<Option<EgressNetwork>> {
+        let out = tokio::process::Command::new(self.runtime.cli())
+            .args([
+                "network",
+                "inspect",
+                "--format",
+                "{{.Id}} {{range .IPAM.Config}}{{.Subnet}} {{.Gateway}} {{end}}",
+                &self.egress_network(),
+            ])
+            .output()
+            .await
+            .with_context(|| format!("failed to run {}", self.runtime.cli()))?;
+        if !out.status.success() {
+            return Ok(None);
         }
+        Ok(parse_egress_network(&String::from_utf8_lossy(&out.stdout)))
+    }
+
+    /// The egress proxy, started on first use. It listens on the gateway address of
+    /// an internal container network, and a host firewall chain makes its port the
+    /// only host port allowlisted containers can reach. Without permission to
+    /// install that chain, allowlist egress runs degraded with a warning.
+    async fn egress_proxy(&self) -> Result<Arc<EgressProxy>> {
+        let proxy = self
+            .egress
+            .proxy
+            .get_or_try_init(|| async {
+                let network_name = self.egress_network();
+                let network = match self.egress_network_info().await? {
+                    Some(network) => network,
+                    None => {
+                        let out = tokio::process::Command::new(self.runtime.cli())
+                            .args(["network", "create", "--internal", &network_name])
+                            .output()
+                            .await
+                            .with_context(|| format!("failed to run {}", self.runtime.cli()))?;
+                        if !out.status.success() {
+                            let stderr = String::from_utf8_lossy(&out.stderr);
+                            bail!(
+                                "{} network create failed: {}",
+                                self.runtime.cli(),
+                                stderr.trim()
+                            );
+                        }
+                        self.egress_network_info().await?.with_context(|| {
+                            format!("network {network_name} has no IPv4 subnet and gateway")
+                        })?
+                    }
+                };
+                let proxy = EgressProxy::bind(
+                    SocketAddr::new(IpAddr::V4(network.gateway), 0),
+                    self.egress.audit.get().cloned(),
+                )
+                .await?;
+                let failures = install_host_firewall(&network, proxy.local_addr()).await;
+                if !failures.is_empty() {
+                    self.egress.firewall_degraded.store(true, Ordering::Relaxed);
+                    tracing::warn!(
+                        network = network_name.as_str(),
+                        errors = failures.join("; "),
+                        "sandbox egress DEGRADED: could not firewall the host from the allowlist \
+                         network, so allowlisted containers can reach host services on {} besides \
+                         the proxy. Domain filtering still applies to proxied traffic. Run as root \
+                         or with CAP_NET_ADMIN (rootless Docker/Podman cannot); see \
+                         `microclaw doctor sandbox`",
+                        network.gateway
+                    );
+                }
+                Ok(proxy)
+            })
+            .await?;
+        Ok(proxy.clone())
+    }
+
+    /// Network arguments for `run` that enforce `egress` for the session.
+    async fn egress_args(&self, session_key: &str, egress: &EgressProfile) -> Result<Vec<String>> {
+        let mut args = vec![
+            "--label".to_string(),
+            format!("{EGRESS_LABEL}={}", egress.mode.as_str()),
+        ];
+        match egress.mode {
+            EgressMode::None => args.push("--network=none".to_string()),
+            EgressMode::Full => {}
+            EgressMode::Allowlist => {
+                let proxy = self.egress_proxy().await?;
+                let url = proxy.proxy_url(&proxy.register(session_key, egress.clone()));
+                args.extend(["--network".to_string(), self.egress_network()]);
+                for var in ["HTTP_PROXY", "HTTPS_PROXY", "http_proxy", "https_proxy"] {
+                    args.extend(["-e".to_string(), format!("{var}={url}")]);
+                }
+                for var in ["NO_PROXY", "no_proxy"] {
+                    args.extend(["-e".to_string(), format!("{var}=localhost,127.0.0.1")]);
+                }
+            }
+        }
+        Ok(args)
     }
 
     fn container_name(&self, session_key: &str) -> String {
@@ -322,17 +747,57 @@ impl Sandbox for DockerSandbox {
         self.runtime.label()
     }
 
-    async fn ensure_ready(&self, session_key: &str) -> Result<()> {
+    async fn ensure_ready(&self, session_key: &str, egress: &EgressProfile) -> Result<()> {
         let name = self.container_name(session_key);
+        let format =
+            format!("{{{{.State.Running}}}} {{{{index .Config.Labels \"{EGRESS_LABEL}\"}}}}");
         let inspect = tokio::process::Command::new(self.runtime.cli())
-            .args(["inspect", "--format", "{{.State.Running}}", &name])
+            .args(["inspect", "--format", &format, &name])
             .output()
             .await;
-        if let Ok(out) = inspect {
+        if let Some(out) = inspect.ok().filter(|out| out.status.success()) {
             let stdout = String::from_utf8_lossy(&out.stdout);
-            if out.status.success() && stdout.trim() == "true" {
+            let mut fields = stdout.split_whitespace();
+            let running = fields.next() == Some("true");
+            // Containers from before egress profiles carry no label.
+            let legacy_mode = if self.config.no_network {
+                EgressMode::None
+            } else {
+                EgressMode::Full
+            };
+            let mode = fields
+                .next()
+                .and_then(EgressMode::parse)
+                .unwrap_or(legacy_mode);
+            let reusable = running
+                && match container_reuse(mode, egress.mode) {
+                    ContainerReuse::Reuse => true,
+                    // Only while the proxy still knows the session.
+                    ContainerReuse::ThroughProxy => self
+                        .egress
+                        .proxy
+                        .get()
+                        .is_some_and(|proxy| proxy.update(session_key, egress)),
+                    ContainerReuse::Recreate => false,
+                };
+            if reusable {
                 return Ok(());
             }
+            tracing::info!(
+                container = name.as_str(),
+                from = mode.as_str(),
+                to = egress.mode.as_str(),
+                "recreating sandbox container for egress profile"
+            );
+            let out = tokio::process::Command::new(self.runtime.cli())
+                .args(["rm", "-f", &name])
+                .output()
+                .await
+                .with_context(|| format!("failed to run {}", self.runtime.cli()))?;
+            if !out.status.success() {
+                let stderr = String::from_utf8_lossy(&out.stderr);
+                bail!("{} rm failed: {}", self.runtime.cli(), stderr.trim());
+            }
         }
 
         let mut args = vec![
@@ -342,9 +807,7 @@ impl Sandbox for DockerSandbox {
             name.clone(),
         ];
         args.extend(self.security_args());
-        if self.config.no_network {
-            args.push("--network=none".to_string());
-        }
+        args.extend(self.egress_args(session_key, egress).await?);
         args.extend(self.resource_args());
         let mount = self.mount_dir.display().to_string();
         args.extend(["-v".to_string(), format!("{mount}:{mount}:rw")]);
@@ -368,14 +831,18 @@ impl Sandbox for DockerSandbox {
         Ok(())
     }
 
-    async fn exec(
+    async fn exec_with_input(
         &self,
         session_key: &str,
         command: &str,
+        input: &[u8],
         opts: &SandboxExecOptions,
     ) -> Result<SandboxExecResult> {
         let name = self.container_name(session_key);
         let mut args = vec!["exec".to_string()];
+        if !input.is_empty() {
+            args.push("-i".to_string());
+        }
 
         if let Some(dir) = &opts.working_dir {
             args.extend(["-w".to_string(), dir.display().to_string()]);
@@ -388,13 +855,14 @@ impl Sandbox for DockerSandbox {
         }
         args.push(name);
         args.extend(["sh".to_string(), "-c".to_string(), command.to_string()]);
-        let child = tokio::process::Command::new(self.runtime.cli())
+        let mut child = tokio::process::Command::new(self.runtime.cli())
             .args(&args)
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
-            .stdin(std::process::Stdio::null())
+            .stdin(stdin_for(input))
             .spawn()
             .with_context(|| format!("failed to spawn {} exec", self.runtime.cli()))?;
+        feed_stdin(&mut child, input);
         match tokio::time::timeout(opts.timeout, child.wait_with_output()).await {
             Ok(Ok(output)) => Ok(SandboxExecResult {
                 stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
@@ -409,29 +877,68 @@ impl Sandbox for DockerSandbox {
             ),
         }
     }
+
+    fn interactive_shell(
+        &self,
+        session_key: &str,
+        opts: &SandboxExecOptions,
+    ) -> Result<ShellLaunch> {
+        let mut args = vec!["exec".to_string(), "-i".to_string(), "-t".to_string()];
+        if let Some(dir) = &opts.working_dir {
+            args.extend(["-w".to_string(), dir.display().to_string()]);
+        }
+        for env_file in &opts.env_files {
+            args.extend(["--env-file".to_string(), env_file.display().to_string()]);
+        }
+        let mut envs: Vec<_> = opts.envs.iter().collect();
+        envs.sort();
+        for (k, v) in envs {
+            args.extend(["-e".to_string(), format!("{k}={v}")]);
+        }
+        args.push(self.container_name(session_key));
+        args.extend([
+            "sh".to_string(),
+            "-c".to_string(),
+            INTERACTIVE_SHELL_SCRIPT.to_string(),
+        ]);
+        Ok(ShellLaunch {
+            program: self.runtime.cli().to_string(),
+            args,
+            envs: Vec::new(),
+            working_dir: None,
+            sandboxed: true,
+        })
+    }
 }
 
 pub struct SandboxRouter {
     config: SandboxConfig,
     backend: Arc<dyn Sandbox>,
+    egress: Arc<EgressState>,
     warned_missing_runtime: AtomicBool,
 }
 
 impl SandboxRouter {
     pub fn new(config: SandboxConfig, working_dir: &Path, extra_mounts: Vec<ExtraMount>) -> Self {
         let mount_dir = resolve_mount_dir(working_dir, &config);
+        let egress = Arc::new(EgressState::default());
         let backend: Arc<dyn Sandbox> = match resolve_runtime(config.backend) {
             Some(runtime) => Arc::new(DockerSandbox::new(
                 runtime,
                 config.clone(),
                 mount_dir,
                 extra_mounts,
+                egress.clone(),
             )),
+            None if config.backend == SandboxBackend::Native && native_available() => {
+                Arc::new(NativeSandbox::new(config.clone(), mount_dir, extra_mounts))
+            }
             None => Arc::new(NoSandbox),
         };
         Self {
             config,
             backend,
+            egress,
             warned_missing_runtime: AtomicBool::new(false),
         }
     }
@@ -441,10 +948,18 @@ impl SandboxRouter {
         Self {
             config,
             backend,
+            egress: Arc::new(EgressState::default()),
             warned_missing_runtime: AtomicBool::new(false),
         }
     }
 
+    /// Reports connections refused by the egress proxy to `sink`. Only the first
+    /// sink installed is used.
+    pub fn with_egress_audit(self, sink: Arc<dyn EgressAuditSink>) -> Self {
+        let _ = self.egress.audit.set(sink);
+        self
+    }
+
     pub fn mode(&self) -> SandboxMode {
         self.config.mode
     }
@@ -457,14 +972,11 @@ impl SandboxRouter {
         self.backend.is_real()
     }
 
-    pub async fn exec(
-        &self,
-        session_key: &str,
-        command: &str,
-        opts: &SandboxExecOptions,
-    ) -> Result<SandboxExecResult> {
+    /// Whether commands run in the sandbox backend (`false`: on the host). Fails
+    /// when the sandbox is enabled and required but no runtime is available.
+    pub fn routes_to_sandbox(&self) -> Result<bool> {
         if self.config.mode == SandboxMode::Off {
-            return exec_host_command(command, opts).await;
+            return Ok(false);
         }
         if !self.backend.is_real() {
             if self.config.require_runtime {
@@ -475,16 +987,113 @@ impl SandboxRouter {
                     "sandbox enabled but no container runtime available, falling back to host"
                 );
             }
-            return exec_host_command(command, opts).await;
+            return Ok(false);
         }
-        self.backend.ensure_ready(session_key).await?;
-        self.backend.exec(session_key, command, opts).await
+        Ok(true)
+    }
+
+    pub async fn exec(
+        &self,
+        session_key: &str,
+        command: &str,
+        opts: &SandboxExecOptions,
+    ) -> Result<SandboxExecResult> {
+        self.exec_with_input(session_key, command, &[], opts).await
+    }
+
+    /// Like [`SandboxRouter::exec`], with `input` written to the command's stdin.
+    pub async fn exec_with_input(
+        &self,
+        session_key: &str,
+        command: &str,
+        input: &[u8],
+        opts: &SandboxExecOptions,
+    ) -> Result<SandboxExecResult> {
+        if !self.routes_to_sandbox()? {
+            return exec_host_command_with_input(command, input, opts).await;
+        }
+        let egress = self.config.egress_profile(session_key, &opts.env_files);
+        self.backend.ensure_ready(session_key, &egress).await?;
+        self.backend
+            .exec_with_input(session_key, command, input, opts)
+            .await
+    }
+
+    /// Launch spec for a persistent interactive shell, with the same sandbox
+    /// selection and host fallback as [`SandboxRouter::exec`].
+    pub async fn interactive_shell(
+        &self,
+        session_key: &str,
+        opts: &SandboxExecOptions,
+    ) -> Result<ShellLaunch> {
+        if !self.routes_to_sandbox()? {
+            return Ok(host_interactive_shell(opts));
+        }
+        let egress = self.config.egress_profile(session_key, &opts.env_files);
+        self.backend.ensure_ready(session_key, &egress).await?;
+        self.backend.interactive_shell(session_key, opts)
     }
 }
 
+pub fn host_interactive_shell(opts: &SandboxExecOptions) -> ShellLaunch {
+    let mut envs = Vec::new();
+    for env_file in &opts.env_files {
+        if let Ok(content) = std::fs::read_to_string(env_file) {
+            let mut parsed: Vec<_> = crate::env_file::parse_dotenv(&content)
+                .into_iter()
+                .collect();
+            parsed.sort();
+            envs.extend(parsed);
+        }
+    }
+    let mut extra: Vec<_> = opts
+        .envs
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    extra.sort();
+    envs.extend(extra);
+    ShellLaunch {
+        program: "sh".to_string(),
+        args: vec!["-c".to_string(), INTERACTIVE_SHELL_SCRIPT.to_string()],
+        envs,
+        working_dir: opts.working_dir.clone(),
+        sandboxed: false,
+    }
+}
+
+/// Stdin for a spawned command: closed unless there is input to feed it.
+pub(crate) fn stdin_for(input: &[u8]) -> std::process::Stdio {
+    if input.is_empty() {
+        std::process::Stdio::null()
+    } else {
+        std::process::Stdio::piped()
+    }
+}
+
+/// Writes `input` to the child's piped stdin in the background, then closes it.
+pub(crate) fn feed_stdin(child: &mut tokio::process::Child, input: &[u8]) {
+    let Some(mut stdin) = child.stdin.take() else {
+        return;
+    };
+    let input = input.to_vec();
+    tokio::spawn(async move {
+        use tokio::io::AsyncWriteExt;
+        let _ = stdin.write_all(&input).await;
+    });
+}
+
 pub async fn exec_host_command(
     command: &str,
     opts: &SandboxExecOptions,
+) -> Result<SandboxExecResult> {
+    exec_host_command_with_input(command, &[], opts).await
+}
+
+pub async fn exec_host_command_with_input(
+    command: &str,
+    input: &[u8],
+    opts: &SandboxExecOptions,
 ) -> Result<SandboxExecResult> {
     let spec = shell_command(command);
     let mut cmd = build_command(&spec, opts.working_dir.as_deref());
@@ -500,8 +1109,9 @@ pub async fn exec_host_command(
     }
     cmd.stdout(std::process::Stdio::piped());
     cmd.stderr(std::process::Stdio::piped());
-    cmd.stdin(std::process::Stdio::null());
-    let child = cmd.spawn().context("failed to start shell command")?;
+    cmd.stdin(stdin_for(input));
+    let mut child = cmd.spawn().context("failed to start shell command")?;
+    feed_stdin(&mut child, input);
     match tokio::time::timeout(opts.timeout, child.wait_with_output()).await {
         Ok(Ok(output)) => Ok(SandboxExecResult {
             stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
@@ -683,6 +1293,22 @@ mod tests {
         assert!(name == "docker" || name == "podman" || name == "none");
     }
 
+    #[test]
+    fn test_router_selects_native_backend_when_available() {
+        let config = SandboxConfig {
+            backend: SandboxBackend::Native,
+            ..SandboxConfig::default()
+        };
+        let router = SandboxRouter::new(config, &std::env::temp_dir(), vec![]);
+        let expected = if native_available() { "native" } else { "none" };
+        assert_eq!(router.backend_name(), expected);
+        assert_eq!(
+            runtime_available_for_backend(SandboxBackend::Native),
+            native_available()
+        );
+        assert_eq!(selected_runtime_cli(SandboxBackend::Native), None);
+    }
+
     #[tokio::test]
     async fn test_router_falls_back_to_host_when_runtime_missing_and_not_required() {
         let cfg = SandboxConfig {
@@ -725,6 +1351,314 @@ mod tests {
             .contains("sandbox is enabled but no container runtime is available"));
     }
 
+    #[test]
+    fn test_docker_interactive_shell_attaches_tty_to_session_container() {
+        let sandbox = DockerSandbox::new(
+            ContainerRuntime::Docker,
+            SandboxConfig::default(),
+            PathBuf::from("/work"),
+            vec![],
+            Arc::new(EgressState::default()),
+        );
+        let opts = SandboxExecOptions {
+            timeout: Duration::from_secs(2),
+            working_dir: Some(PathBuf::from("/work/tmp")),
+            envs: HashMap::from([("PS1".to_string(), String::new())]),
+            env_files: vec![PathBuf::from("/skills/a/.env")],
+        };
+        let launch = sandbox.interactive_shell("web-7", &opts).unwrap();
+        assert_eq!(launch.program, "docker");
+        assert_eq!(
+            launch.args[..10],
+            [
+                "exec",
+                "-i",
+                "-t",
+                "-w",
+                "/work/tmp",
+                "--env-file",
+                "/skills/a/.env",
+                "-e",
+                "PS1=",
+                "microclaw-sandbox-web-7",
+            ]
+        );
+        assert_eq!(launch.args.last().unwrap(), INTERACTIVE_SHELL_SCRIPT);
+        assert!(launch.envs.is_empty());
+        assert!(launch.sandboxed);
+    }
+
+    #[tokio::test]
+    async fn test_docker_egress_args_label_network_mode() {
+        let sandbox = DockerSandbox::new(
+            ContainerRuntime::Docker,
+            SandboxConfig::default(),
+            PathBuf::from("/work"),
+            vec![],
+            Arc::new(EgressState::default()),
+        );
+        let args = sandbox
+            .egress_args("web-7", &EgressProfile::new(EgressMode::None))
+            .await
+            .unwrap();
+        assert_eq!(args, ["--label", "microclaw.egress=none", "--network=none"]);
+        let args = sandbox
+            .egress_args("web-7", &EgressProfile::new(EgressMode::Full))
+            .await
+            .unwrap();
+        assert_eq!(args, ["--label", "microclaw.egress=full"]);
+    }
+
+    #[test]
+    fn test_container_reuse_never_keeps_a_wider_network() {
+        use ContainerReuse::{Recreate, Reuse, ThroughProxy};
+        let (none, allowlist, full) = (EgressMode::None, EgressMode::Allowlist, EgressMode::Full);
+        for (current, requested, expected) in [
+            (none, none, Reuse),
+            (full, full, Reuse),
+            (allowlist, allowlist, ThroughProxy),
+            (allowlist, none, ThroughProxy),
+            (full, none, Recreate),
+            (full, allowlist, Recreate),
+            (none, allowlist, Recreate),
+            (none, full, Recreate),
+            (allowlist, full, Recreate),
+        ] {
+            assert_eq!(
+                container_reuse(current, requested),
+                expected,
+                "{current:?} -> {requested:?}"
+            );
+        }
+        for mode in [none, allowlist, full] {
+            assert_eq!(EgressMode::parse(mode.as_str()), Some(mode));
+        }
+        assert_eq!(EgressMode::parse("<no value>"), None);
+    }
+
+    /// Needs a container runtime; skipped otherwise.
+    #[tokio::test]
+    async fn test_full_container_is_recreated_for_none() {
+        if !runtime_available("docker") {
+            return;
+        }
+        let prefix = format!(
+            "microclaw-reuse-test-{}",
+            &uuid::Uuid::new_v4().simple().to_string()[..8]
+        );
+        let config = SandboxConfig {
+            container_prefix: prefix.clone(),
+            ..SandboxConfig::default()
+        };
+        let sandbox = DockerSandbox::new(
+            ContainerRuntime::Docker,
+            config,
+            std::env::temp_dir(),
+            vec![],
+            Arc::new(EgressState::default()),
+        );
+        let name = sandbox.container_name("web-1");
+        let network_of = || async {
+            let out = tokio::process::Command::new("docker")
+                .args(["inspect", "--format", "{{.HostConfig.NetworkMode}}", &name])
+                .output()
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&out.stdout).trim().to_string()
+        };
+        let result = async {
+            sandbox
+                .ensure_ready("web-1", &EgressProfile::new(EgressMode::Full))
+                .await?;
+            let full = network_of().await;
+            sandbox
+                .ensure_ready("web-1", &EgressProfile::new(EgressMode::None))
+                .await?;
+            anyhow::Ok((full, network_of().await))
+        }
+        .await;
+        let _ = tokio::process::Command::new("docker")
+            .args(["rm", "-f", &name])
+            .output()
+            .await;
+        let (full, none) = match result {
+            Ok(networks) => networks,
+            Err(e) => {
+                eprintln!("skipping: docker unusable: {e:#}");
+                return;
+            }
+        };
+        assert_ne!(full, "none");
+        assert_eq!(none, "none");
+    }
+
+    #[test]
+    fn test_host_firewall_only_admits_the_proxy_port() {
+        let network = parse_egress_network(
+            "0123456789abcdef0123 fd00::/64 fd00::1 172.30.0.0/16 172.30.0.1 \n",
+        )
+        .unwrap();
+        assert_eq!(
+            network,
+            EgressNetwork {
+                id: "0123456789abcdef0123".into(),
+                subnet: "172.30.0.0/16".into(),
+                gateway: Ipv4Addr::new(172, 30, 0, 1),
+                subnets_v6: vec!["fd00::/64".into()],
+            }
+        );
+        assert_eq!(parse_egress_network("0123456789ab fd00::/64 fd00::1"), None);
+
+        let chain = "MICROCLAW-0123456789ab";
+        let [v4, v6] = &host_firewalls(&network, "172.30.0.1:41000".parse().unwrap())[..] else {
+            panic!("expected an IPv4 and an IPv6 firewall");
+        };
+        assert_eq!(v4.cli, "iptables");
+        assert_eq!(
+            v4.chain_commands(),
+            [
+                args(&["-w", "-F", chain]),
+                args(&[
+                    "-w",
+                    "-A",
+                    chain,
+                    "-d",
+                    "172.30.0.1",
+                    "-p",
+                    "tcp",
+                    "--dport",
+                    "41000",
+                    "-j",
+                    "ACCEPT"
+                ]),
+                args(&["-w", "-A", chain, "-j", "REJECT"]),
+            ]
+        );
+        assert_eq!(
+            v4.removal_commands(),
+            [
+                args(&["-w", "-D", "INPUT", "-s", "172.30.0.0/16", "-j", chain]),
+                args(&["-w", "-F", chain]),
+                args(&["-w", "-X", chain]),
+            ]
+        );
+        // The proxy is IPv4-only, so IPv6 gets nothing through to the host.
+        assert_eq!(v6.cli, "ip6tables");
+        assert_eq!(
+            v6.chain_commands(),
+            [
+                args(&["-w", "-F", chain]),
+                args(&["-w", "-A", chain, "-j", "REJECT"]),
+            ]
+        );
+        assert_eq!(v6.subnets, ["fd00::/64"]);
+
+        let ipv4_only = parse_egress_network("0123456789ab 172.30.0.0/16 172.30.0.1").unwrap();
+        assert_eq!(
+            host_firewalls(&ipv4_only, "172.30.0.1:41000".parse().unwrap()).len(),
+            1
+        );
+    }
+
+    /// Needs a container runtime and permission to run iptables; skipped otherwise.
+    #[tokio::test]
+    async fn test_allowlist_container_cannot_reach_host_outside_proxy() {
+        if !runtime_available("docker") {
+            return;
+        }
+        let prefix = format!(
+            "microclaw-egress-test-{}",
+            &uuid::Uuid::new_v4().simple().to_string()[..8]
+        );
+        let config = SandboxConfig {
+            container_prefix: prefix.clone(),
+            ..SandboxConfig::default()
+        };
+        let sandbox = DockerSandbox::new(
+            ContainerRuntime::Docker,
+            config,
+            std::env::temp_dir(),
+            vec![],
+            Arc::new(EgressState::default()),
+        );
+        let mut profile = EgressProfile::new(EgressMode::Allowlist);
+        profile.allowed_domains = vec!["example.com".into()];
+        let cleanup = || async {
+            let _ = tokio::process::Command::new("docker")
+                .args(["rm", "-f", &format!("{prefix}-web-1")])
+                .output()
+                .await;
+            remove_host_firewalls().await;
+            let _ = tokio::process::Command::new("docker")
+                .args(["network", "rm", &format!("{prefix}-egress")])
+                .output()
+                .await;
+        };
+        if let Err(e) = sandbox.ensure_ready("web-1", &profile).await {
+            cleanup().await;
+            eprintln!("skipping: docker unusable: {e:#}");
+            return;
+        }
+        if sandbox.egress.firewall_degraded.load(Ordering::Relaxed) {
+            // Without iptables access allowlist mode runs degraded.
+            cleanup().await;
+            eprintln!("skipping: no permission to run iptables");
+            return;
+        }
+        let network = sandbox.egress_network_info().await.unwrap().unwrap();
+        let jump_present = || async {
+            tokio::process::Command::new("iptables")
+                .args([
+                    "-w",
+                    "-C",
+                    "INPUT",
+                    "-s",
+                    &network.subnet,
+                    "-j",
+                    &host_firewall_chain(&network),
+                ])
+                .output()
+                .await
+                .is_ok_and(|out| out.status.success())
+        };
+
+        let proxy = sandbox.egress_proxy().await.unwrap().local_addr();
+        let host_service = tokio::net::TcpListener::bind("0.0.0.0:0").await.unwrap();
+        let opts = SandboxExecOptions {
+            timeout: Duration::from_secs(20),
+            working_dir: None,
+            envs: HashMap::new(),
+            env_files: Vec::new(),
+        };
+        let connect =
+            |port: u16| format!("timeout 5 bash -c 'exec 3<>/dev/tcp/{}/{port}'", proxy.ip());
+        let blocked = sandbox
+            .exec_with_input(
+                "web-1",
+                &connect(host_service.local_addr().unwrap().port()),
+                &[],
+                &opts,
+            )
+            .await;
+        let via_proxy = sandbox
+            .exec_with_input("web-1", &connect(proxy.port()), &[], &opts)
+            .await;
+        let installed = jump_present().await;
+        cleanup().await;
+        assert!(installed);
+        assert!(!jump_present().await, "teardown must remove the INPUT jump");
+        assert_ne!(blocked.unwrap().exit_code, 0);
+        assert_eq!(via_proxy.unwrap().exit_code, 0);
+    }
+
+    #[test]
+    fn test_sandbox_config_egress_profile_follows_no_network() {
+        let mut config = SandboxConfig::default();
+        assert_eq!(config.egress_profile("web-1", &[]).mode, EgressMode::None);
+        config.no_network = false;
+        assert_eq!(config.egress_profile("web-1", &[]).mode, EgressMode::Full);
+    }
+
     #[test]
     fn test_pick_runtime_matrix() {
         assert_eq!(
@@ -743,5 +1677,6 @@ mod tests {
             Some(ContainerRuntime::Podman)
         );
         assert_eq!(pick_runtime(SandboxBackend::Podman, true, false), None);
+        assert_eq!(pick_runtime(SandboxBackend::Native, true, true), None);
     }
 }
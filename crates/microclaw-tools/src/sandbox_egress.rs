@@ -0,0 +1,684 @@
+//! Network egress profiles for sandbox containers.
+//!
+//! A profile is `none` (no network at all), `full` (the container runtime's default
+//! network) or `allowlist`. Allowlisted containers are attached to an internal network
+//! with no route out and reach the internet only through [`EgressProxy`], a forward
+//! proxy embedded in MicroClaw that checks every `CONNECT` and plain HTTP request
+//! against the container's allowed domains. Refused connections are reported to an
+//! [`EgressAuditSink`].
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::web_fetch::is_private_network_addr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EgressMode {
+    None,
+    Allowlist,
+    Full,
+}
+
+impl EgressMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EgressMode::None => "none",
+            EgressMode::Allowlist => "allowlist",
+            EgressMode::Full => "full",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(EgressMode::None),
+            "allowlist" => Some(EgressMode::Allowlist),
+            "full" => Some(EgressMode::Full),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EgressProfile {
+    pub mode: EgressMode,
+    /// Domains reachable in `allowlist` mode. `example.com` also covers its
+    /// subdomains; `*.example.com` covers only the subdomains.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+}
+
+impl EgressProfile {
+    pub fn new(mode: EgressMode) -> Self {
+        Self {
+            mode,
+            allowed_domains: Vec::new(),
+        }
+    }
+
+    fn normalize(&mut self) {
+        let mut domains: Vec<String> = self
+            .allowed_domains
+            .iter()
+            .map(|d| d.trim().trim_end_matches('.').to_ascii_lowercase())
+            .filter(|d| !d.is_empty())
+            .collect();
+        domains.sort();
+        domains.dedup();
+        self.allowed_domains = domains;
+    }
+
+    /// Widens `self` so it also permits everything `other` permits.
+    fn merge(&mut self, other: &EgressProfile) {
+        self.mode = self.mode.max(other.mode);
+        self.allowed_domains
+            .extend(other.allowed_domains.iter().cloned());
+        self.normalize();
+    }
+
+    pub fn allows_host(&self, host: &str) -> bool {
+        match self.mode {
+            EgressMode::None => false,
+            EgressMode::Full => true,
+            EgressMode::Allowlist => {
+                let host = host.trim_end_matches('.').to_ascii_lowercase();
+                self.allowed_domains
+                    .iter()
+                    .any(|pattern| match pattern.strip_prefix("*.") {
+                        Some(parent) => host.ends_with(&format!(".{parent}")),
+                        None => host == *pattern || host.ends_with(&format!(".{pattern}")),
+                    })
+            }
+        }
+    }
+
+    /// Whether `host` is an allowlisted IP literal. Names never grant access to the
+    /// private or internal addresses they resolve to; only literal entries do.
+    fn allows_private_literal(&self, host: &str) -> bool {
+        self.mode == EgressMode::Full
+            || (host.parse::<IpAddr>().is_ok() && self.allowed_domains.iter().any(|d| d == host))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxEgressConfig {
+    /// Profile for chats without their own entry. Unset follows `sandbox.no_network`.
+    #[serde(default)]
+    pub default: Option<EgressProfile>,
+    /// Profiles keyed by chat session (`<channel>-<chat_id>`, e.g. `telegram-123`).
+    #[serde(default)]
+    pub chats: HashMap<String, EgressProfile>,
+    /// Profiles keyed by skill directory name. They widen the chat's profile while
+    /// that skill's env file is active in the chat.
+    #[serde(default)]
+    pub skills: HashMap<String, EgressProfile>,
+}
+
+impl SandboxEgressConfig {
+    pub fn normalize(&mut self) {
+        if let Some(profile) = self.default.as_mut() {
+            profile.normalize();
+        }
+        self.chats.values_mut().for_each(EgressProfile::normalize);
+        self.skills.values_mut().for_each(EgressProfile::normalize);
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let profiles = self
+            .default
+            .iter()
+            .map(|p| ("default".to_string(), p))
+            .chain(self.chats.iter().map(|(k, p)| (format!("chats.{k}"), p)))
+            .chain(self.skills.iter().map(|(k, p)| (format!("skills.{k}"), p)));
+        for (name, profile) in profiles {
+            for domain in &profile.allowed_domains {
+                let bare = domain.strip_prefix("*.").unwrap_or(domain);
+                let malformed =
+                    bare.contains(['/', ':', '*', ' ', '@']) && bare.parse::<IpAddr>().is_err();
+                if bare.is_empty() || malformed {
+                    return Err(format!(
+                        "sandbox.egress.{name}.allowed_domains: '{domain}' is not a domain name or IP address"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether any profile puts containers on the allowlist network.
+    pub fn uses_allowlist(&self) -> bool {
+        self.default
+            .iter()
+            .chain(self.chats.values())
+            .chain(self.skills.values())
+            .any(|profile| profile.mode == EgressMode::Allowlist)
+    }
+
+    /// The profile for `session_key`: its chat entry (or the default), widened by the
+    /// profiles of skills whose env files are active.
+    pub fn resolve(
+        &self,
+        session_key: &str,
+        no_network: bool,
+        env_files: &[PathBuf],
+    ) -> EgressProfile {
+        let mut profile = self
+            .chats
+            .get(session_key)
+            .or(self.default.as_ref())
+            .cloned()
+            .unwrap_or_else(|| {
+                EgressProfile::new(if no_network {
+                    EgressMode::None
+                } else {
+                    EgressMode::Full
+                })
+            });
+        for env_file in env_files {
+            let skill = env_file
+                .parent()
+                .and_then(|dir| dir.file_name())
+                .and_then(|name| name.to_str());
+            if let Some(skill_profile) = skill.and_then(|name| self.skills.get(name)) {
+                profile.merge(skill_profile);
+            }
+        }
+        profile
+    }
+}
+
+/// A connection the egress proxy refused.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EgressBlocked {
+    pub session_key: String,
+    pub host: String,
+    pub port: u16,
+    pub reason: String,
+}
+
+pub trait EgressAuditSink: Send + Sync {
+    fn record_blocked(&self, event: &EgressBlocked);
+}
+
+const MAX_REQUEST_HEAD_BYTES: usize = 16 * 1024;
+const CLIENT_HEAD_TIMEOUT: Duration = Duration::from_secs(30);
+const UPSTREAM_CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const PROXY_USER: &str = "microclaw";
+
+fn random_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+#[derive(Clone)]
+struct ProxyClient {
+    session_key: String,
+    profile: EgressProfile,
+}
+
+/// HTTP(S) forward proxy enforcing per-session allowlists. Clients identify
+/// themselves with the token from [`EgressProxy::register`] as the proxy password.
+pub struct EgressProxy {
+    addr: SocketAddr,
+    clients: RwLock<HashMap<String, ProxyClient>>,
+    audit: Option<Arc<dyn EgressAuditSink>>,
+}
+
+impl EgressProxy {
+    /// Binds the proxy and starts accepting connections. The accept loop ends once
+    /// the returned proxy is dropped.
+    pub async fn bind(
+        addr: SocketAddr,
+        audit: Option<Arc<dyn EgressAuditSink>>,
+    ) -> Result<Arc<Self>> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("failed to bind egress proxy on {addr}"))?;
+        let proxy = Arc::new(Self {
+            addr: listener.local_addr()?,
+            clients: RwLock::new(HashMap::new()),
+            audit,
+        });
+        tokio::spawn(accept_loop(listener, Arc::downgrade(&proxy)));
+        tracing::info!(addr = %proxy.addr, "sandbox egress proxy listening");
+        Ok(proxy)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Registers `session_key` with `profile` and returns its token, replacing any
+    /// earlier registration of the session.
+    pub fn register(&self, session_key: &str, profile: EgressProfile) -> String {
+        let token = random_token();
+        if let Ok(mut clients) = self.clients.write() {
+            clients.retain(|_, c| c.session_key != session_key);
+            clients.insert(
+                token.clone(),
+                ProxyClient {
+                    session_key: session_key.to_string(),
+                    profile,
+                },
+            );
+        }
+        token
+    }
+
+    /// Replaces the profile of an already registered session. Returns false when
+    /// the session has no registration, e.g. after a restart.
+    pub fn update(&self, session_key: &str, profile: &EgressProfile) -> bool {
+        let Ok(mut clients) = self.clients.write() else {
+            return false;
+        };
+        match clients.values_mut().find(|c| c.session_key == session_key) {
+            Some(client) => {
+                client.profile = profile.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn proxy_url(&self, token: &str) -> String {
+        format!("http://{PROXY_USER}:{token}@{}", self.addr)
+    }
+
+    fn client_for(&self, token: &str) -> Option<ProxyClient> {
+        self.clients.read().ok()?.get(token).cloned()
+    }
+
+    fn report_blocked(&self, session_key: &str, host: &str, port: u16, reason: &str) {
+        tracing::warn!(
+            session = session_key,
+            host,
+            port,
+            reason,
+            "sandbox egress blocked"
+        );
+        if let Some(audit) = &self.audit {
+            audit.record_blocked(&EgressBlocked {
+                session_key: session_key.to_string(),
+                host: host.to_string(),
+                port,
+                reason: reason.to_string(),
+            });
+        }
+    }
+
+    async fn handle(&self, mut client: TcpStream) -> Result<()> {
+        let (head, leftover) =
+            match tokio::time::timeout(CLIENT_HEAD_TIMEOUT, read_head(&mut client)).await {
+                Ok(Ok(read)) => read,
+                Ok(Err(e)) => {
+                    respond(&mut client, "400 Bad Request").await;
+                    return Err(e);
+                }
+                Err(_) => return Ok(()),
+            };
+        let Some(request) = ProxyRequest::parse(&head) else {
+            respond(&mut client, "400 Bad Request").await;
+            return Ok(());
+        };
+        let Some(proxy_client) = request.token.as_deref().and_then(|t| self.client_for(t)) else {
+            respond(
+                &mut client,
+                "407 Proxy Authentication Required\r\nProxy-Authenticate: Basic realm=\"microclaw\"",
+            )
+            .await;
+            return Ok(());
+        };
+        let session = proxy_client.session_key.as_str();
+        let (host, port) = (request.host.as_str(), request.port);
+
+        if !proxy_client.profile.allows_host(host) {
+            self.report_blocked(session, host, port, "host not in egress allowlist");
+            respond(&mut client, "403 Forbidden").await;
+            return Ok(());
+        }
+        let addrs: Vec<SocketAddr> = match tokio::net::lookup_host((host, port)).await {
+            Ok(addrs) => addrs.collect(),
+            Err(_) => Vec::new(),
+        };
+        if addrs.is_empty() {
+            respond(&mut client, "502 Bad Gateway").await;
+            return Ok(());
+        }
+        if !proxy_client.profile.allows_private_literal(host)
+            && addrs.iter().any(|a| is_private_network_addr(a.ip()))
+        {
+            self.report_blocked(
+                session,
+                host,
+                port,
+                "resolves to a private or internal address",
+            );
+            respond(&mut client, "403 Forbidden").await;
+            return Ok(());
+        }
+        let mut upstream =
+            match tokio::time::timeout(UPSTREAM_CONNECT_TIMEOUT, TcpStream::connect(&addrs[..]))
+                .await
+            {
+                Ok(Ok(stream)) => stream,
+                _ => {
+                    respond(&mut client, "502 Bad Gateway").await;
+                    return Ok(());
+                }
+            };
+
+        if request.connect {
+            client
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .await?;
+        } else {
+            upstream
+                .write_all(request.origin_form_head().as_bytes())
+                .await?;
+        }
+        upstream.write_all(&leftover).await?;
+        tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+        Ok(())
+    }
+}
+
+async fn accept_loop(listener: TcpListener, proxy: Weak<EgressProxy>) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let Some(proxy) = proxy.upgrade() else {
+            break;
+        };
+        tokio::spawn(async move {
+            if let Err(e) = proxy.handle(stream).await {
+                tracing::debug!("egress proxy connection ended: {e}");
+            }
+        });
+    }
+}
+
+async fn respond(client: &mut TcpStream, status: &str) {
+    let response = format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    let _ = client.write_all(response.as_bytes()).await;
+}
+
+/// Reads up to the end of the request head, returning the head and any bytes read
+/// past it.
+async fn read_head(client: &mut TcpStream) -> Result<(String, Vec<u8>)> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 2048];
+    loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let leftover = buf.split_off(end + 4);
+            return Ok((String::from_utf8_lossy(&buf).into_owned(), leftover));
+        }
+        if buf.len() > MAX_REQUEST_HEAD_BYTES {
+            anyhow::bail!("request head too large");
+        }
+        let n = client.read(&mut chunk).await?;
+        if n == 0 {
+            anyhow::bail!("connection closed before request head");
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+struct ProxyRequest {
+    connect: bool,
+    method: String,
+    host: String,
+    port: u16,
+    path: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    token: Option<String>,
+}
+
+impl ProxyRequest {
+    fn parse(head: &str) -> Option<Self> {
+        let mut lines = head.split("\r\n");
+        let mut parts = lines.next()?.split_whitespace();
+        let method = parts.next()?.to_string();
+        let target = parts.next()?;
+        let version = parts.next()?.to_string();
+        let headers: Vec<(String, String)> = lines
+            .filter(|l| !l.is_empty())
+            .filter_map(|l| {
+                let (name, value) = l.split_once(':')?;
+                Some((name.trim().to_string(), value.trim().to_string()))
+            })
+            .collect();
+        let token = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("proxy-authorization"))
+            .and_then(|(_, value)| value.strip_prefix("Basic "))
+            .and_then(|b64| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(b64.trim())
+                    .ok()
+            })
+            .and_then(|raw| String::from_utf8(raw).ok())
+            .and_then(|creds| creds.split_once(':').map(|(_, pass)| pass.to_string()));
+
+        let connect = method.eq_ignore_ascii_case("CONNECT");
+        let (authority, path, default_port) = if connect {
+            (target, "/".to_string(), 443)
+        } else {
+            let rest = target.strip_prefix("http://")?;
+            let (authority, path) = match rest.find('/') {
+                Some(i) => (&rest[..i], rest[i..].to_string()),
+                None => (rest, "/".to_string()),
+            };
+            (authority, path, 80)
+        };
+        let (host, port) = split_authority(authority, default_port)?;
+        Some(Self {
+            connect,
+            method,
+            host,
+            port,
+            path,
+            version,
+            headers,
+            token,
+        })
+    }
+
+    /// The request head as sent to the origin server: origin-form target and no
+    /// proxy headers.
+    fn origin_form_head(&self) -> String {
+        let mut head = format!("{} {} {}\r\n", self.method, self.path, self.version);
+        for (name, value) in &self.headers {
+            let lower = name.to_ascii_lowercase();
+            if lower == "proxy-authorization" || lower == "proxy-connection" {
+                continue;
+            }
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str("\r\n");
+        head
+    }
+}
+
+fn split_authority(authority: &str, default_port: u16) -> Option<(String, u16)> {
+    let authority = authority.rsplit('@').next()?;
+    let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+        let (host, after) = rest.split_once(']')?;
+        let port = match after.strip_prefix(':') {
+            Some(p) => p.parse().ok()?,
+            None => default_port,
+        };
+        (host.to_string(), port)
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host.to_string(), port.parse().ok()?),
+            None => (authority.to_string(), default_port),
+        }
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_ascii_lowercase(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingAudit(Mutex<Vec<EgressBlocked>>);
+
+    impl EgressAuditSink for RecordingAudit {
+        fn record_blocked(&self, event: &EgressBlocked) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    fn allowlist(domains: &[&str]) -> EgressProfile {
+        let mut profile = EgressProfile {
+            mode: EgressMode::Allowlist,
+            allowed_domains: domains.iter().map(|d| d.to_string()).collect(),
+        };
+        profile.normalize();
+        profile
+    }
+
+    #[test]
+    fn test_allowlist_matches_domains_and_subdomains() {
+        let profile = allowlist(&["Example.com.", "*.pypi.org"]);
+        assert!(profile.allows_host("example.com"));
+        assert!(profile.allows_host("api.example.com"));
+        assert!(!profile.allows_host("badexample.com"));
+        assert!(profile.allows_host("files.pypi.org"));
+        assert!(!profile.allows_host("pypi.org"));
+        assert!(!EgressProfile::new(EgressMode::None).allows_host("example.com"));
+        assert!(EgressProfile::new(EgressMode::Full).allows_host("example.com"));
+    }
+
+    #[test]
+    fn test_resolve_prefers_chat_profile_and_widens_with_skills() {
+        let mut config = SandboxEgressConfig {
+            default: None,
+            chats: HashMap::from([("web-1".to_string(), allowlist(&["github.com"]))]),
+            skills: HashMap::from([("pip-tools".to_string(), allowlist(&["pypi.org"]))]),
+        };
+        config.normalize();
+        assert_eq!(config.resolve("web-2", true, &[]).mode, EgressMode::None);
+        assert_eq!(config.resolve("web-2", false, &[]).mode, EgressMode::Full);
+        assert_eq!(
+            config.resolve("web-1", true, &[]),
+            allowlist(&["github.com"])
+        );
+
+        let env_files = [PathBuf::from("/skills/pip-tools/.env")];
+        assert_eq!(
+            config.resolve("web-1", true, &env_files),
+            allowlist(&["github.com", "pypi.org"])
+        );
+        assert_eq!(
+            config.resolve("web-2", true, &env_files),
+            allowlist(&["pypi.org"])
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_urls_in_allowlist() {
+        let mut config = SandboxEgressConfig {
+            default: Some(allowlist(&["https://example.com/"])),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        config.default = Some(allowlist(&["*.example.com", "10.0.0.5", "::1"]));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_parse_proxy_requests() {
+        let auth = base64::engine::general_purpose::STANDARD.encode("microclaw:tok");
+        let head = format!(
+            "GET http://Example.com:8080/a?b=1 HTTP/1.1\r\nHost: example.com:8080\r\nProxy-Authorization: Basic {auth}\r\nProxy-Connection: keep-alive\r\n\r\n"
+        );
+        let request = ProxyRequest::parse(&head).unwrap();
+        assert!(!request.connect);
+        assert_eq!((request.host.as_str(), request.port), ("example.com", 8080));
+        assert_eq!(request.token.as_deref(), Some("tok"));
+        assert_eq!(
+            request.origin_form_head(),
+            "GET /a?b=1 HTTP/1.1\r\nHost: example.com:8080\r\n\r\n"
+        );
+
+        let request = ProxyRequest::parse("CONNECT [::1]:8443 HTTP/1.1\r\n\r\n").unwrap();
+        assert!(request.connect);
+        assert_eq!((request.host.as_str(), request.port), ("::1", 8443));
+        assert!(request.token.is_none());
+        assert!(ProxyRequest::parse("GET https://example.com/ HTTP/1.1\r\n\r\n").is_none());
+    }
+
+    async fn proxy_exchange(proxy: &EgressProxy, token: &str, request_line: &str) -> String {
+        let auth = base64::engine::general_purpose::STANDARD.encode(format!("microclaw:{token}"));
+        let mut stream = TcpStream::connect(proxy.local_addr()).await.unwrap();
+        stream
+            .write_all(
+                format!("{request_line}\r\nProxy-Authorization: Basic {auth}\r\n\r\n").as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut buf = vec![0u8; 256];
+        let n = stream.read(&mut buf).await.unwrap();
+        String::from_utf8_lossy(&buf[..n]).into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_proxy_tunnels_allowed_hosts_and_audits_blocked_ones() {
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_port = upstream.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = upstream.accept().await.unwrap();
+            stream.write_all(b"hello from upstream").await.unwrap();
+        });
+
+        let audit = Arc::new(RecordingAudit::default());
+        let proxy = EgressProxy::bind("127.0.0.1:0".parse().unwrap(), Some(audit.clone()))
+            .await
+            .unwrap();
+        let token = proxy.register("web-9", allowlist(&["127.0.0.1", "localhost"]));
+
+        let reply = proxy_exchange(
+            &proxy,
+            &token,
+            &format!("CONNECT 127.0.0.1:{upstream_port} HTTP/1.1"),
+        )
+        .await;
+        assert!(reply.starts_with("HTTP/1.1 200"), "{reply}");
+
+        let reply = proxy_exchange(&proxy, &token, "CONNECT example.com:443 HTTP/1.1").await;
+        assert!(reply.starts_with("HTTP/1.1 403"), "{reply}");
+        // An allowlisted name must not reach loopback through DNS.
+        let reply = proxy_exchange(&proxy, &token, "CONNECT localhost:443 HTTP/1.1").await;
+        assert!(reply.starts_with("HTTP/1.1 403"), "{reply}");
+        let reply = proxy_exchange(&proxy, "wrong", "CONNECT example.com:443 HTTP/1.1").await;
+        assert!(reply.starts_with("HTTP/1.1 407"), "{reply}");
+
+        let events = audit.0.lock().unwrap().clone();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].session_key, "web-9");
+        assert_eq!(
+            (events[0].host.as_str(), events[0].port),
+            ("example.com", 443)
+        );
+        assert_eq!(events[1].host, "localhost");
+
+        assert!(proxy.update("web-9", &allowlist(&["example.org"])));
+        assert!(!proxy.update("web-10", &allowlist(&[])));
+    }
+}
@@ -0,0 +1,367 @@
+//! File access inside the sandbox, for the file tools.
+//!
+//! When the sandbox is on, `read_file`, `write_file`, `edit_file`, `glob` and `grep`
+//! see the same filesystem as `bash`. Every operation is a small shell script run
+//! through [`SandboxRouter::exec_with_input`], and path checks use paths as the
+//! sandbox resolves them, not as the host would.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+
+use crate::path_guard;
+use crate::sandbox::{SandboxExecOptions, SandboxExecResult, SandboxRouter};
+
+const FS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Files per `read_many` round trip.
+const READ_BATCH: usize = 64;
+
+/// What a path points at inside the sandbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Dir,
+    Missing,
+}
+
+/// A path as the sandbox sees it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedPath {
+    pub kind: EntryKind,
+    /// The requested path, made absolute.
+    pub path: String,
+    /// `path` with symlinks and `..` resolved inside the sandbox.
+    pub resolved: String,
+    /// Size in bytes, for regular files.
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListOptions {
+    /// List regular files only (otherwise directories too).
+    pub files_only: bool,
+    /// Skip dot entries, `node_modules` and `target` without descending into them.
+    pub skip_hidden: bool,
+    /// Stop after this many entries.
+    pub limit: usize,
+}
+
+/// The sandbox's view of the filesystem for one session.
+pub struct SandboxFs {
+    router: Arc<SandboxRouter>,
+    session_key: String,
+    /// Absolute working dir of the tool call; relative paths are resolved against it.
+    working_dir: PathBuf,
+    env_files: Vec<PathBuf>,
+}
+
+impl SandboxFs {
+    /// `None` when the router runs commands on the host, in which case file tools
+    /// use the host filesystem directly. Relative paths are taken relative to
+    /// `working_dir`, the tool's resolved working directory.
+    pub fn for_session(
+        router: &Arc<SandboxRouter>,
+        session_key: &str,
+        working_dir: &Path,
+        env_files: Vec<PathBuf>,
+    ) -> Result<Option<Self>> {
+        if !router.routes_to_sandbox()? {
+            return Ok(None);
+        }
+        // Working directories are mounted at their absolute host paths.
+        let working_dir = std::path::absolute(working_dir)
+            .with_context(|| format!("invalid working directory {}", working_dir.display()))?;
+        Ok(Some(Self {
+            router: router.clone(),
+            session_key: session_key.to_string(),
+            working_dir,
+            env_files,
+        }))
+    }
+
+    async fn run(&self, script: &str, input: &[u8]) -> Result<SandboxExecResult> {
+        let opts = SandboxExecOptions {
+            timeout: FS_TIMEOUT,
+            working_dir: None,
+            envs: Default::default(),
+            env_files: self.env_files.clone(),
+        };
+        self.router
+            .exec_with_input(&self.session_key, script, input, &opts)
+            .await
+    }
+
+    async fn run_ok(&self, script: &str, input: &[u8]) -> Result<String> {
+        let out = self.run(script, input).await?;
+        if out.exit_code != 0 {
+            let msg = out.stderr.trim();
+            if msg.is_empty() {
+                bail!("command exited with code {}", out.exit_code);
+            }
+            bail!("{msg}");
+        }
+        Ok(out.stdout)
+    }
+
+    /// Absolute form of a tool path, relative to the tool's working dir.
+    fn absolute(&self, path: &str) -> String {
+        crate::runtime::resolve_tool_path(&self.working_dir, path)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Resolve `path` inside the sandbox and apply the path guard to the result.
+    pub async fn check_path(&self, path: &str) -> Result<EntryKind, String> {
+        let resolved = self.resolve(path).await?;
+        path_guard::check_sandbox_path(&resolved.path, &resolved.resolved)?;
+        Ok(resolved.kind)
+    }
+
+    /// What `path` is inside the sandbox and where it leads, without checking it.
+    pub async fn resolve(&self, path: &str) -> Result<ResolvedPath, String> {
+        let path = self.absolute(path);
+        let script = format!(
+            "p={}\n\
+             if [ -d \"$p\" ]; then echo d; elif [ -e \"$p\" ]; then echo f; else echo -; fi\n\
+             if [ -f \"$p\" ]; then wc -c < \"$p\" 2>/dev/null || echo -; else echo -; fi\n\
+             realpath -m -- \"$p\" 2>/dev/null || readlink -f -- \"$p\" 2>/dev/null || printf '%s\\n' \"$p\"",
+            quote(&path)
+        );
+        let out = self
+            .run_ok(&script, &[])
+            .await
+            .map_err(|e| format!("Failed to resolve '{path}' in sandbox: {e}"))?;
+        let mut lines = out.lines();
+        let kind = match lines.next() {
+            Some("d") => EntryKind::Dir,
+            Some("f") => EntryKind::File,
+            _ => EntryKind::Missing,
+        };
+        let size = lines.next().and_then(|l| l.trim().parse().ok());
+        let resolved = lines.next().unwrap_or(&path).to_string();
+        Ok(ResolvedPath {
+            kind,
+            path,
+            resolved,
+            size,
+        })
+    }
+
+    pub async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let out = self
+            .run_ok(&format!("base64 < {}", quote(&self.absolute(path))), &[])
+            .await?;
+        decode(&out)
+    }
+
+    pub async fn read_to_string(&self, path: &str) -> Result<String> {
+        String::from_utf8(self.read(path).await?)
+            .map_err(|_| anyhow!("stream did not contain valid UTF-8"))
+    }
+
+    /// Write `contents` to `path`, creating parent directories as needed.
+    pub async fn write(&self, path: &str, contents: &[u8]) -> Result<()> {
+        let path = self.absolute(path);
+        let parent = Path::new(&path)
+            .parent()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "/".to_string());
+        let script = format!("mkdir -p -- {} && cat > {}", quote(&parent), quote(&path));
+        self.run_ok(&script, contents).await?;
+        Ok(())
+    }
+
+    pub async fn remove(&self, path: &str) -> Result<()> {
+        self.run_ok(&format!("rm -f -- {}", quote(&self.absolute(path))), &[])
+            .await?;
+        Ok(())
+    }
+
+    /// Entries below `root` (not including it), in `find` order.
+    pub async fn list(&self, root: &str, opts: ListOptions) -> Result<Vec<String>> {
+        let prune = if opts.skip_hidden {
+            "\\( -name '.*' -o -name node_modules -o -name target \\) -prune -o "
+        } else {
+            ""
+        };
+        let kind = if opts.files_only {
+            "-type f"
+        } else {
+            "\\( -type f -o -type d \\)"
+        };
+        let script = format!(
+            "find {} -mindepth 1 {prune}{kind} -print 2>/dev/null | head -n {}",
+            quote(&self.absolute(root)),
+            opts.limit
+        );
+        let out = self.run(&script, &[]).await?;
+        Ok(out
+            .stdout
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Read several files at once. Unreadable files come back as `None`.
+    pub async fn read_many(&self, paths: &[String]) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut all = Vec::with_capacity(paths.len());
+        for batch in paths.chunks(READ_BATCH) {
+            let list = batch.iter().map(|p| quote(p)).collect::<Vec<_>>().join(" ");
+            let script = format!(
+                "for f in {list}; do \
+                 if [ -f \"$f\" ] && [ -r \"$f\" ]; then printf '+'; base64 < \"$f\" | tr -d '\\n'; \
+                 else printf -- '-'; fi; echo; done"
+            );
+            let out = self.run_ok(&script, &[]).await?;
+            let mut lines = out.lines();
+            for _ in batch {
+                let line = lines
+                    .next()
+                    .context("sandbox returned fewer files than requested")?;
+                all.push(match line.strip_prefix('+') {
+                    Some(data) => decode(data).ok(),
+                    None => None,
+                });
+            }
+        }
+        Ok(all)
+    }
+}
+
+fn decode(data: &str) -> Result<Vec<u8>> {
+    let compact: String = data.split_whitespace().collect();
+    base64::engine::general_purpose::STANDARD
+        .decode(compact)
+        .context("failed to decode file contents from sandbox")
+}
+
+/// Single-quote `s` for `sh`.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// The leading directory of a glob pattern that contains no wildcards.
+pub fn glob_base(pattern: &str) -> PathBuf {
+    let mut base = PathBuf::new();
+    for component in Path::new(pattern).components() {
+        if let Component::Normal(part) = component {
+            if part.to_string_lossy().contains(['*', '?', '[', '{']) {
+                break;
+            }
+        }
+        base.push(component);
+    }
+    if base.as_os_str().is_empty() {
+        base.push(".");
+    }
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::{SandboxBackend, SandboxConfig, SandboxMode};
+    use crate::sandbox_native::native_available;
+
+    #[test]
+    fn test_quote_escapes_single_quotes() {
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_glob_base_stops_at_first_wildcard() {
+        assert_eq!(
+            glob_base("/srv/app/src/**/*.rs"),
+            PathBuf::from("/srv/app/src")
+        );
+        assert_eq!(glob_base("/srv/a?c/x"), PathBuf::from("/srv"));
+        assert_eq!(glob_base("*.rs"), PathBuf::from("."));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sandbox_fs_round_trip_on_native_backend() {
+        if !native_available() {
+            eprintln!("skipping: native sandbox unavailable");
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("microclaw_sfs_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = std::fs::canonicalize(&dir).unwrap();
+        let config = SandboxConfig {
+            mode: SandboxMode::All,
+            backend: SandboxBackend::Native,
+            ..SandboxConfig::default()
+        };
+        let router = Arc::new(SandboxRouter::new(config, &dir, vec![]));
+        let fs = SandboxFs::for_session(&router, "chat-1", &dir, vec![])
+            .unwrap()
+            .expect("native backend routes to sandbox");
+
+        let file = dir.join("nested/it's.txt");
+        let file = file.to_str().unwrap();
+        assert_eq!(fs.check_path(file).await, Ok(EntryKind::Missing));
+        fs.write(file, b"hello\nsandbox\n").await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("nested/it's.txt")).unwrap(),
+            "hello\nsandbox\n"
+        );
+        assert_eq!(fs.read_to_string(file).await.unwrap(), "hello\nsandbox\n");
+        assert_eq!(fs.check_path(file).await, Ok(EntryKind::File));
+        assert_eq!(fs.resolve(file).await.unwrap().size, Some(14));
+        // Relative paths resolve against the tool's working dir, not the host's cwd.
+        assert_eq!(
+            fs.read_to_string("nested/it's.txt").await.unwrap(),
+            "hello\nsandbox\n"
+        );
+        assert_eq!(
+            fs.resolve("nested").await.unwrap().path,
+            dir.join("nested").display().to_string()
+        );
+
+        std::fs::write(dir.join(".hidden"), "x").unwrap();
+        let listed = fs
+            .list(
+                dir.to_str().unwrap(),
+                ListOptions {
+                    files_only: true,
+                    skip_hidden: true,
+                    limit: 100,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(listed, vec![file.to_string()]);
+        let many = fs
+            .read_many(&[file.to_string(), dir.join("gone").display().to_string()])
+            .await
+            .unwrap();
+        assert_eq!(many, vec![Some(b"hello\nsandbox\n".to_vec()), None]);
+
+        // Paths outside the mounts do not exist in the sandbox, even if they do on the host.
+        let outside =
+            std::env::temp_dir().join(format!("microclaw_sfs_out_{}", uuid::Uuid::new_v4()));
+        std::fs::write(&outside, "host only").unwrap();
+        assert_eq!(
+            fs.check_path(outside.to_str().unwrap()).await,
+            Ok(EntryKind::Missing)
+        );
+        assert!(fs.write("/usr/microclaw-denied", b"x").await.is_err());
+
+        std::os::unix::fs::symlink(&outside, dir.join("link")).unwrap();
+        let err = fs
+            .check_path(dir.join("link").to_str().unwrap())
+            .await
+            .unwrap_err();
+        assert!(err.contains("symlink validation failed"));
+
+        let _ = std::fs::remove_file(&outside);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1141 @@
+//! Rootless sandbox backend built directly on Linux namespaces, for hosts
+//! without a container runtime.
+//!
+//! Every command runs in fresh user, mount, pid, ipc and uts namespaces, plus a
+//! network namespace unless its egress profile is `full`. The root is a
+//! throwaway tmpfs exposing the host system directories read-only, the working
+//! directory and the configured extra mounts. Rlimits derived from
+//! `memory_limit` / `cpu_quota` / `pids_limit`, an empty capability set and a
+//! seccomp filter are applied right before exec.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+
+use crate::sandbox::{
+    ExtraMount, Sandbox, SandboxConfig, SandboxExecOptions, SandboxExecResult, ShellLaunch,
+};
+use crate::sandbox_egress::{EgressMode, EgressProfile};
+
+const SANDBOX_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// Host directories exposed read-only inside the sandbox, when present.
+const SYSTEM_DIRS: &[&str] = &[
+    "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/libx32", "/etc", "/opt",
+];
+
+/// Resource limits applied to the sandboxed process tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Limits {
+    address_space: Option<u64>,
+    cpu_secs: Option<u64>,
+    processes: Option<u64>,
+}
+
+/// Parses a Docker-style memory size (`512m`, `2g`, `1048576`) into bytes.
+fn parse_memory_limit(raw: &str) -> Option<u64> {
+    let lower = raw.trim().to_ascii_lowercase();
+    let value = lower
+        .strip_suffix("ib")
+        .or_else(|| lower.strip_suffix('b'))
+        .unwrap_or(&lower);
+    let (digits, shift) = match value.chars().last()? {
+        'k' => (&value[..value.len() - 1], 10),
+        'm' => (&value[..value.len() - 1], 20),
+        'g' => (&value[..value.len() - 1], 30),
+        't' => (&value[..value.len() - 1], 40),
+        _ => (value, 0),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(1 << shift)
+        .filter(|bytes| *bytes > 0)
+}
+
+fn limits_for(config: &SandboxConfig, timeout: Duration) -> Result<Limits> {
+    let address_space = match &config.memory_limit {
+        Some(raw) => Some(
+            parse_memory_limit(raw)
+                .with_context(|| format!("invalid sandbox.memory_limit '{raw}'"))?,
+        ),
+        None => None,
+    };
+    // Rlimits cannot throttle, so the quota becomes a CPU-time budget over the
+    // command's wall-clock timeout.
+    let cpu_secs = config
+        .cpu_quota
+        .filter(|quota| *quota > 0.0)
+        .map(|quota| (quota * timeout.as_secs_f64()).ceil().max(1.0) as u64);
+    Ok(Limits {
+        address_space,
+        cpu_secs,
+        processes: config.pids_limit.map(u64::from),
+    })
+}
+
+/// Whether this host can run the native backend. Probed once per process.
+pub fn native_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| match sys::probe() {
+        Ok(()) => true,
+        Err(err) => {
+            tracing::debug!(error = %err, "native sandbox unavailable");
+            false
+        }
+    })
+}
+
+pub struct NativeSandbox {
+    config: SandboxConfig,
+    mount_dir: PathBuf,
+    extra_mounts: Vec<ExtraMount>,
+    /// Egress mode recorded by `ensure_ready`, per session.
+    egress: Mutex<HashMap<String, EgressMode>>,
+    warned_allowlist: AtomicBool,
+}
+
+impl NativeSandbox {
+    pub(crate) fn new(
+        config: SandboxConfig,
+        mount_dir: PathBuf,
+        extra_mounts: Vec<ExtraMount>,
+    ) -> Self {
+        Self {
+            config,
+            mount_dir,
+            extra_mounts,
+            egress: Mutex::new(HashMap::new()),
+            warned_allowlist: AtomicBool::new(false),
+        }
+    }
+
+    fn isolate_network(&self, session_key: &str) -> bool {
+        let recorded = self
+            .egress
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(session_key)
+            .copied();
+        match recorded {
+            Some(mode) => mode != EgressMode::Full,
+            None => self.config.no_network,
+        }
+    }
+
+    fn is_mounted(&self, path: &Path) -> bool {
+        path.starts_with(&self.mount_dir)
+            || self
+                .extra_mounts
+                .iter()
+                .any(|m| path.starts_with(&m.host_path))
+            || SYSTEM_DIRS.iter().any(|dir| path.starts_with(dir))
+    }
+
+    fn spec(&self, session_key: &str, opts: &SandboxExecOptions) -> Result<sys::Spec> {
+        let workdir = opts
+            .working_dir
+            .clone()
+            .unwrap_or_else(|| self.mount_dir.clone());
+        if !self.is_mounted(&workdir) {
+            bail!(
+                "working directory '{}' is not mounted in the native sandbox",
+                workdir.display()
+            );
+        }
+        let mut binds = vec![(self.mount_dir.clone(), false)];
+        binds.extend(
+            self.extra_mounts
+                .iter()
+                .map(|m| (m.host_path.clone(), m.read_only)),
+        );
+        Ok(sys::Spec {
+            root: staging_root()?,
+            binds,
+            workdir,
+            isolate_network: self.isolate_network(session_key),
+            limits: limits_for(&self.config, opts.timeout)?,
+        })
+    }
+}
+
+/// Mount point for each command's private root. Only ever mounted over inside
+/// the command's own mount namespace, so concurrent commands can share it.
+fn staging_root() -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("microclaw-native-{}", sys::uid()));
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("failed to create '{}'", dir.display()))?;
+    Ok(dir)
+}
+
+#[async_trait]
+impl Sandbox for NativeSandbox {
+    fn backend_name(&self) -> &'static str {
+        "native"
+    }
+
+    async fn ensure_ready(&self, session_key: &str, egress: &EgressProfile) -> Result<()> {
+        if egress.mode == EgressMode::Allowlist
+            && !self.warned_allowlist.swap(true, Ordering::Relaxed)
+        {
+            tracing::warn!(
+                "native sandbox cannot reach the egress proxy; allowlist profiles run without network"
+            );
+        }
+        self.egress
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(session_key.to_string(), egress.mode);
+        Ok(())
+    }
+
+    async fn exec_with_input(
+        &self,
+        session_key: &str,
+        command: &str,
+        input: &[u8],
+        opts: &SandboxExecOptions,
+    ) -> Result<SandboxExecResult> {
+        let confinement = sys::Confinement::new(self.spec(session_key, opts)?)?;
+        let mut cmd = tokio::process::Command::new("/bin/sh");
+        cmd.arg("-c")
+            .arg(command)
+            .env_clear()
+            .env("PATH", SANDBOX_PATH)
+            .env("HOME", &self.mount_dir)
+            .env("TMPDIR", "/tmp");
+        if let Some(lang) = std::env::var_os("LANG") {
+            cmd.env("LANG", lang);
+        }
+        for env_file in &opts.env_files {
+            if let Ok(content) = std::fs::read_to_string(env_file) {
+                for (k, v) in crate::env_file::parse_dotenv(&content) {
+                    cmd.env(k, v);
+                }
+            }
+        }
+        for (k, v) in &opts.envs {
+            cmd.env(k, v);
+        }
+        cmd.stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .stdin(crate::sandbox::stdin_for(input))
+            .kill_on_drop(true);
+        confinement.attach(&mut cmd);
+        let mut child = cmd.spawn().context("failed to start native sandbox")?;
+        crate::sandbox::feed_stdin(&mut child, input);
+        match tokio::time::timeout(opts.timeout, child.wait_with_output()).await {
+            Ok(Ok(output)) => Ok(SandboxExecResult {
+                stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+                exit_code: output.status.code().unwrap_or(-1),
+            }),
+            Ok(Err(e)) => bail!("native sandbox exec failed: {e}"),
+            Err(_) => bail!(
+                "native sandbox exec timed out after {} seconds",
+                opts.timeout.as_secs()
+            ),
+        }
+    }
+
+    fn interactive_shell(
+        &self,
+        _session_key: &str,
+        _opts: &SandboxExecOptions,
+    ) -> Result<ShellLaunch> {
+        bail!("persistent shell sessions are not supported by the native sandbox backend")
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod sys {
+    use std::collections::HashSet;
+    use std::ffi::{CStr, CString};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+
+    use anyhow::{Context, Result};
+
+    use super::{Limits, SYSTEM_DIRS};
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    /// Offsets into `struct seccomp_data`.
+    const SECCOMP_NR: u32 = 0;
+    const SECCOMP_ARCH: u32 = 4;
+    const SECCOMP_ARG0_LO: u32 = 16;
+
+    const NAMESPACE_FLAGS: libc::c_int = libc::CLONE_NEWNS
+        | libc::CLONE_NEWUTS
+        | libc::CLONE_NEWIPC
+        | libc::CLONE_NEWUSER
+        | libc::CLONE_NEWPID
+        | libc::CLONE_NEWNET
+        | libc::CLONE_NEWCGROUP;
+
+    /// Syscalls refused with `EPERM`: mount and namespace manipulation, kernel
+    /// modules and keyrings, tracing other processes and host-wide settings.
+    const DENIED_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_fsopen,
+        libc::SYS_fsconfig,
+        libc::SYS_fsmount,
+        libc::SYS_fspick,
+        libc::SYS_move_mount,
+        libc::SYS_open_tree,
+        libc::SYS_mount_setattr,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_kexec_load,
+        libc::SYS_kexec_file_load,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_userfaultfd,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_name_to_handle_at,
+        libc::SYS_reboot,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_acct,
+        libc::SYS_quotactl,
+        libc::SYS_syslog,
+        libc::SYS_vhangup,
+        libc::SYS_settimeofday,
+        libc::SYS_clock_settime,
+        libc::SYS_clock_adjtime,
+        libc::SYS_adjtimex,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_iopl,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_ioperm,
+    ];
+
+    pub(super) struct Spec {
+        pub root: PathBuf,
+        /// Host paths bound at the same location, with their read-only flag.
+        pub binds: Vec<(PathBuf, bool)>,
+        pub workdir: PathBuf,
+        pub isolate_network: bool,
+        pub limits: Limits,
+    }
+
+    enum Op {
+        Dir(CString),
+        File(CString),
+        Symlink {
+            target: CString,
+            path: CString,
+        },
+        Bind {
+            source: CString,
+            target: CString,
+            read_only: bool,
+            /// Flags of the source mount that a remount inside a user
+            /// namespace must keep.
+            locked: libc::c_ulong,
+        },
+        Tmpfs(CString),
+        Proc(CString),
+    }
+
+    /// Everything the child needs between fork and exec, prepared up front so
+    /// that nothing allocates there.
+    pub(super) struct Confinement {
+        clone_flags: libc::c_int,
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+        root: CString,
+        ops: Vec<Op>,
+        workdir: CString,
+        isolate_network: bool,
+        limits: Limits,
+        filter: Vec<libc::sock_filter>,
+    }
+
+    pub(super) fn uid() -> u32 {
+        // SAFETY: getuid cannot fail.
+        unsafe { libc::getuid() }
+    }
+
+    fn cstring(path: &Path) -> Result<CString> {
+        CString::new(path.as_os_str().as_bytes())
+            .with_context(|| format!("path '{}' contains a NUL byte", path.display()))
+    }
+
+    /// Mount flags of the filesystem holding `path` that cannot be cleared
+    /// from inside a user namespace.
+    fn locked_flags(path: &CStr) -> libc::c_ulong {
+        // SAFETY: statvfs only writes into the zeroed buffer.
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return 0;
+        }
+        [
+            (libc::ST_NOSUID, libc::MS_NOSUID),
+            (libc::ST_NODEV, libc::MS_NODEV),
+            (libc::ST_NOEXEC, libc::MS_NOEXEC),
+            (libc::ST_NOATIME, libc::MS_NOATIME),
+            (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+            (libc::ST_RELATIME, libc::MS_RELATIME),
+        ]
+        .iter()
+        .filter(|(st, _)| stat.f_flag & st != 0)
+        .fold(0, |acc, (_, ms)| acc | ms)
+    }
+
+    struct RootBuilder {
+        root: PathBuf,
+        ops: Vec<Op>,
+        created: HashSet<PathBuf>,
+    }
+
+    impl RootBuilder {
+        fn inside(&self, path: &Path) -> PathBuf {
+            self.root.join(path.strip_prefix("/").unwrap_or(path))
+        }
+
+        fn dir(&mut self, path: &Path) -> Result<()> {
+            let mut ancestors: Vec<_> = path.ancestors().filter(|a| a.parent().is_some()).collect();
+            ancestors.reverse();
+            for dir in ancestors {
+                if self.created.insert(dir.to_path_buf()) {
+                    let inside = cstring(&self.inside(dir))?;
+                    self.ops.push(Op::Dir(inside));
+                }
+            }
+            Ok(())
+        }
+
+        fn bind(&mut self, host: &Path, read_only: bool) -> Result<()> {
+            if host.is_dir() {
+                self.dir(host)?;
+            } else {
+                if let Some(parent) = host.parent() {
+                    self.dir(parent)?;
+                }
+                if self.created.insert(host.to_path_buf()) {
+                    self.ops.push(Op::File(cstring(&self.inside(host))?));
+                }
+            }
+            let source = cstring(host)?;
+            let locked = locked_flags(&source);
+            self.ops.push(Op::Bind {
+                target: cstring(&self.inside(host))?,
+                source,
+                read_only,
+                locked,
+            });
+            Ok(())
+        }
+
+        fn system_dir(&mut self, host: &Path) -> Result<()> {
+            let Ok(meta) = std::fs::symlink_metadata(host) else {
+                return Ok(());
+            };
+            if meta.file_type().is_symlink() {
+                // Merged-/usr layouts link /bin and friends into /usr.
+                let target = std::fs::read_link(host)?;
+                if let Some(parent) = host.parent() {
+                    self.dir(parent)?;
+                }
+                if self.created.insert(host.to_path_buf()) {
+                    self.ops.push(Op::Symlink {
+                        target: cstring(&target)?,
+                        path: cstring(&self.inside(host))?,
+                    });
+                }
+                return Ok(());
+            }
+            self.bind(host, true)
+        }
+
+        fn mount(&mut self, path: &Path, op: fn(CString) -> Op) -> Result<()> {
+            self.dir(path)?;
+            let inside = cstring(&self.inside(path))?;
+            self.ops.push(op(inside));
+            Ok(())
+        }
+    }
+
+    fn build_ops(spec: &Spec) -> Result<Vec<Op>> {
+        let mut builder = RootBuilder {
+            root: spec.root.clone(),
+            ops: Vec::new(),
+            created: HashSet::new(),
+        };
+        for dir in SYSTEM_DIRS {
+            builder.system_dir(Path::new(dir))?;
+        }
+        // resolv.conf is often a link into /run, which is not exposed otherwise.
+        if let Ok(resolv) = std::fs::canonicalize("/etc/resolv.conf") {
+            if let Some(dir) = resolv.parent().filter(|dir| !dir.starts_with("/etc")) {
+                builder.bind(dir, true)?;
+            }
+        }
+        builder.dir(Path::new("/dev"))?;
+        for dev in ["null", "zero", "full", "random", "urandom", "tty"] {
+            let host = Path::new("/dev").join(dev);
+            if host.exists() {
+                builder.bind(&host, false)?;
+            }
+        }
+        for (name, target) in [
+            ("fd", "/proc/self/fd"),
+            ("stdin", "/proc/self/fd/0"),
+            ("stdout", "/proc/self/fd/1"),
+            ("stderr", "/proc/self/fd/2"),
+        ] {
+            let path = builder.inside(&Path::new("/dev").join(name));
+            builder.ops.push(Op::Symlink {
+                target: cstring(Path::new(target))?,
+                path: cstring(&path)?,
+            });
+        }
+        builder.mount(Path::new("/dev/shm"), Op::Tmpfs)?;
+        builder.mount(Path::new("/tmp"), Op::Tmpfs)?;
+        builder.mount(Path::new("/proc"), Op::Proc)?;
+        for (host, read_only) in &spec.binds {
+            builder.bind(host, *read_only)?;
+        }
+        Ok(builder.ops)
+    }
+
+    fn stmt(code: u32, k: u32) -> libc::sock_filter {
+        libc::sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter {
+            code: code as u16,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    fn seccomp_filter() -> Vec<libc::sock_filter> {
+        let load = libc::BPF_LD | libc::BPF_W | libc::BPF_ABS;
+        let jeq = libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K;
+        let jset = libc::BPF_JMP | libc::BPF_JSET | libc::BPF_K;
+        let ret = libc::BPF_RET | libc::BPF_K;
+        let eperm = libc::SECCOMP_RET_ERRNO | libc::EPERM as u32;
+
+        let mut filter = vec![
+            stmt(load, SECCOMP_ARCH),
+            jump(jeq, AUDIT_ARCH, 1, 0),
+            stmt(ret, libc::SECCOMP_RET_KILL_PROCESS),
+            stmt(load, SECCOMP_NR),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        {
+            // x32 syscalls share the arch tag but set this bit.
+            filter.push(jump(jset, 0x4000_0000, 0, 1));
+            filter.push(stmt(ret, eperm));
+        }
+        for nr in DENIED_SYSCALLS {
+            filter.push(jump(jeq, *nr as u32, 0, 1));
+            filter.push(stmt(ret, eperm));
+        }
+        // clone3 passes its flags in memory the filter cannot inspect; ENOSYS
+        // makes libc fall back to clone.
+        filter.push(jump(jeq, libc::SYS_clone3 as u32, 0, 1));
+        filter.push(stmt(ret, libc::SECCOMP_RET_ERRNO | libc::ENOSYS as u32));
+        filter.push(jump(jeq, libc::SYS_clone as u32, 0, 3));
+        filter.push(stmt(load, SECCOMP_ARG0_LO));
+        filter.push(jump(jset, NAMESPACE_FLAGS as u32, 0, 1));
+        filter.push(stmt(ret, eperm));
+        filter.push(stmt(ret, libc::SECCOMP_RET_ALLOW));
+        filter
+    }
+
+    impl Confinement {
+        pub(super) fn new(spec: Spec) -> Result<Self> {
+            let mut clone_flags = libc::CLONE_NEWUSER
+                | libc::CLONE_NEWNS
+                | libc::CLONE_NEWPID
+                | libc::CLONE_NEWIPC
+                | libc::CLONE_NEWUTS;
+            if spec.isolate_network {
+                clone_flags |= libc::CLONE_NEWNET;
+            }
+            // SAFETY: getgid cannot fail.
+            let gid = unsafe { libc::getgid() };
+            Ok(Self {
+                clone_flags,
+                uid_map: format!("{0} {0} 1", uid()).into_bytes(),
+                gid_map: format!("{gid} {gid} 1").into_bytes(),
+                root: cstring(&spec.root)?,
+                ops: build_ops(&spec)?,
+                workdir: cstring(&spec.workdir)?,
+                isolate_network: spec.isolate_network,
+                limits: spec.limits,
+                filter: seccomp_filter(),
+            })
+        }
+
+        pub(super) fn attach(self, cmd: &mut tokio::process::Command) {
+            // SAFETY: `enter` only issues raw syscalls on data prepared before
+            // the fork.
+            unsafe {
+                cmd.pre_exec(move || self.enter());
+            }
+        }
+
+        /// Runs in the forked child before exec.
+        unsafe fn enter(&self) -> io::Result<()> {
+            cvt(libc::unshare(self.clone_flags))?;
+            // An unprivileged gid_map write requires setgroups to be denied first.
+            let _ = write_file(c"/proc/self/setgroups", b"deny");
+            write_file(c"/proc/self/uid_map", &self.uid_map)?;
+            write_file(c"/proc/self/gid_map", &self.gid_map)?;
+            // Only children join the new pid namespace, so fork once more and
+            // let the command become its init.
+            match libc::fork() {
+                -1 => return Err(io::Error::last_os_error()),
+                0 => {}
+                pid => supervise(pid),
+            }
+            cvt(libc::prctl(
+                libc::PR_SET_PDEATHSIG,
+                libc::SIGKILL as libc::c_ulong,
+            ))?;
+            self.build_root()?;
+            if self.isolate_network {
+                loopback_up();
+            }
+            self.apply_limits()?;
+            drop_capabilities()?;
+            cvt(libc::prctl(
+                libc::PR_SET_NO_NEW_PRIVS,
+                1 as libc::c_ulong,
+                0,
+                0,
+                0,
+            ))?;
+            let prog = libc::sock_fprog {
+                len: self.filter.len() as u16,
+                filter: self.filter.as_ptr() as *mut libc::sock_filter,
+            };
+            cvt(libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER as libc::c_ulong,
+                &prog as *const libc::sock_fprog,
+            ))?;
+            Ok(())
+        }
+
+        unsafe fn build_root(&self) -> io::Result<()> {
+            let null = std::ptr::null();
+            cvt(libc::mount(
+                null,
+                c"/".as_ptr(),
+                null,
+                libc::MS_REC | libc::MS_PRIVATE,
+                std::ptr::null(),
+            ))?;
+            cvt(libc::mount(
+                c"tmpfs".as_ptr(),
+                self.root.as_ptr(),
+                c"tmpfs".as_ptr(),
+                libc::MS_NOSUID | libc::MS_NODEV,
+                c"mode=0755".as_ptr().cast(),
+            ))?;
+            for op in &self.ops {
+                op.apply()?;
+            }
+            cvt(libc::mount(
+                null,
+                self.root.as_ptr(),
+                null,
+                libc::MS_REMOUNT | libc::MS_RDONLY | libc::MS_NOSUID | libc::MS_NODEV,
+                std::ptr::null(),
+            ))?;
+            cvt(libc::chdir(self.root.as_ptr()))?;
+            cvt(libc::syscall(libc::SYS_pivot_root, c".".as_ptr(), c".".as_ptr()) as libc::c_int)?;
+            cvt(libc::umount2(c".".as_ptr(), libc::MNT_DETACH))?;
+            cvt(libc::chdir(self.workdir.as_ptr()))?;
+            Ok(())
+        }
+
+        unsafe fn apply_limits(&self) -> io::Result<()> {
+            macro_rules! limit {
+                ($resource:expr, $soft:expr, $hard:expr) => {{
+                    let mut current: libc::rlimit = std::mem::zeroed();
+                    cvt(libc::getrlimit($resource, &mut current))?;
+                    let hard = ($hard as libc::rlim_t).min(current.rlim_max);
+                    let value = libc::rlimit {
+                        rlim_cur: ($soft as libc::rlim_t).min(hard),
+                        rlim_max: hard,
+                    };
+                    cvt(libc::setrlimit($resource, &value))?;
+                }};
+            }
+            if let Some(bytes) = self.limits.address_space {
+                limit!(libc::RLIMIT_AS, bytes, bytes);
+            }
+            if let Some(secs) = self.limits.cpu_secs {
+                // SIGXCPU at the soft limit, SIGKILL a second later.
+                limit!(libc::RLIMIT_CPU, secs, secs + 1);
+            }
+            if let Some(procs) = self.limits.processes {
+                limit!(libc::RLIMIT_NPROC, procs, procs);
+            }
+            Ok(())
+        }
+    }
+
+    impl Op {
+        unsafe fn apply(&self) -> io::Result<()> {
+            let null = std::ptr::null();
+            match self {
+                Op::Dir(path) => {
+                    if libc::mkdir(path.as_ptr(), 0o755) != 0
+                        && *libc::__errno_location() != libc::EEXIST
+                    {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Op::File(path) => {
+                    let fd = libc::open(
+                        path.as_ptr(),
+                        libc::O_RDONLY | libc::O_CREAT | libc::O_CLOEXEC,
+                        0o644,
+                    );
+                    if fd >= 0 {
+                        libc::close(fd);
+                    } else if *libc::__errno_location() != libc::EEXIST {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Op::Symlink { target, path } => {
+                    cvt(libc::symlink(target.as_ptr(), path.as_ptr()))?;
+                }
+                Op::Bind {
+                    source,
+                    target,
+                    read_only,
+                    locked,
+                } => {
+                    cvt(libc::mount(
+                        source.as_ptr(),
+                        target.as_ptr(),
+                        null,
+                        libc::MS_BIND | libc::MS_REC,
+                        std::ptr::null(),
+                    ))?;
+                    if *read_only {
+                        cvt(libc::mount(
+                            null,
+                            target.as_ptr(),
+                            null,
+                            libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY | locked,
+                            std::ptr::null(),
+                        ))?;
+                    }
+                }
+                Op::Tmpfs(path) => {
+                    cvt(libc::mount(
+                        c"tmpfs".as_ptr(),
+                        path.as_ptr(),
+                        c"tmpfs".as_ptr(),
+                        libc::MS_NOSUID | libc::MS_NODEV,
+                        c"mode=1777".as_ptr().cast(),
+                    ))?;
+                }
+                Op::Proc(path) => {
+                    cvt(libc::mount(
+                        c"proc".as_ptr(),
+                        path.as_ptr(),
+                        c"proc".as_ptr(),
+                        libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+                        std::ptr::null(),
+                    ))?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn cvt(ret: libc::c_int) -> io::Result<()> {
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    unsafe fn write_file(path: &CStr, data: &[u8]) -> io::Result<()> {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let written = libc::write(fd, data.as_ptr().cast(), data.len());
+        let result = if written == data.len() as isize {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        };
+        libc::close(fd);
+        result
+    }
+
+    /// Waits for the namespace's init and mirrors its exit status.
+    unsafe fn supervise(pid: libc::pid_t) -> ! {
+        // Drop the spawn status pipe so the parent's spawn() returns as soon as
+        // the command execs rather than when it exits.
+        if libc::syscall(libc::SYS_close_range, 3u32, u32::MAX, 0u32) != 0 {
+            for fd in 3..1024 {
+                libc::close(fd);
+            }
+        }
+        let mut status = 0;
+        loop {
+            if libc::waitpid(pid, &mut status, 0) == pid {
+                break;
+            }
+            if *libc::__errno_location() != libc::EINTR {
+                libc::_exit(127);
+            }
+        }
+        let code = if libc::WIFEXITED(status) {
+            libc::WEXITSTATUS(status)
+        } else if libc::WIFSIGNALED(status) {
+            128 + libc::WTERMSIG(status)
+        } else {
+            1
+        };
+        libc::_exit(code)
+    }
+
+    /// A fresh network namespace starts with loopback down; commands that talk
+    /// to localhost expect it up.
+    unsafe fn loopback_up() {
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+        if fd < 0 {
+            return;
+        }
+        let mut req: libc::ifreq = std::mem::zeroed();
+        for (dst, src) in req.ifr_name.iter_mut().zip(b"lo") {
+            *dst = *src as libc::c_char;
+        }
+        req.ifr_ifru.ifru_flags = (libc::IFF_UP | libc::IFF_RUNNING) as libc::c_short;
+        libc::ioctl(fd, libc::SIOCSIFFLAGS as _, &req);
+        libc::close(fd);
+    }
+
+    unsafe fn drop_capabilities() -> io::Result<()> {
+        #[repr(C)]
+        struct CapHeader {
+            version: u32,
+            pid: libc::c_int,
+        }
+        #[repr(C)]
+        struct CapData {
+            effective: u32,
+            permitted: u32,
+            inheritable: u32,
+        }
+        for cap in 0..64 {
+            if libc::prctl(libc::PR_CAPBSET_DROP, cap as libc::c_ulong, 0, 0, 0) != 0
+                && *libc::__errno_location() != libc::EINVAL
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        let _ = libc::prctl(
+            libc::PR_CAP_AMBIENT,
+            libc::PR_CAP_AMBIENT_CLEAR_ALL as libc::c_ulong,
+            0,
+            0,
+            0,
+        );
+        let header = CapHeader {
+            version: 0x2008_0522,
+            pid: 0,
+        };
+        let data = [
+            CapData {
+                effective: 0,
+                permitted: 0,
+                inheritable: 0,
+            },
+            CapData {
+                effective: 0,
+                permitted: 0,
+                inheritable: 0,
+            },
+        ];
+        cvt(libc::syscall(libc::SYS_capset, &header, data.as_ptr()) as libc::c_int)
+    }
+
+    /// Runs `exit 0` through a full confinement, so hosts that allow user
+    /// namespaces but restrict what they may do (AppArmor, seccomp'd
+    /// containers) are detected up front.
+    pub(super) fn probe() -> Result<()> {
+        use std::os::unix::process::CommandExt;
+
+        let confinement = Confinement::new(Spec {
+            root: super::staging_root()?,
+            binds: Vec::new(),
+            workdir: PathBuf::from("/"),
+            isolate_network: true,
+            limits: Limits::default(),
+        })?;
+        let mut cmd = std::process::Command::new("/bin/sh");
+        cmd.args(["-c", "exit 0"])
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null());
+        // SAFETY: as in `Confinement::attach`.
+        unsafe {
+            cmd.pre_exec(move || confinement.enter());
+        }
+        let status = cmd.status().context("failed to start probe")?;
+        if !status.success() {
+            anyhow::bail!("probe exited with {status}");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+mod sys {
+    use std::path::PathBuf;
+
+    use anyhow::{bail, Result};
+
+    use super::Limits;
+
+    #[allow(dead_code)]
+    pub(super) struct Spec {
+        pub root: PathBuf,
+        pub binds: Vec<(PathBuf, bool)>,
+        pub workdir: PathBuf,
+        pub isolate_network: bool,
+        pub limits: Limits,
+    }
+
+    pub(super) struct Confinement;
+
+    impl Confinement {
+        pub(super) fn new(_spec: Spec) -> Result<Self> {
+            bail!("the native sandbox backend requires Linux on x86_64 or aarch64")
+        }
+
+        pub(super) fn attach(self, _cmd: &mut tokio::process::Command) {}
+    }
+
+    pub(super) fn uid() -> u32 {
+        0
+    }
+
+    pub(super) fn probe() -> Result<()> {
+        bail!("the native sandbox backend requires Linux on x86_64 or aarch64")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sandbox(dir: &Path, extra_mounts: Vec<ExtraMount>) -> NativeSandbox {
+        let config = SandboxConfig {
+            memory_limit: Some("1g".to_string()),
+            cpu_quota: Some(2.0),
+            pids_limit: Some(64),
+            ..SandboxConfig::default()
+        };
+        NativeSandbox::new(config, dir.to_path_buf(), extra_mounts)
+    }
+
+    fn opts() -> SandboxExecOptions {
+        SandboxExecOptions {
+            timeout: Duration::from_secs(10),
+            working_dir: None,
+            envs: HashMap::new(),
+            env_files: Vec::new(),
+        }
+    }
+
+    fn temp_dir(tag: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mc_native_{tag}_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::canonicalize(dir).unwrap()
+    }
+
+    #[test]
+    fn test_parse_memory_limit() {
+        assert_eq!(parse_memory_limit("512m"), Some(512 << 20));
+        assert_eq!(parse_memory_limit("2G"), Some(2 << 30));
+        assert_eq!(parse_memory_limit("64kb"), Some(64 << 10));
+        assert_eq!(parse_memory_limit("1MiB"), Some(1 << 20));
+        assert_eq!(parse_memory_limit("4096"), Some(4096));
+        assert_eq!(parse_memory_limit("0"), None);
+        assert_eq!(parse_memory_limit("lots"), None);
+    }
+
+    #[test]
+    fn test_limits_follow_config() {
+        let config = SandboxConfig {
+            memory_limit: Some("256m".to_string()),
+            cpu_quota: Some(0.5),
+            pids_limit: Some(32),
+            ..SandboxConfig::default()
+        };
+        let limits = limits_for(&config, Duration::from_secs(30)).unwrap();
+        assert_eq!(
+            limits,
+            Limits {
+                address_space: Some(256 << 20),
+                cpu_secs: Some(15),
+                processes: Some(32),
+            }
+        );
+        let bad = SandboxConfig {
+            memory_limit: Some("huge".to_string()),
+            ..SandboxConfig::default()
+        };
+        assert!(limits_for(&bad, Duration::from_secs(30)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_native_exec_isolates_process_filesystem_and_network() {
+        if !native_available() {
+            eprintln!("skipping: native sandbox unavailable on this host");
+            return;
+        }
+        let work = temp_dir("work");
+        let hidden = temp_dir("hidden");
+        let sb = sandbox(&work, Vec::new());
+        sb.ensure_ready("chat-1", &EgressProfile::new(EgressMode::None))
+            .await
+            .unwrap();
+        let script = format!(
+            "echo pid=$$; pwd; echo hi > out.txt; \
+             test -e {} && echo hidden-visible; \
+             grep -c : /proc/net/dev; \
+             grep -E '^(Seccomp|NoNewPrivs|CapEff):' /proc/self/status; \
+             grep -E 'Max (processes|address space)' /proc/self/limits",
+            hidden.display()
+        );
+        let out = sb.exec("chat-1", &script, &opts()).await.unwrap();
+        assert_eq!(out.exit_code, 0, "stderr: {}", out.stderr);
+        let stdout = out.stdout;
+        assert!(stdout.contains("pid=1\n"), "{stdout}");
+        assert!(
+            stdout.contains(&format!("{}\n", work.display())),
+            "{stdout}"
+        );
+        assert!(!stdout.contains("hidden-visible"), "{stdout}");
+        // Only the loopback interface exists in the new network namespace.
+        assert!(stdout.contains("\n1\n"), "{stdout}");
+        assert!(stdout.contains("Seccomp:\t2"), "{stdout}");
+        assert!(stdout.contains("NoNewPrivs:\t1"), "{stdout}");
+        assert!(stdout.contains("CapEff:\t0000000000000000"), "{stdout}");
+        assert!(
+            stdout
+                .lines()
+                .any(|l| l.starts_with("Max processes") && l.contains(" 64 ")),
+            "{stdout}"
+        );
+        assert!(
+            stdout
+                .lines()
+                .any(|l| l.starts_with("Max address space") && l.contains(" 1073741824 ")),
+            "{stdout}"
+        );
+        assert_eq!(
+            std::fs::read_to_string(work.join("out.txt")).unwrap(),
+            "hi\n"
+        );
+        let _ = std::fs::remove_dir_all(&work);
+        let _ = std::fs::remove_dir_all(&hidden);
+    }
+
+    #[tokio::test]
+    async fn test_native_exec_mounts_and_blocked_syscalls() {
+        if !native_available() {
+            eprintln!("skipping: native sandbox unavailable on this host");
+            return;
+        }
+        let work = temp_dir("work");
+        let shared = temp_dir("ro");
+        std::fs::write(shared.join("data.txt"), "shared").unwrap();
+        let sb = sandbox(
+            &work,
+            vec![ExtraMount {
+                host_path: shared.clone(),
+                read_only: true,
+            }],
+        );
+        sb.ensure_ready("chat-2", &EgressProfile::new(EgressMode::Full))
+            .await
+            .unwrap();
+        let script = format!(
+            "cat {dir}/data.txt; echo; \
+             (echo x > {dir}/new.txt) 2>/dev/null || echo ro-denied; \
+             (echo x > /usr/microclaw-probe) 2>/dev/null || echo usr-denied; \
+             echo tmp > /tmp/t && cat /tmp/t",
+            dir = shared.display()
+        );
+        let out = sb.exec("chat-2", &script, &opts()).await.unwrap();
+        assert_eq!(out.exit_code, 0, "stderr: {}", out.stderr);
+        assert_eq!(out.stdout, "shared\nro-denied\nusr-denied\ntmp\n");
+        assert!(!shared.join("new.txt").exists());
+
+        let out = sb.exec("chat-2", "exit 7", &opts()).await.unwrap();
+        assert_eq!(out.exit_code, 7);
+
+        let mut short = opts();
+        short.timeout = Duration::from_millis(300);
+        let err = sb.exec("chat-2", "sleep 5", &short).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        let _ = std::fs::remove_dir_all(&work);
+        let _ = std::fs::remove_dir_all(&shared);
+    }
+
+    #[tokio::test]
+    async fn test_native_rejects_unmounted_workdir_and_shell_sessions() {
+        let work = temp_dir("work");
+        let sb = sandbox(&work, Vec::new());
+        let mut opts = opts();
+        opts.working_dir = Some(PathBuf::from("/root/elsewhere"));
+        let err = sb.exec("chat-3", "true", &opts).await.unwrap_err();
+        assert!(err.to_string().contains("not mounted"));
+        assert!(sb.interactive_shell("chat-3", &opts).is_err());
+        let _ = std::fs::remove_dir_all(&work);
+    }
+}
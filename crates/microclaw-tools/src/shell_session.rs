@@ -0,0 +1,898 @@
+//! Persistent interactive shells backing the `bash_session_*` tools.
+//!
+//! A session is a shell attached to a pseudo-terminal, started on the host or inside
+//! the chat's sandbox container (see [`SandboxRouter::interactive_shell`]), so the
+//! working directory, exported variables and background jobs survive between calls.
+//! Every command is followed by a sentinel line carrying its exit status, which is how
+//! [`ShellSession::exec`] tells that the command finished. Sessions left idle for
+//! longer than `idle_timeout_secs` are reaped.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Notify};
+
+use crate::sandbox::{host_interactive_shell, SandboxExecOptions, SandboxRouter, ShellLaunch};
+
+fn default_idle_timeout_secs() -> u64 {
+    1800
+}
+
+fn default_max_sessions() -> usize {
+    8
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ShellSessionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    #[serde(default = "default_max_sessions")]
+    pub max_sessions: usize,
+}
+
+impl Default for ShellSessionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_timeout_secs: default_idle_timeout_secs(),
+            max_sessions: default_max_sessions(),
+        }
+    }
+}
+
+impl ShellSessionConfig {
+    pub fn normalize(&mut self) {
+        if self.idle_timeout_secs == 0 {
+            self.idle_timeout_secs = default_idle_timeout_secs();
+        }
+        if self.max_sessions == 0 {
+            self.max_sessions = default_max_sessions();
+        }
+    }
+}
+
+/// Unread output kept per session; older bytes are dropped past this.
+const MAX_BUFFERED_BYTES: usize = 1024 * 1024;
+const SENTINEL_PREFIX: &str = "__MICROCLAW_DONE_";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
+/// Environment for session shells: no prompts, pagers or history, so the terminal
+/// only carries command output.
+const SHELL_SESSION_ENV: &[(&str, &str)] = &[
+    ("PS1", ""),
+    ("PS2", ""),
+    ("PROMPT_COMMAND", ""),
+    ("HISTFILE", "/dev/null"),
+    ("TERM", "dumb"),
+    ("PAGER", "cat"),
+    ("GIT_PAGER", "cat"),
+];
+
+fn next_nonce() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    format!(
+        "{:x}{:x}{:x}",
+        std::process::id(),
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// The shell input that runs `command` and then prints its exit status as a sentinel
+/// line. The status is printed through a format string so that a terminal echoing the
+/// input never shows a complete sentinel.
+fn wrap_command(command: &str, nonce: &str) -> String {
+    format!(
+        "{}\n__mc_rc=$?; printf '\\n{SENTINEL_PREFIX}%s_%s__\\n' '{nonce}' \"$__mc_rc\"\n",
+        command.trim_end_matches('\n')
+    )
+}
+
+/// Finds the sentinel for `nonce`, returning the byte range of the sentinel line
+/// (including the line break printed before it) and the exit status.
+fn find_sentinel(buf: &[u8], nonce: &str) -> Option<(usize, usize, i32)> {
+    let marker = format!("{SENTINEL_PREFIX}{nonce}_");
+    let pos = find_bytes(buf, marker.as_bytes())?;
+    let digits_start = pos + marker.len();
+    let digits_len = buf[digits_start..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    let mut end = digits_start + digits_len;
+    if digits_len == 0 || !buf[end..].starts_with(b"__") {
+        return None;
+    }
+    end += 2;
+    if buf[end..].starts_with(b"\r\n") {
+        end += 2;
+    } else if buf[end..].starts_with(b"\n") {
+        end += 1;
+    } else {
+        return None;
+    }
+    let code = std::str::from_utf8(&buf[digits_start..digits_start + digits_len])
+        .ok()?
+        .parse()
+        .ok()?;
+    let mut start = pos;
+    if start > 0 && buf[start - 1] == b'\n' {
+        start -= 1;
+        if start > 0 && buf[start - 1] == b'\r' {
+            start -= 1;
+        }
+    }
+    Some((start, end, code))
+}
+
+/// `now + wait`, saturating to a far-future deadline instead of overflowing.
+fn deadline_after(wait: Duration) -> tokio::time::Instant {
+    let now = tokio::time::Instant::now();
+    now.checked_add(wait)
+        .unwrap_or_else(|| now + Duration::from_secs(86_400 * 365 * 30))
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// How many leading bytes of `buf` can be handed out without splitting a UTF-8
+/// character or, while a command is running, a sentinel line or one of `secrets`
+/// that is still arriving.
+fn drainable_len(buf: &[u8], command_running: bool, secrets: &[Vec<u8>]) -> usize {
+    let mut len = buf.len();
+    if let Some(nl) = buf
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .filter(|_| command_running)
+    {
+        let tail = &buf[nl + 1..];
+        let prefix = SENTINEL_PREFIX.as_bytes();
+        let n = tail.len().min(prefix.len());
+        if tail[..n] == prefix[..n] {
+            len = if nl > 0 && buf[nl - 1] == b'\r' {
+                nl - 1
+            } else {
+                nl
+            };
+        }
+    }
+    if command_running {
+        len = secret_safe_len(buf, len, secrets);
+    }
+    match std::str::from_utf8(&buf[..len]) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => len,
+    }
+}
+
+/// Moves `len` back to the start of any secret that straddles it, or whose prefix
+/// ends `buf`, so each secret reaches the caller's redaction in one piece.
+fn secret_safe_len(buf: &[u8], mut len: usize, secrets: &[Vec<u8>]) -> usize {
+    loop {
+        let cut = secrets.iter().filter(|s| !s.is_empty()).find_map(|secret| {
+            let from = len.saturating_sub(secret.len() - 1);
+            (from..len).find(|&p| secret.starts_with(&buf[p..buf.len().min(p + secret.len())]))
+        });
+        match cut {
+            Some(p) => len = p,
+            None => return len,
+        }
+    }
+}
+
+fn bytes_to_text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).replace("\r\n", "\n")
+}
+
+#[derive(Default)]
+struct OutputState {
+    buf: Vec<u8>,
+    dropped: usize,
+    closed: bool,
+}
+
+impl OutputState {
+    fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+        if self.buf.len() > MAX_BUFFERED_BYTES {
+            let excess = self.buf.len() - MAX_BUFFERED_BYTES;
+            self.buf.drain(..excess);
+            self.dropped += excess;
+        }
+    }
+
+    fn take(&mut self, len: usize) -> (String, usize) {
+        let text = bytes_to_text(&self.buf[..len]);
+        self.buf.drain(..len);
+        (text, std::mem::take(&mut self.dropped))
+    }
+}
+
+/// Output collected from a session by one `exec` or `read_output` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellOutput {
+    pub output: String,
+    /// Exit status of the command that finished during this call, if any.
+    pub exit_code: Option<i32>,
+    /// Whether a command is still running in the foreground.
+    pub running: bool,
+    /// Whether the shell has exited.
+    pub closed: bool,
+    /// Bytes discarded because nobody read them before the buffer filled up.
+    pub dropped_bytes: usize,
+}
+
+pub struct ShellSession {
+    key: String,
+    sandboxed: bool,
+    env_files: Vec<PathBuf>,
+    output: Arc<StdMutex<OutputState>>,
+    notify: Arc<Notify>,
+    process: StdMutex<Option<pty::PtyProcess>>,
+    /// Nonce of a command that outlived its `exec` timeout.
+    pending: StdMutex<Option<String>>,
+    /// Values the caller redacts from output; see [`ShellSession::hold_back_secrets`].
+    secrets: StdMutex<Vec<Vec<u8>>>,
+    last_used: StdMutex<Instant>,
+    op_lock: Mutex<()>,
+}
+
+impl ShellSession {
+    async fn spawn(key: &str, launch: ShellLaunch, env_files: Vec<PathBuf>) -> Result<Self> {
+        let output = Arc::new(StdMutex::new(OutputState::default()));
+        let notify = Arc::new(Notify::new());
+        let process = pty::PtyProcess::spawn(&launch, output.clone(), notify.clone())?;
+        let session = Self {
+            key: key.to_string(),
+            sandboxed: launch.sandboxed,
+            env_files,
+            output,
+            notify,
+            process: StdMutex::new(Some(process)),
+            pending: StdMutex::new(None),
+            secrets: StdMutex::new(Vec::new()),
+            last_used: StdMutex::new(Instant::now()),
+            op_lock: Mutex::new(()),
+        };
+        // Turn off terminal echo and wait for the shell to answer; whatever it printed
+        // while starting up (job control warnings and the like) is discarded.
+        let ready = session
+            .exec("stty -echo 2>/dev/null", STARTUP_TIMEOUT)
+            .await?;
+        if ready.closed || ready.running {
+            session.terminate().await;
+            bail!(
+                "shell did not start: {}",
+                ready.output.trim().chars().take(500).collect::<String>()
+            );
+        }
+        Ok(session)
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Whether the shell runs inside a sandbox container rather than on the host.
+    pub fn sandboxed(&self) -> bool {
+        self.sandboxed
+    }
+
+    /// Env files whose variables were loaded into the shell when it started.
+    pub fn env_files(&self) -> &[PathBuf] {
+        &self.env_files
+    }
+
+    /// Sets the values the caller will redact from output. Partial output of a running
+    /// command is never cut inside one of them, so redaction sees each one whole.
+    pub fn hold_back_secrets(&self, values: Vec<String>) {
+        if let Ok(mut secrets) = self.secrets.lock() {
+            *secrets = values.into_iter().map(String::into_bytes).collect();
+        }
+    }
+
+    fn secrets(&self) -> Vec<Vec<u8>> {
+        self.secrets.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.output.lock().map(|s| s.closed).unwrap_or(true)
+    }
+
+    fn touch(&self) {
+        if let Ok(mut last) = self.last_used.lock() {
+            *last = Instant::now();
+        }
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_used
+            .lock()
+            .map(|last| last.elapsed())
+            .unwrap_or_default()
+    }
+
+    fn pending_nonce(&self) -> Option<String> {
+        self.pending.lock().ok().and_then(|p| p.clone())
+    }
+
+    fn set_pending(&self, nonce: Option<String>) {
+        if let Ok(mut pending) = self.pending.lock() {
+            *pending = nonce;
+        }
+    }
+
+    async fn write(&self, input: &[u8]) -> Result<()> {
+        let writer = match self.process.lock() {
+            Ok(process) => match process.as_ref() {
+                Some(process) => process.writer()?,
+                None => bail!("shell session has been killed"),
+            },
+            Err(_) => bail!("shell session state is poisoned"),
+        };
+        let input = input.to_vec();
+        tokio::task::spawn_blocking(move || {
+            use std::io::Write;
+            let mut writer = writer;
+            writer.write_all(&input)?;
+            writer.flush()
+        })
+        .await??;
+        Ok(())
+    }
+
+    /// Removes the sentinel of a command that timed out earlier once it has arrived,
+    /// leaving that command's output in the buffer. Returns its exit status.
+    fn settle_pending(&self, state: &mut OutputState) -> Option<i32> {
+        let nonce = self.pending_nonce()?;
+        let (start, end, code) = find_sentinel(&state.buf, &nonce)?;
+        state.buf.drain(start..end);
+        self.set_pending(None);
+        Some(code)
+    }
+
+    /// Runs `command` and waits up to `timeout` for it to finish. On timeout the
+    /// output so far is returned with `running` set, and the rest can be collected
+    /// with [`ShellSession::read_output`].
+    pub async fn exec(&self, command: &str, timeout: Duration) -> Result<ShellOutput> {
+        let _op = self.op_lock.lock().await;
+        self.touch();
+        if self.pending_nonce().is_some() {
+            let settled = self
+                .output
+                .lock()
+                .map(|mut state| self.settle_pending(&mut state).is_some())
+                .unwrap_or(false);
+            if !settled {
+                bail!(
+                    "the previous command is still running; read its output or interrupt it first"
+                );
+            }
+        }
+        let nonce = next_nonce();
+        self.write(wrap_command(command, &nonce).as_bytes()).await?;
+        let deadline = deadline_after(timeout);
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut state = match self.output.lock() {
+                    Ok(state) => state,
+                    Err(_) => bail!("shell session state is poisoned"),
+                };
+                if let Some((start, end, code)) = find_sentinel(&state.buf, &nonce) {
+                    let (output, dropped_bytes) = state.take(start);
+                    state.buf.drain(..end - start);
+                    return Ok(ShellOutput {
+                        output,
+                        exit_code: Some(code),
+                        running: false,
+                        closed: false,
+                        dropped_bytes,
+                    });
+                }
+                let timed_out = tokio::time::Instant::now() >= deadline;
+                if state.closed || timed_out {
+                    let len = if state.closed {
+                        state.buf.len()
+                    } else {
+                        drainable_len(&state.buf, true, &self.secrets())
+                    };
+                    let (output, dropped_bytes) = state.take(len);
+                    let closed = state.closed;
+                    if !closed {
+                        self.set_pending(Some(nonce));
+                    }
+                    return Ok(ShellOutput {
+                        output,
+                        exit_code: None,
+                        running: !closed,
+                        closed,
+                        dropped_bytes,
+                    });
+                }
+            }
+            let _ = tokio::time::timeout_at(deadline, notified).await;
+        }
+    }
+
+    /// Returns output that has arrived since the last call, waiting up to `wait` for
+    /// some to appear when there is none yet.
+    pub async fn read_output(&self, wait: Duration) -> Result<ShellOutput> {
+        let _op = self.op_lock.lock().await;
+        self.touch();
+        let deadline = deadline_after(wait);
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut state = match self.output.lock() {
+                    Ok(state) => state,
+                    Err(_) => bail!("shell session state is poisoned"),
+                };
+                let exit_code = self.settle_pending(&mut state);
+                let len = if state.closed {
+                    state.buf.len()
+                } else {
+                    drainable_len(&state.buf, self.pending_nonce().is_some(), &self.secrets())
+                };
+                if len > 0
+                    || exit_code.is_some()
+                    || state.closed
+                    || tokio::time::Instant::now() >= deadline
+                {
+                    let (output, dropped_bytes) = state.take(len);
+                    return Ok(ShellOutput {
+                        output,
+                        exit_code,
+                        running: self.pending_nonce().is_some() && !state.closed,
+                        closed: state.closed,
+                        dropped_bytes,
+                    });
+                }
+            }
+            let _ = tokio::time::timeout_at(deadline, notified).await;
+        }
+    }
+
+    /// Sends Ctrl-C to the foreground command, leaving the shell running.
+    pub async fn interrupt(&self) -> Result<()> {
+        self.touch();
+        self.write(b"\x03").await
+    }
+
+    /// Asks the shell to kill its jobs and exit, then kills whatever is left of it.
+    pub async fn terminate(&self) {
+        let _ = self
+            .write(b"\x03\nkill -KILL $(jobs -p) 2>/dev/null; exit\n")
+            .await;
+        let process = self.process.lock().ok().and_then(|mut p| p.take());
+        if let Some(process) = process {
+            let _ = tokio::task::spawn_blocking(move || process.shutdown(KILL_GRACE)).await;
+        }
+    }
+}
+
+pub struct ShellSessionManager {
+    config: ShellSessionConfig,
+    sandbox_router: Option<Arc<SandboxRouter>>,
+    sessions: Mutex<HashMap<String, Arc<ShellSession>>>,
+}
+
+impl ShellSessionManager {
+    /// Creates the manager and, when called inside a Tokio runtime, the task that
+    /// reaps idle sessions. The task stops once the manager is dropped.
+    pub fn new(
+        config: ShellSessionConfig,
+        sandbox_router: Option<Arc<SandboxRouter>>,
+    ) -> Arc<Self> {
+        let manager = Arc::new(Self {
+            config,
+            sandbox_router,
+            sessions: Mutex::new(HashMap::new()),
+        });
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let weak = Arc::downgrade(&manager);
+            let period = Duration::from_secs((manager.config.idle_timeout_secs / 4).clamp(1, 60));
+            handle.spawn(async move {
+                let mut ticker = tokio::time::interval(period);
+                loop {
+                    ticker.tick().await;
+                    let Some(manager) = weak.upgrade() else {
+                        break;
+                    };
+                    manager.reap_idle().await;
+                }
+            });
+        }
+        manager
+    }
+
+    pub fn config(&self) -> &ShellSessionConfig {
+        &self.config
+    }
+
+    pub async fn get(&self, key: &str) -> Option<Arc<ShellSession>> {
+        self.sessions.lock().await.get(key).cloned()
+    }
+
+    /// Returns the live session for `key`, starting one when there is none. The
+    /// boolean is true when a new shell was started.
+    pub async fn start(
+        &self,
+        key: &str,
+        opts: &SandboxExecOptions,
+    ) -> Result<(Arc<ShellSession>, bool)> {
+        if let Some(session) = self.get(key).await {
+            if !session.is_closed() {
+                return Ok((session, false));
+            }
+            self.kill(key).await;
+        }
+        self.reap_idle().await;
+        if self.sessions.lock().await.len() >= self.config.max_sessions {
+            bail!(
+                "too many active shell sessions (max {}); kill one with bash_session_kill",
+                self.config.max_sessions
+            );
+        }
+
+        let mut opts = opts.clone();
+        for (k, v) in SHELL_SESSION_ENV {
+            opts.envs.insert(k.to_string(), v.to_string());
+        }
+        let launch = match &self.sandbox_router {
+            Some(router) => router.interactive_shell(key, &opts).await?,
+            None => host_interactive_shell(&opts),
+        };
+        let sandboxed = launch.sandboxed;
+        let session = Arc::new(ShellSession::spawn(key, launch, opts.env_files.clone()).await?);
+        tracing::info!(session = key, sandboxed, "started shell session");
+
+        let mut sessions = self.sessions.lock().await;
+        if let Some(existing) = sessions.get(key).filter(|s| !s.is_closed()).cloned() {
+            // Another call started a session for this key while we were spawning.
+            drop(sessions);
+            session.terminate().await;
+            return Ok((existing, false));
+        }
+        sessions.insert(key.to_string(), session.clone());
+        Ok((session, true))
+    }
+
+    /// Kills and forgets the session for `key`. Returns false when there was none.
+    pub async fn kill(&self, key: &str) -> bool {
+        let session = self.sessions.lock().await.remove(key);
+        match session {
+            Some(session) => {
+                session.terminate().await;
+                tracing::info!(session = key, "killed shell session");
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Terminates sessions that exited or sat idle past `idle_timeout_secs`.
+    pub async fn reap_idle(&self) -> usize {
+        let idle_timeout = Duration::from_secs(self.config.idle_timeout_secs);
+        let reaped: Vec<Arc<ShellSession>> = {
+            let mut sessions = self.sessions.lock().await;
+            let keys: Vec<String> = sessions
+                .iter()
+                .filter(|(_, s)| s.is_closed() || s.idle_for() >= idle_timeout)
+                .map(|(k, _)| k.clone())
+                .collect();
+            keys.iter().filter_map(|k| sessions.remove(k)).collect()
+        };
+        for session in &reaped {
+            tracing::info!(session = session.key(), "reaping idle shell session");
+            session.terminate().await;
+        }
+        reaped.len()
+    }
+}
+
+#[cfg(unix)]
+mod pty {
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::unix::process::CommandExt;
+    use std::process::{Child, Stdio};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use anyhow::{Context, Result};
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+    use tokio::sync::Notify;
+
+    use super::OutputState;
+    use crate::sandbox::ShellLaunch;
+
+    pub(super) struct PtyProcess {
+        child: Child,
+        master: File,
+    }
+
+    impl PtyProcess {
+        /// Starts `launch` as a session leader whose controlling terminal is a new
+        /// pseudo-terminal, with a reader thread copying its output into `output`.
+        pub(super) fn spawn(
+            launch: &ShellLaunch,
+            output: Arc<Mutex<OutputState>>,
+            notify: Arc<Notify>,
+        ) -> Result<Self> {
+            let winsize = nix::pty::Winsize {
+                ws_row: 50,
+                ws_col: 200,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            };
+            let pty = nix::pty::openpty(Some(&winsize), None)
+                .context("failed to open a pseudo-terminal")?;
+            let mut cmd = std::process::Command::new(&launch.program);
+            cmd.args(&launch.args)
+                .envs(launch.envs.iter().map(|(k, v)| (k, v)))
+                .stdin(Stdio::from(pty.slave.try_clone()?))
+                .stdout(Stdio::from(pty.slave.try_clone()?))
+                .stderr(Stdio::from(pty.slave));
+            if let Some(dir) = &launch.working_dir {
+                cmd.current_dir(dir);
+            }
+            // SAFETY: only async-signal-safe calls (setsid, ioctl) run between fork and exec.
+            unsafe {
+                cmd.pre_exec(|| {
+                    nix::unistd::setsid().map_err(std::io::Error::from)?;
+                    if libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+            let child = cmd
+                .spawn()
+                .with_context(|| format!("failed to start {}", launch.program))?;
+            // Drop our copies of the terminal's slave side so reads see EOF once the
+            // shell exits.
+            drop(cmd);
+
+            let master = File::from(pty.master);
+            let mut reader = master.try_clone()?;
+            std::thread::Builder::new()
+                .name("shell-session-pty".to_string())
+                .spawn(move || {
+                    let mut buf = [0u8; 8192];
+                    loop {
+                        match reader.read(&mut buf) {
+                            Ok(0) | Err(_) => break,
+                            Ok(n) => {
+                                if let Ok(mut state) = output.lock() {
+                                    state.push(&buf[..n]);
+                                }
+                                notify.notify_waiters();
+                            }
+                        }
+                    }
+                    if let Ok(mut state) = output.lock() {
+                        state.closed = true;
+                    }
+                    notify.notify_waiters();
+                })?;
+            Ok(Self { child, master })
+        }
+
+        pub(super) fn writer(&self) -> Result<File> {
+            Ok(self.master.try_clone()?)
+        }
+
+        /// Hangs up the shell's process group, then kills it if it is still around
+        /// after `grace`. Blocks until the process is reaped.
+        pub(super) fn shutdown(mut self, grace: Duration) {
+            let pgid = Pid::from_raw(self.child.id() as i32);
+            let _ = killpg(pgid, Signal::SIGHUP);
+            let deadline = Instant::now() + grace;
+            while Instant::now() < deadline {
+                if let Ok(Some(_)) = self.child.try_wait() {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            let _ = killpg(pgid, Signal::SIGKILL);
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+
+    impl Drop for PtyProcess {
+        fn drop(&mut self) {
+            if let Ok(None) = self.child.try_wait() {
+                let _ = killpg(Pid::from_raw(self.child.id() as i32), Signal::SIGKILL);
+                let _ = self.child.kill();
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod pty {
+    use std::fs::File;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use anyhow::{bail, Result};
+    use tokio::sync::Notify;
+
+    use super::OutputState;
+    use crate::sandbox::ShellLaunch;
+
+    pub(super) struct PtyProcess;
+
+    impl PtyProcess {
+        pub(super) fn spawn(
+            _launch: &ShellLaunch,
+            _output: Arc<Mutex<OutputState>>,
+            _notify: Arc<Notify>,
+        ) -> Result<Self> {
+            bail!("persistent shell sessions are only supported on Unix hosts")
+        }
+
+        pub(super) fn writer(&self) -> Result<File> {
+            bail!("persistent shell sessions are only supported on Unix hosts")
+        }
+
+        pub(super) fn shutdown(self, _grace: Duration) {}
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn host_opts(dir: &std::path::Path) -> SandboxExecOptions {
+        SandboxExecOptions {
+            timeout: Duration::from_secs(10),
+            working_dir: Some(dir.to_path_buf()),
+            envs: HashMap::new(),
+            env_files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_find_sentinel_strips_line_and_reads_status() {
+        let buf = b"hello\r\n\r\n__MICROCLAW_DONE_ab_17__\r\nafter";
+        let (start, end, code) = find_sentinel(buf, "ab").unwrap();
+        assert_eq!(&buf[..start], b"hello\r\n");
+        assert_eq!(&buf[end..], b"after");
+        assert_eq!(code, 17);
+        assert!(find_sentinel(b"x\n__MICROCLAW_DONE_ab_1", "ab").is_none());
+        assert!(find_sentinel(b"x\n__MICROCLAW_DONE_ab_1__\n", "cd").is_none());
+        // An echoed copy of the wrapped command is not a sentinel.
+        assert!(find_sentinel(wrap_command("true", "ab").as_bytes(), "ab").is_none());
+    }
+
+    #[test]
+    fn test_drainable_len_holds_back_partial_sentinel_and_utf8() {
+        assert_eq!(drainable_len(b"abc\n__MICRO", true, &[]), 3);
+        assert_eq!(drainable_len(b"abc\r\n__MICROCLAW_DONE_x_0_", true, &[]), 3);
+        assert_eq!(drainable_len(b"abc\r\n", true, &[]), 3);
+        assert_eq!(drainable_len(b"abc\r\n", false, &[]), 5);
+        assert_eq!(drainable_len(b"abc\ndef", true, &[]), 7);
+        assert_eq!(drainable_len("é".as_bytes(), true, &[]), 2);
+        assert_eq!(drainable_len(&"é".as_bytes()[..1], false, &[]), 0);
+    }
+
+    #[test]
+    fn test_drainable_len_never_splits_a_secret() {
+        let secrets = vec![b"hunter2secret".to_vec()];
+        // A secret that is still arriving is held back entirely.
+        assert_eq!(drainable_len(b"token=hunter2se", true, &secrets), 6);
+        assert_eq!(drainable_len(b"token=h", true, &secrets), 6);
+        // One that arrived whole is handed out whole.
+        assert_eq!(drainable_len(b"token=hunter2secret", true, &secrets), 19);
+        // Finished output has nothing left to arrive.
+        assert_eq!(drainable_len(b"token=hunter2se", false, &secrets), 15);
+    }
+
+    #[tokio::test]
+    async fn test_host_session_keeps_state_between_commands() {
+        let dir = std::env::temp_dir().join(format!("microclaw_shell_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        let manager = ShellSessionManager::new(ShellSessionConfig::default(), None);
+        let (session, created) = manager.start("test-1", &host_opts(&dir)).await.unwrap();
+        assert!(created);
+
+        // An unbounded wait saturates instead of overflowing the deadline.
+        let out = session
+            .exec("cd sub && export MC_VALUE=kept", Duration::MAX)
+            .await
+            .unwrap();
+        assert_eq!(out.exit_code, Some(0));
+        let out = session
+            .exec(
+                "basename \"$PWD\"; echo $MC_VALUE; false",
+                Duration::from_secs(10),
+            )
+            .await
+            .unwrap();
+        assert_eq!(out.output, "sub\nkept\n");
+        assert_eq!(out.exit_code, Some(1));
+
+        let (_, created) = manager.start("test-1", &host_opts(&dir)).await.unwrap();
+        assert!(!created);
+        assert!(manager.kill("test-1").await);
+        assert!(manager.get("test-1").await.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_host_session_timeout_then_read_output() {
+        let dir = std::env::temp_dir().join(format!("microclaw_shell_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manager = ShellSessionManager::new(ShellSessionConfig::default(), None);
+        let (session, _) = manager.start("test-2", &host_opts(&dir)).await.unwrap();
+
+        let out = session
+            .exec(
+                "echo first; sleep 1; echo second",
+                Duration::from_millis(300),
+            )
+            .await
+            .unwrap();
+        assert!(out.running);
+        assert_eq!(out.exit_code, None);
+        assert!(session
+            .exec("echo busy", Duration::from_secs(1))
+            .await
+            .is_err());
+
+        let mut collected = out.output;
+        let mut exit_code = None;
+        for _ in 0..20 {
+            let more = session.read_output(Duration::from_secs(1)).await.unwrap();
+            collected.push_str(&more.output);
+            if more.exit_code.is_some() {
+                exit_code = more.exit_code;
+                break;
+            }
+        }
+        assert_eq!(collected, "first\nsecond\n");
+        assert_eq!(exit_code, Some(0));
+        let out = session
+            .exec("echo next", Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(out.output, "next\n");
+        manager.kill("test-2").await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_reap_idle_removes_exited_sessions() {
+        let dir = std::env::temp_dir().join(format!("microclaw_shell_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manager = ShellSessionManager::new(ShellSessionConfig::default(), None);
+        let (session, _) = manager.start("test-3", &host_opts(&dir)).await.unwrap();
+        let out = session.exec("exit", Duration::from_secs(10)).await.unwrap();
+        assert!(out.closed);
+        assert_eq!(manager.reap_idle().await, 1);
+        assert!(manager.get("test-3").await.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,8 +1,10 @@
 use std::collections::{HashMap, HashSet};
-use std::sync::{Mutex, OnceLock};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant};
 
 use microclaw_core::text::floor_char_boundary;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
 use reqwest::Url;
 use tracing::warn;
 
@@ -43,6 +45,31 @@ fn http_client_no_redirect(timeout_secs: u64) -> reqwest::Client {
     client
 }
 
+/// Client for page fetches when private targets are blocked. Every connection
+/// goes through [`PrivateNetworkGuard`], so the addresses that were checked are
+/// the addresses that get dialed and DNS rebinding has nothing to race.
+fn guarded_http_client(timeout_secs: u64, guard: PrivateNetworkGuard) -> reqwest::Client {
+    static CLIENTS: OnceLock<Mutex<HashMap<(u64, PrivateNetworkGuard), reqwest::Client>>> =
+        OnceLock::new();
+    let cache = CLIENTS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    let key = (timeout_secs, guard);
+    if let Some(client) = cache.get(&key) {
+        return client.clone();
+    }
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(timeout_secs))
+        .redirect(reqwest::redirect::Policy::none())
+        .user_agent("MicroClaw/1.0")
+        .dns_resolver(Arc::new(key.1.clone()))
+        // A proxy resolves the target itself, which would bypass the guard.
+        .no_proxy()
+        .build()
+        .expect("failed to build HTTP client");
+    cache.insert(key, client.clone());
+    client
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum WebFetchFeedMode {
@@ -94,6 +121,15 @@ pub struct WebFetchUrlValidationConfig {
     pub allowlist_hosts: Vec<String>,
     #[serde(default)]
     pub denylist_hosts: Vec<String>,
+    /// Reject targets that are, or resolve to, private, loopback, link-local,
+    /// CGNAT or IPv6 unique-local addresses. Only addresses in
+    /// `allowed_private_networks` are exempt; `allowlist_hosts` does not lift it.
+    #[serde(default = "default_enabled")]
+    pub block_private_networks: bool,
+    /// IPs or CIDR ranges (`10.0.0.0/8`, `fd00::/8`) that may be fetched even
+    /// though they are private.
+    #[serde(default)]
+    pub allowed_private_networks: Vec<String>,
     #[serde(default)]
     pub feed_sync: WebFetchFeedSyncConfig,
 }
@@ -173,6 +209,8 @@ impl Default for WebFetchUrlValidationConfig {
             allowed_schemes: default_allowed_schemes(),
             allowlist_hosts: Vec::new(),
             denylist_hosts: Vec::new(),
+            block_private_networks: default_enabled(),
+            allowed_private_networks: Vec::new(),
             feed_sync: WebFetchFeedSyncConfig::default(),
         }
     }
@@ -192,8 +230,214 @@ impl WebFetchUrlValidationConfig {
 
         normalize_host_list(&mut self.allowlist_hosts);
         normalize_host_list(&mut self.denylist_hosts);
+        self.allowed_private_networks = self
+            .allowed_private_networks
+            .drain(..)
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect();
         self.feed_sync.normalize();
     }
+
+    fn private_network_guard(&self) -> Option<PrivateNetworkGuard> {
+        if !self.enabled || !self.block_private_networks {
+            return None;
+        }
+        Some(PrivateNetworkGuard {
+            allowed_networks: self
+                .allowed_private_networks
+                .iter()
+                .filter_map(|v| {
+                    let parsed = IpNetwork::parse(v);
+                    if parsed.is_none() {
+                        warn!(entry = v, "Ignoring invalid allowed_private_networks entry");
+                    }
+                    parsed
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Whether `ip` is an address web_fetch must not reach by default: private
+/// (RFC 1918), loopback, link-local (including cloud metadata at 169.254.169.254),
+/// CGNAT (100.64.0.0/10), benchmarking (198.18.0.0/15), IETF protocol assignments
+/// (192.0.0.0/24), multicast (224.0.0.0/4, ff00::/8), reserved (240.0.0.0/4),
+/// unspecified, IPv6 unique-local / link-local, or deprecated IPv4-compatible
+/// (`::a.b.c.d`). IPv6 addresses that carry an IPv4 address (mapped, NAT64,
+/// 6to4) are judged by their IPv4 part.
+pub fn is_private_network_addr(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_private_ipv4(v4),
+        IpAddr::V6(v6) => {
+            if let Some(v4) = embedded_ipv4(v6) {
+                return is_private_ipv4(v4);
+            }
+            let seg = v6.segments();
+            let first = seg[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || seg[..6] == [0; 6] // ::/96 deprecated IPv4-compatible
+                || (first & 0xfe00) == 0xfc00 // fc00::/7 unique local
+                || (first & 0xffc0) == 0xfe80 // fe80::/10 link local
+                || (first & 0xffc0) == 0xfec0 // fec0::/10 deprecated site local
+        }
+    }
+}
+
+fn is_private_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 198 && (b == 18 || b == 19))
+        || (a == 192 && b == 0 && c == 0)
+        || a >= 240
+}
+
+/// The IPv4 address an IPv6 address routes to: IPv4-mapped (`::ffff:0:0/96`),
+/// NAT64 (`64:ff9b::/96`) or 6to4 (`2002::/16`).
+fn embedded_ipv4(v6: Ipv6Addr) -> Option<Ipv4Addr> {
+    if let Some(v4) = v6.to_ipv4_mapped() {
+        return Some(v4);
+    }
+    let seg = v6.segments();
+    let from_segments = |hi: u16, lo: u16| Ipv4Addr::from((u32::from(hi) << 16) | u32::from(lo));
+    if seg[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        return Some(from_segments(seg[6], seg[7]));
+    }
+    if seg[0] == 0x2002 {
+        return Some(from_segments(seg[1], seg[2]));
+    }
+    None
+}
+
+/// An IP address or CIDR range from `allowed_private_networks`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    fn parse(raw: &str) -> Option<Self> {
+        let (addr, prefix) = match raw.split_once('/') {
+            Some((addr, prefix)) => (addr.trim(), Some(prefix.trim().parse::<u8>().ok()?)),
+            None => (raw.trim(), None),
+        };
+        let addr: IpAddr = addr.trim_matches(|c| c == '[' || c == ']').parse().ok()?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) if self.addr.is_ipv4() => match embedded_ipv4(v6) {
+                Some(v4) => IpAddr::V4(v4),
+                None => return false,
+            },
+            other => other,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Refuses to hand out private addresses unless the address itself is in an
+/// allowed network. Host names never exempt an address: an allowlisted domain can
+/// still resolve, or be rebound, to an internal one.
+/// Used both for IP-literal URLs and as the HTTP client's DNS resolver.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PrivateNetworkGuard {
+    allowed_networks: Vec<IpNetwork>,
+}
+
+impl PrivateNetworkGuard {
+    fn check(&self, host: &str, ip: IpAddr) -> Result<(), BlockedAddress> {
+        if !is_private_network_addr(ip) || self.allowed_networks.iter().any(|net| net.contains(ip))
+        {
+            return Ok(());
+        }
+        Err(BlockedAddress {
+            host: host.to_string(),
+            ip,
+        })
+    }
+}
+
+impl Resolve for PrivateNetworkGuard {
+    fn resolve(&self, name: Name) -> Resolving {
+        let guard = self.clone();
+        let host = name.as_str().trim_end_matches('.').to_ascii_lowercase();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            // One private answer poisons the whole set; the connector may try any of them.
+            for addr in &addrs {
+                guard.check(&host, addr.ip())?;
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[derive(Debug)]
+struct BlockedAddress {
+    host: String,
+    ip: IpAddr,
+}
+
+impl std::fmt::Display for BlockedAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.host.parse::<IpAddr>().is_ok() {
+            write!(
+                f,
+                "URL host '{}' is a private or internal address",
+                self.host
+            )
+        } else {
+            write!(
+                f,
+                "URL host '{}' resolves to private or internal address {}",
+                self.host, self.ip
+            )
+        }
+    }
+}
+
+impl std::error::Error for BlockedAddress {}
+
+/// Surface a guard rejection from deep inside reqwest's error chain.
+fn describe_request_error(err: &reqwest::Error) -> String {
+    let mut source = std::error::Error::source(err);
+    while let Some(inner) = source {
+        if let Some(blocked) = inner.downcast_ref::<BlockedAddress>() {
+            return blocked.to_string();
+        }
+        source = inner.source();
+    }
+    err.to_string()
 }
 
 fn normalize_host_list(hosts: &mut Vec<String>) {
@@ -446,6 +690,19 @@ pub fn validate_web_fetch_url(
         return Err(format!("URL host '{}' is not in allowlist", host));
     }
 
+    // Hostnames are checked by the resolver at connect time; literals never reach it.
+    if let Some(guard) = config.private_network_guard() {
+        let literal = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>();
+        if let Ok(ip) = literal {
+            guard
+                .check(&ip.to_string(), ip)
+                .map_err(|blocked| blocked.to_string())?;
+        }
+    }
+
     Ok(())
 }
 
@@ -465,10 +722,230 @@ pub async fn fetch_url_with_timeout_and_validation(
     validation: WebContentValidationConfig,
     url_validation: WebFetchUrlValidationConfig,
 ) -> Result<String, String> {
+    match fetch_url_content(
+        url,
+        timeout_secs,
+        validation,
+        url_validation,
+        &WebFetchOptions::default(),
+    )
+    .await?
+    {
+        WebFetchContent::Text(text) => Ok(text),
+        WebFetchContent::Image { media_type, data } => {
+            Ok(format!("[{media_type} image, {} bytes]", data.len()))
+        }
+    }
+}
+
+/// Bytes of text returned per call when the caller does not ask for a page size.
+pub const DEFAULT_PAGE_BYTES: usize = 20_000;
+/// Upper bound for a caller-supplied `max_bytes`.
+pub const MAX_PAGE_BYTES: usize = 100_000;
+/// Responses larger than this are refused rather than buffered.
+const MAX_DOWNLOAD_BYTES: usize = 10 * 1024 * 1024;
+/// Largest image handed to the model; matches the common provider per-image limit.
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WebFetchOptions {
+    /// Byte offset into the extracted text where the returned page starts.
+    pub offset: usize,
+    /// Page size in bytes; defaults to [`DEFAULT_PAGE_BYTES`], capped at [`MAX_PAGE_BYTES`].
+    pub max_bytes: Option<usize>,
+    /// JSONPath applied to JSON responses before they are pretty-printed.
+    pub json_path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebFetchContent {
+    /// Extracted text, already paginated.
+    Text(String),
+    /// Raw image bytes for vision-capable models.
+    Image { media_type: String, data: Vec<u8> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodyKind {
+    Html,
+    Json,
+    Text,
+    Pdf,
+    Image(&'static str),
+    Unsupported,
+}
+
+fn sniff_image(body: &[u8]) -> Option<&'static str> {
+    if body.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if body.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if body.starts_with(b"GIF87a") || body.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if body.len() >= 12 && &body[..4] == b"RIFF" && &body[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+fn looks_like_html(text: &str) -> bool {
+    let head: String = text
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .chars()
+        .take(64)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    head.starts_with("<!doctype html") || head.starts_with("<html") || head.starts_with("<head")
+}
+
+/// Decide how to read a body from its declared media type, falling back to the bytes
+/// themselves when the server is vague (`application/octet-stream` or nothing).
+fn classify_body(media_type: &str, body: &[u8]) -> BodyKind {
+    if crate::pdf_text::is_pdf(body) {
+        return BodyKind::Pdf;
+    }
+    if let Some(image) = sniff_image(body) {
+        return BodyKind::Image(image);
+    }
+    let is_binary = body[..body.len().min(1024)].contains(&0);
+    match media_type {
+        "text/html" | "application/xhtml+xml" => BodyKind::Html,
+        "application/json" | "text/json" => BodyKind::Json,
+        t if t.ends_with("+json") => BodyKind::Json,
+        "application/pdf" => BodyKind::Pdf,
+        t if t.starts_with("image/") && !t.ends_with("+xml") => BodyKind::Unsupported,
+        t if t.starts_with("text/")
+            || t.ends_with("+xml")
+            || matches!(
+                t,
+                "application/xml"
+                    | "application/javascript"
+                    | "application/x-yaml"
+                    | "application/yaml"
+                    | "application/toml"
+            ) =>
+        {
+            if looks_like_html(&String::from_utf8_lossy(&body[..body.len().min(256)])) {
+                BodyKind::Html
+            } else {
+                BodyKind::Text
+            }
+        }
+        "" | "application/octet-stream" | "binary/octet-stream" if !is_binary => {
+            let head = String::from_utf8_lossy(&body[..body.len().min(256)]);
+            if looks_like_html(&head) {
+                BodyKind::Html
+            } else if serde_json::from_slice::<serde_json::Value>(body).is_ok() {
+                BodyKind::Json
+            } else {
+                BodyKind::Text
+            }
+        }
+        _ if !is_binary && std::str::from_utf8(body).is_ok() => BodyKind::Text,
+        _ => BodyKind::Unsupported,
+    }
+}
+
+fn decode_text(body: &[u8], charset: Option<&str>) -> String {
+    let encoding = charset
+        .and_then(|label| encoding_rs::Encoding::for_label(label.trim().as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    encoding.decode(body).0.into_owned()
+}
+
+fn render_json(text: &str, json_path: Option<&str>) -> Result<String, String> {
+    let value: serde_json::Value = match serde_json::from_str(text) {
+        Ok(v) => v,
+        // Mislabelled or truncated JSON is still worth reading as text.
+        Err(_) if json_path.is_none() => return Ok(text.to_string()),
+        Err(e) => return Err(format!("response is not valid JSON: {e}")),
+    };
+    let selected = match json_path.map(str::trim).filter(|p| !p.is_empty()) {
+        None => value,
+        Some(path) => {
+            let mut matches = crate::json_path::select(&value, path)?;
+            match matches.len() {
+                0 => return Err(format!("JSONPath '{path}' matched nothing")),
+                1 => matches.remove(0).clone(),
+                _ => serde_json::Value::Array(matches.into_iter().cloned().collect()),
+            }
+        }
+    };
+    serde_json::to_string_pretty(&selected).map_err(|e| e.to_string())
+}
+
+/// Cut one page out of `text`, with a footer telling the caller how to continue.
+fn paginate(text: &str, offset: usize, max_bytes: usize) -> Result<String, String> {
+    let total = text.len();
+    if offset == 0 && total <= max_bytes {
+        return Ok(text.to_string());
+    }
+    if offset >= total {
+        return Err(format!(
+            "offset {offset} is past the end of the content ({total} bytes)"
+        ));
+    }
+    let start = floor_char_boundary(text, offset);
+    let mut end = floor_char_boundary(text, start.saturating_add(max_bytes).min(total));
+    if end == start {
+        end = text[start..]
+            .chars()
+            .next()
+            .map_or(total, |c| start + c.len_utf8());
+    }
+    let footer = if end < total {
+        format!(
+            "[Showing bytes {start}-{end} of {total}. Call again with offset={end} to continue.]"
+        )
+    } else {
+        format!("[Showing bytes {start}-{end} of {total}; end of content.]")
+    };
+    Ok(format!("{}\n\n{footer}", &text[start..end]))
+}
+
+async fn read_body_limited(mut resp: reqwest::Response) -> Result<Vec<u8>, String> {
+    let too_large = || {
+        format!(
+            "response body exceeds {} MB limit",
+            MAX_DOWNLOAD_BYTES / (1024 * 1024)
+        )
+    };
+    if resp
+        .content_length()
+        .is_some_and(|len| len > MAX_DOWNLOAD_BYTES as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(|e| e.to_string())? {
+        if body.len() + chunk.len() > MAX_DOWNLOAD_BYTES {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Fetch `url` and turn the body into something the model can read: HTML is
+/// reduced to its main text, PDFs to their extracted text, JSON is pretty-printed
+/// (optionally narrowed by `options.json_path`), other text passes through and
+/// images come back as raw bytes. Text results are paginated by `options`.
+pub async fn fetch_url_content(
+    url: &str,
+    timeout_secs: u64,
+    validation: WebContentValidationConfig,
+    url_validation: WebFetchUrlValidationConfig,
+    options: &WebFetchOptions,
+) -> Result<WebFetchContent, String> {
     let effective_url_validation = resolve_url_validation_config(url_validation).await?;
     validate_web_fetch_url(url, effective_url_validation.clone())?;
 
-    let client = http_client_no_redirect(timeout_secs.max(1));
+    let client = match effective_url_validation.private_network_guard() {
+        Some(guard) => guarded_http_client(timeout_secs.max(1), guard),
+        None => http_client_no_redirect(timeout_secs.max(1)),
+    };
     let mut current_url = Url::parse(url).map_err(|e| format!("invalid URL: {e}"))?;
     let mut redirects = 0usize;
 
@@ -477,7 +954,7 @@ pub async fn fetch_url_with_timeout_and_validation(
             .get(current_url.clone())
             .send()
             .await
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| describe_request_error(&e))?;
 
         if !resp.status().is_redirection() {
             break resp;
@@ -505,9 +982,53 @@ pub async fn fetch_url_with_timeout_and_validation(
         return Err(format!("HTTP {}", resp.status()));
     }
 
-    let body = resp.text().await.map_err(|e| e.to_string())?;
-    let primary = extract_primary_html(&body);
-    let text = html_to_text(primary);
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let mut params = content_type.split(';');
+    let media_type = params.next().unwrap_or("").trim().to_string();
+    let charset = params
+        .filter_map(|p| p.trim().strip_prefix("charset="))
+        .map(|c| c.trim_matches('"').to_string())
+        .next();
+    let body = read_body_limited(resp).await?;
+
+    let text = match classify_body(&media_type, &body) {
+        BodyKind::Image(image_type) => {
+            if body.len() > MAX_IMAGE_BYTES {
+                return Err(format!(
+                    "image is {} bytes; the limit is {} MB",
+                    body.len(),
+                    MAX_IMAGE_BYTES / (1024 * 1024)
+                ));
+            }
+            return Ok(WebFetchContent::Image {
+                media_type: image_type.to_string(),
+                data: body,
+            });
+        }
+        BodyKind::Unsupported => {
+            let shown = if media_type.is_empty() {
+                "unknown binary"
+            } else {
+                media_type.as_str()
+            };
+            return Err(format!("unsupported content type: {shown}"));
+        }
+        BodyKind::Pdf => crate::pdf_text::extract_pdf_text(body).await?,
+        BodyKind::Html => {
+            let html = decode_text(&body, charset.as_deref());
+            html_to_text(extract_primary_html(&html))
+        }
+        BodyKind::Json => render_json(
+            &decode_text(&body, charset.as_deref()),
+            options.json_path.as_deref(),
+        )?,
+        BodyKind::Text => decode_text(&body, charset.as_deref()),
+    };
 
     if let Err(failure) = validate_web_content_with_config(&text, validation) {
         warn!(
@@ -517,13 +1038,11 @@ pub async fn fetch_url_with_timeout_and_validation(
         return Err(failure.message());
     }
 
-    const MAX_BYTES: usize = 20_000;
-    if text.len() > MAX_BYTES {
-        let truncated = &text[..floor_char_boundary(&text, MAX_BYTES)];
-        Ok(format!("{truncated}\n\n[Truncated at 20KB]"))
-    } else {
-        Ok(text)
-    }
+    let max_bytes = options
+        .max_bytes
+        .unwrap_or(DEFAULT_PAGE_BYTES)
+        .clamp(1, MAX_PAGE_BYTES);
+    paginate(&text, options.offset, max_bytes).map(WebFetchContent::Text)
 }
 
 pub async fn fetch_url(url: &str) -> Result<String, String> {
@@ -541,9 +1060,11 @@ mod tests {
     use tokio::time::{timeout, Duration};
 
     use super::{
-        fetch_url_with_timeout_and_validation, resolve_and_validate_redirect_target,
-        resolve_url_validation_config, validate_web_fetch_url, WebFetchFeedFormat,
-        WebFetchFeedMode, WebFetchFeedSource, WebFetchFeedSyncConfig, WebFetchUrlValidationConfig,
+        classify_body, fetch_url_content, fetch_url_with_timeout_and_validation,
+        is_private_network_addr, paginate, resolve_and_validate_redirect_target,
+        resolve_url_validation_config, validate_web_fetch_url, BodyKind, IpNetwork,
+        WebFetchContent, WebFetchFeedFormat, WebFetchFeedMode, WebFetchFeedSource,
+        WebFetchFeedSyncConfig, WebFetchOptions, WebFetchUrlValidationConfig,
     };
     use crate::web_content_validation::WebContentValidationConfig;
 
@@ -608,9 +1129,119 @@ mod tests {
             allowed_schemes: vec!["https".into()],
             allowlist_hosts: vec!["allowed.com".into()],
             denylist_hosts: vec!["allowed.com".into()],
+            block_private_networks: true,
+            allowed_private_networks: Vec::new(),
             feed_sync: WebFetchFeedSyncConfig::default(),
         };
-        assert!(validate_web_fetch_url("ftp://bad", cfg).is_ok());
+        assert!(validate_web_fetch_url("ftp://bad", cfg.clone()).is_ok());
+        assert!(validate_web_fetch_url("http://169.254.169.254/", cfg).is_ok());
+    }
+
+    #[test]
+    fn private_network_classification() {
+        for ip in [
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "198.18.0.1",
+            "198.19.255.254",
+            "192.0.0.8",
+            "240.0.0.1",
+            "255.255.255.255",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+            "2002:7f00:1::1",
+            "2002:a9fe:a9fe::",
+            "224.0.0.1",
+            "239.255.255.250",
+            "ff02::1",
+            "ff0e::fb",
+            "::7f00:1",
+            "::808:808",
+            "::a9fe:a9fe",
+        ] {
+            assert!(is_private_network_addr(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "8.8.8.8",
+            "100.128.0.1",
+            "172.32.0.1",
+            "2606:4700::1111",
+            "198.20.0.1",
+            "192.0.2.1",
+            "64:ff9b::808:808",
+            "2002:808:808::1",
+        ] {
+            assert!(!is_private_network_addr(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn ip_network_parses_and_matches() {
+        let net = IpNetwork::parse("10.0.0.0/8").unwrap();
+        assert!(net.contains("10.200.1.1".parse().unwrap()));
+        assert!(net.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(net.contains("64:ff9b::a00:1".parse().unwrap()));
+        assert!(!net.contains("11.0.0.1".parse().unwrap()));
+        let single = IpNetwork::parse("fd00::1").unwrap();
+        assert!(single.contains("fd00::1".parse().unwrap()));
+        assert!(!single.contains("fd00::2".parse().unwrap()));
+        assert!(IpNetwork::parse("10.0.0.0/33").is_none());
+        assert!(IpNetwork::parse("example.com").is_none());
+    }
+
+    #[test]
+    fn url_validation_blocks_private_ip_literals() {
+        for url in [
+            "http://169.254.169.254/latest/meta-data/",
+            "http://127.0.0.1:8080/",
+            "http://2130706433/",
+            "http://[::1]/",
+            "http://[fd12::1]/",
+            "http://100.64.0.1/",
+        ] {
+            let err =
+                validate_web_fetch_url(url, WebFetchUrlValidationConfig::default()).expect_err(url);
+            assert!(err.contains("private or internal"), "{url}: {err}");
+        }
+        assert!(validate_web_fetch_url(
+            "http://93.184.216.34/",
+            WebFetchUrlValidationConfig::default()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn url_validation_private_networks_can_be_allowlisted() {
+        let cfg = WebFetchUrlValidationConfig {
+            allowed_private_networks: vec!["10.0.0.0/8".into(), "::1".into()],
+            ..WebFetchUrlValidationConfig::default()
+        };
+        assert!(validate_web_fetch_url("http://10.1.2.3/", cfg.clone()).is_ok());
+        assert!(validate_web_fetch_url("http://[::1]/", cfg.clone()).is_ok());
+        assert!(validate_web_fetch_url("http://192.168.0.1/", cfg).is_err());
+
+        // Allowlisting a host restricts where fetches may go; it does not exempt it.
+        let cfg = WebFetchUrlValidationConfig {
+            allowlist_hosts: vec!["127.0.0.1".into()],
+            ..WebFetchUrlValidationConfig::default()
+        };
+        assert!(validate_web_fetch_url("http://127.0.0.1/", cfg).is_err());
+
+        let cfg = WebFetchUrlValidationConfig {
+            block_private_networks: false,
+            ..WebFetchUrlValidationConfig::default()
+        };
+        assert!(validate_web_fetch_url("http://192.168.0.1/", cfg).is_ok());
     }
 
     #[tokio::test]
@@ -831,6 +1462,7 @@ mod tests {
         let url_cfg = WebFetchUrlValidationConfig {
             allowlist_hosts: vec!["localhost".to_string()],
             denylist_hosts: vec!["127.0.0.1".to_string()],
+            allowed_private_networks: vec!["127.0.0.1".to_string(), "::1".to_string()],
             ..WebFetchUrlValidationConfig::default()
         };
         let err = fetch_url_with_timeout_and_validation(
@@ -851,4 +1483,295 @@ mod tests {
             "should not request redirect target after URL policy rejection"
         );
     }
+
+    #[tokio::test]
+    async fn fetch_blocks_hostname_resolving_to_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let hit = Arc::new(AtomicBool::new(false));
+        let hit_clone = hit.clone();
+        let server = tokio::spawn(async move {
+            if let Ok(Ok((mut stream, _))) =
+                timeout(Duration::from_millis(500), listener.accept()).await
+            {
+                hit_clone.store(true, Ordering::SeqCst);
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                    .await;
+            }
+        });
+
+        let err = fetch_url_with_timeout_and_validation(
+            &format!("http://localhost:{}/", addr.port()),
+            5,
+            WebContentValidationConfig::default(),
+            WebFetchUrlValidationConfig::default(),
+        )
+        .await
+        .unwrap_err();
+
+        server.await.unwrap();
+        assert!(
+            err.contains("resolves to private or internal address"),
+            "{err}"
+        );
+        assert!(
+            !hit.load(Ordering::SeqCst),
+            "guard must reject before connecting"
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_refuses_allowlisted_host_resolving_to_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let hit = Arc::new(AtomicBool::new(false));
+        let hit_clone = hit.clone();
+        let server = tokio::spawn(async move {
+            if let Ok(Ok((mut stream, _))) =
+                timeout(Duration::from_millis(500), listener.accept()).await
+            {
+                hit_clone.store(true, Ordering::SeqCst);
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                    .await;
+            }
+        });
+
+        let url_cfg = WebFetchUrlValidationConfig {
+            allowlist_hosts: vec!["localhost".to_string()],
+            ..WebFetchUrlValidationConfig::default()
+        };
+        let err = fetch_url_with_timeout_and_validation(
+            &format!("http://localhost:{}/", addr.port()),
+            5,
+            WebContentValidationConfig::default(),
+            url_cfg,
+        )
+        .await
+        .unwrap_err();
+
+        server.await.unwrap();
+        assert!(
+            err.contains("resolves to private or internal address"),
+            "{err}"
+        );
+        assert!(!hit.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn fetch_blocks_redirect_to_private_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await;
+            let _ = stream
+                .write_all(
+                    b"HTTP/1.1 302 Found\r\nLocation: http://169.254.169.254/latest/meta-data/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await;
+        });
+
+        let url_cfg = WebFetchUrlValidationConfig {
+            allowed_private_networks: vec!["127.0.0.1".to_string()],
+            ..WebFetchUrlValidationConfig::default()
+        };
+        let err = fetch_url_with_timeout_and_validation(
+            &format!("http://localhost:{}/start", addr.port()),
+            5,
+            WebContentValidationConfig::default(),
+            url_cfg,
+        )
+        .await
+        .unwrap_err();
+
+        server.await.unwrap();
+        assert!(err.contains("169.254.169.254"), "{err}");
+        assert!(err.contains("private or internal"), "{err}");
+    }
+
+    async fn serve_once(
+        content_type: &'static str,
+        body: Vec<u8>,
+    ) -> (String, tokio::task::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await;
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes()).await;
+            let _ = stream.write_all(&body).await;
+        });
+        (format!("http://127.0.0.1:{}/resource", addr.port()), server)
+    }
+
+    fn loopback_allowed() -> WebFetchUrlValidationConfig {
+        WebFetchUrlValidationConfig {
+            allowed_private_networks: vec!["127.0.0.1".to_string()],
+            ..WebFetchUrlValidationConfig::default()
+        }
+    }
+
+    #[test]
+    fn classify_body_prefers_magic_bytes_then_media_type() {
+        assert_eq!(
+            classify_body("application/octet-stream", b"%PDF-1.7\n"),
+            BodyKind::Pdf
+        );
+        assert_eq!(
+            classify_body("text/plain", b"\x89PNG\r\n\x1a\n\0\0"),
+            BodyKind::Image("image/png")
+        );
+        assert_eq!(classify_body("text/html", b"<p>hi</p>"), BodyKind::Html);
+        assert_eq!(
+            classify_body("text/plain", b"<!DOCTYPE html><html>"),
+            BodyKind::Html
+        );
+        assert_eq!(
+            classify_body("application/vnd.api+json", b"{}"),
+            BodyKind::Json
+        );
+        assert_eq!(classify_body("", b"[1, 2]"), BodyKind::Json);
+        assert_eq!(classify_body("text/markdown", b"# Title"), BodyKind::Text);
+        assert_eq!(
+            classify_body("application/zip", b"PK\x03\x04\0\0"),
+            BodyKind::Unsupported
+        );
+        assert_eq!(classify_body("image/bmp", b"BM"), BodyKind::Unsupported);
+    }
+
+    #[test]
+    fn paginate_reports_ranges_on_char_boundaries() {
+        assert_eq!(paginate("short", 0, 100).unwrap(), "short");
+        let text = "héllo wörld";
+        let first = paginate(text, 0, 2).unwrap();
+        assert!(first.starts_with("h\n\n"), "{first}");
+        assert!(first.contains("offset=1"), "{first}");
+        let rest = paginate(text, 1, 100).unwrap();
+        assert!(rest.starts_with("éllo wörld"));
+        assert!(rest.contains("end of content"));
+        assert!(paginate(text, 100, 10)
+            .unwrap_err()
+            .contains("past the end"));
+    }
+
+    #[tokio::test]
+    async fn fetch_pretty_prints_json_and_applies_json_path() {
+        let (url, server) = serve_once(
+            "application/json; charset=utf-8",
+            br#"{"items":[{"id":1,"name":"first"},{"id":2,"name":"second"}]}"#.to_vec(),
+        )
+        .await;
+        let content = fetch_url_content(
+            &url,
+            5,
+            WebContentValidationConfig::default(),
+            loopback_allowed(),
+            &WebFetchOptions {
+                json_path: Some("$.items[*].name".to_string()),
+                ..WebFetchOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+        server.await.unwrap();
+        assert_eq!(
+            content,
+            WebFetchContent::Text("[\n  \"first\",\n  \"second\"\n]".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_passes_plain_text_through_with_pagination() {
+        let body = "line\n".repeat(10);
+        let (url, server) = serve_once("text/plain", body.clone().into_bytes()).await;
+        let content = fetch_url_content(
+            &url,
+            5,
+            WebContentValidationConfig::default(),
+            loopback_allowed(),
+            &WebFetchOptions {
+                offset: 10,
+                max_bytes: Some(10),
+                json_path: None,
+            },
+        )
+        .await
+        .unwrap();
+        server.await.unwrap();
+        let WebFetchContent::Text(text) = content else {
+            panic!("expected text");
+        };
+        assert!(
+            text.starts_with("line\nline\n\n\n[Showing bytes 10-20 of 50."),
+            "{text}"
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_returns_images_and_rejects_unknown_binary() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        let (url, server) = serve_once("image/png", png.clone()).await;
+        let content = fetch_url_content(
+            &url,
+            5,
+            WebContentValidationConfig::default(),
+            loopback_allowed(),
+            &WebFetchOptions::default(),
+        )
+        .await
+        .unwrap();
+        server.await.unwrap();
+        assert_eq!(
+            content,
+            WebFetchContent::Image {
+                media_type: "image/png".to_string(),
+                data: png,
+            }
+        );
+
+        let (url, server) = serve_once("application/zip", b"PK\x03\x04\0\0".to_vec()).await;
+        let err = fetch_url_content(
+            &url,
+            5,
+            WebContentValidationConfig::default(),
+            loopback_allowed(),
+            &WebFetchOptions::default(),
+        )
+        .await
+        .unwrap_err();
+        server.await.unwrap();
+        assert!(
+            err.contains("unsupported content type: application/zip"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_extracts_pdf_text() {
+        let pdf =
+            crate::pdf_text::single_page_pdf(b"BT /F1 12 Tf 72 720 Td (Quarterly report) Tj ET");
+        let (url, server) = serve_once("application/pdf", pdf).await;
+        let content = fetch_url_content(
+            &url,
+            5,
+            WebContentValidationConfig::default(),
+            loopback_allowed(),
+            &WebFetchOptions::default(),
+        )
+        .await
+        .unwrap();
+        server.await.unwrap();
+        assert_eq!(
+            content,
+            WebFetchContent::Text("Quarterly report".to_string())
+        );
+    }
 }
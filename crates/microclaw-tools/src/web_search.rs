@@ -1,7 +1,13 @@
 use std::collections::HashMap;
-use std::sync::{Mutex, OnceLock};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
 
-use crate::web_html::extract_ddg_results;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::web_html::{extract_ddg_results, html_to_text};
 
 fn http_client(timeout_secs: u64) -> reqwest::Client {
     static CLIENTS: OnceLock<Mutex<HashMap<u64, reqwest::Client>>> = OnceLock::new();
@@ -20,34 +26,649 @@ fn http_client(timeout_secs: u64) -> reqwest::Client {
     client
 }
 
-pub async fn search_ddg_with_timeout(query: &str, timeout_secs: u64) -> Result<String, String> {
-    let encoded = urlencoding::encode(query);
-    let url = format!("https://html.duckduckgo.com/html/?q={encoded}");
-    let client = http_client(timeout_secs.max(1));
+/// One search hit, normalized across backends.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    #[serde(default)]
+    pub snippet: String,
+    /// Publication date as reported by the backend, when it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+}
+
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    fn name(&self) -> &str;
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        timeout_secs: u64,
+    ) -> Result<Vec<SearchResult>, String>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebSearchProvider {
+    #[serde(alias = "ddg")]
+    Duckduckgo,
+    Searxng,
+    Brave,
+    Tavily,
+    Fixture,
+}
+
+impl WebSearchProvider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebSearchProvider::Duckduckgo => "duckduckgo",
+            WebSearchProvider::Searxng => "searxng",
+            WebSearchProvider::Brave => "brave",
+            WebSearchProvider::Tavily => "tavily",
+            WebSearchProvider::Fixture => "fixture",
+        }
+    }
+}
+
+fn default_providers() -> Vec<WebSearchProvider> {
+    vec![WebSearchProvider::Duckduckgo]
+}
+
+const fn default_max_results() -> usize {
+    8
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebSearchConfig {
+    /// Backends tried in order; the first one that answers wins.
+    #[serde(default = "default_providers")]
+    pub providers: Vec<WebSearchProvider>,
+    #[serde(default = "default_max_results")]
+    pub max_results: usize,
+    /// Base URL of a SearXNG instance with the JSON format enabled.
+    #[serde(default)]
+    pub searxng_url: Option<String>,
+    #[serde(default)]
+    pub brave_api_key: Option<String>,
+    #[serde(default)]
+    pub tavily_api_key: Option<String>,
+    /// JSON file with canned results, for offline runs and tests.
+    #[serde(default)]
+    pub fixture_path: Option<String>,
+}
+
+impl Default for WebSearchConfig {
+    fn default() -> Self {
+        Self {
+            providers: default_providers(),
+            max_results: default_max_results(),
+            searxng_url: None,
+            brave_api_key: None,
+            tavily_api_key: None,
+            fixture_path: None,
+        }
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+impl WebSearchConfig {
+    pub fn normalize(&mut self) {
+        if self.providers.is_empty() {
+            self.providers = default_providers();
+        }
+        let mut seen = Vec::new();
+        self.providers.retain(|p| {
+            let first = !seen.contains(p);
+            seen.push(*p);
+            first
+        });
+        if self.max_results == 0 {
+            self.max_results = default_max_results();
+        }
+        self.max_results = self.max_results.min(20);
+        self.searxng_url =
+            non_empty(&self.searxng_url).map(|u| u.trim_end_matches('/').to_string());
+        self.brave_api_key = non_empty(&self.brave_api_key);
+        self.tavily_api_key = non_empty(&self.tavily_api_key);
+        self.fixture_path = non_empty(&self.fixture_path);
+    }
+
+    /// Check that every selected provider has the settings it needs.
+    pub fn validate(&self) -> Result<(), String> {
+        for provider in &self.providers {
+            let missing = match provider {
+                WebSearchProvider::Duckduckgo => None,
+                WebSearchProvider::Searxng => self.searxng_url.is_none().then_some("searxng_url"),
+                WebSearchProvider::Brave => self.brave_api_key.is_none().then_some("brave_api_key"),
+                WebSearchProvider::Tavily => {
+                    self.tavily_api_key.is_none().then_some("tavily_api_key")
+                }
+                WebSearchProvider::Fixture => self.fixture_path.is_none().then_some("fixture_path"),
+            };
+            if let Some(field) = missing {
+                return Err(format!(
+                    "web_search.providers includes '{}' but web_search.{field} is not set",
+                    provider.as_str()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the configured backend chain. Call [`Self::validate`] first; a provider
+    /// missing its settings is skipped here.
+    pub fn build_backend(&self) -> FailoverSearch {
+        let backends = self
+            .providers
+            .iter()
+            .filter_map(|provider| -> Option<Arc<dyn SearchBackend>> {
+                match provider {
+                    WebSearchProvider::Duckduckgo => Some(Arc::new(DuckDuckGoBackend)),
+                    WebSearchProvider::Searxng => self
+                        .searxng_url
+                        .clone()
+                        .map(|base_url| Arc::new(SearxngBackend { base_url }) as _),
+                    WebSearchProvider::Brave => self
+                        .brave_api_key
+                        .clone()
+                        .map(|api_key| Arc::new(BraveBackend { api_key }) as _),
+                    WebSearchProvider::Tavily => self
+                        .tavily_api_key
+                        .clone()
+                        .map(|api_key| Arc::new(TavilyBackend { api_key }) as _),
+                    WebSearchProvider::Fixture => self
+                        .fixture_path
+                        .clone()
+                        .map(|path| Arc::new(FixtureBackend::from_path(path)) as _),
+                }
+            })
+            .collect();
+        FailoverSearch::new(backends)
+    }
+}
+
+/// Tries each backend in order and returns the first successful answer.
+/// An empty result list counts as an answer; only errors fail over.
+pub struct FailoverSearch {
+    backends: Vec<Arc<dyn SearchBackend>>,
+}
+
+impl FailoverSearch {
+    pub fn new(backends: Vec<Arc<dyn SearchBackend>>) -> Self {
+        Self { backends }
+    }
+
+    pub fn backend_names(&self) -> Vec<&str> {
+        self.backends.iter().map(|b| b.name()).collect()
+    }
+}
+
+#[async_trait]
+impl SearchBackend for FailoverSearch {
+    fn name(&self) -> &str {
+        "failover"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        timeout_secs: u64,
+    ) -> Result<Vec<SearchResult>, String> {
+        let mut errors = Vec::new();
+        for backend in &self.backends {
+            match backend.search(query, max_results, timeout_secs).await {
+                Ok(mut results) => {
+                    results.truncate(max_results);
+                    return Ok(results);
+                }
+                Err(e) => {
+                    warn!(backend = backend.name(), "web search backend failed: {e}");
+                    errors.push(format!("{}: {e}", backend.name()));
+                }
+            }
+        }
+        if errors.is_empty() {
+            return Err("no search backend configured".to_string());
+        }
+        Err(errors.join("; "))
+    }
+}
 
-    let resp = client.get(&url).send().await.map_err(|e| e.to_string())?;
+pub fn format_search_results(results: &[SearchResult]) -> String {
+    let mut output = String::new();
+    for (i, item) in results.iter().enumerate() {
+        output.push_str(&format!("{}. {}\n   {}\n", i + 1, item.title, item.url));
+        if let Some(date) = &item.date {
+            output.push_str(&format!("   Date: {date}\n"));
+        }
+        output.push_str(&format!("   {}\n\n", item.snippet));
+    }
+    output
+}
 
+async fn get_json(request: reqwest::RequestBuilder) -> Result<Value, String> {
+    let resp = request.send().await.map_err(|e| e.to_string())?;
     if !resp.status().is_success() {
         return Err(format!("HTTP {}", resp.status()));
     }
+    resp.json::<Value>().await.map_err(|e| e.to_string())
+}
 
-    let body = resp.text().await.map_err(|e| e.to_string())?;
-    let items = extract_ddg_results(&body, 8);
+fn str_field(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|k| value.get(*k).and_then(|v| v.as_str()))
+        .map(str::trim)
+        .find(|v| !v.is_empty())
+        .map(str::to_string)
+}
 
-    let mut output = String::new();
-    for (i, item) in items.iter().enumerate() {
-        output.push_str(&format!(
-            "{}. {}\n   {}\n   {}\n\n",
-            i + 1,
-            item.title,
-            item.url,
-            item.snippet
-        ));
+/// Map a backend's result array into [`SearchResult`]s, skipping entries without a URL.
+fn collect_results(
+    items: Option<&Value>,
+    snippet_keys: &[&str],
+    date_keys: &[&str],
+) -> Vec<SearchResult> {
+    items
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let url = str_field(item, &["url"])?;
+            Some(SearchResult {
+                title: str_field(item, &["title"])
+                    .map(|t| html_to_text(&t))
+                    .unwrap_or_else(|| url.clone()),
+                url,
+                snippet: str_field(item, snippet_keys)
+                    .map(|s| html_to_text(&s))
+                    .unwrap_or_default(),
+                date: str_field(item, date_keys),
+            })
+        })
+        .collect()
+}
+
+/// Scrapes html.duckduckgo.com. Needs no key, but breaks when the markup changes.
+pub struct DuckDuckGoBackend;
+
+#[async_trait]
+impl SearchBackend for DuckDuckGoBackend {
+    fn name(&self) -> &str {
+        "duckduckgo"
     }
 
-    Ok(output)
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        timeout_secs: u64,
+    ) -> Result<Vec<SearchResult>, String> {
+        let encoded = urlencoding::encode(query);
+        let url = format!("https://html.duckduckgo.com/html/?q={encoded}");
+        let client = http_client(timeout_secs.max(1));
+
+        let resp = client.get(&url).send().await.map_err(|e| e.to_string())?;
+
+        if !resp.status().is_success() {
+            return Err(format!("HTTP {}", resp.status()));
+        }
+
+        let body = resp.text().await.map_err(|e| e.to_string())?;
+        parse_ddg_page(&body, max_results)
+    }
+}
+
+/// Results from a DuckDuckGo HTML page. A page with neither results nor the
+/// explicit "no results" notice is a rate-limit or captcha interstitial, and is
+/// reported as an error so failover moves on to the next backend.
+fn parse_ddg_page(body: &str, max_results: usize) -> Result<Vec<SearchResult>, String> {
+    let results: Vec<SearchResult> = extract_ddg_results(body, max_results)
+        .into_iter()
+        .map(|item| SearchResult {
+            title: item.title,
+            url: item.url,
+            snippet: item.snippet,
+            date: None,
+        })
+        .collect();
+    if results.is_empty() && !body.contains("no-results") {
+        return Err("unexpected page without search results (rate limited or captcha)".into());
+    }
+    Ok(results)
+}
+
+/// A self-hosted SearXNG instance (`/search?format=json`).
+pub struct SearxngBackend {
+    pub base_url: String,
+}
+
+#[async_trait]
+impl SearchBackend for SearxngBackend {
+    fn name(&self) -> &str {
+        "searxng"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        timeout_secs: u64,
+    ) -> Result<Vec<SearchResult>, String> {
+        let request = http_client(timeout_secs.max(1))
+            .get(format!("{}/search", self.base_url))
+            .query(&[("q", query), ("format", "json")]);
+        let body = get_json(request).await?;
+        let mut results = collect_results(
+            body.get("results"),
+            &["content"],
+            &["publishedDate", "published_date"],
+        );
+        results.truncate(max_results);
+        Ok(results)
+    }
+}
+
+/// Brave Search API (`X-Subscription-Token`).
+pub struct BraveBackend {
+    pub api_key: String,
+}
+
+#[async_trait]
+impl SearchBackend for BraveBackend {
+    fn name(&self) -> &str {
+        "brave"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        timeout_secs: u64,
+    ) -> Result<Vec<SearchResult>, String> {
+        let count = max_results.clamp(1, 20).to_string();
+        let request = http_client(timeout_secs.max(1))
+            .get("https://api.search.brave.com/res/v1/web/search")
+            .header("Accept", "application/json")
+            .header("X-Subscription-Token", &self.api_key)
+            .query(&[("q", query), ("count", count.as_str())]);
+        let body = get_json(request).await?;
+        Ok(parse_brave_results(&body))
+    }
+}
+
+fn parse_brave_results(body: &Value) -> Vec<SearchResult> {
+    collect_results(
+        body.get("web").and_then(|web| web.get("results")),
+        &["description"],
+        &["page_age", "age"],
+    )
+}
+
+/// Tavily search API (bearer token).
+pub struct TavilyBackend {
+    pub api_key: String,
+}
+
+#[async_trait]
+impl SearchBackend for TavilyBackend {
+    fn name(&self) -> &str {
+        "tavily"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        timeout_secs: u64,
+    ) -> Result<Vec<SearchResult>, String> {
+        let request = http_client(timeout_secs.max(1))
+            .post("https://api.tavily.com/search")
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({
+                "query": query,
+                "max_results": max_results.clamp(1, 20),
+            }));
+        let body = get_json(request).await?;
+        Ok(collect_results(
+            body.get("results"),
+            &["content"],
+            &["published_date"],
+        ))
+    }
+}
+
+/// Canned results for offline runs. The file holds either a result array returned
+/// for every query, or an object mapping queries to arrays (with an optional `"*"`
+/// fallback entry).
+pub struct FixtureBackend {
+    source: FixtureSource,
+}
+
+enum FixtureSource {
+    Path(PathBuf),
+    Inline(Value),
+}
+
+impl FixtureBackend {
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            source: FixtureSource::Path(path.into()),
+        }
+    }
+
+    pub fn from_value(value: Value) -> Self {
+        Self {
+            source: FixtureSource::Inline(value),
+        }
+    }
+
+    fn lookup(fixtures: &Value, query: &str) -> Result<Vec<SearchResult>, String> {
+        let entries = match fixtures {
+            Value::Array(_) => Some(fixtures),
+            Value::Object(map) => map
+                .get(query)
+                .or_else(|| map.get(&query.to_lowercase()))
+                .or_else(|| map.get("*")),
+            _ => return Err("fixture must be a JSON array or object".to_string()),
+        };
+        match entries {
+            Some(entries) => serde_json::from_value(entries.clone())
+                .map_err(|e| format!("invalid fixture results: {e}")),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for FixtureBackend {
+    fn name(&self) -> &str {
+        "fixture"
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        _timeout_secs: u64,
+    ) -> Result<Vec<SearchResult>, String> {
+        let mut results = match &self.source {
+            FixtureSource::Inline(value) => Self::lookup(value, query)?,
+            FixtureSource::Path(path) => {
+                let raw = tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+                let value: Value = serde_json::from_str(&raw)
+                    .map_err(|e| format!("failed to parse {}: {e}", path.display()))?;
+                Self::lookup(&value, query)?
+            }
+        };
+        results.truncate(max_results);
+        Ok(results)
+    }
+}
+
+pub async fn search_ddg_with_timeout(query: &str, timeout_secs: u64) -> Result<String, String> {
+    let items = DuckDuckGoBackend
+        .search(query, default_max_results(), timeout_secs)
+        .await?;
+    Ok(format_search_results(&items))
 }
 
 pub async fn search_ddg(query: &str) -> Result<String, String> {
     search_ddg_with_timeout(query, 15).await
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::{
+        format_search_results, parse_brave_results, parse_ddg_page, FailoverSearch, FixtureBackend,
+        SearchBackend, SearchResult, SearxngBackend, WebSearchConfig, WebSearchProvider,
+    };
+
+    struct FailingBackend;
+
+    #[async_trait]
+    impl SearchBackend for FailingBackend {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        async fn search(&self, _: &str, _: usize, _: u64) -> Result<Vec<SearchResult>, String> {
+            Err("HTTP 429 Too Many Requests".to_string())
+        }
+    }
+
+    fn result(title: &str) -> SearchResult {
+        SearchResult {
+            title: title.to_string(),
+            url: format!("https://example.com/{title}"),
+            snippet: format!("about {title}"),
+            date: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn failover_uses_next_backend_after_error() {
+        let search = FailoverSearch::new(vec![
+            Arc::new(FailingBackend),
+            Arc::new(FixtureBackend::from_value(json!({
+                "rust": [result("one"), result("two"), result("three")],
+                "*": []
+            }))),
+        ]);
+        let results = search.search("rust", 2, 5).await.unwrap();
+        assert_eq!(results, vec![result("one"), result("two")]);
+        assert!(search.search("other", 8, 5).await.unwrap().is_empty());
+
+        let only_failing = FailoverSearch::new(vec![Arc::new(FailingBackend)]);
+        let err = only_failing.search("rust", 8, 5).await.unwrap_err();
+        assert_eq!(err, "failing: HTTP 429 Too Many Requests");
+    }
+
+    #[test]
+    fn config_validates_provider_settings() {
+        let mut cfg: WebSearchConfig =
+            serde_json::from_value(json!({"providers": ["ddg", "searxng", "ddg"]})).unwrap();
+        cfg.normalize();
+        assert_eq!(
+            cfg.providers,
+            vec![WebSearchProvider::Duckduckgo, WebSearchProvider::Searxng]
+        );
+        assert!(cfg.validate().unwrap_err().contains("searxng_url"));
+
+        cfg.searxng_url = Some(" http://search.local/ ".into());
+        cfg.normalize();
+        assert!(cfg.validate().is_ok());
+        assert_eq!(cfg.searxng_url.as_deref(), Some("http://search.local"));
+        assert_eq!(
+            cfg.build_backend().backend_names(),
+            vec!["duckduckgo", "searxng"]
+        );
+    }
+
+    #[test]
+    fn ddg_page_without_result_markup_is_an_error() {
+        let results = r#"<div class="result"><a class="result__a" href="https://example.com">Example</a>
+            <a class="result__snippet">About example</a></div>"#;
+        assert_eq!(parse_ddg_page(results, 5).unwrap().len(), 1);
+
+        let empty = r#"<div class="no-results">No results.</div>"#;
+        assert!(parse_ddg_page(empty, 5).unwrap().is_empty());
+
+        let anomaly = r#"<form id="challenge-form" action="/anomaly.js"><div class="anomaly-modal__title">Unfortunately, bots use DuckDuckGo too.</div></form>"#;
+        assert!(parse_ddg_page(anomaly, 5).unwrap_err().contains("captcha"));
+    }
+
+    #[test]
+    fn brave_results_are_normalized() {
+        let body = json!({"web": {"results": [
+            {"title": "Rust <strong>lang</strong>", "url": "https://rust-lang.org",
+             "description": "A <strong>fast</strong> language", "page_age": "2024-05-01"},
+            {"title": "no url"}
+        ]}});
+        assert_eq!(
+            parse_brave_results(&body),
+            vec![SearchResult {
+                title: "Rust lang".into(),
+                url: "https://rust-lang.org".into(),
+                snippet: "A fast language".into(),
+                date: Some("2024-05-01".into()),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn searxng_backend_reads_json_api() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 2048];
+            let n = stream.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).to_string();
+            let body = json!({"results": [{
+                "title": "SearXNG", "url": "https://docs.searxng.org",
+                "content": "Privacy-respecting metasearch", "publishedDate": "2024-01-02"
+            }]})
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+            request
+        });
+
+        let backend = SearxngBackend {
+            base_url: format!("http://127.0.0.1:{}", addr.port()),
+        };
+        let results = backend.search("rust lang", 5, 5).await.unwrap();
+        let request = server.await.unwrap();
+        assert!(
+            request.starts_with("GET /search?q=rust+lang&format=json "),
+            "{request}"
+        );
+        assert_eq!(results[0].date.as_deref(), Some("2024-01-02"));
+        assert_eq!(
+            format_search_results(&results),
+            "1. SearXNG\n   https://docs.searxng.org\n   Date: 2024-01-02\n   Privacy-respecting metasearch\n\n"
+        );
+    }
+}
@@ -37,6 +37,13 @@ pub struct A2AMessageRequest {
     #[serde(default)]
     pub source_url: Option<String>,
     pub message: String,
+    /// Optional JSON schema the peer's final answer must satisfy.
+    #[serde(
+        default,
+        alias = "response_schema",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub response_schema: Option<serde_json::Value>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -46,6 +53,9 @@ pub struct A2AMessageResponse {
     pub agent_name: String,
     pub session_key: String,
     pub response: String,
+    /// Schema-validated answer, present when the request carried a `responseSchema`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured: Option<serde_json::Value>,
 }
 
 pub fn normalize_peer_name(name: &str) -> Option<String> {
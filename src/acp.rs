@@ -18,7 +18,9 @@ use agent_client_protocol::{
     SessionId, SessionMode, SessionModeState, SessionUpdate, SetSessionModeRequest,
     SetSessionModeResponse, StopReason,
 };
-use microclaw_channels::channel::ConversationKind;
+use microclaw_channels::channel::{
+    deliver_and_store_bot_message, outbound_filter_active, ConversationKind,
+};
 use microclaw_channels::channel_adapter::{ChannelAdapter, ChannelRegistry};
 use microclaw_storage::db::{call_blocking, Database, StoredMessage};
 use std::collections::HashMap;
@@ -42,14 +44,16 @@ pub async fn serve(
     let db = Arc::new(db);
     let llm = llm::create_provider(&config);
     let embedding = embedding::create_provider(&config);
+    let hooks = Arc::new(HookManager::from_config(&config).with_db(db.clone()));
     let mut registry = ChannelRegistry::new();
     registry.register(Arc::new(AcpAdapter));
+    registry.set_outbound_filter(hooks.clone());
     let channel_registry = Arc::new(registry);
 
-    let memory_backend = Arc::new(MemoryBackend::new(
-        db.clone(),
-        MemoryMcpClient::discover(&mcp_manager),
-    ));
+    let memory_backend = Arc::new(
+        MemoryBackend::new(db.clone(), MemoryMcpClient::discover(&mcp_manager))
+            .with_hooks(hooks.clone()),
+    );
     let mut tools = ToolRegistry::new(
         &config,
         channel_registry.clone(),
@@ -66,8 +70,10 @@ pub async fn serve(
         db: db.clone(),
         memory,
         skills,
-        hooks: Arc::new(HookManager::from_config(&config).with_db(db.clone())),
+        hooks,
         llm,
+        llm_health: Arc::new(crate::llm::LlmHealthRegistry::new()),
+        llm_clients: Arc::new(crate::llm::LlmClientPool::new()),
         llm_provider_overrides: Arc::new(RwLock::new(HashMap::new())),
         llm_model_overrides: Arc::new(RwLock::new(HashMap::new())),
         embedding,
@@ -103,6 +109,7 @@ pub async fn serve(
 
             let io_result = io.await;
             notify_task.abort();
+            microclaw_tools::sandbox::remove_host_firewalls().await;
             io_result.map_err(|err| anyhow::anyhow!("ACP transport failed: {err}"))
         })
         .await
@@ -197,6 +204,22 @@ impl MicroClawAcpAgent {
         .await?;
         Ok(())
     }
+
+    /// Runs a final reply through the outbound filter, stores what it lets
+    /// through and returns the text the client should see.
+    async fn deliver_reply(&self, chat_id: i64, text: &str) -> anyhow::Result<String> {
+        let bot_username = self.app_state.config.bot_username_for_channel(ACP_CHANNEL);
+        let delivery = deliver_and_store_bot_message(
+            &self.app_state.channel_registry,
+            self.app_state.db.clone(),
+            &bot_username,
+            chat_id,
+            text,
+        )
+        .await
+        .map_err(anyhow::Error::msg)?;
+        Ok(delivery.into_visible_text())
+    }
 }
 
 #[async_trait::async_trait(?Send)]
@@ -277,21 +300,28 @@ impl Agent for MicroClawAcpAgent {
         if let Some(command_reply) =
             handle_chat_command(&self.app_state, chat_id, ACP_CHANNEL, &prompt_text, None).await
         {
+            let reply = self
+                .deliver_reply(chat_id, &command_reply)
+                .await
+                .map_err(to_acp_error)?;
             self.send_update(
                 args.session_id.clone(),
-                SessionUpdate::AgentMessageChunk(ContentChunk::new(command_reply.clone().into())),
+                SessionUpdate::AgentMessageChunk(ContentChunk::new(reply.into())),
             )?;
-            self.store_message(chat_id, "microclaw", command_reply, true)
-                .await
-                .map_err(to_acp_error)?;
             return Ok(PromptResponse::new(StopReason::EndTurn));
         }
 
+        // Deltas are raw model output; while an outbound filter may rewrite or
+        // block the reply, the client only gets the filtered text as one chunk.
+        let stream_deltas = !outbound_filter_active(&self.app_state.channel_registry).await;
         let (event_tx, mut event_rx) = mpsc::unbounded_channel::<AgentEvent>();
         let outbound_tx = self.outbound_tx.clone();
         let session_id = args.session_id.clone();
         let forward_task = tokio::spawn(async move {
             while let Some(event) = event_rx.recv().await {
+                if !stream_deltas {
+                    continue;
+                }
                 if let AgentEvent::TextDelta { delta } = event {
                     let _ = outbound_tx.send(OutboundNotification {
                         session_id: session_id.clone(),
@@ -318,9 +348,16 @@ impl Agent for MicroClawAcpAgent {
         let _ = forward_task.await;
 
         let response = result.map_err(to_acp_error)?;
-        self.store_message(chat_id, "microclaw", response, true)
+        let reply = self
+            .deliver_reply(chat_id, &response)
             .await
             .map_err(to_acp_error)?;
+        if !stream_deltas {
+            self.send_update(
+                args.session_id.clone(),
+                SessionUpdate::AgentMessageChunk(ContentChunk::new(reply.into())),
+            )?;
+        }
         Ok(PromptResponse::new(StopReason::EndTurn))
     }
 
@@ -402,6 +439,11 @@ fn available_commands() -> Vec<AvailableCommand> {
         AvailableCommand::new("/reset", "Clear session state and chat history."),
         AvailableCommand::new("/clear", "Clear session state but keep scheduled tasks."),
         AvailableCommand::new("/stop", "Cancel the active run for this ACP session."),
+        AvailableCommand::new("/undo", "Restore the working directory to a checkpoint."),
+        AvailableCommand::new(
+            "/review",
+            "Approve, edit or reject reflector-extracted memories.",
+        ),
         AvailableCommand::new("/providers", "List configured providers."),
         AvailableCommand::new("/provider", "Inspect or switch provider overrides."),
         AvailableCommand::new("/models", "List configured models."),
@@ -450,12 +492,130 @@ fn to_acp_error(err: impl std::fmt::Display) -> Error {
 
 #[cfg(test)]
 mod tests {
-    use super::flatten_prompt;
+    use super::*;
     use agent_client_protocol::{
-        BlobResourceContents, ContentBlock, EmbeddedResource, EmbeddedResourceResource,
-        ResourceLink, TextResourceContents,
+        BlobResourceContents, EmbeddedResource, EmbeddedResourceResource, ResourceLink,
+        TextResourceContents,
+    };
+    use microclaw_core::error::MicroClawError;
+    use microclaw_core::llm_types::{
+        Message, MessagesResponse, ResponseContentBlock, ToolDefinition,
     };
 
+    struct LeakyLlm;
+
+    #[async_trait::async_trait]
+    impl crate::llm::LlmProvider for LeakyLlm {
+        async fn send_message(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            Ok(MessagesResponse {
+                content: vec![ResponseContentBlock::Text {
+                    text: "the launch codes are 0000".into(),
+                }],
+                stop_reason: Some("end_turn".into()),
+                usage: None,
+            })
+        }
+    }
+
+    /// An ACP agent whose registry filters replies through a blocking
+    /// `BeforeOutboundMessage` hook.
+    fn agent_with_blocking_hook(
+        root: &std::path::Path,
+    ) -> (
+        MicroClawAcpAgent,
+        mpsc::UnboundedReceiver<OutboundNotification>,
+    ) {
+        let hook_dir = root.join("hooks").join("dlp");
+        std::fs::create_dir_all(&hook_dir).unwrap();
+        std::fs::write(
+            hook_dir.join("HOOK.md"),
+            "---\nname: dlp\nevents: [BeforeOutboundMessage]\ncommand: \"sh hook.sh\"\ntimeout_ms: 2000\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            hook_dir.join("hook.sh"),
+            "#!/bin/sh\nif cat | grep -q 'launch codes'; then\n  echo '{\"action\":\"block\",\"reason\":\"contains launch codes\"}'\nelse\n  echo '{\"action\":\"allow\"}'\nfi\n",
+        )
+        .unwrap();
+        let hooks = Arc::new(HookManager::from_test_paths(
+            root.join("hooks"),
+            root.join("runtime/hooks_state.json"),
+        ));
+
+        let runtime_dir = root.join("runtime");
+        std::fs::create_dir_all(&runtime_dir).unwrap();
+        let mut cfg = Config::test_defaults();
+        cfg.data_dir = root.to_string_lossy().to_string();
+        cfg.working_dir = root.join("tmp").to_string_lossy().to_string();
+        let db = Arc::new(Database::new(runtime_dir.to_str().unwrap()).unwrap());
+        let memory_backend = Arc::new(MemoryBackend::local_only(db.clone()));
+        let mut registry = ChannelRegistry::new();
+        registry.register(Arc::new(AcpAdapter));
+        registry.set_outbound_filter(hooks.clone());
+        let channel_registry = Arc::new(registry);
+        let app_state = Arc::new(AppState {
+            config: cfg.clone(),
+            channel_registry: channel_registry.clone(),
+            db: db.clone(),
+            memory: MemoryManager::new(runtime_dir.to_str().unwrap()),
+            skills: SkillManager::from_skills_dir(&cfg.skills_data_dir()),
+            hooks,
+            llm: Box::new(LeakyLlm),
+            llm_health: Arc::new(crate::llm::LlmHealthRegistry::new()),
+            llm_clients: Arc::new(crate::llm::LlmClientPool::new()),
+            llm_provider_overrides: Arc::new(RwLock::new(HashMap::new())),
+            llm_model_overrides: Arc::new(RwLock::new(HashMap::new())),
+            embedding: None,
+            memory_backend: memory_backend.clone(),
+            tools: ToolRegistry::new(&cfg, channel_registry, db, memory_backend),
+            metric_exporter: None,
+            trace_exporter: None,
+            log_exporter: None,
+        });
+        let (outbound_tx, outbound_rx) = mpsc::unbounded_channel();
+        (MicroClawAcpAgent::new(app_state, outbound_tx), outbound_rx)
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn prompt_reply_goes_through_outbound_hook() {
+        let root = std::env::temp_dir().join(format!("mc_acp_outbound_{}", uuid::Uuid::new_v4()));
+        let (agent, mut outbound_rx) = agent_with_blocking_hook(&root);
+        let db = agent.app_state.db.clone();
+
+        agent
+            .prompt(PromptRequest::new(
+                SessionId::new("acp-test"),
+                vec![ContentBlock::from("what are the codes?")],
+            ))
+            .await
+            .unwrap();
+
+        let mut chunks = Vec::new();
+        while let Ok(notification) = outbound_rx.try_recv() {
+            if let SessionUpdate::AgentMessageChunk(chunk) = notification.update {
+                if let ContentBlock::Text(text) = chunk.content {
+                    chunks.push(text.text);
+                }
+            }
+        }
+        assert_eq!(chunks, ["[Message blocked: contains launch codes]"]);
+
+        let chat_id = db
+            .get_chat_id_by_channel_and_title(ACP_CHANNEL, "acp-test")
+            .unwrap()
+            .unwrap();
+        let stored = db.get_all_messages(chat_id).unwrap();
+        assert!(stored.iter().all(|m| !m.is_from_bot));
+        assert!(stored.iter().all(|m| !m.content.contains("0000")));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
     #[test]
     fn flatten_prompt_keeps_text_and_resource_context() {
         let blocks = vec![
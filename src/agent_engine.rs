@@ -4,24 +4,31 @@ use tokio::sync::mpsc::UnboundedSender;
 use tracing::{debug, info, warn};
 
 use crate::config::ResolvedLlmProviderProfile;
+use crate::context_budget::{
+    estimate_messages_tokens, estimate_text_tokens, estimate_tool_definitions_tokens,
+    truncate_tool_result, ContextBudget,
+};
 use crate::hooks::HookOutcome;
+use crate::llm::{LlmRoute, LlmRouteTarget};
 use crate::memory_service::{build_db_memory_context, maybe_handle_explicit_memory_command};
 use crate::run_control;
 use crate::runtime::AppState;
-use crate::tools::ToolAuthContext;
+use crate::structured_output::{StructuredOutput, StructuredReply, FINAL_ANSWER_TOOL_NAME};
+use crate::tools::{tool_mutates_working_dir, tool_parallel_safe, ToolAuthContext, ToolResult};
 use microclaw_core::llm_types::{
-    ContentBlock, ImageSource, Message, MessageContent, ResponseContentBlock,
+    ContentBlock, ImageSource, Message, MessageContent, ResponseContentBlock, ToolDefinition,
 };
 use microclaw_core::text::floor_char_boundary;
 use microclaw_observability::traces::{
     kv, kv_int, new_span_id, new_trace_id, now_unix_nano, SpanData,
 };
-use microclaw_storage::db::{call_blocking, StoredMessage};
+use microclaw_storage::db::{call_blocking, LlmUsageEntry, StoredMessage};
+use microclaw_tools::runtime::APPROVAL_DIGEST_KEY;
+use opentelemetry_proto::tonic::trace::v1::Status;
 use opentelemetry_semantic_conventions::attribute::{
     GEN_AI_OPERATION_NAME, GEN_AI_REQUEST_MODEL, GEN_AI_SYSTEM, GEN_AI_USAGE_INPUT_TOKENS,
     GEN_AI_USAGE_OUTPUT_TOKENS, USER_ID,
 };
-use opentelemetry_proto::tonic::trace::v1::Status;
 
 #[derive(Debug, Clone, Copy)]
 pub struct AgentRequestContext<'a> {
@@ -50,6 +57,10 @@ pub enum AgentEvent {
     TextDelta {
         delta: String,
     },
+    /// Streamed extended-thinking text; only emitted when `show_thinking` is enabled.
+    ThinkingDelta {
+        delta: String,
+    },
     FinalResponse {
         text: String,
     },
@@ -73,6 +84,18 @@ pub trait AgentEngine: Send + Sync {
         image_data: Option<(String, String)>,
         event_tx: Option<&UnboundedSender<AgentEvent>>,
     ) -> anyhow::Result<String>;
+
+    /// Like `process_with_events`, but the run must end with a `final_answer`
+    /// call that `structured` accepts.
+    async fn process_structured(
+        &self,
+        state: &AppState,
+        context: AgentRequestContext<'_>,
+        override_prompt: Option<&str>,
+        image_data: Option<(String, String)>,
+        event_tx: Option<&UnboundedSender<AgentEvent>>,
+        structured: &mut StructuredOutput,
+    ) -> anyhow::Result<String>;
 }
 
 pub struct DefaultAgentEngine;
@@ -98,7 +121,27 @@ impl AgentEngine for DefaultAgentEngine {
         image_data: Option<(String, String)>,
         event_tx: Option<&UnboundedSender<AgentEvent>>,
     ) -> anyhow::Result<String> {
-        process_with_agent_impl(state, context, override_prompt, image_data, event_tx).await
+        process_with_agent_impl(state, context, override_prompt, image_data, event_tx, None).await
+    }
+
+    async fn process_structured(
+        &self,
+        state: &AppState,
+        context: AgentRequestContext<'_>,
+        override_prompt: Option<&str>,
+        image_data: Option<(String, String)>,
+        event_tx: Option<&UnboundedSender<AgentEvent>>,
+        structured: &mut StructuredOutput,
+    ) -> anyhow::Result<String> {
+        process_with_agent_impl(
+            state,
+            context,
+            override_prompt,
+            image_data,
+            event_tx,
+            Some(structured),
+        )
+        .await
     }
 }
 
@@ -117,6 +160,43 @@ pub async fn process_with_agent_with_events(
     override_prompt: Option<&str>,
     image_data: Option<(String, String)>,
     event_tx: Option<&UnboundedSender<AgentEvent>>,
+) -> anyhow::Result<String> {
+    run_with_control(state, context, override_prompt, image_data, event_tx, None).await
+}
+
+/// Run the agent and require its final answer to match `schema`. The validated
+/// value is returned alongside the reply text.
+pub async fn process_with_agent_structured(
+    state: &AppState,
+    context: AgentRequestContext<'_>,
+    override_prompt: Option<&str>,
+    schema: Value,
+    event_tx: Option<&UnboundedSender<AgentEvent>>,
+) -> anyhow::Result<StructuredReply> {
+    let mut structured = StructuredOutput::new(schema)
+        .map_err(|e| anyhow::anyhow!("invalid response schema: {e}"))?;
+    let text = run_with_control(
+        state,
+        context,
+        override_prompt,
+        None,
+        event_tx,
+        Some(&mut structured),
+    )
+    .await?;
+    let output = structured
+        .take_result()
+        .ok_or_else(|| anyhow::anyhow!("agent run ended without a structured answer: {text}"))?;
+    Ok(StructuredReply { text, output })
+}
+
+async fn run_with_control(
+    state: &AppState,
+    context: AgentRequestContext<'_>,
+    override_prompt: Option<&str>,
+    image_data: Option<(String, String)>,
+    event_tx: Option<&UnboundedSender<AgentEvent>>,
+    structured: Option<&mut StructuredOutput>,
 ) -> anyhow::Result<String> {
     let source_message_id = call_blocking(state.db.clone(), move |db| {
         db.get_recent_messages(context.chat_id, 20)
@@ -145,19 +225,52 @@ pub async fn process_with_agent_with_events(
             }
             Ok(run_control::STOPPED_TEXT.to_string())
         }
-        out = engine.process_with_events(state, context, override_prompt, image_data, event_tx) => out,
+        out = async {
+            match structured {
+                Some(structured) => {
+                    engine.process_structured(state, context, override_prompt, image_data, event_tx, structured).await
+                }
+                None => engine.process_with_events(state, context, override_prompt, image_data, event_tx).await,
+            }
+        } => out,
     };
     run_control::unregister_run(context.caller_channel, context.chat_id, run_id).await;
     result
 }
 
-fn with_high_risk_approval_marker(input: &Value) -> Value {
+/// Approval digests of previews shown to the user, by (channel, chat, tool), so the
+/// approved retry on the next turn runs against the state the user actually saw.
+#[allow(clippy::type_complexity)]
+static SHOWN_APPROVAL_DIGESTS: std::sync::LazyLock<
+    std::sync::Mutex<std::collections::HashMap<(String, i64, String), String>>,
+> = std::sync::LazyLock::new(Default::default);
+
+fn approval_digest_key(channel: &str, chat_id: i64, tool: &str) -> (String, i64, String) {
+    (channel.to_string(), chat_id, tool.to_string())
+}
+
+fn held_approval_digest(result: &ToolResult) -> Option<String> {
+    result
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("approval_digest"))
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+}
+
+fn with_high_risk_approval_marker(input: &Value, digest: Option<&str>) -> Value {
     let mut approved_input = input.clone();
     if let Some(obj) = approved_input.as_object_mut() {
         obj.insert(
             "__microclaw_high_risk_approved".to_string(),
             Value::Bool(true),
         );
+        if let Some(digest) = digest {
+            obj.insert(
+                APPROVAL_DIGEST_KEY.to_string(),
+                Value::String(digest.into()),
+            );
+        }
         return approved_input;
     }
     serde_json::json!({
@@ -263,10 +376,47 @@ fn build_provider_runtime_config(
     cfg.llm_base_url = profile.llm_base_url.clone();
     cfg.llm_user_agent = profile.llm_user_agent.clone();
     cfg.show_thinking = profile.show_thinking;
+    cfg.thinking_budget_tokens = profile.thinking_budget_tokens;
     cfg.model = model.to_string();
     cfg
 }
 
+/// The shared client for a non-default profile; `None` means `state.llm`.
+fn scoped_llm_client(
+    state: &AppState,
+    profile: &ResolvedLlmProviderProfile,
+    model: &str,
+) -> Option<std::sync::Arc<dyn crate::llm::LlmProvider>> {
+    (profile.alias != state.config.llm_provider).then(|| {
+        state.llm_clients.get_or_build(&profile.alias, || {
+            crate::llm::create_provider(&build_provider_runtime_config(state, profile, model))
+        })
+    })
+}
+
+/// Build the failover chain for the active profile: the profile itself with the
+/// effective model, then each configured fallback profile with its default model.
+fn build_llm_route<'a>(
+    state: &'a AppState,
+    profile: &ResolvedLlmProviderProfile,
+    model: &str,
+) -> LlmRoute<'a> {
+    let target_for = |profile: &ResolvedLlmProviderProfile, model: &str| {
+        let client = scoped_llm_client(state, profile, model);
+        LlmRouteTarget::new(&profile.alias, &profile.provider, model, client)
+    };
+    let mut route = LlmRoute::new(
+        state.llm.as_ref(),
+        &state.llm_health,
+        state.config.llm_failover.clone(),
+        target_for(profile, model),
+    );
+    for fallback in state.config.llm_fallback_chain(profile) {
+        route = route.with_fallback(target_for(&fallback, &fallback.default_model));
+    }
+    route
+}
+
 async fn resolve_effective_provider_and_model(
     state: &AppState,
     caller_channel: &str,
@@ -459,6 +609,7 @@ pub(crate) async fn process_with_agent_impl(
     override_prompt: Option<&str>,
     image_data: Option<(String, String)>,
     event_tx: Option<&UnboundedSender<AgentEvent>>,
+    structured: Option<&mut StructuredOutput>,
 ) -> anyhow::Result<String> {
     let trace_id = new_trace_id();
     let root_span_id = new_span_id();
@@ -471,6 +622,7 @@ pub(crate) async fn process_with_agent_impl(
         override_prompt,
         image_data,
         event_tx,
+        structured,
         &mut metrics,
         &trace_id,
         &root_span_id,
@@ -540,6 +692,7 @@ async fn process_with_agent_logic(
     override_prompt: Option<&str>,
     image_data: Option<(String, String)>,
     event_tx: Option<&UnboundedSender<AgentEvent>>,
+    mut structured: Option<&mut StructuredOutput>,
     metrics: &mut AgentMetrics,
     trace_id: &[u8],
     parent_span_id: &[u8],
@@ -552,13 +705,30 @@ async fn process_with_agent_logic(
         chat_type = context.chat_type,
         has_override_prompt = override_prompt.is_some(),
         has_image = image_data.is_some(),
+        structured_output = structured.is_some(),
         "Agent request started"
     );
 
-    if let Some(reply) =
+    // Inbound hooks see the pending user messages before anything else does
+    // (memory commands and retrieval included), so they can redact or drop them.
+    if override_prompt.is_none() {
+        if let Some(blocked) = apply_inbound_message_hooks(state, context).await? {
+            if let Some(tx) = event_tx {
+                let _ = tx.send(AgentEvent::FinalResponse {
+                    text: blocked.clone(),
+                });
+            }
+            return Ok(blocked);
+        }
+    }
+
+    let explicit_memory_reply = if structured.is_none() {
         maybe_handle_explicit_memory_command(state, chat_id, override_prompt, image_data.clone())
             .await?
-    {
+    } else {
+        None
+    };
+    if let Some(reply) = explicit_memory_reply {
         info!(
             chat_id,
             fast_path = "explicit_memory",
@@ -664,17 +834,25 @@ async fn process_with_agent_logic(
 
     let explicit_user_approval = is_explicit_user_approval(&latest_user_text_for_approval);
 
+    let (effective_profile, effective_model) =
+        resolve_effective_provider_and_model(state, context.caller_channel).await;
+    let context_budget = ContextBudget::resolve(&state.config, &effective_model);
+
     // Build system prompt
     let file_memory = state
         .memory
         .build_memory_context(context.caller_channel, chat_id);
+    // Small windows should not spend more than a tenth of the context on memories.
+    let memory_token_budget = context_budget.map_or(state.config.memory_token_budget, |b| {
+        state.config.memory_token_budget.min(b.window / 10)
+    });
     let db_memory = build_db_memory_context(
         &state.memory_backend,
         &state.db,
         state.embedding.as_ref(),
         chat_id,
         &query,
-        state.config.memory_token_budget,
+        memory_token_budget,
     )
     .await;
     let memory_context = format!("{}{}", file_memory, db_memory);
@@ -738,32 +916,12 @@ async fn process_with_agent_logic(
         return Ok("I didn't receive any message to process.".into());
     }
 
-    // Compact if messages exceed threshold
-    if messages.len() > state.config.max_session_messages {
-        let msg_count_before = messages.len();
-        archive_conversation(
-            &state.config.data_dir,
-            context.caller_channel,
-            chat_id,
-            &messages,
-        );
-        messages = compact_messages(
-            state,
-            context.caller_channel,
-            chat_id,
-            &messages,
-            state.config.compact_keep_recent,
-        )
-        .await;
-        info!(
-            chat_id,
-            messages_before = msg_count_before,
-            messages_after = messages.len(),
-            "Context compacted"
-        );
+    let mut tool_defs = state.tools.definitions().to_vec();
+    if let Some(structured) = structured.as_deref() {
+        tool_defs.push(structured.tool_definition());
+        system_prompt.push_str(&structured.prompt_section());
     }
 
-    let tool_defs = state.tools.definitions().to_vec();
     let mut skill_env_files: Vec<String> = {
         let db = state.db.clone();
         call_blocking(db, move |db| db.load_session_skill_envs(chat_id))
@@ -784,28 +942,44 @@ async fn process_with_agent_logic(
     let mut seen_failed_tool_details: std::collections::HashSet<String> =
         std::collections::HashSet::new();
     let mut empty_visible_reply_retry_attempted = false;
-    let (effective_profile, effective_model) =
-        resolve_effective_provider_and_model(state, context.caller_channel).await;
     metrics.model = effective_model.clone();
-    let scoped_provider = if effective_profile.alias != state.config.llm_provider {
-        Some(crate::llm::create_provider(&build_provider_runtime_config(
-            state,
-            &effective_profile,
-            &effective_model,
-        )))
-    } else {
-        None
-    };
+    let llm_route = build_llm_route(state, &effective_profile, &effective_model);
     let mut consecutive_send_message_calls: usize = 0;
     let mut last_tool_use_fingerprint: Option<String> = None;
     let mut repeated_tool_use_streak: usize = 0;
     const MAX_IDENTICAL_TOOL_USE_STREAK: usize = 6;
+    let mut turn_checkpoint_taken = false;
+    // Offering only `final_answer` makes providers force that call (see
+    // `llm::tool_choice`); done on the last iteration and after a free-text reply.
+    let final_answer_only: Vec<ToolDefinition> = structured
+        .as_deref()
+        .map(|s| vec![s.tool_definition()])
+        .unwrap_or_default();
+    let mut force_final_answer = false;
     for iteration in 0..state.config.max_tool_iterations {
         if let Some(tx) = event_tx {
             let _ = tx.send(AgentEvent::Iteration {
                 iteration: iteration + 1,
             });
         }
+        let request_tools = if !final_answer_only.is_empty()
+            && (force_final_answer || iteration + 1 == state.config.max_tool_iterations)
+        {
+            final_answer_only.as_slice()
+        } else {
+            tool_defs.as_slice()
+        };
+        // Tool results grow the history between requests, so the projection is
+        // re-checked before every call, not just the first.
+        compact_if_over_budget(
+            state,
+            context,
+            context_budget,
+            &system_prompt,
+            &tool_defs,
+            &mut messages,
+        )
+        .await;
         if let Ok(hook_outcome) = state
             .hooks
             .run_before_llm(
@@ -842,7 +1016,7 @@ async fn process_with_agent_logic(
         let llm_span_id = new_span_id();
         let llm_start = now_unix_nano();
 
-        let response = if let Some(tx) = event_tx {
+        let routed = if let Some(tx) = event_tx {
             let (llm_tx, mut llm_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
             let forward_tx = tx.clone();
             let forward_handle = tokio::spawn(async move {
@@ -850,57 +1024,42 @@ async fn process_with_agent_logic(
                     let _ = forward_tx.send(AgentEvent::TextDelta { delta });
                 }
             });
-            let response = if let Some(provider) = scoped_provider.as_ref() {
-                provider
-                    .send_message_stream_with_model(
-                        &system_prompt,
-                        messages.clone(),
-                        Some(tool_defs.clone()),
-                        Some(&llm_tx),
-                        Some(&effective_model),
-                    )
-                    .await?
-            } else {
-                state
-                    .llm
-                    .send_message_stream_with_model(
-                        &system_prompt,
-                        messages.clone(),
-                        Some(tool_defs.clone()),
-                        Some(&llm_tx),
-                        Some(&effective_model),
-                    )
-                    .await?
-            };
-            drop(llm_tx);
-            let _ = forward_handle.await;
-            response
-        } else if let Some(provider) = scoped_provider.as_ref() {
-            provider
-                .send_message_with_model(
+            let (thinking_tx, mut thinking_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+            let forward_tx = tx.clone();
+            let thinking_handle = tokio::spawn(async move {
+                while let Some(delta) = thinking_rx.recv().await {
+                    let _ = forward_tx.send(AgentEvent::ThinkingDelta { delta });
+                }
+            });
+            let routed = llm_route
+                .send(
                     &system_prompt,
-                    messages.clone(),
-                    Some(tool_defs.clone()),
-                    Some(&effective_model),
+                    &messages,
+                    Some(request_tools),
+                    Some(&llm_tx),
+                    effective_profile.show_thinking.then_some(&thinking_tx),
                 )
-                .await?
+                .await?;
+            drop(llm_tx);
+            drop(thinking_tx);
+            let _ = forward_handle.await;
+            let _ = thinking_handle.await;
+            routed
         } else {
-            state
-                .llm
-                .send_message_with_model(
-                    &system_prompt,
-                    messages.clone(),
-                    Some(tool_defs.clone()),
-                    Some(&effective_model),
-                )
+            llm_route
+                .send(&system_prompt, &messages, Some(request_tools), None, None)
                 .await?
         };
+        let served_by = routed.target;
+        let fallback_from = routed.fallback_from.map(str::to_string);
+        let mut response = routed.response;
+        metrics.model = served_by.model.clone();
 
         if let Some(exp) = &state.trace_exporter {
             let mut attrs = vec![
                 kv(GEN_AI_OPERATION_NAME, "chat"),
-                kv(GEN_AI_SYSTEM, &effective_profile.provider),
-                kv(GEN_AI_REQUEST_MODEL, &effective_model),
+                kv(GEN_AI_SYSTEM, &served_by.provider),
+                kv(GEN_AI_REQUEST_MODEL, &served_by.model),
             ];
             // Combine system prompt and messages for input visualization
             let input_repr = if let Ok(json) = serde_json::to_string(&messages) {
@@ -933,6 +1092,9 @@ async fn process_with_agent_logic(
                     ResponseContentBlock::ToolUse { name, input, .. } => {
                         format!("[tool_use: {}({})]", name, input)
                     }
+                    ResponseContentBlock::Thinking { thinking, .. } => {
+                        format!("[thinking: {}]", truncate_for_log(thinking, 500))
+                    }
                     _ => "[other]".to_string(),
                 })
                 .collect::<Vec<_>>()
@@ -959,25 +1121,64 @@ async fn process_with_agent_logic(
             metrics.input_tokens += usage.input_tokens as i64;
             metrics.output_tokens += usage.output_tokens as i64;
             let channel = context.caller_channel.to_string();
-            let provider = effective_profile.alias.clone();
-            let model = effective_model.clone();
+            let provider = served_by.alias.clone();
+            let model = served_by.model.clone();
             let input_tokens = i64::from(usage.input_tokens);
             let output_tokens = i64::from(usage.output_tokens);
+            let cache_read_tokens = i64::from(usage.cache_read_input_tokens);
+            let cache_write_tokens = i64::from(usage.cache_creation_input_tokens);
             let _ = call_blocking(state.db.clone(), move |db| {
-                db.log_llm_usage(
+                db.log_llm_usage_entry(&LlmUsageEntry {
                     chat_id,
-                    &channel,
-                    &provider,
-                    &model,
+                    caller_channel: &channel,
+                    provider: &provider,
+                    model: &model,
                     input_tokens,
                     output_tokens,
-                    "agent_loop",
-                )
+                    cache_read_tokens,
+                    cache_write_tokens,
+                    request_kind: "agent_loop",
+                    fallback_from: fallback_from.as_deref(),
+                })
                 .map(|_| ())
             })
             .await;
         }
 
+        if let Ok(hook_outcome) = state
+            .hooks
+            .run_after_llm(
+                chat_id,
+                context.caller_channel,
+                iteration + 1,
+                &served_by.alias,
+                &served_by.model,
+                &response,
+            )
+            .await
+        {
+            match hook_outcome {
+                HookOutcome::Block { reason } => {
+                    let text = if reason.trim().is_empty() {
+                        "Response blocked by policy hook.".to_string()
+                    } else {
+                        reason
+                    };
+                    if let Some(tx) = event_tx {
+                        let _ = tx.send(AgentEvent::FinalResponse { text: text.clone() });
+                    }
+                    return Ok(text);
+                }
+                HookOutcome::Allow { patches } => {
+                    for patch in patches {
+                        if let Some(v) = patch.get("text").and_then(|v| v.as_str()) {
+                            replace_response_text(&mut response.content, v);
+                        }
+                    }
+                }
+            }
+        }
+
         let stop_reason = response.stop_reason.as_deref().unwrap_or("end_turn");
         let (in_tok, out_tok) = response
             .usage
@@ -1091,9 +1292,49 @@ async fn process_with_agent_logic(
 
             // Always compute visible text without thinking tags for retry/fallback decisions.
             let visible_text = strip_thinking(&text);
+            if let Some(structured) = structured.as_deref_mut() {
+                if let Err(problems) = structured.accept_text(&visible_text) {
+                    if structured.exhausted() {
+                        anyhow::bail!(
+                            "final answer did not match the response schema:\n{problems}"
+                        );
+                    }
+                    warn!(
+                        chat_id,
+                        iteration = iteration + 1,
+                        "Structured output missing or invalid; asking the model to retry"
+                    );
+                    messages.push(Message {
+                        role: "assistant".into(),
+                        content: MessageContent::Text(text.clone()),
+                    });
+                    messages.push(Message {
+                        role: "user".into(),
+                        content: MessageContent::Text(format!(
+                            "[runtime_guard]: Your reply was not a valid structured answer:\n{problems}\nCall the `{FINAL_ANSWER_TOOL_NAME}` tool with input matching the schema."
+                        )),
+                    });
+                    force_final_answer = true;
+                    continue;
+                }
+            }
             // Keep raw thinking text only when show_thinking is enabled.
             let display_text = if effective_profile.show_thinking {
-                text.clone()
+                let thinking = response
+                    .content
+                    .iter()
+                    .filter_map(|block| match block {
+                        ResponseContentBlock::Thinking { thinking, .. } => Some(thinking.trim()),
+                        _ => None,
+                    })
+                    .filter(|t| !t.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                if thinking.is_empty() {
+                    text.clone()
+                } else {
+                    format!("<thought>\n{thinking}\n</thought>\n\n{text}")
+                }
             } else {
                 visible_text.clone()
             };
@@ -1248,6 +1489,18 @@ async fn process_with_agent_logic(
                         input: input.clone(),
                         thought_signature: thought_signature.clone(),
                     }),
+                    // Anthropic requires the turn's thinking blocks to precede its tool
+                    // calls when the tool results are sent back.
+                    ResponseContentBlock::Thinking {
+                        thinking,
+                        signature,
+                    } => Some(ContentBlock::Thinking {
+                        thinking: thinking.clone(),
+                        signature: signature.clone(),
+                    }),
+                    ResponseContentBlock::RedactedThinking { data } => {
+                        Some(ContentBlock::RedactedThinking { data: data.clone() })
+                    }
                     ResponseContentBlock::Other => None,
                 })
                 .collect();
@@ -1258,9 +1511,13 @@ async fn process_with_agent_logic(
             });
 
             let mut tool_results = Vec::new();
+            let mut tool_images = Vec::new();
             let mut waiting_for_user_approval = false;
             let mut waiting_approval_tool: Option<String> = None;
-            for block in &response.content {
+            let mut waiting_approval_preview: Option<String> = None;
+            let mut prefetched_tools: std::collections::HashMap<usize, PrefetchedToolCall> =
+                std::collections::HashMap::new();
+            for (block_idx, block) in response.content.iter().enumerate() {
                 if let ResponseContentBlock::ToolUse {
                     id, name, input, ..
                 } = block
@@ -1279,6 +1536,24 @@ async fn process_with_agent_logic(
                         });
                         continue;
                     }
+                    if let Some(structured) = structured
+                        .as_deref_mut()
+                        .filter(|_| name == FINAL_ANSWER_TOOL_NAME)
+                    {
+                        let (content, is_error) = match structured.accept_tool_input(input) {
+                            Ok(()) => ("Final answer accepted.".to_string(), None),
+                            Err(problems) => (
+                                format!("final_answer rejected, the input does not match the schema:\n{problems}"),
+                                Some(true),
+                            ),
+                        };
+                        tool_results.push(ContentBlock::ToolResult {
+                            tool_use_id: id.clone(),
+                            content,
+                            is_error,
+                        });
+                        continue;
+                    }
                     if name != "send_message" {
                         consecutive_send_message_calls = 0;
                     } else if consecutive_send_message_calls >= 3 {
@@ -1308,41 +1583,54 @@ async fn process_with_agent_logic(
                         });
                         continue;
                     }
-                    let mut effective_input = input.clone();
-                    if let Ok(hook_outcome) = state
-                        .hooks
-                        .run_before_tool(
-                            chat_id,
-                            context.caller_channel,
-                            iteration + 1,
-                            name,
-                            &effective_input,
-                        )
-                        .await
+                    // Run a consecutive batch of read-only calls concurrently; their
+                    // results are consumed below in original order.
+                    if state.config.max_parallel_tool_calls > 1
+                        && !prefetched_tools.contains_key(&block_idx)
                     {
-                        match hook_outcome {
-                            HookOutcome::Block { reason } => {
-                                tool_results.push(ContentBlock::ToolResult {
-                                    tool_use_id: id.clone(),
-                                    content: if reason.trim().is_empty() {
-                                        format!("tool '{}' blocked by policy hook", name)
-                                    } else {
-                                        reason
-                                    },
-                                    is_error: Some(true),
-                                });
-                                continue;
-                            }
-                            HookOutcome::Allow { patches } => {
-                                for patch in patches {
-                                    if let Some(v) = patch.get("tool_input") {
-                                        effective_input = v.clone();
-                                    }
-                                }
-                            }
+                        let batch = parallel_tool_batch(&response.content, block_idx);
+                        if batch.len() > 1 {
+                            prefetched_tools.extend(
+                                prefetch_parallel_tool_calls(
+                                    state,
+                                    chat_id,
+                                    context.caller_channel,
+                                    iteration + 1,
+                                    batch,
+                                    &tool_auth,
+                                    event_tx,
+                                )
+                                .await,
+                            );
                         }
                     }
-                    if let Some(tx) = event_tx {
+                    let prefetched = prefetched_tools.remove(&block_idx);
+                    let hook_input = match &prefetched {
+                        Some(call) => call.input.clone(),
+                        None => {
+                            apply_before_tool_hooks(
+                                state,
+                                chat_id,
+                                context.caller_channel,
+                                iteration + 1,
+                                name,
+                                input,
+                            )
+                            .await
+                        }
+                    };
+                    let effective_input = match hook_input {
+                        Ok(v) => v,
+                        Err(content) => {
+                            tool_results.push(ContentBlock::ToolResult {
+                                tool_use_id: id.clone(),
+                                content,
+                                is_error: Some(true),
+                            });
+                            continue;
+                        }
+                    };
+                    if let (None, Some(tx)) = (&prefetched, event_tx) {
                         let _ = tx.send(AgentEvent::ToolStart {
                             name: name.clone(),
                             input: effective_input.clone(),
@@ -1352,19 +1640,45 @@ async fn process_with_agent_logic(
                         chat_id,
                         tool = %name,
                         iteration = iteration + 1,
+                        parallel = prefetched.is_some(),
                         "Executing tool"
                     );
-                    let started = std::time::Instant::now();
                     let mut executed_input = effective_input.clone();
 
                     let tool_span_id = new_span_id();
-                    let tool_start = now_unix_nano();
                     metrics.tool_calls += 1;
 
-                    let mut result = state
-                        .tools
-                        .execute_with_auth(name, executed_input.clone(), &tool_auth)
-                        .await;
+                    let (started, tool_start, mut result) = match prefetched
+                        .and_then(|call| call.result.map(|r| (call.started, call.span_start, r)))
+                    {
+                        Some(done) => done,
+                        None => {
+                            if crate::checkpoints::turn_checkpoints_enabled(&state.config)
+                                && !turn_checkpoint_taken
+                                && tool_mutates_working_dir(name)
+                            {
+                                turn_checkpoint_taken = true;
+                                if let Err(e) = crate::checkpoints::checkpoint_turn(
+                                    state,
+                                    context.caller_channel,
+                                    chat_id,
+                                )
+                                .await
+                                {
+                                    warn!(
+                                        "Failed to checkpoint working dir for chat {chat_id}: {e}"
+                                    );
+                                }
+                            }
+                            let started = std::time::Instant::now();
+                            let tool_start = now_unix_nano();
+                            let result = state
+                                .tools
+                                .execute_with_auth(name, executed_input.clone(), &tool_auth)
+                                .await;
+                            (started, tool_start, result)
+                        }
+                    };
 
                     if let Some(exp) = &state.trace_exporter {
                         let mut attrs = vec![
@@ -1413,7 +1727,20 @@ async fn process_with_agent_logic(
                                 true
                             };
                         if can_retry_with_approval {
-                            executed_input = with_high_risk_approval_marker(&effective_input);
+                            let held_digest = held_approval_digest(&result);
+                            let digest = if state.config.high_risk_tool_user_confirmation_required {
+                                let key =
+                                    approval_digest_key(context.caller_channel, chat_id, name);
+                                SHOWN_APPROVAL_DIGESTS
+                                    .lock()
+                                    .ok()
+                                    .and_then(|mut shown| shown.remove(&key))
+                                    .or(held_digest)
+                            } else {
+                                held_digest
+                            };
+                            executed_input =
+                                with_high_risk_approval_marker(&effective_input, digest.as_deref());
                             if state.config.high_risk_tool_user_confirmation_required {
                                 info!("Retrying tool '{}' after explicit user approval", name);
                             } else {
@@ -1472,6 +1799,20 @@ async fn process_with_agent_logic(
                         } else if state.config.high_risk_tool_user_confirmation_required {
                             waiting_for_user_approval = true;
                             waiting_approval_tool = Some(name.clone());
+                            waiting_approval_preview = result
+                                .metadata
+                                .as_ref()
+                                .and_then(|m| m.get("approval_preview"))
+                                .and_then(|v| v.as_str())
+                                .map(str::to_string);
+                            if let (Some(digest), Ok(mut shown)) =
+                                (held_approval_digest(&result), SHOWN_APPROVAL_DIGESTS.lock())
+                            {
+                                shown.insert(
+                                    approval_digest_key(context.caller_channel, chat_id, name),
+                                    digest,
+                                );
+                            }
                         }
                     }
                     if name == "activate_skill" && !result.is_error {
@@ -1605,18 +1946,103 @@ async fn process_with_agent_logic(
                     if name == "send_message" {
                         consecutive_send_message_calls += 1;
                     }
+                    let spill_path =
+                        tool_result_spill_path(&state.config, context.caller_channel, chat_id, id);
+                    let content = match truncate_tool_result(
+                        &result.content,
+                        state.config.max_tool_result_tokens,
+                        &spill_path,
+                    ) {
+                        Some(truncated) => {
+                            if let Some(dir) = spill_path.parent() {
+                                prune_tool_result_spills(
+                                    dir,
+                                    TOOL_RESULT_SPILL_KEEP,
+                                    TOOL_RESULT_SPILL_MAX_AGE,
+                                );
+                            }
+                            truncated
+                        }
+                        None => result.content,
+                    };
                     tool_results.push(ContentBlock::ToolResult {
                         tool_use_id: id.clone(),
-                        content: result.content,
+                        content,
                         is_error: if result.is_error { Some(true) } else { None },
                     });
+                    tool_images.extend(result.images);
                 }
             }
+            // Images go after every tool_result block; providers expect results first.
+            tool_results.extend(
+                tool_images
+                    .into_iter()
+                    .map(|source| ContentBlock::Image { source }),
+            );
 
             messages.push(Message {
                 role: "user".into(),
                 content: MessageContent::Blocks(tool_results),
             });
+            if let Some(structured) = structured.as_deref() {
+                let final_text = match structured.result() {
+                    Some(output) => {
+                        let visible = strip_thinking(
+                            &response
+                                .content
+                                .iter()
+                                .filter_map(|block| match block {
+                                    ResponseContentBlock::Text { text } => Some(text.as_str()),
+                                    _ => None,
+                                })
+                                .collect::<Vec<_>>()
+                                .join(""),
+                        );
+                        if visible.trim().is_empty() {
+                            serde_json::to_string_pretty(output)?
+                        } else {
+                            visible
+                        }
+                    }
+                    None if structured.exhausted() => {
+                        "I couldn't produce an answer matching the requested schema.".to_string()
+                    }
+                    None => String::new(),
+                };
+                if !final_text.is_empty() {
+                    // Close the tool turn so the session can be resumed.
+                    messages.push(Message {
+                        role: "assistant".into(),
+                        content: MessageContent::Text(final_text.clone()),
+                    });
+                    persist_session_with_skill_env_files(
+                        state,
+                        chat_id,
+                        &mut messages,
+                        &skill_env_files,
+                    )
+                    .await;
+                    if structured.result().is_none() {
+                        anyhow::bail!(
+                            "final answer did not match the response schema after {} attempts",
+                            crate::structured_output::MAX_STRUCTURED_OUTPUT_ATTEMPTS
+                        );
+                    }
+                    if let Some(tx) = event_tx {
+                        let _ = tx.send(AgentEvent::FinalResponse {
+                            text: final_text.clone(),
+                        });
+                    }
+                    info!(
+                        chat_id,
+                        channel = context.caller_channel,
+                        iterations = iteration + 1,
+                        duration_ms = request_start.elapsed().as_millis(),
+                        "Agent request completed with structured output"
+                    );
+                    return Ok(final_text);
+                }
+            }
             if waiting_for_user_approval {
                 persist_session_with_skill_env_files(
                     state,
@@ -1626,9 +2052,14 @@ async fn process_with_agent_logic(
                 )
                 .await;
                 let tool_name = waiting_approval_tool.unwrap_or_else(|| "this tool".to_string());
-                let text = format!(
-                    "High-risk tool '{tool_name}' is waiting for your confirmation. Reply with \"批准\" or \"approve\" to continue."
-                );
+                let text = match waiting_approval_preview {
+                    Some(preview) => format!(
+                        "High-risk tool '{tool_name}' is waiting for your confirmation.\n\n{preview}\n\nReply with \"批准\" or \"approve\" to continue."
+                    ),
+                    None => format!(
+                        "High-risk tool '{tool_name}' is waiting for your confirmation. Reply with \"批准\" or \"approve\" to continue."
+                    ),
+                };
                 if let Some(tx) = event_tx {
                     let _ = tx.send(AgentEvent::FinalResponse { text: text.clone() });
                 }
@@ -2197,6 +2628,7 @@ pub(crate) fn message_to_text(msg: &Message) -> String {
                     ContentBlock::Image { .. } => {
                         parts.push("[image]".into());
                     }
+                    ContentBlock::Thinking { .. } | ContentBlock::RedactedThinking { .. } => {}
                 }
             }
             parts.join("\n")
@@ -2219,6 +2651,69 @@ pub(crate) fn strip_images_for_session(messages: &mut [Message]) {
     }
 }
 
+/// Spill files kept per chat; older ones are pruned after each new spill.
+const TOOL_RESULT_SPILL_KEEP: usize = 50;
+/// Spill files older than this are pruned regardless of count.
+const TOOL_RESULT_SPILL_MAX_AGE: std::time::Duration =
+    std::time::Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Where oversized tool output is saved so the model can page through it later:
+/// `<runtime>/tool_results/<channel>/<chat_id>/<tool_use_id>.txt`. It stays out of
+/// the working dir, which chats may share, and the sandbox mounts the root
+/// read-only at the same path so `read_file` can open it either way.
+fn tool_result_spill_path(
+    config: &crate::config::Config,
+    channel: &str,
+    chat_id: i64,
+    tool_use_id: &str,
+) -> std::path::PathBuf {
+    let sanitize = |s: &str| -> String {
+        s.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+    let channel_dir = match sanitize(channel.trim()) {
+        c if c.is_empty() => "unknown".to_string(),
+        c => c,
+    };
+    let root = config.tool_results_dir();
+    let root = std::fs::canonicalize(&root).unwrap_or(root);
+    root.join(channel_dir)
+        .join(chat_id.to_string())
+        .join(format!("{}.txt", sanitize(tool_use_id)))
+}
+
+/// Drop spill files in `dir` older than `max_age`, then the oldest beyond `keep`.
+fn prune_tool_result_spills(dir: &std::path::Path, keep: usize, max_age: std::time::Duration) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let now = std::time::SystemTime::now();
+    let mut files: Vec<(std::time::SystemTime, std::path::PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            meta.is_file()
+                .then(|| (meta.modified().unwrap_or(now), entry.path()))
+        })
+        .collect();
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+    for (i, (modified, path)) in files.into_iter().enumerate() {
+        let expired = now.duration_since(modified).unwrap_or_default() > max_age;
+        if i >= keep || expired {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("Failed to prune tool result {}: {e}", path.display());
+            }
+        }
+    }
+}
+
 /// Archive the full conversation to a markdown file before compaction.
 /// Saved to `<data_dir>/groups/<channel>/<chat_id>/conversations/<timestamp>.md`.
 pub fn archive_conversation(data_dir: &str, channel: &str, chat_id: i64, messages: &[Message]) {
@@ -2258,6 +2753,368 @@ pub fn archive_conversation(data_dir: &str, channel: &str, chat_id: i64, message
     }
 }
 
+/// A tool call executed ahead of the serial tool loop as part of a parallel batch.
+struct PrefetchedToolCall {
+    /// Input after `BeforeToolCall` hooks, or the tool result text when a hook blocked it.
+    input: Result<Value, String>,
+    started: std::time::Instant,
+    span_start: u64,
+    result: Option<ToolResult>,
+}
+
+/// Run `OnInboundMessage` hooks on each user message the agent is about to pick
+/// up: those stored since the session was last saved, or the trailing ones when
+/// there is no session yet. A `content` patch rewrites the stored message and a
+/// block deletes it, so the session, memory commands, the reflector and exports
+/// never see the original text. Returns the block reason when no pending
+/// message is left to answer.
+async fn apply_inbound_message_hooks(
+    state: &AppState,
+    context: AgentRequestContext<'_>,
+) -> anyhow::Result<Option<String>> {
+    let chat_id = context.chat_id;
+    let session_updated_at = call_blocking(state.db.clone(), move |db| db.load_session(chat_id))
+        .await?
+        .map(|(_, updated_at)| updated_at);
+    let pending: Vec<StoredMessage> = match session_updated_at {
+        Some(since) => {
+            call_blocking(state.db.clone(), move |db| {
+                db.get_new_user_messages_since(chat_id, &since)
+            })
+            .await?
+        }
+        None => {
+            let limit = state.config.max_history_messages.max(1);
+            let mut recent = call_blocking(state.db.clone(), move |db| {
+                db.get_recent_messages(chat_id, limit)
+            })
+            .await?;
+            let first_pending = recent
+                .iter()
+                .rposition(|m| m.is_from_bot)
+                .map_or(0, |idx| idx + 1);
+            recent.split_off(first_pending)
+        }
+    };
+
+    let mut remaining = 0usize;
+    let mut block_reason = None;
+    for mut msg in pending
+        .into_iter()
+        .filter(|m| !is_slash_command_text(&m.content))
+    {
+        let Ok(outcome) = state
+            .hooks
+            .run_inbound_message(
+                chat_id,
+                context.caller_channel,
+                context.chat_type,
+                &msg.content,
+            )
+            .await
+        else {
+            remaining += 1;
+            continue;
+        };
+        match outcome {
+            HookOutcome::Block { reason } => {
+                info!(chat_id, "Inbound message dropped by hook: {reason}");
+                let message_id = msg.id.clone();
+                call_blocking(state.db.clone(), move |db| {
+                    db.delete_message(chat_id, &message_id)
+                })
+                .await?;
+                block_reason = Some(reason);
+            }
+            HookOutcome::Allow { patches } => {
+                remaining += 1;
+                let patched = patches
+                    .iter()
+                    .filter_map(|patch| patch.get("content").and_then(|v| v.as_str()))
+                    .next_back()
+                    .map(str::to_string);
+                if let Some(content) = patched {
+                    msg.content = content;
+                    call_blocking(state.db.clone(), move |db| db.store_message(&msg)).await?;
+                }
+            }
+        }
+    }
+
+    if remaining > 0 {
+        return Ok(None);
+    }
+    Ok(block_reason.map(|reason| {
+        if reason.trim().is_empty() {
+            "Message blocked by policy hook.".to_string()
+        } else {
+            reason
+        }
+    }))
+}
+
+/// Compact `messages` once the projected request outgrows the model's context
+/// window. Models with an unknown window fall back to the message-count threshold.
+async fn compact_if_over_budget(
+    state: &AppState,
+    context: AgentRequestContext<'_>,
+    context_budget: Option<ContextBudget>,
+    system_prompt: &str,
+    tool_defs: &[ToolDefinition],
+    messages: &mut Vec<Message>,
+) {
+    let chat_id = context.chat_id;
+    let system_tokens = estimate_text_tokens(system_prompt);
+    let tool_tokens = estimate_tool_definitions_tokens(tool_defs);
+    let history_tokens = estimate_messages_tokens(messages);
+    let compact_keep_recent = match context_budget {
+        Some(budget) => {
+            let allowance = budget.history_allowance(system_tokens, tool_tokens);
+            (history_tokens > allowance).then(|| {
+                budget.recent_messages_to_keep(
+                    messages,
+                    allowance,
+                    state.config.compact_keep_recent,
+                )
+            })
+        }
+        None => (messages.len() > state.config.max_session_messages)
+            .then_some(state.config.compact_keep_recent),
+    };
+    debug!(
+        chat_id,
+        system_tokens,
+        tool_tokens,
+        history_tokens,
+        context_window = ?context_budget.map(|b| b.window),
+        "Context budget estimated"
+    );
+    let Some(keep_recent) = compact_keep_recent else {
+        return;
+    };
+    let msg_count_before = messages.len();
+    archive_conversation(
+        &state.config.data_dir,
+        context.caller_channel,
+        chat_id,
+        messages,
+    );
+    *messages = compact_messages(
+        state,
+        context.caller_channel,
+        chat_id,
+        messages,
+        keep_recent,
+    )
+    .await;
+    apply_session_compacted_hooks(state, context, msg_count_before, messages).await;
+    info!(
+        chat_id,
+        messages_before = msg_count_before,
+        messages_after = messages.len(),
+        tokens_before = history_tokens,
+        tokens_after = estimate_messages_tokens(messages),
+        "Context compacted"
+    );
+}
+
+/// Run `OnSessionCompacted` hooks. A `summary` patch rewrites the summary turn;
+/// a block discards the summary and keeps only the recent messages.
+async fn apply_session_compacted_hooks(
+    state: &AppState,
+    context: AgentRequestContext<'_>,
+    messages_before: usize,
+    messages: &mut Vec<Message>,
+) {
+    let summary = messages.first().and_then(|m| match &m.content {
+        MessageContent::Text(t) => t.strip_prefix(COMPACTION_SUMMARY_PREFIX),
+        _ => None,
+    });
+    let has_summary = summary.is_some();
+    let Ok(outcome) = state
+        .hooks
+        .run_session_compacted(
+            context.chat_id,
+            context.caller_channel,
+            messages_before,
+            messages.len(),
+            summary,
+        )
+        .await
+    else {
+        return;
+    };
+    match outcome {
+        HookOutcome::Block { reason } => {
+            info!(
+                chat_id = context.chat_id,
+                "Compaction summary discarded by hook: {reason}"
+            );
+            if has_summary {
+                // Drop the summary turn and its acknowledgement.
+                messages.drain(..messages.len().min(2));
+            }
+        }
+        HookOutcome::Allow { patches } if has_summary => {
+            for patch in patches {
+                if let Some(v) = patch.get("summary").and_then(|v| v.as_str()) {
+                    messages[0].content =
+                        MessageContent::Text(format!("{COMPACTION_SUMMARY_PREFIX}{v}"));
+                }
+            }
+        }
+        HookOutcome::Allow { .. } => {}
+    }
+}
+
+/// Replace the text blocks of a model response with `text`, keeping tool calls and
+/// thinking blocks where they are.
+fn replace_response_text(content: &mut Vec<ResponseContentBlock>, text: &str) {
+    let first_text = content
+        .iter()
+        .position(|b| matches!(b, ResponseContentBlock::Text { .. }))
+        .unwrap_or(0);
+    content.retain(|b| !matches!(b, ResponseContentBlock::Text { .. }));
+    content.insert(
+        first_text.min(content.len()),
+        ResponseContentBlock::Text {
+            text: text.to_string(),
+        },
+    );
+}
+
+/// Runs `BeforeToolCall` hooks and returns the (possibly patched) tool input, or the
+/// error text to report back to the model when a hook blocks the call.
+async fn apply_before_tool_hooks(
+    state: &AppState,
+    chat_id: i64,
+    caller_channel: &str,
+    iteration: usize,
+    name: &str,
+    input: &Value,
+) -> Result<Value, String> {
+    let mut effective_input = input.clone();
+    if let Ok(hook_outcome) = state
+        .hooks
+        .run_before_tool(chat_id, caller_channel, iteration, name, &effective_input)
+        .await
+    {
+        match hook_outcome {
+            HookOutcome::Block { reason } => {
+                return Err(if reason.trim().is_empty() {
+                    format!("tool '{}' blocked by policy hook", name)
+                } else {
+                    reason
+                });
+            }
+            HookOutcome::Allow { patches } => {
+                for patch in patches {
+                    if let Some(v) = patch.get("tool_input") {
+                        effective_input = v.clone();
+                    }
+                }
+            }
+        }
+    }
+    Ok(effective_input)
+}
+
+/// Collects the run of consecutive parallel-safe tool calls starting at `start`.
+/// Stops at the first call that must run serially so ordering against writes holds.
+fn parallel_tool_batch(
+    content: &[ResponseContentBlock],
+    start: usize,
+) -> Vec<(usize, &str, &Value)> {
+    let mut batch = Vec::new();
+    for (idx, block) in content.iter().enumerate().skip(start) {
+        if let ResponseContentBlock::ToolUse { name, input, .. } = block {
+            if name.trim().is_empty() || name == FINAL_ANSWER_TOOL_NAME || !tool_parallel_safe(name)
+            {
+                break;
+            }
+            batch.push((idx, name.as_str(), input));
+        }
+    }
+    batch
+}
+
+/// Runs hooks for a batch serially, then executes the allowed calls concurrently, at
+/// most `max_parallel_tool_calls` at a time. Results are keyed by content block index.
+async fn prefetch_parallel_tool_calls(
+    state: &AppState,
+    chat_id: i64,
+    caller_channel: &str,
+    iteration: usize,
+    batch: Vec<(usize, &str, &Value)>,
+    tool_auth: &ToolAuthContext,
+    event_tx: Option<&UnboundedSender<AgentEvent>>,
+) -> std::collections::HashMap<usize, PrefetchedToolCall> {
+    use futures_util::StreamExt;
+
+    let mut prepared = Vec::with_capacity(batch.len());
+    for (idx, name, input) in batch {
+        let input =
+            apply_before_tool_hooks(state, chat_id, caller_channel, iteration, name, input).await;
+        if let (Ok(input), Some(tx)) = (&input, event_tx) {
+            let _ = tx.send(AgentEvent::ToolStart {
+                name: name.to_string(),
+                input: input.clone(),
+            });
+        }
+        prepared.push((idx, name, input));
+    }
+    info!(
+        chat_id,
+        iteration,
+        tools = prepared.len(),
+        "Executing tool calls in parallel"
+    );
+    let mut calls = Vec::with_capacity(prepared.len());
+    for (idx, name, input) in prepared {
+        calls.push(run_prefetched_tool_call(state, tool_auth, idx, name, input));
+    }
+    futures_util::stream::iter(calls)
+        .buffered(state.config.max_parallel_tool_calls.max(1))
+        .collect()
+        .await
+}
+
+async fn run_prefetched_tool_call(
+    state: &AppState,
+    tool_auth: &ToolAuthContext,
+    idx: usize,
+    name: &str,
+    input: Result<Value, String>,
+) -> (usize, PrefetchedToolCall) {
+    let started = std::time::Instant::now();
+    let span_start = now_unix_nano();
+    let result = match &input {
+        Ok(v) => Some(
+            state
+                .tools
+                .execute_with_auth(name, v.clone(), tool_auth)
+                .await,
+        ),
+        Err(_) => None,
+    };
+    (
+        idx,
+        PrefetchedToolCall {
+            input,
+            started,
+            span_start,
+            result,
+        },
+    )
+}
+
+const COMPACTION_SUMMARY_PREFIX: &str = "[Conversation Summary]\n";
+
+fn has_tool_results(message: &Message) -> bool {
+    matches!(&message.content, MessageContent::Blocks(blocks)
+        if blocks.iter().any(|b| matches!(b, ContentBlock::ToolResult { .. })))
+}
+
 /// Compact old messages by summarizing them via LLM, keeping recent messages verbatim.
 async fn compact_messages(
     state: &AppState,
@@ -2271,7 +3128,12 @@ async fn compact_messages(
         return messages.to_vec();
     }
 
-    let split_at = total - keep_recent;
+    // Mid-loop the tail can open with tool results; keep the assistant turn that
+    // issued those calls so every result still has its tool_use.
+    let mut split_at = total - keep_recent;
+    if split_at > 0 && has_tool_results(&messages[split_at]) {
+        split_at -= 1;
+    }
     let old_messages = &messages[..split_at];
     let recent_messages = &messages[split_at..];
 
@@ -2298,15 +3160,7 @@ async fn compact_messages(
     }];
     let (effective_profile, effective_model) =
         resolve_effective_provider_and_model(state, caller_channel).await;
-    let scoped_provider = if effective_profile.alias != state.config.llm_provider {
-        Some(crate::llm::create_provider(&build_provider_runtime_config(
-            state,
-            &effective_profile,
-            &effective_model,
-        )))
-    } else {
-        None
-    };
+    let scoped_provider = scoped_llm_client(state, &effective_profile, &effective_model);
 
     let timeout_secs = state.config.compaction_timeout_secs;
     let summary = match tokio::time::timeout(std::time::Duration::from_secs(timeout_secs), async {
@@ -2377,18 +3231,23 @@ async fn compact_messages(
     };
 
     // Build compacted message list: summary context + recent messages
-    let mut compacted = vec![
-        Message {
-            role: "user".into(),
-            content: MessageContent::Text(format!("[Conversation Summary]\n{summary}")),
-        },
-        Message {
+    let mut compacted = vec![Message {
+        role: "user".into(),
+        content: MessageContent::Text(format!("{COMPACTION_SUMMARY_PREFIX}{summary}")),
+    }];
+    // An assistant turn at the head of the tail follows the summary directly;
+    // merging it into an acknowledgement would flatten its tool calls to text.
+    if recent_messages
+        .first()
+        .is_none_or(|m| m.role != "assistant")
+    {
+        compacted.push(Message {
             role: "assistant".into(),
             content: MessageContent::Text(
                 "Understood, I have the conversation context. How can I help?".into(),
             ),
-        },
-    ];
+        });
+    }
 
     // Append recent messages, fixing role alternation
     for msg in recent_messages {
@@ -2419,10 +3278,12 @@ async fn compact_messages(
 #[cfg(test)]
 mod tests {
     use super::{
-        build_db_memory_context, history_to_claude_messages, process_with_agent, strip_thinking,
-        AgentRequestContext,
+        build_db_memory_context, history_to_claude_messages, message_to_text, process_with_agent,
+        process_with_agent_structured, process_with_agent_with_events, strip_thinking, AgentEvent,
+        AgentRequestContext, FINAL_ANSWER_TOOL_NAME,
     };
     use crate::config::{Config, WorkingDirIsolation};
+    use crate::context_budget::COMPACTION_TRIGGER_RATIO;
     use crate::llm::LlmProvider;
     use crate::memory::MemoryManager;
     use crate::runtime::AppState;
@@ -2500,319 +3361,1347 @@ mod tests {
         }
     }
 
-    struct ApprovalLoopUntilSuccessfulToolLlm {
+    struct StructuredRetryLlm {
         calls: Arc<AtomicUsize>,
-        saw_successful_tool_result: Arc<AtomicBool>,
     }
 
     #[async_trait::async_trait]
-    impl LlmProvider for ApprovalLoopUntilSuccessfulToolLlm {
+    impl LlmProvider for StructuredRetryLlm {
         async fn send_message(
             &self,
             _system: &str,
-            messages: Vec<Message>,
-            _tools: Option<Vec<ToolDefinition>>,
+            _messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
         ) -> Result<MessagesResponse, MicroClawError> {
+            assert!(tools
+                .unwrap_or_default()
+                .iter()
+                .any(|t| t.name == FINAL_ANSWER_TOOL_NAME));
             let idx = self.calls.fetch_add(1, Ordering::SeqCst);
-            if idx == 0 {
-                return Ok(MessagesResponse {
-                    content: vec![ResponseContentBlock::ToolUse {
-                        id: "tool-bash-1".to_string(),
-                        name: "bash".to_string(),
-                        input: json!({"command": "printf approved"}),
-                        thought_signature: None,
-                    }],
-                    stop_reason: Some("tool_use".to_string()),
-                    usage: None,
-                });
-            }
-
-            let mut approval_failed = false;
-            let mut approval_succeeded = false;
-            for msg in messages.iter().rev() {
-                if msg.role != "user" {
-                    continue;
-                }
-                if let microclaw_core::llm_types::MessageContent::Blocks(blocks) = &msg.content {
-                    for block in blocks {
-                        if let microclaw_core::llm_types::ContentBlock::ToolResult {
-                            content,
-                            is_error,
-                            ..
-                        } = block
-                        {
-                            if is_error.unwrap_or(false)
-                                && content.contains("Approval required for high-risk tool")
-                            {
-                                approval_failed = true;
-                            } else if !is_error.unwrap_or(false) && content.contains("approved") {
-                                approval_succeeded = true;
-                            }
-                        }
-                    }
-                    break;
-                }
-            }
-
-            if approval_succeeded {
-                self.saw_successful_tool_result
-                    .store(true, Ordering::SeqCst);
-                return Ok(MessagesResponse {
-                    content: vec![ResponseContentBlock::Text {
-                        text: "approval loop resolved".to_string(),
-                    }],
-                    stop_reason: Some("end_turn".to_string()),
-                    usage: None,
-                });
-            }
-
-            if approval_failed {
-                return Ok(MessagesResponse {
-                    content: vec![ResponseContentBlock::ToolUse {
-                        id: format!("tool-bash-retry-{idx}"),
-                        name: "bash".to_string(),
-                        input: json!({"command": "printf approved"}),
-                        thought_signature: None,
-                    }],
-                    stop_reason: Some("tool_use".to_string()),
-                    usage: None,
-                });
-            }
-
+            let input = if idx == 0 {
+                json!({"city": "Paris"})
+            } else {
+                json!({"city": "Paris", "temp_c": 21})
+            };
             Ok(MessagesResponse {
-                content: vec![ResponseContentBlock::Text {
-                    text: "unexpected state".to_string(),
+                content: vec![ResponseContentBlock::ToolUse {
+                    id: format!("final-{idx}"),
+                    name: FINAL_ANSWER_TOOL_NAME.to_string(),
+                    input,
+                    thought_signature: None,
                 }],
-                stop_reason: Some("end_turn".to_string()),
+                stop_reason: Some("tool_use".to_string()),
                 usage: None,
             })
         }
     }
 
-    fn test_db() -> (Arc<Database>, std::path::PathBuf) {
-        let dir = std::env::temp_dir().join(format!("mc_agent_engine_{}", uuid::Uuid::new_v4()));
-        std::fs::create_dir_all(&dir).unwrap();
-        let db = Arc::new(Database::new(dir.to_str().unwrap()).unwrap());
-        (db, dir)
+    /// Answers in plain text first, as providers without forced tool use may.
+    struct StructuredTextFirstLlm {
+        offered: Arc<std::sync::Mutex<Vec<Vec<String>>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for StructuredTextFirstLlm {
+        async fn send_message(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            let names = tools
+                .unwrap_or_default()
+                .into_iter()
+                .map(|t| t.name)
+                .collect::<Vec<_>>();
+            let mut offered = self.offered.lock().unwrap();
+            offered.push(names);
+            let (content, stop_reason) = if offered.len() == 1 {
+                let text = "It is sunny in Paris.".into();
+                (ResponseContentBlock::Text { text }, "end_turn")
+            } else {
+                let call = ResponseContentBlock::ToolUse {
+                    id: "final".into(),
+                    name: FINAL_ANSWER_TOOL_NAME.to_string(),
+                    input: json!({"city": "Paris", "temp_c": 21}),
+                    thought_signature: None,
+                };
+                (call, "tool_use")
+            };
+            Ok(MessagesResponse {
+                content: vec![content],
+                stop_reason: Some(stop_reason.to_string()),
+                usage: None,
+            })
+        }
+    }
+
+    struct ParallelProbeTool {
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::tools::Tool for ParallelProbeTool {
+        fn name(&self) -> &str {
+            "parallel_probe"
+        }
+
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: "parallel_probe".into(),
+                description: "probe".into(),
+                input_schema: crate::tools::schema_object(json!({}), &[]),
+            }
+        }
+
+        async fn execute(&self, input: serde_json::Value) -> crate::tools::ToolResult {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+            // Later calls finish first so ordering bugs would show up.
+            let n = input.get("n").and_then(|v| v.as_u64()).unwrap_or(0);
+            tokio::time::sleep(std::time::Duration::from_millis(150 - n * 50)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            crate::tools::ToolResult::success(format!("probe {n}"))
+        }
+    }
+
+    struct ParallelToolCallsLlm {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for ParallelToolCallsLlm {
+        async fn send_message(
+            &self,
+            _system: &str,
+            messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Ok(MessagesResponse {
+                    content: (0..3)
+                        .map(|n| ResponseContentBlock::ToolUse {
+                            id: format!("probe-{n}"),
+                            name: "parallel_probe".into(),
+                            input: json!({ "n": n }),
+                            thought_signature: None,
+                        })
+                        .collect(),
+                    stop_reason: Some("tool_use".to_string()),
+                    usage: None,
+                });
+            }
+            let results: Vec<String> = messages
+                .last()
+                .map(|m| match &m.content {
+                    microclaw_core::llm_types::MessageContent::Blocks(blocks) => blocks
+                        .iter()
+                        .filter_map(|b| match b {
+                            microclaw_core::llm_types::ContentBlock::ToolResult {
+                                tool_use_id,
+                                content,
+                                ..
+                            } => Some(format!("{tool_use_id}={content}")),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                })
+                .unwrap_or_default();
+            Ok(MessagesResponse {
+                content: vec![ResponseContentBlock::Text {
+                    text: results.join(","),
+                }],
+                stop_reason: Some("end_turn".to_string()),
+                usage: None,
+            })
+        }
+    }
+
+    struct CompactionProbeLlm {
+        summarize_calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for CompactionProbeLlm {
+        async fn send_message(
+            &self,
+            system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            let text = if system == "You are a helpful summarizer." {
+                self.summarize_calls.fetch_add(1, Ordering::SeqCst);
+                "earlier chat summary"
+            } else {
+                "done"
+            };
+            Ok(MessagesResponse {
+                content: vec![ResponseContentBlock::Text { text: text.into() }],
+                stop_reason: Some("end_turn".to_string()),
+                usage: None,
+            })
+        }
+    }
+
+    struct BigOutputTool;
+
+    #[async_trait::async_trait]
+    impl crate::tools::Tool for BigOutputTool {
+        fn name(&self) -> &str {
+            "big_output"
+        }
+
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: "big_output".into(),
+                description: "big".into(),
+                input_schema: crate::tools::schema_object(json!({}), &[]),
+            }
+        }
+
+        async fn execute(&self, _input: serde_json::Value) -> crate::tools::ToolResult {
+            crate::tools::ToolResult::success("log line\n".repeat(5000))
+        }
+    }
+
+    struct BigOutputLlm {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for BigOutputLlm {
+        async fn send_message(
+            &self,
+            _system: &str,
+            messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Ok(MessagesResponse {
+                    content: vec![ResponseContentBlock::ToolUse {
+                        id: "big-1".into(),
+                        name: "big_output".into(),
+                        input: json!({}),
+                        thought_signature: None,
+                    }],
+                    stop_reason: Some("tool_use".to_string()),
+                    usage: None,
+                });
+            }
+            let truncated = messages.last().is_some_and(|m| match &m.content {
+                microclaw_core::llm_types::MessageContent::Blocks(blocks) => {
+                    blocks.iter().any(|b| {
+                        matches!(
+                            b,
+                            microclaw_core::llm_types::ContentBlock::ToolResult { content, .. }
+                                if content.contains("tool output truncated") && content.len() < 4000
+                        )
+                    })
+                }
+                _ => false,
+            });
+            Ok(MessagesResponse {
+                content: vec![ResponseContentBlock::Text {
+                    text: if truncated { "truncated" } else { "full" }.into(),
+                }],
+                stop_reason: Some("end_turn".to_string()),
+                usage: None,
+            })
+        }
+    }
+
+    struct ToolLoopCompactionLlm {
+        summarize_calls: Arc<AtomicUsize>,
+        overhead_tokens: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for ToolLoopCompactionLlm {
+        async fn send_message(
+            &self,
+            system: &str,
+            messages: Vec<Message>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            use microclaw_core::llm_types::{ContentBlock, MessageContent};
+            if system == "You are a helpful summarizer." {
+                self.summarize_calls.fetch_add(1, Ordering::SeqCst);
+                return Ok(MessagesResponse {
+                    content: vec![ResponseContentBlock::Text {
+                        text: "earlier chat summary".into(),
+                    }],
+                    stop_reason: Some("end_turn".to_string()),
+                    usage: None,
+                });
+            }
+            self.overhead_tokens.store(
+                crate::context_budget::estimate_text_tokens(system)
+                    + crate::context_budget::estimate_tool_definitions_tokens(
+                        tools.as_deref().unwrap_or_default(),
+                    ),
+                Ordering::SeqCst,
+            );
+            let has_block = |pred: &dyn Fn(&ContentBlock) -> bool| {
+                messages.iter().any(|m| match &m.content {
+                    MessageContent::Blocks(blocks) => blocks.iter().any(pred),
+                    MessageContent::Text(_) => false,
+                })
+            };
+            if !has_block(&|b| matches!(b, ContentBlock::ToolResult { .. })) {
+                return Ok(MessagesResponse {
+                    content: vec![ResponseContentBlock::ToolUse {
+                        id: "big-1".into(),
+                        name: "big_output".into(),
+                        input: json!({}),
+                        thought_signature: None,
+                    }],
+                    stop_reason: Some("tool_use".to_string()),
+                    usage: None,
+                });
+            }
+            let paired =
+                has_block(&|b| matches!(b, ContentBlock::ToolUse { id, .. } if id == "big-1"));
+            Ok(MessagesResponse {
+                content: vec![ResponseContentBlock::Text {
+                    text: if paired {
+                        "done"
+                    } else {
+                        "orphaned tool result"
+                    }
+                    .into(),
+                }],
+                stop_reason: Some("end_turn".to_string()),
+                usage: None,
+            })
+        }
+    }
+
+    struct TranscriptProbeLlm {
+        transcripts: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for TranscriptProbeLlm {
+        async fn send_message(
+            &self,
+            _system: &str,
+            messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            let transcript = messages
+                .iter()
+                .map(message_to_text)
+                .collect::<Vec<_>>()
+                .join("\n");
+            self.transcripts.lock().unwrap().push(transcript);
+            Ok(MessagesResponse {
+                content: vec![ResponseContentBlock::Text { text: "ok".into() }],
+                stop_reason: Some("end_turn".to_string()),
+                usage: None,
+            })
+        }
+    }
+
+    struct ApprovalLoopUntilSuccessfulToolLlm {
+        calls: Arc<AtomicUsize>,
+        saw_successful_tool_result: Arc<AtomicBool>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for ApprovalLoopUntilSuccessfulToolLlm {
+        async fn send_message(
+            &self,
+            _system: &str,
+            messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            let idx = self.calls.fetch_add(1, Ordering::SeqCst);
+            if idx == 0 {
+                return Ok(MessagesResponse {
+                    content: vec![ResponseContentBlock::ToolUse {
+                        id: "tool-bash-1".to_string(),
+                        name: "bash".to_string(),
+                        input: json!({"command": "printf approved"}),
+                        thought_signature: None,
+                    }],
+                    stop_reason: Some("tool_use".to_string()),
+                    usage: None,
+                });
+            }
+
+            let mut approval_failed = false;
+            let mut approval_succeeded = false;
+            for msg in messages.iter().rev() {
+                if msg.role != "user" {
+                    continue;
+                }
+                if let microclaw_core::llm_types::MessageContent::Blocks(blocks) = &msg.content {
+                    for block in blocks {
+                        if let microclaw_core::llm_types::ContentBlock::ToolResult {
+                            content,
+                            is_error,
+                            ..
+                        } = block
+                        {
+                            if is_error.unwrap_or(false)
+                                && content.contains("Approval required for high-risk tool")
+                            {
+                                approval_failed = true;
+                            } else if !is_error.unwrap_or(false) && content.contains("approved") {
+                                approval_succeeded = true;
+                            }
+                        }
+                    }
+                    break;
+                }
+            }
+
+            if approval_succeeded {
+                self.saw_successful_tool_result
+                    .store(true, Ordering::SeqCst);
+                return Ok(MessagesResponse {
+                    content: vec![ResponseContentBlock::Text {
+                        text: "approval loop resolved".to_string(),
+                    }],
+                    stop_reason: Some("end_turn".to_string()),
+                    usage: None,
+                });
+            }
+
+            if approval_failed {
+                return Ok(MessagesResponse {
+                    content: vec![ResponseContentBlock::ToolUse {
+                        id: format!("tool-bash-retry-{idx}"),
+                        name: "bash".to_string(),
+                        input: json!({"command": "printf approved"}),
+                        thought_signature: None,
+                    }],
+                    stop_reason: Some("tool_use".to_string()),
+                    usage: None,
+                });
+            }
+
+            Ok(MessagesResponse {
+                content: vec![ResponseContentBlock::Text {
+                    text: "unexpected state".to_string(),
+                }],
+                stop_reason: Some("end_turn".to_string()),
+                usage: None,
+            })
+        }
+    }
+
+    fn test_db() -> (Arc<Database>, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("mc_agent_engine_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Arc::new(Database::new(dir.to_str().unwrap()).unwrap());
+        (db, dir)
     }
 
     fn test_state_with_base_dir(base_dir: &std::path::Path) -> Arc<AppState> {
         test_state_with_llm(base_dir, Box::new(DummyLlm))
     }
 
-    fn test_state_with_llm_and_confirmation(
-        base_dir: &std::path::Path,
-        llm: Box<dyn LlmProvider>,
-        require_user_confirmation: bool,
-    ) -> Arc<AppState> {
-        let runtime_dir = base_dir.join("runtime");
-        std::fs::create_dir_all(&runtime_dir).unwrap();
-        let mut cfg = Config::test_defaults();
-        cfg.data_dir = base_dir.to_string_lossy().to_string();
-        cfg.working_dir = base_dir.join("tmp").to_string_lossy().to_string();
-        cfg.working_dir_isolation = WorkingDirIsolation::Shared;
-        cfg.high_risk_tool_user_confirmation_required = require_user_confirmation;
-        cfg.web_port = 3900;
-        let db = Arc::new(Database::new(runtime_dir.to_str().unwrap()).unwrap());
-        let memory_backend = Arc::new(crate::memory_backend::MemoryBackend::local_only(db.clone()));
-        let mut registry = ChannelRegistry::new();
-        registry.register(Arc::new(WebAdapter));
-        let channel_registry = Arc::new(registry);
-        Arc::new(AppState {
-            config: cfg.clone(),
-            channel_registry: channel_registry.clone(),
-            db: db.clone(),
-            memory: MemoryManager::new(runtime_dir.to_str().unwrap()),
-            skills: SkillManager::from_skills_dir(&cfg.skills_data_dir()),
-            hooks: Arc::new(crate::hooks::HookManager::from_config(&cfg)),
-            llm,
-            llm_provider_overrides: Arc::new(tokio::sync::RwLock::new(
-                std::collections::HashMap::new(),
-            )),
-            llm_model_overrides: Arc::new(tokio::sync::RwLock::new(
-                std::collections::HashMap::new(),
-            )),
-            embedding: None,
-            memory_backend: memory_backend.clone(),
-            tools: ToolRegistry::new(&cfg, channel_registry, db, memory_backend),
-            metric_exporter: None,
-            trace_exporter: None,
-            log_exporter: None,
-        })
+    fn test_state_with_llm_and_confirmation(
+        base_dir: &std::path::Path,
+        llm: Box<dyn LlmProvider>,
+        require_user_confirmation: bool,
+    ) -> Arc<AppState> {
+        test_state_with_options(
+            base_dir,
+            llm,
+            require_user_confirmation,
+            WorkingDirIsolation::Shared,
+        )
+    }
+
+    fn test_state_with_options(
+        base_dir: &std::path::Path,
+        llm: Box<dyn LlmProvider>,
+        require_user_confirmation: bool,
+        working_dir_isolation: WorkingDirIsolation,
+    ) -> Arc<AppState> {
+        let runtime_dir = base_dir.join("runtime");
+        std::fs::create_dir_all(&runtime_dir).unwrap();
+        let mut cfg = Config::test_defaults();
+        cfg.data_dir = base_dir.to_string_lossy().to_string();
+        cfg.working_dir = base_dir.join("tmp").to_string_lossy().to_string();
+        cfg.working_dir_isolation = working_dir_isolation;
+        cfg.high_risk_tool_user_confirmation_required = require_user_confirmation;
+        cfg.web_port = 3900;
+        let db = Arc::new(Database::new(runtime_dir.to_str().unwrap()).unwrap());
+        let memory_backend = Arc::new(crate::memory_backend::MemoryBackend::local_only(db.clone()));
+        let mut registry = ChannelRegistry::new();
+        registry.register(Arc::new(WebAdapter));
+        let channel_registry = Arc::new(registry);
+        Arc::new(AppState {
+            config: cfg.clone(),
+            channel_registry: channel_registry.clone(),
+            db: db.clone(),
+            memory: MemoryManager::new(runtime_dir.to_str().unwrap()),
+            skills: SkillManager::from_skills_dir(&cfg.skills_data_dir()),
+            hooks: Arc::new(crate::hooks::HookManager::from_config(&cfg)),
+            llm,
+            llm_health: Arc::new(crate::llm::LlmHealthRegistry::new()),
+            llm_clients: Arc::new(crate::llm::LlmClientPool::new()),
+            llm_provider_overrides: Arc::new(tokio::sync::RwLock::new(
+                std::collections::HashMap::new(),
+            )),
+            llm_model_overrides: Arc::new(tokio::sync::RwLock::new(
+                std::collections::HashMap::new(),
+            )),
+            embedding: None,
+            memory_backend: memory_backend.clone(),
+            tools: ToolRegistry::new(&cfg, channel_registry, db, memory_backend),
+            metric_exporter: None,
+            trace_exporter: None,
+            log_exporter: None,
+        })
+    }
+
+    fn test_state_with_llm(base_dir: &std::path::Path, llm: Box<dyn LlmProvider>) -> Arc<AppState> {
+        test_state_with_llm_and_confirmation(base_dir, llm, false)
+    }
+
+    fn store_user_message(db: &Database, chat_id: i64, text: &str) {
+        let msg = StoredMessage {
+            id: format!("msg-{}", uuid::Uuid::new_v4()),
+            chat_id,
+            sender_name: "tester".to_string(),
+            content: text.to_string(),
+            is_from_bot: false,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        db.store_message(&msg).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_build_db_memory_context_respects_token_budget() {
+        let (db, dir) = test_db();
+        db.insert_memory(Some(100), "short memory one", "PROFILE")
+            .unwrap();
+        db.insert_memory(Some(100), "short memory two", "KNOWLEDGE")
+            .unwrap();
+        db.insert_memory(Some(100), "short memory three", "EVENT")
+            .unwrap();
+
+        let memory_backend = Arc::new(crate::memory_backend::MemoryBackend::local_only(db.clone()));
+        let context = build_db_memory_context(&memory_backend, &db, None, 100, "short", 20).await;
+        assert!(context.contains("<structured_memories>"));
+        assert!(context.contains("(+"));
+        assert!(context.contains("memories omitted"));
+        assert!(context.contains("</structured_memories>"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_build_db_memory_context_large_budget_keeps_all() {
+        let (db, dir) = test_db();
+        db.insert_memory(Some(100), "user likes rust", "PROFILE")
+            .unwrap();
+        db.insert_memory(Some(100), "user likes coffee", "PROFILE")
+            .unwrap();
+
+        let memory_backend = Arc::new(crate::memory_backend::MemoryBackend::local_only(db.clone()));
+        let context =
+            build_db_memory_context(&memory_backend, &db, None, 100, "likes", 10_000).await;
+        assert!(context.contains("user likes rust"));
+        assert!(context.contains("user likes coffee"));
+        assert!(!context.contains("memories omitted"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_build_db_memory_context_cjk_relevance() {
+        let (db, dir) = test_db();
+        db.insert_memory(Some(100), "用户喜欢咖啡和编程", "PROFILE")
+            .unwrap();
+        db.insert_memory(Some(100), "User prefers Rust and tea", "PROFILE")
+            .unwrap();
+
+        let memory_backend = Arc::new(crate::memory_backend::MemoryBackend::local_only(db.clone()));
+        let context =
+            build_db_memory_context(&memory_backend, &db, None, 100, "喜欢 咖啡", 10_000).await;
+        let first_line = context
+            .lines()
+            .find(|line| line.starts_with('['))
+            .unwrap_or("");
+        assert!(first_line.contains("用户喜欢咖啡和编程"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_build_db_memory_context_fts_reaches_past_recent_window() {
+        let (db, dir) = test_db();
+        db.insert_memory(
+            Some(100),
+            "Production deploys need a change ticket",
+            "KNOWLEDGE",
+        )
+        .unwrap();
+        for i in 0..120 {
+            db.insert_memory(Some(100), &format!("filler note number {i}"), "EVENT")
+                .unwrap();
+        }
+
+        let memory_backend = Arc::new(crate::memory_backend::MemoryBackend::local_only(db.clone()));
+        let context =
+            build_db_memory_context(&memory_backend, &db, None, 100, "how do I deploy?", 10_000)
+                .await;
+        let first_line = context
+            .lines()
+            .find(|line| line.starts_with('['))
+            .unwrap_or("");
+        assert!(first_line.contains("Production deploys need a change ticket"));
+
+        let logs = db
+            .get_memory_injection_logs(Some(100), None, 10, 0)
+            .unwrap();
+        assert_eq!(logs[0].retrieval_method, "fts");
+        assert_eq!(logs[0].candidate_count, 101);
+
+        let _ = build_db_memory_context(&memory_backend, &db, None, 100, "", 10_000).await;
+        let logs = db
+            .get_memory_injection_logs(Some(100), None, 10, 0)
+            .unwrap();
+        assert!(logs.iter().any(|l| l.retrieval_method == "recency"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_explicit_memory_fast_path_works_across_channels_and_recall_after_restart() {
+        let cases = vec![
+            (
+                "web",
+                "chat-ext-web-1",
+                "web",
+                "Remember that production database port is 5433",
+            ),
+            (
+                "telegram",
+                "1001",
+                "private",
+                "Remember that production database port is 5433",
+            ),
+            (
+                "discord",
+                "discord-room-a",
+                "discord",
+                "Remember that production database port is 5433",
+            ),
+        ];
+
+        for (caller_channel, external_chat_id, chat_type, message) in cases {
+            let base_dir = std::env::temp_dir()
+                .join(format!("mc_agent_cross_channel_{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&base_dir).unwrap();
+            let state = test_state_with_base_dir(&base_dir);
+            let chat_id = state
+                .db
+                .resolve_or_create_chat_id(
+                    caller_channel,
+                    external_chat_id,
+                    Some("test-chat"),
+                    chat_type,
+                )
+                .unwrap();
+
+            store_user_message(&state.db, chat_id, message);
+            let reply = process_with_agent(
+                &state,
+                AgentRequestContext {
+                    caller_channel,
+                    chat_id,
+                    chat_type,
+                },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+            assert!(
+                reply.contains("Saved memory #"),
+                "expected explicit fast-path save reply, got: {reply}"
+            );
+
+            let mems = state.db.get_all_memories_for_chat(Some(chat_id)).unwrap();
+            assert_eq!(mems.iter().filter(|m| !m.is_archived).count(), 1);
+            drop(state);
+
+            // Restart simulation: new AppState reading the same runtime data.
+            let restarted = test_state_with_base_dir(&base_dir);
+            let recalled = build_db_memory_context(
+                &restarted.memory_backend,
+                &restarted.db,
+                None,
+                chat_id,
+                "database port",
+                1500,
+            )
+            .await;
+            assert!(
+                recalled.contains("production database port is 5433"),
+                "expected memory recall after restart, got: {recalled}"
+            );
+
+            drop(restarted);
+            let _ = std::fs::remove_dir_all(&base_dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_explicit_memory_topic_conflict_supersedes_old_value() {
+        let base_dir =
+            std::env::temp_dir().join(format!("mc_agent_topic_conflict_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let state = test_state_with_base_dir(&base_dir);
+        let chat_id = state
+            .db
+            .resolve_or_create_chat_id("web", "topic-conflict-chat", Some("topic"), "web")
+            .unwrap();
+
+        store_user_message(
+            &state.db,
+            chat_id,
+            "Remember that production database port is 5433",
+        );
+        let first = process_with_agent(
+            &state,
+            AgentRequestContext {
+                caller_channel: "web",
+                chat_id,
+                chat_type: "web",
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(
+            first.contains("Saved memory #"),
+            "unexpected first reply: {first}"
+        );
+
+        store_user_message(
+            &state.db,
+            chat_id,
+            "Remember that db port for primary cluster is 6432",
+        );
+        let second = process_with_agent(
+            &state,
+            AgentRequestContext {
+                caller_channel: "web",
+                chat_id,
+                chat_type: "web",
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(
+            second.contains("Superseded memory #"),
+            "expected supersede reply, got: {second}"
+        );
+
+        let all = state.db.get_all_memories_for_chat(Some(chat_id)).unwrap();
+        let active: Vec<_> = all.iter().filter(|m| !m.is_archived).collect();
+        let archived: Vec<_> = all.iter().filter(|m| m.is_archived).collect();
+        assert_eq!(active.len(), 1);
+        assert!(
+            active[0].content.contains("6432"),
+            "active memory should keep latest value"
+        );
+        assert!(
+            archived.iter().any(|m| m.content.contains("5433")),
+            "old value should be archived after supersede"
+        );
+
+        drop(state);
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[tokio::test]
+    async fn test_empty_visible_reply_auto_retries_once() {
+        let base_dir =
+            std::env::temp_dir().join(format!("mc_agent_empty_retry_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let llm = EmptyVisibleThenNormalLlm {
+            calls: calls.clone(),
+        };
+        let state = test_state_with_llm(&base_dir, Box::new(llm));
+        let chat_id = state
+            .db
+            .resolve_or_create_chat_id("web", "empty-retry-chat", Some("empty"), "web")
+            .unwrap();
+        store_user_message(&state.db, chat_id, "hello");
+
+        let reply = process_with_agent(
+            &state,
+            AgentRequestContext {
+                caller_channel: "web",
+                chat_id,
+                chat_type: "web",
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(reply, "Visible retry answer.");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        drop(state);
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_retries_until_schema_matches() {
+        let base_dir =
+            std::env::temp_dir().join(format!("mc_agent_structured_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let llm = StructuredRetryLlm {
+            calls: calls.clone(),
+        };
+        let state = test_state_with_llm(&base_dir, Box::new(llm));
+        let chat_id = state
+            .db
+            .resolve_or_create_chat_id("web", "structured-chat", Some("structured"), "web")
+            .unwrap();
+        store_user_message(&state.db, chat_id, "weather in paris?");
+
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "city": {"type": "string"},
+                "temp_c": {"type": "number"}
+            },
+            "required": ["city", "temp_c"]
+        });
+        let reply = process_with_agent_structured(
+            &state,
+            AgentRequestContext {
+                caller_channel: "web",
+                chat_id,
+                chat_type: "web",
+            },
+            None,
+            schema,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(reply.output, json!({"city": "Paris", "temp_c": 21}));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        drop(state);
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_forces_final_answer_after_text_reply() {
+        let base_dir =
+            std::env::temp_dir().join(format!("mc_agent_forced_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let offered = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let llm = StructuredTextFirstLlm {
+            offered: offered.clone(),
+        };
+        let state = test_state_with_llm(&base_dir, Box::new(llm));
+        let chat_id = state
+            .db
+            .resolve_or_create_chat_id("web", "forced-chat", Some("forced"), "web")
+            .unwrap();
+        store_user_message(&state.db, chat_id, "weather in paris?");
+
+        let schema = json!({
+            "type": "object",
+            "properties": {"city": {"type": "string"}, "temp_c": {"type": "number"}},
+            "required": ["city", "temp_c"]
+        });
+        let reply = process_with_agent_structured(
+            &state,
+            AgentRequestContext {
+                caller_channel: "web",
+                chat_id,
+                chat_type: "web",
+            },
+            None,
+            schema,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(reply.output, json!({"city": "Paris", "temp_c": 21}));
+        let offered = offered.lock().unwrap();
+        assert_eq!(offered.len(), 2);
+        assert!(offered[0].len() > 1);
+        assert!(offered[0].iter().any(|n| n == FINAL_ANSWER_TOOL_NAME));
+        assert_eq!(offered[1], vec![FINAL_ANSWER_TOOL_NAME.to_string()]);
+
+        drop(state);
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[tokio::test]
+    async fn test_low_risk_tool_calls_run_in_parallel_and_keep_order() {
+        let base_dir =
+            std::env::temp_dir().join(format!("mc_agent_parallel_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let llm = ParallelToolCallsLlm {
+            calls: Arc::new(AtomicUsize::new(0)),
+        };
+        let mut state = test_state_with_llm(&base_dir, Box::new(llm));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        Arc::get_mut(&mut state)
+            .unwrap()
+            .tools
+            .add_tool(Box::new(ParallelProbeTool {
+                in_flight: Arc::new(AtomicUsize::new(0)),
+                max_in_flight: max_in_flight.clone(),
+            }));
+        let chat_id = state
+            .db
+            .resolve_or_create_chat_id("web", "parallel-chat", Some("parallel"), "web")
+            .unwrap();
+        store_user_message(&state.db, chat_id, "probe three times");
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let reply = process_with_agent_with_events(
+            &state,
+            AgentRequestContext {
+                caller_channel: "web",
+                chat_id,
+                chat_type: "web",
+            },
+            None,
+            None,
+            Some(&tx),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(reply, "probe-0=probe 0,probe-1=probe 1,probe-2=probe 2");
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+        drop(tx);
+        let mut starts = 0;
+        let mut results = 0;
+        while let Some(event) = rx.recv().await {
+            match event {
+                AgentEvent::ToolStart { .. } => starts += 1,
+                AgentEvent::ToolResult { is_error, .. } => {
+                    assert!(!is_error);
+                    results += 1;
+                }
+                _ => {}
+            }
+        }
+        assert_eq!((starts, results), (3, 3));
+
+        drop(state);
+        let _ = std::fs::remove_dir_all(&base_dir);
     }
 
-    fn test_state_with_llm(base_dir: &std::path::Path, llm: Box<dyn LlmProvider>) -> Arc<AppState> {
-        test_state_with_llm_and_confirmation(base_dir, llm, false)
+    struct WriteFileThenDoneLlm {
+        calls: Arc<AtomicUsize>,
+        path: String,
     }
 
-    fn store_user_message(db: &Database, chat_id: i64, text: &str) {
-        let msg = StoredMessage {
-            id: format!("msg-{}", uuid::Uuid::new_v4()),
-            chat_id,
-            sender_name: "tester".to_string(),
-            content: text.to_string(),
-            is_from_bot: false,
-            timestamp: chrono::Utc::now().to_rfc3339(),
-        };
-        db.store_message(&msg).unwrap();
+    #[async_trait::async_trait]
+    impl LlmProvider for WriteFileThenDoneLlm {
+        async fn send_message(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Ok(MessagesResponse {
+                    content: ["v2", "v3"]
+                        .iter()
+                        .map(|content| ResponseContentBlock::ToolUse {
+                            id: format!("write-{content}"),
+                            name: "write_file".into(),
+                            input: json!({ "path": self.path, "content": content }),
+                            thought_signature: None,
+                        })
+                        .collect(),
+                    stop_reason: Some("tool_use".to_string()),
+                    usage: None,
+                });
+            }
+            Ok(MessagesResponse {
+                content: vec![ResponseContentBlock::Text {
+                    text: "written".to_string(),
+                }],
+                stop_reason: Some("end_turn".to_string()),
+                usage: None,
+            })
+        }
     }
 
     #[tokio::test]
-    async fn test_build_db_memory_context_respects_token_budget() {
-        let (db, dir) = test_db();
-        db.insert_memory(Some(100), "short memory one", "PROFILE")
-            .unwrap();
-        db.insert_memory(Some(100), "short memory two", "KNOWLEDGE")
+    async fn test_file_changing_turn_is_checkpointed_once_and_undone() {
+        let base_dir =
+            std::env::temp_dir().join(format!("mc_agent_checkpoint_{}", uuid::Uuid::new_v4()));
+        // Relative to the chat's own working dir; `/undo` is refused for a shared one.
+        let llm = WriteFileThenDoneLlm {
+            calls: Arc::new(AtomicUsize::new(0)),
+            path: "notes.txt".to_string(),
+        };
+        let state =
+            test_state_with_options(&base_dir, Box::new(llm), false, WorkingDirIsolation::Chat);
+        let chat_id = state
+            .db
+            .resolve_or_create_chat_id("web", "checkpoint-chat", Some("checkpoint"), "web")
             .unwrap();
-        db.insert_memory(Some(100), "short memory three", "EVENT")
+        let work_dir = crate::checkpoints::chat_working_dir(&state.config, "web", chat_id);
+        std::fs::create_dir_all(&work_dir).unwrap();
+        let file = work_dir.join("notes.txt");
+        std::fs::write(&file, "v1").unwrap();
+        store_user_message(&state.db, chat_id, "rewrite the notes");
+
+        let reply = process_with_agent(
+            &state,
+            AgentRequestContext {
+                caller_channel: "web",
+                chat_id,
+                chat_type: "web",
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(reply, "written");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "v3");
+
+        let checkpoints = state.db.list_checkpoints(chat_id, 10).unwrap();
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].message_count, 1);
+
+        let undo = crate::chat_commands::handle_chat_command(&state, chat_id, "web", "/undo", None)
+            .await
             .unwrap();
+        assert!(undo.starts_with("Restored working directory"), "{undo}");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "v1");
+        drop(state);
 
-        let memory_backend = Arc::new(crate::memory_backend::MemoryBackend::local_only(db.clone()));
-        let context = build_db_memory_context(&memory_backend, &db, None, 100, "short", 20).await;
-        assert!(context.contains("<structured_memories>"));
-        assert!(context.contains("(+"));
-        assert!(context.contains("memories omitted"));
-        assert!(context.contains("</structured_memories>"));
+        let llm = WriteFileThenDoneLlm {
+            calls: Arc::new(AtomicUsize::new(0)),
+            path: "notes.txt".to_string(),
+        };
+        let shared = test_state_with_llm(&base_dir.join("shared"), Box::new(llm));
+        let shared_chat_id = shared
+            .db
+            .resolve_or_create_chat_id("web", "checkpoint-chat", Some("checkpoint"), "web")
+            .unwrap();
+        store_user_message(&shared.db, shared_chat_id, "rewrite the notes");
+        process_with_agent(
+            &shared,
+            AgentRequestContext {
+                caller_channel: "web",
+                chat_id: shared_chat_id,
+                chat_type: "web",
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(shared
+            .db
+            .list_checkpoints(shared_chat_id, 10)
+            .unwrap()
+            .is_empty());
+        let refused = crate::chat_commands::handle_chat_command(
+            &shared,
+            shared_chat_id,
+            "web",
+            "/undo",
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(
+            refused.contains("working_dir_isolation: shared"),
+            "{refused}"
+        );
 
-        let _ = std::fs::remove_dir_all(dir);
+        drop(shared);
+        let _ = std::fs::remove_dir_all(&base_dir);
     }
 
     #[tokio::test]
-    async fn test_build_db_memory_context_large_budget_keeps_all() {
-        let (db, dir) = test_db();
-        db.insert_memory(Some(100), "user likes rust", "PROFILE")
+    async fn test_reflector_review_queue_gates_low_confidence_facts() {
+        let base_dir =
+            std::env::temp_dir().join(format!("mc_memory_review_{}", uuid::Uuid::new_v4()));
+        let mut state = test_state_with_base_dir(&base_dir);
+        let review = &mut Arc::get_mut(&mut state).unwrap().config.memory_review;
+        review.enabled = true;
+        review.reject_below = 0.3;
+        let chat_id = state
+            .db
+            .resolve_or_create_chat_id("web", "review-chat", Some("review"), "web")
             .unwrap();
-        db.insert_memory(Some(100), "user likes coffee", "PROFILE")
+
+        let extracted = vec![
+            serde_json::json!({"content": "User prefers dark roast coffee", "category": "PROFILE", "confidence": 0.95}),
+            serde_json::json!({"content": "User works on the payments team", "category": "KNOWLEDGE", "confidence": 0.6}),
+            serde_json::json!({"content": "User might enjoy jazz concerts", "category": "PROFILE", "confidence": 0.1}),
+        ];
+        let outcome =
+            crate::memory_service::apply_reflector_extractions(&state, chat_id, &[], &extracted)
+                .await;
+        assert_eq!(outcome.inserted, 1);
+        assert_eq!(outcome.auto_approved, 1);
+        assert_eq!(outcome.skipped, 1);
+        assert_eq!(outcome.queued_review_ids.len(), 1);
+        let review_id = outcome.queued_review_ids[0];
+        assert_eq!(
+            state
+                .db
+                .get_all_memories_for_chat(Some(chat_id))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // A fact already waiting for review is not queued twice.
+        let again = crate::memory_service::apply_reflector_extractions(
+            &state,
+            chat_id,
+            &[],
+            &extracted[1..2],
+        )
+        .await;
+        assert!(again.queued_review_ids.is_empty());
+        assert_eq!(again.skipped, 1);
+
+        let list =
+            crate::chat_commands::handle_chat_command(&state, chat_id, "web", "/review", None)
+                .await
+                .unwrap();
+        assert!(list.contains(&format!("#{review_id}")), "{list}");
+        assert!(list.contains("payments team"), "{list}");
+
+        let other_chat = state
+            .db
+            .resolve_or_create_chat_id("web", "other-chat", Some("other"), "web")
             .unwrap();
+        let denied = crate::chat_commands::handle_chat_command(
+            &state,
+            other_chat,
+            "web",
+            &format!("/review approve {review_id}"),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(denied.contains("not found"), "{denied}");
 
-        let memory_backend = Arc::new(crate::memory_backend::MemoryBackend::local_only(db.clone()));
-        let context =
-            build_db_memory_context(&memory_backend, &db, None, 100, "likes", 10_000).await;
-        assert!(context.contains("user likes rust"));
-        assert!(context.contains("user likes coffee"));
-        assert!(!context.contains("memories omitted"));
+        let approved = crate::chat_commands::handle_chat_command(
+            &state,
+            chat_id,
+            "web",
+            &format!("/review edit {review_id} User works on the payments platform team"),
+            Some("alice"),
+        )
+        .await
+        .unwrap();
+        assert!(approved.starts_with("Saved memory"), "{approved}");
+        let memories = state.db.get_all_memories_for_chat(Some(chat_id)).unwrap();
+        assert!(memories
+            .iter()
+            .any(|m| m.content == "User works on the payments platform team"
+                && m.source == "reflector_reviewed"));
+        let decided = state.db.get_memory_review(review_id).unwrap().unwrap();
+        assert_eq!(decided.status, "edited");
+        assert_eq!(decided.decided_by.as_deref(), Some("web:alice"));
 
-        let _ = std::fs::remove_dir_all(dir);
+        drop(state);
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    fn save_alternating_session(db: &Database, chat_id: i64, count: usize, text: &str) {
+        let messages: Vec<Message> = (0..count)
+            .map(|i| Message {
+                role: if i % 2 == 0 { "user" } else { "assistant" }.into(),
+                content: microclaw_core::llm_types::MessageContent::Text(format!("{text} {i}")),
+            })
+            .collect();
+        db.save_session(chat_id, &serde_json::to_string(&messages).unwrap())
+            .unwrap();
     }
 
     #[tokio::test]
-    async fn test_build_db_memory_context_cjk_relevance() {
-        let (db, dir) = test_db();
-        db.insert_memory(Some(100), "用户喜欢咖啡和编程", "PROFILE")
+    async fn test_short_messages_do_not_compact_within_token_budget() {
+        let base_dir =
+            std::env::temp_dir().join(format!("mc_agent_budget_short_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let summarize_calls = Arc::new(AtomicUsize::new(0));
+        let llm = CompactionProbeLlm {
+            summarize_calls: summarize_calls.clone(),
+        };
+        let state = test_state_with_llm(&base_dir, Box::new(llm));
+        let chat_id = state
+            .db
+            .resolve_or_create_chat_id("web", "budget-short", Some("budget"), "web")
             .unwrap();
-        db.insert_memory(Some(100), "User prefers Rust and tea", "PROFILE")
+        // More messages than max_session_messages, but only a few hundred tokens.
+        save_alternating_session(&state.db, chat_id, 45, "ok");
+
+        let reply = process_with_agent(
+            &state,
+            AgentRequestContext {
+                caller_channel: "web",
+                chat_id,
+                chat_type: "web",
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(reply, "done");
+        assert_eq!(summarize_calls.load(Ordering::SeqCst), 0);
+
+        drop(state);
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[tokio::test]
+    async fn test_context_compacts_when_projected_tokens_exceed_window() {
+        let base_dir =
+            std::env::temp_dir().join(format!("mc_agent_budget_full_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let summarize_calls = Arc::new(AtomicUsize::new(0));
+        let llm = CompactionProbeLlm {
+            summarize_calls: summarize_calls.clone(),
+        };
+        let mut state = test_state_with_llm(&base_dir, Box::new(llm));
+        Arc::get_mut(&mut state)
+            .unwrap()
+            .config
+            .context_window_tokens = Some(1_000);
+        let chat_id = state
+            .db
+            .resolve_or_create_chat_id("web", "budget-full", Some("budget"), "web")
             .unwrap();
+        // Well under max_session_messages, but far beyond a 1k-token window.
+        save_alternating_session(&state.db, chat_id, 5, &"pasted log ".repeat(500));
+
+        let reply = process_with_agent(
+            &state,
+            AgentRequestContext {
+                caller_channel: "web",
+                chat_id,
+                chat_type: "web",
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
 
-        let memory_backend = Arc::new(crate::memory_backend::MemoryBackend::local_only(db.clone()));
-        let context =
-            build_db_memory_context(&memory_backend, &db, None, 100, "喜欢 咖啡", 10_000).await;
-        let first_line = context
-            .lines()
-            .find(|line| line.starts_with('['))
-            .unwrap_or("");
-        assert!(first_line.contains("用户喜欢咖啡和编程"));
+        assert_eq!(reply, "done");
+        assert_eq!(summarize_calls.load(Ordering::SeqCst), 1);
 
-        let _ = std::fs::remove_dir_all(dir);
+        drop(state);
+        let _ = std::fs::remove_dir_all(&base_dir);
     }
 
     #[tokio::test]
-    async fn test_explicit_memory_fast_path_works_across_channels_and_recall_after_restart() {
-        let cases = vec![
-            (
-                "web",
-                "chat-ext-web-1",
-                "web",
-                "Remember that production database port is 5433",
-            ),
-            (
-                "telegram",
-                "1001",
-                "private",
-                "Remember that production database port is 5433",
-            ),
-            (
-                "discord",
-                "discord-room-a",
-                "discord",
-                "Remember that production database port is 5433",
-            ),
-        ];
-
-        for (caller_channel, external_chat_id, chat_type, message) in cases {
-            let base_dir = std::env::temp_dir()
-                .join(format!("mc_agent_cross_channel_{}", uuid::Uuid::new_v4()));
+    async fn test_context_compacts_when_tool_results_outgrow_window_mid_loop() {
+        let run = |window: Option<usize>| async move {
+            let base_dir =
+                std::env::temp_dir().join(format!("mc_agent_budget_loop_{}", uuid::Uuid::new_v4()));
             std::fs::create_dir_all(&base_dir).unwrap();
-            let state = test_state_with_base_dir(&base_dir);
+            let summarize_calls = Arc::new(AtomicUsize::new(0));
+            let overhead_tokens = Arc::new(AtomicUsize::new(0));
+            let llm = ToolLoopCompactionLlm {
+                summarize_calls: summarize_calls.clone(),
+                overhead_tokens: overhead_tokens.clone(),
+            };
+            let mut state = test_state_with_llm(&base_dir, Box::new(llm));
+            let max_tokens = {
+                let state_mut = Arc::get_mut(&mut state).unwrap();
+                state_mut.config.max_tool_result_tokens = 2_000;
+                state_mut.config.context_window_tokens = window;
+                state_mut.tools.add_tool(Box::new(BigOutputTool));
+                state_mut.config.max_tokens as usize
+            };
             let chat_id = state
                 .db
-                .resolve_or_create_chat_id(
-                    caller_channel,
-                    external_chat_id,
-                    Some("test-chat"),
-                    chat_type,
-                )
+                .resolve_or_create_chat_id("web", "budget-loop", Some("budget"), "web")
                 .unwrap();
-
-            store_user_message(&state.db, chat_id, message);
+            store_user_message(&state.db, chat_id, "dump the logs");
             let reply = process_with_agent(
                 &state,
                 AgentRequestContext {
-                    caller_channel,
+                    caller_channel: "web",
                     chat_id,
-                    chat_type,
+                    chat_type: "web",
                 },
                 None,
                 None,
             )
             .await
             .unwrap();
-            assert!(
-                reply.contains("Saved memory #"),
-                "expected explicit fast-path save reply, got: {reply}"
-            );
-
-            let mems = state.db.get_all_memories_for_chat(Some(chat_id)).unwrap();
-            assert_eq!(mems.iter().filter(|m| !m.is_archived).count(), 1);
             drop(state);
-
-            // Restart simulation: new AppState reading the same runtime data.
-            let restarted = test_state_with_base_dir(&base_dir);
-            let recalled = build_db_memory_context(
-                &restarted.memory_backend,
-                &restarted.db,
-                None,
-                chat_id,
-                "database port",
-                1500,
+            let _ = std::fs::remove_dir_all(&base_dir);
+            (
+                reply,
+                summarize_calls.load(Ordering::SeqCst),
+                overhead_tokens.load(Ordering::SeqCst) + max_tokens,
             )
-            .await;
-            assert!(
-                recalled.contains("production database port is 5433"),
-                "expected memory recall after restart, got: {recalled}"
-            );
+        };
 
-            drop(restarted);
-            let _ = std::fs::remove_dir_all(&base_dir);
-        }
+        // Measure the fixed request overhead, then size the window so the opening
+        // prompt fits but the ~2k-token tool result does not.
+        let (reply, summarize_calls, reserved) = run(Some(1_000_000)).await;
+        assert_eq!(reply, "done");
+        assert_eq!(summarize_calls, 0);
+        let window = ((reserved + 1_000) as f64 / COMPACTION_TRIGGER_RATIO).ceil() as usize;
+
+        let (reply, summarize_calls, _) = run(Some(window)).await;
+        assert_eq!(reply, "done");
+        assert_eq!(summarize_calls, 1);
     }
 
     #[tokio::test]
-    async fn test_explicit_memory_topic_conflict_supersedes_old_value() {
+    async fn test_oversized_tool_result_is_truncated_and_spilled() {
         let base_dir =
-            std::env::temp_dir().join(format!("mc_agent_topic_conflict_{}", uuid::Uuid::new_v4()));
+            std::env::temp_dir().join(format!("mc_agent_tool_spill_{}", uuid::Uuid::new_v4()));
         std::fs::create_dir_all(&base_dir).unwrap();
-        let state = test_state_with_base_dir(&base_dir);
+        let llm = BigOutputLlm {
+            calls: Arc::new(AtomicUsize::new(0)),
+        };
+        let mut state = test_state_with_llm(&base_dir, Box::new(llm));
+        {
+            let state_mut = Arc::get_mut(&mut state).unwrap();
+            state_mut.config.max_tool_result_tokens = 200;
+            state_mut.tools.add_tool(Box::new(BigOutputTool));
+        }
         let chat_id = state
             .db
-            .resolve_or_create_chat_id("web", "topic-conflict-chat", Some("topic"), "web")
+            .resolve_or_create_chat_id("web", "tool-spill", Some("spill"), "web")
             .unwrap();
+        store_user_message(&state.db, chat_id, "dump the logs");
 
-        store_user_message(
-            &state.db,
-            chat_id,
-            "Remember that production database port is 5433",
-        );
-        let first = process_with_agent(
+        let reply = process_with_agent(
             &state,
             AgentRequestContext {
                 caller_channel: "web",
@@ -2824,17 +4713,172 @@ mod tests {
         )
         .await
         .unwrap();
-        assert!(
-            first.contains("Saved memory #"),
-            "unexpected first reply: {first}"
+
+        assert_eq!(reply, "truncated");
+        let spilled = state
+            .config
+            .tool_results_dir()
+            .join("web")
+            .join(chat_id.to_string())
+            .join("big-1.txt");
+        assert_eq!(
+            std::fs::read_to_string(spilled).unwrap(),
+            "log line\n".repeat(5000)
         );
+        assert!(!base_dir
+            .join("tmp")
+            .join("shared")
+            .join(".tool_results")
+            .exists());
 
-        store_user_message(
-            &state.db,
-            chat_id,
-            "Remember that db port for primary cluster is 6432",
+        drop(state);
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[tokio::test]
+    async fn test_spilled_tool_result_is_readable_inside_sandbox() {
+        use super::tool_result_spill_path;
+        use crate::context_budget::truncate_tool_result;
+        use crate::tools::Tool;
+        use microclaw_tools::sandbox::{SandboxBackend, SandboxConfig, SandboxMode, SandboxRouter};
+        if !microclaw_tools::sandbox_native::native_available() {
+            eprintln!("skipping: native sandbox unavailable");
+            return;
+        }
+        let base_dir =
+            std::env::temp_dir().join(format!("mc_agent_spill_sandbox_{}", uuid::Uuid::new_v4()));
+        let work = base_dir.join("work");
+        std::fs::create_dir_all(&work).unwrap();
+        let work = std::fs::canonicalize(&work).unwrap();
+        let mut cfg = Config::test_defaults();
+        cfg.data_dir = base_dir.join("data").to_string_lossy().to_string();
+        cfg.working_dir = work.to_string_lossy().to_string();
+        cfg.working_dir_isolation = WorkingDirIsolation::Chat;
+
+        let extra_mounts = crate::tools::ToolRegistry::build_extra_mounts(
+            &work,
+            &cfg.skills_data_dir(),
+            &cfg.tool_results_dir(),
         );
-        let second = process_with_agent(
+        let results_root = std::fs::canonicalize(cfg.tool_results_dir()).unwrap();
+        let spill_path = tool_result_spill_path(&cfg, "web", 42, "toolu/1");
+        assert!(spill_path.starts_with(results_root.join("web").join("42")));
+        assert!(!spill_path.starts_with(&work));
+        let content = "log line\n".repeat(5000);
+        let truncated = truncate_tool_result(&content, 200, &spill_path).unwrap();
+        assert!(truncated.contains(&spill_path.display().to_string()));
+
+        let router = Arc::new(SandboxRouter::new(
+            SandboxConfig {
+                mode: SandboxMode::All,
+                backend: SandboxBackend::Native,
+                ..SandboxConfig::default()
+            },
+            &work,
+            extra_mounts,
+        ));
+        let read = crate::tools::read_file::ReadFileTool::new_with_isolation(
+            &cfg.working_dir,
+            WorkingDirIsolation::Chat,
+        )
+        .with_sandbox_router(router);
+        let result = read
+            .execute(json!({
+                "path": spill_path.to_string_lossy(),
+                "offset": 4990,
+                "limit": 5,
+                "__microclaw_auth": {"caller_channel": "web", "caller_chat_id": 42}
+            }))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result.content.contains("log line"));
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_prune_tool_result_spills_keeps_newest_and_drops_expired() {
+        use super::prune_tool_result_spills;
+        let dir =
+            std::env::temp_dir().join(format!("mc_agent_spill_prune_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let now = std::time::SystemTime::now();
+        for (name, age_secs) in [("a", 30), ("b", 20), ("c", 10), ("old", 900)] {
+            let path = dir.join(format!("{name}.txt"));
+            std::fs::write(&path, name).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - std::time::Duration::from_secs(age_secs))
+                .unwrap();
+        }
+
+        prune_tool_result_spills(&dir, 3, std::time::Duration::from_secs(600));
+        let mut left: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(left, vec!["a.txt", "b.txt", "c.txt"]);
+
+        prune_tool_result_spills(&dir, 2, std::time::Duration::from_secs(600));
+        assert!(!dir.join("a.txt").exists());
+        assert!(dir.join("b.txt").exists() && dir.join("c.txt").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    struct TimeThenAnswerLlm {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for TimeThenAnswerLlm {
+        async fn send_message(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            let content = if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                ResponseContentBlock::ToolUse {
+                    id: "time-1".into(),
+                    name: "get_current_time".into(),
+                    input: json!({}),
+                    thought_signature: None,
+                }
+            } else {
+                ResponseContentBlock::Text {
+                    text: "Replayed answer.".into(),
+                }
+            };
+            let stop_reason = match content {
+                ResponseContentBlock::ToolUse { .. } => "tool_use",
+                _ => "end_turn",
+            };
+            Ok(MessagesResponse {
+                content: vec![content],
+                stop_reason: Some(stop_reason.into()),
+                usage: None,
+            })
+        }
+    }
+
+    async fn run_replay_scenario(
+        llm: Box<dyn LlmProvider>,
+        question: &str,
+    ) -> Result<String, anyhow::Error> {
+        let base_dir =
+            std::env::temp_dir().join(format!("mc_agent_replay_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let state = test_state_with_llm(&base_dir, llm);
+        let chat_id = state
+            .db
+            .resolve_or_create_chat_id("web", "replay-chat", Some("replay"), "web")
+            .unwrap();
+        store_user_message(&state.db, chat_id, question);
+        let result = process_with_agent(
             &state,
             AgentRequestContext {
                 caller_channel: "web",
@@ -2844,61 +4888,208 @@ mod tests {
             None,
             None,
         )
+        .await;
+        drop(state);
+        let _ = std::fs::remove_dir_all(&base_dir);
+        result
+    }
+
+    #[tokio::test]
+    async fn test_agent_loop_runs_offline_from_replay_cassette() {
+        let cassette_dir =
+            std::env::temp_dir().join(format!("mc_agent_cassette_{}", uuid::Uuid::new_v4()));
+        let recorded = cassette_dir.join("agent.jsonl");
+        let reply = run_replay_scenario(
+            Box::new(crate::llm::RecordingProvider::new(
+                Box::new(TimeThenAnswerLlm {
+                    calls: AtomicUsize::new(0),
+                }),
+                &recorded,
+            )),
+            "what time is it?",
+        )
         .await
         .unwrap();
-        assert!(
-            second.contains("Superseded memory #"),
-            "expected supersede reply, got: {second}"
-        );
+        assert_eq!(reply, "Replayed answer.");
+        let diverging = cassette_dir.join("diverging.jsonl");
+        std::fs::copy(&recorded, &diverging).unwrap();
+
+        // A fresh run of the same scenario replays offline despite new timestamps and paths.
+        let reply = run_replay_scenario(
+            Box::new(crate::llm::ReplayProvider::new(&recorded)),
+            "what time is it?",
+        )
+        .await
+        .unwrap();
+        assert_eq!(reply, "Replayed answer.");
 
-        let all = state.db.get_all_memories_for_chat(Some(chat_id)).unwrap();
-        let active: Vec<_> = all.iter().filter(|m| !m.is_archived).collect();
-        let archived: Vec<_> = all.iter().filter(|m| m.is_archived).collect();
-        assert_eq!(active.len(), 1);
-        assert!(
-            active[0].content.contains("6432"),
-            "active memory should keep latest value"
-        );
+        // A run that sends the model something else fails instead of replaying green.
+        let err = run_replay_scenario(
+            Box::new(crate::llm::ReplayProvider::new(&diverging)),
+            "what day is it?",
+        )
+        .await
+        .unwrap_err();
         assert!(
-            archived.iter().any(|m| m.content.contains("5433")),
-            "old value should be archived after supersede"
+            err.to_string()
+                .contains("does not match any recorded entry"),
+            "{err}"
         );
 
-        drop(state);
-        let _ = std::fs::remove_dir_all(&base_dir);
+        let _ = std::fs::remove_dir_all(&cassette_dir);
     }
 
+    #[cfg(not(windows))]
     #[tokio::test]
-    async fn test_empty_visible_reply_auto_retries_once() {
+    async fn test_inbound_message_hook_rewrites_and_drops_user_turns() {
         let base_dir =
-            std::env::temp_dir().join(format!("mc_agent_empty_retry_{}", uuid::Uuid::new_v4()));
-        std::fs::create_dir_all(&base_dir).unwrap();
-        let calls = Arc::new(AtomicUsize::new(0));
-        let llm = EmptyVisibleThenNormalLlm {
-            calls: calls.clone(),
+            std::env::temp_dir().join(format!("mc_agent_inbound_hook_{}", uuid::Uuid::new_v4()));
+        let hook_dir = base_dir.join("hooks").join("inbound-dlp");
+        std::fs::create_dir_all(&hook_dir).unwrap();
+        std::fs::write(
+            hook_dir.join("HOOK.md"),
+            "---\nname: inbound-dlp\nevents: [OnInboundMessage]\ncommand: \"sh hook.sh\"\ntimeout_ms: 2000\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            hook_dir.join("hook.sh"),
+            r#"#!/bin/sh
+payload="$(cat)"
+if echo "$payload" | grep -q 'drop me'; then
+  echo '{"action":"block","reason":"dropped by policy"}'
+elif echo "$payload" | grep -q 'sk-secret'; then
+  echo '{"action":"modify","patch":{"content":"[redacted]"}}'
+else
+  echo '{"action":"allow"}'
+fi
+"#,
+        )
+        .unwrap();
+        let transcripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let llm = TranscriptProbeLlm {
+            transcripts: transcripts.clone(),
         };
         let state = test_state_with_llm(&base_dir, Box::new(llm));
         let chat_id = state
             .db
-            .resolve_or_create_chat_id("web", "empty-retry-chat", Some("empty"), "web")
+            .resolve_or_create_chat_id("web", "inbound-hook", Some("inbound"), "web")
             .unwrap();
-        store_user_message(&state.db, chat_id, "hello");
+        let context = AgentRequestContext {
+            caller_channel: "web",
+            chat_id,
+            chat_type: "web",
+        };
 
-        let reply = process_with_agent(
-            &state,
-            AgentRequestContext {
-                caller_channel: "web",
-                chat_id,
-                chat_type: "web",
-            },
-            None,
-            None,
+        store_user_message(&state.db, chat_id, "my key is sk-secret");
+        let reply = process_with_agent(&state, context, None, None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "ok");
+
+        store_user_message(&state.db, chat_id, "please drop me");
+        let reply = process_with_agent(&state, context, None, None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "dropped by policy");
+        assert_eq!(transcripts.lock().unwrap().len(), 1);
+
+        store_user_message(&state.db, chat_id, "hello again");
+        let reply = process_with_agent(&state, context, None, None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "ok");
+
+        let transcripts = transcripts.lock().unwrap().clone();
+        assert_eq!(transcripts.len(), 2);
+        assert!(transcripts[0].contains("[redacted]"));
+        assert!(transcripts[1].contains("hello again"));
+        for transcript in &transcripts {
+            assert!(!transcript.contains("sk-secret"));
+            assert!(!transcript.contains("drop me"));
+        }
+        // The stored history is rewritten too, not just what the model saw.
+        let stored = state.db.get_all_messages(chat_id).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].content, "[redacted]");
+        assert_eq!(stored[1].content, "hello again");
+
+        drop(state);
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_inbound_message_hook_runs_before_explicit_memory_commands() {
+        let base_dir = std::env::temp_dir().join(format!(
+            "mc_agent_inbound_memory_hook_{}",
+            uuid::Uuid::new_v4()
+        ));
+        let hook_dir = base_dir.join("hooks").join("inbound-dlp");
+        std::fs::create_dir_all(&hook_dir).unwrap();
+        std::fs::write(
+            hook_dir.join("HOOK.md"),
+            "---\nname: inbound-dlp\nevents: [OnInboundMessage]\ncommand: \"sh hook.sh\"\ntimeout_ms: 2000\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            hook_dir.join("hook.sh"),
+            r#"#!/bin/sh
+payload="$(cat)"
+if echo "$payload" | grep -q 'vault code'; then
+  echo '{"action":"block","reason":"dropped by policy"}'
+elif echo "$payload" | grep -q '4321'; then
+  echo '{"action":"modify","patch":{"content":"remember that my badge number is [redacted]"}}'
+else
+  echo '{"action":"allow"}'
+fi
+"#,
         )
-        .await
         .unwrap();
+        let transcripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let state = test_state_with_llm(
+            &base_dir,
+            Box::new(TranscriptProbeLlm {
+                transcripts: transcripts.clone(),
+            }),
+        );
+        let chat_id = state
+            .db
+            .resolve_or_create_chat_id("web", "inbound-memory", Some("inbound"), "web")
+            .unwrap();
+        let context = AgentRequestContext {
+            caller_channel: "web",
+            chat_id,
+            chat_type: "web",
+        };
 
-        assert_eq!(reply, "Visible retry answer.");
-        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        store_user_message(&state.db, chat_id, "remember that the vault code is 9981");
+        let reply = process_with_agent(&state, context, None, None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "dropped by policy");
+        assert!(state
+            .db
+            .get_all_memories_for_chat(Some(chat_id))
+            .unwrap()
+            .is_empty());
+        assert!(state.db.get_all_messages(chat_id).unwrap().is_empty());
+
+        store_user_message(&state.db, chat_id, "remember that my badge number is 4321");
+        let reply = process_with_agent(&state, context, None, None)
+            .await
+            .unwrap();
+        assert!(reply.starts_with("Noted."), "{reply}");
+        let memories = state.db.get_all_memories_for_chat(Some(chat_id)).unwrap();
+        assert_eq!(memories.len(), 1);
+        assert!(memories[0].content.contains("[redacted]"));
+        assert!(!memories[0].content.contains("4321"));
+        let stored = state.db.get_all_messages(chat_id).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(
+            stored[0].content,
+            "remember that my badge number is [redacted]"
+        );
+        assert!(transcripts.lock().unwrap().is_empty());
 
         drop(state);
         let _ = std::fs::remove_dir_all(&base_dir);
@@ -4,7 +4,7 @@ use std::sync::Arc;
 use axum::http::HeaderMap;
 use axum::{Json, Router};
 use serde::Deserialize;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::agent_engine::process_with_agent_with_events;
 use crate::agent_engine::{AgentEvent, AgentRequestContext};
@@ -15,7 +15,7 @@ use crate::channels::startup_guard::{
 use crate::chat_commands::{handle_chat_command, is_slash_command, unknown_command_response};
 use crate::runtime::AppState;
 use crate::setup_def::{ChannelFieldDef, DynamicChannelDef};
-use microclaw_channels::channel::ConversationKind;
+use microclaw_channels::channel::{send_and_store_channel_reply, ConversationKind};
 use microclaw_channels::channel_adapter::ChannelAdapter;
 use microclaw_storage::db::{call_blocking, StoredMessage};
 
@@ -503,19 +503,23 @@ async fn process_dingtalk_webhook_message(
                     );
                 }
             } else if !response.is_empty() {
-                if let Err(e) = adapter.send_text(&chat_id_external, &response).await {
-                    error!("DingTalk: failed to send response: {e}");
-                }
-                let bot_msg = StoredMessage {
-                    id: uuid::Uuid::new_v4().to_string(),
+                if let Err(e) = send_and_store_channel_reply(
+                    &app_state.channel_registry,
+                    app_state.db.clone(),
+                    &runtime_ctx.channel_name,
+                    &runtime_ctx.bot_username,
                     chat_id,
-                    sender_name: runtime_ctx.bot_username.clone(),
-                    content: response,
-                    is_from_bot: true,
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                };
-                let _ =
-                    call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg)).await;
+                    &response,
+                    |text| async move {
+                        if let Err(e) = adapter.send_text(&chat_id_external, &text).await {
+                            error!("DingTalk: failed to send response: {e}");
+                        }
+                    },
+                )
+                .await
+                {
+                    warn!("DingTalk: final response for chat {chat_id}: {e}");
+                }
             } else {
                 let _ = adapter
                     .send_text(
@@ -23,7 +23,7 @@ use crate::chat_commands::maybe_handle_plugin_command;
 use crate::chat_commands::{handle_chat_command, is_slash_command, unknown_command_response};
 use crate::runtime::AppState;
 use crate::tools::ToolAuthContext;
-use microclaw_channels::channel::ConversationKind;
+use microclaw_channels::channel::{send_and_store_channel_reply, ConversationKind};
 use microclaw_channels::channel_adapter::ChannelAdapter;
 use microclaw_core::text::{floor_char_boundary, split_text};
 use microclaw_storage::db::call_blocking;
@@ -596,21 +596,21 @@ impl EventHandler for Handler {
                         );
                     }
                 } else if !response.is_empty() {
-                    send_discord_response(&ctx, msg.channel_id, &response).await;
-
-                    // Store bot response
-                    let bot_msg = StoredMessage {
-                        id: uuid::Uuid::new_v4().to_string(),
-                        chat_id: channel_id,
-                        sender_name: self.runtime.bot_username.clone(),
-                        content: response,
-                        is_from_bot: true,
-                        timestamp: chrono::Utc::now().to_rfc3339(),
-                    };
-                    let _ = call_blocking(self.app_state.db.clone(), move |db| {
-                        db.store_message(&bot_msg)
-                    })
-                    .await;
+                    if let Err(e) = send_and_store_channel_reply(
+                        &self.app_state.channel_registry,
+                        self.app_state.db.clone(),
+                        &self.runtime.channel_name,
+                        &self.runtime.bot_username,
+                        channel_id,
+                        &response,
+                        |text| async move {
+                            send_discord_response(&ctx, msg.channel_id, &text).await;
+                        },
+                    )
+                    .await
+                    {
+                        warn!("Discord: final response for chat {channel_id}: {e}");
+                    }
                 } else {
                     let fallback = "I couldn't produce a visible reply after an automatic retry. Please try again.".to_string();
                     send_discord_response(&ctx, msg.channel_id, &fallback).await;
@@ -6,7 +6,7 @@ use std::sync::Arc;
 use axum::response::IntoResponse;
 use axum::{http::HeaderMap, Json, Router};
 use serde::Deserialize;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::agent_engine::process_with_agent_with_events;
 use crate::agent_engine::{AgentEvent, AgentRequestContext};
@@ -17,7 +17,7 @@ use crate::channels::startup_guard::{
 use crate::chat_commands::{handle_chat_command, is_slash_command, unknown_command_response};
 use crate::runtime::AppState;
 use crate::setup_def::{ChannelFieldDef, DynamicChannelDef};
-use microclaw_channels::channel::ConversationKind;
+use microclaw_channels::channel::{send_and_store_channel_reply, ConversationKind};
 use microclaw_channels::channel_adapter::ChannelAdapter;
 use microclaw_core::text::split_text;
 use microclaw_storage::db::{call_blocking, StoredMessage};
@@ -604,33 +604,37 @@ async fn process_email_webhook_message(
                     );
                 }
             } else if !response.is_empty() {
-                let mut email_body = String::new();
-                if !payload.subject.trim().is_empty() {
-                    email_body.push_str(&format!("Re: {}\n\n", payload.subject.trim()));
-                }
-                for chunk in split_text(&response, 8_000) {
-                    email_body.push_str(&chunk);
-                    email_body.push('\n');
-                }
-                if let Err(e) = send_email_via_sendmail(
-                    &runtime_ctx.sendmail_path,
-                    &runtime_ctx.from_address,
-                    &target,
-                    "MicroClaw reply",
-                    &email_body,
-                ) {
-                    error!("Email: failed to send response: {e}");
-                }
-                let bot_msg = StoredMessage {
-                    id: uuid::Uuid::new_v4().to_string(),
+                if let Err(e) = send_and_store_channel_reply(
+                    &app_state.channel_registry,
+                    app_state.db.clone(),
+                    &runtime_ctx.channel_name,
+                    &runtime_ctx.bot_username,
                     chat_id,
-                    sender_name: runtime_ctx.bot_username.clone(),
-                    content: response,
-                    is_from_bot: true,
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                };
-                let _ =
-                    call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg)).await;
+                    &response,
+                    |text| async move {
+                        let mut email_body = String::new();
+                        if !payload.subject.trim().is_empty() {
+                            email_body.push_str(&format!("Re: {}\n\n", payload.subject.trim()));
+                        }
+                        for chunk in split_text(&text, 8_000) {
+                            email_body.push_str(&chunk);
+                            email_body.push('\n');
+                        }
+                        if let Err(e) = send_email_via_sendmail(
+                            &runtime_ctx.sendmail_path,
+                            &runtime_ctx.from_address,
+                            &target,
+                            "MicroClaw reply",
+                            &email_body,
+                        ) {
+                            error!("Email: failed to send response: {e}");
+                        }
+                    },
+                )
+                .await
+                {
+                    warn!("Email: final response for chat {chat_id}: {e}");
+                }
             } else {
                 let fallback =
                     "I couldn't produce a visible reply after an automatic retry. Please try again.";
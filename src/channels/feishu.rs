@@ -18,7 +18,7 @@ use crate::chat_commands::maybe_handle_plugin_command;
 use crate::chat_commands::{handle_chat_command, is_slash_command, unknown_command_response};
 use crate::runtime::AppState;
 use crate::setup_def::{ChannelFieldDef, DynamicChannelDef};
-use microclaw_channels::channel::ConversationKind;
+use microclaw_channels::channel::{send_and_store_channel_reply, ConversationKind};
 use microclaw_channels::channel_adapter::ChannelAdapter;
 use microclaw_storage::db::call_blocking;
 use microclaw_storage::db::StoredMessage;
@@ -2985,32 +2985,33 @@ async fn handle_feishu_message(
                                 &thinking_text,
                                 &visible_response,
                             );
-                            if let Err(e) = send_feishu_response(
-                                &http_client,
-                                base_url,
-                                &token,
-                                external_chat_id,
+                            if let Err(e) = send_and_store_channel_reply(
+                                &app_state.channel_registry,
+                                app_state.db.clone(),
+                                &runtime.channel_name,
+                                &runtime.bot_username,
+                                chat_id,
                                 &outbound,
-                                message_id,
-                                topic_mode,
+                                |text| async move {
+                                    if let Err(e) = send_feishu_response(
+                                        &http_client,
+                                        base_url,
+                                        &token,
+                                        external_chat_id,
+                                        &text,
+                                        message_id,
+                                        topic_mode,
+                                    )
+                                    .await
+                                    {
+                                        error!("Feishu: failed to send response: {e}");
+                                    }
+                                },
                             )
                             .await
                             {
-                                error!("Feishu: failed to send response: {e}");
+                                warn!("Feishu: final response for chat {chat_id}: {e}");
                             }
-
-                            let bot_msg = StoredMessage {
-                                id: uuid::Uuid::new_v4().to_string(),
-                                chat_id,
-                                sender_name: runtime.bot_username.clone(),
-                                content: outbound,
-                                is_from_bot: true,
-                                timestamp: chrono::Utc::now().to_rfc3339(),
-                            };
-                            let _ = call_blocking(app_state.db.clone(), move |db| {
-                                db.store_message(&bot_msg)
-                            })
-                            .await;
                             return;
                         }
                         warn!(
@@ -3033,31 +3034,33 @@ async fn handle_feishu_message(
                     if outbound.is_empty() {
                         return;
                     }
-                    if let Err(e) = send_feishu_response(
-                        &http_client,
-                        base_url,
-                        &token,
-                        external_chat_id,
+                    if let Err(e) = send_and_store_channel_reply(
+                        &app_state.channel_registry,
+                        app_state.db.clone(),
+                        &runtime.channel_name,
+                        &runtime.bot_username,
+                        chat_id,
                         &outbound,
-                        message_id,
-                        topic_mode,
+                        |text| async move {
+                            if let Err(e) = send_feishu_response(
+                                &http_client,
+                                base_url,
+                                &token,
+                                external_chat_id,
+                                &text,
+                                message_id,
+                                topic_mode,
+                            )
+                            .await
+                            {
+                                error!("Feishu: failed to send response: {e}");
+                            }
+                        },
                     )
                     .await
                     {
-                        error!("Feishu: failed to send response: {e}");
+                        warn!("Feishu: final response for chat {chat_id}: {e}");
                     }
-
-                    let bot_msg = StoredMessage {
-                        id: uuid::Uuid::new_v4().to_string(),
-                        chat_id,
-                        sender_name: runtime.bot_username.clone(),
-                        content: outbound,
-                        is_from_bot: true,
-                        timestamp: chrono::Utc::now().to_rfc3339(),
-                    };
-                    let _ =
-                        call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg))
-                            .await;
                 } else {
                     info!(
                         "Feishu: agent returned empty response for chat {}, sending fallback",
@@ -3180,32 +3183,33 @@ async fn handle_feishu_message(
                                 &thinking_text,
                                 &visible_response,
                             );
-                            if let Err(e) = send_feishu_response(
-                                &http_client,
-                                base_url,
-                                &token,
-                                external_chat_id,
+                            if let Err(e) = send_and_store_channel_reply(
+                                &app_state.channel_registry,
+                                app_state.db.clone(),
+                                &runtime.channel_name,
+                                &runtime.bot_username,
+                                chat_id,
                                 &outbound,
-                                message_id,
-                                topic_mode,
+                                |text| async move {
+                                    if let Err(e) = send_feishu_response(
+                                        &http_client,
+                                        base_url,
+                                        &token,
+                                        external_chat_id,
+                                        &text,
+                                        message_id,
+                                        topic_mode,
+                                    )
+                                    .await
+                                    {
+                                        error!("Feishu: failed to send response: {e}");
+                                    }
+                                },
                             )
                             .await
                             {
-                                error!("Feishu: failed to send response: {e}");
+                                warn!("Feishu: final response for chat {chat_id}: {e}");
                             }
-
-                            let bot_msg = StoredMessage {
-                                id: uuid::Uuid::new_v4().to_string(),
-                                chat_id,
-                                sender_name: runtime.bot_username.clone(),
-                                content: outbound,
-                                is_from_bot: true,
-                                timestamp: chrono::Utc::now().to_rfc3339(),
-                            };
-                            let _ = call_blocking(app_state.db.clone(), move |db| {
-                                db.store_message(&bot_msg)
-                            })
-                            .await;
                             return;
                         }
                         warn!(
@@ -3228,31 +3232,33 @@ async fn handle_feishu_message(
                     if outbound.is_empty() {
                         return;
                     }
-                    if let Err(e) = send_feishu_response(
-                        &http_client,
-                        base_url,
-                        &token,
-                        external_chat_id,
+                    if let Err(e) = send_and_store_channel_reply(
+                        &app_state.channel_registry,
+                        app_state.db.clone(),
+                        &runtime.channel_name,
+                        &runtime.bot_username,
+                        chat_id,
                         &outbound,
-                        message_id,
-                        topic_mode,
+                        |text| async move {
+                            if let Err(e) = send_feishu_response(
+                                &http_client,
+                                base_url,
+                                &token,
+                                external_chat_id,
+                                &text,
+                                message_id,
+                                topic_mode,
+                            )
+                            .await
+                            {
+                                error!("Feishu: failed to send response: {e}");
+                            }
+                        },
                     )
                     .await
                     {
-                        error!("Feishu: failed to send response: {e}");
+                        warn!("Feishu: final response for chat {chat_id}: {e}");
                     }
-
-                    let bot_msg = StoredMessage {
-                        id: uuid::Uuid::new_v4().to_string(),
-                        chat_id,
-                        sender_name: runtime.bot_username.clone(),
-                        content: outbound,
-                        is_from_bot: true,
-                        timestamp: chrono::Utc::now().to_rfc3339(),
-                    };
-                    let _ =
-                        call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg))
-                            .await;
                 } else {
                     info!(
                         "Feishu: agent returned empty response for chat {}, sending fallback",
@@ -17,7 +17,7 @@ use crate::chat_commands::maybe_handle_plugin_command;
 use crate::chat_commands::{handle_chat_command, is_slash_command, unknown_command_response};
 use crate::runtime::AppState;
 use crate::setup_def::{ChannelFieldDef, DynamicChannelDef};
-use microclaw_channels::channel::ConversationKind;
+use microclaw_channels::channel::{send_and_store_channel_reply, ConversationKind};
 use microclaw_channels::channel_adapter::ChannelAdapter;
 use microclaw_core::text::floor_char_boundary;
 use microclaw_storage::db::call_blocking;
@@ -552,19 +552,23 @@ async fn handle_irc_message(
                     );
                 }
             } else if !response.is_empty() {
-                if let Err(e) = adapter.send_text(&response_target, &response).await {
-                    error!("IRC: failed to send response: {e}");
-                }
-                let bot_msg = StoredMessage {
-                    id: uuid::Uuid::new_v4().to_string(),
+                if let Err(e) = send_and_store_channel_reply(
+                    &app_state.channel_registry,
+                    app_state.db.clone(),
+                    "irc",
+                    &app_state.config.bot_username_for_channel("irc"),
                     chat_id,
-                    sender_name: app_state.config.bot_username_for_channel("irc"),
-                    content: response,
-                    is_from_bot: true,
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                };
-                let _ =
-                    call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg)).await;
+                    &response,
+                    |text| async move {
+                        if let Err(e) = adapter.send_text(&response_target, &text).await {
+                            error!("IRC: failed to send response: {e}");
+                        }
+                    },
+                )
+                .await
+                {
+                    warn!("IRC: final response for chat {chat_id}: {e}");
+                }
             } else {
                 let fallback =
                     "I couldn't produce a visible reply after an automatic retry. Please try again.";
@@ -31,7 +31,9 @@ use crate::channels::startup_guard::{
 use crate::chat_commands::{handle_chat_command, is_slash_command, unknown_command_response};
 use crate::runtime::AppState;
 use crate::setup_def::{ChannelFieldDef, DynamicChannelDef};
-use microclaw_channels::channel::ConversationKind;
+use microclaw_channels::channel::{
+    outbound_filter_active, send_and_store_channel_reply, ConversationKind,
+};
 use microclaw_channels::channel_adapter::ChannelAdapter;
 use microclaw_core::text::split_text;
 use microclaw_storage::db::call_blocking;
@@ -2177,8 +2179,10 @@ async fn handle_matrix_message(
     let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
 
     // Check if streaming is enabled for this room
+    // Streamed drafts would bypass outbound hooks, so a filter turns streaming off.
     let streaming_config = runtime.streaming.clone();
-    let use_streaming = streaming_config.enabled;
+    let use_streaming =
+        streaming_config.enabled && !outbound_filter_active(&app_state.channel_registry).await;
 
     match process_with_agent_with_events(
         &app_state,
@@ -2283,23 +2287,33 @@ async fn handle_matrix_message(
                     }
                 }
 
-                if let Err(e) =
-                    send_matrix_text_runtime(&runtime, &msg.room_id, &response, msg.prefer_sdk_send)
-                        .await
+                if let Err(e) = send_and_store_channel_reply(
+                    &app_state.channel_registry,
+                    app_state.db.clone(),
+                    &runtime.channel_name,
+                    &runtime.bot_username,
+                    chat_id,
+                    &response,
+                    |text| {
+                        let runtime = &runtime;
+                        async move {
+                            if let Err(e) = send_matrix_text_runtime(
+                                runtime,
+                                &msg.room_id,
+                                &text,
+                                msg.prefer_sdk_send,
+                            )
+                            .await
+                            {
+                                error!("Matrix: failed to send response: {e}");
+                            }
+                        }
+                    },
+                )
+                .await
                 {
-                    error!("Matrix: failed to send response: {e}");
+                    warn!("Matrix: final response for chat {chat_id}: {e}");
                 }
-
-                let bot_msg = StoredMessage {
-                    id: uuid::Uuid::new_v4().to_string(),
-                    chat_id,
-                    sender_name: runtime.bot_username.clone(),
-                    content: response,
-                    is_from_bot: true,
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                };
-                let _ =
-                    call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg)).await;
             } else {
                 let fallback =
                     "I couldn't produce a visible reply after an automatic retry. Please try again.";
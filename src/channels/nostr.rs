@@ -5,7 +5,7 @@ use std::sync::Arc;
 use axum::http::HeaderMap;
 use axum::{Json, Router};
 use serde::Deserialize;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::agent_engine::process_with_agent_with_events;
 use crate::agent_engine::{AgentEvent, AgentRequestContext};
@@ -16,7 +16,7 @@ use crate::channels::startup_guard::{
 use crate::chat_commands::{handle_chat_command, is_slash_command, unknown_command_response};
 use crate::runtime::AppState;
 use crate::setup_def::{ChannelFieldDef, DynamicChannelDef};
-use microclaw_channels::channel::ConversationKind;
+use microclaw_channels::channel::{send_and_store_channel_reply, ConversationKind};
 use microclaw_channels::channel_adapter::ChannelAdapter;
 use microclaw_storage::db::{call_blocking, StoredMessage};
 
@@ -485,19 +485,23 @@ async fn nostr_webhook_handler(
                     runtime_ctx.channel_name.clone(),
                     runtime_ctx.publish_command.clone(),
                 );
-                if let Err(e) = adapter.send_text(pubkey, &response).await {
-                    error!("Nostr: failed to publish response: {e}");
-                }
-                let bot_msg = StoredMessage {
-                    id: uuid::Uuid::new_v4().to_string(),
+                if let Err(e) = send_and_store_channel_reply(
+                    &app_state.channel_registry,
+                    app_state.db.clone(),
+                    &runtime_ctx.channel_name,
+                    &runtime_ctx.bot_username,
                     chat_id,
-                    sender_name: runtime_ctx.bot_username.clone(),
-                    content: response,
-                    is_from_bot: true,
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                };
-                let _ =
-                    call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg)).await;
+                    &response,
+                    |text| async move {
+                        if let Err(e) = adapter.send_text(pubkey, &text).await {
+                            error!("Nostr: failed to publish response: {e}");
+                        }
+                    },
+                )
+                .await
+                {
+                    warn!("Nostr: final response for chat {chat_id}: {e}");
+                }
             } else {
                 let adapter = NostrAdapter::new(
                     runtime_ctx.channel_name.clone(),
@@ -20,7 +20,7 @@ use crate::chat_commands::{handle_chat_command, is_slash_command, unknown_comman
 use crate::runtime::AppState;
 use crate::setup_def::{ChannelFieldDef, DynamicChannelDef};
 use crate::tools::ToolAuthContext;
-use microclaw_channels::channel::ConversationKind;
+use microclaw_channels::channel::{send_and_store_channel_reply, ConversationKind};
 use microclaw_channels::channel_adapter::ChannelAdapter;
 use microclaw_core::text::split_text;
 use microclaw_storage::db::call_blocking;
@@ -1218,22 +1218,26 @@ async fn handle_slack_message(
                     );
                 }
             } else if !response.is_empty() {
-                if let Err(e) =
-                    send_slack_response(bot_token, channel, normalized_thread_ts, &response).await
+                if let Err(e) = send_and_store_channel_reply(
+                    &app_state.channel_registry,
+                    app_state.db.clone(),
+                    &runtime.channel_name,
+                    &runtime.bot_username,
+                    chat_id,
+                    &response,
+                    |text| async move {
+                        if let Err(e) =
+                            send_slack_response(bot_token, channel, normalized_thread_ts, &text)
+                                .await
+                        {
+                            error!("Slack: failed to send response: {e}");
+                        }
+                    },
+                )
+                .await
                 {
-                    error!("Slack: failed to send response: {e}");
+                    warn!("Slack: final response for chat {chat_id}: {e}");
                 }
-
-                let bot_msg = StoredMessage {
-                    id: uuid::Uuid::new_v4().to_string(),
-                    chat_id,
-                    sender_name: runtime.bot_username.clone(),
-                    content: response,
-                    is_from_bot: true,
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                };
-                let _ =
-                    call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg)).await;
             } else {
                 let fallback = "I couldn't produce a visible reply after an automatic retry. Please try again.";
                 let _ =
@@ -19,7 +19,9 @@ use crate::chat_commands::maybe_handle_plugin_command;
 use crate::chat_commands::{handle_chat_command, is_slash_command, unknown_command_response};
 use crate::runtime::AppState;
 use crate::tools::ToolAuthContext;
-use microclaw_channels::channel::ConversationKind;
+use microclaw_channels::channel::{
+    outbound_filter_active, send_and_store_channel_reply, ConversationKind,
+};
 use microclaw_channels::channel_adapter::ChannelAdapter;
 #[cfg(test)]
 use microclaw_core::llm_types::{ContentBlock, ImageSource, MessageContent};
@@ -1082,9 +1084,11 @@ async fn handle_message(
         }
     });
 
-    // Check if streaming is enabled for this chat
+    // Check if streaming is enabled for this chat. Streamed drafts would reach
+    // the chat before outbound hooks see the reply, so a filter turns it off.
     let streaming_config = tg_ctx.streaming.clone();
-    let use_streaming = streaming_config.enabled;
+    let use_streaming =
+        streaming_config.enabled && !outbound_filter_active(&state.channel_registry).await;
 
     // Process through platform-agnostic agent engine.
     let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
@@ -1163,19 +1167,21 @@ async fn handle_message(
                         );
                     }
                 } else if !response.is_empty() {
-                    send_response(&bot, msg.chat.id, &response, msg.thread_id).await;
-
-                    // Store bot response
-                    let bot_msg = StoredMessage {
-                        id: uuid::Uuid::new_v4().to_string(),
+                    if let Err(e) = send_and_store_channel_reply(
+                        &state.channel_registry,
+                        state.db.clone(),
+                        &tg_channel_name,
+                        &tg_bot_username,
                         chat_id,
-                        sender_name: tg_bot_username.clone(),
-                        content: response,
-                        is_from_bot: true,
-                        timestamp: chrono::Utc::now().to_rfc3339(),
-                    };
-                    let _ =
-                        call_blocking(state.db.clone(), move |db| db.store_message(&bot_msg)).await;
+                        &response,
+                        |text| async move {
+                            send_response(&bot, msg.chat.id, &text, msg.thread_id).await;
+                        },
+                    )
+                    .await
+                    {
+                        warn!("Telegram: final response for chat {chat_id}: {e}");
+                    }
                 } else {
                     let fallback = "I couldn't produce a visible reply after an automatic retry. Please try again.".to_string();
                     send_response(&bot, msg.chat.id, &fallback, msg.thread_id).await;
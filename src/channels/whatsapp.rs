@@ -5,7 +5,7 @@ use axum::extract::Query;
 use axum::response::IntoResponse;
 use axum::{Json, Router};
 use serde::Deserialize;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::agent_engine::process_with_agent_with_events;
 use crate::agent_engine::{should_suppress_user_error, AgentEvent, AgentRequestContext};
@@ -16,7 +16,7 @@ use crate::channels::startup_guard::{
 use crate::chat_commands::{handle_chat_command, is_slash_command, unknown_command_response};
 use crate::runtime::AppState;
 use crate::setup_def::{ChannelFieldDef, DynamicChannelDef};
-use microclaw_channels::channel::ConversationKind;
+use microclaw_channels::channel::{send_and_store_channel_reply, ConversationKind};
 use microclaw_channels::channel_adapter::ChannelAdapter;
 use microclaw_core::text::split_text;
 use microclaw_storage::db::{call_blocking, StoredMessage};
@@ -691,29 +691,32 @@ async fn handle_whatsapp_message(
                     );
                 }
             } else if !response.is_empty() {
-                if let Err(e) = send_whatsapp_text(
-                    &reqwest::Client::new(),
-                    &runtime.access_token,
-                    &runtime.phone_number_id,
-                    &runtime.api_version,
-                    external_chat_id,
+                if let Err(e) = send_and_store_channel_reply(
+                    &app_state.channel_registry,
+                    app_state.db.clone(),
+                    &runtime.channel_name,
+                    &runtime.bot_username,
+                    chat_id,
                     &response,
+                    |text| async move {
+                        if let Err(e) = send_whatsapp_text(
+                            &reqwest::Client::new(),
+                            &runtime.access_token,
+                            &runtime.phone_number_id,
+                            &runtime.api_version,
+                            external_chat_id,
+                            &text,
+                        )
+                        .await
+                        {
+                            error!("WhatsApp: failed to send response: {e}");
+                        }
+                    },
                 )
                 .await
                 {
-                    error!("WhatsApp: failed to send response: {e}");
+                    warn!("WhatsApp: final response for chat {chat_id}: {e}");
                 }
-
-                let bot_msg = StoredMessage {
-                    id: uuid::Uuid::new_v4().to_string(),
-                    chat_id,
-                    sender_name: runtime.bot_username.clone(),
-                    content: response,
-                    is_from_bot: true,
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                };
-                let _ =
-                    call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg)).await;
             } else {
                 let fallback =
                     "I couldn't produce a visible reply after an automatic retry. Please try again.";
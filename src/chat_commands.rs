@@ -105,6 +105,25 @@ pub async fn handle_chat_command(
         return Some("No active run in this chat.".to_string());
     }
 
+    if trimmed == "/undo" || trimmed.starts_with("/undo ") {
+        let args = trimmed.strip_prefix("/undo").unwrap_or("").trim();
+        return Some(crate::checkpoints::handle_undo_command(state, chat_id, args).await);
+    }
+
+    if trimmed == "/review" || trimmed.starts_with("/review ") {
+        let args = trimmed.strip_prefix("/review").unwrap_or("").trim();
+        return Some(
+            crate::memory_service::handle_review_command(
+                state,
+                chat_id,
+                caller_channel,
+                sender_id,
+                args,
+            )
+            .await,
+        );
+    }
+
     if trimmed == "/skills" {
         return Some(state.skills.list_skills_formatted());
     }
@@ -129,7 +148,11 @@ pub async fn handle_chat_command(
     }
 
     if trimmed == "/usage" {
-        let text = match build_usage_report(state.db.clone(), chat_id).await {
+        let text = match build_usage_report(state.db.clone(), chat_id, &|row| {
+            state.config.estimate_usage_cost_usd(row)
+        })
+        .await
+        {
             Ok(v) => v,
             Err(e) => format!("Failed to query usage statistics: {e}"),
         };
@@ -764,6 +787,8 @@ mod tests {
                 default_model: Some("gpt-5.2".to_string()),
                 models: vec!["gpt-5.2".to_string(), "gpt-5".to_string()],
                 show_thinking: None,
+                thinking_budget_tokens: None,
+                fallback_providers: Vec::new(),
             },
         );
         cfg.llm_providers.insert(
@@ -779,6 +804,8 @@ mod tests {
                     "claude-opus-4-6-20260205".to_string(),
                 ],
                 show_thinking: None,
+                thinking_budget_tokens: None,
+                fallback_providers: Vec::new(),
             },
         );
         cfg
@@ -973,6 +1000,8 @@ mod tests {
                 default_model: Some("custom-model".to_string()),
                 models: vec!["custom-model".to_string()],
                 show_thinking: None,
+                thinking_budget_tokens: None,
+                fallback_providers: Vec::new(),
             },
         );
         let provider_overrides = Arc::new(RwLock::new(HashMap::new()));
@@ -1025,6 +1054,8 @@ mod tests {
                 default_model: Some("custom-model".to_string()),
                 models: vec!["custom-model".to_string()],
                 show_thinking: None,
+                thinking_budget_tokens: None,
+                fallback_providers: Vec::new(),
             },
         );
         let provider_overrides = Arc::new(RwLock::new(HashMap::new()));
@@ -1069,6 +1100,8 @@ mod tests {
             default_model: "x".to_string(),
             models: vec!["x".to_string()],
             show_thinking: false,
+            thinking_budget_tokens: None,
+            fallback_providers: Vec::new(),
         };
         assert_eq!(
             resolve_openai_models_url(&mk("synthetic")),
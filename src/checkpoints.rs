@@ -0,0 +1,274 @@
+//! Per-turn working-directory checkpoints and `/undo`.
+//!
+//! The agent loop calls [`checkpoint_turn`] before the first tool in a turn that can
+//! change files. Each checkpoint is recorded with the chat's message count at that
+//! point, which is how `/undo`, the sessions API and session forking find the file
+//! state that belongs to a given point in the conversation.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::{anyhow, Context, Result};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::config::{Config, SandboxMode, WorkingDirIsolation};
+use crate::runtime::AppState;
+use microclaw_storage::db::{call_blocking, CheckpointRecord, ForkCheckpoint};
+use microclaw_tools::checkpoint_store::{
+    CheckpointKind, CheckpointStore, RestoreSummary, Snapshot,
+};
+use microclaw_tools::runtime::working_dir_for_chat;
+
+/// Checkpoints shown by `/undo list`.
+const LIST_LIMIT: usize = 10;
+
+/// Held shared from taking a snapshot until its checkpoint row exists (or, for a
+/// fork, until it has been restored), and exclusively while collecting garbage. The
+/// store is shared by all chats, so without it one chat's garbage collection could
+/// delete the manifest and objects of another chat's snapshot that is not yet listed
+/// as live.
+static STORE_GC_LOCK: LazyLock<RwLock<()>> = LazyLock::new(|| RwLock::new(()));
+
+fn store_for(config: &Config) -> CheckpointStore {
+    // A sandboxed agent can write `.git/config`; never let host-side git read it.
+    let sandboxed = !matches!(config.sandbox.mode, SandboxMode::Off);
+    CheckpointStore::new(Path::new(&config.data_dir), config.checkpoints.clone())
+        .with_git_snapshots(!sandboxed)
+}
+
+fn snapshot_of(record: &CheckpointRecord) -> Result<Snapshot> {
+    let kind = CheckpointKind::parse(&record.kind)
+        .ok_or_else(|| anyhow!("unknown checkpoint kind '{}'", record.kind))?;
+    Ok(Snapshot {
+        kind,
+        reference: record.reference.clone(),
+    })
+}
+
+/// The working dir tools use for this chat.
+pub fn chat_working_dir(config: &Config, channel: &str, chat_id: i64) -> PathBuf {
+    working_dir_for_chat(
+        Path::new(&config.working_dir),
+        config.working_dir_isolation,
+        channel,
+        chat_id,
+    )
+}
+
+/// Whether file-changing turns are checkpointed. A shared working dir is skipped:
+/// `/undo` is refused there and forks already see the same files.
+pub fn turn_checkpoints_enabled(config: &Config) -> bool {
+    config.checkpoints.enabled && matches!(config.working_dir_isolation, WorkingDirIsolation::Chat)
+}
+
+/// Snapshot the chat's working dir and record it, then drop checkpoints beyond
+/// `checkpoints.max_per_chat`.
+pub async fn checkpoint_turn(
+    state: &AppState,
+    channel: &str,
+    chat_id: i64,
+) -> Result<CheckpointRecord> {
+    let dir = chat_working_dir(&state.config, channel, chat_id);
+    let working_dir = dir.to_string_lossy().to_string();
+    let (previous, message_count) = call_blocking(state.db.clone(), move |db| {
+        let previous = db.list_checkpoints(chat_id, 1)?.into_iter().next();
+        Ok((previous, db.count_messages(chat_id)?))
+    })
+    .await?;
+    // Only a snapshot of the same directory is useful as a stat cache.
+    let previous = previous
+        .filter(|p| p.working_dir == working_dir)
+        .and_then(|p| snapshot_of(&p).ok());
+
+    let snapshot_guard = STORE_GC_LOCK.read().await;
+    let store = store_for(&state.config);
+    let snapshot = tokio::task::spawn_blocking(move || {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create '{}'", dir.display()))?;
+        store.snapshot(&dir, previous.as_ref())
+    })
+    .await??;
+
+    let record = CheckpointRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        chat_id,
+        kind: snapshot.kind.as_str().to_string(),
+        reference: snapshot.reference,
+        working_dir,
+        message_count,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        restored_at: None,
+    };
+    let inserted = record.clone();
+    let keep = state.config.checkpoints.max_per_chat;
+    let pruned = call_blocking(state.db.clone(), move |db| {
+        db.insert_checkpoint(&inserted)?;
+        db.prune_checkpoints(chat_id, keep)
+    })
+    .await?;
+    drop(snapshot_guard);
+
+    if !pruned.is_empty() {
+        // Live references are read under the exclusive lock: every snapshot taken
+        // before it was granted is recorded by then, and none can start until GC ends.
+        let _gc_guard = STORE_GC_LOCK.write().await;
+        let live = call_blocking(state.db.clone(), |db| {
+            db.checkpoint_references(CheckpointKind::Store.as_str())
+        })
+        .await;
+        let store = store_for(&state.config);
+        let result = tokio::task::spawn_blocking(move || -> Result<()> {
+            let live = live?;
+            for old in &pruned {
+                store.discard(&snapshot_of(old)?, Path::new(&old.working_dir))?;
+            }
+            store.collect_garbage(&live.into_iter().collect::<HashSet<_>>())?;
+            Ok(())
+        })
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|r| r);
+        if let Err(e) = result {
+            warn!("Failed to clean up old checkpoints for chat {chat_id}: {e}");
+        }
+    }
+    Ok(record)
+}
+
+/// Put a checkpoint's files back into `target_dir` (its own working dir when `None`).
+pub async fn restore_checkpoint(
+    config: &Config,
+    record: &CheckpointRecord,
+    target_dir: Option<PathBuf>,
+) -> Result<RestoreSummary> {
+    let snapshot = snapshot_of(record)?;
+    let source = PathBuf::from(&record.working_dir);
+    let target = target_dir.unwrap_or_else(|| source.clone());
+    let store = store_for(config);
+    tokio::task::spawn_blocking(move || store.restore(&snapshot, &source, &target)).await?
+}
+
+/// Why `/undo` is refused with a shared working dir: every chat writes to the same
+/// directory, so restoring one chat's checkpoint would roll back the others too.
+pub const SHARED_WORKING_DIR_UNDO_ERROR: &str =
+    "undo is unavailable with working_dir_isolation: shared, because restoring would also revert other chats' changes";
+
+/// Restore a checkpoint of this chat and mark it restored, so the next plain `/undo`
+/// goes one step further back. `id` of `None` picks the newest unrestored checkpoint.
+pub async fn undo(
+    state: &AppState,
+    chat_id: i64,
+    id: Option<String>,
+) -> Result<Option<(CheckpointRecord, RestoreSummary)>> {
+    if matches!(
+        state.config.working_dir_isolation,
+        WorkingDirIsolation::Shared
+    ) {
+        return Err(anyhow!(SHARED_WORKING_DIR_UNDO_ERROR));
+    }
+    let record = call_blocking(state.db.clone(), move |db| match id {
+        Some(id) => db.get_checkpoint(chat_id, &id),
+        None => db.latest_unrestored_checkpoint(chat_id),
+    })
+    .await?;
+    let Some(record) = record else {
+        return Ok(None);
+    };
+    let summary = restore_checkpoint(&state.config, &record, None).await?;
+    let id = record.id.clone();
+    call_blocking(state.db.clone(), move |db| db.mark_checkpoint_restored(&id)).await?;
+    Ok(Some((record, summary)))
+}
+
+/// `/undo`, `/undo list` and `/undo <id>`.
+pub async fn handle_undo_command(state: &AppState, chat_id: i64, args: &str) -> String {
+    if !state.config.checkpoints.enabled {
+        return "Checkpoints are disabled (checkpoints.enabled: false).".to_string();
+    }
+    if args == "list" {
+        return match call_blocking(state.db.clone(), move |db| {
+            db.list_checkpoints(chat_id, LIST_LIMIT)
+        })
+        .await
+        {
+            Ok(records) if records.is_empty() => "No checkpoints for this chat.".to_string(),
+            Ok(records) => {
+                let mut lines = vec!["Checkpoints (newest first):".to_string()];
+                for r in records {
+                    let restored = if r.restored_at.is_some() {
+                        " (restored)"
+                    } else {
+                        ""
+                    };
+                    lines.push(format!(
+                        "- {} {} [{}] after {} messages{}",
+                        r.id, r.created_at, r.kind, r.message_count, restored
+                    ));
+                }
+                lines.join("\n")
+            }
+            Err(e) => format!("Failed to list checkpoints: {e}"),
+        };
+    }
+
+    let id = (!args.is_empty()).then(|| args.to_string());
+    match undo(state, chat_id, id).await {
+        Ok(Some((record, summary))) => format!(
+            "Restored working directory to checkpoint {} from {} ({} files written, {} removed). The conversation is unchanged.",
+            record.id, record.created_at, summary.written, summary.removed
+        ),
+        Ok(None) if args.is_empty() => "Nothing to undo.".to_string(),
+        Ok(None) => format!("Checkpoint '{args}' not found in this chat."),
+        Err(e) => format!("Failed to restore checkpoint: {e}"),
+    }
+}
+
+/// Give a forked chat the files its source had at `fork_point`: the matching
+/// checkpoint if one was taken after that point, otherwise the source's current
+/// files. Fails when that checkpoint was pruned, since no later state matches the
+/// fork point. Returns the checkpoint id used, if any.
+pub async fn restore_for_fork(
+    state: &AppState,
+    source_chat_id: i64,
+    fork_point: i64,
+    target_dir: PathBuf,
+) -> Result<Option<String>> {
+    // Held from the lookup until the files are restored, so another chat's garbage
+    // collection cannot delete the checkpoint's objects in between.
+    let _snapshot_guard = STORE_GC_LOCK.read().await;
+    let (found, source_channel) = call_blocking(state.db.clone(), move |db| {
+        Ok((
+            db.checkpoint_for_fork_point(source_chat_id, fork_point)?,
+            db.get_chat_channel(source_chat_id)?,
+        ))
+    })
+    .await?;
+    match found {
+        ForkCheckpoint::Exact(record) => {
+            restore_checkpoint(&state.config, &record, Some(target_dir)).await?;
+            return Ok(Some(record.id));
+        }
+        ForkCheckpoint::Pruned => {
+            return Err(anyhow!(
+                "the checkpoint for message {fork_point} was pruned (checkpoints.max_per_chat); files were not restored"
+            ));
+        }
+        ForkCheckpoint::Current => {}
+    }
+
+    let source_channel = source_channel.unwrap_or_else(|| "web".to_string());
+    let source = chat_working_dir(&state.config, &source_channel, source_chat_id);
+    let store = store_for(&state.config);
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        if !source.is_dir() {
+            return Ok(());
+        }
+        let snapshot = store.snapshot(&source, None)?;
+        store.restore(&snapshot, &source, &target_dir)?;
+        store.discard(&snapshot, &source)
+    })
+    .await??;
+    Ok(None)
+}
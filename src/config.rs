@@ -11,10 +11,16 @@ use crate::codex_auth::{
 };
 use crate::plugins::PluginsConfig;
 use microclaw_core::error::MicroClawError;
+use microclaw_storage::db::LlmModelUsageSummary;
+use microclaw_storage::vector_index::VectorBackend;
+use microclaw_tools::checkpoint_store::CheckpointConfig;
+use microclaw_tools::path_guard::PathPolicyConfig;
 pub use microclaw_tools::sandbox::{SandboxBackend, SandboxConfig, SandboxMode, SecurityProfile};
+use microclaw_tools::shell_session::ShellSessionConfig;
 pub use microclaw_tools::types::WorkingDirIsolation;
 use microclaw_tools::web_content_validation::WebContentValidationConfig;
 use microclaw_tools::web_fetch::WebFetchUrlValidationConfig;
+use microclaw_tools::web_search::WebSearchConfig;
 
 fn default_telegram_bot_token() -> String {
     String::new()
@@ -40,6 +46,9 @@ fn default_max_tokens() -> u32 {
 fn default_max_tool_iterations() -> usize {
     100
 }
+fn default_max_parallel_tool_calls() -> usize {
+    4
+}
 fn default_compaction_timeout_secs() -> u64 {
     180
 }
@@ -102,6 +111,9 @@ fn detect_system_timezone() -> String {
 fn default_max_session_messages() -> usize {
     40
 }
+fn default_max_tool_result_tokens() -> usize {
+    8000
+}
 fn default_compact_keep_recent() -> usize {
     20
 }
@@ -171,6 +183,18 @@ fn default_subagent_max_tokens_per_run() -> i64 {
 fn default_subagent_orchestrate_max_workers() -> usize {
     5
 }
+fn default_llm_failover_max_retries() -> u32 {
+    2
+}
+fn default_llm_failover_retry_base_delay_ms() -> u64 {
+    500
+}
+fn default_llm_failover_circuit_breaker_threshold() -> u32 {
+    3
+}
+fn default_llm_failover_circuit_breaker_cooldown_secs() -> u64 {
+    60
+}
 fn default_a2a_enabled() -> bool {
     false
 }
@@ -184,6 +208,9 @@ fn default_reflector_enabled() -> bool {
 fn default_reflector_interval_mins() -> u64 {
     15
 }
+fn default_memory_review_auto_approve_confidence() -> f64 {
+    0.9
+}
 fn default_soul_path() -> Option<String> {
     None
 }
@@ -231,6 +258,24 @@ pub struct ModelPrice {
     pub model: String,
     pub input_per_million_usd: f64,
     pub output_per_million_usd: f64,
+    /// Price for prompt-cache reads. Defaults to 10% of the input price.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_per_million_usd: Option<f64>,
+    /// Price for prompt-cache writes. Defaults to 125% of the input price.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_per_million_usd: Option<f64>,
+}
+
+impl ModelPrice {
+    pub fn cache_read_price(&self) -> f64 {
+        self.cache_read_per_million_usd
+            .unwrap_or(self.input_per_million_usd * 0.1)
+    }
+
+    pub fn cache_write_price(&self) -> f64 {
+        self.cache_write_per_million_usd
+            .unwrap_or(self.input_per_million_usd * 1.25)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
@@ -249,6 +294,13 @@ pub struct LlmProviderProfile {
     pub models: Vec<String>,
     #[serde(default)]
     pub show_thinking: Option<bool>,
+    /// Extended thinking budget for this profile. Overrides the global `thinking_budget_tokens`.
+    #[serde(default)]
+    pub thinking_budget_tokens: Option<u32>,
+    /// Ordered profile aliases to try when this profile is unhealthy or keeps
+    /// failing with retryable errors. Overrides the global `llm_fallback_providers`.
+    #[serde(default)]
+    pub fallback_providers: Vec<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -261,6 +313,60 @@ pub struct ResolvedLlmProviderProfile {
     pub default_model: String,
     pub models: Vec<String>,
     pub show_thinking: bool,
+    pub thinking_budget_tokens: Option<u32>,
+    pub fallback_providers: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmCassetteMode {
+    #[default]
+    Off,
+    /// Forward calls to the real provider and append each exchange to the cassette.
+    Record,
+    /// Serve recorded exchanges for matching requests without contacting any provider.
+    Replay,
+}
+
+/// Record/replay of LLM traffic. `MICROCLAW_LLM_CASSETTE=record:<path>` or
+/// `replay:<path>` overrides this section.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LlmCassetteConfig {
+    #[serde(default)]
+    pub mode: LlmCassetteMode,
+    /// JSON Lines cassette file, one exchange per line.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// In replay, warn about requests that differ from the recording and serve
+    /// the next entry instead of failing the call.
+    #[serde(default)]
+    pub lenient: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LlmFailoverConfig {
+    /// Retries against the same profile before moving down the fallback chain.
+    #[serde(default = "default_llm_failover_max_retries")]
+    pub max_retries: u32,
+    /// Base delay for exponential backoff between retries (`base * 2^attempt`).
+    #[serde(default = "default_llm_failover_retry_base_delay_ms")]
+    pub retry_base_delay_ms: u64,
+    /// Consecutive failed calls before a profile's circuit opens. `0` disables the breaker.
+    #[serde(default = "default_llm_failover_circuit_breaker_threshold")]
+    pub circuit_breaker_threshold: u32,
+    #[serde(default = "default_llm_failover_circuit_breaker_cooldown_secs")]
+    pub circuit_breaker_cooldown_secs: u64,
+}
+
+impl Default for LlmFailoverConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_llm_failover_max_retries(),
+            retry_base_delay_ms: default_llm_failover_retry_base_delay_ms(),
+            circuit_breaker_threshold: default_llm_failover_circuit_breaker_threshold(),
+            circuit_breaker_cooldown_secs: default_llm_failover_circuit_breaker_cooldown_secs(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -304,6 +410,30 @@ impl Default for SubagentConfig {
     }
 }
 
+/// Human review of reflector-extracted memories. When enabled, facts the reflector is
+/// less sure about wait in a queue (web UI or `/review`) instead of being written.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryReviewConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Facts at or above this confidence are written without review.
+    #[serde(default = "default_memory_review_auto_approve_confidence")]
+    pub auto_approve_confidence: f64,
+    /// Facts below this confidence are dropped without queueing.
+    #[serde(default)]
+    pub reject_below: f64,
+}
+
+impl Default for MemoryReviewConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            auto_approve_confidence: default_memory_review_auto_approve_confidence(),
+            reject_below: 0.0,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct A2APeerConfig {
     #[serde(default = "default_true")]
@@ -360,6 +490,14 @@ pub struct Config {
     pub provider_presets: HashMap<String, LlmProviderProfile>,
     #[serde(default)]
     pub llm_providers: HashMap<String, LlmProviderProfile>,
+    /// Profile aliases tried in order when the active profile fails with retryable
+    /// errors. Used for profiles that do not declare their own `fallback_providers`.
+    #[serde(default)]
+    pub llm_fallback_providers: Vec<String>,
+    #[serde(default)]
+    pub llm_failover: LlmFailoverConfig,
+    #[serde(default)]
+    pub llm_cassette: LlmCassetteConfig,
     #[serde(default)]
     pub llm_base_url: Option<String>,
     #[serde(default = "default_llm_user_agent")]
@@ -368,6 +506,10 @@ pub struct Config {
     pub max_tokens: u32,
     #[serde(default = "default_max_tool_iterations")]
     pub max_tool_iterations: usize,
+    /// Cap on low-risk tool calls from one assistant turn that run concurrently.
+    /// `0` or `1` runs every call serially.
+    #[serde(default = "default_max_parallel_tool_calls")]
+    pub max_parallel_tool_calls: usize,
     #[serde(default = "default_compaction_timeout_secs")]
     pub compaction_timeout_secs: u64,
     #[serde(default = "default_max_history_messages")]
@@ -380,6 +522,13 @@ pub struct Config {
     pub max_session_messages: usize,
     #[serde(default = "default_compact_keep_recent")]
     pub compact_keep_recent: usize,
+    /// Context window used for token budgeting. Unset uses the built-in table for
+    /// known models; unknown models fall back to `max_session_messages` counts.
+    #[serde(default)]
+    pub context_window_tokens: Option<usize>,
+    /// Tool results larger than this are truncated and spilled to a file. `0` disables.
+    #[serde(default = "default_max_tool_result_tokens")]
+    pub max_tool_result_tokens: usize,
     #[serde(default = "default_tool_timeout_secs")]
     pub default_tool_timeout_secs: u64,
     #[serde(default)]
@@ -388,6 +537,14 @@ pub struct Config {
     pub default_mcp_request_timeout_secs: u64,
     #[serde(default)]
     pub show_thinking: bool,
+    /// Token budget for Anthropic extended thinking. Unset disables thinking; values are
+    /// clamped to the API minimum (1024) and below `max_tokens`.
+    #[serde(default)]
+    pub thinking_budget_tokens: Option<u32>,
+    /// Emit Anthropic `cache_control` breakpoints on the system prompt, tools and
+    /// conversation prefix. Disable for Anthropic-compatible endpoints that reject them.
+    #[serde(default = "default_true")]
+    pub anthropic_prompt_cache: bool,
     #[serde(default)]
     pub subagents: SubagentConfig,
     #[serde(default)]
@@ -418,6 +575,12 @@ pub struct Config {
     pub high_risk_tool_user_confirmation_required: bool,
     #[serde(default)]
     pub sandbox: SandboxConfig,
+    #[serde(default)]
+    pub bash_sessions: ShellSessionConfig,
+    #[serde(default)]
+    pub checkpoints: CheckpointConfig,
+    #[serde(default)]
+    pub path_policy: PathPolicyConfig,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub override_timezone: Option<String>,
     #[serde(default = "default_timezone", skip_serializing)]
@@ -454,6 +617,8 @@ pub struct Config {
     pub web_fetch_validation: WebContentValidationConfig,
     #[serde(default)]
     pub web_fetch_url_validation: WebFetchUrlValidationConfig,
+    #[serde(default)]
+    pub web_search: WebSearchConfig,
 
     // --- Embedding ---
     #[serde(default)]
@@ -466,6 +631,9 @@ pub struct Config {
     pub embedding_model: Option<String>,
     #[serde(default)]
     pub embedding_dim: Option<usize>,
+    /// Vector index backend: "auto", "sqlite-vec" or "hnsw".
+    #[serde(default)]
+    pub vector_index: Option<String>,
     #[serde(default)]
     pub openai_api_key: Option<String>,
 
@@ -478,6 +646,8 @@ pub struct Config {
     pub reflector_enabled: bool,
     #[serde(default = "default_reflector_interval_mins")]
     pub reflector_interval_mins: u64,
+    #[serde(default)]
+    pub memory_review: MemoryReviewConfig,
 
     // --- Soul ---
     /// Path to a SOUL.md file that defines the bot's personality, voice, and values.
@@ -786,10 +956,14 @@ impl Config {
             model: "claude-sonnet-4-5-20250929".into(),
             provider_presets: HashMap::new(),
             llm_providers: HashMap::new(),
+            llm_fallback_providers: Vec::new(),
+            llm_failover: LlmFailoverConfig::default(),
+            llm_cassette: LlmCassetteConfig::default(),
             llm_base_url: None,
             llm_user_agent: default_llm_user_agent(),
             max_tokens: 8192,
             max_tool_iterations: 100,
+            max_parallel_tool_calls: default_max_parallel_tool_calls(),
             compaction_timeout_secs: 180,
             max_history_messages: 50,
             max_document_size_mb: 100,
@@ -800,6 +974,9 @@ impl Config {
             working_dir_isolation: WorkingDirIsolation::Chat,
             high_risk_tool_user_confirmation_required: true,
             sandbox: SandboxConfig::default(),
+            bash_sessions: ShellSessionConfig::default(),
+            checkpoints: CheckpointConfig::default(),
+            path_policy: PathPolicyConfig::default(),
             openai_api_key: None,
             override_timezone: None,
             timezone: "UTC".into(),
@@ -807,6 +984,8 @@ impl Config {
             control_chat_ids: vec![],
             max_session_messages: 40,
             compact_keep_recent: 20,
+            context_window_tokens: None,
+            max_tool_result_tokens: default_max_tool_result_tokens(),
             default_tool_timeout_secs: default_tool_timeout_secs(),
             tool_timeout_overrides: HashMap::new(),
             default_mcp_request_timeout_secs: default_mcp_request_timeout_secs(),
@@ -815,6 +994,8 @@ impl Config {
             discord_no_mention: false,
             allow_group_slash_without_mention: false,
             show_thinking: false,
+            thinking_budget_tokens: None,
+            anthropic_prompt_cache: true,
             subagents: SubagentConfig::default(),
             a2a: A2AConfig::default(),
             openai_compat_body_overrides: HashMap::new(),
@@ -830,14 +1011,17 @@ impl Config {
             web_session_idle_ttl_seconds: 300,
             web_fetch_validation: WebContentValidationConfig::default(),
             web_fetch_url_validation: WebFetchUrlValidationConfig::default(),
+            web_search: WebSearchConfig::default(),
             model_prices: vec![],
             embedding_provider: None,
             embedding_api_key: None,
             embedding_base_url: None,
             embedding_model: None,
             embedding_dim: None,
+            vector_index: None,
             reflector_enabled: true,
             reflector_interval_mins: 15,
+            memory_review: MemoryReviewConfig::default(),
             soul_path: None,
             souls_dir: None,
             clawhub: ClawHubConfig::default(),
@@ -864,6 +1048,19 @@ impl Config {
         root.join("runtime").to_string_lossy().to_string()
     }
 
+    /// Where oversized tool output is spilled, one subdirectory per chat.
+    pub fn tool_results_dir(&self) -> PathBuf {
+        PathBuf::from(self.runtime_data_dir()).join("tool_results")
+    }
+
+    /// Vector index backend for memory embeddings; unset means `auto`.
+    pub fn vector_backend(&self) -> VectorBackend {
+        self.vector_index
+            .as_deref()
+            .and_then(VectorBackend::parse)
+            .unwrap_or_default()
+    }
+
     /// Skills directory. Priority: MICROCLAW_SKILLS_DIR env var > skills_dir config > <data_dir>/skills
     pub fn skills_data_dir(&self) -> String {
         // 1. Check env var first
@@ -1013,6 +1210,7 @@ impl Config {
                 "anthropic" => "claude-sonnet-4-5-20250929".into(),
                 "ollama" => "llama3.2".into(),
                 "openai-codex" => "gpt-5.3-codex".into(),
+                "gemini" => "gemini-2.5-flash".into(),
                 _ => "gpt-5.2".into(),
             };
         }
@@ -1023,6 +1221,8 @@ impl Config {
         self.provider_presets =
             normalize_provider_profiles(std::mem::take(&mut self.provider_presets));
         self.llm_providers = normalize_provider_profiles(std::mem::take(&mut self.llm_providers));
+        self.llm_fallback_providers =
+            normalize_fallback_aliases(std::mem::take(&mut self.llm_fallback_providers));
         for (alias, preset) in self.provider_presets.clone() {
             self.llm_providers
                 .entry(alias)
@@ -1121,6 +1321,17 @@ impl Config {
         if self.sandbox.container_prefix.is_empty() {
             self.sandbox.container_prefix = default_sandbox_container_prefix();
         }
+        self.sandbox.egress.normalize();
+        self.sandbox
+            .egress
+            .validate()
+            .map_err(MicroClawError::Config)?;
+        self.bash_sessions.normalize();
+        self.checkpoints.normalize();
+        self.path_policy.normalize();
+        self.path_policy
+            .validate()
+            .map_err(MicroClawError::Config)?;
         if self.web_host.trim().is_empty() {
             self.web_host = default_web_host();
         }
@@ -1203,6 +1414,26 @@ impl Config {
                 self.embedding_dim = None;
             }
         }
+        if self.embedding_provider.as_deref() == Some("local") {
+            if let Some(model) = self
+                .embedding_model
+                .as_deref()
+                .filter(|m| *m != crate::embedding::HASHING_MODEL)
+            {
+                return Err(MicroClawError::Config(format!(
+                    "embedding_provider 'local' only supports embedding_model '{}' (got '{model}'); loading model files is not supported, use 'ollama' to run a sentence-embedding model locally",
+                    crate::embedding::HASHING_MODEL
+                )));
+            }
+        }
+        if let Some(v) = &self.vector_index {
+            let backend = VectorBackend::parse(v).ok_or_else(|| {
+                MicroClawError::Config(format!(
+                    "vector_index must be one of: auto, sqlite-vec, hnsw (got '{v}')"
+                ))
+            })?;
+            self.vector_index = Some(backend.as_str().to_string());
+        }
         if let Some(web_cfg) = self
             .channels
             .get_mut("web")
@@ -1235,6 +1466,8 @@ Use operator password + API keys for Web auth."
         }
         self.web_fetch_validation.normalize();
         self.web_fetch_url_validation.normalize();
+        self.web_search.normalize();
+        self.web_search.validate().map_err(MicroClawError::Config)?;
         if self.max_document_size_mb == 0 {
             self.max_document_size_mb = default_max_document_size_mb();
         }
@@ -1276,6 +1509,23 @@ Use operator password + API keys for Web auth."
         }
         self.subagents.orchestrate_max_workers =
             self.subagents.orchestrate_max_workers.clamp(1, 12);
+        let review = &self.memory_review;
+        for (name, value) in [
+            ("auto_approve_confidence", review.auto_approve_confidence),
+            ("reject_below", review.reject_below),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(MicroClawError::Config(format!(
+                    "memory_review.{name} must be between 0 and 1 (got {value})"
+                )));
+            }
+        }
+        if review.reject_below > review.auto_approve_confidence {
+            return Err(MicroClawError::Config(
+                "memory_review.reject_below must not exceed memory_review.auto_approve_confidence"
+                    .into(),
+            ));
+        }
         self.tool_timeout_overrides = self
             .tool_timeout_overrides
             .drain()
@@ -1344,6 +1594,35 @@ Use operator password + API keys for Web auth."
                     price.model
                 )));
             }
+            for (field, value) in [
+                (
+                    "cache_read_per_million_usd",
+                    price.cache_read_per_million_usd,
+                ),
+                (
+                    "cache_write_per_million_usd",
+                    price.cache_write_per_million_usd,
+                ),
+            ] {
+                if value.is_some_and(|v| !(v.is_finite() && v >= 0.0)) {
+                    return Err(MicroClawError::Config(format!(
+                        "model_prices[{}].{field} must be >= 0",
+                        price.model
+                    )));
+                }
+            }
+        }
+
+        self.llm_cassette.path = self
+            .llm_cassette
+            .path
+            .take()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty());
+        if self.llm_cassette.mode != LlmCassetteMode::Off && self.llm_cassette.path.is_none() {
+            return Err(MicroClawError::Config(
+                "llm_cassette.path is required when llm_cassette.mode is record or replay".into(),
+            ));
         }
 
         // Synthesize `channels` map from legacy flat fields if empty
@@ -1473,6 +1752,8 @@ Use operator password + API keys for Web auth."
             let mut default_model = self.model.clone();
             let mut models = vec![default_model.clone()];
             let mut show_thinking = self.show_thinking;
+            let mut thinking_budget_tokens = self.thinking_budget_tokens;
+            let mut fallback_providers = Vec::new();
             if let Some(profile) = self.llm_providers.get(&alias) {
                 if let Some(v) = &profile.provider {
                     provider = v.clone();
@@ -1495,6 +1776,10 @@ Use operator password + API keys for Web auth."
                 if let Some(v) = profile.show_thinking {
                     show_thinking = v;
                 }
+                if let Some(v) = profile.thinking_budget_tokens {
+                    thinking_budget_tokens = Some(v);
+                }
+                fallback_providers = profile.fallback_providers.clone();
             }
             if !models.iter().any(|m| m == &default_model) {
                 models.push(default_model.clone());
@@ -1510,6 +1795,8 @@ Use operator password + API keys for Web auth."
                 default_model,
                 models,
                 show_thinking,
+                thinking_budget_tokens,
+                fallback_providers,
             });
         }
 
@@ -1551,6 +1838,10 @@ Use operator password + API keys for Web auth."
             default_model,
             models,
             show_thinking,
+            thinking_budget_tokens: profile
+                .thinking_budget_tokens
+                .or(self.thinking_budget_tokens),
+            fallback_providers: profile.fallback_providers.clone(),
         })
     }
 
@@ -1577,6 +1868,31 @@ Use operator password + API keys for Web auth."
         out
     }
 
+    /// Resolve the ordered fallback profiles for `profile`: its own
+    /// `fallback_providers` if set, otherwise the global `llm_fallback_providers`.
+    /// The profile itself and unknown aliases are skipped.
+    pub fn llm_fallback_chain(
+        &self,
+        profile: &ResolvedLlmProviderProfile,
+    ) -> Vec<ResolvedLlmProviderProfile> {
+        let aliases = if profile.fallback_providers.is_empty() {
+            &self.llm_fallback_providers
+        } else {
+            &profile.fallback_providers
+        };
+        aliases
+            .iter()
+            .filter(|alias| **alias != profile.alias)
+            .filter_map(|alias| {
+                let resolved = self.resolve_llm_provider_profile(alias);
+                if resolved.is_none() {
+                    warn!("Ignoring unknown llm fallback provider '{alias}'");
+                }
+                resolved
+            })
+            .collect()
+    }
+
     /// Deserialize a typed channel config from the `channels` map.
     pub fn channel_config<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
         self.channels
@@ -1597,13 +1913,38 @@ Use operator password + API keys for Web auth."
         model: &str,
         input_tokens: i64,
         output_tokens: i64,
+    ) -> Option<f64> {
+        self.estimate_cost_usd_with_cache(model, input_tokens, output_tokens, 0, 0)
+    }
+
+    /// Like [`Config::estimate_cost_usd`], pricing prompt-cache reads and writes
+    /// separately from uncached input tokens.
+    pub fn estimate_cost_usd_with_cache(
+        &self,
+        model: &str,
+        input_tokens: i64,
+        output_tokens: i64,
+        cache_read_tokens: i64,
+        cache_write_tokens: i64,
     ) -> Option<f64> {
         let price = self.model_price(model)?;
-        let in_tok = input_tokens.max(0) as f64;
-        let out_tok = output_tokens.max(0) as f64;
+        let per_million = |tokens: i64, usd: f64| (tokens.max(0) as f64 / 1_000_000.0) * usd;
         Some(
-            (in_tok / 1_000_000.0) * price.input_per_million_usd
-                + (out_tok / 1_000_000.0) * price.output_per_million_usd,
+            per_million(input_tokens, price.input_per_million_usd)
+                + per_million(output_tokens, price.output_per_million_usd)
+                + per_million(cache_read_tokens, price.cache_read_price())
+                + per_million(cache_write_tokens, price.cache_write_price()),
+        )
+    }
+
+    /// Price one `/usage` model row, cache reads and writes included.
+    pub fn estimate_usage_cost_usd(&self, row: &LlmModelUsageSummary) -> Option<f64> {
+        self.estimate_cost_usd_with_cache(
+            &row.model,
+            row.input_tokens,
+            row.output_tokens,
+            row.cache_read_tokens,
+            row.cache_write_tokens,
         )
     }
 
@@ -1696,6 +2037,7 @@ fn normalize_provider_profiles(
                 .collect::<Vec<_>>();
             profile.models.sort();
             profile.models.dedup();
+            profile.fallback_providers = normalize_fallback_aliases(profile.fallback_providers);
             Some((alias, profile))
         })
         .collect()
@@ -1761,9 +2103,28 @@ fn merge_provider_profile(
         default_model: override_profile.default_model.or(base.default_model),
         models,
         show_thinking: override_profile.show_thinking.or(base.show_thinking),
+        thinking_budget_tokens: override_profile
+            .thinking_budget_tokens
+            .or(base.thinking_budget_tokens),
+        fallback_providers: if override_profile.fallback_providers.is_empty() {
+            base.fallback_providers
+        } else {
+            override_profile.fallback_providers
+        },
     }
 }
 
+fn normalize_fallback_aliases(aliases: Vec<String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for alias in aliases {
+        let alias = alias.trim().to_ascii_lowercase();
+        if !alias.is_empty() && !out.contains(&alias) {
+            out.push(alias);
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1867,6 +2228,7 @@ voice_transcription_command: "whisper-mlx --file {file}"
         assert_eq!(config.llm_provider, "anthropic");
         assert_eq!(config.max_tokens, 8192);
         assert_eq!(config.max_tool_iterations, 100);
+        assert_eq!(config.max_parallel_tool_calls, 4);
         assert!(config.data_dir.ends_with(".microclaw"));
         assert!(std::path::PathBuf::from(&config.working_dir)
             .ends_with(std::path::Path::new(".microclaw").join("working_dir")));
@@ -1891,6 +2253,7 @@ voice_transcription_command: "whisper-mlx --file {file}"
         );
         assert!(config.web_fetch_url_validation.allowlist_hosts.is_empty());
         assert!(config.web_fetch_url_validation.denylist_hosts.is_empty());
+        assert!(config.web_fetch_url_validation.block_private_networks);
     }
 
     #[test]
@@ -1977,6 +2340,95 @@ llm_providers:
         assert!(profile.show_thinking);
     }
 
+    #[test]
+    fn test_thinking_budget_profile_overrides_global() {
+        let yaml = r#"
+telegram_bot_token: tok
+bot_username: bot
+api_key: key
+llm_provider: anthropic
+thinking_budget_tokens: 2048
+llm_providers:
+  deep:
+    provider: anthropic
+    thinking_budget_tokens: 8000
+  plain:
+    provider: anthropic
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+
+        let default = config.resolve_llm_provider_profile("anthropic").unwrap();
+        assert_eq!(default.thinking_budget_tokens, Some(2048));
+        let deep = config.resolve_llm_provider_profile("deep").unwrap();
+        assert_eq!(deep.thinking_budget_tokens, Some(8000));
+        let plain = config.resolve_llm_provider_profile("plain").unwrap();
+        assert_eq!(plain.thinking_budget_tokens, Some(2048));
+    }
+
+    #[test]
+    fn test_llm_cassette_requires_path() {
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nllm_cassette:\n  mode: replay\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config.post_deserialize().unwrap_err();
+        assert!(err.to_string().contains("llm_cassette.path"));
+
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nllm_cassette:\n  mode: record\n  path: \" cassettes/run.jsonl \"\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(config.llm_cassette.mode, LlmCassetteMode::Record);
+        assert_eq!(
+            config.llm_cassette.path.as_deref(),
+            Some("cassettes/run.jsonl")
+        );
+    }
+
+    #[test]
+    fn test_llm_fallback_chain_prefers_profile_list_over_global() {
+        let yaml = r#"
+telegram_bot_token: tok
+bot_username: bot
+api_key: key
+llm_provider: anthropic
+llm_fallback_providers: [" Backup ", anthropic, missing, backup]
+llm_failover:
+  max_retries: 4
+llm_providers:
+  backup:
+    provider: openai
+    default_model: gpt-5.2
+  local:
+    provider: ollama
+    default_model: llama3
+    fallback_providers: [backup, local]
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(
+            config.llm_fallback_providers,
+            vec!["backup", "anthropic", "missing"]
+        );
+        assert_eq!(config.llm_failover.max_retries, 4);
+        assert_eq!(config.llm_cassette.mode, LlmCassetteMode::Off);
+        assert_eq!(config.llm_failover.circuit_breaker_threshold, 3);
+
+        let primary = config.resolve_llm_provider_profile("anthropic").unwrap();
+        let chain: Vec<String> = config
+            .llm_fallback_chain(&primary)
+            .into_iter()
+            .map(|p| p.alias)
+            .collect();
+        assert_eq!(chain, vec!["backup"]);
+
+        let local = config.resolve_llm_provider_profile("local").unwrap();
+        let chain: Vec<String> = config
+            .llm_fallback_chain(&local)
+            .into_iter()
+            .map(|p| p.alias)
+            .collect();
+        assert_eq!(chain, vec!["backup"]);
+    }
+
     #[test]
     fn test_llm_provider_overrides_support_provider_preset_and_legacy_llm_provider_keys() {
         let mut config = test_config();
@@ -2113,6 +2565,125 @@ channels:
         assert_eq!(config.sandbox.image, "ubuntu:25.10");
     }
 
+    #[test]
+    fn test_post_deserialize_sandbox_egress_profiles() {
+        let yaml = r#"telegram_bot_token: tok
+bot_username: bot
+api_key: key
+sandbox:
+  egress:
+    chats:
+      telegram-42: { mode: allowlist, allowed_domains: [" GitHub.com "] }
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        let profile = config.sandbox.egress_profile("telegram-42", &[]);
+        assert!(profile.allows_host("api.github.com"));
+        assert!(!profile.allows_host("example.com"));
+        assert!(!config
+            .sandbox
+            .egress_profile("telegram-7", &[])
+            .allows_host("github.com"));
+
+        let yaml = r#"telegram_bot_token: tok
+bot_username: bot
+api_key: key
+sandbox:
+  egress:
+    default: { mode: allowlist, allowed_domains: ["https://github.com/"] }
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config.post_deserialize().unwrap_err().to_string();
+        assert!(err.contains("sandbox.egress.default.allowed_domains"));
+    }
+
+    #[test]
+    fn test_post_deserialize_path_policy() {
+        let yaml = r#"telegram_bot_token: tok
+bot_username: bot
+api_key: key
+path_policy:
+  default:
+    deny_globs: ["*.pem"]
+    max_read_bytes: 1024
+  chats:
+    telegram-42: { read_only: ["/srv/docs/"] }
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(
+            config.path_policy.chats["telegram-42"].read_only,
+            vec!["/srv/docs".to_string()]
+        );
+        let policy = config.path_policy.resolve("telegram-42");
+        assert!(policy.check_read_size("/srv/a", 2048).is_err());
+        assert!(policy
+            .check(
+                "/srv/docs/a.md",
+                microclaw_tools::path_guard::PathAccess::Write
+            )
+            .is_err());
+
+        let yaml = r#"telegram_bot_token: tok
+bot_username: bot
+api_key: key
+path_policy:
+  default:
+    write_only: ["scratch"]
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config.post_deserialize().unwrap_err().to_string();
+        assert!(err.contains("path_policy.default.write_only"));
+    }
+
+    #[test]
+    fn test_post_deserialize_vector_index() {
+        let yaml =
+            "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nvector_index: ' HNSW '\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(config.vector_index.as_deref(), Some("hnsw"));
+        assert_eq!(config.vector_backend(), VectorBackend::Hnsw);
+
+        let yaml =
+            "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nvector_index: faiss\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config.post_deserialize().unwrap_err().to_string();
+        assert!(err.contains("vector_index"));
+    }
+
+    #[test]
+    fn test_post_deserialize_rejects_local_embedding_model_files() {
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nembedding_provider: local\nembedding_model: hashing-v1\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nembedding_provider: local\nembedding_model: all-MiniLM-L6-v2\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config.post_deserialize().unwrap_err().to_string();
+        assert!(err.contains("embedding_model"));
+        assert!(err.contains("all-MiniLM-L6-v2"));
+    }
+
+    #[test]
+    fn test_post_deserialize_memory_review_thresholds() {
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nmemory_review:\n  enabled: true\n  reject_below: 0.3\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert!(config.memory_review.enabled);
+        assert_eq!(config.memory_review.auto_approve_confidence, 0.9);
+        assert_eq!(config.memory_review.reject_below, 0.3);
+
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nmemory_review:\n  auto_approve_confidence: 0.5\n  reject_below: 0.6\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config.post_deserialize().unwrap_err().to_string();
+        assert!(err.contains("reject_below"));
+
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nmemory_review:\n  auto_approve_confidence: 1.5\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.post_deserialize().is_err());
+    }
+
     #[test]
     fn test_post_deserialize_empty_working_dir_uses_default() {
         let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nworking_dir: '  '\n";
@@ -2760,6 +3331,35 @@ model_prices:
         assert!((est - 0.033).abs() < 1e-9);
     }
 
+    #[test]
+    fn test_model_prices_price_cache_tokens_separately() {
+        let yaml = r#"
+telegram_bot_token: tok
+bot_username: bot
+api_key: key
+model_prices:
+  - model: claude-sonnet-4-5-20250929
+    input_per_million_usd: 3.0
+    output_per_million_usd: 15.0
+  - model: custom
+    input_per_million_usd: 1.0
+    output_per_million_usd: 2.0
+    cache_read_per_million_usd: 0.5
+    cache_write_per_million_usd: 0.0
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        // Defaults: reads at 10% and writes at 125% of the input price.
+        let est = config
+            .estimate_cost_usd_with_cache("claude-sonnet-4-5-20250929", 0, 0, 1_000_000, 1_000_000)
+            .unwrap();
+        assert!((est - (0.3 + 3.75)).abs() < 1e-9);
+        let est = config
+            .estimate_cost_usd_with_cache("custom", 1_000_000, 0, 2_000_000, 1_000_000)
+            .unwrap();
+        assert!((est - 2.0).abs() < 1e-9);
+    }
+
     #[test]
     fn test_model_prices_invalid_rejected() {
         let yaml = r#"
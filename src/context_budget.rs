@@ -0,0 +1,318 @@
+//! Token-based context budgeting.
+//!
+//! The agent loop sends the system prompt (with memories), tool schemas and the
+//! conversation history on every iteration. This module estimates how many tokens
+//! those parts take, knows the context window of common models, and decides when
+//! the history has to be compacted. It also caps single tool results, spilling the
+//! full output to disk so the model can page through it with `read_file`.
+
+use std::path::Path;
+
+use crate::config::Config;
+use crate::llm_types::{ContentBlock, Message, MessageContent, ToolDefinition};
+use crate::text::floor_char_boundary;
+
+/// Compaction starts once the projected request exceeds this share of the window.
+pub const COMPACTION_TRIGGER_RATIO: f64 = 0.8;
+/// Share of the history allowance kept verbatim after compaction.
+const KEEP_RECENT_RATIO: f64 = 0.5;
+/// Rough per-message framing overhead (role markers, separators).
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// Providers bill images by resolution; this is a typical mid-size image.
+const IMAGE_TOKENS: usize = 1600;
+
+/// Estimate tokens for a piece of text without a model-specific tokenizer.
+///
+/// ASCII averages about four characters per token; CJK and other non-ASCII
+/// characters are counted as roughly one token each.
+pub fn estimate_text_tokens(text: &str) -> usize {
+    let mut ascii = 0usize;
+    let mut other = 0usize;
+    for ch in text.chars() {
+        if ch.is_ascii() {
+            ascii += 1;
+        } else {
+            other += 1;
+        }
+    }
+    ascii.div_ceil(4) + other
+}
+
+pub fn estimate_message_tokens(message: &Message) -> usize {
+    let content = match &message.content {
+        MessageContent::Text(text) => estimate_text_tokens(text),
+        MessageContent::Blocks(blocks) => blocks
+            .iter()
+            .map(|block| match block {
+                ContentBlock::Text { text } => estimate_text_tokens(text),
+                ContentBlock::Image { .. } => IMAGE_TOKENS,
+                ContentBlock::ToolUse { name, input, .. } => {
+                    estimate_text_tokens(name) + estimate_text_tokens(&input.to_string())
+                }
+                ContentBlock::ToolResult { content, .. } => estimate_text_tokens(content),
+                ContentBlock::Thinking { thinking, .. } => estimate_text_tokens(thinking),
+                // Opaque payload; the provider counts the decrypted thinking instead.
+                ContentBlock::RedactedThinking { data } => estimate_text_tokens(data) / 2,
+            })
+            .sum(),
+    };
+    content + MESSAGE_OVERHEAD_TOKENS
+}
+
+pub fn estimate_messages_tokens(messages: &[Message]) -> usize {
+    messages.iter().map(estimate_message_tokens).sum()
+}
+
+pub fn estimate_tool_definitions_tokens(tools: &[ToolDefinition]) -> usize {
+    tools
+        .iter()
+        .map(|tool| {
+            estimate_text_tokens(&tool.name)
+                + estimate_text_tokens(&tool.description)
+                + estimate_text_tokens(&tool.input_schema.to_string())
+        })
+        .sum()
+}
+
+/// Context window of well-known model families, matched on the model id.
+/// Returns `None` for models we know nothing about.
+pub fn model_context_window(model: &str) -> Option<usize> {
+    let model = model.trim().to_ascii_lowercase();
+    // Strip router prefixes such as `anthropic/` or `openai/`.
+    let model = model.rsplit('/').next().unwrap_or(&model);
+    let window = if model.contains("claude") {
+        200_000
+    } else if model.starts_with("gemini") {
+        1_048_576
+    } else if model.starts_with("gpt-4.1") {
+        1_047_576
+    } else if model.starts_with("gpt-5") {
+        400_000
+    } else if model.starts_with("o3") || model.starts_with("o4") || model.starts_with("o1") {
+        200_000
+    } else if model.starts_with("gpt-4o") || model.starts_with("gpt-4-turbo") {
+        128_000
+    } else if model.starts_with("gpt-4") {
+        8_192
+    } else if model.starts_with("gpt-3.5") {
+        16_385
+    } else if model.starts_with("deepseek")
+        || model.starts_with("kimi")
+        || model.starts_with("moonshot")
+        || model.starts_with("glm")
+        || model.starts_with("llama")
+    {
+        128_000
+    } else if model.starts_with("qwen") || model.starts_with("grok") {
+        131_072
+    } else if model.starts_with("mistral") || model.starts_with("mixtral") {
+        32_000
+    } else {
+        return None;
+    };
+    Some(window)
+}
+
+/// Token budget for one request against a model's context window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextBudget {
+    pub window: usize,
+    /// Tokens held back for the model's reply (`max_tokens`).
+    pub reserved_output: usize,
+}
+
+impl ContextBudget {
+    /// Budget for `model`, preferring the configured `context_window_tokens` override.
+    /// `None` means the window is unknown and callers fall back to message counts.
+    pub fn resolve(config: &Config, model: &str) -> Option<Self> {
+        let window = config
+            .context_window_tokens
+            .filter(|w| *w > 0)
+            .or_else(|| model_context_window(model))?;
+        Some(ContextBudget {
+            window,
+            reserved_output: config.max_tokens as usize,
+        })
+    }
+
+    /// Tokens available to the conversation history once the system prompt, tool
+    /// schemas and reply reservation are accounted for.
+    pub fn history_allowance(&self, system_tokens: usize, tool_tokens: usize) -> usize {
+        let usable = (self.window as f64 * COMPACTION_TRIGGER_RATIO) as usize;
+        usable.saturating_sub(self.reserved_output + system_tokens + tool_tokens)
+    }
+
+    /// Number of trailing messages to keep verbatim when compacting: as many as fit
+    /// in half the history allowance, capped at `max_keep`, and never fewer than one.
+    pub fn recent_messages_to_keep(
+        &self,
+        messages: &[Message],
+        history_allowance: usize,
+        max_keep: usize,
+    ) -> usize {
+        let limit = (history_allowance as f64 * KEEP_RECENT_RATIO) as usize;
+        let mut used = 0usize;
+        let mut keep = 0usize;
+        for message in messages.iter().rev().take(max_keep) {
+            used += estimate_message_tokens(message);
+            if used > limit {
+                break;
+            }
+            keep += 1;
+        }
+        keep.max(1)
+    }
+}
+
+/// Cap a tool result at roughly `max_tokens`. The full output is written to
+/// `spill_path` and the returned text keeps the head and tail plus a pointer to it.
+/// Returns `None` when the content already fits.
+pub fn truncate_tool_result(content: &str, max_tokens: usize, spill_path: &Path) -> Option<String> {
+    let total_tokens = estimate_text_tokens(content);
+    if max_tokens == 0 || total_tokens <= max_tokens {
+        return None;
+    }
+    let saved = spill_path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(spill_path, content));
+    let handle = match saved {
+        Ok(()) => format!(
+            "Full output saved to {}; use read_file with offset/limit to view the rest.",
+            spill_path.display()
+        ),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to save truncated tool output to {}: {e}",
+                spill_path.display()
+            );
+            "The full output could not be saved.".to_string()
+        }
+    };
+
+    // Keep ~4 chars per token, three quarters from the head where errors and
+    // headers usually are, the rest from the tail.
+    let keep_bytes = max_tokens.saturating_mul(4);
+    let head_end = floor_char_boundary(content, keep_bytes * 3 / 4);
+    let mut tail_start = content.len().saturating_sub(keep_bytes / 4).max(head_end);
+    while !content.is_char_boundary(tail_start) {
+        tail_start += 1;
+    }
+    Some(format!(
+        "{}\n\n[... tool output truncated: ~{total_tokens} tokens total, showing ~{max_tokens}. {handle}]\n\n{}",
+        &content[..head_end],
+        &content[tail_start..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text_message(role: &str, text: &str) -> Message {
+        Message {
+            role: role.into(),
+            content: MessageContent::Text(text.into()),
+        }
+    }
+
+    #[test]
+    fn test_estimate_text_tokens_ascii_and_cjk() {
+        assert_eq!(estimate_text_tokens(""), 0);
+        assert_eq!(estimate_text_tokens("abcd"), 1);
+        assert_eq!(estimate_text_tokens("abcde"), 2);
+        assert_eq!(estimate_text_tokens("你好世界"), 4);
+    }
+
+    #[test]
+    fn test_estimate_message_tokens_counts_blocks() {
+        let message = Message {
+            role: "user".into(),
+            content: MessageContent::Blocks(vec![
+                ContentBlock::ToolResult {
+                    tool_use_id: "t1".into(),
+                    content: "x".repeat(400),
+                    is_error: None,
+                },
+                ContentBlock::ToolUse {
+                    id: "t2".into(),
+                    name: "bash".into(),
+                    input: json!({"command": "ls"}),
+                    thought_signature: None,
+                },
+            ]),
+        };
+        let tokens = estimate_message_tokens(&message);
+        assert!(tokens > 100 && tokens < 120, "{tokens}");
+    }
+
+    #[test]
+    fn test_model_context_window_lookup() {
+        assert_eq!(
+            model_context_window("claude-sonnet-4-5-20250929"),
+            Some(200_000)
+        );
+        assert_eq!(
+            model_context_window("anthropic/claude-3-haiku"),
+            Some(200_000)
+        );
+        assert_eq!(model_context_window("gpt-4o-mini"), Some(128_000));
+        assert_eq!(model_context_window("gpt-4.1"), Some(1_047_576));
+        assert_eq!(model_context_window("gpt-4"), Some(8_192));
+        assert_eq!(model_context_window("my-local-model"), None);
+    }
+
+    #[test]
+    fn test_budget_resolve_prefers_override() {
+        let mut config = Config::test_defaults();
+        config.model = "my-local-model".into();
+        assert!(ContextBudget::resolve(&config, "my-local-model").is_none());
+        config.context_window_tokens = Some(32_000);
+        let budget = ContextBudget::resolve(&config, "claude-sonnet-4-5").unwrap();
+        assert_eq!(budget.window, 32_000);
+        assert_eq!(budget.reserved_output, config.max_tokens as usize);
+    }
+
+    #[test]
+    fn test_history_allowance_and_keep_recent() {
+        let budget = ContextBudget {
+            window: 10_000,
+            reserved_output: 2_000,
+        };
+        assert_eq!(budget.history_allowance(1_000, 1_000), 4_000);
+        assert_eq!(budget.history_allowance(9_000, 0), 0);
+
+        // Each message is ~504 tokens; half of a 4000 allowance fits three.
+        let messages: Vec<Message> = (0..10)
+            .map(|i| {
+                text_message(
+                    if i % 2 == 0 { "user" } else { "assistant" },
+                    &"a".repeat(2000),
+                )
+            })
+            .collect();
+        assert_eq!(budget.recent_messages_to_keep(&messages, 4_000, 20), 3);
+        assert_eq!(budget.recent_messages_to_keep(&messages, 4_000, 2), 2);
+        assert_eq!(budget.recent_messages_to_keep(&messages, 0, 20), 1);
+    }
+
+    #[test]
+    fn test_truncate_tool_result_spills_full_output() {
+        let dir = std::env::temp_dir().join(format!("mc_ctx_budget_{}", uuid::Uuid::new_v4()));
+        let path = dir.join("tool_results").join("t1.txt");
+        let content = format!("HEAD{}TAIL", "x".repeat(10_000));
+
+        assert!(truncate_tool_result("short", 100, &path).is_none());
+        assert!(truncate_tool_result(&content, 0, &path).is_none());
+
+        let truncated = truncate_tool_result(&content, 100, &path).unwrap();
+        assert!(truncated.starts_with("HEAD"));
+        assert!(truncated.ends_with("TAIL"));
+        assert!(truncated.contains(&path.display().to_string()));
+        assert!(estimate_text_tokens(&truncated) < 200);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
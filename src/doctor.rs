@@ -6,7 +6,10 @@ use serde::Serialize;
 
 use crate::config::{Config, SandboxBackend, SandboxMode};
 use crate::mcp::McpConfig;
-use microclaw_tools::sandbox::{runtime_available_for_backend, selected_runtime_cli};
+use microclaw_tools::path_guard::{PathAccess, PathPolicyConfig};
+use microclaw_tools::sandbox::{
+    host_firewall_available, runtime_available_for_backend, selected_runtime_cli,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -103,7 +106,7 @@ impl DoctorReport {
 #[command(
     name = "microclaw doctor",
     about = "Preflight diagnostics",
-    long_about = "Checks PATH, shell/runtime dependencies, browser automation prerequisites, MCP command dependencies, and sandbox readiness."
+    long_about = "Checks PATH, shell/runtime dependencies, browser automation prerequisites, MCP command dependencies, sandbox readiness, and how the file tool path policy treats given paths."
 )]
 struct DoctorCli {
     #[command(subcommand)]
@@ -115,6 +118,13 @@ struct DoctorCli {
 #[derive(Debug, Subcommand)]
 enum DoctorCommand {
     Sandbox,
+    /// Explain whether the file tools may read and write the given paths.
+    Paths {
+        paths: Vec<String>,
+        /// Chat key (`<channel>-<chat_id>`) whose path_policy rules apply.
+        #[arg(long)]
+        chat: Option<String>,
+    },
 }
 
 pub fn run_cli(args: &[String]) -> anyhow::Result<()> {
@@ -134,7 +144,6 @@ pub fn run_cli(args: &[String]) -> anyhow::Result<()> {
         Err(err) => return Err(anyhow::anyhow!(err.to_string())),
     };
     let json_output = cli.json;
-    let sandbox_only = matches!(cli.command, Some(DoctorCommand::Sandbox));
 
     match migrate_channels_config() {
         Ok(Some((path, changed))) => {
@@ -154,10 +163,12 @@ pub fn run_cli(args: &[String]) -> anyhow::Result<()> {
         }
     }
 
-    let report = if sandbox_only {
-        build_sandbox_report()
-    } else {
-        build_report()
+    let report = match &cli.command {
+        Some(DoctorCommand::Sandbox) => build_sandbox_report(),
+        Some(DoctorCommand::Paths { paths, chat }) => {
+            build_paths_report(paths, chat.as_deref().unwrap_or(""))
+        }
+        None => build_report(),
     };
 
     if json_output {
@@ -420,11 +431,114 @@ fn build_sandbox_report() -> DoctorReport {
     check_web_fetch_validation(&mut report);
     check_sandbox_config(&mut report);
     check_docker_runtime(&mut report);
+    check_egress_firewall(&mut report);
     check_sandbox_image(&mut report);
     check_mount_allowlist(&mut report);
     report
 }
 
+fn build_paths_report(paths: &[String], chat: &str) -> DoctorReport {
+    let mut report = DoctorReport::new();
+    check_config(&mut report);
+    let policy_cfg = match Config::load() {
+        Ok(cfg) => cfg.path_policy,
+        Err(err) => {
+            report.push(
+                "paths.config",
+                "Path policy config",
+                CheckStatus::Warn,
+                format!("config unavailable, checking built-in rules only: {err}"),
+                None,
+            );
+            PathPolicyConfig::default()
+        }
+    };
+    check_path_policy(&mut report, &policy_cfg, paths, chat);
+    report
+}
+
+fn check_path_policy(
+    report: &mut DoctorReport,
+    policy_cfg: &PathPolicyConfig,
+    paths: &[String],
+    chat: &str,
+) {
+    let chat_rules = policy_cfg.chats.get(chat);
+    let rules = std::iter::once(&policy_cfg.default).chain(chat_rules);
+    let (mut deny, mut read_only, mut write_only) = (0, 0, 0);
+    for r in rules {
+        deny += r.deny_globs.len();
+        read_only += r.read_only.len();
+        write_only += r.write_only.len();
+    }
+    let policy = policy_cfg.resolve(chat);
+    report.push(
+        "paths.policy",
+        "Path policy",
+        CheckStatus::Pass,
+        format!(
+            "chat={} chat_rules={} deny_globs={} read_only={} write_only={} chat_overrides={}",
+            if chat.is_empty() { "-" } else { chat },
+            chat_rules.is_some(),
+            deny,
+            read_only,
+            write_only,
+            policy_cfg.chats.len(),
+        ),
+        None,
+    );
+    if paths.is_empty() {
+        report.push(
+            "paths.check",
+            "Path check",
+            CheckStatus::Miss,
+            "no paths given".to_string(),
+            Some(
+                "Run `microclaw doctor paths <path>... [--chat <channel>-<chat_id>]`.".to_string(),
+            ),
+        );
+        return;
+    }
+    for (i, path) in paths.iter().enumerate() {
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| PathBuf::from(path));
+        let absolute = absolute.to_string_lossy();
+        let verdicts: Vec<_> = [PathAccess::Read, PathAccess::Write]
+            .into_iter()
+            .map(|access| (access, policy.check(&absolute, access)))
+            .collect();
+        let denied = verdicts.iter().any(|(_, v)| v.is_err());
+        let detail = verdicts
+            .iter()
+            .map(|(access, verdict)| match verdict {
+                Ok(()) => format!("{}=allowed", access.as_str()),
+                Err(d) => match &d.rule {
+                    Some(rule) => {
+                        format!("{}=denied ({}: {rule})", access.as_str(), d.reason.as_str())
+                    }
+                    None => format!(
+                        "{}=denied ({}): {}",
+                        access.as_str(),
+                        d.reason.as_str(),
+                        d.message
+                    ),
+                },
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        report.push(
+            format!("paths.check.{i}"),
+            absolute.to_string(),
+            if denied {
+                CheckStatus::Warn
+            } else {
+                CheckStatus::Pass
+            },
+            detail,
+            None,
+        );
+    }
+}
+
 fn check_config(report: &mut DoctorReport) {
     match Config::resolve_config_path() {
         Ok(Some(path)) => report.push(
@@ -521,6 +635,19 @@ fn check_web_fetch_validation(report: &mut DoctorReport) {
         url_fix,
     );
 
+    if url_cfg.enabled && !url_cfg.block_private_networks {
+        report.push(
+            "web_fetch.url_policy.private_networks",
+            "Web fetch private network guard",
+            CheckStatus::Warn,
+            "block_private_networks=false".to_string(),
+            Some(
+                "Set web_fetch_url_validation.block_private_networks: true and list internal targets in allowed_private_networks."
+                    .to_string(),
+            ),
+        );
+    }
+
     if url_cfg.enabled {
         let bad_schemes: Vec<String> = url_cfg
             .allowed_schemes
@@ -985,10 +1112,12 @@ fn check_sandbox_config(report: &mut DoctorReport) {
             CheckStatus::Warn
         },
         format!(
-            "mode={} backend={:?} no_network={} require_runtime={} security_profile={} cap_add=[{}]",
+            "mode={} backend={:?} no_network={} egress_default={} egress_overrides={} require_runtime={} security_profile={} cap_add=[{}]",
             mode_label,
             config.sandbox.backend,
             config.sandbox.no_network,
+            config.sandbox.egress_profile("", &[]).mode.as_str(),
+            config.sandbox.egress.chats.len() + config.sandbox.egress.skills.len(),
             config.sandbox.require_runtime,
             config.sandbox.security_profile,
             config.sandbox.cap_add.join(","),
@@ -1009,6 +1138,10 @@ fn check_docker_runtime(report: &mut DoctorReport) {
         SandboxBackend::Auto => &["docker"],
         SandboxBackend::Docker => &["docker"],
         SandboxBackend::Podman => &["podman"],
+        SandboxBackend::Native => {
+            check_native_sandbox(report);
+            return;
+        }
     };
     let found_clis = expected_clis
         .iter()
@@ -1084,6 +1217,60 @@ fn check_docker_runtime(report: &mut DoctorReport) {
     }
 }
 
+fn check_egress_firewall(report: &mut DoctorReport) {
+    let Ok(config) = Config::load() else {
+        return;
+    };
+    if config.sandbox.backend == SandboxBackend::Native || !config.sandbox.egress.uses_allowlist() {
+        return;
+    }
+    match host_firewall_available() {
+        Ok(()) => report.push(
+            "sandbox.egress_firewall",
+            "Egress host firewall",
+            CheckStatus::Pass,
+            "iptables is usable; allowlisted containers only reach the proxy on the host"
+                .to_string(),
+            None,
+        ),
+        Err(err) => report.push(
+            "sandbox.egress_firewall",
+            "Egress host firewall",
+            CheckStatus::Warn,
+            format!(
+                "cannot manage iptables ({err}); allowlist egress runs degraded and containers can reach host services besides the proxy"
+            ),
+            Some(
+                "Run MicroClaw as root or with CAP_NET_ADMIN. Rootless Docker/Podman cannot install the host firewall."
+                    .to_string(),
+            ),
+        ),
+    }
+}
+
+fn check_native_sandbox(report: &mut DoctorReport) {
+    if runtime_available_for_backend(SandboxBackend::Native) {
+        report.push(
+            "sandbox.native",
+            "Native sandbox",
+            CheckStatus::Pass,
+            "user/mount/pid/net namespaces and seccomp are usable".to_string(),
+            None,
+        );
+    } else {
+        report.push(
+            "sandbox.native",
+            "Native sandbox",
+            CheckStatus::Fail,
+            "unprivileged user namespaces are unavailable or restricted".to_string(),
+            Some(
+                "Check `sysctl user.max_user_namespaces` (and `kernel.apparmor_restrict_unprivileged_userns` on Ubuntu), or use backend docker/podman."
+                    .to_string(),
+            ),
+        );
+    }
+}
+
 fn check_sandbox_image(report: &mut DoctorReport) {
     let config = match Config::load() {
         Ok(cfg) => cfg,
@@ -1101,6 +1288,10 @@ fn check_sandbox_image(report: &mut DoctorReport) {
         return;
     }
     let backend = config.sandbox.backend;
+    if backend == SandboxBackend::Native {
+        // The native backend runs on the host's own filesystem.
+        return;
+    }
     let Some(cli) = selected_runtime_cli(backend) else {
         report.push(
             "sandbox.image",
@@ -1449,6 +1640,41 @@ mod tests {
         assert!(report.checks.iter().any(|c| c.id == "sandbox.mode"));
     }
 
+    #[test]
+    fn test_check_path_policy_explains_denials() {
+        let policy: PathPolicyConfig = serde_yaml::from_str(
+            "default: { deny_globs: ['*.pem'] }\nchats:\n  telegram-42: { read_only: ['/srv/docs'] }\n",
+        )
+        .unwrap();
+        let paths = vec![
+            "/srv/app/server.pem".to_string(),
+            "/srv/docs/a.md".to_string(),
+            "/srv/app/main.rs".to_string(),
+        ];
+        let mut report = DoctorReport::new();
+        check_path_policy(&mut report, &policy, &paths, "telegram-42");
+
+        let policy_check = report
+            .checks
+            .iter()
+            .find(|c| c.id == "paths.policy")
+            .unwrap();
+        assert!(policy_check.detail.contains("chat_rules=true"));
+        let checks: Vec<_> = report
+            .checks
+            .iter()
+            .filter(|c| c.id.starts_with("paths.check."))
+            .collect();
+        assert_eq!(checks.len(), 3);
+        assert_eq!(checks[0].status, CheckStatus::Warn);
+        assert!(checks[0].detail.contains("read=denied (deny_glob: *.pem)"));
+        assert!(checks[1].detail.contains("read=allowed"));
+        assert!(checks[1]
+            .detail
+            .contains("write=denied (read_only: /srv/docs)"));
+        assert_eq!(checks[2].status, CheckStatus::Pass);
+    }
+
     #[test]
     fn test_build_report_has_web_fetch_validation_checks() {
         let _guard = env_lock();
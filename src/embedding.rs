@@ -3,6 +3,7 @@ use std::sync::Arc;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 use crate::config::Config;
 
@@ -28,6 +29,15 @@ pub struct OllamaEmbeddingProvider {
     dim: usize,
 }
 
+/// In-process embedder using the hashing trick: word unigrams, word bigrams
+/// and character trigrams are hashed into signed buckets, then L2-normalized.
+/// Deterministic across runs and platforms, and needs no model or network.
+pub struct HashingEmbeddingProvider {
+    dim: usize,
+}
+
+pub const HASHING_MODEL: &str = "hashing-v1";
+
 #[derive(Debug, Serialize)]
 struct OpenAIEmbeddingRequest<'a> {
     model: &'a str,
@@ -55,7 +65,6 @@ struct OllamaEmbeddingResponse {
     embedding: Vec<f32>,
 }
 
-#[cfg(feature = "sqlite-vec")]
 fn infer_default_dim(provider: &str, model: &str) -> usize {
     match provider {
         "openai" => {
@@ -66,6 +75,7 @@ fn infer_default_dim(provider: &str, model: &str) -> usize {
             }
         }
         "ollama" => 1024,
+        "local" => 384,
         _ => 1536,
     }
 }
@@ -141,69 +151,142 @@ impl EmbeddingProvider for OllamaEmbeddingProvider {
     }
 }
 
-pub fn create_provider(config: &Config) -> Option<Arc<dyn EmbeddingProvider>> {
-    #[cfg(not(feature = "sqlite-vec"))]
-    {
-        let _ = config;
-        None
-    }
-
-    #[cfg(feature = "sqlite-vec")]
-    {
-        let provider = config
-            .embedding_provider
-            .as_deref()
-            .unwrap_or("")
-            .trim()
-            .to_lowercase();
-        if provider.is_empty() {
-            return None;
-        }
+const UNIGRAM_WEIGHT: f32 = 1.0;
+const BIGRAM_WEIGHT: f32 = 0.5;
+const TRIGRAM_WEIGHT: f32 = 0.25;
 
-        let model = config
-            .embedding_model
-            .clone()
-            .unwrap_or_else(|| match provider.as_str() {
-                "openai" => "text-embedding-3-small".to_string(),
-                "ollama" => "nomic-embed-text".to_string(),
-                _ => "text-embedding-3-small".to_string(),
-            });
-        let dim = config
-            .embedding_dim
-            .unwrap_or_else(|| infer_default_dim(&provider, &model));
-        let client = reqwest::Client::new();
-
-        match provider.as_str() {
-            "openai" => {
-                let api_key = config.embedding_api_key.clone().unwrap_or_default();
-                if api_key.trim().is_empty() {
-                    return None;
-                }
-                let base_url = config
-                    .embedding_base_url
-                    .clone()
-                    .unwrap_or_else(|| "https://api.openai.com/v1".to_string());
-                Some(Arc::new(OpenAIEmbeddingProvider {
-                    client,
-                    base_url,
-                    api_key,
-                    model,
-                    dim,
-                }))
+impl HashingEmbeddingProvider {
+    pub fn new(dim: usize) -> Self {
+        Self { dim: dim.max(1) }
+    }
+
+    fn add_feature(&self, out: &mut [f32], feature: &str, weight: f32) {
+        let hash = fnv1a(feature.as_bytes());
+        let bucket = (hash % self.dim as u64) as usize;
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        out[bucket] += sign * weight;
+    }
+
+    fn embed_sync(&self, text: &str) -> Vec<f32> {
+        let mut out = vec![0.0f32; self.dim];
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+            .collect();
+        for word in &words {
+            self.add_feature(&mut out, &format!("w:{word}"), UNIGRAM_WEIGHT);
+            let chars: Vec<char> = format!("#{word}#").chars().collect();
+            for tri in chars.windows(3) {
+                let tri: String = tri.iter().collect();
+                self.add_feature(&mut out, &format!("c:{tri}"), TRIGRAM_WEIGHT);
             }
-            "ollama" => {
-                let base_url = config
-                    .embedding_base_url
-                    .clone()
-                    .unwrap_or_else(|| "http://127.0.0.1:11434".to_string());
-                Some(Arc::new(OllamaEmbeddingProvider {
-                    client,
-                    base_url,
-                    model,
-                    dim,
-                }))
+        }
+        for pair in words.windows(2) {
+            self.add_feature(
+                &mut out,
+                &format!("b:{} {}", pair[0], pair[1]),
+                BIGRAM_WEIGHT,
+            );
+        }
+        let norm = out.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            for v in &mut out {
+                *v /= norm;
             }
-            _ => None,
+        }
+        out
+    }
+}
+
+/// 64-bit FNV-1a; unlike `DefaultHasher` it is stable across Rust releases,
+/// which keeps stored vectors comparable after an upgrade.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+#[async_trait]
+impl EmbeddingProvider for HashingEmbeddingProvider {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        Ok(self.embed_sync(text))
+    }
+
+    fn model(&self) -> &str {
+        HASHING_MODEL
+    }
+
+    fn dimension(&self) -> usize {
+        self.dim
+    }
+}
+
+pub fn create_provider(config: &Config) -> Option<Arc<dyn EmbeddingProvider>> {
+    let provider = config
+        .embedding_provider
+        .as_deref()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+    if provider.is_empty() {
+        return None;
+    }
+
+    let model = config
+        .embedding_model
+        .clone()
+        .unwrap_or_else(|| match provider.as_str() {
+            "openai" => "text-embedding-3-small".to_string(),
+            "ollama" => "nomic-embed-text".to_string(),
+            "local" => HASHING_MODEL.to_string(),
+            _ => "text-embedding-3-small".to_string(),
+        });
+    let dim = config
+        .embedding_dim
+        .unwrap_or_else(|| infer_default_dim(&provider, &model));
+    let client = reqwest::Client::new();
+
+    match provider.as_str() {
+        "openai" => {
+            let api_key = config.embedding_api_key.clone().unwrap_or_default();
+            if api_key.trim().is_empty() {
+                warn!("embedding_provider 'openai' has no embedding_api_key; semantic memory is disabled");
+                return None;
+            }
+            let base_url = config
+                .embedding_base_url
+                .clone()
+                .unwrap_or_else(|| "https://api.openai.com/v1".to_string());
+            Some(Arc::new(OpenAIEmbeddingProvider {
+                client,
+                base_url,
+                api_key,
+                model,
+                dim,
+            }))
+        }
+        "ollama" => {
+            let base_url = config
+                .embedding_base_url
+                .clone()
+                .unwrap_or_else(|| "http://127.0.0.1:11434".to_string());
+            Some(Arc::new(OllamaEmbeddingProvider {
+                client,
+                base_url,
+                model,
+                dim,
+            }))
+        }
+        "local" if model == HASHING_MODEL => Some(Arc::new(HashingEmbeddingProvider::new(dim))),
+        _ => {
+            warn!(
+                "embedding_provider '{provider}' with embedding_model '{model}' is not supported; semantic memory is disabled"
+            );
+            None
         }
     }
 }
@@ -225,7 +308,6 @@ mod tests {
         assert!(create_provider(&cfg).is_none());
     }
 
-    #[cfg(feature = "sqlite-vec")]
     #[test]
     fn test_create_provider_openai_when_configured() {
         let mut cfg = base_config();
@@ -241,4 +323,38 @@ mod tests {
             Some("text-embedding-3-small")
         );
     }
+
+    #[tokio::test]
+    async fn test_local_hashing_provider_is_deterministic_and_similar() {
+        let mut cfg = base_config();
+        cfg.embedding_provider = Some("local".into());
+        let provider = create_provider(&cfg).expect("local provider");
+        assert_eq!(provider.model(), HASHING_MODEL);
+        assert_eq!(provider.dimension(), 384);
+
+        let a = provider
+            .embed("User prefers dark mode in the editor")
+            .await
+            .unwrap();
+        let again = provider
+            .embed("User prefers dark mode in the editor")
+            .await
+            .unwrap();
+        let near = provider
+            .embed("the user prefers dark themes")
+            .await
+            .unwrap();
+        let far = provider
+            .embed("Deploy runs on Fridays at noon")
+            .await
+            .unwrap();
+        assert_eq!(a, again);
+        assert_eq!(a.len(), 384);
+        let dot = |x: &[f32], y: &[f32]| x.iter().zip(y).map(|(p, q)| p * q).sum::<f32>();
+        assert!((dot(&a, &a) - 1.0).abs() < 1e-5);
+        assert!(dot(&a, &near) > dot(&a, &far) + 0.2);
+
+        cfg.embedding_model = Some("all-MiniLM-L6-v2".into());
+        assert!(create_provider(&cfg).is_none());
+    }
 }
@@ -4,8 +4,11 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use clap::{Parser, Subcommand};
-use microclaw_storage::db::{call_blocking, Database};
+use microclaw_channels::channel_adapter::{OutboundDecision, OutboundMessageFilter};
+use microclaw_core::llm_types::{MessagesResponse, ResponseContentBlock};
+use microclaw_storage::db::{call_blocking, Database, ScheduledTask};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::io::AsyncWriteExt;
@@ -15,27 +18,49 @@ use tracing::warn;
 use crate::config::Config;
 use crate::tools::ToolResult;
 
+mod daemon;
+
+use daemon::HookDaemon;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum HookEvent {
     BeforeLLMCall,
+    AfterLLMCall,
     BeforeToolCall,
     AfterToolCall,
+    OnInboundMessage,
+    BeforeOutboundMessage,
+    OnSessionCompacted,
+    OnScheduledTaskRun,
+    OnMemoryWrite,
 }
 
 impl HookEvent {
     fn as_str(self) -> &'static str {
         match self {
             HookEvent::BeforeLLMCall => "BeforeLLMCall",
+            HookEvent::AfterLLMCall => "AfterLLMCall",
             HookEvent::BeforeToolCall => "BeforeToolCall",
             HookEvent::AfterToolCall => "AfterToolCall",
+            HookEvent::OnInboundMessage => "OnInboundMessage",
+            HookEvent::BeforeOutboundMessage => "BeforeOutboundMessage",
+            HookEvent::OnSessionCompacted => "OnSessionCompacted",
+            HookEvent::OnScheduledTaskRun => "OnScheduledTaskRun",
+            HookEvent::OnMemoryWrite => "OnMemoryWrite",
         }
     }
 
     fn from_str(v: &str) -> Option<Self> {
         match v.trim() {
             "BeforeLLMCall" => Some(HookEvent::BeforeLLMCall),
+            "AfterLLMCall" => Some(HookEvent::AfterLLMCall),
             "BeforeToolCall" => Some(HookEvent::BeforeToolCall),
             "AfterToolCall" => Some(HookEvent::AfterToolCall),
+            "OnInboundMessage" => Some(HookEvent::OnInboundMessage),
+            "BeforeOutboundMessage" => Some(HookEvent::BeforeOutboundMessage),
+            "OnSessionCompacted" => Some(HookEvent::OnSessionCompacted),
+            "OnScheduledTaskRun" => Some(HookEvent::OnScheduledTaskRun),
+            "OnMemoryWrite" => Some(HookEvent::OnMemoryWrite),
             _ => None,
         }
     }
@@ -47,6 +72,7 @@ pub struct HookInfo {
     pub description: String,
     pub events: Vec<String>,
     pub command: String,
+    pub mode: String,
     pub timeout_ms: u64,
     pub enabled: bool,
     pub path: String,
@@ -59,17 +85,45 @@ struct HookFrontmatter {
     #[serde(default)]
     events: Vec<String>,
     command: Option<String>,
+    mode: Option<String>,
     enabled: Option<bool>,
     timeout_ms: Option<u64>,
     priority: Option<i32>,
 }
 
+/// How a hook command is run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HookMode {
+    /// A fresh process per call; the payload is stdin, the response is stdout.
+    Exec,
+    /// One long-lived process speaking newline-delimited JSON-RPC (see `daemon`).
+    Daemon,
+}
+
+impl HookMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            HookMode::Exec => "exec",
+            HookMode::Daemon => "daemon",
+        }
+    }
+
+    fn from_str(v: &str) -> Option<Self> {
+        match v.trim().to_ascii_lowercase().as_str() {
+            "" | "exec" => Some(HookMode::Exec),
+            "daemon" => Some(HookMode::Daemon),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct HookDef {
     name: String,
     description: String,
     events: Vec<HookEvent>,
     command: String,
+    mode: HookMode,
     timeout_ms: u64,
     enabled_by_default: bool,
     priority: i32,
@@ -95,6 +149,7 @@ pub struct HookManager {
     state_file: PathBuf,
     hooks: Arc<RwLock<Vec<HookDef>>>,
     state_overrides: Arc<RwLock<HashMap<String, bool>>>,
+    daemons: Arc<tokio::sync::Mutex<HashMap<String, Arc<HookDaemon>>>>,
     db: Option<Arc<Database>>,
     enabled: bool,
     max_input_bytes: usize,
@@ -127,6 +182,7 @@ impl HookManager {
             state_file,
             hooks: Arc::new(RwLock::new(Vec::new())),
             state_overrides: Arc::new(RwLock::new(HashMap::new())),
+            daemons: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
             db: None,
             enabled,
             max_input_bytes,
@@ -150,6 +206,7 @@ impl HookManager {
             state_file: tmp.join("runtime").join("hooks_state.json"),
             hooks: Arc::new(RwLock::new(Vec::new())),
             state_overrides: Arc::new(RwLock::new(HashMap::new())),
+            daemons: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
             db: None,
             enabled: true,
             max_input_bytes: 128 * 1024,
@@ -166,6 +223,7 @@ impl HookManager {
             state_file,
             hooks: Arc::new(RwLock::new(Vec::new())),
             state_overrides: Arc::new(RwLock::new(HashMap::new())),
+            daemons: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
             db: None,
             enabled: true,
             max_input_bytes: 128 * 1024,
@@ -194,6 +252,20 @@ impl HookManager {
         }
     }
 
+    /// Re-reads hook definitions and state, then replaces every running daemon.
+    /// The old daemons are shut down; new ones start lazily from the reloaded
+    /// definitions.
+    pub async fn reload(&self) {
+        let hooks = discover_hooks(&self.hooks_dir_candidates);
+        let state = read_state_file(&self.state_file).unwrap_or_default();
+        *self.hooks.write().await = hooks;
+        *self.state_overrides.write().await = state;
+        let retired = std::mem::take(&mut *self.daemons.lock().await);
+        for daemon in retired.into_values() {
+            daemon.shutdown().await;
+        }
+    }
+
     pub async fn list(&self) -> Vec<HookInfo> {
         let hooks = self.hooks.read().await;
         let state = self.state_overrides.read().await;
@@ -204,6 +276,7 @@ impl HookManager {
                 description: h.description.clone(),
                 events: h.events.iter().map(|e| e.as_str().to_string()).collect(),
                 command: h.command.clone(),
+                mode: h.mode.as_str().to_string(),
                 timeout_ms: h.timeout_ms,
                 enabled: state.get(&h.name).copied().unwrap_or(h.enabled_by_default),
                 path: h.dir.join("HOOK.md").to_string_lossy().to_string(),
@@ -228,9 +301,39 @@ impl HookManager {
             state.insert(name.to_string(), enabled);
             write_state_file(&self.state_file, &state)?;
         }
+        if !enabled {
+            let removed = self.daemons.lock().await.remove(name);
+            if let Some(daemon) = removed {
+                daemon.shutdown().await;
+            }
+        }
         Ok(())
     }
 
+    async fn daemon_for(&self, hook: &HookDef) -> Arc<HookDaemon> {
+        let mut daemons = self.daemons.lock().await;
+        match daemons.get(&hook.name) {
+            Some(daemon) if daemon.matches(hook) => daemon.clone(),
+            _ => {
+                let daemon = Arc::new(HookDaemon::new(hook));
+                daemons.insert(hook.name.clone(), daemon.clone());
+                daemon
+            }
+        }
+    }
+
+    /// Whether any enabled hook subscribes to `event`.
+    pub async fn has_enabled_hooks(&self, event: HookEvent) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let states = self.state_overrides.read().await;
+        self.hooks.read().await.iter().any(|h| {
+            h.events.contains(&event)
+                && states.get(&h.name).copied().unwrap_or(h.enabled_by_default)
+        })
+    }
+
     pub async fn run(&self, event: HookEvent, payload: serde_json::Value) -> Result<HookOutcome> {
         if !self.enabled {
             return Ok(HookOutcome::Allow {
@@ -259,14 +362,30 @@ impl HookManager {
             if !enabled {
                 continue;
             }
-            let output = run_hook_command(
-                &hook,
-                event,
-                &payload,
-                self.max_input_bytes,
-                self.max_output_bytes,
-            )
-            .await;
+            let output = match hook.mode {
+                HookMode::Exec => {
+                    run_hook_command(
+                        &hook,
+                        event,
+                        &payload,
+                        self.max_input_bytes,
+                        self.max_output_bytes,
+                    )
+                    .await
+                }
+                HookMode::Daemon => {
+                    self.daemon_for(&hook)
+                        .await
+                        .request(
+                            &hook,
+                            event,
+                            &payload,
+                            self.max_input_bytes,
+                            self.max_output_bytes,
+                        )
+                        .await
+                }
+            };
             let response = match output {
                 Ok(r) => r,
                 Err(e) => {
@@ -398,6 +517,186 @@ impl HookManager {
         )
         .await
     }
+
+    pub async fn run_after_llm(
+        &self,
+        chat_id: i64,
+        caller_channel: &str,
+        iteration: usize,
+        provider: &str,
+        model: &str,
+        response: &MessagesResponse,
+    ) -> Result<HookOutcome> {
+        let text = response
+            .content
+            .iter()
+            .filter_map(|b| match b {
+                ResponseContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("");
+        let tool_calls = response
+            .content
+            .iter()
+            .filter_map(|b| match b {
+                ResponseContentBlock::ToolUse { name, input, .. } => {
+                    Some(json!({"name": name, "input": input}))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        self.run(
+            HookEvent::AfterLLMCall,
+            json!({
+                "event": HookEvent::AfterLLMCall.as_str(),
+                "chat_id": chat_id,
+                "caller_channel": caller_channel,
+                "iteration": iteration,
+                "provider": provider,
+                "model": model,
+                "stop_reason": response.stop_reason,
+                "text": text,
+                "tool_calls": tool_calls,
+                "usage": response.usage.as_ref().map(|u| json!({
+                    "input_tokens": u.input_tokens,
+                    "output_tokens": u.output_tokens
+                }))
+            }),
+        )
+        .await
+    }
+
+    pub async fn run_inbound_message(
+        &self,
+        chat_id: i64,
+        caller_channel: &str,
+        chat_type: &str,
+        content: &str,
+    ) -> Result<HookOutcome> {
+        self.run(
+            HookEvent::OnInboundMessage,
+            json!({
+                "event": HookEvent::OnInboundMessage.as_str(),
+                "chat_id": chat_id,
+                "caller_channel": caller_channel,
+                "chat_type": chat_type,
+                "content": content
+            }),
+        )
+        .await
+    }
+
+    pub async fn run_before_outbound(
+        &self,
+        chat_id: i64,
+        channel: &str,
+        text: &str,
+    ) -> Result<HookOutcome> {
+        self.run(
+            HookEvent::BeforeOutboundMessage,
+            json!({
+                "event": HookEvent::BeforeOutboundMessage.as_str(),
+                "chat_id": chat_id,
+                "channel": channel,
+                "text": text
+            }),
+        )
+        .await
+    }
+
+    pub async fn run_session_compacted(
+        &self,
+        chat_id: i64,
+        caller_channel: &str,
+        messages_before: usize,
+        messages_after: usize,
+        summary: Option<&str>,
+    ) -> Result<HookOutcome> {
+        self.run(
+            HookEvent::OnSessionCompacted,
+            json!({
+                "event": HookEvent::OnSessionCompacted.as_str(),
+                "chat_id": chat_id,
+                "caller_channel": caller_channel,
+                "messages_before": messages_before,
+                "messages_after": messages_after,
+                "summary": summary
+            }),
+        )
+        .await
+    }
+
+    pub async fn run_scheduled_task(
+        &self,
+        task: &ScheduledTask,
+        caller_channel: &str,
+    ) -> Result<HookOutcome> {
+        self.run(
+            HookEvent::OnScheduledTaskRun,
+            json!({
+                "event": HookEvent::OnScheduledTaskRun.as_str(),
+                "task_id": task.id,
+                "chat_id": task.chat_id,
+                "caller_channel": caller_channel,
+                "prompt": task.prompt,
+                "schedule_type": task.schedule_type,
+                "schedule_value": task.schedule_value
+            }),
+        )
+        .await
+    }
+
+    pub async fn run_memory_write(
+        &self,
+        operation: &str,
+        chat_id: Option<i64>,
+        memory_id: Option<i64>,
+        content: &str,
+        category: &str,
+        source: &str,
+    ) -> Result<HookOutcome> {
+        self.run(
+            HookEvent::OnMemoryWrite,
+            json!({
+                "event": HookEvent::OnMemoryWrite.as_str(),
+                "operation": operation,
+                "chat_id": chat_id,
+                "memory_id": memory_id,
+                "content": content,
+                "category": category,
+                "source": source
+            }),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl OutboundMessageFilter for HookManager {
+    async fn is_active(&self) -> bool {
+        self.has_enabled_hooks(HookEvent::BeforeOutboundMessage)
+            .await
+    }
+
+    async fn filter_outbound(&self, channel: &str, chat_id: i64, text: &str) -> OutboundDecision {
+        match self.run_before_outbound(chat_id, channel, text).await {
+            Ok(HookOutcome::Block { reason }) => OutboundDecision::Block(reason),
+            Ok(HookOutcome::Allow { patches }) => {
+                let mut text = text.to_string();
+                for patch in patches {
+                    if let Some(v) = patch.get("text").and_then(|v| v.as_str()) {
+                        text = v.to_string();
+                    }
+                }
+                OutboundDecision::Send(text)
+            }
+            Err(e) => {
+                warn!("BeforeOutboundMessage hooks failed: {e}");
+                OutboundDecision::Send(text.to_string())
+            }
+        }
+    }
 }
 
 async fn run_hook_command(
@@ -544,6 +843,7 @@ fn parse_hook_md(hook_md_path: &Path, dir: &Path) -> Option<HookDef> {
     if command.is_empty() {
         return None;
     }
+    let mode = HookMode::from_str(fm.mode.as_deref().unwrap_or_default())?;
     let events = fm
         .events
         .into_iter()
@@ -563,6 +863,7 @@ fn parse_hook_md(hook_md_path: &Path, dir: &Path) -> Option<HookDef> {
         description: fm.description.unwrap_or_default(),
         events,
         command,
+        mode,
         timeout_ms: fm.timeout_ms.unwrap_or(1500),
         enabled_by_default: fm.enabled.unwrap_or(true),
         priority: fm.priority.unwrap_or(100),
@@ -598,9 +899,10 @@ pub async fn handle_hooks_cli(args: &[String]) -> Result<()> {
             }
             for h in hooks {
                 println!(
-                    "{}\tenabled={}\tevents={}\tcommand={}",
+                    "{}\tenabled={}\tmode={}\tevents={}\tcommand={}",
                     h.name,
                     h.enabled,
+                    h.mode,
                     h.events.join(","),
                     h.command
                 );
@@ -763,4 +1065,279 @@ fi
             _ => panic!("expected allow after disable"),
         }
     }
+
+    #[cfg(not(windows))]
+    fn write_sh_hook(hooks_dir: &Path, name: &str, events: &str, script: &str) {
+        let hook_dir = hooks_dir.join(name);
+        std::fs::create_dir_all(&hook_dir).unwrap();
+        std::fs::write(
+            hook_dir.join("HOOK.md"),
+            format!("---\nname: {name}\nevents: [{events}]\ncommand: \"sh hook.sh\"\ntimeout_ms: 2000\n---\n"),
+        )
+        .unwrap();
+        std::fs::write(hook_dir.join("hook.sh"), script).unwrap();
+    }
+
+    #[test]
+    fn test_lifecycle_event_names_round_trip() {
+        for event in [
+            HookEvent::AfterLLMCall,
+            HookEvent::OnInboundMessage,
+            HookEvent::BeforeOutboundMessage,
+            HookEvent::OnSessionCompacted,
+            HookEvent::OnScheduledTaskRun,
+            HookEvent::OnMemoryWrite,
+        ] {
+            assert_eq!(HookEvent::from_str(event.as_str()), Some(event));
+        }
+        assert_eq!(HookEvent::from_str("OnSomethingElse"), None);
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_outbound_filter_redacts_and_blocks() {
+        let root = std::env::temp_dir().join(format!("hook_outbound_{}", uuid::Uuid::new_v4()));
+        let hooks_dir = root.join("hooks");
+        write_sh_hook(
+            &hooks_dir,
+            "dlp",
+            "BeforeOutboundMessage",
+            r#"#!/bin/sh
+payload="$(cat)"
+if echo "$payload" | grep -q 'launch codes'; then
+  echo '{"action":"block","reason":"contains launch codes"}'
+elif echo "$payload" | grep -q 'sk-[a-z0-9]*'; then
+  echo '{"action":"modify","patch":{"text":"key: [redacted]"}}'
+else
+  echo '{"action":"allow"}'
+fi
+"#,
+        );
+        let manager =
+            HookManager::from_test_paths(hooks_dir, root.join("runtime/hooks_state.json"));
+
+        assert_eq!(
+            manager.filter_outbound("web", 1, "hello").await,
+            OutboundDecision::Send("hello".to_string())
+        );
+        assert_eq!(
+            manager.filter_outbound("web", 1, "key: sk-abc123").await,
+            OutboundDecision::Send("key: [redacted]".to_string())
+        );
+        assert_eq!(
+            manager
+                .filter_outbound("web", 1, "the launch codes are 0000")
+                .await,
+            OutboundDecision::Block("contains launch codes".to_string())
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_outbound_filter_covers_channel_final_replies() {
+        use microclaw_channels::channel::{outbound_filter_active, send_and_store_channel_reply};
+        use microclaw_channels::channel_adapter::ChannelRegistry;
+
+        let root = std::env::temp_dir().join(format!("hook_reply_{}", uuid::Uuid::new_v4()));
+        let hooks_dir = root.join("hooks");
+        write_sh_hook(
+            &hooks_dir,
+            "dlp",
+            "BeforeOutboundMessage",
+            r#"#!/bin/sh
+payload="$(cat)"
+if echo "$payload" | grep -q 'launch codes'; then
+  echo '{"action":"block","reason":"contains launch codes"}'
+elif echo "$payload" | grep -q 'sk-[a-z0-9]*'; then
+  echo '{"action":"modify","patch":{"text":"key: [redacted]"}}'
+else
+  echo '{"action":"allow"}'
+fi
+"#,
+        );
+        let manager = Arc::new(HookManager::from_test_paths(
+            hooks_dir,
+            root.join("runtime/hooks_state.json"),
+        ));
+        let mut registry = ChannelRegistry::new();
+        assert!(!outbound_filter_active(&registry).await);
+        registry.set_outbound_filter(manager);
+        assert!(outbound_filter_active(&registry).await);
+        let db = Arc::new(Database::new(root.join("db").to_str().unwrap()).unwrap());
+        let sent = std::sync::Mutex::new(Vec::<String>::new());
+
+        let blocked = send_and_store_channel_reply(
+            &registry,
+            db.clone(),
+            "telegram",
+            "bot",
+            7,
+            "the launch codes are 0000",
+            |text| async { sent.lock().unwrap().push(text) },
+        )
+        .await;
+        assert!(blocked.unwrap_err().contains("contains launch codes"));
+        assert!(sent.lock().unwrap().is_empty());
+        assert!(db.get_all_messages(7).unwrap().is_empty());
+
+        send_and_store_channel_reply(
+            &registry,
+            db.clone(),
+            "telegram",
+            "bot",
+            7,
+            "key: sk-abc123",
+            |text| async { sent.lock().unwrap().push(text) },
+        )
+        .await
+        .unwrap();
+        assert_eq!(*sent.lock().unwrap(), vec!["key: [redacted]".to_string()]);
+        let stored = db.get_all_messages(7).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].content, "key: [redacted]");
+        assert!(stored[0].is_from_bot);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_daemon_hook_keeps_state_and_restarts_after_crash() {
+        let root = std::env::temp_dir().join(format!("hook_daemon_{}", uuid::Uuid::new_v4()));
+        let hooks_dir = root.join("hooks");
+        let hook_dir = hooks_dir.join("counter");
+        std::fs::create_dir_all(&hook_dir).unwrap();
+        std::fs::write(
+            hook_dir.join("HOOK.md"),
+            "---\nname: counter\nevents: [BeforeToolCall]\nmode: daemon\ncommand: \"sh daemon.sh\"\ntimeout_ms: 2000\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            hook_dir.join("daemon.sh"),
+            r#"#!/bin/sh
+count=0
+while IFS= read -r line; do
+  count=$((count + 1))
+  id=$(printf '%s' "$line" | sed 's/.*"id":\([0-9]*\).*/\1/')
+  if printf '%s' "$line" | grep -q '"crash"'; then
+    exit 1
+  fi
+  printf '{"jsonrpc":"2.0","id":%s,"result":{"action":"modify","patch":{"count":%s}}}\n' "$id" "$count"
+done
+"#,
+        )
+        .unwrap();
+        let manager =
+            HookManager::from_test_paths(hooks_dir, root.join("runtime/hooks_state.json"));
+        assert_eq!(manager.info("counter").await.unwrap().mode, "daemon");
+
+        let count = |outcome: HookOutcome| match outcome {
+            HookOutcome::Allow { patches } => patches.first().and_then(|p| p["count"].as_i64()),
+            HookOutcome::Block { .. } => panic!("expected allow"),
+        };
+        let payload = json!({"tool_name": "bash"});
+        let first = manager
+            .run(HookEvent::BeforeToolCall, payload.clone())
+            .await
+            .unwrap();
+        let second = manager
+            .run(HookEvent::BeforeToolCall, payload.clone())
+            .await
+            .unwrap();
+        // One process served both calls.
+        assert_eq!(count(first), Some(1));
+        assert_eq!(count(second), Some(2));
+
+        let crashed = manager
+            .run(HookEvent::BeforeToolCall, json!({"tool_name": "crash"}))
+            .await
+            .unwrap();
+        assert_eq!(count(crashed), None);
+        let backing_off = manager
+            .run(HookEvent::BeforeToolCall, payload.clone())
+            .await
+            .unwrap();
+        assert_eq!(count(backing_off), None);
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        let restarted = manager
+            .run(HookEvent::BeforeToolCall, payload)
+            .await
+            .unwrap();
+        assert_eq!(count(restarted), Some(1));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_reload_replaces_daemons_under_contention() {
+        let root = std::env::temp_dir().join(format!("hook_reload_{}", uuid::Uuid::new_v4()));
+        let hooks_dir = root.join("hooks");
+        let hook_dir = hooks_dir.join("counter");
+        std::fs::create_dir_all(&hook_dir).unwrap();
+        std::fs::write(
+            hook_dir.join("HOOK.md"),
+            "---\nname: counter\nevents: [BeforeToolCall]\nmode: daemon\ncommand: \"sh daemon.sh\"\ntimeout_ms: 2000\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            hook_dir.join("daemon.sh"),
+            r#"#!/bin/sh
+count=0
+while IFS= read -r line; do
+  count=$((count + 1))
+  id=$(printf '%s' "$line" | sed 's/.*"id":\([0-9]*\).*/\1/')
+  printf '{"jsonrpc":"2.0","id":%s,"result":{"action":"modify","patch":{"count":%s}}}\n' "$id" "$count"
+done
+"#,
+        )
+        .unwrap();
+        let manager =
+            HookManager::from_test_paths(hooks_dir, root.join("runtime/hooks_state.json"));
+        let count = |outcome: HookOutcome| match outcome {
+            HookOutcome::Allow { patches } => patches.first().and_then(|p| p["count"].as_i64()),
+            HookOutcome::Block { .. } => panic!("expected allow"),
+        };
+        let payload = json!({"tool_name": "bash"});
+        let first = manager
+            .run(HookEvent::BeforeToolCall, payload.clone())
+            .await
+            .unwrap();
+        assert_eq!(count(first), Some(1));
+        let old = manager
+            .daemons
+            .lock()
+            .await
+            .get("counter")
+            .cloned()
+            .unwrap();
+
+        // The reload waits out a held daemon lock instead of skipping the reset.
+        let guard = manager.daemons.lock().await;
+        let reloading = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.reload().await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(guard);
+        reloading.await.unwrap();
+
+        assert!(old
+            .request(
+                &manager.hooks.read().await[0],
+                HookEvent::BeforeToolCall,
+                &payload,
+                1024,
+                1024,
+            )
+            .await
+            .is_err());
+        let fresh = manager
+            .run(HookEvent::BeforeToolCall, payload)
+            .await
+            .unwrap();
+        assert_eq!(count(fresh), Some(1));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
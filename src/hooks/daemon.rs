@@ -0,0 +1,242 @@
+//! Long-lived hook processes (`mode: daemon`).
+//!
+//! A daemon hook is started on first use and kept running. Each hook call is one
+//! newline-delimited JSON-RPC 2.0 request on its stdin
+//! (`{"jsonrpc":"2.0","id":1,"method":"BeforeToolCall","params":{...}}`) and it
+//! answers with one response line on stdout whose `result` is the usual
+//! `{"action": ...}` object. Requests to the same daemon are serialized.
+//!
+//! A daemon that exits, writes an oversized line or misses `timeout_ms` is killed
+//! and restarted on the next call, with exponential backoff between restarts.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use super::*;
+
+const RESTART_BACKOFF_BASE: Duration = Duration::from_millis(250);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    id: Option<u64>,
+    result: Option<HookResponse>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+struct DaemonProcess {
+    // Held so the process is killed when the daemon is restarted or dropped.
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+#[derive(Default)]
+struct DaemonState {
+    process: Option<DaemonProcess>,
+    next_id: u64,
+    consecutive_failures: u32,
+    restart_after: Option<Instant>,
+}
+
+impl DaemonState {
+    fn record_failure(&mut self) {
+        self.process = None;
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        let backoff = RESTART_BACKOFF_BASE
+            .saturating_mul(1 << self.consecutive_failures.saturating_sub(1).min(16))
+            .min(RESTART_BACKOFF_MAX);
+        self.restart_after = Some(Instant::now() + backoff);
+    }
+}
+
+pub(super) struct HookDaemon {
+    name: String,
+    command: String,
+    dir: PathBuf,
+    state: Mutex<DaemonState>,
+    retired: AtomicBool,
+}
+
+impl HookDaemon {
+    pub(super) fn new(hook: &HookDef) -> Self {
+        Self {
+            name: hook.name.clone(),
+            command: hook.command.clone(),
+            dir: hook.dir.clone(),
+            state: Mutex::new(DaemonState::default()),
+            retired: AtomicBool::new(false),
+        }
+    }
+
+    /// Kills the process and refuses further requests. Callers still holding this
+    /// daemon from before a reload get an error instead of respawning it.
+    pub(super) async fn shutdown(&self) {
+        self.retired.store(true, Ordering::SeqCst);
+        self.state.lock().await.process = None;
+        debug!("stopped hook daemon '{}'", self.name);
+    }
+
+    /// Whether this daemon was started from the same definition as `hook`.
+    pub(super) fn matches(&self, hook: &HookDef) -> bool {
+        self.command == hook.command && self.dir == hook.dir
+    }
+
+    pub(super) async fn request(
+        &self,
+        hook: &HookDef,
+        event: HookEvent,
+        payload: &serde_json::Value,
+        max_input_bytes: usize,
+        max_output_bytes: usize,
+    ) -> Result<HookResponse> {
+        let mut state = self.state.lock().await;
+        if self.retired.load(Ordering::SeqCst) {
+            return Err(anyhow!("hook daemon was shut down"));
+        }
+        if state.process.is_none() {
+            if let Some(at) = state.restart_after {
+                if Instant::now() < at {
+                    return Err(anyhow!("hook daemon is waiting to restart"));
+                }
+            }
+            match self.spawn() {
+                Ok(process) => state.process = Some(process),
+                Err(e) => {
+                    state.record_failure();
+                    return Err(e);
+                }
+            }
+        }
+
+        state.next_id += 1;
+        let id = state.next_id;
+        let mut body = serde_json::to_vec(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": event.as_str(),
+            "params": payload
+        }))?;
+        if body.len() > max_input_bytes {
+            return Err(anyhow!("hook input exceeds max bytes"));
+        }
+        body.push(b'\n');
+
+        let process = state
+            .process
+            .as_mut()
+            .expect("daemon process started above");
+        let timeout = Duration::from_millis(hook.timeout_ms.clamp(10, 120_000));
+        match tokio::time::timeout(timeout, exchange(process, &body, id, max_output_bytes)).await {
+            Ok(Ok(response)) => {
+                state.consecutive_failures = 0;
+                state.restart_after = None;
+                // An error reply is a hook failure, but the daemon itself is healthy.
+                if let Some(err) = response.error {
+                    return Err(anyhow!("hook error {}: {}", err.code, err.message));
+                }
+                Ok(response.result.unwrap_or(HookResponse {
+                    action: Some("allow".to_string()),
+                    reason: None,
+                    patch: None,
+                }))
+            }
+            Ok(Err(e)) => {
+                state.record_failure();
+                Err(e)
+            }
+            Err(_) => {
+                state.record_failure();
+                Err(anyhow!("hook timed out after {}ms", hook.timeout_ms))
+            }
+        }
+    }
+
+    fn spawn(&self) -> Result<DaemonProcess> {
+        let shell = if cfg!(windows) { "cmd" } else { "sh" };
+        let shell_arg = if cfg!(windows) { "/C" } else { "-lc" };
+        let mut child = tokio::process::Command::new(shell)
+            .arg(shell_arg)
+            .arg(&self.command)
+            .current_dir(&self.dir)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .env("MICROCLAW_HOOK_NAME", &self.name)
+            .env("MICROCLAW_HOOK_MODE", "daemon")
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("hook daemon stdin unavailable"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("hook daemon stdout unavailable"))?;
+        if let Some(stderr) = child.stderr.take() {
+            let name = self.name.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    debug!("hook '{}' stderr: {}", name, line);
+                }
+            });
+        }
+        debug!("started hook daemon '{}'", self.name);
+        Ok(DaemonProcess {
+            _child: child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+}
+
+async fn exchange(
+    process: &mut DaemonProcess,
+    body: &[u8],
+    id: u64,
+    max_output_bytes: usize,
+) -> Result<RpcResponse> {
+    process.stdin.write_all(body).await?;
+    process.stdin.flush().await?;
+    loop {
+        let mut line = Vec::new();
+        let read = (&mut process.stdout)
+            .take(max_output_bytes as u64 + 1)
+            .read_until(b'\n', &mut line)
+            .await?;
+        if read == 0 {
+            return Err(anyhow!("hook daemon exited"));
+        }
+        if line.len() > max_output_bytes {
+            return Err(anyhow!("hook output exceeds max bytes"));
+        }
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_slice::<RpcResponse>(line) {
+            Ok(r) => r,
+            Err(e) => {
+                debug!("ignoring non JSON-RPC hook output: {e}");
+                continue;
+            }
+        };
+        // Notifications and replies to other ids are not ours.
+        if response.id == Some(id) {
+            return Ok(response);
+        }
+    }
+}
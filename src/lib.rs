@@ -3,9 +3,11 @@ pub mod acp;
 pub mod agent_engine;
 pub mod channels;
 pub mod chat_commands;
+pub mod checkpoints;
 pub mod clawhub;
 pub mod codex_auth;
 pub mod config;
+pub mod context_budget;
 pub mod doctor;
 pub mod embedding;
 pub mod gateway;
@@ -22,6 +24,7 @@ pub mod scheduler;
 pub mod setup;
 pub mod setup_def;
 pub mod skills;
+pub mod structured_output;
 pub mod tools;
 pub mod web;
 
@@ -16,12 +16,20 @@ use crate::config::Config;
 #[cfg(test)]
 use crate::config::WorkingDirIsolation;
 use crate::http_client::llm_user_agent;
+use crate::structured_output::FINAL_ANSWER_TOOL_NAME;
 use microclaw_core::error::MicroClawError;
 use microclaw_core::llm_types::{
     ContentBlock, ImageSource, Message, MessageContent, MessagesRequest, MessagesResponse,
     ResponseContentBlock, ToolDefinition, Usage,
 };
 
+mod cassette;
+mod failover;
+mod gemini;
+pub use cassette::{RecordingProvider, ReplayProvider, CASSETTE_ENV_VAR};
+pub use failover::{LlmClientPool, LlmHealthRegistry, LlmRoute, LlmRouteTarget, RoutedResponse};
+pub use gemini::GeminiProvider;
+
 /// Remove invalid `ToolResult` blocks that cannot be matched to the most recent
 /// assistant `ToolUse` turn. This can happen after session compaction or
 /// malformed history reconstruction.
@@ -231,12 +239,40 @@ pub trait LlmProvider: Send + Sync {
         self.send_message_stream(system, messages, tools, text_tx)
             .await
     }
+
+    /// Like `send_message_stream_with_model`, additionally forwarding reasoning
+    /// deltas to `thinking_tx` for providers that stream thinking blocks.
+    async fn send_message_stream_with_thinking(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+        _thinking_tx: Option<&UnboundedSender<String>>,
+        model_override: Option<&str>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.send_message_stream_with_model(system, messages, tools, text_tx, model_override)
+            .await
+    }
 }
 
 pub fn create_provider(config: &Config) -> Box<dyn LlmProvider> {
-    match config.llm_provider.trim().to_lowercase().as_str() {
+    let cassette = cassette::cassette_settings(config);
+    if let Some((crate::config::LlmCassetteMode::Replay, path)) = &cassette {
+        return Box::new(
+            ReplayProvider::new(path).with_lenient_matching(config.llm_cassette.lenient),
+        );
+    }
+    let provider: Box<dyn LlmProvider> = match config.llm_provider.trim().to_lowercase().as_str() {
         "anthropic" => Box::new(AnthropicProvider::new(config)),
+        "gemini" => Box::new(GeminiProvider::new(config)),
         _ => Box::new(OpenAiProvider::new(config)),
+    };
+    match cassette {
+        Some((crate::config::LlmCassetteMode::Record, path)) => {
+            Box::new(RecordingProvider::new(provider, &path))
+        }
+        _ => provider,
     }
 }
 
@@ -250,6 +286,8 @@ pub struct AnthropicProvider {
     model: String,
     max_tokens: u32,
     base_url: String,
+    prompt_cache: bool,
+    thinking_budget: Option<u32>,
 }
 
 impl AnthropicProvider {
@@ -260,13 +298,20 @@ impl AnthropicProvider {
             model: config.model.clone(),
             max_tokens: config.max_tokens,
             base_url: resolve_anthropic_messages_url(config.llm_base_url.as_deref().unwrap_or("")),
+            prompt_cache: config.anthropic_prompt_cache,
+            thinking_budget: config.thinking_budget_tokens,
         }
     }
 
+    fn request_body(&self, request: &MessagesRequest) -> serde_json::Value {
+        anthropic_request_body(request, self.prompt_cache, self.thinking_budget)
+    }
+
     async fn send_message_stream_single_pass(
         &self,
         request: &MessagesRequest,
         text_tx: Option<&UnboundedSender<String>>,
+        thinking_tx: Option<&UnboundedSender<String>>,
     ) -> Result<MessagesResponse, MicroClawError> {
         let mut streamed_request = request.clone();
         streamed_request.stream = Some(true);
@@ -285,7 +330,7 @@ impl AnthropicProvider {
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("content-type", "application/json")
-            .json(&streamed_request)
+            .json(&self.request_body(&streamed_request))
             .send()
             .await?;
 
@@ -293,23 +338,20 @@ impl AnthropicProvider {
         if !status.is_success() {
             let body = response.text().await.unwrap_or_default();
             if let Ok(api_err) = serde_json::from_str::<AnthropicApiError>(&body) {
-                return Err(MicroClawError::LlmApi(format!(
-                    "{}: {}",
-                    api_err.error.error_type, api_err.error.message
-                )));
+                return Err(MicroClawError::llm_status(
+                    status.as_u16(),
+                    format!("{}: {}", api_err.error.error_type, api_err.error.message),
+                ));
             }
-            return Err(MicroClawError::LlmApi(format!("HTTP {status}: {body}")));
+            return Err(MicroClawError::llm_status(
+                status.as_u16(),
+                format!("HTTP {status}: {body}"),
+            ));
         }
 
         let mut byte_stream = response.bytes_stream();
         let mut sse = SseEventParser::default();
-        let mut stop_reason: Option<String> = None;
-        let mut usage: Option<Usage> = None;
-        let mut text_blocks: std::collections::HashMap<usize, String> =
-            std::collections::HashMap::new();
-        let mut tool_blocks: std::collections::HashMap<usize, StreamToolUseBlock> =
-            std::collections::HashMap::new();
-        let mut ordered_indexes: Vec<usize> = Vec::new();
+        let mut state = AnthropicStreamState::default();
 
         'outer: while let Some(chunk_res) = byte_stream.next().await {
             let chunk = match chunk_res {
@@ -320,38 +362,23 @@ impl AnthropicProvider {
                 if data == "[DONE]" {
                     break 'outer;
                 }
-                process_anthropic_stream_event(
-                    &data,
-                    text_tx,
-                    &mut stop_reason,
-                    &mut usage,
-                    &mut text_blocks,
-                    &mut tool_blocks,
-                    &mut ordered_indexes,
-                );
+                process_anthropic_stream_event(&data, text_tx, thinking_tx, &mut state);
             }
         }
         for data in sse.finish() {
             if data == "[DONE]" {
                 break;
             }
-            process_anthropic_stream_event(
-                &data,
-                text_tx,
-                &mut stop_reason,
-                &mut usage,
-                &mut text_blocks,
-                &mut tool_blocks,
-                &mut ordered_indexes,
-            );
+            process_anthropic_stream_event(&data, text_tx, thinking_tx, &mut state);
         }
 
         Ok(build_stream_response(
-            ordered_indexes,
-            text_blocks,
-            tool_blocks,
-            stop_reason,
-            usage,
+            state.ordered_indexes,
+            state.text_blocks,
+            state.thinking_blocks,
+            state.tool_blocks,
+            state.stop_reason,
+            state.usage,
         ))
     }
 }
@@ -367,6 +394,160 @@ fn resolve_anthropic_messages_url(configured_base: &str) -> String {
     format!("{trimmed}/v1/messages")
 }
 
+/// How a request constrains tool use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ToolChoice<'a> {
+    Auto,
+    /// Some tool must be called.
+    Any,
+    /// This tool must be called.
+    Tool(&'a str),
+}
+
+/// Structured-output requests offer the `final_answer` tool and must end with a
+/// tool call instead of free text: any tool while others are offered, and
+/// `final_answer` itself once the agent loop narrows the list down to it.
+pub(crate) fn tool_choice(tools: &[ToolDefinition]) -> ToolChoice<'_> {
+    match tools {
+        [only] if only.name == FINAL_ANSWER_TOOL_NAME => ToolChoice::Tool(&only.name),
+        _ if tools.iter().any(|t| t.name == FINAL_ANSWER_TOOL_NAME) => ToolChoice::Any,
+        _ => ToolChoice::Auto,
+    }
+}
+
+/// `tool_choice` for the Chat Completions API; `None` leaves the default (`auto`).
+fn oai_tool_choice(tools: &[ToolDefinition]) -> Option<serde_json::Value> {
+    match tool_choice(tools) {
+        ToolChoice::Auto => None,
+        ToolChoice::Any => Some(json!("required")),
+        ToolChoice::Tool(name) => Some(json!({"type": "function", "function": {"name": name}})),
+    }
+}
+
+/// Smallest `budget_tokens` the Messages API accepts for extended thinking.
+const ANTHROPIC_MIN_THINKING_BUDGET: u32 = 1024;
+
+/// Serialize a Messages API request.
+///
+/// With a thinking budget, extended thinking is enabled and replayed thinking
+/// blocks are kept (unsigned ones, e.g. from another provider, are dropped);
+/// without one, replayed thinking blocks are removed entirely.
+///
+/// With prompt caching, `cache_control` breakpoints are added on the system
+/// prompt, the last tool definition and the last block of the conversation.
+/// Each tool-loop iteration then reads the previous iteration's prefix from cache.
+///
+/// Structured-output requests get a forcing `tool_choice` (see [`tool_choice`]),
+/// except with extended thinking, which only accepts `auto`; those runs rely on the
+/// prompt and the agent loop's validation retries.
+fn anthropic_request_body(
+    request: &MessagesRequest,
+    prompt_cache: bool,
+    thinking_budget: Option<u32>,
+) -> serde_json::Value {
+    let mut body = serde_json::to_value(request).unwrap_or_else(|_| json!({}));
+    // budget_tokens must be below max_tokens.
+    let thinking_budget = thinking_budget
+        .filter(|_| request.max_tokens > ANTHROPIC_MIN_THINKING_BUDGET)
+        .map(|b| b.clamp(ANTHROPIC_MIN_THINKING_BUDGET, request.max_tokens - 1));
+    if let Some(budget) = thinking_budget {
+        body["thinking"] = json!({"type": "enabled", "budget_tokens": budget});
+    } else {
+        match tool_choice(request.tools.as_deref().unwrap_or_default()) {
+            ToolChoice::Auto => {}
+            ToolChoice::Any => body["tool_choice"] = json!({"type": "any"}),
+            ToolChoice::Tool(name) => body["tool_choice"] = json!({"type": "tool", "name": name}),
+        }
+    }
+    if let Some(messages) = body.get_mut("messages").and_then(|m| m.as_array_mut()) {
+        for message in messages {
+            if let Some(blocks) = message.get_mut("content").and_then(|c| c.as_array_mut()) {
+                blocks.retain(|block| match block.get("type").and_then(|t| t.as_str()) {
+                    Some("thinking") => {
+                        thinking_budget.is_some()
+                            && block
+                                .get("signature")
+                                .and_then(|s| s.as_str())
+                                .is_some_and(|s| !s.is_empty())
+                    }
+                    Some("redacted_thinking") => thinking_budget.is_some(),
+                    _ => true,
+                });
+            }
+        }
+    }
+    if !prompt_cache {
+        return body;
+    }
+    let breakpoint = json!({"type": "ephemeral"});
+    if !request.system.trim().is_empty() {
+        body["system"] = json!([{
+            "type": "text",
+            "text": request.system,
+            "cache_control": breakpoint,
+        }]);
+    }
+    if let Some(last_tool) = body
+        .get_mut("tools")
+        .and_then(|t| t.as_array_mut())
+        .and_then(|t| t.last_mut())
+    {
+        last_tool["cache_control"] = breakpoint.clone();
+    }
+    if let Some(last_message) = body
+        .get_mut("messages")
+        .and_then(|m| m.as_array_mut())
+        .and_then(|m| m.last_mut())
+    {
+        if let Some(text) = last_message
+            .get("content")
+            .and_then(|c| c.as_str())
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+        {
+            last_message["content"] = json!([{
+                "type": "text",
+                "text": text,
+                "cache_control": breakpoint,
+            }]);
+        } else if let Some(block) = last_message
+            .get_mut("content")
+            .and_then(|c| c.as_array_mut())
+            .and_then(|b| b.last_mut())
+        {
+            // Empty text blocks and thinking blocks cannot carry a cache breakpoint.
+            let block_type = block.get("type").and_then(|t| t.as_str());
+            let empty_text = block_type == Some("text")
+                && block
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .is_none_or(str::is_empty);
+            let thinking = matches!(block_type, Some("thinking" | "redacted_thinking"));
+            if !empty_text && !thinking {
+                block["cache_control"] = breakpoint;
+            }
+        }
+    }
+    body
+}
+
+#[derive(Default)]
+struct StreamThinkingBlock {
+    thinking: String,
+    signature: Option<String>,
+    redacted_data: Option<String>,
+}
+
+#[derive(Default)]
+struct AnthropicStreamState {
+    stop_reason: Option<String>,
+    usage: Option<Usage>,
+    text_blocks: std::collections::HashMap<usize, String>,
+    thinking_blocks: std::collections::HashMap<usize, StreamThinkingBlock>,
+    tool_blocks: std::collections::HashMap<usize, StreamToolUseBlock>,
+    ordered_indexes: Vec<usize>,
+}
+
 #[derive(Default)]
 struct StreamToolUseBlock {
     id: String,
@@ -385,9 +566,17 @@ fn usage_from_json(v: &serde_json::Value) -> Option<Usage> {
         .and_then(json_u64)
         .or_else(|| v.get("completion_tokens").and_then(json_u64))
         .unwrap_or(0);
+    let cached = |key: &str| {
+        v.get(key)
+            .and_then(json_u64)
+            .map(|n| u32::try_from(n).unwrap_or(u32::MAX))
+            .unwrap_or(0)
+    };
     Some(Usage {
         input_tokens: u32::try_from(input).unwrap_or(u32::MAX),
         output_tokens: u32::try_from(output).unwrap_or(u32::MAX),
+        cache_creation_input_tokens: cached("cache_creation_input_tokens"),
+        cache_read_input_tokens: cached("cache_read_input_tokens"),
     })
 }
 
@@ -402,15 +591,20 @@ fn json_u64(v: &serde_json::Value) -> Option<u64> {
 fn process_anthropic_stream_event(
     data: &str,
     text_tx: Option<&UnboundedSender<String>>,
-    stop_reason: &mut Option<String>,
-    usage: &mut Option<Usage>,
-    text_blocks: &mut std::collections::HashMap<usize, String>,
-    tool_blocks: &mut std::collections::HashMap<usize, StreamToolUseBlock>,
-    ordered_indexes: &mut Vec<usize>,
+    thinking_tx: Option<&UnboundedSender<String>>,
+    state: &mut AnthropicStreamState,
 ) {
     let Ok(v) = serde_json::from_str::<serde_json::Value>(data) else {
         return;
     };
+    let AnthropicStreamState {
+        stop_reason,
+        usage,
+        text_blocks,
+        thinking_blocks,
+        tool_blocks,
+        ordered_indexes,
+    } = state;
 
     let event_type = v.get("type").and_then(|t| t.as_str()).unwrap_or_default();
     match event_type {
@@ -433,6 +627,40 @@ fn process_anthropic_stream_event(
                                 .to_string();
                             text_blocks.insert(index, text);
                         }
+                        Some("thinking") => {
+                            let thinking = block
+                                .get("thinking")
+                                .and_then(|t| t.as_str())
+                                .unwrap_or_default()
+                                .to_string();
+                            let signature = block
+                                .get("signature")
+                                .and_then(|s| s.as_str())
+                                .filter(|s| !s.is_empty())
+                                .map(str::to_string);
+                            thinking_blocks.insert(
+                                index,
+                                StreamThinkingBlock {
+                                    thinking,
+                                    signature,
+                                    redacted_data: None,
+                                },
+                            );
+                        }
+                        Some("redacted_thinking") => {
+                            let data = block
+                                .get("data")
+                                .and_then(|d| d.as_str())
+                                .unwrap_or_default()
+                                .to_string();
+                            thinking_blocks.insert(
+                                index,
+                                StreamThinkingBlock {
+                                    redacted_data: Some(data),
+                                    ..Default::default()
+                                },
+                            );
+                        }
                         Some("tool_use") => {
                             let id = block
                                 .get("id")
@@ -491,6 +719,32 @@ fn process_anthropic_stream_event(
                         }
                     }
                 }
+                Some("thinking_delta") => {
+                    let piece = delta
+                        .get("thinking")
+                        .and_then(|t| t.as_str())
+                        .unwrap_or_default();
+                    if !piece.is_empty() {
+                        thinking_blocks
+                            .entry(index)
+                            .or_default()
+                            .thinking
+                            .push_str(piece);
+                        if let Some(tx) = thinking_tx {
+                            let _ = tx.send(piece.to_string());
+                        }
+                    }
+                }
+                Some("signature_delta") => {
+                    if let Some(sig) = delta.get("signature").and_then(|s| s.as_str()) {
+                        thinking_blocks
+                            .entry(index)
+                            .or_default()
+                            .signature
+                            .get_or_insert_with(String::new)
+                            .push_str(sig);
+                    }
+                }
                 Some("input_json_delta") => {
                     let piece = delta
                         .get("partial_json")
@@ -515,8 +769,21 @@ fn process_anthropic_stream_event(
             {
                 *stop_reason = Some(reason.to_string());
             }
+            // `message_delta` usage is cumulative and may carry only `output_tokens`;
+            // keep the input/cache counts reported by `message_start`.
             if let Some(u) = v.get("usage") {
-                *usage = usage_from_json(u);
+                match usage_from_json(u) {
+                    Some(parsed) => merge_usage_max(usage, parsed),
+                    None => {
+                        if let (Some(current), Some(out)) =
+                            (usage.as_mut(), u.get("output_tokens").and_then(json_u64))
+                        {
+                            current.output_tokens = current
+                                .output_tokens
+                                .max(u32::try_from(out).unwrap_or(u32::MAX));
+                        }
+                    }
+                }
             }
         }
         "message_start" => {
@@ -652,6 +919,12 @@ fn merge_usage_max(slot: &mut Option<Usage>, incoming: Usage) {
         Some(current) => {
             current.input_tokens = current.input_tokens.max(incoming.input_tokens);
             current.output_tokens = current.output_tokens.max(incoming.output_tokens);
+            current.cache_creation_input_tokens = current
+                .cache_creation_input_tokens
+                .max(incoming.cache_creation_input_tokens);
+            current.cache_read_input_tokens = current
+                .cache_read_input_tokens
+                .max(incoming.cache_read_input_tokens);
         }
         None => {
             *slot = Some(incoming);
@@ -697,12 +970,24 @@ fn combine_visible_and_reasoning_text(visible: &str, reasoning: &str) -> String
 fn build_stream_response(
     ordered_indexes: Vec<usize>,
     text_blocks: std::collections::HashMap<usize, String>,
+    thinking_blocks: std::collections::HashMap<usize, StreamThinkingBlock>,
     tool_blocks: std::collections::HashMap<usize, StreamToolUseBlock>,
     stop_reason: Option<String>,
     usage: Option<Usage>,
 ) -> MessagesResponse {
     let mut content = Vec::new();
     for index in ordered_indexes {
+        if let Some(block) = thinking_blocks.get(&index) {
+            match &block.redacted_data {
+                Some(data) => {
+                    content.push(ResponseContentBlock::RedactedThinking { data: data.clone() })
+                }
+                None => content.push(ResponseContentBlock::Thinking {
+                    thinking: block.thinking.clone(),
+                    signature: block.signature.clone(),
+                }),
+            }
+        }
         if let Some(text) = text_blocks.get(&index) {
             if !text.is_empty() {
                 content.push(ResponseContentBlock::Text { text: text.clone() });
@@ -786,6 +1071,7 @@ impl LlmProvider for AnthropicProvider {
             stream: None,
         };
 
+        let body = self.request_body(&request);
         let mut retries = 0u32;
         let max_retries = 3;
 
@@ -796,7 +1082,7 @@ impl LlmProvider for AnthropicProvider {
                 .header("x-api-key", &self.api_key)
                 .header("anthropic-version", "2023-06-01")
                 .header("content-type", "application/json")
-                .json(&request)
+                .json(&body)
                 .send()
                 .await?;
 
@@ -823,12 +1109,15 @@ impl LlmProvider for AnthropicProvider {
 
             let body = response.text().await.unwrap_or_default();
             if let Ok(api_err) = serde_json::from_str::<AnthropicApiError>(&body) {
-                return Err(MicroClawError::LlmApi(format!(
-                    "{}: {}",
-                    api_err.error.error_type, api_err.error.message
-                )));
+                return Err(MicroClawError::llm_status(
+                    status.as_u16(),
+                    format!("{}: {}", api_err.error.error_type, api_err.error.message),
+                ));
             }
-            return Err(MicroClawError::LlmApi(format!("HTTP {status}: {body}")));
+            return Err(MicroClawError::llm_status(
+                status.as_u16(),
+                format!("HTTP {status}: {body}"),
+            ));
         }
     }
 
@@ -850,6 +1139,26 @@ impl LlmProvider for AnthropicProvider {
         tools: Option<Vec<ToolDefinition>>,
         text_tx: Option<&UnboundedSender<String>>,
         model_override: Option<&str>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.send_message_stream_with_thinking(
+            system,
+            messages,
+            tools,
+            text_tx,
+            None,
+            model_override,
+        )
+        .await
+    }
+
+    async fn send_message_stream_with_thinking(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+        thinking_tx: Option<&UnboundedSender<String>>,
+        model_override: Option<&str>,
     ) -> Result<MessagesResponse, MicroClawError> {
         let messages = sanitize_messages(messages);
         let model = model_override
@@ -865,7 +1174,7 @@ impl LlmProvider for AnthropicProvider {
             stream: Some(true),
         };
 
-        self.send_message_stream_single_pass(&request, text_tx)
+        self.send_message_stream_single_pass(&request, text_tx, thinking_tx)
             .await
     }
 }
@@ -1335,6 +1644,9 @@ impl LlmProvider for OpenAiProvider {
         if let Some(ref tool_defs) = tools {
             if !tool_defs.is_empty() {
                 body["tools"] = json!(translate_tools_to_oai(tool_defs));
+                if let Some(choice) = oai_tool_choice(tool_defs) {
+                    body["tool_choice"] = choice;
+                }
             }
         }
 
@@ -1385,9 +1697,15 @@ impl LlmProvider for OpenAiProvider {
                 continue;
             }
             if let Ok(err) = serde_json::from_str::<OaiErrorResponse>(&text) {
-                return Err(MicroClawError::LlmApi(err.error.message));
+                return Err(MicroClawError::llm_status(
+                    status.as_u16(),
+                    err.error.message,
+                ));
             }
-            return Err(MicroClawError::LlmApi(format!("HTTP {status}: {text}")));
+            return Err(MicroClawError::llm_status(
+                status.as_u16(),
+                format!("HTTP {status}: {text}"),
+            ));
         }
     }
 
@@ -1478,6 +1796,9 @@ impl LlmProvider for OpenAiProvider {
         if let Some(ref tool_defs) = tools {
             if !tool_defs.is_empty() {
                 body["tools"] = json!(translate_tools_to_oai(tool_defs));
+                if let Some(choice) = oai_tool_choice(tool_defs) {
+                    body["tool_choice"] = choice;
+                }
             }
         }
 
@@ -1523,9 +1844,15 @@ impl LlmProvider for OpenAiProvider {
                 continue;
             }
             if let Ok(err) = serde_json::from_str::<OaiErrorResponse>(&text) {
-                return Err(MicroClawError::LlmApi(err.error.message));
+                return Err(MicroClawError::llm_status(
+                    status.as_u16(),
+                    err.error.message,
+                ));
             }
-            return Err(MicroClawError::LlmApi(format!("HTTP {status}: {text}")));
+            return Err(MicroClawError::llm_status(
+                status.as_u16(),
+                format!("HTTP {status}: {text}"),
+            ));
         };
 
         let mut byte_stream = response.bytes_stream();
@@ -1646,7 +1973,11 @@ impl OpenAiProvider {
         if let Some(ref tool_defs) = tools {
             if !tool_defs.is_empty() {
                 body["tools"] = json!(translate_tools_to_oai_responses(tool_defs));
-                body["tool_choice"] = json!("auto");
+                body["tool_choice"] = match tool_choice(tool_defs) {
+                    ToolChoice::Auto => json!("auto"),
+                    ToolChoice::Any => json!("required"),
+                    ToolChoice::Tool(name) => json!({"type": "function", "name": name}),
+                };
             }
         }
 
@@ -1689,9 +2020,15 @@ impl OpenAiProvider {
 
             let text = response.text().await.unwrap_or_default();
             if let Ok(err) = serde_json::from_str::<OaiErrorResponse>(&text) {
-                return Err(MicroClawError::LlmApi(err.error.message));
+                return Err(MicroClawError::llm_status(
+                    status.as_u16(),
+                    err.error.message,
+                ));
             }
-            return Err(MicroClawError::LlmApi(format!("HTTP {status}: {text}")));
+            return Err(MicroClawError::llm_status(
+                status.as_u16(),
+                format!("HTTP {status}: {text}"),
+            ));
         }
     }
 }
@@ -1860,6 +2197,26 @@ fn translate_messages_to_oai_with_reasoning(
                         if !emitted_any_tool {
                             pending_tool_ids.clear();
                         }
+                        // Tool messages are text-only; images returned by tools follow
+                        // as a user message.
+                        let image_parts: Vec<serde_json::Value> = blocks
+                            .iter()
+                            .filter_map(|b| match b {
+                                ContentBlock::Image {
+                                    source:
+                                        ImageSource {
+                                            media_type, data, ..
+                                        },
+                                } => Some(json!({
+                                    "type": "image_url",
+                                    "image_url": {"url": format!("data:{media_type};base64,{data}")}
+                                })),
+                                _ => None,
+                            })
+                            .collect();
+                        if emitted_any_tool && !image_parts.is_empty() {
+                            out.push(json!({"role": "user", "content": image_parts}));
+                        }
                     } else {
                         pending_tool_ids.clear();
                         // Images + text → multipart content array
@@ -2024,6 +2381,32 @@ fn translate_messages_to_oai_responses_input(messages: &[Message]) -> Vec<serde_
                         if !emitted_any_tool {
                             pending_tool_ids.clear();
                         }
+                        let image_parts: Vec<serde_json::Value> = blocks
+                            .iter()
+                            .filter_map(|b| match b {
+                                ContentBlock::Image {
+                                    source:
+                                        ImageSource {
+                                            media_type, data, ..
+                                        },
+                                } => Some(json!({
+                                    "type": "input_image",
+                                    "source": {
+                                        "type": "base64",
+                                        "media_type": media_type,
+                                        "data": data,
+                                    }
+                                })),
+                                _ => None,
+                            })
+                            .collect();
+                        if emitted_any_tool && !image_parts.is_empty() {
+                            out.push(json!({
+                                "type": "message",
+                                "role": "user",
+                                "content": image_parts,
+                            }));
+                        }
                     } else {
                         pending_tool_ids.clear();
                         let has_images = blocks
@@ -2137,6 +2520,7 @@ fn translate_oai_responses_response(resp: OaiResponsesResponse) -> MessagesRespo
         usage: resp.usage.map(|usage| Usage {
             input_tokens: usage.input_tokens,
             output_tokens: usage.output_tokens,
+            ..Default::default()
         }),
     }
 }
@@ -2211,6 +2595,7 @@ fn translate_oai_response(oai: OaiResponse) -> MessagesResponse {
     let usage = oai.usage.map(|u| Usage {
         input_tokens: u.prompt_tokens,
         output_tokens: u.completion_tokens,
+        ..Default::default()
     });
 
     MessagesResponse {
@@ -2386,6 +2771,52 @@ mod tests {
         assert_eq!(out[1]["content"], "file1.rs\nfile2.rs");
     }
 
+    #[test]
+    fn test_translate_messages_tool_result_images_follow_as_user_message() {
+        let msgs = vec![
+            Message {
+                role: "assistant".into(),
+                content: MessageContent::Blocks(vec![ContentBlock::ToolUse {
+                    id: "t1".into(),
+                    name: "web_fetch".into(),
+                    input: json!({}),
+                    thought_signature: None,
+                }]),
+            },
+            Message {
+                role: "user".into(),
+                content: MessageContent::Blocks(vec![
+                    ContentBlock::ToolResult {
+                        tool_use_id: "t1".into(),
+                        content: "Fetched image/png image".into(),
+                        is_error: None,
+                    },
+                    ContentBlock::Image {
+                        source: ImageSource {
+                            source_type: "base64".into(),
+                            media_type: "image/png".into(),
+                            data: "AAAA".into(),
+                        },
+                    },
+                ]),
+            },
+        ];
+        let out = translate_messages_to_oai("", &msgs);
+        assert_eq!(out.len(), 3);
+        assert_eq!(out[1]["role"], "tool");
+        assert_eq!(out[2]["role"], "user");
+        assert_eq!(
+            out[2]["content"][0]["image_url"]["url"],
+            "data:image/png;base64,AAAA"
+        );
+
+        let out = translate_messages_to_oai_responses_input(&msgs);
+        assert_eq!(out.len(), 3);
+        assert_eq!(out[1]["type"], "function_call_output");
+        assert_eq!(out[2]["role"], "user");
+        assert_eq!(out[2]["content"][0]["type"], "input_image");
+    }
+
     #[test]
     fn test_translate_messages_tool_result_error() {
         let msgs = vec![
@@ -2813,6 +3244,7 @@ mod tests {
             vec![],
             std::collections::HashMap::new(),
             std::collections::HashMap::new(),
+            std::collections::HashMap::new(),
             Some("tool_calls".into()),
             None,
         );
@@ -3227,6 +3659,7 @@ mod tests {
         let resp = build_stream_response(
             vec![0],
             std::collections::HashMap::new(),
+            std::collections::HashMap::new(),
             tool_blocks,
             Some("tool_use".into()),
             None,
@@ -3278,6 +3711,19 @@ mod tests {
         let _provider = create_provider(&config);
     }
 
+    #[test]
+    fn test_create_provider_gemini() {
+        let mut config = Config::test_defaults();
+        config.llm_provider = "gemini".into();
+        config.model = "gemini-2.5-flash".into();
+        config.data_dir = "/tmp".into();
+        config.working_dir = "/tmp".into();
+        config.working_dir_isolation = WorkingDirIsolation::Shared;
+        config.web_enabled = false;
+        config.web_port = 3900;
+        let _provider = create_provider(&config);
+    }
+
     #[tokio::test]
     #[allow(clippy::await_holding_lock)]
     async fn test_openai_codex_stream_uses_responses_endpoint() {
@@ -3667,6 +4113,255 @@ mod tests {
         assert_eq!(base, "https://api.openai.com/v1");
     }
 
+    #[test]
+    fn test_anthropic_request_body_adds_cache_breakpoints() {
+        let request = MessagesRequest {
+            model: "claude-test".into(),
+            max_tokens: 100,
+            system: "You are helpful.".into(),
+            messages: vec![
+                Message {
+                    role: "user".into(),
+                    content: MessageContent::Text("hello".into()),
+                },
+                Message {
+                    role: "assistant".into(),
+                    content: MessageContent::Blocks(vec![ContentBlock::ToolUse {
+                        id: "t1".into(),
+                        name: "bash".into(),
+                        input: json!({"command": "ls"}),
+                        thought_signature: None,
+                    }]),
+                },
+                Message {
+                    role: "user".into(),
+                    content: MessageContent::Blocks(vec![ContentBlock::ToolResult {
+                        tool_use_id: "t1".into(),
+                        content: "ok".into(),
+                        is_error: None,
+                    }]),
+                },
+            ],
+            tools: Some(vec![
+                ToolDefinition {
+                    name: "a".into(),
+                    description: "A".into(),
+                    input_schema: json!({"type": "object"}),
+                },
+                ToolDefinition {
+                    name: "b".into(),
+                    description: "B".into(),
+                    input_schema: json!({"type": "object"}),
+                },
+            ]),
+            stream: None,
+        };
+
+        let body = anthropic_request_body(&request, true, None);
+        assert_eq!(body["system"][0]["text"], "You are helpful.");
+        assert_eq!(body["system"][0]["cache_control"]["type"], "ephemeral");
+        assert!(body["tools"][0].get("cache_control").is_none());
+        assert_eq!(body["tools"][1]["cache_control"]["type"], "ephemeral");
+        assert!(body["messages"][0]["content"].is_string());
+        assert!(body["messages"][1]["content"][0]
+            .get("cache_control")
+            .is_none());
+        assert_eq!(
+            body["messages"][2]["content"][0]["cache_control"]["type"],
+            "ephemeral"
+        );
+
+        let plain = anthropic_request_body(&request, false, None);
+        assert_eq!(plain["system"], "You are helpful.");
+        assert!(plain["tools"][1].get("cache_control").is_none());
+    }
+
+    #[test]
+    fn test_structured_requests_force_tool_use() {
+        let tool = |name: &str| ToolDefinition {
+            name: name.into(),
+            description: String::new(),
+            input_schema: json!({"type": "object"}),
+        };
+        let mut request = MessagesRequest {
+            model: "claude-test".into(),
+            max_tokens: 4096,
+            system: String::new(),
+            messages: vec![Message {
+                role: "user".into(),
+                content: MessageContent::Text("hi".into()),
+            }],
+            tools: Some(vec![tool("bash")]),
+            stream: None,
+        };
+        assert!(anthropic_request_body(&request, false, None)
+            .get("tool_choice")
+            .is_none());
+        assert_eq!(oai_tool_choice(&[tool("bash")]), None);
+
+        request.tools = Some(vec![tool("bash"), tool(FINAL_ANSWER_TOOL_NAME)]);
+        assert_eq!(
+            anthropic_request_body(&request, false, None)["tool_choice"],
+            json!({"type": "any"})
+        );
+        assert_eq!(
+            oai_tool_choice(request.tools.as_ref().unwrap()),
+            Some(json!("required"))
+        );
+
+        request.tools = Some(vec![tool(FINAL_ANSWER_TOOL_NAME)]);
+        assert_eq!(
+            anthropic_request_body(&request, false, None)["tool_choice"],
+            json!({"type": "tool", "name": "final_answer"})
+        );
+        assert_eq!(
+            oai_tool_choice(request.tools.as_ref().unwrap()),
+            Some(json!({"type": "function", "function": {"name": "final_answer"}}))
+        );
+        // Extended thinking only supports automatic tool choice.
+        assert!(anthropic_request_body(&request, false, Some(2048))
+            .get("tool_choice")
+            .is_none());
+    }
+
+    #[test]
+    fn test_anthropic_request_body_wraps_trailing_text_message() {
+        let request = MessagesRequest {
+            model: "claude-test".into(),
+            max_tokens: 100,
+            system: String::new(),
+            messages: vec![Message {
+                role: "user".into(),
+                content: MessageContent::Text("hi".into()),
+            }],
+            tools: None,
+            stream: None,
+        };
+        let body = anthropic_request_body(&request, true, None);
+        assert_eq!(body["system"], "");
+        assert_eq!(body["messages"][0]["content"][0]["text"], "hi");
+        assert_eq!(
+            body["messages"][0]["content"][0]["cache_control"]["type"],
+            "ephemeral"
+        );
+    }
+
+    #[test]
+    fn test_anthropic_request_body_thinking_budget_and_replayed_blocks() {
+        let request = MessagesRequest {
+            model: "claude-test".into(),
+            max_tokens: 4096,
+            system: String::new(),
+            messages: vec![Message {
+                role: "assistant".into(),
+                content: MessageContent::Blocks(vec![
+                    ContentBlock::Thinking {
+                        thinking: "signed".into(),
+                        signature: Some("sig".into()),
+                    },
+                    ContentBlock::Thinking {
+                        thinking: "unsigned".into(),
+                        signature: None,
+                    },
+                    ContentBlock::RedactedThinking {
+                        data: "opaque".into(),
+                    },
+                    ContentBlock::Text {
+                        text: "answer".into(),
+                    },
+                ]),
+            }],
+            tools: None,
+            stream: None,
+        };
+
+        let body = anthropic_request_body(&request, false, Some(500));
+        assert_eq!(body["thinking"]["type"], "enabled");
+        assert_eq!(body["thinking"]["budget_tokens"], 1024);
+        let blocks = body["messages"][0]["content"].as_array().unwrap();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0]["signature"], "sig");
+        assert_eq!(blocks[1]["type"], "redacted_thinking");
+
+        let body = anthropic_request_body(&request, false, Some(100_000));
+        assert_eq!(body["thinking"]["budget_tokens"], 4095);
+
+        let body = anthropic_request_body(&request, false, None);
+        assert!(body.get("thinking").is_none());
+        let blocks = body["messages"][0]["content"].as_array().unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0]["type"], "text");
+    }
+
+    #[test]
+    fn test_anthropic_stream_collects_thinking_blocks() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut state = AnthropicStreamState::default();
+        for event in [
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Let me "}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"check."}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"sig_abc"}}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"redacted_thinking","data":"opaque"}}"#,
+            r#"{"type":"content_block_start","index":2,"content_block":{"type":"tool_use","id":"t1","name":"bash","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":2,"delta":{"type":"input_json_delta","partial_json":"{\"command\":\"ls\"}"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"}}"#,
+        ] {
+            process_anthropic_stream_event(event, None, Some(&tx), &mut state);
+        }
+        drop(tx);
+        let mut streamed = String::new();
+        while let Ok(piece) = rx.try_recv() {
+            streamed.push_str(&piece);
+        }
+        assert_eq!(streamed, "Let me check.");
+
+        let resp = build_stream_response(
+            state.ordered_indexes,
+            state.text_blocks,
+            state.thinking_blocks,
+            state.tool_blocks,
+            state.stop_reason,
+            state.usage,
+        );
+        assert_eq!(resp.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(resp.content.len(), 3);
+        match &resp.content[0] {
+            ResponseContentBlock::Thinking {
+                thinking,
+                signature,
+            } => {
+                assert_eq!(thinking, "Let me check.");
+                assert_eq!(signature.as_deref(), Some("sig_abc"));
+            }
+            other => panic!("expected thinking block, got {other:?}"),
+        }
+        assert!(matches!(
+            &resp.content[1],
+            ResponseContentBlock::RedactedThinking { data } if data == "opaque"
+        ));
+        assert!(matches!(
+            &resp.content[2],
+            ResponseContentBlock::ToolUse { name, .. } if name == "bash"
+        ));
+    }
+
+    #[test]
+    fn test_anthropic_stream_usage_keeps_cache_counts_from_message_start() {
+        let mut state = AnthropicStreamState::default();
+        for event in [
+            r#"{"type":"message_start","message":{"usage":{"input_tokens":12,"output_tokens":1,"cache_creation_input_tokens":300,"cache_read_input_tokens":2000}}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":42}}"#,
+        ] {
+            process_anthropic_stream_event(event, None, None, &mut state);
+        }
+        let usage = state.usage.unwrap();
+        assert_eq!(usage.input_tokens, 12);
+        assert_eq!(usage.output_tokens, 42);
+        assert_eq!(usage.cache_creation_input_tokens, 300);
+        assert_eq!(usage.cache_read_input_tokens, 2000);
+    }
+
     #[test]
     fn test_resolve_anthropic_messages_url_defaults() {
         let url = resolve_anthropic_messages_url("");
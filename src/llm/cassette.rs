@@ -0,0 +1,953 @@
+//! Record/replay of LLM traffic for regression tests and offline debugging.
+//!
+//! [`RecordingProvider`] wraps a live provider and appends every call — request,
+//! streamed text/thinking deltas, and the response or error — as one JSON line to
+//! a cassette file. [`ReplayProvider`] serves those exchanges back without any
+//! network access, checking each incoming request against the recorded one.
+//! `create_provider` picks either one from the `llm_cassette` config section or
+//! the `MICROCLAW_LLM_CASSETTE` env var.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex as StdMutex, OnceLock};
+
+use serde::Serialize;
+
+use super::*;
+use crate::config::LlmCassetteMode;
+use microclaw_core::error::LlmErrorKind;
+
+/// `record:<path>` or `replay:<path>`; takes precedence over `llm_cassette`.
+pub const CASSETTE_ENV_VAR: &str = "MICROCLAW_LLM_CASSETTE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CassetteDeltaKind {
+    Text,
+    Thinking,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteDelta {
+    pub kind: CassetteDeltaKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteRequest {
+    pub system: String,
+    pub messages: Vec<Message>,
+    /// Tool names offered to the model; schemas are left out to keep cassettes small.
+    #[serde(default)]
+    pub tools: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+static UUID_RE: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(
+        r"(?i)[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}|[0-9a-f]{32}",
+    )
+    .expect("valid uuid pattern")
+});
+static DIGITS_RE: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"\d+").expect("valid digits pattern"));
+
+/// Blank out the parts of a prompt that change from run to run (timestamps,
+/// ids, temp paths) so two runs of the same scenario compare equal.
+fn normalize_volatile(text: &str) -> String {
+    let text = UUID_RE.replace_all(text, "<id>");
+    DIGITS_RE.replace_all(&text, "0").into_owned()
+}
+
+impl CassetteRequest {
+    fn new(
+        system: &str,
+        messages: &[Message],
+        tools: Option<&[ToolDefinition]>,
+        model_override: Option<&str>,
+    ) -> Self {
+        CassetteRequest {
+            system: system.to_string(),
+            messages: messages.to_vec(),
+            tools: tools
+                .into_iter()
+                .flatten()
+                .map(|tool| tool.name.clone())
+                .collect(),
+            model: model_override.map(str::to_string),
+        }
+    }
+
+    /// Why `incoming` is not the call this request was recorded for, comparing
+    /// tool names, the last message and the normalized system prompt.
+    fn mismatch(&self, incoming: &CassetteRequest) -> Option<String> {
+        let mut recorded_tools = self.tools.clone();
+        let mut incoming_tools = incoming.tools.clone();
+        recorded_tools.sort();
+        incoming_tools.sort();
+        if recorded_tools != incoming_tools {
+            return Some(format!(
+                "tools differ: recorded [{}], got [{}]",
+                recorded_tools.join(", "),
+                incoming_tools.join(", ")
+            ));
+        }
+        let last_message = |req: &CassetteRequest| {
+            req.messages
+                .last()
+                .and_then(|m| serde_json::to_string(m).ok())
+                .map(|raw| normalize_volatile(&raw))
+                .unwrap_or_default()
+        };
+        if last_message(self) != last_message(incoming) {
+            return Some(format!(
+                "last message differs: recorded {}, got {}",
+                truncate_for_error(&last_message(self)),
+                truncate_for_error(&last_message(incoming))
+            ));
+        }
+        let recorded_system = normalize_volatile(&self.system);
+        let incoming_system = normalize_volatile(&incoming.system);
+        if recorded_system != incoming_system {
+            let line = recorded_system
+                .lines()
+                .zip(incoming_system.lines())
+                .position(|(a, b)| a != b)
+                .unwrap_or_else(|| {
+                    recorded_system
+                        .lines()
+                        .count()
+                        .min(incoming_system.lines().count())
+                });
+            return Some(format!("system prompt differs at line {}", line + 1));
+        }
+        None
+    }
+}
+
+fn truncate_for_error(text: &str) -> String {
+    const MAX_CHARS: usize = 200;
+    if text.chars().count() <= MAX_CHARS {
+        return text.to_string();
+    }
+    let head: String = text.chars().take(MAX_CHARS).collect();
+    format!("{head}...")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CassetteError {
+    RateLimited,
+    LlmApi {
+        message: String,
+    },
+    /// A classified provider error; `class` keeps failover decisions identical on replay.
+    Upstream {
+        class: LlmErrorKind,
+        message: String,
+    },
+}
+
+impl From<&MicroClawError> for CassetteError {
+    fn from(err: &MicroClawError) -> Self {
+        match err {
+            MicroClawError::RateLimited => CassetteError::RateLimited,
+            MicroClawError::LlmApi(message) => CassetteError::LlmApi {
+                message: message.clone(),
+            },
+            MicroClawError::LlmUpstream { kind, message } => CassetteError::Upstream {
+                class: *kind,
+                message: message.clone(),
+            },
+            other => CassetteError::LlmApi {
+                message: other.to_string(),
+            },
+        }
+    }
+}
+
+impl From<CassetteError> for MicroClawError {
+    fn from(err: CassetteError) -> Self {
+        match err {
+            CassetteError::RateLimited => MicroClawError::RateLimited,
+            CassetteError::LlmApi { message } => MicroClawError::LlmApi(message),
+            CassetteError::Upstream { class, message } => MicroClawError::LlmUpstream {
+                kind: class,
+                message,
+            },
+        }
+    }
+}
+
+/// One recorded provider call.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CassetteEntry {
+    pub request: CassetteRequest,
+    /// Streamed deltas in arrival order; empty for non-streaming calls.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deltas: Vec<CassetteDelta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<MessagesResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<CassetteError>,
+}
+
+/// Cassette file shared by every provider in the process that points at the same
+/// path, so scoped providers (sub-agents, per-chat profiles, compaction) record into
+/// and replay from one stream.
+pub struct Cassette {
+    path: PathBuf,
+    write_lock: StdMutex<()>,
+    /// Unserved entries in replay mode, loaded on first use.
+    pending: StdMutex<Option<VecDeque<CassetteEntry>>>,
+}
+
+impl Cassette {
+    pub fn shared(path: &Path) -> Arc<Cassette> {
+        static CASSETTES: OnceLock<StdMutex<HashMap<PathBuf, Arc<Cassette>>>> = OnceLock::new();
+        let mut cassettes = CASSETTES
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        cassettes
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                Arc::new(Cassette {
+                    path: path.to_path_buf(),
+                    write_lock: StdMutex::new(()),
+                    pending: StdMutex::new(None),
+                })
+            })
+            .clone()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn append(&self, entry: &CassetteEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())
+    }
+
+    /// Take the earliest unserved entry recorded for `request`, so concurrent
+    /// callers each get their own exchanges. When none matches, a strict replay
+    /// fails with the difference from the next entry; a lenient one logs it and
+    /// serves that entry anyway.
+    fn next_entry(
+        &self,
+        request: &CassetteRequest,
+        strict: bool,
+    ) -> Result<CassetteEntry, MicroClawError> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if pending.is_none() {
+            *pending = Some(load_cassette(&self.path)?);
+        }
+        let Some(entries) = pending.as_mut().filter(|entries| !entries.is_empty()) else {
+            return Err(MicroClawError::LlmApi(format!(
+                "replay cassette {} has no more recorded responses",
+                self.path.display()
+            )));
+        };
+        if let Some(idx) = entries
+            .iter()
+            .position(|entry| entry.request.mismatch(request).is_none())
+        {
+            return Ok(entries.remove(idx).expect("index from position"));
+        }
+        let reason = entries[0].request.mismatch(request).unwrap_or_default();
+        if strict {
+            return Err(MicroClawError::LlmApi(format!(
+                "replay cassette {}: request does not match any recorded entry ({reason})",
+                self.path.display()
+            )));
+        }
+        warn!(
+            "Replay cassette {}: request does not match the recording ({reason}); serving the next entry",
+            self.path.display()
+        );
+        Ok(entries.pop_front().expect("non-empty entries"))
+    }
+}
+
+fn load_cassette(path: &Path) -> Result<VecDeque<CassetteEntry>, MicroClawError> {
+    let raw = std::fs::read_to_string(path).map_err(|e| {
+        MicroClawError::LlmApi(format!(
+            "failed to read replay cassette {}: {e}",
+            path.display()
+        ))
+    })?;
+    raw.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                MicroClawError::LlmApi(format!(
+                    "invalid replay cassette {} line {}: {e}",
+                    path.display(),
+                    idx + 1
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Cassette mode and path from `MICROCLAW_LLM_CASSETTE`, falling back to config.
+pub fn cassette_settings(config: &Config) -> Option<(LlmCassetteMode, PathBuf)> {
+    if let Ok(raw) = std::env::var(CASSETTE_ENV_VAR) {
+        match parse_cassette_env(&raw) {
+            Some(settings) => return Some(settings),
+            None if !raw.trim().is_empty() => {
+                warn!(
+                    "Ignoring {CASSETTE_ENV_VAR}={raw:?}; expected record:<path> or replay:<path>"
+                );
+            }
+            None => {}
+        }
+    }
+    match (
+        config.llm_cassette.mode,
+        config.llm_cassette.path.as_deref(),
+    ) {
+        (LlmCassetteMode::Off, _) | (_, None) => None,
+        (mode, Some(path)) => Some((mode, PathBuf::from(path))),
+    }
+}
+
+fn parse_cassette_env(raw: &str) -> Option<(LlmCassetteMode, PathBuf)> {
+    let (mode, path) = raw.trim().split_once(':')?;
+    let mode = match mode.trim().to_ascii_lowercase().as_str() {
+        "record" => LlmCassetteMode::Record,
+        "replay" => LlmCassetteMode::Replay,
+        _ => return None,
+    };
+    let path = path.trim();
+    (!path.is_empty()).then(|| (mode, PathBuf::from(path)))
+}
+
+/// Forwards deltas from the provider to the caller's channel while recording them.
+fn tap_deltas(
+    target: Option<&UnboundedSender<String>>,
+    kind: CassetteDeltaKind,
+    recorded: &UnboundedSender<CassetteDelta>,
+) -> (
+    Option<UnboundedSender<String>>,
+    Option<tokio::task::JoinHandle<()>>,
+) {
+    let Some(target) = target.cloned() else {
+        return (None, None);
+    };
+    let recorded = recorded.clone();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let handle = tokio::spawn(async move {
+        while let Some(text) = rx.recv().await {
+            let _ = recorded.send(CassetteDelta {
+                kind,
+                text: text.clone(),
+            });
+            let _ = target.send(text);
+        }
+    });
+    (Some(tx), Some(handle))
+}
+
+pub struct RecordingProvider {
+    inner: Box<dyn LlmProvider>,
+    cassette: Arc<Cassette>,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Box<dyn LlmProvider>, path: &Path) -> Self {
+        RecordingProvider {
+            inner,
+            cassette: Cassette::shared(path),
+        }
+    }
+
+    async fn record(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+        thinking_tx: Option<&UnboundedSender<String>>,
+        model_override: Option<&str>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let request = CassetteRequest::new(system, &messages, tools.as_deref(), model_override);
+
+        let (delta_tx, mut delta_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (tap_text, text_handle) = tap_deltas(text_tx, CassetteDeltaKind::Text, &delta_tx);
+        let (tap_thinking, thinking_handle) =
+            tap_deltas(thinking_tx, CassetteDeltaKind::Thinking, &delta_tx);
+        drop(delta_tx);
+
+        let result = if tap_text.is_some() || tap_thinking.is_some() {
+            self.inner
+                .send_message_stream_with_thinking(
+                    system,
+                    messages,
+                    tools,
+                    tap_text.as_ref(),
+                    tap_thinking.as_ref(),
+                    model_override,
+                )
+                .await
+        } else {
+            self.inner
+                .send_message_with_model(system, messages, tools, model_override)
+                .await
+        };
+
+        drop(tap_text);
+        drop(tap_thinking);
+        for handle in [text_handle, thinking_handle].into_iter().flatten() {
+            let _ = handle.await;
+        }
+        let mut deltas = Vec::new();
+        while let Ok(delta) = delta_rx.try_recv() {
+            deltas.push(delta);
+        }
+
+        let (response, error) = match result {
+            Ok(response) => (Some(response), None),
+            Err(e) => (None, Some(CassetteError::from(&e))),
+        };
+        let entry = CassetteEntry {
+            request,
+            deltas,
+            response,
+            error,
+        };
+        if let Err(e) = self.cassette.append(&entry) {
+            warn!(
+                "Failed to append to LLM cassette {}: {e}",
+                self.cassette.path().display()
+            );
+        }
+        match (entry.response, entry.error) {
+            (Some(response), _) => Ok(response),
+            (None, Some(error)) => Err(error.into()),
+            (None, None) => unreachable!("recorded entry has a response or an error"),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for RecordingProvider {
+    async fn send_message(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.record(system, messages, tools, None, None, None).await
+    }
+
+    async fn send_message_with_model(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        model_override: Option<&str>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.record(system, messages, tools, None, None, model_override)
+            .await
+    }
+
+    async fn send_message_stream(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.record(system, messages, tools, text_tx, None, None)
+            .await
+    }
+
+    async fn send_message_stream_with_model(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+        model_override: Option<&str>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.record(system, messages, tools, text_tx, None, model_override)
+            .await
+    }
+
+    async fn send_message_stream_with_thinking(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+        thinking_tx: Option<&UnboundedSender<String>>,
+        model_override: Option<&str>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.record(
+            system,
+            messages,
+            tools,
+            text_tx,
+            thinking_tx,
+            model_override,
+        )
+        .await
+    }
+}
+
+/// Serves recorded exchanges for matching requests. A request matches when its
+/// tool names, last message and system prompt equal the recorded ones once
+/// timestamps and ids are blanked out, so a regression that changes what the
+/// model is sent fails replay instead of passing silently.
+pub struct ReplayProvider {
+    cassette: Arc<Cassette>,
+    strict: bool,
+}
+
+impl ReplayProvider {
+    pub fn new(path: &Path) -> Self {
+        ReplayProvider {
+            cassette: Cassette::shared(path),
+            strict: true,
+        }
+    }
+
+    /// Warn on a request mismatch and serve the next entry instead of failing.
+    pub fn with_lenient_matching(mut self, lenient: bool) -> Self {
+        self.strict = !lenient;
+        self
+    }
+
+    fn replay(
+        &self,
+        request: CassetteRequest,
+        text_tx: Option<&UnboundedSender<String>>,
+        thinking_tx: Option<&UnboundedSender<String>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let entry = self.cassette.next_entry(&request, self.strict)?;
+        for delta in &entry.deltas {
+            let tx = match delta.kind {
+                CassetteDeltaKind::Text => text_tx,
+                CassetteDeltaKind::Thinking => thinking_tx,
+            };
+            if let Some(tx) = tx {
+                let _ = tx.send(delta.text.clone());
+            }
+        }
+        match (entry.response, entry.error) {
+            (Some(response), _) => {
+                // Recorded without streaming but replayed to a streaming caller.
+                if entry.deltas.is_empty() {
+                    if let Some(tx) = text_tx {
+                        for block in &response.content {
+                            if let ResponseContentBlock::Text { text } = block {
+                                let _ = tx.send(text.clone());
+                            }
+                        }
+                    }
+                }
+                Ok(response)
+            }
+            (None, Some(error)) => Err(error.into()),
+            (None, None) => Err(MicroClawError::LlmApi(format!(
+                "replay cassette {} has an entry without response or error",
+                self.cassette.path().display()
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ReplayProvider {
+    async fn send_message(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let request = CassetteRequest::new(system, &messages, tools.as_deref(), None);
+        self.replay(request, None, None)
+    }
+
+    async fn send_message_stream(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let request = CassetteRequest::new(system, &messages, tools.as_deref(), None);
+        self.replay(request, text_tx, None)
+    }
+
+    async fn send_message_stream_with_thinking(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+        thinking_tx: Option<&UnboundedSender<String>>,
+        model_override: Option<&str>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let request = CassetteRequest::new(system, &messages, tools.as_deref(), model_override);
+        self.replay(request, text_tx, thinking_tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct ScriptedProvider {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmProvider for ScriptedProvider {
+        async fn send_message(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            match self.calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Ok(MessagesResponse {
+                    content: vec![ResponseContentBlock::ToolUse {
+                        id: "t1".into(),
+                        name: "read_file".into(),
+                        input: json!({"path": "README.md"}),
+                        thought_signature: None,
+                    }],
+                    stop_reason: Some("tool_use".into()),
+                    usage: Some(Usage {
+                        input_tokens: 12,
+                        output_tokens: 3,
+                        ..Default::default()
+                    }),
+                }),
+                1 => Err(MicroClawError::RateLimited),
+                _ => Err(MicroClawError::LlmApi("boom".into())),
+            }
+        }
+
+        async fn send_message_stream_with_thinking(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+            text_tx: Option<&UnboundedSender<String>>,
+            thinking_tx: Option<&UnboundedSender<String>>,
+            _model_override: Option<&str>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            if let Some(tx) = thinking_tx {
+                let _ = tx.send("pondering".into());
+            }
+            if let Some(tx) = text_tx {
+                let _ = tx.send("Hel".into());
+                let _ = tx.send("lo".into());
+            }
+            Ok(MessagesResponse {
+                content: vec![ResponseContentBlock::Text {
+                    text: "Hello".into(),
+                }],
+                stop_reason: Some("end_turn".into()),
+                usage: None,
+            })
+        }
+    }
+
+    fn user(text: &str) -> Vec<Message> {
+        vec![Message {
+            role: "user".into(),
+            content: MessageContent::Text(text.into()),
+        }]
+    }
+
+    fn drain(rx: &mut tokio::sync::mpsc::UnboundedReceiver<String>) -> Vec<String> {
+        let mut out = Vec::new();
+        while let Ok(text) = rx.try_recv() {
+            out.push(text);
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_round_trip() {
+        let dir = std::env::temp_dir().join(format!("mc_cassette_{}", uuid::Uuid::new_v4()));
+        let path = dir.join("run.jsonl");
+        let recorder = RecordingProvider::new(
+            Box::new(ScriptedProvider {
+                calls: AtomicUsize::new(0),
+            }),
+            &path,
+        );
+        let tools = vec![ToolDefinition {
+            name: "read_file".into(),
+            description: "read".into(),
+            input_schema: json!({"type": "object"}),
+        }];
+
+        let first = recorder
+            .send_message_with_model("sys", user("hi"), Some(tools), Some("m1"))
+            .await
+            .unwrap();
+        assert_eq!(first.stop_reason.as_deref(), Some("tool_use"));
+        let (text_tx, mut text_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (thinking_tx, mut thinking_rx) = tokio::sync::mpsc::unbounded_channel();
+        recorder
+            .send_message_stream_with_thinking(
+                "sys",
+                user("stream"),
+                None,
+                Some(&text_tx),
+                Some(&thinking_tx),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(drain(&mut text_rx), vec!["Hel", "lo"]);
+        assert_eq!(drain(&mut thinking_rx), vec!["pondering"]);
+        assert!(matches!(
+            recorder.send_message("sys", user("again"), None).await,
+            Err(MicroClawError::RateLimited)
+        ));
+
+        let raw = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(raw.lines().count(), 3);
+        let first_line: serde_json::Value =
+            serde_json::from_str(raw.lines().next().unwrap()).unwrap();
+        assert_eq!(first_line["request"]["tools"], json!(["read_file"]));
+        assert_eq!(first_line["request"]["model"], "m1");
+
+        let replay = ReplayProvider::new(&path);
+        let tools = vec![ToolDefinition {
+            name: "read_file".into(),
+            description: "read".into(),
+            input_schema: json!({"type": "object"}),
+        }];
+        match replay
+            .send_message("sys", user("hi"), Some(tools))
+            .await
+            .unwrap()
+            .content
+            .as_slice()
+        {
+            [ResponseContentBlock::ToolUse { name, input, .. }] => {
+                assert_eq!(name, "read_file");
+                assert_eq!(input["path"], "README.md");
+            }
+            other => panic!("unexpected replay content: {other:?}"),
+        }
+        let (text_tx, mut text_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (thinking_tx, mut thinking_rx) = tokio::sync::mpsc::unbounded_channel();
+        let streamed = replay
+            .send_message_stream_with_thinking(
+                "sys",
+                user("stream"),
+                None,
+                Some(&text_tx),
+                Some(&thinking_tx),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            streamed.content.as_slice(),
+            [ResponseContentBlock::Text { text }] if text == "Hello"
+        ));
+        assert_eq!(drain(&mut text_rx), vec!["Hel", "lo"]);
+        assert_eq!(drain(&mut thinking_rx), vec!["pondering"]);
+        assert!(matches!(
+            replay.send_message("sys", user("again"), None).await,
+            Err(MicroClawError::RateLimited)
+        ));
+        let exhausted = replay.send_message("sys", user("again"), None).await;
+        assert!(matches!(
+            exhausted,
+            Err(MicroClawError::LlmApi(msg)) if msg.contains("no more recorded responses")
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cassette_error_keeps_upstream_classification() {
+        let err = MicroClawError::llm_status(503, "HTTP 503: busy");
+        let line = serde_json::to_string(&CassetteError::from(&err)).unwrap();
+        let replayed: MicroClawError = serde_json::from_str::<CassetteError>(&line).unwrap().into();
+        assert!(replayed.is_retryable());
+        assert_eq!(replayed.to_string(), err.to_string());
+    }
+
+    fn write_cassette(path: &Path, entries: &[(&str, &str, &str)]) {
+        let lines: String = entries
+            .iter()
+            .map(|(system, message, reply)| {
+                let entry = CassetteEntry {
+                    request: CassetteRequest::new(system, &user(message), None, None),
+                    deltas: Vec::new(),
+                    response: Some(MessagesResponse {
+                        content: vec![ResponseContentBlock::Text {
+                            text: reply.to_string(),
+                        }],
+                        stop_reason: Some("end_turn".into()),
+                        usage: None,
+                    }),
+                    error: None,
+                };
+                format!("{}\n", serde_json::to_string(&entry).unwrap())
+            })
+            .collect();
+        std::fs::write(path, lines).unwrap();
+    }
+
+    fn reply_text(resp: MessagesResponse) -> String {
+        match resp.content.as_slice() {
+            [ResponseContentBlock::Text { text }] => text.clone(),
+            other => panic!("unexpected replay content: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_fails_when_request_changed() {
+        let path = std::env::temp_dir().join(format!(
+            "mc_cassette_mismatch_{}.jsonl",
+            uuid::Uuid::new_v4()
+        ));
+        write_cassette(
+            &path,
+            &[("Now: 2026-01-01T10:00:00Z\nBe brief.", "hi", "hello")],
+        );
+
+        // Timestamps are blanked out before comparing.
+        let replay = ReplayProvider::new(&path);
+        let resp = replay
+            .send_message("Now: 2026-10-17T08:30:12Z\nBe brief.", user("hi"), None)
+            .await
+            .unwrap();
+        assert_eq!(reply_text(resp), "hello");
+        let _ = std::fs::remove_file(&path);
+
+        let path = std::env::temp_dir().join(format!(
+            "mc_cassette_mismatch_{}.jsonl",
+            uuid::Uuid::new_v4()
+        ));
+        write_cassette(
+            &path,
+            &[("Now: 2026-01-01T10:00:00Z\nBe brief.", "hi", "hello")],
+        );
+        let replay = ReplayProvider::new(&path);
+        let err = replay
+            .send_message("Now: 2026-01-01T10:00:00Z\nBe verbose.", user("hi"), None)
+            .await;
+        assert!(matches!(
+            err,
+            Err(MicroClawError::LlmApi(msg)) if msg.contains("system prompt differs at line 2")
+        ));
+        let tools = vec![ToolDefinition {
+            name: "bash".into(),
+            description: "run".into(),
+            input_schema: json!({"type": "object"}),
+        }];
+        let err = replay
+            .send_message(
+                "Now: 2026-01-01T10:00:00Z\nBe brief.",
+                user("hi"),
+                Some(tools),
+            )
+            .await;
+        assert!(matches!(
+            err,
+            Err(MicroClawError::LlmApi(msg)) if msg.contains("tools differ")
+        ));
+        let err = replay
+            .send_message("Now: 2026-01-01T10:00:00Z\nBe brief.", user("bye"), None)
+            .await;
+        assert!(matches!(
+            err,
+            Err(MicroClawError::LlmApi(msg)) if msg.contains("last message differs")
+        ));
+
+        // Lenient replay serves the next entry anyway.
+        let lenient = ReplayProvider::new(&path).with_lenient_matching(true);
+        let resp = lenient
+            .send_message("something else", user("bye"), None)
+            .await
+            .unwrap();
+        assert_eq!(reply_text(resp), "hello");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_replay_serves_each_caller_its_own_entry() {
+        let path = std::env::temp_dir().join(format!(
+            "mc_cassette_interleaved_{}.jsonl",
+            uuid::Uuid::new_v4()
+        ));
+        write_cassette(
+            &path,
+            &[
+                ("main", "question", "main answer"),
+                ("compaction", "summarize", "summary"),
+            ],
+        );
+        let replay = ReplayProvider::new(&path);
+        let summary = replay
+            .send_message("compaction", user("summarize"), None)
+            .await
+            .unwrap();
+        assert_eq!(reply_text(summary), "summary");
+        let answer = replay
+            .send_message("main", user("question"), None)
+            .await
+            .unwrap();
+        assert_eq!(reply_text(answer), "main answer");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_replay_missing_cassette_errors() {
+        let path = std::env::temp_dir().join(format!(
+            "mc_cassette_missing_{}.jsonl",
+            uuid::Uuid::new_v4()
+        ));
+        let replay = ReplayProvider::new(&path);
+        let err = replay.send_message("sys", user("hi"), None).await;
+        assert!(matches!(
+            err,
+            Err(MicroClawError::LlmApi(msg)) if msg.contains("failed to read replay cassette")
+        ));
+    }
+
+    #[test]
+    fn test_parse_cassette_env() {
+        assert_eq!(
+            parse_cassette_env("record:/tmp/a.jsonl"),
+            Some((LlmCassetteMode::Record, PathBuf::from("/tmp/a.jsonl")))
+        );
+        assert_eq!(
+            parse_cassette_env(" Replay : cassettes/b.jsonl "),
+            Some((LlmCassetteMode::Replay, PathBuf::from("cassettes/b.jsonl")))
+        );
+        assert_eq!(parse_cassette_env("replay:"), None);
+        assert_eq!(parse_cassette_env("/tmp/a.jsonl"), None);
+        assert_eq!(parse_cassette_env("off:/tmp/a.jsonl"), None);
+    }
+}
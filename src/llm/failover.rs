@@ -0,0 +1,633 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use super::*;
+use crate::config::LlmFailoverConfig;
+use crate::mcp::CircuitBreakerState;
+
+/// Process-wide health of `llm_providers` profiles, keyed by alias. A profile
+/// whose calls keep failing with retryable errors has its circuit opened for
+/// `circuit_breaker_cooldown_secs` and is skipped by [`LlmRoute::send`].
+#[derive(Default)]
+pub struct LlmHealthRegistry {
+    breakers: StdMutex<HashMap<String, CircuitBreakerState>>,
+}
+
+impl LlmHealthRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_breaker<R>(
+        &self,
+        alias: &str,
+        settings: &LlmFailoverConfig,
+        f: impl FnOnce(&mut CircuitBreakerState) -> R,
+    ) -> R {
+        let mut guard = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        let breaker = guard.entry(alias.to_string()).or_insert_with(|| {
+            CircuitBreakerState::new(
+                settings.circuit_breaker_threshold,
+                settings.circuit_breaker_cooldown_secs,
+            )
+        });
+        f(breaker)
+    }
+
+    /// Seconds until the circuit for `alias` closes, or `None` when calls are allowed.
+    pub fn open_for_secs(&self, alias: &str, settings: &LlmFailoverConfig) -> Option<u64> {
+        self.with_breaker(alias, settings, |b| b.check_ready(Instant::now()).err())
+    }
+
+    fn record_success(&self, alias: &str, settings: &LlmFailoverConfig) {
+        self.with_breaker(alias, settings, |b| b.record_success());
+    }
+
+    fn record_failure(&self, alias: &str, settings: &LlmFailoverConfig) -> bool {
+        self.with_breaker(alias, settings, |b| b.record_failure(Instant::now()))
+    }
+}
+
+/// Clients for non-default `llm_providers` profiles, built once per alias and
+/// shared by every request. The model is passed per call, so one client serves
+/// all of a profile's models.
+#[derive(Default)]
+pub struct LlmClientPool {
+    clients: StdMutex<HashMap<String, Arc<dyn LlmProvider>>>,
+}
+
+impl LlmClientPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The client for `alias`, built with `build` on first use.
+    pub fn get_or_build(
+        &self,
+        alias: &str,
+        build: impl FnOnce() -> Box<dyn LlmProvider>,
+    ) -> Arc<dyn LlmProvider> {
+        let mut guard = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        guard
+            .entry(alias.to_string())
+            .or_insert_with(|| Arc::from(build()))
+            .clone()
+    }
+}
+
+/// One candidate in a failover chain.
+pub struct LlmRouteTarget {
+    /// `llm_providers` alias, recorded as the serving provider in usage logs.
+    pub alias: String,
+    /// Provider protocol name (e.g. `anthropic`, `openai`).
+    pub provider: String,
+    pub model: String,
+    /// `None` reuses the route's default client (the process-wide `AppState::llm`).
+    client: Option<Arc<dyn LlmProvider>>,
+}
+
+impl LlmRouteTarget {
+    pub fn new(
+        alias: impl Into<String>,
+        provider: impl Into<String>,
+        model: impl Into<String>,
+        client: Option<Arc<dyn LlmProvider>>,
+    ) -> Self {
+        Self {
+            alias: alias.into(),
+            provider: provider.into(),
+            model: model.into(),
+            client,
+        }
+    }
+}
+
+pub struct RoutedResponse<'r> {
+    pub response: MessagesResponse,
+    pub target: &'r LlmRouteTarget,
+    /// Alias of the primary target when a fallback served the call.
+    pub fallback_from: Option<&'r str>,
+}
+
+/// Ordered chain of provider targets: the primary first, then its fallbacks.
+pub struct LlmRoute<'a> {
+    default_client: &'a dyn LlmProvider,
+    health: &'a LlmHealthRegistry,
+    settings: LlmFailoverConfig,
+    targets: Vec<LlmRouteTarget>,
+}
+
+impl<'a> LlmRoute<'a> {
+    pub fn new(
+        default_client: &'a dyn LlmProvider,
+        health: &'a LlmHealthRegistry,
+        settings: LlmFailoverConfig,
+        primary: LlmRouteTarget,
+    ) -> Self {
+        Self {
+            default_client,
+            health,
+            settings,
+            targets: vec![primary],
+        }
+    }
+
+    pub fn with_fallback(mut self, target: LlmRouteTarget) -> Self {
+        if !self.targets.iter().any(|t| t.alias == target.alias) {
+            self.targets.push(target);
+        }
+        self
+    }
+
+    pub fn primary(&self) -> &LlmRouteTarget {
+        &self.targets[0]
+    }
+
+    /// Send the request down the chain. Targets with an open circuit are
+    /// skipped (the primary is still tried if every circuit is open). Retryable
+    /// errors are retried with exponential backoff before moving to the next
+    /// target; rate limits move on without a retry here, since the provider
+    /// already retried them. Non-retryable errors, and any error after a stream
+    /// has emitted deltas, are returned immediately so no text is replayed.
+    pub async fn send(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: Option<&[ToolDefinition]>,
+        text_tx: Option<&UnboundedSender<String>>,
+        thinking_tx: Option<&UnboundedSender<String>>,
+    ) -> Result<RoutedResponse<'_>, MicroClawError> {
+        let mut candidates: Vec<usize> = (0..self.targets.len())
+            .filter(|&idx| {
+                match self
+                    .health
+                    .open_for_secs(&self.targets[idx].alias, &self.settings)
+                {
+                    Some(secs) => {
+                        warn!(
+                            "Skipping llm provider '{}': circuit open for {}s",
+                            self.targets[idx].alias, secs
+                        );
+                        false
+                    }
+                    None => true,
+                }
+            })
+            .collect();
+        if candidates.is_empty() {
+            candidates.push(0);
+        }
+
+        let mut last_err: Option<MicroClawError> = None;
+        for idx in candidates {
+            let target = &self.targets[idx];
+            let client = target.client.as_deref().unwrap_or(self.default_client);
+            let mut attempt: u32 = 0;
+            loop {
+                let emitted = Arc::new(AtomicBool::new(false));
+                let (attempt_text_tx, text_forward) = forward_deltas(text_tx, &emitted);
+                let (attempt_thinking_tx, thinking_forward) = forward_deltas(thinking_tx, &emitted);
+                let result = match (text_tx, thinking_tx) {
+                    (None, None) => {
+                        client
+                            .send_message_with_model(
+                                system,
+                                messages.to_vec(),
+                                tools.map(<[ToolDefinition]>::to_vec),
+                                Some(&target.model),
+                            )
+                            .await
+                    }
+                    _ => {
+                        client
+                            .send_message_stream_with_thinking(
+                                system,
+                                messages.to_vec(),
+                                tools.map(<[ToolDefinition]>::to_vec),
+                                attempt_text_tx.as_ref(),
+                                attempt_thinking_tx.as_ref(),
+                                Some(&target.model),
+                            )
+                            .await
+                    }
+                };
+                drop((attempt_text_tx, attempt_thinking_tx));
+                for forward in [text_forward, thinking_forward].into_iter().flatten() {
+                    let _ = forward.await;
+                }
+                match result {
+                    Ok(response) => {
+                        self.health.record_success(&target.alias, &self.settings);
+                        let fallback_from = (idx > 0).then(|| self.primary().alias.as_str());
+                        if let Some(primary) = fallback_from {
+                            info!(
+                                "LLM call served by fallback provider '{}' (primary '{}')",
+                                target.alias, primary
+                            );
+                        }
+                        return Ok(RoutedResponse {
+                            response,
+                            target,
+                            fallback_from,
+                        });
+                    }
+                    Err(e) if !e.is_retryable() => return Err(e),
+                    Err(e) if emitted.load(Ordering::SeqCst) => {
+                        self.health.record_failure(&target.alias, &self.settings);
+                        warn!(
+                            "LLM provider '{}' failed mid-stream, not retrying: {}",
+                            target.alias, e
+                        );
+                        return Err(e);
+                    }
+                    Err(e) => {
+                        if attempt < self.settings.max_retries && !e.is_rate_limited() {
+                            let delay = retry_delay(self.settings.retry_base_delay_ms, attempt);
+                            warn!(
+                                "LLM provider '{}' failed (attempt {}), retrying in {:?}: {}",
+                                target.alias,
+                                attempt + 1,
+                                delay,
+                                e
+                            );
+                            tokio::time::sleep(delay).await;
+                            attempt += 1;
+                            continue;
+                        }
+                        if self.health.record_failure(&target.alias, &self.settings) {
+                            warn!(
+                                "LLM provider '{}' circuit opened for {}s",
+                                target.alias, self.settings.circuit_breaker_cooldown_secs
+                            );
+                        }
+                        warn!("LLM provider '{}' exhausted retries: {}", target.alias, e);
+                        last_err = Some(e);
+                        break;
+                    }
+                }
+            }
+        }
+        Err(last_err
+            .unwrap_or_else(|| MicroClawError::LlmApi("No LLM provider available".to_string())))
+    }
+}
+
+/// Relay one attempt's deltas to the caller's channel, flagging `emitted` once
+/// anything has gone out.
+fn forward_deltas(
+    dest: Option<&UnboundedSender<String>>,
+    emitted: &Arc<AtomicBool>,
+) -> (
+    Option<UnboundedSender<String>>,
+    Option<tokio::task::JoinHandle<()>>,
+) {
+    let Some(dest) = dest.cloned() else {
+        return (None, None);
+    };
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let emitted = emitted.clone();
+    let task = tokio::spawn(async move {
+        while let Some(delta) = rx.recv().await {
+            emitted.store(true, Ordering::SeqCst);
+            let _ = dest.send(delta);
+        }
+    });
+    (Some(tx), Some(task))
+}
+
+fn retry_delay(base_ms: u64, attempt: u32) -> Duration {
+    Duration::from_millis(base_ms.saturating_mul(1u64 << attempt.min(6)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct ScriptedLlm {
+        calls: Arc<AtomicUsize>,
+        script: StdMutex<VecDeque<Result<MessagesResponse, MicroClawError>>>,
+    }
+
+    impl ScriptedLlm {
+        fn boxed(
+            script: Vec<Result<MessagesResponse, MicroClawError>>,
+        ) -> (Box<dyn LlmProvider>, Arc<AtomicUsize>) {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let llm = ScriptedLlm {
+                calls: calls.clone(),
+                script: StdMutex::new(script.into()),
+            };
+            (Box::new(llm), calls)
+        }
+    }
+
+    #[async_trait]
+    impl LlmProvider for ScriptedLlm {
+        async fn send_message(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.script
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_else(|| Err(MicroClawError::llm_status(503, "HTTP 503: script empty")))
+        }
+    }
+
+    fn ok(text: &str) -> Result<MessagesResponse, MicroClawError> {
+        Ok(MessagesResponse {
+            content: vec![ResponseContentBlock::Text {
+                text: text.to_string(),
+            }],
+            stop_reason: Some("end_turn".into()),
+            usage: None,
+        })
+    }
+
+    fn overloaded() -> Result<MessagesResponse, MicroClawError> {
+        Err(MicroClawError::llm_status(
+            529,
+            "overloaded_error: Overloaded",
+        ))
+    }
+
+    fn settings() -> LlmFailoverConfig {
+        LlmFailoverConfig {
+            max_retries: 1,
+            retry_base_delay_ms: 1,
+            circuit_breaker_threshold: 1,
+            circuit_breaker_cooldown_secs: 60,
+        }
+    }
+
+    fn text_of(routed: &RoutedResponse<'_>) -> String {
+        match &routed.response.content[0] {
+            ResponseContentBlock::Text { text } => text.clone(),
+            _ => String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retryable_error_is_retried_on_same_provider() {
+        let (primary, primary_calls) = ScriptedLlm::boxed(vec![overloaded(), ok("hi")]);
+        let (backup, backup_calls) = ScriptedLlm::boxed(vec![ok("backup")]);
+        let health = LlmHealthRegistry::new();
+        let route = LlmRoute::new(
+            primary.as_ref(),
+            &health,
+            settings(),
+            LlmRouteTarget::new("main", "anthropic", "m1", None),
+        )
+        .with_fallback(LlmRouteTarget::new(
+            "backup",
+            "openai",
+            "m2",
+            Some(backup.into()),
+        ));
+
+        let routed = route.send("sys", &[], None, None, None).await.unwrap();
+        assert_eq!(text_of(&routed), "hi");
+        assert_eq!(routed.target.alias, "main");
+        assert!(routed.fallback_from.is_none());
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(backup_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_and_opens_primary_circuit() {
+        let (primary, primary_calls) = ScriptedLlm::boxed(vec![overloaded(), overloaded()]);
+        let (backup, backup_calls) = ScriptedLlm::boxed(vec![ok("one"), ok("two")]);
+        let health = LlmHealthRegistry::new();
+        let route = LlmRoute::new(
+            primary.as_ref(),
+            &health,
+            settings(),
+            LlmRouteTarget::new("main", "anthropic", "m1", None),
+        )
+        .with_fallback(LlmRouteTarget::new(
+            "backup",
+            "openai",
+            "m2",
+            Some(backup.into()),
+        ));
+
+        let routed = route.send("sys", &[], None, None, None).await.unwrap();
+        assert_eq!(text_of(&routed), "one");
+        assert_eq!(routed.target.alias, "backup");
+        assert_eq!(routed.target.model, "m2");
+        assert_eq!(routed.fallback_from, Some("main"));
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 2);
+        assert!(health.open_for_secs("main", &settings()).is_some());
+
+        // Open circuit: the primary is skipped entirely on the next call.
+        let routed = route.send("sys", &[], None, None, None).await.unwrap();
+        assert_eq!(text_of(&routed), "two");
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(backup_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_client_pool_builds_each_profile_once() {
+        let pool = LlmClientPool::new();
+        let builds = AtomicUsize::new(0);
+        let build = || {
+            builds.fetch_add(1, Ordering::SeqCst);
+            ScriptedLlm::boxed(Vec::new()).0
+        };
+        let first = pool.get_or_build("backup", build);
+        let again = pool.get_or_build("backup", build);
+        assert!(Arc::ptr_eq(&first, &again));
+        pool.get_or_build("other", build);
+        assert_eq!(builds.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_error_is_returned_without_fallback() {
+        let (primary, primary_calls) = ScriptedLlm::boxed(vec![Err(MicroClawError::llm_status(
+            400,
+            "invalid_request_error: prompt is too long",
+        ))]);
+        let (backup, backup_calls) = ScriptedLlm::boxed(vec![ok("backup")]);
+        let health = LlmHealthRegistry::new();
+        let route = LlmRoute::new(
+            primary.as_ref(),
+            &health,
+            settings(),
+            LlmRouteTarget::new("main", "anthropic", "m1", None),
+        )
+        .with_fallback(LlmRouteTarget::new(
+            "backup",
+            "openai",
+            "m2",
+            Some(backup.into()),
+        ));
+
+        let err = route
+            .send("sys", &[], None, None, None)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("prompt is too long"));
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(backup_calls.load(Ordering::SeqCst), 0);
+        assert!(health.open_for_secs("main", &settings()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_primary_is_probed_when_every_circuit_is_open() {
+        let (primary, primary_calls) =
+            ScriptedLlm::boxed(vec![overloaded(), overloaded(), ok("ok")]);
+        let health = LlmHealthRegistry::new();
+        let route = LlmRoute::new(
+            primary.as_ref(),
+            &health,
+            settings(),
+            LlmRouteTarget::new("main", "anthropic", "m1", None),
+        );
+
+        assert!(route.send("sys", &[], None, None, None).await.is_err());
+        assert!(health.open_for_secs("main", &settings()).is_some());
+
+        let routed = route.send("sys", &[], None, None, None).await.unwrap();
+        assert_eq!(text_of(&routed), "ok");
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 3);
+        assert!(health.open_for_secs("main", &settings()).is_none());
+    }
+
+    /// Streams a partial delta, then fails with a retryable error.
+    struct StreamThenFailLlm {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl LlmProvider for StreamThenFailLlm {
+        async fn send_message(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            overloaded()
+        }
+
+        async fn send_message_stream(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+            text_tx: Option<&UnboundedSender<String>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if let Some(tx) = text_tx {
+                let _ = tx.send("Hel".to_string());
+            }
+            overloaded()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_failing_after_deltas_is_not_replayed() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let primary: Box<dyn LlmProvider> = Box::new(StreamThenFailLlm {
+            calls: calls.clone(),
+        });
+        let (backup, backup_calls) = ScriptedLlm::boxed(vec![ok("backup")]);
+        let health = LlmHealthRegistry::new();
+        let route = LlmRoute::new(
+            primary.as_ref(),
+            &health,
+            settings(),
+            LlmRouteTarget::new("main", "anthropic", "m1", None),
+        )
+        .with_fallback(LlmRouteTarget::new(
+            "backup",
+            "openai",
+            "m2",
+            Some(backup.into()),
+        ));
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let err = route
+            .send("sys", &[], None, Some(&tx), None)
+            .await
+            .err()
+            .unwrap();
+        drop(tx);
+        assert!(err.to_string().contains("Overloaded"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(backup_calls.load(Ordering::SeqCst), 0);
+        let mut deltas = Vec::new();
+        while let Some(d) = rx.recv().await {
+            deltas.push(d);
+        }
+        assert_eq!(deltas, vec!["Hel".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_failing_before_deltas_falls_back_with_backup_text() {
+        let (primary, primary_calls) = ScriptedLlm::boxed(vec![overloaded(), overloaded()]);
+        let (backup, _) = ScriptedLlm::boxed(vec![ok("backup")]);
+        let health = LlmHealthRegistry::new();
+        let route = LlmRoute::new(
+            primary.as_ref(),
+            &health,
+            settings(),
+            LlmRouteTarget::new("main", "anthropic", "m1", None),
+        )
+        .with_fallback(LlmRouteTarget::new(
+            "backup",
+            "openai",
+            "m2",
+            Some(backup.into()),
+        ));
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let routed = route.send("sys", &[], None, Some(&tx), None).await.unwrap();
+        drop(tx);
+        assert_eq!(routed.target.alias, "backup");
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(rx.recv().await.as_deref(), Some("backup"));
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_fails_over_without_route_retry() {
+        let (primary, primary_calls) = ScriptedLlm::boxed(vec![
+            Err(MicroClawError::llm_status(
+                429,
+                "HTTP 429 Too Many Requests: slow down",
+            )),
+            ok("late"),
+        ]);
+        let (backup, backup_calls) = ScriptedLlm::boxed(vec![ok("backup")]);
+        let health = LlmHealthRegistry::new();
+        let route = LlmRoute::new(
+            primary.as_ref(),
+            &health,
+            settings(),
+            LlmRouteTarget::new("main", "anthropic", "m1", None),
+        )
+        .with_fallback(LlmRouteTarget::new(
+            "backup",
+            "openai",
+            "m2",
+            Some(backup.into()),
+        ));
+
+        let routed = route.send("sys", &[], None, None, None).await.unwrap();
+        assert_eq!(text_of(&routed), "backup");
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(backup_calls.load(Ordering::SeqCst), 1);
+    }
+}
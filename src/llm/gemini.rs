@@ -0,0 +1,1058 @@
+use super::*;
+use microclaw_core::error::LlmErrorKind;
+
+// ---------------------------------------------------------------------------
+// Google Gemini native provider (generateContent / streamGenerateContent)
+// ---------------------------------------------------------------------------
+
+pub struct GeminiProvider {
+    http: reqwest::Client,
+    api_key: String,
+    model: String,
+    max_tokens: u32,
+    include_thoughts: bool,
+    base_url: String,
+}
+
+pub(super) fn resolve_gemini_base(configured_base: &str) -> String {
+    let trimmed = configured_base.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        return "https://generativelanguage.googleapis.com/v1beta".to_string();
+    }
+    // Accept the OpenAI-compat base from the `google` preset and use the native root instead.
+    trimmed.trim_end_matches("/openai").to_string()
+}
+
+impl GeminiProvider {
+    pub fn new(config: &Config) -> Self {
+        GeminiProvider {
+            http: reqwest::Client::builder()
+                .user_agent(llm_user_agent(&config.llm_user_agent))
+                .build()
+                .unwrap_or_else(|e| {
+                    warn!("Failed to build LLM HTTP client with user-agent: {e}");
+                    reqwest::Client::new()
+                }),
+            api_key: config.api_key.clone(),
+            model: config.model.clone(),
+            max_tokens: config.max_tokens,
+            include_thoughts: config.show_thinking,
+            base_url: resolve_gemini_base(config.llm_base_url.as_deref().unwrap_or("")),
+        }
+    }
+
+    fn model_url(&self, model: &str, method: &str) -> String {
+        let model = model.trim().trim_start_matches("models/");
+        format!("{}/models/{model}:{method}", self.base_url)
+    }
+
+    fn build_body(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: Option<&[ToolDefinition]>,
+    ) -> serde_json::Value {
+        let mut body = json!({
+            "contents": translate_messages_to_gemini(messages),
+            "generationConfig": {
+                "maxOutputTokens": self.max_tokens,
+            },
+        });
+        if !system.trim().is_empty() {
+            body["systemInstruction"] = json!({"parts": [{"text": system}]});
+        }
+        if self.include_thoughts && !has_visible_reply_runtime_guard(messages) {
+            body["generationConfig"]["thinkingConfig"] = json!({"includeThoughts": true});
+        }
+        if let Some(tool_defs) = tools {
+            if !tool_defs.is_empty() {
+                body["tools"] = json!([{
+                    "functionDeclarations": translate_tools_to_gemini(tool_defs),
+                }]);
+                match tool_choice(tool_defs) {
+                    ToolChoice::Auto => {}
+                    ToolChoice::Any => {
+                        body["toolConfig"] = json!({"functionCallingConfig": {"mode": "ANY"}});
+                    }
+                    ToolChoice::Tool(name) => {
+                        body["toolConfig"] = json!({"functionCallingConfig": {
+                            "mode": "ANY",
+                            "allowedFunctionNames": [name],
+                        }});
+                    }
+                }
+            }
+        }
+        body
+    }
+
+    async fn post_with_retry(
+        &self,
+        url: &str,
+        body: &serde_json::Value,
+    ) -> Result<reqwest::Response, MicroClawError> {
+        let mut retries = 0u32;
+        let max_retries = 3;
+
+        loop {
+            let mut req = self
+                .http
+                .post(url)
+                .header("Content-Type", "application/json")
+                .json(body);
+            if !self.api_key.trim().is_empty() {
+                req = req.header("x-goog-api-key", &self.api_key);
+            }
+            let response = req.send().await?;
+            let status = response.status();
+            if status.is_success() {
+                return Ok(response);
+            }
+
+            if status.as_u16() == 429 && retries < max_retries {
+                retries += 1;
+                let delay = std::time::Duration::from_secs(2u64.pow(retries));
+                warn!(
+                    "Rate limited, retrying in {:?} (attempt {retries}/{max_retries})",
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+
+            let text = response.text().await.unwrap_or_default();
+            if let Ok(err) = serde_json::from_str::<GeminiErrorResponse>(&text) {
+                return Err(MicroClawError::llm_status(
+                    status.as_u16(),
+                    err.error.describe(),
+                ));
+            }
+            return Err(MicroClawError::llm_status(
+                status.as_u16(),
+                format!("HTTP {status}: {text}"),
+            ));
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiErrorResponse {
+    error: GeminiErrorDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiErrorDetail {
+    #[serde(default)]
+    code: Option<u16>,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    status: Option<String>,
+}
+
+impl GeminiErrorDetail {
+    /// Classifies an in-band error by its HTTP-equivalent code, falling back to the
+    /// canonical status name.
+    fn kind(&self) -> LlmErrorKind {
+        if let Some(code) = self.code {
+            return LlmErrorKind::from_status(code);
+        }
+        match self.status.as_deref() {
+            Some("RESOURCE_EXHAUSTED") => LlmErrorKind::RateLimited,
+            Some("UNAVAILABLE" | "INTERNAL" | "DEADLINE_EXCEEDED") => LlmErrorKind::Transient,
+            _ => LlmErrorKind::Permanent,
+        }
+    }
+
+    fn describe(self) -> String {
+        match self.status {
+            Some(code) if !code.is_empty() => format!("{code}: {}", self.message),
+            _ => self.message,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GeminiProvider {
+    async fn send_message(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.send_message_with_model(system, messages, tools, None)
+            .await
+    }
+
+    async fn send_message_with_model(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        model_override: Option<&str>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let messages = sanitize_messages(messages);
+        let model = model_override
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .unwrap_or(&self.model);
+        let body = self.build_body(system, &messages, tools.as_deref());
+        let url = self.model_url(model, "generateContent");
+
+        let response = self.post_with_retry(&url, &body).await?;
+        let text = response.text().await?;
+        let value: serde_json::Value = serde_json::from_str(&text).map_err(|e| {
+            MicroClawError::LlmApi(format!(
+                "Failed to parse Gemini response: {e}\nBody: {text}"
+            ))
+        })?;
+        let mut acc = GeminiAccumulator::default();
+        acc.push_chunk(&value, None)?;
+        Ok(acc.finish())
+    }
+
+    async fn send_message_stream(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.send_message_stream_with_model(system, messages, tools, text_tx, None)
+            .await
+    }
+
+    async fn send_message_stream_with_model(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+        model_override: Option<&str>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let messages = sanitize_messages(messages);
+        let model = model_override
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .unwrap_or(&self.model);
+        let body = self.build_body(system, &messages, tools.as_deref());
+        let url = format!("{}?alt=sse", self.model_url(model, "streamGenerateContent"));
+
+        debug!(
+            provider = "gemini",
+            model = %model,
+            url = %url,
+            messages_count = messages.len(),
+            "Sending LLM stream request"
+        );
+
+        let response = self.post_with_retry(&url, &body).await?;
+        let mut byte_stream = response.bytes_stream();
+        let mut sse = SseEventParser::default();
+        let mut acc = GeminiAccumulator::default();
+
+        while let Some(chunk_res) = byte_stream.next().await {
+            let chunk = chunk_res
+                .map_err(|e| MicroClawError::LlmApi(format!("Gemini stream interrupted: {e}")))?;
+            for data in sse.push_chunk(&String::from_utf8_lossy(&chunk)) {
+                if let Ok(v) = serde_json::from_str::<serde_json::Value>(&data) {
+                    acc.push_chunk(&v, text_tx)?;
+                }
+            }
+        }
+        for data in sse.finish() {
+            if let Ok(v) = serde_json::from_str::<serde_json::Value>(&data) {
+                acc.push_chunk(&v, text_tx)?;
+            }
+        }
+
+        Ok(acc.finish())
+    }
+}
+
+/// Collects `GenerateContentResponse` payloads. Non-streaming calls push a single
+/// payload; streaming calls push one per SSE event, each carrying only new parts.
+#[derive(Default)]
+pub(super) struct GeminiAccumulator {
+    text: String,
+    reasoning_text: String,
+    tool_calls: Vec<StreamToolUseBlock>,
+    finish_reason: Option<String>,
+    usage: Option<Usage>,
+}
+
+impl GeminiAccumulator {
+    /// Errors on an in-band `error` payload or a blocked prompt, which Gemini can
+    /// send mid-stream after a 200 instead of an HTTP error status.
+    pub(super) fn push_chunk(
+        &mut self,
+        v: &serde_json::Value,
+        text_tx: Option<&UnboundedSender<String>>,
+    ) -> Result<(), MicroClawError> {
+        if let Some(err) = v.get("error") {
+            let Ok(detail) = serde_json::from_value::<GeminiErrorDetail>(err.clone()) else {
+                return Err(MicroClawError::LlmApi(format!(
+                    "Gemini stream error: {err}"
+                )));
+            };
+            return Err(MicroClawError::LlmUpstream {
+                kind: detail.kind(),
+                message: format!("Gemini stream error: {}", detail.describe()),
+            });
+        }
+        if let Some(reason) = v
+            .get("promptFeedback")
+            .and_then(|f| f.get("blockReason"))
+            .and_then(|r| r.as_str())
+        {
+            return Err(MicroClawError::LlmApi(format!(
+                "Gemini blocked the prompt: {reason}"
+            )));
+        }
+
+        if let Some(parsed_usage) = v.get("usageMetadata").and_then(gemini_usage_from_json) {
+            merge_usage_max(&mut self.usage, parsed_usage);
+        }
+
+        let Some(candidate) = v
+            .get("candidates")
+            .and_then(|c| c.as_array())
+            .and_then(|arr| arr.first())
+        else {
+            return Ok(());
+        };
+
+        if let Some(reason) = candidate.get("finishReason").and_then(|r| r.as_str()) {
+            self.finish_reason = Some(reason.to_string());
+        }
+
+        let parts = candidate
+            .get("content")
+            .and_then(|c| c.get("parts"))
+            .and_then(|p| p.as_array());
+        for part in parts.into_iter().flatten() {
+            let thought_signature = part
+                .get("thoughtSignature")
+                .and_then(|s| s.as_str())
+                .map(str::to_string);
+            if let Some(call) = part.get("functionCall") {
+                let name = call
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                let id = call
+                    .get("id")
+                    .and_then(|n| n.as_str())
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple()));
+                let args = call.get("args").cloned().unwrap_or_else(|| json!({}));
+                self.tool_calls.push(StreamToolUseBlock {
+                    id,
+                    name,
+                    input_json: args.to_string(),
+                    thought_signature,
+                });
+                continue;
+            }
+            let Some(piece) = part.get("text").and_then(|t| t.as_str()) else {
+                continue;
+            };
+            if piece.is_empty() {
+                continue;
+            }
+            if part.get("thought").and_then(|t| t.as_bool()) == Some(true) {
+                if self.reasoning_text.is_empty() {
+                    debug!("AI started generating thinking/thought");
+                }
+                self.reasoning_text.push_str(piece);
+            } else {
+                self.text.push_str(piece);
+                if let Some(tx) = text_tx {
+                    let _ = tx.send(piece.to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn finish(self) -> MessagesResponse {
+        let mut content = Vec::new();
+        let combined_text = combine_visible_and_reasoning_text(&self.text, &self.reasoning_text);
+        if !combined_text.is_empty() {
+            content.push(ResponseContentBlock::Text {
+                text: combined_text,
+            });
+        }
+        for tool in &self.tool_calls {
+            content.push(ResponseContentBlock::ToolUse {
+                id: tool.id.clone(),
+                name: tool.name.clone(),
+                input: parse_tool_input(&tool.input_json),
+                thought_signature: tool.thought_signature.clone(),
+            });
+        }
+        if content.is_empty() {
+            content.push(ResponseContentBlock::Text {
+                text: String::new(),
+            });
+        }
+
+        let stop_reason = if !self.tool_calls.is_empty() {
+            "tool_use".to_string()
+        } else {
+            match self.finish_reason.as_deref() {
+                None | Some("STOP") | Some("FINISH_REASON_UNSPECIFIED") => "end_turn".into(),
+                Some("MAX_TOKENS") => "max_tokens".into(),
+                Some(other) => other.to_ascii_lowercase(),
+            }
+        };
+
+        MessagesResponse {
+            content,
+            stop_reason: Some(stop_reason),
+            usage: self.usage,
+        }
+    }
+}
+
+fn gemini_usage_from_json(v: &serde_json::Value) -> Option<Usage> {
+    let input = v.get("promptTokenCount").and_then(json_u64)?;
+    // Thinking tokens are billed as output but reported separately.
+    let output = v
+        .get("candidatesTokenCount")
+        .and_then(json_u64)
+        .unwrap_or(0)
+        .saturating_add(v.get("thoughtsTokenCount").and_then(json_u64).unwrap_or(0));
+    Some(Usage {
+        input_tokens: u32::try_from(input).unwrap_or(u32::MAX),
+        output_tokens: u32::try_from(output).unwrap_or(u32::MAX),
+        ..Default::default()
+    })
+}
+
+/// Gemini's `parameters` field accepts an OpenAPI schema subset and rejects a few
+/// JSON-Schema keywords our tools emit.
+fn sanitize_gemini_schema(schema: &serde_json::Value) -> serde_json::Value {
+    match schema {
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .filter(|(k, _)| !matches!(k.as_str(), "$schema" | "additionalProperties"))
+                .map(|(k, v)| (k.clone(), sanitize_gemini_schema(v)))
+                .collect(),
+        ),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(sanitize_gemini_schema).collect())
+        }
+        other => other.clone(),
+    }
+}
+
+pub(super) fn translate_tools_to_gemini(tools: &[ToolDefinition]) -> Vec<serde_json::Value> {
+    tools
+        .iter()
+        .map(|t| {
+            json!({
+                "name": t.name,
+                "description": t.description,
+                "parameters": sanitize_gemini_schema(&t.input_schema),
+            })
+        })
+        .collect()
+}
+
+/// Translate internal Anthropic-style messages into Gemini `contents`.
+///
+/// Gemini keys function responses by tool name rather than call id, so names are
+/// tracked from the preceding assistant `ToolUse` blocks. Adjacent turns with the
+/// same role are merged because the API expects alternating roles.
+pub(super) fn translate_messages_to_gemini(messages: &[Message]) -> Vec<serde_json::Value> {
+    let mut out: Vec<serde_json::Value> = Vec::new();
+    let mut tool_names: HashMap<String, String> = HashMap::new();
+
+    for msg in messages {
+        let role = if msg.role == "assistant" {
+            "model"
+        } else {
+            "user"
+        };
+        let parts: Vec<serde_json::Value> = match &msg.content {
+            MessageContent::Text(text) => {
+                if text.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![json!({"text": text})]
+                }
+            }
+            MessageContent::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => {
+                        if text.is_empty() {
+                            None
+                        } else {
+                            Some(json!({"text": text}))
+                        }
+                    }
+                    ContentBlock::Image {
+                        source:
+                            ImageSource {
+                                media_type, data, ..
+                            },
+                    } => Some(json!({
+                        "inlineData": {"mimeType": media_type, "data": data}
+                    })),
+                    ContentBlock::ToolUse {
+                        id,
+                        name,
+                        input,
+                        thought_signature,
+                    } => {
+                        tool_names.insert(id.clone(), name.clone());
+                        let mut part = json!({
+                            "functionCall": {"name": name, "args": input}
+                        });
+                        if let Some(sig) = thought_signature {
+                            part["thoughtSignature"] = json!(sig);
+                        }
+                        Some(part)
+                    }
+                    // Thinking from other providers cannot be replayed to Gemini.
+                    ContentBlock::Thinking { .. } | ContentBlock::RedactedThinking { .. } => None,
+                    ContentBlock::ToolResult {
+                        tool_use_id,
+                        content,
+                        is_error,
+                    } => {
+                        let Some(name) = tool_names.get(tool_use_id) else {
+                            // Gemini pairs results with calls by name; without one, keep
+                            // the output as plain text rather than losing it.
+                            warn!(
+                                "Gemini: tool result '{tool_use_id}' has no matching tool call; sending it as text"
+                            );
+                            return Some(json!({
+                                "text": format!("[tool result {tool_use_id}]\n{content}")
+                            }));
+                        };
+                        let response = if is_error == &Some(true) {
+                            json!({"error": content})
+                        } else {
+                            json!({"content": content})
+                        };
+                        Some(json!({
+                            "functionResponse": {"name": name, "response": response}
+                        }))
+                    }
+                })
+                .collect(),
+        };
+        if parts.is_empty() {
+            continue;
+        }
+
+        if let Some(last) = out.last_mut() {
+            if last.get("role").and_then(|r| r.as_str()) == Some(role) {
+                if let Some(existing) = last.get_mut("parts").and_then(|p| p.as_array_mut()) {
+                    existing.extend(parts);
+                    continue;
+                }
+            }
+        }
+        out.push(json!({"role": role, "parts": parts}));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn test_config(base_url: &str) -> Config {
+        let mut config = Config::test_defaults();
+        config.llm_provider = "gemini".into();
+        config.api_key = "gem-key".into();
+        config.model = "gemini-2.5-flash".into();
+        config.llm_base_url = Some(base_url.to_string());
+        config.data_dir = "/tmp".into();
+        config.working_dir = "/tmp".into();
+        config.web_enabled = false;
+        config
+    }
+
+    type CapturedRequest = (String, Option<String>, serde_json::Value);
+
+    /// Serve a single canned response and report back the request line, the
+    /// `x-goog-api-key` header and the JSON body.
+    fn spawn_mock_server(
+        content_type: &'static str,
+        body: String,
+    ) -> (
+        String,
+        mpsc::Receiver<CapturedRequest>,
+        std::thread::JoinHandle<()>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (request_tx, request_rx) = mpsc::channel();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(2)))
+                .unwrap();
+            let mut raw = Vec::new();
+            let mut buf = [0u8; 8192];
+            loop {
+                let n = stream.read(&mut buf).unwrap_or(0);
+                if n == 0 {
+                    break;
+                }
+                raw.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&raw);
+                if let Some((head, rest)) = text.split_once("\r\n\r\n") {
+                    let content_length = head
+                        .lines()
+                        .find_map(|l| {
+                            l.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|v| v.trim().parse::<usize>().unwrap_or(0))
+                        })
+                        .unwrap_or(0);
+                    if rest.len() >= content_length {
+                        break;
+                    }
+                }
+            }
+            let req = String::from_utf8_lossy(&raw).to_string();
+            let (head, req_body) = req.split_once("\r\n\r\n").unwrap_or((&req, ""));
+            let request_line = head.lines().next().unwrap_or("").to_string();
+            let api_key = head.lines().find_map(|line| {
+                line.to_ascii_lowercase()
+                    .starts_with("x-goog-api-key:")
+                    .then(|| line.split_once(':').unwrap().1.trim().to_string())
+            });
+            let json_body = serde_json::from_str(req_body).unwrap_or(serde_json::Value::Null);
+            let _ = request_tx.send((request_line, api_key, json_body));
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+            let _ = stream.flush();
+        });
+        (format!("http://{addr}/v1beta"), request_rx, server)
+    }
+
+    #[test]
+    fn test_resolve_gemini_base() {
+        assert_eq!(
+            resolve_gemini_base(""),
+            "https://generativelanguage.googleapis.com/v1beta"
+        );
+        assert_eq!(
+            resolve_gemini_base("https://generativelanguage.googleapis.com/v1beta/openai/"),
+            "https://generativelanguage.googleapis.com/v1beta"
+        );
+        assert_eq!(
+            resolve_gemini_base("http://127.0.0.1:9000/v1beta"),
+            "http://127.0.0.1:9000/v1beta"
+        );
+    }
+
+    #[test]
+    fn test_translate_messages_to_gemini_maps_tool_use_result_and_image() {
+        let messages = vec![
+            Message {
+                role: "user".into(),
+                content: MessageContent::Blocks(vec![
+                    ContentBlock::Image {
+                        source: ImageSource {
+                            source_type: "base64".into(),
+                            media_type: "image/png".into(),
+                            data: "AAAA".into(),
+                        },
+                    },
+                    ContentBlock::Text {
+                        text: "what is this?".into(),
+                    },
+                ]),
+            },
+            Message {
+                role: "assistant".into(),
+                content: MessageContent::Blocks(vec![ContentBlock::ToolUse {
+                    id: "call_1".into(),
+                    name: "read_file".into(),
+                    input: json!({"path": "a.txt"}),
+                    thought_signature: Some("sig-1".into()),
+                }]),
+            },
+            Message {
+                role: "user".into(),
+                content: MessageContent::Blocks(vec![ContentBlock::ToolResult {
+                    tool_use_id: "call_1".into(),
+                    content: "not found".into(),
+                    is_error: Some(true),
+                }]),
+            },
+            Message {
+                role: "user".into(),
+                content: MessageContent::Text("try again".into()),
+            },
+        ];
+        let contents = translate_messages_to_gemini(&messages);
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[0]["role"], "user");
+        assert_eq!(
+            contents[0]["parts"][0]["inlineData"]["mimeType"],
+            "image/png"
+        );
+        assert_eq!(contents[0]["parts"][1]["text"], "what is this?");
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(contents[1]["parts"][0]["functionCall"]["name"], "read_file");
+        assert_eq!(
+            contents[1]["parts"][0]["functionCall"]["args"]["path"],
+            "a.txt"
+        );
+        assert_eq!(contents[1]["parts"][0]["thoughtSignature"], "sig-1");
+        // Tool result and the following user text merge into one user turn.
+        assert_eq!(contents[2]["role"], "user");
+        let parts = contents[2]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0]["functionResponse"]["name"], "read_file");
+        assert_eq!(
+            parts[0]["functionResponse"]["response"]["error"],
+            "not found"
+        );
+        assert_eq!(parts[1]["text"], "try again");
+    }
+
+    #[test]
+    fn test_translate_messages_to_gemini_keeps_unmatched_tool_result_as_text() {
+        let messages = vec![Message {
+            role: "user".into(),
+            content: MessageContent::Blocks(vec![ContentBlock::ToolResult {
+                tool_use_id: "call_gone".into(),
+                content: "file contents".into(),
+                is_error: None,
+            }]),
+        }];
+        let out = translate_messages_to_gemini(&messages);
+        assert_eq!(out.len(), 1);
+        assert_eq!(
+            out[0]["parts"][0]["text"],
+            "[tool result call_gone]\nfile contents"
+        );
+    }
+
+    #[test]
+    fn test_translate_tools_to_gemini_strips_unsupported_schema_keys() {
+        let tools = vec![ToolDefinition {
+            name: "bash".into(),
+            description: "Run bash".into(),
+            input_schema: json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "opts": {"type": "object", "additionalProperties": {"type": "string"}}
+                }
+            }),
+        }];
+        let decls = translate_tools_to_gemini(&tools);
+        assert_eq!(decls[0]["name"], "bash");
+        let params = &decls[0]["parameters"];
+        assert!(params.get("$schema").is_none());
+        assert!(params.get("additionalProperties").is_none());
+        assert!(params["properties"]["opts"]
+            .get("additionalProperties")
+            .is_none());
+        assert_eq!(params["type"], "object");
+    }
+
+    #[test]
+    fn test_gemini_accumulator_maps_function_calls_thoughts_and_usage() {
+        let mut acc = GeminiAccumulator::default();
+        acc.push_chunk(
+            &json!({
+                "candidates": [{
+                    "content": {"role": "model", "parts": [
+                        {"text": "plan", "thought": true},
+                        {"text": "Checking."},
+                        {"functionCall": {"name": "bash", "args": {"command": "ls"}}, "thoughtSignature": "sig"}
+                    ]},
+                    "finishReason": "STOP"
+                }],
+                "usageMetadata": {"promptTokenCount": 12, "candidatesTokenCount": 5, "thoughtsTokenCount": 3}
+            }),
+            None,
+        )
+        .unwrap();
+        let resp = acc.finish();
+        assert_eq!(resp.stop_reason.as_deref(), Some("tool_use"));
+        match &resp.content[0] {
+            ResponseContentBlock::Text { text } => {
+                assert_eq!(text, "<thought>\nplan\n</thought>\n\nChecking.")
+            }
+            _ => panic!("Expected Text block"),
+        }
+        match &resp.content[1] {
+            ResponseContentBlock::ToolUse {
+                id,
+                name,
+                input,
+                thought_signature,
+            } => {
+                assert!(id.starts_with("call_"));
+                assert_eq!(name, "bash");
+                assert_eq!(input["command"], "ls");
+                assert_eq!(thought_signature.as_deref(), Some("sig"));
+            }
+            _ => panic!("Expected ToolUse block"),
+        }
+        let usage = resp.usage.unwrap();
+        assert_eq!(usage.input_tokens, 12);
+        assert_eq!(usage.output_tokens, 8);
+    }
+
+    #[test]
+    fn test_gemini_accumulator_max_tokens_stop_reason() {
+        let mut acc = GeminiAccumulator::default();
+        acc.push_chunk(
+            &json!({"candidates": [{"content": {"parts": [{"text": "cut"}]}, "finishReason": "MAX_TOKENS"}]}),
+            None,
+        )
+        .unwrap();
+        assert_eq!(acc.finish().stop_reason.as_deref(), Some("max_tokens"));
+    }
+
+    #[test]
+    fn test_gemini_accumulator_rejects_error_payload_and_blocked_prompt() {
+        let mut acc = GeminiAccumulator::default();
+        let err = acc
+            .push_chunk(
+                &json!({"error": {"code": 500, "message": "internal", "status": "INTERNAL"}}),
+                None,
+            )
+            .unwrap_err();
+        assert!(
+            matches!(&err, MicroClawError::LlmUpstream { kind: LlmErrorKind::Transient, message }
+                if message.contains("INTERNAL: internal")),
+            "{err:?}"
+        );
+
+        let mut acc = GeminiAccumulator::default();
+        let err = acc
+            .push_chunk(
+                &json!({"promptFeedback": {"blockReason": "SAFETY"}, "usageMetadata": {"promptTokenCount": 3}}),
+                None,
+            )
+            .unwrap_err();
+        assert!(
+            matches!(&err, MicroClawError::LlmApi(msg) if msg.contains("blocked the prompt: SAFETY")),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gemini_send_message_against_mock_server() {
+        let body = json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "hello from gemini"}]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 7, "candidatesTokenCount": 4}
+        })
+        .to_string();
+        let (base, request_rx, server) = spawn_mock_server("application/json", body);
+        let provider = GeminiProvider::new(&test_config(&base));
+        let tools = vec![ToolDefinition {
+            name: "bash".into(),
+            description: "Run bash".into(),
+            input_schema: json!({"type": "object"}),
+        }];
+        let resp = provider
+            .send_message(
+                "be brief",
+                vec![Message {
+                    role: "user".into(),
+                    content: MessageContent::Text("hi".into()),
+                }],
+                Some(tools),
+            )
+            .await
+            .unwrap();
+
+        let (request_line, api_key, req_body) =
+            request_rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        server.join().unwrap();
+        assert!(request_line
+            .starts_with("POST /v1beta/models/gemini-2.5-flash:generateContent HTTP/1.1"));
+        assert_eq!(api_key.as_deref(), Some("gem-key"));
+        assert_eq!(
+            req_body["systemInstruction"]["parts"][0]["text"],
+            "be brief"
+        );
+        assert_eq!(req_body["contents"][0]["parts"][0]["text"], "hi");
+        assert_eq!(
+            req_body["tools"][0]["functionDeclarations"][0]["name"],
+            "bash"
+        );
+        assert_eq!(req_body["generationConfig"]["maxOutputTokens"], 8192);
+
+        assert_eq!(resp.stop_reason.as_deref(), Some("end_turn"));
+        match &resp.content[0] {
+            ResponseContentBlock::Text { text } => assert_eq!(text, "hello from gemini"),
+            _ => panic!("Expected Text block"),
+        }
+        let usage = resp.usage.unwrap();
+        assert_eq!(usage.input_tokens, 7);
+        assert_eq!(usage.output_tokens, 4);
+    }
+
+    #[tokio::test]
+    async fn test_gemini_stream_against_mock_server() {
+        let chunk1 = json!({
+            "candidates": [{"content": {"role": "model", "parts": [{"text": "Hel"}]}}]
+        });
+        let chunk2 = json!({
+            "candidates": [{"content": {"role": "model", "parts": [{"text": "lo"}]}}]
+        });
+        let chunk3 = json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"functionCall": {"name": "read_file", "args": {"path": "x"}, "id": "fc-1"}}
+                ]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 20, "candidatesTokenCount": 6}
+        });
+        let body = format!("data: {chunk1}\r\n\r\ndata: {chunk2}\r\n\r\ndata: {chunk3}\r\n\r\n");
+        let (base, request_rx, server) = spawn_mock_server("text/event-stream", body);
+        let provider = GeminiProvider::new(&test_config(&base));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let resp = provider
+            .send_message_stream_with_model(
+                "",
+                vec![Message {
+                    role: "user".into(),
+                    content: MessageContent::Text("hi".into()),
+                }],
+                None,
+                Some(&tx),
+                Some("models/gemini-2.5-pro"),
+            )
+            .await
+            .unwrap();
+        drop(tx);
+
+        let (request_line, _, req_body) = request_rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        server.join().unwrap();
+        assert!(request_line.starts_with(
+            "POST /v1beta/models/gemini-2.5-pro:streamGenerateContent?alt=sse HTTP/1.1"
+        ));
+        assert!(req_body.get("systemInstruction").is_none());
+        assert!(req_body.get("tools").is_none());
+
+        let mut deltas = Vec::new();
+        while let Some(d) = rx.recv().await {
+            deltas.push(d);
+        }
+        assert_eq!(deltas, vec!["Hel".to_string(), "lo".to_string()]);
+        assert_eq!(resp.stop_reason.as_deref(), Some("tool_use"));
+        match &resp.content[0] {
+            ResponseContentBlock::Text { text } => assert_eq!(text, "Hello"),
+            _ => panic!("Expected Text block"),
+        }
+        match &resp.content[1] {
+            ResponseContentBlock::ToolUse { id, name, .. } => {
+                assert_eq!(id, "fc-1");
+                assert_eq!(name, "read_file");
+            }
+            _ => panic!("Expected ToolUse block"),
+        }
+        assert_eq!(resp.usage.unwrap().input_tokens, 20);
+    }
+
+    #[tokio::test]
+    async fn test_gemini_stream_mid_stream_error_is_an_error() {
+        let chunk1 = json!({
+            "candidates": [{"content": {"role": "model", "parts": [{"text": "Hel"}]}}]
+        });
+        let chunk2 =
+            json!({"error": {"code": 503, "message": "overloaded", "status": "UNAVAILABLE"}});
+        let body = format!("data: {chunk1}\r\n\r\ndata: {chunk2}\r\n\r\n");
+        let (base, _request_rx, server) = spawn_mock_server("text/event-stream", body);
+        let provider = GeminiProvider::new(&test_config(&base));
+        let err = provider
+            .send_message_stream(
+                "",
+                vec![Message {
+                    role: "user".into(),
+                    content: MessageContent::Text("hi".into()),
+                }],
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        server.join().unwrap();
+        assert!(err.is_retryable());
+        assert!(
+            matches!(&err, MicroClawError::LlmUpstream { message, .. }
+                if message.contains("UNAVAILABLE: overloaded")),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gemini_stream_interrupted_is_an_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_millis(200)))
+                .unwrap();
+            let mut buf = [0u8; 8192];
+            let _ = stream.read(&mut buf);
+            let chunk = json!({
+                "candidates": [{"content": {"role": "model", "parts": [{"text": "Hel"}]}}]
+            });
+            let body = format!("data: {chunk}\r\n\r\n");
+            // Promise more bytes than are sent, then hang up mid-stream.
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len() + 64,
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+            let _ = stream.flush();
+        });
+        let provider = GeminiProvider::new(&test_config(&format!("http://{addr}/v1beta")));
+        let err = provider
+            .send_message_stream_with_model(
+                "",
+                vec![Message {
+                    role: "user".into(),
+                    content: MessageContent::Text("hi".into()),
+                }],
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        server.join().unwrap();
+        assert!(
+            matches!(&err, MicroClawError::LlmApi(msg) if msg.contains("stream interrupted")),
+            "{err:?}"
+        );
+    }
+}
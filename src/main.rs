@@ -69,7 +69,12 @@ enum MainCommand {
     },
     /// Manage Web UI configurations
     Web(WebCommand),
-    /// Re-embed active memories (requires `sqlite-vec` feature)
+    /// Export or import structured memories (export/import)
+    Memory {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Re-embed active memories into the configured vector index
     Reembed,
     /// Upgrade MicroClaw to latest release
     Upgrade,
@@ -411,70 +416,66 @@ fn apply_config_override(path: Option<&PathBuf>) -> anyhow::Result<()> {
 }
 
 async fn reembed_memories() -> anyhow::Result<()> {
+    use microclaw::embedding;
+
     let config = Config::load()?;
+    let runtime_data_dir = config.runtime_data_dir();
+    let db = db::Database::new(&runtime_data_dir)?;
 
-    #[cfg(not(feature = "sqlite-vec"))]
-    {
-        let _ = config;
-        anyhow::bail!(
-            "sqlite-vec feature not enabled. Rebuild with: cargo build --release --features sqlite-vec"
-        );
-    }
+    let provider = embedding::create_provider(&config);
+    let provider = match provider {
+        Some(p) => p,
+        None => {
+            eprintln!("No embedding provider configured. Check embedding_provider in config.");
+            std::process::exit(1);
+        }
+    };
 
-    #[cfg(feature = "sqlite-vec")]
-    {
-        use microclaw::embedding;
-        let runtime_data_dir = config.runtime_data_dir();
-        let db = db::Database::new(&runtime_data_dir)?;
-
-        let provider = embedding::create_provider(&config);
-        let provider = match provider {
-            Some(p) => p,
-            None => {
-                eprintln!("No embedding provider configured. Check embedding_provider in config.");
-                std::process::exit(1);
-            }
-        };
+    let dim = provider.dimension();
+    db.set_vector_backend(config.vector_backend());
+    db.prepare_vector_index(dim)?;
+    println!(
+        "Embedding provider: {} ({}D, {} index)",
+        provider.model(),
+        dim,
+        db.vector_backend().as_str()
+    );
 
-        let dim = provider.dimension();
-        db.prepare_vector_index(dim)?;
-        println!("Embedding provider: {} ({}D)", provider.model(), dim);
-
-        let memories = db.get_all_active_memories()?;
-        println!("Re-embedding {} active memories...", memories.len());
-
-        let mut success = 0usize;
-        let mut failed = 0usize;
-        for (i, (id, content)) in memories.iter().enumerate() {
-            match provider.embed(content).await {
-                Ok(embedding) => {
-                    if let Err(e) = db.upsert_memory_vec(*id, &embedding) {
-                        eprintln!("  [{}] DB error: {}", id, e);
-                        failed += 1;
-                    } else {
-                        let _ = db.update_memory_embedding_model(*id, provider.model());
-                        success += 1;
-                    }
-                }
-                Err(e) => {
-                    eprintln!("  [{}] Embed error: {}", id, e);
+    let memories = db.get_all_active_memories()?;
+    println!("Re-embedding {} active memories...", memories.len());
+
+    let mut success = 0usize;
+    let mut failed = 0usize;
+    for (i, (id, content)) in memories.iter().enumerate() {
+        match provider.embed(content).await {
+            Ok(embedding) => {
+                if let Err(e) = db.upsert_memory_vec(*id, &embedding) {
+                    eprintln!("  [{}] DB error: {}", id, e);
                     failed += 1;
+                } else {
+                    let _ = db.update_memory_embedding_model(*id, provider.model());
+                    success += 1;
                 }
             }
-            if (i + 1) % 20 == 0 {
-                println!(
-                    "  Progress: {}/{} (ok={}, fail={})",
-                    i + 1,
-                    memories.len(),
-                    success,
-                    failed
-                );
+            Err(e) => {
+                eprintln!("  [{}] Embed error: {}", id, e);
+                failed += 1;
             }
         }
-
-        println!("Done! {} embedded, {} failed", success, failed);
-        Ok(())
+        if (i + 1) % 20 == 0 {
+            println!(
+                "  Progress: {}/{} (ok={}, fail={})",
+                i + 1,
+                memories.len(),
+                success,
+                failed
+            );
+        }
     }
+
+    db.flush_vector_index()?;
+    println!("Done! {} embedded, {} failed", success, failed);
+    Ok(())
 }
 
 #[tokio::main]
@@ -525,6 +526,10 @@ async fn main() -> anyhow::Result<()> {
             hooks::handle_hooks_cli(&args).await?;
             return Ok(());
         }
+        Some(MainCommand::Memory { args }) => {
+            microclaw::memory_service::handle_memory_cli(&args).await?;
+            return Ok(());
+        }
         Some(MainCommand::Reembed) => {
             return reembed_memories().await;
         }
@@ -790,6 +795,24 @@ mod tests {
         let _ = std::fs::remove_dir_all(root);
     }
 
+    #[test]
+    fn cli_passes_memory_args_through() {
+        let cli = Cli::parse_from([
+            "microclaw",
+            "memory",
+            "import",
+            "b.jsonl",
+            "--strategy",
+            "dedupe",
+        ]);
+        match cli.command {
+            Some(MainCommand::Memory { args }) => {
+                assert_eq!(args, vec!["import", "b.jsonl", "--strategy", "dedupe"])
+            }
+            other => panic!("unexpected command: {other:?}"),
+        }
+    }
+
     #[test]
     fn cli_parses_upgrade_command() {
         let cli = Cli::parse_from(["microclaw", "upgrade"]);
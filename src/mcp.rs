@@ -98,7 +98,7 @@ pub struct McpToolInfo {
 // --- Resilience primitives (unchanged from before) ---
 
 #[derive(Debug)]
-struct CircuitBreakerState {
+pub(crate) struct CircuitBreakerState {
     threshold: u32,
     cooldown: Duration,
     consecutive_failures: u32,
@@ -106,7 +106,7 @@ struct CircuitBreakerState {
 }
 
 impl CircuitBreakerState {
-    fn new(threshold: u32, cooldown_secs: u64) -> Self {
+    pub(crate) fn new(threshold: u32, cooldown_secs: u64) -> Self {
         Self {
             threshold,
             cooldown: Duration::from_secs(cooldown_secs.max(1)),
@@ -115,7 +115,7 @@ impl CircuitBreakerState {
         }
     }
 
-    fn check_ready(&mut self, now: Instant) -> Result<(), u64> {
+    pub(crate) fn check_ready(&mut self, now: Instant) -> Result<(), u64> {
         if self.threshold == 0 {
             return Ok(());
         }
@@ -129,12 +129,12 @@ impl CircuitBreakerState {
         Ok(())
     }
 
-    fn record_success(&mut self) {
+    pub(crate) fn record_success(&mut self) {
         self.consecutive_failures = 0;
         self.open_until = None;
     }
 
-    fn record_failure(&mut self, now: Instant) -> bool {
+    pub(crate) fn record_failure(&mut self, now: Instant) -> bool {
         if self.threshold == 0 {
             return false;
         }
@@ -6,6 +6,7 @@ use std::sync::Arc;
 
 use tracing::{info, warn};
 
+use crate::hooks::{HookManager, HookOutcome};
 use crate::mcp::{McpManager, McpServer, McpToolInfo};
 use microclaw_core::error::MicroClawError;
 use microclaw_storage::db::{call_blocking, Database, Memory};
@@ -168,6 +169,7 @@ pub struct MemoryBackend {
     stats: Arc<MemoryBackendStats>,
     primary_provider: Option<Arc<dyn MemoryProvider>>,
     primary_provider_name: Option<String>,
+    hooks: Option<Arc<HookManager>>,
 }
 
 type ProviderBundle = (
@@ -201,6 +203,7 @@ impl MemoryBackend {
             stats,
             primary_provider,
             primary_provider_name,
+            hooks: None,
         }
     }
 
@@ -210,6 +213,7 @@ impl MemoryBackend {
             stats: Arc::new(MemoryBackendStats::new()),
             primary_provider: None,
             primary_provider_name: None,
+            hooks: None,
         }
     }
 
@@ -220,6 +224,7 @@ impl MemoryBackend {
             stats: Arc::new(MemoryBackendStats::new()),
             primary_provider: None,
             primary_provider_name: None,
+            hooks: None,
         }
     }
 
@@ -234,9 +239,56 @@ impl MemoryBackend {
             stats,
             primary_provider: None,
             primary_provider_name,
+            hooks: None,
         }
     }
 
+    /// Run `OnMemoryWrite` hooks before inserts, updates and supersedes.
+    pub fn with_hooks(mut self, hooks: Arc<HookManager>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    /// Returns the (possibly patched) content and category, or an error when a
+    /// hook blocks the write.
+    async fn apply_write_hooks(
+        &self,
+        operation: &str,
+        chat_id: Option<i64>,
+        memory_id: Option<i64>,
+        content: &str,
+        category: &str,
+        source: &str,
+    ) -> Result<(String, String), MicroClawError> {
+        let mut content = content.to_string();
+        let mut category = category.to_string();
+        let Some(hooks) = &self.hooks else {
+            return Ok((content, category));
+        };
+        match hooks
+            .run_memory_write(operation, chat_id, memory_id, &content, &category, source)
+            .await
+        {
+            Ok(HookOutcome::Block { reason }) => {
+                return Err(MicroClawError::ToolExecution(format!(
+                    "memory write blocked: {reason}"
+                )));
+            }
+            Ok(HookOutcome::Allow { patches }) => {
+                for patch in patches {
+                    if let Some(v) = patch.get("content").and_then(|v| v.as_str()) {
+                        content = v.to_string();
+                    }
+                    if let Some(v) = patch.get("category").and_then(|v| v.as_str()) {
+                        category = v.to_string();
+                    }
+                }
+            }
+            Err(e) => warn!("OnMemoryWrite hooks failed: {e}"),
+        }
+        Ok((content, category))
+    }
+
     pub fn supports_local_semantic_ranking(&self) -> bool {
         self.provider.supports_local_semantic_ranking()
     }
@@ -316,8 +368,11 @@ impl MemoryBackend {
         source: &str,
         confidence: f64,
     ) -> Result<i64, MicroClawError> {
+        let (content, category) = self
+            .apply_write_hooks("insert", chat_id, None, content, category, source)
+            .await?;
         self.provider
-            .insert_memory_with_metadata(chat_id, content, category, source, confidence)
+            .insert_memory_with_metadata(chat_id, &content, &category, source, confidence)
             .await
     }
 
@@ -329,8 +384,11 @@ impl MemoryBackend {
         confidence: f64,
         source: &str,
     ) -> Result<bool, MicroClawError> {
+        let (content, category) = self
+            .apply_write_hooks("update", None, Some(id), content, category, source)
+            .await?;
         self.provider
-            .update_memory_with_metadata(id, content, category, confidence, source)
+            .update_memory_with_metadata(id, &content, &category, confidence, source)
             .await
     }
 
@@ -357,11 +415,21 @@ impl MemoryBackend {
         confidence: f64,
         reason: Option<&str>,
     ) -> Result<i64, MicroClawError> {
+        let (new_content, category) = self
+            .apply_write_hooks(
+                "supersede",
+                None,
+                Some(from_memory_id),
+                new_content,
+                category,
+                source,
+            )
+            .await?;
         self.provider
             .supersede_memory(
                 from_memory_id,
-                new_content,
-                category,
+                &new_content,
+                &category,
                 source,
                 confidence,
                 reason,
@@ -1490,4 +1558,58 @@ mod tests {
         let err = parse_single_memory_strict(&payload).unwrap_err();
         assert!(err.contains("missing non-empty `content`"));
     }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_memory_write_hooks_patch_and_block() {
+        let root = std::env::temp_dir().join(format!("mc_memory_hooks_{}", uuid::Uuid::new_v4()));
+        let hook_dir = root.join("hooks").join("memory-dlp");
+        std::fs::create_dir_all(&hook_dir).unwrap();
+        std::fs::write(
+            hook_dir.join("HOOK.md"),
+            "---\nname: memory-dlp\nevents: [OnMemoryWrite]\ncommand: \"sh hook.sh\"\ntimeout_ms: 2000\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            hook_dir.join("hook.sh"),
+            r#"#!/bin/sh
+payload="$(cat)"
+if echo "$payload" | grep -q 'password'; then
+  echo '{"action":"block","reason":"credentials are not stored"}'
+else
+  echo '{"action":"modify","patch":{"category":"PROFILE"}}'
+fi
+"#,
+        )
+        .unwrap();
+        let db = Arc::new(Database::new(root.join("runtime").to_str().unwrap()).unwrap());
+        let hooks = Arc::new(HookManager::from_test_paths(
+            root.join("hooks"),
+            root.join("runtime/hooks_state.json"),
+        ));
+        let backend = MemoryBackend::local_only(db).with_hooks(hooks);
+
+        let id = backend
+            .insert_memory_with_metadata(Some(7), "likes green tea", "KNOWLEDGE", "test", 0.9)
+            .await
+            .unwrap();
+        let stored = backend.get_memory_by_id(id).await.unwrap().unwrap();
+        assert_eq!(stored.category, "PROFILE");
+
+        let err = backend
+            .insert_memory_with_metadata(Some(7), "password is hunter2", "KNOWLEDGE", "test", 0.9)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("credentials are not stored"));
+        assert_eq!(
+            backend
+                .get_all_memories_for_chat(Some(7))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
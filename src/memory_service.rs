@@ -1,22 +1,125 @@
+use std::io::{Read, Write};
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use anyhow::Result;
-use tracing::info;
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use tracing::{info, warn};
 
 use crate::agent_engine::is_slash_command_text;
+use crate::config::{Config, MemoryReviewConfig};
 use crate::embedding::EmbeddingProvider;
+use crate::memory::{AgentsFileScope, MemoryManager};
 use crate::memory_backend::MemoryBackend;
 use crate::runtime::AppState;
-use microclaw_storage::db::{call_blocking, Database, Memory};
+use microclaw_storage::db::{call_blocking, Database, Memory, MemoryReview};
+use microclaw_storage::memory_bundle::{
+    read_bundle, write_bundle, BundleAgentsFile, BundleRecord, BundleScope, ExportOptions,
+    ImportReport, MergeStrategy,
+};
 use microclaw_storage::memory_quality;
 
 pub(crate) struct ReflectorApplyOutcome {
     pub inserted: usize,
     pub updated: usize,
     pub skipped: usize,
+    /// Facts written without review because they met the auto-approve threshold.
+    pub auto_approved: usize,
+    /// `memory_reviews` rows created for facts that need a human decision.
+    pub queued_review_ids: Vec<i64>,
     pub dedup_method: &'static str,
 }
 
+/// Confidence given to memories a reviewer approved.
+const REVIEWED_MEMORY_CONFIDENCE: f64 = 0.9;
+
+/// Review gating for one reflector run. Facts below `reject_below` are dropped, facts
+/// at or above `auto_approve_confidence` are written, everything in between waits in
+/// `memory_reviews` for a human decision.
+struct ReflectorReviewGate<'a> {
+    config: &'a MemoryReviewConfig,
+    chat_id: i64,
+    /// Pending and rejected facts for the chat, which are not proposed again.
+    reviewed_contents: Vec<String>,
+    auto_approved: usize,
+    dropped: usize,
+    queued_review_ids: Vec<i64>,
+}
+
+impl<'a> ReflectorReviewGate<'a> {
+    async fn load(state: &'a Arc<AppState>, chat_id: i64) -> ReflectorReviewGate<'a> {
+        let config = &state.config.memory_review;
+        let reviewed_contents = if config.enabled {
+            call_blocking(state.db.clone(), move |db| {
+                db.get_unapproved_memory_review_contents(chat_id)
+            })
+            .await
+            .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        ReflectorReviewGate {
+            config,
+            chat_id,
+            reviewed_contents,
+            auto_approved: 0,
+            dropped: 0,
+            queued_review_ids: Vec::new(),
+        }
+    }
+
+    fn already_reviewed(&self, content: &str) -> bool {
+        self.reviewed_contents
+            .iter()
+            .any(|reviewed| jaccard_similar(reviewed, content, 0.5))
+    }
+
+    /// Whether the fact may be written now; otherwise it was queued or dropped.
+    async fn admit(
+        &mut self,
+        state: &Arc<AppState>,
+        action: &'static str,
+        target_memory_id: Option<i64>,
+        content: &str,
+        category: &str,
+        confidence: f64,
+    ) -> bool {
+        if !self.config.enabled {
+            return true;
+        }
+        if confidence >= self.config.auto_approve_confidence {
+            self.auto_approved += 1;
+            return true;
+        }
+        if confidence < self.config.reject_below {
+            self.dropped += 1;
+            return false;
+        }
+        let chat_id = self.chat_id;
+        let queued_content = content.to_string();
+        let queued_category = category.to_string();
+        match call_blocking(state.db.clone(), move |db| {
+            db.enqueue_memory_review(
+                chat_id,
+                action,
+                target_memory_id,
+                &queued_content,
+                &queued_category,
+                confidence,
+            )
+        })
+        .await
+        {
+            Ok(id) => {
+                self.queued_review_ids.push(id);
+                self.reviewed_contents.push(content.to_string());
+            }
+            Err(e) => warn!("Reflector: failed to queue memory review for chat {chat_id}: {e}"),
+        }
+        false
+    }
+}
+
 fn jaccard_similarity_ratio(a: &str, b: &str) -> f64 {
     use std::collections::HashSet;
     let a_words: HashSet<&str> = a.split_whitespace().collect();
@@ -147,7 +250,6 @@ pub(crate) fn should_skip_memory_poisoning_risk(content: &str) -> bool {
     looks_like_broken_behavior_fact(content) && !is_corrective_action_item(content)
 }
 
-#[cfg(feature = "sqlite-vec")]
 pub(crate) async fn upsert_memory_embedding(
     state: &Arc<AppState>,
     memory_id: i64,
@@ -160,7 +262,6 @@ pub(crate) async fn upsert_memory_embedding(
     upsert_memory_embedding_with_provider(state.db.clone(), provider, memory_id, content).await
 }
 
-#[cfg(feature = "sqlite-vec")]
 async fn upsert_memory_embedding_with_provider(
     db: Arc<Database>,
     provider: &Arc<dyn EmbeddingProvider>,
@@ -274,17 +375,14 @@ pub(crate) async fn maybe_handle_explicit_memory_command(
         )
         .await?;
 
-    #[cfg(feature = "sqlite-vec")]
-    {
-        if let Some(provider) = &state.embedding {
-            let _ = upsert_memory_embedding_with_provider(
-                state.db.clone(),
-                provider,
-                inserted_id,
-                &explicit_content,
-            )
-            .await;
-        }
+    if let Some(provider) = &state.embedding {
+        let _ = upsert_memory_embedding_with_provider(
+            state.db.clone(),
+            provider,
+            inserted_id,
+            &explicit_content,
+        )
+        .await;
     }
 
     Ok(Some(format!(
@@ -309,65 +407,12 @@ pub(crate) async fn build_db_memory_context(
         return String::new();
     }
 
-    let mut ordered: Vec<&Memory> = Vec::new();
-    #[cfg(feature = "sqlite-vec")]
-    let mut retrieval_method = if memory_supports_local_semantic_ranking(memory_backend) {
-        "keyword"
+    let (ordered, retrieval_method) = if memory_supports_local_semantic_ranking(memory_backend) {
+        rank_hybrid(db, embedding, chat_id, query, memories).await
     } else {
-        "provider"
-    };
-    #[cfg(not(feature = "sqlite-vec"))]
-    let retrieval_method = "keyword";
-
-    #[cfg(feature = "sqlite-vec")]
-    {
-        if let Some(provider) = embedding {
-            if memory_supports_local_semantic_ranking(memory_backend) && !query.trim().is_empty() {
-                if let Ok(query_vec) = provider.embed(query).await {
-                    let knn_result = call_blocking(db.clone(), move |db| {
-                        db.knn_memories(chat_id, &query_vec, 20)
-                    })
-                    .await;
-                    if let Ok(knn_rows) = knn_result {
-                        let by_id: std::collections::HashMap<i64, &Memory> =
-                            memories.iter().map(|m| (m.id, m)).collect();
-                        for (id, _) in knn_rows {
-                            if let Some(mem) = by_id.get(&id) {
-                                ordered.push(*mem);
-                            }
-                        }
-                        if !ordered.is_empty() {
-                            retrieval_method = "knn";
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    #[cfg(not(feature = "sqlite-vec"))]
-    {
         let _ = embedding;
-    }
-
-    if ordered.is_empty() {
-        let query_tokens = tokenize_for_relevance(query);
-        let mut scored: Vec<(usize, usize, &Memory)> = memories
-            .iter()
-            .enumerate()
-            .map(|(idx, m)| {
-                (
-                    score_relevance_with_cache(&m.content, &query_tokens),
-                    idx,
-                    m,
-                )
-            })
-            .collect();
-        if !query.is_empty() {
-            scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
-        }
-        ordered = scored.into_iter().map(|(_, _, m)| m).collect();
-    }
+        (rank_by_keywords(memories, query), "provider")
+    };
 
     let mut out = String::from("<structured_memories>\n");
     let mut used_tokens = 0usize;
@@ -416,6 +461,183 @@ pub(crate) async fn build_db_memory_context(
     out
 }
 
+/// Order memories by token overlap with the query, keeping the input order for ties.
+fn rank_by_keywords(memories: Vec<Memory>, query: &str) -> Vec<Memory> {
+    let query_tokens = tokenize_for_relevance(query);
+    let mut scored: Vec<(usize, usize, Memory)> = memories
+        .into_iter()
+        .enumerate()
+        .map(|(idx, m)| {
+            (
+                score_relevance_with_cache(&m.content, &query_tokens),
+                idx,
+                m,
+            )
+        })
+        .collect();
+    if !query.is_empty() {
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    }
+    scored.into_iter().map(|(_, _, m)| m).collect()
+}
+
+/// Reciprocal-rank fusion constant: a memory at rank `r` in a list scores `weight / (RRF_K + r)`.
+const RRF_K: f64 = 60.0;
+/// Weight of the confidence and recency lists, which rank every candidate, relative to
+/// the lexical and semantic lists, which only rank matches.
+const RRF_PRIOR_WEIGHT: f64 = 0.5;
+const FTS_CANDIDATES: usize = 50;
+const KNN_CANDIDATES: usize = 20;
+
+/// Sum each id's reciprocal-rank scores over the weighted rankings, best first.
+/// Ties keep the order in which ids first appear.
+fn reciprocal_rank_fusion(rankings: &[(&[i64], f64)]) -> Vec<i64> {
+    let mut scores: Vec<(i64, f64)> = Vec::new();
+    let mut index: std::collections::HashMap<i64, usize> = std::collections::HashMap::new();
+    for (ranking, weight) in rankings {
+        for (rank, id) in ranking.iter().enumerate() {
+            let score = weight / (RRF_K + rank as f64 + 1.0);
+            match index.get(id) {
+                Some(&i) => scores[i].1 += score,
+                None => {
+                    index.insert(*id, scores.len());
+                    scores.push((*id, score));
+                }
+            }
+        }
+    }
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scores.into_iter().map(|(id, _)| id).collect()
+}
+
+/// Rank memories stored in the local database by fusing BM25 full-text matches (or token
+/// overlap when FTS finds nothing, e.g. for CJK text), embedding similarity when available,
+/// confidence and recency. Matches outside the recent `candidates` are pulled in too.
+/// Returns the order and the retrieval method that produced it.
+async fn rank_hybrid(
+    db: &Arc<Database>,
+    embedding: Option<&Arc<dyn EmbeddingProvider>>,
+    chat_id: i64,
+    query: &str,
+    candidates: Vec<Memory>,
+) -> (Vec<Memory>, &'static str) {
+    let mut pool: std::collections::HashMap<i64, Memory> = std::collections::HashMap::new();
+    let mut recent_ids = Vec::with_capacity(candidates.len());
+    for m in candidates {
+        recent_ids.push(m.id);
+        pool.insert(m.id, m);
+    }
+
+    let fts_query = query.to_string();
+    let fts_hits = if query.trim().is_empty() {
+        Vec::new()
+    } else {
+        call_blocking(db.clone(), move |db| {
+            db.search_memories_fts(chat_id, &fts_query, FTS_CANDIDATES)
+        })
+        .await
+        .unwrap_or_default()
+    };
+    let (lexical, lexical_method) = if fts_hits.is_empty() {
+        let query_tokens = tokenize_for_relevance(query);
+        let mut scored: Vec<(usize, i64)> = recent_ids
+            .iter()
+            .map(|id| {
+                (
+                    score_relevance_with_cache(&pool[id].content, &query_tokens),
+                    *id,
+                )
+            })
+            .filter(|(score, _)| *score > 0)
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0));
+        (
+            scored.into_iter().map(|(_, id)| id).collect::<Vec<_>>(),
+            "keyword",
+        )
+    } else {
+        let ids = fts_hits.iter().map(|(m, _)| m.id).collect();
+        for (m, _) in fts_hits {
+            pool.entry(m.id).or_insert(m);
+        }
+        (ids, "fts")
+    };
+
+    let semantic = semantic_ranking(db, embedding, chat_id, query, &mut pool).await;
+
+    let mut by_confidence: Vec<&Memory> = pool.values().collect();
+    by_confidence.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    let by_confidence: Vec<i64> = by_confidence.into_iter().map(|m| m.id).collect();
+    let mut by_recency: Vec<&Memory> = pool.values().collect();
+    by_recency.sort_by(|a, b| {
+        b.last_seen_at
+            .cmp(&a.last_seen_at)
+            .then_with(|| b.updated_at.cmp(&a.updated_at))
+    });
+    let by_recency: Vec<i64> = by_recency.into_iter().map(|m| m.id).collect();
+
+    let order = reciprocal_rank_fusion(&[
+        (&lexical, 1.0),
+        (&semantic, 1.0),
+        (&by_confidence, RRF_PRIOR_WEIGHT),
+        (&by_recency, RRF_PRIOR_WEIGHT),
+    ]);
+    let method = match (lexical.is_empty(), semantic.is_empty()) {
+        (false, false) => "hybrid",
+        (false, true) => lexical_method,
+        (true, false) => "knn",
+        (true, true) => "recency",
+    };
+    let ordered = order
+        .into_iter()
+        .filter_map(|id| pool.remove(&id))
+        .collect();
+    (ordered, method)
+}
+
+/// Memory ids nearest to the query embedding, adding hits missing from `pool` to it.
+async fn semantic_ranking(
+    db: &Arc<Database>,
+    embedding: Option<&Arc<dyn EmbeddingProvider>>,
+    chat_id: i64,
+    query: &str,
+    pool: &mut std::collections::HashMap<i64, Memory>,
+) -> Vec<i64> {
+    let Some(provider) = embedding else {
+        return Vec::new();
+    };
+    if query.trim().is_empty() {
+        return Vec::new();
+    }
+    let Ok(query_vec) = provider.embed(query).await else {
+        return Vec::new();
+    };
+    let known: std::collections::HashSet<i64> = pool.keys().copied().collect();
+    let rows = call_blocking(db.clone(), move |db| {
+        let mut rows = Vec::new();
+        for (id, _) in db.knn_memories(chat_id, &query_vec, KNN_CANDIDATES)? {
+            if known.contains(&id) {
+                rows.push((id, None));
+            } else if let Some(m) = db.get_memory_by_id(id)? {
+                if !m.is_archived && m.confidence >= 0.45 {
+                    rows.push((id, Some(m)));
+                }
+            }
+        }
+        Ok(rows)
+    })
+    .await
+    .unwrap_or_default();
+    rows.into_iter()
+        .map(|(id, memory)| {
+            if let Some(m) = memory {
+                pool.insert(id, m);
+            }
+            id
+        })
+        .collect()
+}
+
 pub(crate) async fn apply_reflector_extractions(
     state: &Arc<AppState>,
     chat_id: i64,
@@ -425,14 +647,12 @@ pub(crate) async fn apply_reflector_extractions(
     let mut inserted = 0usize;
     let mut updated = 0usize;
     let mut skipped = 0usize;
-    #[cfg(feature = "sqlite-vec")]
+    let mut review = ReflectorReviewGate::load(state, chat_id).await;
     let dedup_method = if state.embedding.is_some() {
         "semantic"
     } else {
         "jaccard"
     };
-    #[cfg(not(feature = "sqlite-vec"))]
-    let dedup_method = "jaccard";
 
     let mut seen_contents: Vec<(i64, String)> =
         existing.iter().map(|m| (m.id, m.content.clone())).collect();
@@ -468,10 +688,31 @@ pub(crate) async fn apply_reflector_extractions(
         if !memory_quality::memory_quality_ok(&content) {
             continue;
         }
+        if review.already_reviewed(&content) {
+            skipped += 1;
+            continue;
+        }
+        let stated_confidence = item
+            .get("confidence")
+            .and_then(|v| v.as_f64())
+            .map(|c| c.clamp(0.0, 1.0));
 
         let supersedes_id = item.get("supersedes_id").and_then(|v| v.as_i64());
         if let Some(sid) = supersedes_id {
             if existing.iter().any(|m| m.id == sid) {
+                if !review
+                    .admit(
+                        state,
+                        "update",
+                        Some(sid),
+                        &content,
+                        &category,
+                        stated_confidence.unwrap_or(0.78),
+                    )
+                    .await
+                {
+                    continue;
+                }
                 let content = content.to_string();
                 let category = category.to_string();
                 let db_content = content.clone();
@@ -482,10 +723,7 @@ pub(crate) async fn apply_reflector_extractions(
                     .is_ok()
                 {
                     updated += 1;
-                    #[cfg(feature = "sqlite-vec")]
-                    {
-                        let _ = upsert_memory_embedding(state, sid, &content).await;
-                    }
+                    let _ = upsert_memory_embedding(state, sid, &content).await;
                     seen_contents.push((sid, content));
                 }
                 continue;
@@ -498,6 +736,19 @@ pub(crate) async fn apply_reflector_extractions(
                 if !prev.content.eq_ignore_ascii_case(&content)
                     && !jaccard_similar(&prev.content, &content, 0.85)
                 {
+                    if !review
+                        .admit(
+                            state,
+                            "supersede",
+                            Some(prev_id),
+                            &content,
+                            &category,
+                            stated_confidence.unwrap_or(0.74),
+                        )
+                        .await
+                    {
+                        continue;
+                    }
                     let new_content = content.to_string();
                     let new_category = category.to_string();
                     if let Ok(new_id) = state
@@ -513,10 +764,7 @@ pub(crate) async fn apply_reflector_extractions(
                         .await
                     {
                         updated += 1;
-                        #[cfg(feature = "sqlite-vec")]
-                        {
-                            let _ = upsert_memory_embedding(state, new_id, &content).await;
-                        }
+                        let _ = upsert_memory_embedding(state, new_id, &content).await;
                         topic_latest.insert(topic_key, new_id);
                         seen_contents.push((new_id, content));
                         continue;
@@ -526,32 +774,22 @@ pub(crate) async fn apply_reflector_extractions(
         }
 
         let duplicate_id = {
-            #[cfg(feature = "sqlite-vec")]
-            {
-                if let Some(provider) = &state.embedding {
-                    if let Ok(query_vec) = provider.embed(&content).await {
-                        let nearest = call_blocking(state.db.clone(), move |db| {
-                            db.knn_memories(chat_id, &query_vec, 1)
-                        })
-                        .await
-                        .ok()
-                        .and_then(|rows| rows.first().copied());
-                        nearest.and_then(|(id, dist)| if dist < 0.15 { Some(id) } else { None })
-                    } else {
-                        seen_contents
-                            .iter()
-                            .find(|(_, existing)| jaccard_similar(existing, &content, 0.5))
-                            .map(|(id, _)| *id)
-                    }
+            if let Some(provider) = &state.embedding {
+                if let Ok(query_vec) = provider.embed(&content).await {
+                    let nearest = call_blocking(state.db.clone(), move |db| {
+                        db.knn_memories(chat_id, &query_vec, 1)
+                    })
+                    .await
+                    .ok()
+                    .and_then(|rows| rows.first().copied());
+                    nearest.and_then(|(id, dist)| if dist < 0.15 { Some(id) } else { None })
                 } else {
                     seen_contents
                         .iter()
                         .find(|(_, existing)| jaccard_similar(existing, &content, 0.5))
                         .map(|(id, _)| *id)
                 }
-            }
-            #[cfg(not(feature = "sqlite-vec"))]
-            {
+            } else {
                 seen_contents
                     .iter()
                     .find(|(_, existing)| jaccard_similar(existing, &content, 0.5))
@@ -561,6 +799,19 @@ pub(crate) async fn apply_reflector_extractions(
         if let Some(dup_id) = duplicate_id {
             if let Some(existing_mem) = existing_by_id.get(&dup_id) {
                 if should_merge_duplicate(existing_mem, &content, &category) {
+                    if !review
+                        .admit(
+                            state,
+                            "update",
+                            Some(dup_id),
+                            &content,
+                            &category,
+                            stated_confidence.unwrap_or(0.70),
+                        )
+                        .await
+                    {
+                        continue;
+                    }
                     let update_content = content.to_string();
                     let update_category = category.to_string();
                     if state
@@ -592,6 +843,19 @@ pub(crate) async fn apply_reflector_extractions(
             continue;
         }
 
+        if !review
+            .admit(
+                state,
+                "insert",
+                None,
+                &content,
+                &category,
+                stated_confidence.unwrap_or(0.68),
+            )
+            .await
+        {
+            continue;
+        }
         let content = content.to_string();
         let db_content = content.clone();
         let category = category.to_string();
@@ -602,12 +866,7 @@ pub(crate) async fn apply_reflector_extractions(
             .ok();
         if let Some(memory_id) = inserted_id {
             inserted += 1;
-            #[cfg(feature = "sqlite-vec")]
-            {
-                let _ = upsert_memory_embedding(state, memory_id, &content).await;
-            }
-            #[cfg(not(feature = "sqlite-vec"))]
-            let _ = memory_id;
+            let _ = upsert_memory_embedding(state, memory_id, &content).await;
             seen_contents.push((memory_id, content));
             topic_latest.insert(topic_key, memory_id);
         }
@@ -616,12 +875,509 @@ pub(crate) async fn apply_reflector_extractions(
     ReflectorApplyOutcome {
         inserted,
         updated,
-        skipped,
+        skipped: skipped + review.dropped,
+        auto_approved: review.auto_approved,
+        queued_review_ids: review.queued_review_ids,
         dedup_method,
     }
 }
 
-#[cfg(feature = "sqlite-vec")]
+/// A reviewer's decision on a queued reflector fact.
+#[derive(Debug, Clone)]
+pub(crate) enum MemoryReviewDecision {
+    Approve,
+    /// Approve with reviewer-supplied content (and optionally category).
+    Edit {
+        content: String,
+        category: Option<String>,
+    },
+    Reject,
+}
+
+impl MemoryReviewDecision {
+    /// Build an edit decision, validating the text like reflector output.
+    pub(crate) fn edit(content: &str, category: Option<&str>) -> Result<Self, String> {
+        let content = memory_quality::normalize_memory_content(content, 180)
+            .filter(|c| memory_quality::memory_quality_ok(c))
+            .ok_or_else(|| "edited memory is empty or too vague to store".to_string())?;
+        let category = match category.map(|c| c.trim().to_ascii_uppercase()) {
+            None => None,
+            Some(c) if c.is_empty() => None,
+            Some(c) if matches!(c.as_str(), "PROFILE" | "KNOWLEDGE" | "EVENT") => Some(c),
+            Some(c) => {
+                return Err(format!(
+                    "unknown category '{c}' (expected PROFILE, KNOWLEDGE or EVENT)"
+                ))
+            }
+        };
+        Ok(MemoryReviewDecision::Edit { content, category })
+    }
+
+    fn status(&self) -> &'static str {
+        match self {
+            MemoryReviewDecision::Approve => "approved",
+            MemoryReviewDecision::Edit { .. } => "edited",
+            MemoryReviewDecision::Reject => "rejected",
+        }
+    }
+}
+
+/// Apply a decision to a pending review: approvals write the fact through the memory
+/// backend, rejections only record the decision. Returns `Ok(None)` when the review
+/// does not exist or was already decided. If the write fails the review is reopened.
+pub(crate) async fn decide_memory_review(
+    state: &AppState,
+    review_id: i64,
+    decision: MemoryReviewDecision,
+    decided_by: &str,
+) -> Result<Option<MemoryReview>> {
+    let status = decision.status();
+    let (content, category) = match decision {
+        MemoryReviewDecision::Edit { content, category } => (Some(content), category),
+        _ => (None, None),
+    };
+    let decided_by = decided_by.to_string();
+    let Some(mut review) = call_blocking(state.db.clone(), move |db| {
+        db.decide_memory_review(
+            review_id,
+            status,
+            content.as_deref(),
+            category.as_deref(),
+            &decided_by,
+        )
+    })
+    .await?
+    else {
+        return Ok(None);
+    };
+    if status == "rejected" {
+        return Ok(Some(review));
+    }
+
+    match write_reviewed_memory(state, &review).await {
+        Ok(memory_id) => {
+            if let Some(provider) = &state.embedding {
+                let _ = upsert_memory_embedding_with_provider(
+                    state.db.clone(),
+                    provider,
+                    memory_id,
+                    &review.content,
+                )
+                .await;
+            }
+            call_blocking(state.db.clone(), move |db| {
+                db.set_memory_review_memory_id(review_id, memory_id)
+            })
+            .await?;
+            review.memory_id = Some(memory_id);
+            Ok(Some(review))
+        }
+        Err(e) => {
+            if let Err(reopen_err) = call_blocking(state.db.clone(), move |db| {
+                db.reopen_memory_review(review_id)
+            })
+            .await
+            {
+                warn!("Failed to reopen memory review #{review_id}: {reopen_err}");
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Write an approved review. Updates and supersedes fall back to a plain insert when
+/// their target memory has been archived or deleted since the fact was queued.
+async fn write_reviewed_memory(state: &AppState, review: &MemoryReview) -> Result<i64> {
+    let backend = &state.memory_backend;
+    let target = match review.target_memory_id {
+        Some(id) => backend
+            .get_memory_by_id(id)
+            .await?
+            .filter(|m| !m.is_archived)
+            .map(|m| m.id),
+        None => None,
+    };
+    match (review.action.as_str(), target) {
+        ("update", Some(id)) => {
+            if backend
+                .update_memory_with_metadata(
+                    id,
+                    &review.content,
+                    &review.category,
+                    REVIEWED_MEMORY_CONFIDENCE,
+                    "reflector_reviewed",
+                )
+                .await?
+            {
+                return Ok(id);
+            }
+        }
+        ("supersede", Some(id)) => {
+            return Ok(backend
+                .supersede_memory(
+                    id,
+                    &review.content,
+                    &review.category,
+                    "reflector_reviewed",
+                    REVIEWED_MEMORY_CONFIDENCE,
+                    Some("topic_conflict"),
+                )
+                .await?);
+        }
+        _ => {}
+    }
+    Ok(backend
+        .insert_memory_with_metadata(
+            Some(review.chat_id),
+            &review.content,
+            &review.category,
+            "reflector_reviewed",
+            REVIEWED_MEMORY_CONFIDENCE,
+        )
+        .await?)
+}
+
+/// `/review` — list pending reflector facts for this chat, or approve, edit or reject
+/// one by id. Control chats see and may decide reviews from every chat.
+pub async fn handle_review_command(
+    state: &AppState,
+    chat_id: i64,
+    caller_channel: &str,
+    sender_id: Option<&str>,
+    args: &str,
+) -> String {
+    const USAGE: &str =
+        "Usage: /review | /review approve <id> | /review edit <id> <text> | /review reject <id>";
+    let is_control = state.config.control_chat_ids.contains(&chat_id);
+    let mut parts = args.splitn(3, char::is_whitespace);
+    let action = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+
+    if action.is_empty() || action == "list" {
+        let scope = (!is_control).then_some(chat_id);
+        return match call_blocking(state.db.clone(), move |db| {
+            db.get_memory_reviews(scope, Some("pending"), 20)
+        })
+        .await
+        {
+            Ok(reviews) if reviews.is_empty() => {
+                if state.config.memory_review.enabled {
+                    "No memories waiting for review.".to_string()
+                } else {
+                    "No memories waiting for review (memory_review.enabled: false).".to_string()
+                }
+            }
+            Ok(reviews) => {
+                let mut lines = vec!["Memories waiting for review:".to_string()];
+                for r in reviews {
+                    let target = r
+                        .target_memory_id
+                        .map(|id| format!(" memory #{id}"))
+                        .unwrap_or_default();
+                    let chat = if r.chat_id == chat_id {
+                        String::new()
+                    } else {
+                        format!(" chat {}", r.chat_id)
+                    };
+                    lines.push(format!(
+                        "- #{} [{}] {}{}{} (conf {:.2}): {}",
+                        r.id, r.category, r.action, target, chat, r.confidence, r.content
+                    ));
+                }
+                lines.push(USAGE.to_string());
+                lines.join("\n")
+            }
+            Err(e) => format!("Failed to list memory reviews: {e}"),
+        };
+    }
+
+    let Some(review_id) = parts
+        .next()
+        .and_then(|v| v.trim().trim_start_matches('#').parse::<i64>().ok())
+    else {
+        return USAGE.to_string();
+    };
+    let decision = match action.as_str() {
+        "approve" => MemoryReviewDecision::Approve,
+        "reject" => MemoryReviewDecision::Reject,
+        "edit" => match MemoryReviewDecision::edit(parts.next().unwrap_or(""), None) {
+            Ok(decision) => decision,
+            Err(e) => return format!("Cannot edit review #{review_id}: {e}"),
+        },
+        _ => return USAGE.to_string(),
+    };
+
+    match call_blocking(state.db.clone(), move |db| db.get_memory_review(review_id)).await {
+        Ok(Some(review)) if review.chat_id == chat_id || is_control => {}
+        Ok(_) => return format!("Review #{review_id} not found in this chat."),
+        Err(e) => return format!("Failed to load review #{review_id}: {e}"),
+    }
+    let decided_by = format!(
+        "{caller_channel}:{}",
+        sender_id
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .unwrap_or("unknown")
+    );
+    match decide_memory_review(state, review_id, decision, &decided_by).await {
+        Ok(Some(review)) if review.status == "rejected" => {
+            format!("Rejected review #{review_id}. It will not be proposed again.")
+        }
+        Ok(Some(review)) => format!(
+            "Saved memory #{} from review #{review_id}: {}",
+            review.memory_id.unwrap_or_default(),
+            review.content
+        ),
+        Ok(None) => format!("Review #{review_id} was already decided."),
+        Err(e) => format!("Failed to apply review #{review_id}: {e}"),
+    }
+}
+
 pub(crate) fn memory_supports_local_semantic_ranking(memory_backend: &MemoryBackend) -> bool {
     memory_backend.supports_local_semantic_ranking()
 }
+
+/// Export memories and the matching `AGENTS.md` files as a JSONL bundle (see
+/// `microclaw_storage::memory_bundle`).
+pub async fn export_memory_bundle(
+    db: Arc<Database>,
+    memory: &MemoryManager,
+    options: ExportOptions,
+) -> Result<Vec<u8>> {
+    let chat_id = options.chat_id;
+    let global_only = options.global_only;
+    let mut records =
+        call_blocking(db.clone(), move |db| db.export_memory_bundle(&options)).await?;
+
+    let mut agents_files = Vec::new();
+    for (scope, content) in memory.list_agents_files() {
+        let scope = match scope {
+            AgentsFileScope::Global => BundleScope::Global,
+            _ if global_only && chat_id.is_none() => continue,
+            AgentsFileScope::Bot { channel } => {
+                if chat_id.is_some() {
+                    continue;
+                }
+                BundleScope::Bot { channel }
+            }
+            AgentsFileScope::Chat {
+                channel,
+                chat_id: file_chat_id,
+            } => {
+                if chat_id.is_some_and(|cid| cid != file_chat_id) {
+                    continue;
+                }
+                let external =
+                    call_blocking(db.clone(), move |db| db.get_chat_external_id(file_chat_id))
+                        .await?;
+                let Some(external_chat_id) = external else {
+                    continue;
+                };
+                BundleScope::Chat {
+                    channel,
+                    external_chat_id,
+                }
+            }
+        };
+        agents_files.push(BundleRecord::AgentsFile(BundleAgentsFile {
+            scope,
+            content,
+        }));
+    }
+    if let Some(BundleRecord::Header(header)) = records.first_mut() {
+        header.agents_file_count = agents_files.len();
+    }
+    records.extend(agents_files);
+
+    let mut out = Vec::new();
+    write_bundle(&records, &mut out)?;
+    Ok(out)
+}
+
+/// Merge parsed bundle records. Bundled embeddings are reused when they come from the
+/// configured embedding model; everything else is embedded by the reflector's backfill.
+/// `AGENTS.md` files are merged with the same strategy once the database import commits.
+pub async fn import_memory_bundle(
+    db: Arc<Database>,
+    memory: &MemoryManager,
+    embedding: Option<&Arc<dyn EmbeddingProvider>>,
+    records: Vec<BundleRecord>,
+    strategy: MergeStrategy,
+) -> Result<ImportReport> {
+    let model = embedding.map(|p| p.model().to_string());
+    let (mut report, records) = call_blocking(db.clone(), move |db| {
+        let report = db.import_memory_bundle(&records, strategy, model.as_deref())?;
+        db.flush_vector_index()?;
+        Ok((report, records))
+    })
+    .await?;
+
+    for record in records {
+        let BundleRecord::AgentsFile(file) = record else {
+            continue;
+        };
+        let scope = match file.scope {
+            BundleScope::Global => Some(AgentsFileScope::Global),
+            BundleScope::Bot { channel } => {
+                is_channel_dir_name(&channel).then_some(AgentsFileScope::Bot { channel })
+            }
+            BundleScope::Chat {
+                channel,
+                external_chat_id,
+            } if is_channel_dir_name(&channel) => {
+                let lookup = channel.clone();
+                call_blocking(db.clone(), move |db| {
+                    db.get_chat_id_by_channel_and_external_id(&lookup, &external_chat_id)
+                })
+                .await?
+                .map(|chat_id| AgentsFileScope::Chat { channel, chat_id })
+            }
+            BundleScope::Chat { .. } => None,
+        };
+        let Some(scope) = scope else {
+            report.unmapped += 1;
+            continue;
+        };
+        if memory.merge_agents_file(&scope, &file.content, strategy)? {
+            report.agents_files += 1;
+        } else {
+            report.skipped += 1;
+        }
+    }
+    Ok(report)
+}
+
+/// Bundled channel names become directories under `groups/`, so only plain names pass.
+fn is_channel_dir_name(channel: &str) -> bool {
+    let channel = channel.trim();
+    !channel.is_empty() && channel != "." && channel != ".." && !channel.contains(['/', '\\'])
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "microclaw memory",
+    about = "Export or import structured memories",
+    disable_help_subcommand = true
+)]
+struct MemoryCli {
+    #[command(subcommand)]
+    command: MemoryCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum MemoryCommand {
+    /// Write memories to a JSONL bundle
+    Export {
+        /// Output file (`-` or omitted for stdout)
+        #[arg(long, short, value_name = "PATH")]
+        out: Option<PathBuf>,
+        /// Only memories of this chat
+        #[arg(long, conflicts_with = "global")]
+        chat_id: Option<i64>,
+        /// Only global memories
+        #[arg(long)]
+        global: bool,
+        /// Leave out archived (superseded or stale) memories
+        #[arg(long)]
+        active_only: bool,
+        /// Include stored embedding vectors
+        #[arg(long)]
+        embeddings: bool,
+    },
+    /// Merge a JSONL bundle into this instance
+    Import {
+        /// Bundle file (`-` for stdin)
+        path: PathBuf,
+        /// How to merge with existing memories: skip, overwrite or dedupe
+        #[arg(long, default_value = "skip", value_parser = parse_merge_strategy)]
+        strategy: MergeStrategy,
+    },
+}
+
+fn parse_merge_strategy(value: &str) -> std::result::Result<MergeStrategy, String> {
+    MergeStrategy::parse(value)
+        .ok_or_else(|| format!("unknown strategy '{value}' (expected skip, overwrite or dedupe)"))
+}
+
+pub async fn handle_memory_cli(args: &[String]) -> Result<()> {
+    let cli = match MemoryCli::try_parse_from(
+        std::iter::once("memory").chain(args.iter().map(String::as_str)),
+    ) {
+        Ok(cli) => cli,
+        Err(err)
+            if matches!(
+                err.kind(),
+                clap::error::ErrorKind::DisplayHelp
+                    | clap::error::ErrorKind::DisplayVersion
+                    | clap::error::ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+            ) =>
+        {
+            err.print()?;
+            return Ok(());
+        }
+        Err(err) => return Err(anyhow!(err.to_string())),
+    };
+
+    let config = Config::load()?;
+    let db = Arc::new(Database::new(&config.runtime_data_dir())?);
+    let memory = MemoryManager::new(&config.runtime_data_dir());
+    let embedding = crate::embedding::create_provider(&config);
+    if let Some(provider) = &embedding {
+        db.set_vector_backend(config.vector_backend());
+        if let Err(e) = db.prepare_vector_index(provider.dimension()) {
+            warn!("Vector index unavailable, embeddings are skipped: {e}");
+        }
+    }
+
+    match cli.command {
+        MemoryCommand::Export {
+            out,
+            chat_id,
+            global,
+            active_only,
+            embeddings,
+        } => {
+            let options = ExportOptions {
+                chat_id,
+                global_only: global,
+                include_archived: !active_only,
+                include_embeddings: embeddings,
+            };
+            let bundle = export_memory_bundle(db, &memory, options).await?;
+            let lines = bundle.iter().filter(|b| **b == b'\n').count();
+            match out.filter(|p| p.as_os_str() != "-") {
+                Some(path) => {
+                    std::fs::write(&path, &bundle)?;
+                    eprintln!(
+                        "Exported {} records to {}",
+                        lines.saturating_sub(1),
+                        path.display()
+                    );
+                }
+                None => std::io::stdout().write_all(&bundle)?,
+            }
+        }
+        MemoryCommand::Import { path, strategy } => {
+            let mut bundle = Vec::new();
+            if path.as_os_str() == "-" {
+                std::io::stdin().read_to_end(&mut bundle)?;
+            } else {
+                bundle = std::fs::read(&path)?;
+            }
+            let records = read_bundle(bundle.as_slice())?;
+            let report =
+                import_memory_bundle(db, &memory, embedding.as_ref(), records, strategy).await?;
+            println!(
+                "Imported with strategy {}: inserted={} updated={} skipped={} unmapped={} edges={} embeddings={} agents_files={}",
+                strategy.as_str(),
+                report.inserted,
+                report.updated,
+                report.skipped,
+                report.unmapped,
+                report.edges,
+                report.embeddings,
+                report.agents_files
+            );
+        }
+    }
+    Ok(())
+}
@@ -31,7 +31,7 @@ use crate::channels::{
 use crate::config::Config;
 use crate::embedding::EmbeddingProvider;
 use crate::hooks::HookManager;
-use crate::llm::LlmProvider;
+use crate::llm::{LlmClientPool, LlmHealthRegistry, LlmProvider};
 use crate::memory::MemoryManager;
 use crate::memory_backend::MemoryBackend;
 use crate::skills::SkillManager;
@@ -51,6 +51,8 @@ pub struct AppState {
     pub skills: SkillManager,
     pub hooks: Arc<HookManager>,
     pub llm: Box<dyn LlmProvider>,
+    pub llm_health: Arc<LlmHealthRegistry>,
+    pub llm_clients: Arc<LlmClientPool>,
     pub llm_provider_overrides: Arc<RwLock<HashMap<String, String>>>,
     pub llm_model_overrides: Arc<RwLock<HashMap<String, String>>>,
     pub embedding: Option<Arc<dyn EmbeddingProvider>>,
@@ -137,15 +139,13 @@ pub async fn run(
     let db = Arc::new(db);
     let llm = crate::llm::create_provider(&config);
     let embedding = crate::embedding::create_provider(&config);
-    #[cfg(feature = "sqlite-vec")]
-    {
-        let dim = embedding
-            .as_ref()
-            .map(|e| e.dimension())
-            .or(config.embedding_dim)
-            .unwrap_or(1536);
-        if let Err(e) = db.prepare_vector_index(dim) {
-            warn!("Failed to initialize sqlite-vec index: {e}");
+    db.set_vector_backend(config.vector_backend());
+    if let Some(provider) = &embedding {
+        if let Err(e) = db.prepare_vector_index(provider.dimension()) {
+            warn!(
+                "Failed to initialize {} vector index: {e}",
+                db.vector_backend().as_str()
+            );
         }
     }
 
@@ -412,12 +412,17 @@ pub async fn run(
         registry.register(Arc::new(WebAdapter));
     }
 
+    let hooks = Arc::new(HookManager::from_config(&config).with_db(db.clone()));
+    registry.set_outbound_filter(hooks.clone());
     let channel_registry = Arc::new(registry);
 
-    let memory_backend = Arc::new(MemoryBackend::new(
-        db.clone(),
-        crate::memory_backend::MemoryMcpClient::discover(&mcp_manager),
-    ));
+    let memory_backend = Arc::new(
+        MemoryBackend::new(
+            db.clone(),
+            crate::memory_backend::MemoryMcpClient::discover(&mcp_manager),
+        )
+        .with_hooks(hooks.clone()),
+    );
     let mut tools = ToolRegistry::new(
         &config,
         channel_registry.clone(),
@@ -429,7 +434,6 @@ pub async fn run(
         tools.add_tool(Box::new(crate::tools::mcp::McpTool::new(server, tool_info)));
     }
 
-    let hooks = Arc::new(HookManager::from_config(&config).with_db(db.clone()));
     let llm_provider_overrides = config.llm_provider_overrides();
 
     let metric_exporter = OtlpMetricExporter::from_observability(config.observability.as_ref());
@@ -444,6 +448,8 @@ pub async fn run(
         skills,
         hooks,
         llm,
+        llm_health: Arc::new(LlmHealthRegistry::new()),
+        llm_clients: Arc::new(LlmClientPool::new()),
         llm_provider_overrides: Arc::new(RwLock::new(llm_provider_overrides)),
         llm_model_overrides: Arc::new(RwLock::new(llm_model_overrides)),
         embedding,
@@ -713,6 +719,7 @@ pub async fn run(
         tokio::signal::ctrl_c()
             .await
             .map_err(|e| anyhow!("Failed to listen for Ctrl-C: {e}"))?;
+        microclaw_tools::sandbox::remove_host_firewalls().await;
         Ok(())
     } else {
         Err(anyhow!(
@@ -5,8 +5,9 @@ use chrono::Utc;
 use tokio::time::{Duration, Instant, MissedTickBehavior};
 use tracing::{error, info, warn};
 
-use crate::agent_engine::process_with_agent;
 use crate::agent_engine::AgentRequestContext;
+use crate::agent_engine::{process_with_agent, process_with_agent_structured};
+use crate::hooks::HookOutcome;
 use crate::memory_service::apply_reflector_extractions;
 use crate::runtime::AppState;
 use microclaw_channels::channel::{
@@ -96,51 +97,86 @@ async fn run_due_tasks(state: &Arc<AppState>) {
                 }
             });
 
-        // Run agent loop with the task prompt
-        let (success, result_summary) = match process_with_agent(
-            state,
-            AgentRequestContext {
+        let mut prompt = task.prompt.clone();
+        let mut skipped_by_hook = None;
+        if let Ok(hook_outcome) = state
+            .hooks
+            .run_scheduled_task(&task, &routing.channel_name)
+            .await
+        {
+            match hook_outcome {
+                HookOutcome::Block { reason } => skipped_by_hook = Some(reason),
+                HookOutcome::Allow { patches } => {
+                    for patch in patches {
+                        if let Some(v) = patch.get("prompt").and_then(|v| v.as_str()) {
+                            prompt = v.to_string();
+                        }
+                    }
+                }
+            }
+        }
+
+        let (success, result_summary) = if let Some(reason) = skipped_by_hook {
+            info!("Scheduler: task #{} skipped by hook: {reason}", task.id);
+            (true, Some(format!("Skipped by hook: {reason}")))
+        } else {
+            // Run agent loop with the task prompt
+            let context = AgentRequestContext {
                 caller_channel: &routing.channel_name,
                 chat_id: task.chat_id,
                 chat_type: routing.conversation.as_agent_chat_type(),
-            },
-            Some(&task.prompt),
-            None,
-        )
-        .await
-        {
-            Ok(response) => {
-                if !response.is_empty() {
+            };
+            let outcome = match task.response_schema.as_deref() {
+                Some(raw) => match serde_json::from_str::<serde_json::Value>(raw) {
+                    // Tasks with a response schema deliver the validated JSON.
+                    Ok(schema) => {
+                        process_with_agent_structured(state, context, Some(&prompt), schema, None)
+                            .await
+                            .map(|reply| {
+                                serde_json::to_string_pretty(&reply.output).unwrap_or(reply.text)
+                            })
+                    }
+                    Err(e) => Err(anyhow::anyhow!(
+                        "stored response schema is not valid JSON: {e}"
+                    )),
+                },
+                None => process_with_agent(state, context, Some(&prompt), None).await,
+            };
+            match outcome {
+                Ok(response) => {
+                    if !response.is_empty() {
+                        let bot_username =
+                            state.config.bot_username_for_channel(&routing.channel_name);
+                        let _ = deliver_and_store_bot_message(
+                            &state.channel_registry,
+                            state.db.clone(),
+                            &bot_username,
+                            task.chat_id,
+                            &response,
+                        )
+                        .await;
+                    }
+                    let summary = if response.len() > 200 {
+                        format!("{}...", &response[..floor_char_boundary(&response, 200)])
+                    } else {
+                        response
+                    };
+                    (true, Some(summary))
+                }
+                Err(e) => {
+                    error!("Scheduler: task #{} failed: {e}", task.id);
+                    let err_text = format!("Scheduled task #{} failed: {e}", task.id);
                     let bot_username = state.config.bot_username_for_channel(&routing.channel_name);
                     let _ = deliver_and_store_bot_message(
                         &state.channel_registry,
                         state.db.clone(),
                         &bot_username,
                         task.chat_id,
-                        &response,
+                        &err_text,
                     )
                     .await;
+                    (false, Some(format!("Error: {e}")))
                 }
-                let summary = if response.len() > 200 {
-                    format!("{}...", &response[..floor_char_boundary(&response, 200)])
-                } else {
-                    response
-                };
-                (true, Some(summary))
-            }
-            Err(e) => {
-                error!("Scheduler: task #{} failed: {e}", task.id);
-                let err_text = format!("Scheduled task #{} failed: {e}", task.id);
-                let bot_username = state.config.bot_username_for_channel(&routing.channel_name);
-                let _ = deliver_and_store_bot_message(
-                    &state.channel_registry,
-                    state.db.clone(),
-                    &bot_username,
-                    task.chat_id,
-                    &err_text,
-                )
-                .await;
-                (false, Some(format!("Error: {e}")))
             }
         };
 
@@ -230,7 +266,8 @@ Rules:
 - Each memory < 100 characters, specific and concrete
 - Category must be exactly one of: PROFILE (user attributes/preferences), KNOWLEDGE (facts/expertise), EVENT (significant things that happened)
 - If a new memory updates or supersedes an existing one, add "supersedes_id": <id> to replace it
-- Output ONLY valid JSON array: [{"content":"...","category":"PROFILE","supersedes_id":null}]
+- "confidence" is how certain you are (0.0-1.0) that the memory is correct and durable; stated facts score high, inferences lower
+- Output ONLY valid JSON array: [{"content":"...","category":"PROFILE","supersedes_id":null,"confidence":0.8}]
 - If nothing worth remembering: []
 
 CRITICAL — how to memorize bugs and problems:
@@ -245,7 +282,6 @@ CRITICAL — how to memorize bugs and problems:
   GOOD: "TODO: strictly follow TOOLS.md rules for every tool call"
 - The memory should tell the agent HOW TO BEHAVE CORRECTLY, never describe the broken behavior."#;
 
-#[cfg(feature = "sqlite-vec")]
 async fn backfill_embeddings(state: &Arc<AppState>) {
     if state.embedding.is_none() {
         return;
@@ -261,6 +297,9 @@ async fn backfill_embeddings(state: &Arc<AppState>) {
     for mem in pending {
         let _ = crate::memory_service::upsert_memory_embedding(state, mem.id, &mem.content).await;
     }
+    if let Err(e) = call_blocking(state.db.clone(), |db| db.flush_vector_index()).await {
+        warn!("Reflector: failed to persist vector index: {e}");
+    }
 }
 
 pub fn spawn_reflector(state: Arc<AppState>) {
@@ -339,7 +378,6 @@ fn parse_reflector_json_array(text: &str) -> Result<Vec<serde_json::Value>, serd
 }
 
 async fn run_reflector(state: &Arc<AppState>) {
-    #[cfg(feature = "sqlite-vec")]
     backfill_embeddings(state).await;
 
     let _ = call_blocking(state.db.clone(), move |db| db.archive_stale_memories(30)).await;
@@ -585,6 +623,8 @@ async fn reflect_for_chat(state: &Arc<AppState>, chat_id: i64) {
     let inserted = outcome.inserted;
     let updated = outcome.updated;
     let skipped = outcome.skipped;
+    let auto_approved = outcome.auto_approved;
+    let queued_review_ids = outcome.queued_review_ids;
     let dedup_method = outcome.dedup_method;
 
     if let Some(ts) = latest_message_ts {
@@ -594,15 +634,16 @@ async fn reflect_for_chat(state: &Arc<AppState>, chat_id: i64) {
         .await;
     }
 
-    if inserted > 0 || updated > 0 {
+    if inserted > 0 || updated > 0 || !queued_review_ids.is_empty() {
         info!(
-            "Reflector: chat {chat_id} -> {inserted} new ({dedup_method} dedup), {updated} updated, {skipped} skipped"
+            "Reflector: chat {chat_id} -> {inserted} new ({dedup_method} dedup), {updated} updated, {skipped} skipped, {} queued for review",
+            queued_review_ids.len()
         );
     }
 
     let finished_at = Utc::now().to_rfc3339();
     let _ = call_blocking(state.db.clone(), move |db| {
-        db.log_reflector_run(
+        let run_id = db.log_reflector_run(
             chat_id,
             &started_at,
             &finished_at,
@@ -613,8 +654,11 @@ async fn reflect_for_chat(state: &Arc<AppState>, chat_id: i64) {
             dedup_method,
             true,
             None,
-        )
-        .map(|_| ())
+        )?;
+        if !queued_review_ids.is_empty() || auto_approved > 0 {
+            db.link_memory_reviews_to_run(run_id, &queued_review_ids, auto_approved)?;
+        }
+        Ok(())
     })
     .await;
 }
@@ -695,6 +695,7 @@ fn default_souls_dir_for_setup() -> String {
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum ProviderProtocol {
     Anthropic,
+    Gemini,
     OpenAiCompat,
 }
 
@@ -762,6 +763,17 @@ const PROVIDER_PRESETS: &[ProviderPreset] = &[
             "gemini-2.5-flash-lite",
         ],
     },
+    ProviderPreset {
+        id: "gemini",
+        label: "Google Gemini (native API)",
+        protocol: ProviderProtocol::Gemini,
+        default_base_url: "https://generativelanguage.googleapis.com/v1beta",
+        models: &[
+            "gemini-2.5-flash",
+            "gemini-2.5-pro",
+            "gemini-2.5-flash-lite",
+        ],
+    },
     ProviderPreset {
         id: "aliyun-bailian",
         label: "Alibaba Cloud Bailian",
@@ -1625,7 +1637,7 @@ impl SetupApp {
                 },
                 Field {
                     key: "EMBEDDING_PROVIDER".into(),
-                    label: "Embedding provider (optional: openai/ollama)".into(),
+                    label: "Embedding provider (optional: openai/ollama/local)".into(),
                     value: existing
                         .get("EMBEDDING_PROVIDER")
                         .cloned()
@@ -2807,6 +2819,8 @@ impl SetupApp {
                     .filter(|v| !v.is_empty()),
                 models: Vec::new(),
                 show_thinking: Some(draft.show_thinking),
+                thinking_budget_tokens: None,
+                fallback_providers: Vec::new(),
             };
             presets.insert(id, profile);
         }
@@ -5506,6 +5520,32 @@ fn perform_online_validation(
             )));
         }
         checks.push(format!("LLM OK (anthropic, model={model})"));
+    } else if protocol == ProviderProtocol::Gemini {
+        let base = if base_url.is_empty() {
+            preset.map(|p| p.default_base_url).unwrap_or_default()
+        } else {
+            base_url
+        };
+        let base = base.trim_end_matches('/').trim_end_matches("/openai");
+        let body = serde_json::json!({
+            "contents": [{"role": "user", "parts": [{"text": "hi"}]}],
+            "generationConfig": {"maxOutputTokens": VALIDATION_MAX_OUTPUT_TOKENS}
+        });
+        let resp = client
+            .post(format!("{base}/models/{model}:generateContent"))
+            .header("x-goog-api-key", api_key)
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .send()?;
+        let status = resp.status();
+        if !status.is_success() {
+            let text = resp.text().unwrap_or_default();
+            return Err(MicroClawError::Config(format!(
+                "LLM validation failed: {}",
+                extract_openai_error_detail(status, &text)
+            )));
+        }
+        checks.push(format!("LLM OK (gemini, model={model})"));
     } else {
         let base = resolve_openai_compat_validation_base(provider, base_url, preset);
         let resp = if is_openai_codex_provider(provider) {
@@ -6632,9 +6672,7 @@ fn save_config_yaml(
         || !embedding_model.is_empty()
         || !embedding_dim.is_empty()
     {
-        yaml.push_str(
-            "\n# Optional embedding config for semantic memory retrieval (requires sqlite-vec feature)\n",
-        );
+        yaml.push_str("\n# Optional embedding config for semantic memory retrieval\n");
         if !embedding_provider.is_empty() {
             yaml.push_str(&format!("embedding_provider: \"{}\"\n", embedding_provider));
         }
@@ -0,0 +1,525 @@
+//! JSON-schema constrained final answers.
+//!
+//! API callers and scheduled tasks can attach a JSON schema to a request. The
+//! agent loop then exposes a `final_answer` tool whose input is that schema,
+//! validates whatever the model submits, and feeds validation errors back to the
+//! model until it produces a conforming value or runs out of attempts.
+
+use serde_json::{json, Value};
+
+use crate::llm_types::ToolDefinition;
+
+pub const FINAL_ANSWER_TOOL_NAME: &str = "final_answer";
+/// Number of invalid submissions tolerated before the request fails.
+pub const MAX_STRUCTURED_OUTPUT_ATTEMPTS: usize = 3;
+const MAX_REPORTED_ERRORS: usize = 8;
+const WRAPPED_VALUE_KEY: &str = "value";
+
+/// Final reply of a structured-output run.
+#[derive(Debug, Clone)]
+pub struct StructuredReply {
+    pub text: String,
+    pub output: Value,
+}
+
+/// Per-request structured output state threaded through the agent loop.
+#[derive(Debug)]
+pub struct StructuredOutput {
+    schema: Value,
+    /// Tool inputs must be objects, so non-object schemas are wrapped in `{"value": ...}`.
+    wrapped: bool,
+    failures: usize,
+    result: Option<Value>,
+}
+
+impl StructuredOutput {
+    pub fn new(schema: Value) -> Result<Self, String> {
+        check_schema(&schema)?;
+        let wrapped = !is_object_schema(&schema);
+        Ok(StructuredOutput {
+            schema,
+            wrapped,
+            failures: 0,
+            result: None,
+        })
+    }
+
+    pub fn tool_definition(&self) -> ToolDefinition {
+        let input_schema = if self.wrapped {
+            json!({
+                "type": "object",
+                "properties": { WRAPPED_VALUE_KEY: self.schema },
+                "required": [WRAPPED_VALUE_KEY],
+            })
+        } else {
+            self.schema.clone()
+        };
+        ToolDefinition {
+            name: FINAL_ANSWER_TOOL_NAME.into(),
+            description: "Submit the final answer for this request. The input must satisfy the caller's JSON schema. Call it exactly once, after any other tool calls you need.".into(),
+            input_schema,
+        }
+    }
+
+    pub fn prompt_section(&self) -> String {
+        let schema = serde_json::to_string_pretty(&self.schema).unwrap_or_default();
+        format!(
+            "\n\n# Structured Output\n\nThe caller expects a machine-readable answer. When you are done, call the `{FINAL_ANSWER_TOOL_NAME}` tool with arguments matching this JSON schema instead of replying with free-form text. If the tool reports validation errors, fix them and call it again.\n\n```json\n{schema}\n```"
+        )
+    }
+
+    /// Validate a `final_answer` tool input. Records the value on success.
+    pub fn accept_tool_input(&mut self, input: &Value) -> Result<(), String> {
+        let candidate = if self.wrapped {
+            input.get(WRAPPED_VALUE_KEY).cloned().unwrap_or(Value::Null)
+        } else {
+            input.clone()
+        };
+        self.accept(candidate)
+    }
+
+    /// Validate a plain-text reply, for models that answered with JSON directly.
+    pub fn accept_text(&mut self, text: &str) -> Result<(), String> {
+        match extract_json(text) {
+            Some(candidate) => self.accept(candidate),
+            None => {
+                self.failures += 1;
+                Err(format!(
+                    "reply is not a `{FINAL_ANSWER_TOOL_NAME}` call and contains no JSON value"
+                ))
+            }
+        }
+    }
+
+    fn accept(&mut self, candidate: Value) -> Result<(), String> {
+        let errors = validate(&self.schema, &candidate);
+        if errors.is_empty() {
+            self.result = Some(candidate);
+            return Ok(());
+        }
+        self.failures += 1;
+        let mut message = errors
+            .iter()
+            .take(MAX_REPORTED_ERRORS)
+            .map(|e| format!("- {e}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if errors.len() > MAX_REPORTED_ERRORS {
+            message.push_str(&format!(
+                "\n- ... and {} more",
+                errors.len() - MAX_REPORTED_ERRORS
+            ));
+        }
+        Err(message)
+    }
+
+    pub fn result(&self) -> Option<&Value> {
+        self.result.as_ref()
+    }
+
+    pub fn take_result(&mut self) -> Option<Value> {
+        self.result.take()
+    }
+
+    pub fn exhausted(&self) -> bool {
+        self.failures >= MAX_STRUCTURED_OUTPUT_ATTEMPTS
+    }
+}
+
+fn is_object_schema(schema: &Value) -> bool {
+    match schema.get("type") {
+        Some(Value::String(t)) => t == "object",
+        Some(_) => false,
+        None => schema.get("properties").is_some(),
+    }
+}
+
+const KNOWN_TYPES: &[&str] = &[
+    "object", "array", "string", "number", "integer", "boolean", "null",
+];
+
+/// Keywords `validate` enforces.
+const SUPPORTED_KEYWORDS: &[&str] = &[
+    "type",
+    "enum",
+    "const",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "minItems",
+    "maxItems",
+    "minLength",
+    "maxLength",
+    "minimum",
+    "maximum",
+    "anyOf",
+    "oneOf",
+    "allOf",
+];
+
+/// Annotations that never constrain a value, so ignoring them is harmless.
+const ANNOTATION_KEYWORDS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "default",
+    "examples",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+];
+
+/// Reject schemas the validator cannot interpret, including any keyword it
+/// would otherwise silently skip.
+pub fn check_schema(schema: &Value) -> Result<(), String> {
+    let Some(obj) = schema.as_object() else {
+        return Err("schema must be a JSON object".into());
+    };
+    if let Some(key) = obj.keys().find(|k| {
+        !SUPPORTED_KEYWORDS.contains(&k.as_str()) && !ANNOTATION_KEYWORDS.contains(&k.as_str())
+    }) {
+        return Err(format!("unsupported schema keyword: `{key}`"));
+    }
+    if let Some(t) = obj.get("type") {
+        let names: Vec<&Value> = match t {
+            Value::Array(items) => items.iter().collect(),
+            other => vec![other],
+        };
+        for name in names {
+            match name.as_str() {
+                Some(n) if KNOWN_TYPES.contains(&n) => {}
+                _ => return Err(format!("unsupported schema type: {name}")),
+            }
+        }
+    }
+    if let Some(props) = obj.get("properties") {
+        let Some(props) = props.as_object() else {
+            return Err("`properties` must be an object".into());
+        };
+        for (key, sub) in props {
+            check_schema(sub).map_err(|e| format!("properties.{key}: {e}"))?;
+        }
+    }
+    if let Some(required) = obj.get("required") {
+        if !required
+            .as_array()
+            .is_some_and(|r| r.iter().all(Value::is_string))
+        {
+            return Err("`required` must be an array of strings".into());
+        }
+    }
+    if let Some(items) = obj.get("items") {
+        check_schema(items).map_err(|e| format!("items: {e}"))?;
+    }
+    if let Some(extra) = obj.get("additionalProperties") {
+        if !extra.is_boolean() {
+            check_schema(extra).map_err(|e| format!("additionalProperties: {e}"))?;
+        }
+    }
+    for key in ["anyOf", "oneOf", "allOf"] {
+        if let Some(variants) = obj.get(key) {
+            let Some(variants) = variants.as_array() else {
+                return Err(format!("`{key}` must be an array"));
+            };
+            for (idx, sub) in variants.iter().enumerate() {
+                check_schema(sub).map_err(|e| format!("{key}[{idx}]: {e}"))?;
+            }
+        }
+    }
+    if let Some(values) = obj.get("enum") {
+        if !values.is_array() {
+            return Err("`enum` must be an array".into());
+        }
+    }
+    Ok(())
+}
+
+/// Validate `value` against the supported JSON Schema subset: `type`, `enum`,
+/// `const`, `properties`, `required`, `additionalProperties`, `items`,
+/// `minItems`/`maxItems`, `minLength`/`maxLength`, `minimum`/`maximum` and
+/// `anyOf`/`oneOf`/`allOf`. Returns one message per violation.
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, value, "$", &mut errors);
+    errors
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(obj) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(t) = obj.get("type") {
+        let allowed: Vec<&str> = match t {
+            Value::String(s) => vec![s.as_str()],
+            Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(t, value)) {
+            errors.push(format!(
+                "{path}: expected {}, got {}",
+                allowed.join(" | "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(values) = obj.get("enum").and_then(Value::as_array) {
+        if !values.contains(value) {
+            errors.push(format!(
+                "{path}: must be one of {}",
+                Value::Array(values.clone())
+            ));
+        }
+    }
+    if let Some(expected) = obj.get("const") {
+        if expected != value {
+            errors.push(format!("{path}: must equal {expected}"));
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            if let Some(required) = obj.get("required").and_then(Value::as_array) {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !map.contains_key(key) {
+                        errors.push(format!("{path}: missing required property `{key}`"));
+                    }
+                }
+            }
+            let props = obj.get("properties").and_then(Value::as_object);
+            for (key, item) in map {
+                let child = format!("{path}.{key}");
+                match props.and_then(|p| p.get(key)) {
+                    Some(sub) => validate_at(sub, item, &child, errors),
+                    None => match obj.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            errors.push(format!("{path}: unexpected property `{key}`"))
+                        }
+                        Some(sub @ Value::Object(_)) => validate_at(sub, item, &child, errors),
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = obj.get("minItems").and_then(Value::as_u64) {
+                if (items.len() as u64) < min {
+                    errors.push(format!("{path}: expected at least {min} items"));
+                }
+            }
+            if let Some(max) = obj.get("maxItems").and_then(Value::as_u64) {
+                if (items.len() as u64) > max {
+                    errors.push(format!("{path}: expected at most {max} items"));
+                }
+            }
+            if let Some(sub) = obj.get("items") {
+                for (idx, item) in items.iter().enumerate() {
+                    validate_at(sub, item, &format!("{path}[{idx}]"), errors);
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = obj.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    errors.push(format!("{path}: expected at least {min} characters"));
+                }
+            }
+            if let Some(max) = obj.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    errors.push(format!("{path}: expected at most {max} characters"));
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = obj.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    errors.push(format!("{path}: must be >= {min}"));
+                }
+            }
+            if let Some(max) = obj.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    errors.push(format!("{path}: must be <= {max}"));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(variants) = obj.get("allOf").and_then(Value::as_array) {
+        for sub in variants {
+            validate_at(sub, value, path, errors);
+        }
+    }
+    let matching = |variants: &Vec<Value>| {
+        variants
+            .iter()
+            .filter(|sub| validate(sub, value).is_empty())
+            .count()
+    };
+    if let Some(variants) = obj.get("anyOf").and_then(Value::as_array) {
+        if matching(variants) == 0 {
+            errors.push(format!("{path}: does not match any allowed schema"));
+        }
+    }
+    if let Some(variants) = obj.get("oneOf").and_then(Value::as_array) {
+        if matching(variants) != 1 {
+            errors.push(format!("{path}: must match exactly one allowed schema"));
+        }
+    }
+}
+
+fn matches_type(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|f| f.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Parse a JSON value from a reply, tolerating a surrounding ```json fence.
+fn extract_json(text: &str) -> Option<Value> {
+    let trimmed = text.trim();
+    if let Ok(v) = serde_json::from_str(trimmed) {
+        return Some(v);
+    }
+    let fenced = trimmed
+        .split_once("```")
+        .map(|(_, rest)| rest)
+        .and_then(|rest| rest.rsplit_once("```").map(|(body, _)| body))?;
+    let body = fenced
+        .strip_prefix("json")
+        .or_else(|| fenced.strip_prefix("JSON"))
+        .unwrap_or(fenced);
+    serde_json::from_str(body.trim()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "minLength": 1},
+                "age": {"type": "integer", "minimum": 0},
+                "tags": {"type": "array", "items": {"type": "string"}, "maxItems": 2},
+                "role": {"enum": ["admin", "user"]}
+            },
+            "required": ["name", "age"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_validate_accepts_conforming_value() {
+        let value = json!({"name": "Ada", "age": 36, "tags": ["math"], "role": "admin"});
+        assert!(validate(&person_schema(), &value).is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_each_violation_with_path() {
+        let value = json!({"name": "", "age": -1.5, "tags": ["a", 2, "c"], "extra": true});
+        let errors = validate(&person_schema(), &value);
+        assert!(errors.contains(&"$.name: expected at least 1 characters".to_string()));
+        assert!(errors.contains(&"$.age: expected integer, got number".to_string()));
+        assert!(errors.contains(&"$.tags: expected at most 2 items".to_string()));
+        assert!(errors.contains(&"$.tags[1]: expected string, got number".to_string()));
+        assert!(errors.contains(&"$: unexpected property `extra`".to_string()));
+    }
+
+    #[test]
+    fn test_validate_any_of_and_one_of() {
+        let schema = json!({"anyOf": [{"type": "string"}, {"type": "integer"}]});
+        assert!(validate(&schema, &json!(3)).is_empty());
+        assert_eq!(validate(&schema, &json!(true)).len(), 1);
+
+        let schema = json!({"oneOf": [{"type": "number"}, {"type": "integer"}]});
+        assert_eq!(validate(&schema, &json!(3)).len(), 1);
+        assert!(validate(&schema, &json!(3.5)).is_empty());
+    }
+
+    #[test]
+    fn test_check_schema_rejects_unknown_types() {
+        assert!(check_schema(&json!({"type": "object"})).is_ok());
+        assert!(check_schema(&json!("object")).is_err());
+        assert!(check_schema(&json!({"type": "date"})).is_err());
+        assert!(check_schema(&json!({"properties": {"a": {"type": "strnig"}}})).is_err());
+    }
+
+    #[test]
+    fn test_check_schema_rejects_unenforced_keywords() {
+        let err = check_schema(&json!({"type": "string", "pattern": "^a"})).unwrap_err();
+        assert_eq!(err, "unsupported schema keyword: `pattern`");
+        let err = check_schema(&json!({
+            "type": "object",
+            "properties": {"ids": {"type": "array", "uniqueItems": true}}
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "properties.ids: unsupported schema keyword: `uniqueItems`"
+        );
+        assert!(check_schema(&json!({"$defs": {}, "$ref": "#/$defs/x"})).is_err());
+        assert!(check_schema(&json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Person",
+            "description": "annotations are fine",
+            "type": "object"
+        }))
+        .is_ok());
+        assert!(check_schema(&person_schema()).is_ok());
+    }
+
+    #[test]
+    fn test_structured_output_wraps_non_object_schema() {
+        let mut out =
+            StructuredOutput::new(json!({"type": "array", "items": {"type": "integer"}})).unwrap();
+        let def = out.tool_definition();
+        assert_eq!(def.name, FINAL_ANSWER_TOOL_NAME);
+        assert_eq!(def.input_schema["type"], "object");
+        assert_eq!(def.input_schema["required"][0], "value");
+
+        assert!(out.accept_tool_input(&json!({"value": ["x"]})).is_err());
+        out.accept_tool_input(&json!({"value": [1, 2]})).unwrap();
+        assert_eq!(out.take_result(), Some(json!([1, 2])));
+    }
+
+    #[test]
+    fn test_structured_output_accepts_fenced_text_and_counts_failures() {
+        let mut out = StructuredOutput::new(person_schema()).unwrap();
+        assert!(out.accept_text("no json here").is_err());
+        assert!(out.accept_text(r#"{"name": "Ada"}"#).is_err());
+        assert!(!out.exhausted());
+        out.accept_text("Here you go:\n```json\n{\"name\": \"Ada\", \"age\": 36}\n```")
+            .unwrap();
+        assert_eq!(out.result().unwrap()["age"], 36);
+        assert!(out.accept_text("still wrong").is_err());
+        assert!(out.exhausted());
+    }
+}
@@ -118,6 +118,10 @@ impl Tool for A2ASendTool {
                     "timeout_secs": {
                         "type": "integer",
                         "description": "HTTP timeout in seconds."
+                    },
+                    "response_schema": {
+                        "type": "object",
+                        "description": "Optional JSON schema the remote agent's final answer must satisfy. The validated value is returned as `structured`."
                     }
                 }),
                 &["peer", "message"],
@@ -170,6 +174,7 @@ impl Tool for A2ASendTool {
             source_agent: Some(crate::a2a::local_agent_name(&self.config)),
             source_url: self.config.a2a.public_base_url.clone(),
             message: message.to_string(),
+            response_schema: input.get("response_schema").cloned(),
         };
 
         let mut request = self
@@ -218,7 +223,8 @@ impl Tool for A2ASendTool {
                 "protocol_version": parsed.protocol_version,
                 "agent_name": parsed.agent_name,
                 "session_key": parsed.session_key,
-                "response": parsed.response
+                "response": parsed.response,
+                "structured": parsed.structured
             }))
             .unwrap_or(parsed.response),
         )
@@ -273,6 +279,7 @@ mod tests {
                 agent_name: "Worker".into(),
                 session_key: "a2a:worker".into(),
                 response: "done".into(),
+                structured: None,
             })
         }
 
@@ -0,0 +1,563 @@
+use async_trait::async_trait;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::config::WorkingDirIsolation;
+use microclaw_core::llm_types::ToolDefinition;
+use microclaw_tools::patch::{apply_hunks, parse_patch, whole_file_diff, FileChange};
+use microclaw_tools::path_guard::{PathAccess, PathPolicy, PathPolicyConfig};
+use microclaw_tools::runtime::{with_approval_digest, APPROVAL_DIGEST_KEY};
+use microclaw_tools::sandbox::SandboxRouter;
+use microclaw_tools::sandbox_fs::{EntryKind, SandboxFs};
+
+use super::{schema_object, Tool, ToolResult};
+
+/// Longest diff echoed back in a result or approval preview.
+const MAX_DIFF_CHARS: usize = 12_000;
+
+pub struct ApplyPatchTool {
+    working_dir: PathBuf,
+    working_dir_isolation: WorkingDirIsolation,
+    sandbox_router: Option<Arc<SandboxRouter>>,
+    path_policy: Arc<PathPolicyConfig>,
+}
+
+/// One file as it will look after the patch. `None` means absent.
+struct PlannedFile {
+    path: PathBuf,
+    original: Option<String>,
+    updated: Option<String>,
+}
+
+struct Plan {
+    files: Vec<PlannedFile>,
+    summary: Vec<String>,
+    diff: String,
+    added: usize,
+    removed: usize,
+}
+
+/// Why a patch cannot be applied: a message, or a tool error as it should be
+/// returned, such as a path the path policy refused.
+enum PlanError {
+    Message(String),
+    Refused(ToolResult),
+}
+
+impl From<String> for PlanError {
+    fn from(message: String) -> Self {
+        PlanError::Message(message)
+    }
+}
+
+impl From<&str> for PlanError {
+    fn from(message: &str) -> Self {
+        PlanError::Message(message.to_string())
+    }
+}
+
+impl PlanError {
+    fn message(&self) -> &str {
+        match self {
+            PlanError::Message(m) => m,
+            PlanError::Refused(result) => &result.content,
+        }
+    }
+
+    fn into_result(self) -> ToolResult {
+        match self {
+            PlanError::Message(m) => ToolResult::error(m),
+            PlanError::Refused(result) => result,
+        }
+    }
+}
+
+impl Plan {
+    /// Hash of every change the plan makes, including what `render` truncates.
+    fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for file in &self.files {
+            hasher.update(file.path.to_string_lossy().as_bytes());
+            for content in [&file.original, &file.updated] {
+                match content {
+                    Some(c) => {
+                        hasher.update((c.len() as u64).to_le_bytes());
+                        hasher.update(c.as_bytes());
+                    }
+                    None => hasher.update(u64::MAX.to_le_bytes()),
+                }
+            }
+        }
+        let digest = format!("{:x}", hasher.finalize());
+        digest[..16].to_string()
+    }
+
+    fn render(&self, heading: &str) -> String {
+        let mut diff = self.diff.clone();
+        if diff.len() > MAX_DIFF_CHARS {
+            let mut cut = MAX_DIFF_CHARS;
+            while !diff.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            diff.truncate(cut);
+            diff.push_str("\n... (diff truncated)\n");
+        }
+        format!(
+            "{heading} {} file(s) (+{} -{}):\n{}\n\n```diff\n{}```",
+            self.summary.len(),
+            self.added,
+            self.removed,
+            self.summary.join("\n"),
+            diff
+        )
+    }
+}
+
+impl ApplyPatchTool {
+    pub fn new(working_dir: &str) -> Self {
+        Self::new_with_isolation(working_dir, WorkingDirIsolation::Shared)
+    }
+
+    pub fn new_with_isolation(
+        working_dir: &str,
+        working_dir_isolation: WorkingDirIsolation,
+    ) -> Self {
+        Self {
+            working_dir: PathBuf::from(working_dir),
+            working_dir_isolation,
+            sandbox_router: None,
+            path_policy: Arc::default(),
+        }
+    }
+
+    pub fn with_sandbox_router(mut self, router: Arc<SandboxRouter>) -> Self {
+        self.sandbox_router = Some(router);
+        self
+    }
+
+    pub fn with_path_policy(mut self, policy: Arc<PathPolicyConfig>) -> Self {
+        self.path_policy = policy;
+        self
+    }
+
+    /// Parse the patch and work out every file's new content without writing anything.
+    async fn plan(
+        &self,
+        input: &serde_json::Value,
+    ) -> Result<(Plan, Option<SandboxFs>), PlanError> {
+        let patch = input
+            .get("patch")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing 'patch' parameter")?;
+        let patches = parse_patch(patch).map_err(|e| format!("Invalid patch: {e}"))?;
+        let working_dir =
+            super::resolve_tool_working_dir(&self.working_dir, self.working_dir_isolation, input);
+        let sandbox = super::sandbox_fs_for(self.sandbox_router.as_ref(), &working_dir, input)?;
+        let policy = super::path_policy_for(&self.path_policy, input);
+
+        let mut plan = Plan {
+            files: Vec::new(),
+            summary: Vec::new(),
+            diff: String::new(),
+            added: 0,
+            removed: 0,
+        };
+        for file in patches {
+            let path = super::resolve_tool_path(&working_dir, &file.path);
+            let access = match file.change {
+                FileChange::Add { .. } => PathAccess::Write,
+                _ => PathAccess::ReadWrite,
+            };
+            let exists = check_target(sandbox.as_ref(), &policy, &path, access).await?;
+            match file.change {
+                FileChange::Add { content } => {
+                    if exists {
+                        return Err(
+                            format!("Cannot add '{}': file already exists", file.path).into()
+                        );
+                    }
+                    let (diff, lines) = whole_file_diff(&content, true);
+                    plan.diff
+                        .push_str(&format!("--- /dev/null\n+++ b/{}\n{diff}", file.path));
+                    plan.summary.push(format!("A {} (+{lines})", file.path));
+                    plan.added += lines;
+                    plan.files.push(PlannedFile {
+                        path,
+                        original: None,
+                        updated: Some(content),
+                    });
+                }
+                FileChange::Delete => {
+                    if !exists {
+                        return Err(format!("Cannot delete '{}': file not found", file.path).into());
+                    }
+                    let original = read(sandbox.as_ref(), &path).await?;
+                    let (diff, lines) = whole_file_diff(&original, false);
+                    plan.diff
+                        .push_str(&format!("--- a/{}\n+++ /dev/null\n{diff}", file.path));
+                    plan.summary.push(format!("D {} (-{lines})", file.path));
+                    plan.removed += lines;
+                    plan.files.push(PlannedFile {
+                        path,
+                        original: Some(original),
+                        updated: None,
+                    });
+                }
+                FileChange::Update { hunks, move_to } => {
+                    if !exists {
+                        return Err(format!("Cannot update '{}': file not found", file.path).into());
+                    }
+                    let original = read(sandbox.as_ref(), &path).await?;
+                    let applied = apply_hunks(&original, &hunks)
+                        .map_err(|e| format!("Patch does not apply to '{}': {e}", file.path))?;
+                    let target_name = move_to.as_deref().unwrap_or(&file.path);
+                    plan.diff.push_str(&format!(
+                        "--- a/{}\n+++ b/{target_name}\n{}",
+                        file.path, applied.diff
+                    ));
+                    plan.added += applied.added;
+                    plan.removed += applied.removed;
+                    match move_to {
+                        Some(target) => {
+                            let target_path = super::resolve_tool_path(&working_dir, &target);
+                            if check_target(
+                                sandbox.as_ref(),
+                                &policy,
+                                &target_path,
+                                PathAccess::Write,
+                            )
+                            .await?
+                            {
+                                return Err(format!(
+                                    "Cannot move '{}' to '{target}': target already exists",
+                                    file.path
+                                )
+                                .into());
+                            }
+                            plan.summary.push(format!(
+                                "R {} -> {target} (+{} -{})",
+                                file.path, applied.added, applied.removed
+                            ));
+                            plan.files.push(PlannedFile {
+                                path,
+                                original: Some(original),
+                                updated: None,
+                            });
+                            plan.files.push(PlannedFile {
+                                path: target_path,
+                                original: None,
+                                updated: Some(applied.content),
+                            });
+                        }
+                        None => {
+                            plan.summary.push(format!(
+                                "M {} (+{} -{})",
+                                file.path, applied.added, applied.removed
+                            ));
+                            plan.files.push(PlannedFile {
+                                path,
+                                original: Some(original),
+                                updated: Some(applied.content),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        for file in &plan.files {
+            if let Some(content) = &file.updated {
+                policy
+                    .check_write_size(&file.path.to_string_lossy(), content.len() as u64)
+                    .map_err(|denied| PlanError::Refused(super::path_denied_result(denied)))?;
+            }
+        }
+        Ok((plan, sandbox))
+    }
+}
+
+/// Path guard for `path`; returns whether it currently exists as a file.
+async fn check_target(
+    sandbox: Option<&SandboxFs>,
+    policy: &PathPolicy,
+    path: &Path,
+    access: PathAccess,
+) -> Result<bool, PlanError> {
+    let kind = super::check_tool_path(sandbox, policy, &path.to_string_lossy(), access)
+        .await
+        .map_err(PlanError::Refused)?;
+    let (is_dir, exists) = match kind {
+        Some(kind) => (kind == EntryKind::Dir, kind == EntryKind::File),
+        None => (path.is_dir(), path.exists()),
+    };
+    if is_dir {
+        return Err(format!("'{}' is a directory", path.display()).into());
+    }
+    Ok(exists)
+}
+
+async fn read(sandbox: Option<&SandboxFs>, path: &Path) -> Result<String, String> {
+    super::read_tool_file(sandbox, &path.to_string_lossy())
+        .await
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))
+}
+
+/// Make `path` hold `content` (or not exist, for `None`).
+async fn put(
+    sandbox: Option<&SandboxFs>,
+    path: &Path,
+    content: Option<&str>,
+) -> Result<(), String> {
+    let path_str = path.to_string_lossy();
+    match (sandbox, content) {
+        (Some(fs), Some(c)) => fs
+            .write(&path_str, c.as_bytes())
+            .await
+            .map_err(|e| e.to_string()),
+        (Some(fs), None) => fs.remove(&path_str).await.map_err(|e| e.to_string()),
+        (None, Some(c)) => {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| format!("Failed to create directories: {e}"))?;
+            }
+            // Write next to the target and rename, so a file is never left half-written.
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let tmp = path.with_file_name(format!(".{name}.{}.tmp", uuid::Uuid::new_v4()));
+            if let Err(e) = tokio::fs::write(&tmp, c).await {
+                let _ = tokio::fs::remove_file(&tmp).await;
+                return Err(e.to_string());
+            }
+            if let Ok(meta) = tokio::fs::metadata(path).await {
+                let _ = tokio::fs::set_permissions(&tmp, meta.permissions()).await;
+            }
+            tokio::fs::rename(&tmp, path).await.map_err(|e| {
+                let _ = std::fs::remove_file(&tmp);
+                e.to_string()
+            })
+        }
+        (None, None) => match tokio::fs::remove_file(path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        },
+    }
+    .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+#[async_trait]
+impl Tool for ApplyPatchTool {
+    fn name(&self) -> &str {
+        "apply_patch"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "apply_patch".into(),
+            description: "Apply a multi-file patch atomically. Accepts a unified diff (as produced by `diff -u` or `git diff`, several files allowed) or a patch envelope:\n*** Begin Patch\n*** Update File: path\n@@ optional line to anchor on\n context\n-old\n+new\n*** Add File: path\n+line\n*** Delete File: path\n*** End Patch\n(`*** Move to: new_path` right after an Update File line renames it). Every hunk is checked against the current files first; if any does not match, nothing is written. Prefer this over edit_file for multi-hunk or multi-file changes.".into(),
+            input_schema: schema_object(
+                json!({
+                    "patch": {
+                        "type": "string",
+                        "description": "Unified diff or *** Begin Patch envelope. Paths are relative to the working directory unless absolute."
+                    }
+                }),
+                &["patch"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let (plan, sandbox) = match self.plan(&input).await {
+            Ok(v) => v,
+            Err(e) => return e.into_result(),
+        };
+        if let Some(approved) = input.get(APPROVAL_DIGEST_KEY).and_then(|v| v.as_str()) {
+            if approved != plan.digest() {
+                return ToolResult::error(
+                    "Refusing to apply patch: files changed since approval. Nothing was written; the patch needs to be reviewed and approved again.".into(),
+                )
+                .with_error_type("approval_stale");
+            }
+        }
+        info!(
+            "Applying patch to {} file(s) (+{} -{})",
+            plan.summary.len(),
+            plan.added,
+            plan.removed
+        );
+
+        for (done, file) in plan.files.iter().enumerate() {
+            if let Err(e) = put(sandbox.as_ref(), &file.path, file.updated.as_deref()).await {
+                // Put back what was already changed so the patch lands whole or not at all.
+                for prior in plan.files[..done].iter().rev() {
+                    if let Err(undo) =
+                        put(sandbox.as_ref(), &prior.path, prior.original.as_deref()).await
+                    {
+                        warn!("Failed to roll back partial patch: {undo}");
+                    }
+                }
+                return ToolResult::error(format!("{e}. No changes were kept."));
+            }
+        }
+        ToolResult::success(plan.render("Applied patch to"))
+    }
+
+    async fn approval_preview(&self, input: serde_json::Value) -> Option<String> {
+        Some(match self.plan(&input).await {
+            Ok((plan, _)) => with_approval_digest(plan.render("Pending patch for"), &plan.digest()),
+            Err(e) => format!("The patch cannot be applied as is: {}", e.message()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn setup() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("microclaw_ap_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("shared")).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_multi_file_unified_diff() {
+        let dir = setup();
+        let shared = dir.join("shared");
+        std::fs::write(shared.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        std::fs::write(shared.join("old.txt"), "bye\n").unwrap();
+        let tool = ApplyPatchTool::new(dir.to_str().unwrap());
+        let patch = "\
+--- a/a.txt
++++ b/a.txt
+@@ -1,3 +1,3 @@
+ one
+-two
++TWO
+ three
+--- /dev/null
++++ b/sub/new.txt
+@@ -0,0 +1 @@
++hello
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+        let result = tool.execute(json!({ "patch": patch })).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result
+            .content
+            .starts_with("Applied patch to 3 file(s) (+2 -2):"));
+        assert!(result.content.contains("```diff\n--- a/a.txt"));
+        assert_eq!(
+            std::fs::read_to_string(shared.join("a.txt")).unwrap(),
+            "one\nTWO\nthree\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(shared.join("sub/new.txt")).unwrap(),
+            "hello\n"
+        );
+        assert!(!shared.join("old.txt").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_is_all_or_nothing() {
+        let dir = setup();
+        let shared = dir.join("shared");
+        std::fs::write(shared.join("a.txt"), "one\n").unwrap();
+        std::fs::write(shared.join("b.txt"), "unchanged\n").unwrap();
+        let tool = ApplyPatchTool::new(dir.to_str().unwrap());
+        let patch = "*** Begin Patch\n*** Update File: a.txt\n-one\n+ONE\n*** Update File: b.txt\n-missing\n+x\n*** End Patch";
+        let result = tool.execute(json!({ "patch": patch })).await;
+        assert!(result.is_error);
+        assert!(result.content.contains("Patch does not apply to 'b.txt'"));
+        assert_eq!(
+            std::fs::read_to_string(shared.join("a.txt")).unwrap(),
+            "one\n"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_envelope_move_and_preview() {
+        let dir = setup();
+        let shared = dir.join("shared");
+        std::fs::write(shared.join("lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        let tool = ApplyPatchTool::new(dir.to_str().unwrap());
+        let input = json!({
+            "patch": "*** Begin Patch\n*** Update File: lib.rs\n*** Move to: core.rs\n@@ fn a() {}\n-fn b() {}\n+fn c() {}\n*** End Patch"
+        });
+
+        let preview = tool.approval_preview(input.clone()).await.unwrap();
+        assert!(preview.contains("R lib.rs -> core.rs (+1 -1)"), "{preview}");
+        assert!(preview.contains("-fn b() {}\n+fn c() {}"));
+        assert!(shared.join("lib.rs").exists());
+
+        let result = tool.execute(input).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert!(!shared.join("lib.rs").exists());
+        assert_eq!(
+            std::fs::read_to_string(shared.join("core.rs")).unwrap(),
+            "fn a() {}\nfn c() {}\n"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_refuses_when_files_changed_since_approval() {
+        let dir = setup();
+        let shared = dir.join("shared");
+        std::fs::write(shared.join("cfg.txt"), "a\nb\nc\n").unwrap();
+        let tool = ApplyPatchTool::new(dir.to_str().unwrap());
+        let patch = "*** Begin Patch\n*** Update File: cfg.txt\n-b\n+B\n*** End Patch";
+
+        let preview = tool
+            .approval_preview(json!({ "patch": patch }))
+            .await
+            .unwrap();
+        let digest = microclaw_tools::runtime::approval_digest(&preview)
+            .unwrap()
+            .to_string();
+
+        // The patch still applies, but not to the file the operator looked at.
+        std::fs::write(shared.join("cfg.txt"), "a\nb\nc\nd\n").unwrap();
+        let approved = json!({ "patch": patch, APPROVAL_DIGEST_KEY: digest });
+        let result = tool.execute(approved.clone()).await;
+        assert!(result.is_error);
+        assert_eq!(result.error_type.as_deref(), Some("approval_stale"));
+        assert!(result.content.contains("files changed since approval"));
+        assert_eq!(
+            std::fs::read_to_string(shared.join("cfg.txt")).unwrap(),
+            "a\nb\nc\nd\n"
+        );
+
+        std::fs::write(shared.join("cfg.txt"), "a\nb\nc\n").unwrap();
+        let result = tool.execute(approved).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert_eq!(
+            std::fs::read_to_string(shared.join("cfg.txt")).unwrap(),
+            "a\nB\nc\n"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch_refuses_to_overwrite_on_add() {
+        let dir = setup();
+        std::fs::write(dir.join("shared/x.txt"), "keep\n").unwrap();
+        let tool = ApplyPatchTool::new(dir.to_str().unwrap());
+        let result = tool
+            .execute(
+                json!({ "patch": "*** Begin Patch\n*** Add File: x.txt\n+new\n*** End Patch" }),
+            )
+            .await;
+        assert!(result.is_error);
+        assert!(result.content.contains("already exists"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
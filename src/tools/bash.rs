@@ -46,7 +46,7 @@ impl BashTool {
     }
 }
 
-fn extract_env_files(input: &serde_json::Value) -> Vec<PathBuf> {
+pub(super) fn extract_env_files(input: &serde_json::Value) -> Vec<PathBuf> {
     super::auth_context_from_input(input)
         .map(|auth| auth.env_files.iter().map(PathBuf::from).collect())
         .unwrap_or_default()
@@ -54,7 +54,8 @@ fn extract_env_files(input: &serde_json::Value) -> Vec<PathBuf> {
 
 const REDACT_MIN_VALUE_LEN: usize = 8;
 
-fn redact_env_secrets(output: &str, env_files: &[PathBuf]) -> String {
+/// Key/value pairs from `env_files` long enough to be worth redacting, longest value first.
+pub(super) fn env_secrets(env_files: &[PathBuf]) -> Vec<(String, String)> {
     let mut secrets: Vec<(String, String)> = Vec::new();
     for env_file in env_files {
         if let Ok(content) = std::fs::read_to_string(env_file) {
@@ -65,10 +66,15 @@ fn redact_env_secrets(output: &str, env_files: &[PathBuf]) -> String {
             }
         }
     }
+    secrets.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+    secrets
+}
+
+pub(super) fn redact_env_secrets(output: &str, env_files: &[PathBuf]) -> String {
+    let secrets = env_secrets(env_files);
     if secrets.is_empty() {
         return output.to_string();
     }
-    secrets.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
     let mut redacted = output.to_string();
     for (key, value) in &secrets {
         redacted = redacted.replace(value, &format!("[REDACTED:{key}]"));
@@ -76,7 +82,7 @@ fn redact_env_secrets(output: &str, env_files: &[PathBuf]) -> String {
     redacted
 }
 
-fn contains_explicit_tmp_absolute_path(command: &str) -> bool {
+pub(super) fn contains_explicit_tmp_absolute_path(command: &str) -> bool {
     let mut start = 0usize;
     while let Some(offset) = command[start..].find("/tmp/") {
         let idx = start + offset;
@@ -98,7 +104,7 @@ fn contains_explicit_tmp_absolute_path(command: &str) -> bool {
     false
 }
 
-fn command_accesses_dotenv(command: &str) -> bool {
+pub(super) fn command_accesses_dotenv(command: &str) -> bool {
     let patterns = [".env", "dotenv", "env_file"];
     let lower = command.to_ascii_lowercase();
     patterns.iter().any(|p| lower.contains(p))
@@ -168,9 +174,7 @@ impl Tool for BashTool {
 
         info!("Executing bash in {}: {}", working_dir.display(), command);
 
-        let session_key = super::auth_context_from_input(&input)
-            .map(|auth| format!("{}-{}", auth.caller_channel, auth.caller_chat_id))
-            .unwrap_or_else(|| "shared".to_string());
+        let session_key = super::sandbox_session_key(&input);
         let env_files_for_redact = env_files.clone();
         let exec_opts = SandboxExecOptions {
             timeout: std::time::Duration::from_secs(timeout_secs),
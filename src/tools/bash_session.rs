@@ -0,0 +1,546 @@
+use async_trait::async_trait;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+use crate::config::WorkingDirIsolation;
+use microclaw_core::llm_types::ToolDefinition;
+use microclaw_core::text::floor_char_boundary;
+use microclaw_tools::sandbox::SandboxExecOptions;
+use microclaw_tools::shell_session::{ShellOutput, ShellSession, ShellSessionManager};
+
+use super::bash::{
+    command_accesses_dotenv, contains_explicit_tmp_absolute_path, env_secrets, extract_env_files,
+    redact_env_secrets,
+};
+use super::{schema_object, Tool, ToolResult};
+
+const MAX_OUTPUT_CHARS: usize = 30000;
+const DEFAULT_READ_WAIT_SECS: u64 = 2;
+const MAX_READ_WAIT_SECS: u64 = 60;
+/// A command that outlives this keeps running and can be collected with
+/// `bash_session_read_output`, so a longer wait buys nothing.
+const MAX_EXEC_TIMEOUT_SECS: u64 = 3600;
+
+/// Env files whose secrets are redacted from session output: this call's and the
+/// session's start.
+fn session_env_files(session: &ShellSession, input: &serde_json::Value) -> Vec<PathBuf> {
+    let mut env_files = extract_env_files(input);
+    env_files.extend(session.env_files().iter().cloned());
+    env_files
+}
+
+/// Tells the session which values will be redacted, so output that is still
+/// arriving is never cut in the middle of one of them.
+fn hold_back_secrets(session: &ShellSession, input: &serde_json::Value) {
+    let secrets = env_secrets(&session_env_files(session, input));
+    session.hold_back_secrets(secrets.into_iter().map(|(_, value)| value).collect());
+}
+
+/// Redacts env-file secrets and caps the length of session output before it goes
+/// back to the model.
+fn render_output(session: &ShellSession, input: &serde_json::Value, out: &ShellOutput) -> String {
+    let env_files = session_env_files(session, input);
+    let mut text = String::new();
+    if out.dropped_bytes > 0 {
+        text.push_str(&format!(
+            "[{} earlier bytes of output were dropped]\n",
+            out.dropped_bytes
+        ));
+    }
+    text.push_str(&out.output);
+    let mut text = redact_env_secrets(&text, &env_files);
+    if text.len() > MAX_OUTPUT_CHARS {
+        let cutoff = floor_char_boundary(&text, MAX_OUTPUT_CHARS);
+        text.truncate(cutoff);
+        text.push_str("\n... (output truncated)");
+    }
+    text
+}
+
+fn session_opts(
+    working_dir: &std::path::Path,
+    isolation: WorkingDirIsolation,
+    input: &serde_json::Value,
+) -> Result<SandboxExecOptions, String> {
+    let working_dir = super::resolve_tool_working_dir(working_dir, isolation, input).join("tmp");
+    std::fs::create_dir_all(&working_dir).map_err(|e| {
+        format!(
+            "Failed to create working directory {}: {e}",
+            working_dir.display()
+        )
+    })?;
+    Ok(SandboxExecOptions {
+        timeout: Duration::from_secs(30),
+        working_dir: Some(working_dir),
+        envs: std::collections::HashMap::new(),
+        env_files: extract_env_files(input),
+    })
+}
+
+async fn start_session(
+    manager: &ShellSessionManager,
+    working_dir: &std::path::Path,
+    isolation: WorkingDirIsolation,
+    input: &serde_json::Value,
+) -> Result<(Arc<ShellSession>, bool), ToolResult> {
+    let opts = session_opts(working_dir, isolation, input).map_err(ToolResult::error)?;
+    manager
+        .start(&super::sandbox_session_key(input), &opts)
+        .await
+        .map_err(|e| {
+            ToolResult::error(format!("Failed to start shell session: {e}"))
+                .with_error_type("spawn_error")
+        })
+}
+
+// --- BashSessionStartTool ---
+
+pub struct BashSessionStartTool {
+    manager: Arc<ShellSessionManager>,
+    working_dir: PathBuf,
+    working_dir_isolation: WorkingDirIsolation,
+}
+
+impl BashSessionStartTool {
+    pub fn new(
+        manager: Arc<ShellSessionManager>,
+        working_dir: &str,
+        working_dir_isolation: WorkingDirIsolation,
+    ) -> Self {
+        Self {
+            manager,
+            working_dir: PathBuf::from(working_dir),
+            working_dir_isolation,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for BashSessionStartTool {
+    fn name(&self) -> &str {
+        "bash_session_start"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "bash_session_start".into(),
+            description: "Start a persistent shell session for this chat, or confirm the existing one. Unlike `bash`, the working directory, exported variables, activated virtualenvs and background processes survive between bash_session_exec calls. The session is closed after a period of inactivity.".into(),
+            input_schema: schema_object(json!({}), &[]),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let (session, created) = match start_session(
+            &self.manager,
+            &self.working_dir,
+            self.working_dir_isolation,
+            &input,
+        )
+        .await
+        {
+            Ok(started) => started,
+            Err(result) => return result,
+        };
+        let location = if session.sandboxed() {
+            "sandbox container"
+        } else {
+            "host"
+        };
+        if created {
+            ToolResult::success(format!("Started shell session on the {location}."))
+        } else {
+            ToolResult::success(format!(
+                "Shell session on the {location} is already running."
+            ))
+        }
+    }
+}
+
+// --- BashSessionExecTool ---
+
+pub struct BashSessionExecTool {
+    manager: Arc<ShellSessionManager>,
+    working_dir: PathBuf,
+    working_dir_isolation: WorkingDirIsolation,
+    default_timeout_secs: u64,
+}
+
+impl BashSessionExecTool {
+    pub fn new(
+        manager: Arc<ShellSessionManager>,
+        working_dir: &str,
+        working_dir_isolation: WorkingDirIsolation,
+    ) -> Self {
+        Self {
+            manager,
+            working_dir: PathBuf::from(working_dir),
+            working_dir_isolation,
+            default_timeout_secs: 30,
+        }
+    }
+
+    pub fn with_default_timeout_secs(mut self, timeout_secs: u64) -> Self {
+        self.default_timeout_secs = timeout_secs;
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for BashSessionExecTool {
+    fn name(&self) -> &str {
+        "bash_session_exec"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "bash_session_exec".into(),
+            description: "Run a command in this chat's persistent shell session (started automatically if needed) and return its output and exit code. State such as `cd`, exported variables and background jobs carries over to later calls. If the command is still running when the timeout expires, the output so far is returned and the command keeps running; collect the rest with bash_session_read_output or stop it with bash_session_kill.".into(),
+            input_schema: schema_object(
+                json!({
+                    "command": {
+                        "type": "string",
+                        "description": "The command to run in the session shell"
+                    },
+                    "timeout_secs": {
+                        "type": "integer",
+                        "description": "How long to wait for the command to finish before returning (defaults to configured tool timeout budget)"
+                    }
+                }),
+                &["command"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let command = match input.get("command").and_then(|v| v.as_str()) {
+            Some(c) => c,
+            None => return ToolResult::error("Missing 'command' parameter".into()),
+        };
+        let timeout_secs = input
+            .get("timeout_secs")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(self.default_timeout_secs)
+            .min(MAX_EXEC_TIMEOUT_SECS);
+
+        if contains_explicit_tmp_absolute_path(command) {
+            return ToolResult::error(
+                "Command contains absolute /tmp path, which is disallowed. Use paths under the session's working directory.".into(),
+            )
+            .with_error_type("path_policy_blocked");
+        }
+        if !extract_env_files(&input).is_empty() && command_accesses_dotenv(command) {
+            return ToolResult::error(
+                "Command appears to access .env files, which is blocked for security. Skill environment variables are already injected automatically.".into(),
+            )
+            .with_error_type("env_access_blocked");
+        }
+
+        let (session, _) = match start_session(
+            &self.manager,
+            &self.working_dir,
+            self.working_dir_isolation,
+            &input,
+        )
+        .await
+        {
+            Ok(started) => started,
+            Err(result) => return result,
+        };
+        info!("Executing in shell session {}: {}", session.key(), command);
+        hold_back_secrets(&session, &input);
+
+        let out = match session
+            .exec(command, Duration::from_secs(timeout_secs))
+            .await
+        {
+            Ok(out) => out,
+            Err(e) => {
+                return ToolResult::error(format!("Failed to run command in session: {e}"))
+                    .with_error_type("session_busy")
+            }
+        };
+        let text = render_output(&session, &input, &out);
+        if out.closed {
+            self.manager.kill(session.key()).await;
+            return ToolResult::error(format!(
+                "Shell session exited.\n{text}\nThe next bash_session_exec call starts a new session."
+            ))
+            .with_error_type("session_closed");
+        }
+        match out.exit_code {
+            Some(0) => {
+                let text = if text.is_empty() {
+                    "Command completed with exit code 0".to_string()
+                } else {
+                    text
+                };
+                ToolResult::success(text).with_status_code(0)
+            }
+            Some(code) => ToolResult::error(format!("Exit code {code}\n{text}"))
+                .with_status_code(code)
+                .with_error_type("process_exit"),
+            None => ToolResult::success(format!(
+                "{text}\n[Command still running after {timeout_secs} seconds. Use bash_session_read_output to collect more output or bash_session_kill to stop it.]"
+            )),
+        }
+    }
+}
+
+// --- BashSessionReadOutputTool ---
+
+pub struct BashSessionReadOutputTool {
+    manager: Arc<ShellSessionManager>,
+}
+
+impl BashSessionReadOutputTool {
+    pub fn new(manager: Arc<ShellSessionManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl Tool for BashSessionReadOutputTool {
+    fn name(&self) -> &str {
+        "bash_session_read_output"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "bash_session_read_output".into(),
+            description: "Read output produced by this chat's shell session since the last read, e.g. from a command that outlived its bash_session_exec timeout or a background server. Reports the exit code once a pending command finishes.".into(),
+            input_schema: schema_object(
+                json!({
+                    "wait_secs": {
+                        "type": "integer",
+                        "description": "How long to wait for output when none is available yet (default 2, max 60)"
+                    }
+                }),
+                &[],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let Some(session) = self.manager.get(&super::sandbox_session_key(&input)).await else {
+            return ToolResult::error(
+                "No shell session is running for this chat. Start one with bash_session_start."
+                    .into(),
+            )
+            .with_error_type("session_not_found");
+        };
+        let wait_secs = input
+            .get("wait_secs")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_READ_WAIT_SECS)
+            .min(MAX_READ_WAIT_SECS);
+        hold_back_secrets(&session, &input);
+        let out = match session.read_output(Duration::from_secs(wait_secs)).await {
+            Ok(out) => out,
+            Err(e) => return ToolResult::error(format!("Failed to read session output: {e}")),
+        };
+        let mut text = render_output(&session, &input, &out);
+        if text.is_empty() {
+            text.push_str("(no new output)");
+        }
+        if let Some(code) = out.exit_code {
+            text.push_str(&format!("\n[Command finished with exit code {code}]"));
+        } else if out.running {
+            text.push_str("\n[Command still running]");
+        }
+        if out.closed {
+            self.manager.kill(session.key()).await;
+            text.push_str("\n[Shell session exited]");
+        }
+        ToolResult::success(text)
+    }
+}
+
+// --- BashSessionKillTool ---
+
+pub struct BashSessionKillTool {
+    manager: Arc<ShellSessionManager>,
+}
+
+impl BashSessionKillTool {
+    pub fn new(manager: Arc<ShellSessionManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl Tool for BashSessionKillTool {
+    fn name(&self) -> &str {
+        "bash_session_kill"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "bash_session_kill".into(),
+            description: "Stop this chat's shell session and its background jobs. With `interrupt_only`, send Ctrl-C to the running command instead and keep the session.".into(),
+            input_schema: schema_object(
+                json!({
+                    "interrupt_only": {
+                        "type": "boolean",
+                        "description": "Only interrupt the foreground command (default false)"
+                    }
+                }),
+                &[],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let key = super::sandbox_session_key(&input);
+        let interrupt_only = input
+            .get("interrupt_only")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if interrupt_only {
+            let Some(session) = self.manager.get(&key).await else {
+                return ToolResult::error("No shell session is running for this chat.".into())
+                    .with_error_type("session_not_found");
+            };
+            return match session.interrupt().await {
+                Ok(()) => ToolResult::success(
+                    "Sent interrupt to the running command. Use bash_session_read_output to see the result.".into(),
+                ),
+                Err(e) => ToolResult::error(format!("Failed to interrupt session: {e}")),
+            };
+        }
+        if self.manager.kill(&key).await {
+            ToolResult::success("Shell session killed.".into())
+        } else {
+            ToolResult::success("No shell session was running.".into())
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use microclaw_tools::shell_session::ShellSessionConfig;
+
+    fn tools(
+        root: &std::path::Path,
+    ) -> (
+        BashSessionExecTool,
+        BashSessionReadOutputTool,
+        BashSessionKillTool,
+    ) {
+        let manager = ShellSessionManager::new(ShellSessionConfig::default(), None);
+        let working_dir = root.to_str().unwrap();
+        (
+            BashSessionExecTool::new(manager.clone(), working_dir, WorkingDirIsolation::Chat),
+            BashSessionReadOutputTool::new(manager.clone()),
+            BashSessionKillTool::new(manager),
+        )
+    }
+
+    fn auth_input(chat_id: i64, mut input: serde_json::Value) -> serde_json::Value {
+        input["__microclaw_auth"] = json!({
+            "caller_channel": "web",
+            "caller_chat_id": chat_id,
+            "control_chat_ids": []
+        });
+        input
+    }
+
+    #[tokio::test]
+    async fn test_session_state_persists_and_is_keyed_by_chat() {
+        let root = std::env::temp_dir().join(format!("microclaw_bsess_{}", uuid::Uuid::new_v4()));
+        let (exec, _, kill) = tools(&root);
+
+        let result = exec
+            .execute(auth_input(
+                1,
+                json!({"command": "export MC_SESSION_VAR=one"}),
+            ))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        let result = exec
+            .execute(auth_input(
+                1,
+                json!({"command": "echo \"$MC_SESSION_VAR\""}),
+            ))
+            .await;
+        assert_eq!(result.content, "one\n");
+        let result = exec
+            .execute(auth_input(
+                2,
+                json!({"command": "echo \"x$MC_SESSION_VAR\""}),
+            ))
+            .await;
+        assert_eq!(result.content, "x\n");
+
+        let result = exec
+            .execute(auth_input(1, json!({"command": "exit 3"})))
+            .await;
+        assert!(result.is_error);
+        assert_eq!(result.error_type.as_deref(), Some("session_closed"));
+
+        kill.execute(auth_input(1, json!({}))).await;
+        kill.execute(auth_input(2, json!({}))).await;
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_session_output_redacts_env_file_secrets() {
+        let root = std::env::temp_dir().join(format!("microclaw_bsess_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let env_file = root.join("skill.env");
+        std::fs::write(&env_file, "MC_SESSION_TOKEN=supersecretvalue\n").unwrap();
+        let (exec, read, kill) = tools(&root);
+        let mut input = auth_input(5, json!({"command": "echo token=$MC_SESSION_TOKEN"}));
+        input["__microclaw_auth"]["env_files"] = json!([env_file.to_str().unwrap()]);
+
+        let result = exec.execute(input).await;
+        assert_eq!(result.content, "token=[REDACTED:MC_SESSION_TOKEN]\n");
+
+        // The session remembers its env files, so later reads stay redacted too.
+        let result = exec
+            .execute(auth_input(
+                5,
+                json!({"command": "sleep 1; echo $MC_SESSION_TOKEN", "timeout_secs": 0}),
+            ))
+            .await;
+        assert!(result.content.contains("still running"));
+        let result = read.execute(auth_input(5, json!({"wait_secs": 5}))).await;
+        let mut collected = result.content;
+        if !collected.contains("exit code") {
+            collected.push_str(
+                &read
+                    .execute(auth_input(5, json!({"wait_secs": 5})))
+                    .await
+                    .content,
+            );
+        }
+        assert!(collected.contains("[REDACTED:MC_SESSION_TOKEN]"));
+        assert!(!collected.contains("supersecretvalue"));
+
+        let result = kill.execute(auth_input(5, json!({}))).await;
+        assert_eq!(result.content, "Shell session killed.");
+        let result = read.execute(auth_input(5, json!({}))).await;
+        assert_eq!(result.error_type.as_deref(), Some("session_not_found"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_session_exec_clamps_huge_timeout() {
+        let root = std::env::temp_dir().join(format!("microclaw_bsess_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let (exec, _read, kill) = tools(&root);
+        let result = exec
+            .execute(auth_input(
+                6,
+                json!({"command": "echo hi", "timeout_secs": u64::MAX}),
+            ))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result.content.contains("hi"));
+        kill.execute(auth_input(6, json!({}))).await;
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
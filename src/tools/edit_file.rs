@@ -1,16 +1,21 @@
 use async_trait::async_trait;
 use serde_json::json;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tracing::info;
 
 use crate::config::WorkingDirIsolation;
 use microclaw_core::llm_types::ToolDefinition;
+use microclaw_tools::path_guard::{PathAccess, PathPolicyConfig};
+use microclaw_tools::sandbox::SandboxRouter;
 
 use super::{schema_object, Tool, ToolResult};
 
 pub struct EditFileTool {
     working_dir: PathBuf,
     working_dir_isolation: WorkingDirIsolation,
+    sandbox_router: Option<Arc<SandboxRouter>>,
+    path_policy: Arc<PathPolicyConfig>,
 }
 
 impl EditFileTool {
@@ -25,8 +30,20 @@ impl EditFileTool {
         Self {
             working_dir: PathBuf::from(working_dir),
             working_dir_isolation,
+            sandbox_router: None,
+            path_policy: Arc::default(),
         }
     }
+
+    pub fn with_sandbox_router(mut self, router: Arc<SandboxRouter>) -> Self {
+        self.sandbox_router = Some(router);
+        self
+    }
+
+    pub fn with_path_policy(mut self, policy: Arc<PathPolicyConfig>) -> Self {
+        self.path_policy = policy;
+        self
+    }
 }
 
 #[async_trait]
@@ -69,8 +86,21 @@ impl Tool for EditFileTool {
         let resolved_path = super::resolve_tool_path(&working_dir, path);
         let resolved_path_str = resolved_path.to_string_lossy().to_string();
 
-        if let Err(msg) = microclaw_tools::path_guard::check_path(&resolved_path_str) {
-            return ToolResult::error(msg);
+        let sandbox =
+            match super::sandbox_fs_for(self.sandbox_router.as_ref(), &working_dir, &input) {
+                Ok(fs) => fs,
+                Err(msg) => return ToolResult::error(msg),
+            };
+        let policy = super::path_policy_for(&self.path_policy, &input);
+        if let Err(denied) = super::check_tool_path(
+            sandbox.as_ref(),
+            &policy,
+            &resolved_path_str,
+            PathAccess::ReadWrite,
+        )
+        .await
+        {
+            return denied;
         }
 
         let old_string = match input.get("old_string").and_then(|v| v.as_str()) {
@@ -84,7 +114,7 @@ impl Tool for EditFileTool {
 
         info!("Editing file: {}", resolved_path.display());
 
-        let content = match tokio::fs::read_to_string(&resolved_path).await {
+        let content = match super::read_tool_file(sandbox.as_ref(), &resolved_path_str).await {
             Ok(c) => c,
             Err(e) => return ToolResult::error(format!("Failed to read file: {e}")),
         };
@@ -102,7 +132,19 @@ impl Tool for EditFileTool {
         }
 
         let new_content = content.replacen(old_string, new_string, 1);
-        match tokio::fs::write(&resolved_path, new_content).await {
+        if let Err(denied) = policy.check_write_size(&resolved_path_str, new_content.len() as u64) {
+            return super::path_denied_result(denied);
+        }
+        let written = match &sandbox {
+            Some(fs) => fs
+                .write(&resolved_path_str, new_content.as_bytes())
+                .await
+                .map_err(|e| e.to_string()),
+            None => tokio::fs::write(&resolved_path, new_content)
+                .await
+                .map_err(|e| e.to_string()),
+        };
+        match written {
             Ok(()) => {
                 ToolResult::success(format!("Successfully edited {}", resolved_path.display()))
             }
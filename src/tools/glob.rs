@@ -1,16 +1,25 @@
 use async_trait::async_trait;
 use serde_json::json;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::info;
 
 use crate::config::WorkingDirIsolation;
 use microclaw_core::llm_types::ToolDefinition;
+use microclaw_tools::path_guard::{PathAccess, PathPolicy, PathPolicyConfig};
+use microclaw_tools::sandbox::SandboxRouter;
+use microclaw_tools::sandbox_fs::{glob_base, ListOptions, SandboxFs};
 
 use super::{schema_object, Tool, ToolResult};
 
+/// Entries listed from the sandbox before matching a glob against them.
+const MAX_SANDBOX_ENTRIES: usize = 20000;
+
 pub struct GlobTool {
     working_dir: PathBuf,
     working_dir_isolation: WorkingDirIsolation,
+    sandbox_router: Option<Arc<SandboxRouter>>,
+    path_policy: Arc<PathPolicyConfig>,
 }
 
 impl GlobTool {
@@ -25,8 +34,20 @@ impl GlobTool {
         Self {
             working_dir: PathBuf::from(working_dir),
             working_dir_isolation,
+            sandbox_router: None,
+            path_policy: Arc::default(),
         }
     }
+
+    pub fn with_sandbox_router(mut self, router: Arc<SandboxRouter>) -> Self {
+        self.sandbox_router = Some(router);
+        self
+    }
+
+    pub fn with_path_policy(mut self, policy: Arc<PathPolicyConfig>) -> Self {
+        self.path_policy = policy;
+        self
+    }
 }
 
 #[async_trait]
@@ -66,8 +87,21 @@ impl Tool for GlobTool {
         let resolved_base = super::resolve_tool_path(&working_dir, base);
         let resolved_base_str = resolved_base.to_string_lossy().to_string();
 
-        if let Err(msg) = microclaw_tools::path_guard::check_path(&resolved_base_str) {
-            return ToolResult::error(msg);
+        let sandbox =
+            match super::sandbox_fs_for(self.sandbox_router.as_ref(), &working_dir, &input) {
+                Ok(fs) => fs,
+                Err(msg) => return ToolResult::error(msg),
+            };
+        let policy = super::path_policy_for(&self.path_policy, &input);
+        if let Err(denied) = super::check_tool_path(
+            sandbox.as_ref(),
+            &policy,
+            &resolved_base_str,
+            PathAccess::Read,
+        )
+        .await
+        {
+            return denied;
         }
 
         info!("Glob: {} in {}", pattern, resolved_base.display());
@@ -78,31 +112,79 @@ impl Tool for GlobTool {
             format!("{}/{}", resolved_base.display(), pattern)
         };
 
+        if let Some(fs) = &sandbox {
+            return match sandbox_glob(fs, &policy, &full_pattern).await {
+                Ok(matches) => render_matches(matches),
+                Err(msg) => ToolResult::error(msg),
+            };
+        }
+
         match glob::glob(&full_pattern) {
             Ok(paths) => {
-                let mut matches: Vec<String> = paths
+                let matches: Vec<String> = paths
                     .filter_map(|p| p.ok())
                     .map(|p| p.display().to_string())
                     .collect();
-                matches = microclaw_tools::path_guard::filter_paths(matches);
-                matches.sort();
-
-                if matches.is_empty() {
-                    ToolResult::success("No files found matching pattern.".into())
-                } else {
-                    let count = matches.len();
-                    if count > 500 {
-                        matches.truncate(500);
-                        matches.push(format!("... and {} more files", count - 500));
-                    }
-                    ToolResult::success(matches.join("\n"))
-                }
+                render_matches(policy.filter_paths(matches))
             }
             Err(e) => ToolResult::error(format!("Invalid glob pattern: {e}")),
         }
     }
 }
 
+fn render_matches(mut matches: Vec<String>) -> ToolResult {
+    matches.sort();
+    if matches.is_empty() {
+        ToolResult::success("No files found matching pattern.".into())
+    } else {
+        let count = matches.len();
+        if count > 500 {
+            matches.truncate(500);
+            matches.push(format!("... and {} more files", count - 500));
+        }
+        ToolResult::success(matches.join("\n"))
+    }
+}
+
+/// Glob inside the sandbox: list everything under the pattern's literal prefix
+/// there and match the listing, since the host cannot walk the sandbox's tree.
+async fn sandbox_glob(
+    fs: &SandboxFs,
+    policy: &PathPolicy,
+    full_pattern: &str,
+) -> Result<Vec<String>, String> {
+    let absolute = std::path::absolute(full_pattern).unwrap_or_else(|_| full_pattern.into());
+    // `find` prints paths built from the root we pass, so match against the same spelling.
+    let full_pattern = absolute
+        .components()
+        .collect::<PathBuf>()
+        .to_string_lossy()
+        .into_owned();
+    let pattern =
+        glob::Pattern::new(&full_pattern).map_err(|e| format!("Invalid glob pattern: {e}"))?;
+    let base = glob_base(&full_pattern);
+    let listed = fs
+        .list(
+            &base.to_string_lossy(),
+            ListOptions {
+                files_only: false,
+                skip_hidden: false,
+                limit: MAX_SANDBOX_ENTRIES,
+            },
+        )
+        .await
+        .map_err(|e| format!("Glob failed in sandbox: {e}"))?;
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    Ok(listed
+        .into_iter()
+        .filter(|p| pattern.matches_with(p, options))
+        .filter(|p| policy.permits_sandbox(Path::new(p), PathAccess::Read))
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -166,4 +248,53 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(&root);
     }
+
+    #[tokio::test]
+    async fn test_glob_and_grep_inside_native_sandbox() {
+        use microclaw_tools::sandbox::{SandboxBackend, SandboxConfig, SandboxMode};
+        if !microclaw_tools::sandbox_native::native_available() {
+            eprintln!("skipping: native sandbox unavailable");
+            return;
+        }
+        let root = std::env::temp_dir().join(format!("microclaw_glob4_{}", uuid::Uuid::new_v4()));
+        let shared = root.join("workspace/shared");
+        std::fs::create_dir_all(shared.join("src/deep")).unwrap();
+        std::fs::create_dir_all(shared.join(".git")).unwrap();
+        std::fs::write(shared.join("src/lib.rs"), "fn alpha() {}\n").unwrap();
+        std::fs::write(shared.join("src/deep/mod.rs"), "fn beta() {}\n").unwrap();
+        std::fs::write(shared.join("src/.env"), "SECRET=alpha\n").unwrap();
+        std::fs::write(shared.join(".git/config"), "alpha\n").unwrap();
+        let work = std::fs::canonicalize(root.join("workspace")).unwrap();
+        let config = SandboxConfig {
+            mode: SandboxMode::All,
+            backend: SandboxBackend::Native,
+            ..SandboxConfig::default()
+        };
+        let router = Arc::new(SandboxRouter::new(config, &work, vec![]));
+        let work_str = work.to_str().unwrap();
+
+        let glob = GlobTool::new(work_str).with_sandbox_router(router.clone());
+        let result = glob.execute(json!({"pattern": "src/*.rs"})).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result.content.contains("src/lib.rs"));
+        assert!(!result.content.contains("mod.rs"));
+        let result = glob.execute(json!({"pattern": "**/*.rs"})).await;
+        assert!(result.content.contains("src/deep/mod.rs"));
+        let result = glob.execute(json!({"pattern": "src/.*"})).await;
+        assert!(!result.content.contains(".env"));
+
+        let grep = super::super::grep::GrepTool::new(work_str).with_sandbox_router(router.clone());
+        let result = grep.execute(json!({"pattern": "alpha|beta"})).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result.content.contains("src/lib.rs:1: fn alpha() {}"));
+        assert!(result.content.contains("src/deep/mod.rs:1: fn beta() {}"));
+        assert!(!result.content.contains("SECRET"));
+        assert!(!result.content.contains(".git"));
+        let result = grep
+            .execute(json!({"pattern": "beta", "glob": "lib.*"}))
+            .await;
+        assert!(result.content.contains("No matches found."));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
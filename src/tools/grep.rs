@@ -1,16 +1,27 @@
 use async_trait::async_trait;
 use serde_json::json;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::info;
 
 use crate::config::WorkingDirIsolation;
 use microclaw_core::llm_types::ToolDefinition;
+use microclaw_tools::path_guard::{PathAccess, PathPolicy, PathPolicyConfig};
+use microclaw_tools::sandbox::SandboxRouter;
+use microclaw_tools::sandbox_fs::{EntryKind, ListOptions, SandboxFs};
 
 use super::{schema_object, Tool, ToolResult};
 
+/// Files searched per call.
+const MAX_FILES: usize = 10000;
+/// Files fetched from the sandbox per round trip.
+const SANDBOX_READ_CHUNK: usize = 256;
+
 pub struct GrepTool {
     working_dir: PathBuf,
     working_dir_isolation: WorkingDirIsolation,
+    sandbox_router: Option<Arc<SandboxRouter>>,
+    path_policy: Arc<PathPolicyConfig>,
 }
 
 impl GrepTool {
@@ -25,8 +36,20 @@ impl GrepTool {
         Self {
             working_dir: PathBuf::from(working_dir),
             working_dir_isolation,
+            sandbox_router: None,
+            path_policy: Arc::default(),
         }
     }
+
+    pub fn with_sandbox_router(mut self, router: Arc<SandboxRouter>) -> Self {
+        self.sandbox_router = Some(router);
+        self
+    }
+
+    pub fn with_path_policy(mut self, policy: Arc<PathPolicyConfig>) -> Self {
+        self.path_policy = policy;
+        self
+    }
 }
 
 #[async_trait]
@@ -69,9 +92,23 @@ impl Tool for GrepTool {
             super::resolve_tool_working_dir(&self.working_dir, self.working_dir_isolation, &input);
         let resolved_path = super::resolve_tool_path(&working_dir, path);
         let resolved_path_str = resolved_path.to_string_lossy().to_string();
-        if let Err(msg) = microclaw_tools::path_guard::check_path(&resolved_path_str) {
-            return ToolResult::error(msg);
-        }
+        let sandbox =
+            match super::sandbox_fs_for(self.sandbox_router.as_ref(), &working_dir, &input) {
+                Ok(fs) => fs,
+                Err(msg) => return ToolResult::error(msg),
+            };
+        let policy = super::path_policy_for(&self.path_policy, &input);
+        let sandbox_kind = match super::check_tool_path(
+            sandbox.as_ref(),
+            &policy,
+            &resolved_path_str,
+            PathAccess::Read,
+        )
+        .await
+        {
+            Ok(kind) => kind,
+            Err(denied) => return denied,
+        };
         let file_glob = input.get("glob").and_then(|v| v.as_str());
 
         info!("Grep: {} in {}", pattern, resolved_path.display());
@@ -84,13 +121,30 @@ impl Tool for GrepTool {
         let mut results = Vec::new();
         let mut file_count = 0;
 
-        if let Err(e) = grep_recursive(
-            &resolved_path,
-            file_glob,
-            &re,
-            &mut results,
-            &mut file_count,
-        ) {
+        let searched = match (&sandbox, sandbox_kind) {
+            (Some(fs), Some(kind)) => {
+                sandbox_grep(
+                    fs,
+                    &policy,
+                    &resolved_path_str,
+                    kind,
+                    file_glob,
+                    &re,
+                    &mut results,
+                )
+                .await
+            }
+            _ => grep_recursive(
+                &resolved_path,
+                &policy,
+                file_glob,
+                &re,
+                &mut results,
+                &mut file_count,
+            )
+            .map_err(|e| e.to_string()),
+        };
+        if let Err(e) = searched {
             return ToolResult::error(format!("Search error: {e}"));
         }
 
@@ -108,6 +162,7 @@ impl Tool for GrepTool {
 
 fn grep_recursive(
     path: &Path,
+    policy: &PathPolicy,
     file_glob: Option<&str>,
     re: &regex::Regex,
     results: &mut Vec<String>,
@@ -131,9 +186,9 @@ fn grep_recursive(
             }
 
             if entry_path.is_dir() {
-                grep_recursive(&entry_path, file_glob, re, results, file_count)?;
+                grep_recursive(&entry_path, policy, file_glob, re, results, file_count)?;
             } else if entry_path.is_file() {
-                if microclaw_tools::path_guard::is_blocked(&entry_path) {
+                if !policy.permits(&entry_path, PathAccess::Read) {
                     continue;
                 }
                 if let Some(ref pat) = glob_pattern {
@@ -142,7 +197,7 @@ fn grep_recursive(
                     }
                 }
                 *file_count += 1;
-                if *file_count > 10000 {
+                if *file_count > MAX_FILES {
                     return Ok(());
                 }
                 grep_file(&entry_path, re, results)?;
@@ -152,21 +207,78 @@ fn grep_recursive(
     Ok(())
 }
 
+/// Grep inside the sandbox, following the same walk rules as [`grep_recursive`].
+async fn sandbox_grep(
+    fs: &SandboxFs,
+    policy: &PathPolicy,
+    path: &str,
+    kind: EntryKind,
+    file_glob: Option<&str>,
+    re: &regex::Regex,
+    results: &mut Vec<String>,
+) -> Result<(), String> {
+    let files = match kind {
+        EntryKind::File => vec![path.to_string()],
+        EntryKind::Dir => {
+            let glob_pattern = file_glob.and_then(|g| glob::Pattern::new(g).ok());
+            fs.list(
+                path,
+                ListOptions {
+                    files_only: true,
+                    skip_hidden: true,
+                    limit: MAX_FILES,
+                },
+            )
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|p| policy.permits_sandbox(Path::new(p), PathAccess::Read))
+            .filter(|p| {
+                let name = Path::new(p)
+                    .file_name()
+                    .map(|n| n.to_string_lossy())
+                    .unwrap_or_default();
+                glob_pattern.as_ref().is_none_or(|pat| pat.matches(&name))
+            })
+            .collect()
+        }
+        EntryKind::Missing => return Err("No such file or directory".into()),
+    };
+
+    for batch in files.chunks(SANDBOX_READ_CHUNK) {
+        let contents = fs.read_many(batch).await.map_err(|e| e.to_string())?;
+        for (file, data) in batch.iter().zip(contents) {
+            // Skip binary / unreadable files
+            let Some(Ok(content)) = data.map(String::from_utf8) else {
+                continue;
+            };
+            grep_content(Path::new(file), &content, re, results);
+            if results.len() > 500 {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
 fn grep_file(path: &Path, re: &regex::Regex, results: &mut Vec<String>) -> std::io::Result<()> {
     let content = match std::fs::read_to_string(path) {
         Ok(c) => c,
         Err(_) => return Ok(()), // Skip binary / unreadable files
     };
+    grep_content(path, &content, re, results);
+    Ok(())
+}
 
+fn grep_content(path: &Path, content: &str, re: &regex::Regex, results: &mut Vec<String>) {
     for (line_num, line) in content.lines().enumerate() {
         if re.is_match(line) {
             results.push(format!("{}:{}: {}", path.display(), line_num + 1, line));
             if results.len() >= 500 {
-                return Ok(());
+                return;
             }
         }
     }
-    Ok(())
 }
 
 #[cfg(test)]
@@ -276,7 +388,15 @@ mod tests {
         let re = regex::Regex::new("match_me").unwrap();
         let mut results = Vec::new();
         let mut count = 0;
-        grep_recursive(&dir, None, &re, &mut results, &mut count).unwrap();
+        grep_recursive(
+            &dir,
+            &PathPolicy::default(),
+            None,
+            &re,
+            &mut results,
+            &mut count,
+        )
+        .unwrap();
 
         // Should only find in visible.txt
         assert_eq!(results.len(), 1);
@@ -1,6 +1,8 @@
 pub mod a2a;
 pub mod activate_skill;
+pub mod apply_patch;
 pub mod bash;
+pub mod bash_session;
 pub mod browser;
 pub mod edit_file;
 pub mod export_chat;
@@ -21,24 +23,134 @@ pub mod web_search;
 pub mod write_file;
 
 use std::sync::{Arc, OnceLock};
-use std::{path::PathBuf, time::Instant};
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
 use crate::config::Config;
 use crate::memory_backend::MemoryBackend;
 use microclaw_channels::channel_adapter::ChannelRegistry;
 use microclaw_core::llm_types::ToolDefinition;
 use microclaw_storage::db::Database;
+use microclaw_tools::path_guard::{PathAccess, PathDenied, PathPolicy, PathPolicyConfig};
+use microclaw_tools::runtime::{approval_digest, inject_auth_context, require_high_risk_approval};
 pub use microclaw_tools::runtime::{
     auth_context_from_input, authorize_chat_access, resolve_tool_path, resolve_tool_working_dir,
-    schema_object, tool_execution_policy, tool_risk, validate_execution_policy, Tool,
-    ToolAuthContext, ToolResult, ToolRisk,
+    schema_object, tool_execution_policy, tool_mutates_working_dir, tool_parallel_safe, tool_risk,
+    validate_execution_policy, Tool, ToolAuthContext, ToolResult, ToolRisk,
 };
-use microclaw_tools::runtime::{inject_auth_context, require_high_risk_approval};
 use microclaw_tools::sandbox::{ExtraMount, SandboxMode, SandboxRouter};
+use microclaw_tools::sandbox_egress::{EgressAuditSink, EgressBlocked};
+use microclaw_tools::sandbox_fs::{EntryKind, SandboxFs};
+use microclaw_tools::shell_session::ShellSessionManager;
+
+/// Records connections refused by the sandbox egress proxy in `audit_logs`.
+struct AuditLogEgressSink(Arc<Database>);
+
+impl EgressAuditSink for AuditLogEgressSink {
+    fn record_blocked(&self, event: &EgressBlocked) {
+        let db = self.0.clone();
+        let event = event.clone();
+        tokio::task::spawn_blocking(move || {
+            let target = format!("{}:{}", event.host, event.port);
+            if let Err(e) = db.log_audit_event(
+                "sandbox_egress",
+                &event.session_key,
+                "connect",
+                Some(&target),
+                "blocked",
+                Some(&event.reason),
+            ) {
+                tracing::warn!("Failed to record blocked sandbox egress: {e}");
+            }
+        });
+    }
+}
+
+/// Sandbox session a tool call belongs to: one per chat, or a shared one outside chats.
+pub(crate) fn sandbox_session_key(input: &serde_json::Value) -> String {
+    auth_context_from_input(input)
+        .map(|auth| format!("{}-{}", auth.caller_channel, auth.caller_chat_id))
+        .unwrap_or_else(|| "shared".to_string())
+}
+
+/// The sandbox filesystem for a file tool call, or `None` to use the host's.
+pub(crate) fn sandbox_fs_for(
+    router: Option<&Arc<SandboxRouter>>,
+    working_dir: &std::path::Path,
+    input: &serde_json::Value,
+) -> Result<Option<SandboxFs>, String> {
+    let Some(router) = router else {
+        return Ok(None);
+    };
+    SandboxFs::for_session(
+        router,
+        &sandbox_session_key(input),
+        working_dir,
+        bash::extract_env_files(input),
+    )
+    .map_err(|e| format!("Sandbox unavailable: {e}"))
+}
+
+/// Path policy for a file tool call: the deployment's rules plus those of the caller's chat.
+pub(crate) fn path_policy_for(config: &PathPolicyConfig, input: &serde_json::Value) -> PathPolicy {
+    config.resolve(&sandbox_session_key(input))
+}
+
+/// Error result for a refused path. The registry audits results carrying `path_denied`.
+pub(crate) fn path_denied_result(denied: PathDenied) -> ToolResult {
+    let metadata = serde_json::json!({
+        "path_denied": {
+            "path": denied.path,
+            "reason": denied.reason.as_str(),
+            "rule": denied.rule,
+        }
+    });
+    ToolResult::error(denied.message)
+        .with_error_type(denied.reason.error_type())
+        .with_metadata(metadata)
+}
+
+/// Path guard for a file tool, against the sandbox's view of `path` when there is one.
+/// Returns what `path` is inside the sandbox, or `None` on the host.
+pub(crate) async fn check_tool_path(
+    sandbox: Option<&SandboxFs>,
+    policy: &PathPolicy,
+    path: &str,
+    access: PathAccess,
+) -> Result<Option<EntryKind>, ToolResult> {
+    match sandbox {
+        Some(fs) => {
+            let resolved = fs.resolve(path).await.map_err(ToolResult::error)?;
+            policy
+                .check_sandbox(&resolved.path, &resolved.resolved, resolved.size, access)
+                .map_err(path_denied_result)?;
+            Ok(Some(resolved.kind))
+        }
+        None => {
+            policy.check(path, access).map_err(path_denied_result)?;
+            Ok(None)
+        }
+    }
+}
+
+pub(crate) async fn read_tool_file(
+    sandbox: Option<&SandboxFs>,
+    path: &str,
+) -> Result<String, String> {
+    match sandbox {
+        Some(fs) => fs.read_to_string(path).await.map_err(|e| e.to_string()),
+        None => tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| e.to_string()),
+    }
+}
 
 pub struct ToolRegistry {
     config: Config,
     tools: Vec<Box<dyn Tool>>,
+    audit_db: Option<Arc<Database>>,
     sandbox_mode: SandboxMode,
     sandbox_runtime_available: bool,
     cached_static_definitions: OnceLock<Vec<ToolDefinition>>,
@@ -102,16 +214,24 @@ impl ToolRegistry {
             );
         }
         let skills_data_dir = config.skills_data_dir();
-        let sandbox_router = Arc::new(SandboxRouter::new(
-            config.sandbox.clone(),
-            &working_dir,
-            Self::build_extra_mounts(&working_dir, &skills_data_dir),
-        ));
+        let sandbox_router = Arc::new(
+            SandboxRouter::new(
+                config.sandbox.clone(),
+                &working_dir,
+                Self::build_extra_mounts(
+                    &working_dir,
+                    &skills_data_dir,
+                    &config.tool_results_dir(),
+                ),
+            )
+            .with_egress_audit(Arc::new(AuditLogEgressSink(db.clone()))),
+        );
         tracing::info!(
             mode = ?sandbox_router.mode(),
             backend = sandbox_router.backend_name(),
             "Sandbox initialized"
         );
+        let path_policy = Arc::new(config.path_policy.clone());
         let mut tools: Vec<Box<dyn Tool>> = vec![
             Box::new(
                 bash::BashTool::new_with_isolation(
@@ -125,26 +245,54 @@ impl ToolRegistry {
                 browser::BrowserTool::new(&config.data_dir)
                     .with_default_timeout_secs(config.tool_timeout_secs("browser", 30)),
             ),
-            Box::new(read_file::ReadFileTool::new_with_isolation(
-                &config.working_dir,
-                config.working_dir_isolation,
-            )),
-            Box::new(write_file::WriteFileTool::new_with_isolation(
-                &config.working_dir,
-                config.working_dir_isolation,
-            )),
-            Box::new(edit_file::EditFileTool::new_with_isolation(
-                &config.working_dir,
-                config.working_dir_isolation,
-            )),
-            Box::new(glob::GlobTool::new_with_isolation(
-                &config.working_dir,
-                config.working_dir_isolation,
-            )),
-            Box::new(grep::GrepTool::new_with_isolation(
-                &config.working_dir,
-                config.working_dir_isolation,
-            )),
+            Box::new(
+                read_file::ReadFileTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
+            Box::new(
+                write_file::WriteFileTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
+            Box::new(
+                edit_file::EditFileTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
+            Box::new(
+                apply_patch::ApplyPatchTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
+            Box::new(
+                glob::GlobTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
+            Box::new(
+                grep::GrepTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
             Box::new(memory::ReadMemoryTool::new(&config.data_dir, db.clone())),
             Box::new(memory::WriteMemoryTool::new(
                 &config.data_dir,
@@ -158,6 +306,8 @@ impl ToolRegistry {
             )),
             Box::new(web_search::WebSearchTool::new(
                 config.tool_timeout_secs("web_search", 15),
+                Arc::new(config.web_search.build_backend()),
+                config.web_search.max_results,
             )),
             Box::new(time_math::GetCurrentTimeTool::new(config.timezone.clone())),
             Box::new(time_math::CompareTimeTool::new(config.timezone.clone())),
@@ -215,6 +365,7 @@ impl ToolRegistry {
                 config,
                 db.clone(),
                 channel_registry.clone(),
+                sandbox_router.clone(),
             )),
             Box::new(subagents::SubagentsListTool::new(db.clone())),
             Box::new(subagents::SubagentsInfoTool::new(db.clone())),
@@ -226,11 +377,13 @@ impl ToolRegistry {
                 config,
                 db.clone(),
                 channel_registry.clone(),
+                sandbox_router.clone(),
             )),
             Box::new(subagents::SubagentsOrchestrateTool::new(
                 config,
                 db.clone(),
                 channel_registry.clone(),
+                sandbox_router.clone(),
             )),
             Box::new(subagents::SubagentsLogTool::new(db.clone())),
             Box::new(subagents::SubagentsRetryAnnouncesTool::new(
@@ -259,6 +412,30 @@ impl ToolRegistry {
             )),
         ];
 
+        if config.bash_sessions.enabled {
+            let manager = ShellSessionManager::new(
+                config.bash_sessions.clone(),
+                Some(sandbox_router.clone()),
+            );
+            tools.push(Box::new(bash_session::BashSessionStartTool::new(
+                manager.clone(),
+                &config.working_dir,
+                config.working_dir_isolation,
+            )));
+            tools.push(Box::new(
+                bash_session::BashSessionExecTool::new(
+                    manager.clone(),
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_default_timeout_secs(config.tool_timeout_secs("bash_session_exec", 30)),
+            ));
+            tools.push(Box::new(bash_session::BashSessionReadOutputTool::new(
+                manager.clone(),
+            )));
+            tools.push(Box::new(bash_session::BashSessionKillTool::new(manager)));
+        }
+
         // Add ClawHub tools if enabled
         if config.clawhub.agent_tools_enabled {
             tools.push(Box::new(crate::clawhub::tools::ClawHubSearchTool::new(
@@ -272,6 +449,7 @@ impl ToolRegistry {
         ToolRegistry {
             config: config.clone(),
             tools,
+            audit_db: Some(db),
             sandbox_mode: sandbox_router.mode(),
             sandbox_runtime_available: sandbox_router.runtime_available(),
             cached_static_definitions: OnceLock::new(),
@@ -280,11 +458,14 @@ impl ToolRegistry {
 
     /// Create a restricted tool registry for sub-agents.
     /// When `allow_session_tools` is true, orchestration tools are exposed for depth-limited child spawning.
+    /// `sandbox_router` is the parent's, so both run in the same per-chat container
+    /// behind the same egress proxy.
     pub fn new_sub_agent(
         config: &Config,
         db: Arc<Database>,
         channel_registry: Option<Arc<ChannelRegistry>>,
         allow_session_tools: bool,
+        sandbox_router: Arc<SandboxRouter>,
     ) -> Self {
         let working_dir = PathBuf::from(&config.working_dir);
         if let Err(e) = std::fs::create_dir_all(&working_dir) {
@@ -295,12 +476,8 @@ impl ToolRegistry {
             );
         }
         let skills_data_dir = config.skills_data_dir();
-        let sandbox_router = Arc::new(SandboxRouter::new(
-            config.sandbox.clone(),
-            &working_dir,
-            Self::build_extra_mounts(&working_dir, &skills_data_dir),
-        ));
         let memory_backend = Arc::new(MemoryBackend::local_only(db.clone()));
+        let path_policy = Arc::new(config.path_policy.clone());
         let mut tools: Vec<Box<dyn Tool>> = vec![
             Box::new(
                 bash::BashTool::new_with_isolation(
@@ -314,26 +491,54 @@ impl ToolRegistry {
                 browser::BrowserTool::new(&config.data_dir)
                     .with_default_timeout_secs(config.tool_timeout_secs("browser", 30)),
             ),
-            Box::new(read_file::ReadFileTool::new_with_isolation(
-                &config.working_dir,
-                config.working_dir_isolation,
-            )),
-            Box::new(write_file::WriteFileTool::new_with_isolation(
-                &config.working_dir,
-                config.working_dir_isolation,
-            )),
-            Box::new(edit_file::EditFileTool::new_with_isolation(
-                &config.working_dir,
-                config.working_dir_isolation,
-            )),
-            Box::new(glob::GlobTool::new_with_isolation(
-                &config.working_dir,
-                config.working_dir_isolation,
-            )),
-            Box::new(grep::GrepTool::new_with_isolation(
-                &config.working_dir,
-                config.working_dir_isolation,
-            )),
+            Box::new(
+                read_file::ReadFileTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
+            Box::new(
+                write_file::WriteFileTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
+            Box::new(
+                edit_file::EditFileTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
+            Box::new(
+                apply_patch::ApplyPatchTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
+            Box::new(
+                glob::GlobTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
+            Box::new(
+                grep::GrepTool::new_with_isolation(
+                    &config.working_dir,
+                    config.working_dir_isolation,
+                )
+                .with_sandbox_router(sandbox_router.clone())
+                .with_path_policy(path_policy.clone()),
+            ),
             Box::new(memory::ReadMemoryTool::new(&config.data_dir, db.clone())),
             Box::new(web_fetch::WebFetchTool::new(
                 config.tool_timeout_secs("web_fetch", 15),
@@ -342,6 +547,8 @@ impl ToolRegistry {
             )),
             Box::new(web_search::WebSearchTool::new(
                 config.tool_timeout_secs("web_search", 15),
+                Arc::new(config.web_search.build_backend()),
+                config.web_search.max_results,
             )),
             Box::new(time_math::GetCurrentTimeTool::new(config.timezone.clone())),
             Box::new(time_math::CompareTimeTool::new(config.timezone.clone())),
@@ -361,6 +568,7 @@ impl ToolRegistry {
                     config,
                     db.clone(),
                     channel_registry.clone(),
+                    sandbox_router.clone(),
                 )));
                 tools.push(Box::new(subagents::SubagentsListTool::new(db.clone())));
                 tools.push(Box::new(subagents::SubagentsInfoTool::new(db.clone())));
@@ -372,6 +580,7 @@ impl ToolRegistry {
                     config,
                     db.clone(),
                     channel_registry.clone(),
+                    sandbox_router.clone(),
                 )));
                 tools.push(Box::new(subagents::SubagentsLogTool::new(db.clone())));
             }
@@ -379,13 +588,18 @@ impl ToolRegistry {
         ToolRegistry {
             config: config.clone(),
             tools,
+            audit_db: Some(db),
             sandbox_mode: sandbox_router.mode(),
             sandbox_runtime_available: sandbox_router.runtime_available(),
             cached_static_definitions: OnceLock::new(),
         }
     }
 
-    fn build_extra_mounts(working_dir: &PathBuf, skills_data_dir: &str) -> Vec<ExtraMount> {
+    pub(crate) fn build_extra_mounts(
+        working_dir: &PathBuf,
+        skills_data_dir: &str,
+        tool_results_dir: &Path,
+    ) -> Vec<ExtraMount> {
         let skills_path = PathBuf::from(skills_data_dir);
         let canonical_skills = std::fs::canonicalize(&skills_path).unwrap_or(skills_path.clone());
         let canonical_working =
@@ -397,6 +611,23 @@ impl ToolRegistry {
                 read_only: true,
             });
         }
+        // Spilled tool output must exist before the sandbox starts so it can be
+        // bind-mounted; `read_file` pages through it at the same path inside.
+        if let Err(e) = std::fs::create_dir_all(tool_results_dir) {
+            tracing::warn!(
+                "Failed to create tool results dir '{}': {}",
+                tool_results_dir.display(),
+                e
+            );
+        }
+        if let Ok(canonical_results) = std::fs::canonicalize(tool_results_dir) {
+            if !canonical_results.starts_with(&canonical_working) {
+                mounts.push(ExtraMount {
+                    host_path: canonical_results,
+                    read_only: true,
+                });
+            }
+        }
         mounts
     }
 
@@ -442,6 +673,44 @@ impl ToolRegistry {
         ToolResult::error(format!("Unknown tool: {name}")).with_error_type("unknown_tool")
     }
 
+    /// Record a path the path guard refused in `audit_logs` (`kind = path_guard`).
+    fn audit_path_denial(&self, tool: &str, auth: &ToolAuthContext, denied: &serde_json::Value) {
+        let Some(db) = self.audit_db.clone() else {
+            return;
+        };
+        let actor = format!("{}-{}", auth.caller_channel, auth.caller_chat_id);
+        let action = tool.to_string();
+        let target = denied
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        let reason = denied
+            .get("reason")
+            .and_then(|v| v.as_str())
+            .unwrap_or("denied");
+        let detail = match denied.get("rule").and_then(|v| v.as_str()) {
+            Some(rule) => format!("{reason}: {rule}"),
+            None => reason.to_string(),
+        };
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = db.log_audit_event(
+                "path_guard",
+                &actor,
+                &action,
+                target.as_deref(),
+                "denied",
+                Some(&detail),
+            ) {
+                tracing::warn!("Failed to record path guard denial: {e}");
+            }
+        });
+    }
+
+    async fn approval_preview(&self, name: &str, input: serde_json::Value) -> Option<String> {
+        let tool = self.tools.iter().find(|t| t.name() == name)?;
+        tool.approval_preview(input).await
+    }
+
     pub async fn execute_with_auth(
         &self,
         name: &str,
@@ -453,7 +722,19 @@ impl ToolRegistry {
         {
             return ToolResult::error(msg).with_error_type("execution_policy_blocked");
         }
-        if let Some(blocked) = require_high_risk_approval(name, auth, &input) {
+        if let Some(mut blocked) = require_high_risk_approval(name, auth, &input) {
+            if let Some(preview) = self
+                .approval_preview(name, inject_auth_context(input, auth))
+                .await
+            {
+                blocked.content = format!("{}\n\n{preview}", blocked.content);
+                blocked.bytes = blocked.content.len();
+                let digest = approval_digest(&preview).map(str::to_string);
+                blocked.metadata = Some(serde_json::json!({
+                    "approval_preview": preview,
+                    "approval_digest": digest,
+                }));
+            }
             return blocked;
         }
 
@@ -468,6 +749,9 @@ impl ToolRegistry {
         let input = Self::inject_default_chat_id_if_missing(name, input, auth);
         let input = inject_auth_context(input, auth);
         let result = self.execute(name, input.clone()).await;
+        if let Some(denied) = result.metadata.as_ref().and_then(|m| m.get("path_denied")) {
+            self.audit_path_denial(name, auth, denied);
+        }
         if result.error_type.as_deref() == Some("unknown_tool") {
             if let Some(dynamic) =
                 crate::plugins::execute_dynamic_plugin_tool(&self.config, name, input).await
@@ -637,16 +921,34 @@ mod tests {
     #[test]
     fn test_tool_risk_levels() {
         assert_eq!(tool_risk("bash"), ToolRisk::High);
+        assert_eq!(tool_risk("bash_session_exec"), ToolRisk::High);
+        assert_eq!(tool_risk("apply_patch"), ToolRisk::High);
+        assert_eq!(tool_risk("bash_session_kill"), ToolRisk::Medium);
         assert_eq!(tool_risk("write_file"), ToolRisk::Medium);
         assert_eq!(tool_risk("pause_scheduled_task"), ToolRisk::Medium);
         assert_eq!(tool_risk("sync_skills"), ToolRisk::Medium);
         assert_eq!(tool_risk("read_file"), ToolRisk::Low);
     }
 
+    #[test]
+    fn test_tool_parallel_safe() {
+        assert!(tool_parallel_safe("read_file"));
+        assert!(tool_parallel_safe("web_fetch"));
+        assert!(tool_parallel_safe("structured_memory_search"));
+        assert!(!tool_parallel_safe("bash"));
+        assert!(!tool_parallel_safe("write_file"));
+        assert!(!tool_parallel_safe("activate_skill"));
+        assert!(!tool_parallel_safe("todo_write"));
+        assert!(!tool_parallel_safe("bash_session_read_output"));
+        assert!(!tool_parallel_safe("subagents_kill"));
+        assert!(!tool_parallel_safe("mcp_github_create_issue"));
+    }
+
     #[tokio::test]
     async fn test_high_risk_tool_requires_explicit_approval_on_web() {
         let registry = ToolRegistry {
             config: crate::config::Config::test_defaults(),
+            audit_db: None,
             sandbox_mode: SandboxMode::Off,
             sandbox_runtime_available: false,
             cached_static_definitions: OnceLock::new(),
@@ -684,6 +986,7 @@ mod tests {
     async fn test_high_risk_tool_requires_explicit_approval_on_control_chat() {
         let registry = ToolRegistry {
             config: crate::config::Config::test_defaults(),
+            audit_db: None,
             sandbox_mode: SandboxMode::Off,
             sandbox_runtime_available: false,
             cached_static_definitions: OnceLock::new(),
@@ -713,10 +1016,68 @@ mod tests {
         assert_eq!(approved.content, "ok");
     }
 
+    #[tokio::test]
+    async fn test_apply_patch_approval_shows_diff_before_writing() {
+        let dir = std::env::temp_dir().join(format!("microclaw_ap_gate_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("shared")).unwrap();
+        std::fs::write(dir.join("shared/notes.txt"), "old\n").unwrap();
+        let registry = ToolRegistry {
+            config: crate::config::Config::test_defaults(),
+            audit_db: None,
+            sandbox_mode: SandboxMode::Off,
+            sandbox_runtime_available: false,
+            cached_static_definitions: OnceLock::new(),
+            tools: vec![Box::new(apply_patch::ApplyPatchTool::new(
+                dir.to_str().unwrap(),
+            ))],
+        };
+        let auth = ToolAuthContext {
+            caller_channel: "web".into(),
+            caller_chat_id: 1,
+            control_chat_ids: vec![],
+            env_files: vec![],
+        };
+        let patch = "--- a/notes.txt\n+++ b/notes.txt\n@@ -1 +1 @@\n-old\n+new\n";
+
+        let held = registry
+            .execute_with_auth("apply_patch", json!({ "patch": patch }), &auth)
+            .await;
+        assert_eq!(held.error_type.as_deref(), Some("approval_required"));
+        let preview = held.metadata.as_ref().unwrap()["approval_preview"]
+            .as_str()
+            .unwrap();
+        assert!(preview.contains("M notes.txt (+1 -1)"), "{preview}");
+        assert_eq!(
+            held.metadata.as_ref().unwrap()["approval_digest"].as_str(),
+            microclaw_tools::runtime::approval_digest(preview)
+        );
+        assert!(preview.contains("Approval digest: "));
+        assert!(preview.contains("-old\n+new"));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("shared/notes.txt")).unwrap(),
+            "old\n"
+        );
+
+        let approved = registry
+            .execute_with_auth(
+                "apply_patch",
+                json!({ "patch": patch, "__microclaw_high_risk_approved": true }),
+                &auth,
+            )
+            .await;
+        assert!(!approved.is_error, "{}", approved.content);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("shared/notes.txt")).unwrap(),
+            "new\n"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[tokio::test]
     async fn test_medium_risk_tool_no_second_approval() {
         let registry = ToolRegistry {
             config: crate::config::Config::test_defaults(),
+            audit_db: None,
             sandbox_mode: SandboxMode::Off,
             sandbox_runtime_available: false,
             cached_static_definitions: OnceLock::new(),
@@ -771,6 +1132,7 @@ tools:
         let registry = ToolRegistry {
             config,
             tools: vec![],
+            audit_db: None,
             sandbox_mode: SandboxMode::Off,
             sandbox_runtime_available: false,
             cached_static_definitions: OnceLock::new(),
@@ -798,6 +1160,7 @@ tools:
     async fn test_injects_default_chat_id_for_memory_tools() {
         let registry = ToolRegistry {
             config: crate::config::Config::test_defaults(),
+            audit_db: None,
             sandbox_mode: SandboxMode::Off,
             sandbox_runtime_available: false,
             cached_static_definitions: OnceLock::new(),
@@ -828,6 +1191,7 @@ tools:
     async fn test_does_not_override_existing_chat_id() {
         let registry = ToolRegistry {
             config: crate::config::Config::test_defaults(),
+            audit_db: None,
             sandbox_mode: SandboxMode::Off,
             sandbox_runtime_available: false,
             cached_static_definitions: OnceLock::new(),
@@ -858,6 +1222,7 @@ tools:
     async fn test_injects_default_chat_id_for_send_message_tool() {
         let registry = ToolRegistry {
             config: crate::config::Config::test_defaults(),
+            audit_db: None,
             sandbox_mode: SandboxMode::Off,
             sandbox_runtime_available: false,
             cached_static_definitions: OnceLock::new(),
@@ -1,16 +1,21 @@
 use async_trait::async_trait;
 use serde_json::json;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tracing::info;
 
 use crate::config::WorkingDirIsolation;
 use microclaw_core::llm_types::ToolDefinition;
+use microclaw_tools::path_guard::{PathAccess, PathPolicyConfig};
+use microclaw_tools::sandbox::SandboxRouter;
 
 use super::{schema_object, Tool, ToolResult};
 
 pub struct ReadFileTool {
     working_dir: PathBuf,
     working_dir_isolation: WorkingDirIsolation,
+    sandbox_router: Option<Arc<SandboxRouter>>,
+    path_policy: Arc<PathPolicyConfig>,
 }
 
 impl ReadFileTool {
@@ -25,8 +30,20 @@ impl ReadFileTool {
         Self {
             working_dir: PathBuf::from(working_dir),
             working_dir_isolation,
+            sandbox_router: None,
+            path_policy: Arc::default(),
         }
     }
+
+    pub fn with_sandbox_router(mut self, router: Arc<SandboxRouter>) -> Self {
+        self.sandbox_router = Some(router);
+        self
+    }
+
+    pub fn with_path_policy(mut self, policy: Arc<PathPolicyConfig>) -> Self {
+        self.path_policy = policy;
+        self
+    }
 }
 
 #[async_trait]
@@ -69,13 +86,26 @@ impl Tool for ReadFileTool {
         let resolved_path = super::resolve_tool_path(&working_dir, path);
         let resolved_path_str = resolved_path.to_string_lossy().to_string();
 
-        if let Err(msg) = microclaw_tools::path_guard::check_path(&resolved_path_str) {
-            return ToolResult::error(msg);
+        let sandbox =
+            match super::sandbox_fs_for(self.sandbox_router.as_ref(), &working_dir, &input) {
+                Ok(fs) => fs,
+                Err(msg) => return ToolResult::error(msg),
+            };
+        let policy = super::path_policy_for(&self.path_policy, &input);
+        if let Err(denied) = super::check_tool_path(
+            sandbox.as_ref(),
+            &policy,
+            &resolved_path_str,
+            PathAccess::Read,
+        )
+        .await
+        {
+            return denied;
         }
 
         info!("Reading file: {}", resolved_path.display());
 
-        let content = match tokio::fs::read_to_string(&resolved_path).await {
+        let content = match super::read_tool_file(sandbox.as_ref(), &resolved_path_str).await {
             Ok(c) => c,
             Err(e) => return ToolResult::error(format!("Failed to read file: {e}")),
         };
@@ -180,6 +180,10 @@ impl Tool for ScheduleTaskTool {
                     "timezone": {
                         "type": "string",
                         "description": "Optional IANA timezone name (e.g. 'US/Eastern', 'Europe/London'). Defaults to server timezone setting."
+                    },
+                    "response_schema": {
+                        "type": "object",
+                        "description": "Optional JSON schema each run's final answer must satisfy. The validated JSON is delivered instead of free-form text."
                     }
                 }),
                 &["chat_id", "prompt", "schedule_type", "schedule_value"],
@@ -216,6 +220,15 @@ impl Tool for ScheduleTaskTool {
             .get("timezone")
             .and_then(|v| v.as_str())
             .unwrap_or(&self.default_timezone);
+        let response_schema = match input.get("response_schema").filter(|v| !v.is_null()) {
+            Some(schema) => {
+                if let Err(e) = crate::structured_output::check_schema(schema) {
+                    return ToolResult::error(format!("Invalid response_schema: {e}"));
+                }
+                Some(schema.to_string())
+            }
+            None => None,
+        };
 
         let next_run = match schedule_type {
             "cron" => match compute_next_run(schedule_value, tz_name) {
@@ -252,14 +265,18 @@ impl Tool for ScheduleTaskTool {
         let tz_name_owned = tz_name.to_string();
         let next_run_owned = next_run.clone();
         match call_blocking(self.db.clone(), move |db| {
-            db.create_scheduled_task_with_timezone(
+            let id = db.create_scheduled_task_with_timezone(
                 chat_id,
                 &prompt_owned,
                 &schedule_type_owned,
                 &schedule_value_owned,
                 &tz_name_owned,
                 &next_run_owned,
-            )
+            )?;
+            if response_schema.is_some() {
+                db.set_task_response_schema(id, response_schema.as_deref())?;
+            }
+            Ok(id)
         })
         .await
         {
@@ -274,6 +291,9 @@ impl Tool for ScheduleTaskTool {
                 if let Some(c) = cadence {
                     message.push_str(&format!("\nCron interpretation: {c}."));
                 }
+                if input.get("response_schema").is_some_and(|v| !v.is_null()) {
+                    message.push_str("\nRuns will return JSON matching the response schema.");
+                }
                 ToolResult::success(message)
             }
             Err(e) => ToolResult::error(format!("Failed to create task: {e}")),
@@ -1197,6 +1217,40 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[tokio::test]
+    async fn test_schedule_task_with_response_schema() {
+        let (db, dir) = test_db();
+        let tool = ScheduleTaskTool::new(test_registry(), db.clone(), "UTC".into());
+        let result = tool
+            .execute(json!({
+                "chat_id": 100,
+                "prompt": "report status",
+                "schedule_type": "cron",
+                "schedule_value": "0 0 * * * *",
+                "response_schema": {"type": "date"}
+            }))
+            .await;
+        assert!(result.is_error);
+        assert!(result.content.contains("Invalid response_schema"));
+
+        let result = tool
+            .execute(json!({
+                "chat_id": 100,
+                "prompt": "report status",
+                "schedule_type": "cron",
+                "schedule_value": "0 0 * * * *",
+                "response_schema": {"type": "object", "required": ["ok"]}
+            }))
+            .await;
+        assert!(!result.is_error, "Error: {}", result.content);
+        let tasks = db.get_tasks_for_chat(100).unwrap();
+        assert_eq!(tasks.len(), 1);
+        let schema: serde_json::Value =
+            serde_json::from_str(tasks[0].response_schema.as_deref().unwrap()).unwrap();
+        assert_eq!(schema["required"][0], "ok");
+        cleanup(&dir);
+    }
+
     #[tokio::test]
     async fn test_get_task_history_empty() {
         let (db, dir) = test_db();
@@ -7,7 +7,7 @@ use tracing::{info, warn};
 
 use super::{authorize_chat_access, schema_object, Tool, ToolResult};
 use microclaw_channels::channel::{
-    deliver_and_store_bot_message, enforce_channel_policy, get_required_chat_routing,
+    deliver_and_store_bot_message, enforce_channel_policy, get_required_chat_routing, BotDelivery,
 };
 use microclaw_channels::channel_adapter::ChannelRegistry;
 use microclaw_core::llm_types::ToolDefinition;
@@ -335,6 +335,7 @@ impl Tool for SendMessageTool {
                 &text,
             )
             .await
+            .and_then(BotDelivery::into_result)
             {
                 Ok(_) => {
                     info!("send_message text sent: chat_id={}", chat_id);
@@ -11,7 +11,7 @@ use super::{
     ToolRegistry, ToolResult,
 };
 use crate::config::Config;
-use microclaw_channels::channel::deliver_and_store_bot_message;
+use microclaw_channels::channel::{deliver_and_store_bot_message, BotDelivery};
 use microclaw_channels::channel_adapter::ChannelRegistry;
 use microclaw_core::llm_types::{
     ContentBlock, Message, MessageContent, ResponseContentBlock, ToolDefinition,
@@ -19,6 +19,7 @@ use microclaw_core::llm_types::{
 use microclaw_storage::db::{
     call_blocking, CreateSubagentRunParams, Database, FinishSubagentRunParams,
 };
+use microclaw_tools::sandbox::SandboxRouter;
 
 const MAX_SUB_AGENT_ITERATIONS: usize = 16;
 
@@ -216,6 +217,7 @@ struct RunSubAgentTaskParams {
     config: Config,
     db: Arc<Database>,
     channel_registry: Arc<ChannelRegistry>,
+    sandbox_router: Arc<SandboxRouter>,
     auth_context: ToolAuthContext,
     run_id: String,
     depth: i64,
@@ -232,6 +234,7 @@ async fn run_sub_agent_task(
         config,
         db,
         channel_registry,
+        sandbox_router,
         auth_context,
         run_id,
         depth,
@@ -247,6 +250,7 @@ async fn run_sub_agent_task(
         db.clone(),
         Some(channel_registry),
         allow_session_tools,
+        sandbox_router,
     );
     let tool_defs = tools.definitions().to_vec();
 
@@ -351,6 +355,16 @@ async fn run_sub_agent_task(
                         input: input.clone(),
                         thought_signature: thought_signature.clone(),
                     }),
+                    ResponseContentBlock::Thinking {
+                        thinking,
+                        signature,
+                    } => Some(ContentBlock::Thinking {
+                        thinking: thinking.clone(),
+                        signature: signature.clone(),
+                    }),
+                    ResponseContentBlock::RedactedThinking { data } => {
+                        Some(ContentBlock::RedactedThinking { data: data.clone() })
+                    }
                     ResponseContentBlock::Other => None,
                 })
                 .collect();
@@ -500,7 +514,8 @@ pub async fn flush_pending_announces_once(
             row.chat_id,
             &row.payload_text,
         )
-        .await;
+        .await
+        .and_then(BotDelivery::into_result);
         match delivery {
             Ok(_) => {
                 let id = row.id;
@@ -539,14 +554,21 @@ pub struct SessionsSpawnTool {
     config: Config,
     db: Arc<Database>,
     channel_registry: Arc<ChannelRegistry>,
+    sandbox_router: Arc<SandboxRouter>,
 }
 
 impl SessionsSpawnTool {
-    pub fn new(config: &Config, db: Arc<Database>, channel_registry: Arc<ChannelRegistry>) -> Self {
+    pub fn new(
+        config: &Config,
+        db: Arc<Database>,
+        channel_registry: Arc<ChannelRegistry>,
+        sandbox_router: Arc<SandboxRouter>,
+    ) -> Self {
         Self {
             config: config.clone(),
             db,
             channel_registry,
+            sandbox_router,
         }
     }
 }
@@ -734,6 +756,7 @@ impl Tool for SessionsSpawnTool {
         };
         let channel_registry = self.channel_registry.clone();
         let subagent_channel_registry = self.channel_registry.clone();
+        let sandbox_router = self.sandbox_router.clone();
         tokio::spawn(async move {
             let run_id_for_finish = run_id_async.clone();
             let _ = call_blocking(db.clone(), {
@@ -775,6 +798,7 @@ impl Tool for SessionsSpawnTool {
                 config: cfg.clone(),
                 db: db.clone(),
                 channel_registry: subagent_channel_registry,
+                sandbox_router,
                 auth_context: auth_async,
                 run_id: run_id_async.clone(),
                 depth: child_depth,
@@ -1384,14 +1408,21 @@ pub struct SubagentsSendTool {
     config: Config,
     db: Arc<Database>,
     channel_registry: Arc<ChannelRegistry>,
+    sandbox_router: Arc<SandboxRouter>,
 }
 
 impl SubagentsSendTool {
-    pub fn new(config: &Config, db: Arc<Database>, channel_registry: Arc<ChannelRegistry>) -> Self {
+    pub fn new(
+        config: &Config,
+        db: Arc<Database>,
+        channel_registry: Arc<ChannelRegistry>,
+        sandbox_router: Arc<SandboxRouter>,
+    ) -> Self {
         Self {
             config: config.clone(),
             db,
             channel_registry,
+            sandbox_router,
         }
     }
 }
@@ -1451,8 +1482,12 @@ impl Tool for SubagentsSendTool {
             Err(e) => return ToolResult::error(format!("Failed loading focused subagent: {e}")),
         };
 
-        let spawn_tool =
-            SessionsSpawnTool::new(&self.config, self.db.clone(), self.channel_registry.clone());
+        let spawn_tool = SessionsSpawnTool::new(
+            &self.config,
+            self.db.clone(),
+            self.channel_registry.clone(),
+            self.sandbox_router.clone(),
+        );
         let remaining_budget = (parent.token_budget - parent.total_tokens).max(0);
         let spawn_input = json!({
             "task": format!("Continuation request: {message}"),
@@ -1481,14 +1516,21 @@ pub struct SubagentsOrchestrateTool {
     config: Config,
     db: Arc<Database>,
     channel_registry: Arc<ChannelRegistry>,
+    sandbox_router: Arc<SandboxRouter>,
 }
 
 impl SubagentsOrchestrateTool {
-    pub fn new(config: &Config, db: Arc<Database>, channel_registry: Arc<ChannelRegistry>) -> Self {
+    pub fn new(
+        config: &Config,
+        db: Arc<Database>,
+        channel_registry: Arc<ChannelRegistry>,
+        sandbox_router: Arc<SandboxRouter>,
+    ) -> Self {
         Self {
             config: config.clone(),
             db,
             channel_registry,
+            sandbox_router,
         }
     }
 
@@ -1634,8 +1676,12 @@ impl Tool for SubagentsOrchestrateTool {
         }
         let each_budget = (total_budget / packages.len() as i64).clamp(2_000, total_budget);
 
-        let spawn_tool =
-            SessionsSpawnTool::new(&self.config, self.db.clone(), self.channel_registry.clone());
+        let spawn_tool = SessionsSpawnTool::new(
+            &self.config,
+            self.db.clone(),
+            self.channel_registry.clone(),
+            self.sandbox_router.clone(),
+        );
         let mut spawned = Vec::new();
         for (idx, pkg) in packages.iter().enumerate() {
             let spawn_input = json!({
@@ -1932,8 +1978,14 @@ mod tests {
 
     #[tokio::test]
     async fn test_sessions_spawn_requires_task() {
+        let config = test_config();
+        let router = Arc::new(SandboxRouter::new(
+            config.sandbox.clone(),
+            std::path::Path::new(&config.working_dir),
+            vec![],
+        ));
         let tool =
-            SessionsSpawnTool::new(&test_config(), test_db(), Arc::new(ChannelRegistry::new()));
+            SessionsSpawnTool::new(&config, test_db(), Arc::new(ChannelRegistry::new()), router);
         let result = tool
             .execute(json!({"__microclaw_auth": {"caller_channel":"web", "caller_chat_id": 1}}))
             .await;
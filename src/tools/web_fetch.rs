@@ -1,10 +1,13 @@
 use async_trait::async_trait;
+use base64::Engine;
 use microclaw_tools::web_content_validation::WebContentValidationConfig;
-use microclaw_tools::web_fetch::WebFetchUrlValidationConfig;
+use microclaw_tools::web_fetch::{
+    WebFetchContent, WebFetchOptions, WebFetchUrlValidationConfig, MAX_PAGE_BYTES,
+};
 use serde_json::json;
 
 use super::{schema_object, Tool, ToolResult};
-use microclaw_core::llm_types::ToolDefinition;
+use microclaw_core::llm_types::{ImageSource, ToolDefinition};
 
 pub struct WebFetchTool {
     default_timeout_secs: u64,
@@ -36,7 +39,7 @@ impl Tool for WebFetchTool {
         ToolDefinition {
             name: "web_fetch".into(),
             description:
-                "Fetch a URL and return its text content (HTML parsed, scripts/styles removed). Max 20KB."
+                "Fetch a URL and return its content. HTML is reduced to readable text (scripts/styles removed), PDFs to their extracted text, JSON is pretty-printed (optionally narrowed with json_path), other text is returned as-is and images are returned for viewing. Returns up to 20KB per call; use offset/max_bytes to read further."
                     .into(),
             input_schema: schema_object(
                 json!({
@@ -47,6 +50,18 @@ impl Tool for WebFetchTool {
                     "timeout_secs": {
                         "type": "integer",
                         "description": "Timeout in seconds (defaults to configured tool timeout budget)"
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "description": "Byte offset into the extracted text to start from (default 0). Use the offset given at the end of a previous page."
+                    },
+                    "max_bytes": {
+                        "type": "integer",
+                        "description": format!("Bytes of text to return (default 20000, max {MAX_PAGE_BYTES})")
+                    },
+                    "json_path": {
+                        "type": "string",
+                        "description": "JSONPath selecting part of a JSON response, e.g. $.items[*].name"
                     }
                 }),
                 &["url"],
@@ -64,15 +79,41 @@ impl Tool for WebFetchTool {
             .and_then(|v| v.as_u64())
             .unwrap_or(self.default_timeout_secs);
 
-        match microclaw_tools::web_fetch::fetch_url_with_timeout_and_validation(
+        let options = WebFetchOptions {
+            offset: input
+                .get("offset")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as usize)
+                .unwrap_or(0),
+            max_bytes: input
+                .get("max_bytes")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as usize),
+            json_path: input
+                .get("json_path")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+        };
+
+        match microclaw_tools::web_fetch::fetch_url_content(
             url,
             timeout_secs,
             self.validation,
             self.url_validation.clone(),
+            &options,
         )
         .await
         {
-            Ok(text) => ToolResult::success(text),
+            Ok(WebFetchContent::Text(text)) => ToolResult::success(text),
+            Ok(WebFetchContent::Image { media_type, data }) => ToolResult::success(format!(
+                "Fetched {media_type} image ({} bytes); it is attached below.",
+                data.len()
+            ))
+            .with_image(ImageSource {
+                source_type: "base64".into(),
+                media_type,
+                data: base64::engine::general_purpose::STANDARD.encode(&data),
+            }),
             Err(e) => ToolResult::error(format!("Failed to fetch URL: {e}")),
         }
     }
@@ -95,6 +136,8 @@ mod tests {
         assert_eq!(def.name, "web_fetch");
         assert!(def.description.contains("20KB"));
         assert!(def.input_schema["properties"]["url"].is_object());
+        assert!(def.input_schema["properties"]["offset"].is_object());
+        assert!(def.input_schema["properties"]["json_path"].is_object());
         let required = def.input_schema["required"].as_array().unwrap();
         assert!(required.iter().any(|v| v == "url"));
     }
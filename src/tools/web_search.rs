@@ -1,4 +1,7 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
+use microclaw_tools::web_search::{format_search_results, SearchBackend};
 use serde_json::json;
 
 use super::{schema_object, Tool, ToolResult};
@@ -6,15 +9,23 @@ use microclaw_core::llm_types::ToolDefinition;
 
 pub struct WebSearchTool {
     default_timeout_secs: u64,
+    backend: Arc<dyn SearchBackend>,
+    max_results: usize,
 }
 
 const MIN_TIMEOUT_SECS: u64 = 1;
 const MAX_TIMEOUT_SECS: u64 = 60;
 
 impl WebSearchTool {
-    pub fn new(default_timeout_secs: u64) -> Self {
+    pub fn new(
+        default_timeout_secs: u64,
+        backend: Arc<dyn SearchBackend>,
+        max_results: usize,
+    ) -> Self {
         Self {
             default_timeout_secs,
+            backend,
+            max_results: max_results.max(1),
         }
     }
 }
@@ -28,7 +39,7 @@ impl Tool for WebSearchTool {
     fn definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "web_search".into(),
-            description: "Search the web using DuckDuckGo. Returns titles, URLs, and snippets."
+            description: "Search the web (DuckDuckGo unless SearXNG, Brave or Tavily is configured). Returns titles, URLs, snippets, and dates when known."
                 .into(),
             input_schema: schema_object(
                 json!({
@@ -53,12 +64,16 @@ impl Tool for WebSearchTool {
         };
         let timeout_secs = resolve_timeout_secs(&input, self.default_timeout_secs);
 
-        match microclaw_tools::web_search::search_ddg_with_timeout(&query, timeout_secs).await {
+        match self
+            .backend
+            .search(&query, self.max_results, timeout_secs)
+            .await
+        {
             Ok(results) => {
                 if results.is_empty() {
                     ToolResult::success("No results found.".into())
                 } else {
-                    ToolResult::success(results)
+                    ToolResult::success(format_search_results(&results))
                 }
             }
             Err(e) => ToolResult::error(format!("Search failed: {e}")),
@@ -89,11 +104,22 @@ fn resolve_timeout_secs(input: &serde_json::Value, default_timeout_secs: u64) ->
 #[cfg(test)]
 mod tests {
     use super::*;
+    use microclaw_tools::web_search::{FailoverSearch, FixtureBackend};
     use serde_json::json;
 
+    fn fixture_tool() -> WebSearchTool {
+        let backend = FixtureBackend::from_value(json!({
+            "rust": [
+                {"title": "Rust", "url": "https://www.rust-lang.org", "snippet": "A language", "date": "2024-05-01"},
+                {"title": "Docs", "url": "https://doc.rust-lang.org", "snippet": "The book"}
+            ]
+        }));
+        WebSearchTool::new(15, Arc::new(backend), 8)
+    }
+
     #[test]
     fn test_web_search_definition() {
-        let tool = WebSearchTool::new(15);
+        let tool = fixture_tool();
         assert_eq!(tool.name(), "web_search");
         let def = tool.definition();
         assert_eq!(def.name, "web_search");
@@ -105,7 +131,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_web_search_missing_query() {
-        let tool = WebSearchTool::new(15);
+        let tool = fixture_tool();
         let result = tool.execute(json!({})).await;
         assert!(result.is_error);
         assert!(result.content.contains("Missing required parameter: query"));
@@ -113,7 +139,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_web_search_null_query() {
-        let tool = WebSearchTool::new(15);
+        let tool = fixture_tool();
         let result = tool.execute(json!({"query": null})).await;
         assert!(result.is_error);
         assert!(result.content.contains("Missing required parameter: query"));
@@ -121,12 +147,43 @@ mod tests {
 
     #[tokio::test]
     async fn test_web_search_empty_query() {
-        let tool = WebSearchTool::new(15);
+        let tool = fixture_tool();
         let result = tool.execute(json!({"query": "   " })).await;
         assert!(result.is_error);
         assert!(result.content.contains("Missing required parameter: query"));
     }
 
+    #[tokio::test]
+    async fn test_web_search_formats_backend_results() {
+        let tool = fixture_tool();
+        let result = tool.execute(json!({"query": " rust "})).await;
+        assert!(!result.is_error);
+        assert_eq!(
+            result.content,
+            "1. Rust\n   https://www.rust-lang.org\n   Date: 2024-05-01\n   A language\n\n\
+             2. Docs\n   https://doc.rust-lang.org\n   The book\n\n"
+        );
+
+        let result = tool.execute(json!({"query": "unknown"})).await;
+        assert_eq!(result.content, "No results found.");
+    }
+
+    #[tokio::test]
+    async fn test_web_search_reports_backend_failure() {
+        let tool = WebSearchTool::new(
+            15,
+            Arc::new(FailoverSearch::new(vec![Arc::new(
+                FixtureBackend::from_path("/nonexistent/search-fixture.json"),
+            )])),
+            8,
+        );
+        let result = tool.execute(json!({"query": "rust"})).await;
+        assert!(result.is_error);
+        assert!(result
+            .content
+            .starts_with("Search failed: fixture: failed to read"));
+    }
+
     #[test]
     fn test_resolve_timeout_secs_clamps_bounds() {
         assert_eq!(resolve_timeout_secs(&json!({"timeout_secs": 0}), 15), 1);
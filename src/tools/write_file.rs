@@ -1,16 +1,21 @@
 use async_trait::async_trait;
 use serde_json::json;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tracing::info;
 
 use crate::config::WorkingDirIsolation;
 use microclaw_core::llm_types::ToolDefinition;
+use microclaw_tools::path_guard::{PathAccess, PathPolicyConfig};
+use microclaw_tools::sandbox::SandboxRouter;
 
 use super::{schema_object, Tool, ToolResult};
 
 pub struct WriteFileTool {
     working_dir: PathBuf,
     working_dir_isolation: WorkingDirIsolation,
+    sandbox_router: Option<Arc<SandboxRouter>>,
+    path_policy: Arc<PathPolicyConfig>,
 }
 
 impl WriteFileTool {
@@ -25,8 +30,20 @@ impl WriteFileTool {
         Self {
             working_dir: PathBuf::from(working_dir),
             working_dir_isolation,
+            sandbox_router: None,
+            path_policy: Arc::default(),
         }
     }
+
+    pub fn with_sandbox_router(mut self, router: Arc<SandboxRouter>) -> Self {
+        self.sandbox_router = Some(router);
+        self
+    }
+
+    pub fn with_path_policy(mut self, policy: Arc<PathPolicyConfig>) -> Self {
+        self.path_policy = policy;
+        self
+    }
 }
 
 #[async_trait]
@@ -65,8 +82,21 @@ impl Tool for WriteFileTool {
         let resolved_path = super::resolve_tool_path(&working_dir, path);
         let resolved_path_str = resolved_path.to_string_lossy().to_string();
 
-        if let Err(msg) = microclaw_tools::path_guard::check_path(&resolved_path_str) {
-            return ToolResult::error(msg);
+        let sandbox =
+            match super::sandbox_fs_for(self.sandbox_router.as_ref(), &working_dir, &input) {
+                Ok(fs) => fs,
+                Err(msg) => return ToolResult::error(msg),
+            };
+        let policy = super::path_policy_for(&self.path_policy, &input);
+        if let Err(denied) = super::check_tool_path(
+            sandbox.as_ref(),
+            &policy,
+            &resolved_path_str,
+            PathAccess::Write,
+        )
+        .await
+        {
+            return denied;
         }
 
         // Guard: SKILL.md files must go in the dedicated skills directory, not runtime/skills/.
@@ -90,8 +120,22 @@ impl Tool for WriteFileTool {
             None => return ToolResult::error("Missing 'content' parameter".into()),
         };
 
+        if let Err(denied) = policy.check_write_size(&resolved_path_str, content.len() as u64) {
+            return super::path_denied_result(denied);
+        }
+
         info!("Writing file: {}", resolved_path.display());
 
+        if let Some(fs) = &sandbox {
+            return match fs.write(&resolved_path_str, content.as_bytes()).await {
+                Ok(()) => ToolResult::success(format!(
+                    "Successfully wrote to {}",
+                    resolved_path.display()
+                )),
+                Err(e) => ToolResult::error(format!("Failed to write file: {e}")),
+            };
+        }
+
         if let Some(parent) = resolved_path.parent() {
             if let Err(e) = tokio::fs::create_dir_all(parent).await {
                 return ToolResult::error(format!("Failed to create directories: {e}"));
@@ -177,4 +221,61 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(&root);
     }
+
+    #[tokio::test]
+    async fn test_file_tools_operate_inside_native_sandbox() {
+        use microclaw_tools::sandbox::{SandboxBackend, SandboxConfig, SandboxMode};
+        if !microclaw_tools::sandbox_native::native_available() {
+            eprintln!("skipping: native sandbox unavailable");
+            return;
+        }
+        let root = std::env::temp_dir().join(format!("microclaw_wf4_{}", uuid::Uuid::new_v4()));
+        let work = root.join("workspace");
+        std::fs::create_dir_all(&work).unwrap();
+        let work = std::fs::canonicalize(&work).unwrap();
+        let config = SandboxConfig {
+            mode: SandboxMode::All,
+            backend: SandboxBackend::Native,
+            ..SandboxConfig::default()
+        };
+        let router = Arc::new(SandboxRouter::new(config, &work, vec![]));
+        let work_str = work.to_str().unwrap();
+
+        let write = WriteFileTool::new(work_str).with_sandbox_router(router.clone());
+        let result = write
+            .execute(json!({"path": "notes/a.txt", "content": "alpha\nbeta\n"}))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        assert_eq!(
+            std::fs::read_to_string(work.join("shared/notes/a.txt")).unwrap(),
+            "alpha\nbeta\n"
+        );
+
+        let edit = super::super::edit_file::EditFileTool::new(work_str)
+            .with_sandbox_router(router.clone());
+        let result = edit
+            .execute(json!({"path": "notes/a.txt", "old_string": "beta", "new_string": "gamma"}))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+
+        let read = super::super::read_file::ReadFileTool::new(work_str)
+            .with_sandbox_router(router.clone());
+        let result = read.execute(json!({"path": "notes/a.txt"})).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result.content.contains("gamma"));
+
+        // Host files outside the sandbox mounts are invisible, and writes there stay in the sandbox.
+        let outside = root.join("outside.txt");
+        std::fs::write(&outside, "host only").unwrap();
+        let result = read
+            .execute(json!({"path": outside.to_str().unwrap()}))
+            .await;
+        assert!(result.is_error);
+        let _ = write
+            .execute(json!({"path": root.join("new.txt").to_str().unwrap(), "content": "x"}))
+            .await;
+        assert!(!root.join("new.txt").exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
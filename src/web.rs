@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use axum::extract::{Path, Query, State};
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::response::{Html, IntoResponse};
@@ -15,13 +15,15 @@ use serde_json::json;
 use tokio::sync::{broadcast, Mutex};
 use tracing::{error, info, warn};
 
-use crate::agent_engine::{process_with_agent_with_events, AgentEvent, AgentRequestContext};
+use crate::agent_engine::{
+    process_with_agent_structured, process_with_agent_with_events, AgentEvent, AgentRequestContext,
+};
 use crate::chat_commands::handle_chat_command;
 use crate::config::{Config, WorkingDirIsolation};
 use crate::runtime::AppState;
 use microclaw_channels::channel::ConversationKind;
 use microclaw_channels::channel::{
-    deliver_and_store_bot_message, get_chat_routing, session_source_for_chat,
+    deliver_and_store_bot_message, get_chat_routing, session_source_for_chat, BotDelivery,
 };
 use microclaw_channels::channel_adapter::{ChannelAdapter, ChannelRegistry};
 use microclaw_observability::metrics::{OtlpMetricExporter, OtlpMetricSnapshot};
@@ -31,6 +33,7 @@ use microclaw_storage::usage::build_usage_report;
 mod a2a;
 mod auth;
 mod config;
+mod memory;
 mod metrics;
 mod middleware;
 mod sessions;
@@ -754,6 +757,10 @@ struct SendRequest {
     session_key: Option<String>,
     sender_name: Option<String>,
     message: String,
+    /// JSON schema the final answer must satisfy; the validated value is
+    /// returned as `structured` next to `response`.
+    #[serde(default)]
+    response_schema: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -903,6 +910,15 @@ struct ForkSessionRequest {
     source_session_key: String,
     target_session_key: Option<String>,
     fork_point: Option<usize>,
+    /// Restore the source's working-dir checkpoint for the fork point into the fork
+    /// (default true).
+    restore_files: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RestoreCheckpointRequest {
+    /// Newest unrestored checkpoint when omitted, like `/undo`.
+    checkpoint_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1355,9 +1371,11 @@ async fn api_usage(
 
     let session_key = normalize_session_key(query.session_key.as_deref());
     let chat_id = resolve_chat_id_for_session_key_read(&state, &session_key).await?;
-    let report = build_usage_report(state.app_state.db.clone(), chat_id)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let report = build_usage_report(state.app_state.db.clone(), chat_id, &|row| {
+        state.app_state.config.estimate_usage_cost_usd(row)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
     let memory_observability = call_blocking(state.app_state.db.clone(), move |db| {
         db.get_memory_observability_summary(Some(chat_id))
     })
@@ -1379,6 +1397,10 @@ async fn api_usage(
             "reflector_inserted_24h": memory_observability.reflector_inserted_24h,
             "reflector_updated_24h": memory_observability.reflector_updated_24h,
             "reflector_skipped_24h": memory_observability.reflector_skipped_24h,
+            "reflector_queued_24h": memory_observability.reflector_queued_24h,
+            "reflector_approved_24h": memory_observability.reflector_approved_24h,
+            "reflector_rejected_24h": memory_observability.reflector_rejected_24h,
+            "review_pending": memory_observability.review_pending,
             "injection_events_24h": memory_observability.injection_events_24h,
             "injection_selected_24h": memory_observability.injection_selected_24h,
             "injection_candidates_24h": memory_observability.injection_candidates_24h,
@@ -1452,6 +1474,10 @@ async fn api_memory_observability(
             "reflector_inserted_24h": summary.reflector_inserted_24h,
             "reflector_updated_24h": summary.reflector_updated_24h,
             "reflector_skipped_24h": summary.reflector_skipped_24h,
+            "reflector_queued_24h": summary.reflector_queued_24h,
+            "reflector_approved_24h": summary.reflector_approved_24h,
+            "reflector_rejected_24h": summary.reflector_rejected_24h,
+            "review_pending": summary.review_pending,
             "injection_events_24h": summary.injection_events_24h,
             "injection_selected_24h": summary.injection_selected_24h,
             "injection_candidates_24h": summary.injection_candidates_24h
@@ -1465,6 +1491,11 @@ async fn api_memory_observability(
             "inserted_count": r.inserted_count,
             "updated_count": r.updated_count,
             "skipped_count": r.skipped_count,
+            "queued_count": r.queued_count,
+            "auto_approved_count": r.auto_approved_count,
+            "approved_count": r.approved_count,
+            "edited_count": r.edited_count,
+            "rejected_count": r.rejected_count,
             "dedup_method": r.dedup_method,
             "parse_ok": r.parse_ok,
             "error_text": r.error_text,
@@ -1543,6 +1574,7 @@ async fn api_hook_agent(
         session_key: Some(session_key),
         sender_name: body.sender_name.or(body.name),
         message: body.message,
+        response_schema: None,
     };
     stream::start_stream_run_with_actor(state, send, "hook:token".to_string(), "/hooks/agent").await
 }
@@ -1594,6 +1626,7 @@ async fn api_hook_wake(
         session_key: Some(session_key),
         sender_name: Some(sender_name),
         message,
+        response_schema: None,
     };
     stream::start_stream_run_with_actor(state, send, "hook:token".to_string(), "/hooks/wake").await
 }
@@ -1620,6 +1653,14 @@ async fn send_and_store_response_with_events(
     if text.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "message is required".into()));
     }
+    if let Some(schema) = &body.response_schema {
+        crate::structured_output::check_schema(schema).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("invalid response_schema: {e}"),
+            )
+        })?;
+    }
 
     let session_key = normalize_session_key(body.session_key.as_deref());
     let parsed_chat_id = parse_chat_id_from_session_key(&session_key);
@@ -1657,13 +1698,8 @@ async fn send_and_store_response_with_events(
     if let Some(command_reply) =
         handle_chat_command(&state.app_state, chat_id, "web", &text, None).await
     {
-        if let Some(tx) = event_tx {
-            let _ = tx.send(AgentEvent::FinalResponse {
-                text: command_reply.clone(),
-            });
-        }
         let bot_username = state.app_state.config.bot_username_for_channel("web");
-        deliver_and_store_bot_message(
+        let delivery = deliver_and_store_bot_message(
             &state.app_state.channel_registry,
             state.app_state.db.clone(),
             &bot_username,
@@ -1672,11 +1708,19 @@ async fn send_and_store_response_with_events(
         )
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        let blocked = matches!(delivery, BotDelivery::Blocked(_));
+        let command_reply = delivery.into_visible_text();
+        if let Some(tx) = event_tx {
+            let _ = tx.send(AgentEvent::FinalResponse {
+                text: command_reply.clone(),
+            });
+        }
         return Ok(Json(json!({
             "ok": true,
             "session_key": session_key,
             "chat_id": chat_id,
             "response": command_reply,
+            "blocked": blocked,
         })));
     }
 
@@ -1699,16 +1743,44 @@ async fn send_and_store_response_with_events(
         chat_id,
         chat_type: "web",
     };
-    let response = if let Some(tx) = event_tx {
-        process_with_agent_with_events(&state.app_state, request_ctx, None, None, Some(tx))
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    let run = |tx| {
+        let app_state = &state.app_state;
+        let schema = body.response_schema.clone();
+        async move {
+            match schema {
+                Some(schema) => {
+                    process_with_agent_structured(app_state, request_ctx, None, schema, Some(tx))
+                        .await
+                        .map(|reply| (reply.text, Some(reply.output)))
+                }
+                None => {
+                    process_with_agent_with_events(app_state, request_ctx, None, None, Some(tx))
+                        .await
+                        .map(|text| (text, None))
+                }
+            }
+        }
+    };
+    let (response, structured) = if let Some(tx) = event_tx {
+        // The engine's FinalResponse carries unfiltered text; it is re-sent after delivery.
+        let (engine_tx, mut engine_rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
+        let outer_tx = tx.clone();
+        let relay = tokio::spawn(async move {
+            while let Some(evt) = engine_rx.recv().await {
+                if !matches!(evt, AgentEvent::FinalResponse { .. }) {
+                    let _ = outer_tx.send(evt);
+                }
+            }
+        });
+        let result = run(&engine_tx).await;
+        drop(engine_tx);
+        let _ = relay.await;
+        result.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
     } else {
         let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
-        let result =
-            process_with_agent_with_events(&state.app_state, request_ctx, None, None, Some(&tx))
-                .await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        let result = run(&tx)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
         drop(tx);
         while let Some(evt) = rx.recv().await {
             metrics_apply_agent_event(&state, &evt).await;
@@ -1728,7 +1800,7 @@ async fn send_and_store_response_with_events(
     }
 
     let bot_username = state.app_state.config.bot_username_for_channel("web");
-    deliver_and_store_bot_message(
+    let delivery = deliver_and_store_bot_message(
         &state.app_state.channel_registry,
         state.app_state.db.clone(),
         &bot_username,
@@ -1737,13 +1809,29 @@ async fn send_and_store_response_with_events(
     )
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let blocked = matches!(delivery, BotDelivery::Blocked(_));
+    let visible = delivery.into_visible_text();
+    let filtered = visible != response;
+    let response = visible;
+    if let Some(tx) = event_tx {
+        let _ = tx.send(AgentEvent::FinalResponse {
+            text: response.clone(),
+        });
+    }
 
-    Ok(Json(json!({
+    let mut payload = json!({
         "ok": true,
         "session_key": session_key,
         "chat_id": chat_id,
         "response": response,
-    })))
+        "blocked": blocked,
+    });
+    // Structured output is built from the unfiltered reply, so it is dropped
+    // whenever the outbound filter changed or blocked that reply.
+    if let Some(structured) = structured.filter(|_| !filtered) {
+        payload["structured"] = structured;
+    }
+    Ok(Json(payload))
 }
 
 async fn api_audit_logs(
@@ -1925,10 +2013,28 @@ fn build_router(web_state: WebState) -> Router {
         .route("/api/sessions", get(sessions::api_sessions))
         .route("/api/sessions/tree", get(sessions::api_sessions_tree))
         .route("/api/sessions/fork", post(sessions::api_sessions_fork))
+        .route(
+            "/api/sessions/:id/checkpoints",
+            get(sessions::api_session_checkpoints).post(sessions::api_restore_session_checkpoint),
+        )
         .route("/api/audit", get(api_audit_logs))
         .route("/api/history", get(sessions::api_history))
         .route("/api/usage", get(api_usage))
         .route("/api/memory_observability", get(api_memory_observability))
+        .route("/api/memory/export", get(memory::api_memory_export))
+        .route(
+            "/api/memory/import",
+            post(memory::api_memory_import).layer(DefaultBodyLimit::max(memory::IMPORT_MAX_BYTES)),
+        )
+        .route("/api/memory/reviews", get(memory::api_memory_reviews))
+        .route(
+            "/api/memory/reviews/:id/approve",
+            post(memory::api_memory_review_approve),
+        )
+        .route(
+            "/api/memory/reviews/:id/reject",
+            post(memory::api_memory_review_reject),
+        )
         .route("/api/metrics", get(metrics::api_metrics))
         .route("/api/metrics/summary", get(metrics::api_metrics_summary))
         .route("/api/metrics/history", get(metrics::api_metrics_history))
@@ -2141,7 +2247,17 @@ mod tests {
         cfg
     }
 
-    fn test_state_with_config(llm: Box<dyn LlmProvider>, mut cfg: Config) -> Arc<AppState> {
+    fn test_state_with_config(llm: Box<dyn LlmProvider>, cfg: Config) -> Arc<AppState> {
+        let mut registry = ChannelRegistry::new();
+        registry.register(Arc::new(WebAdapter));
+        test_state_with_registry(llm, cfg, registry)
+    }
+
+    fn test_state_with_registry(
+        llm: Box<dyn LlmProvider>,
+        mut cfg: Config,
+        registry: ChannelRegistry,
+    ) -> Arc<AppState> {
         let dir = std::env::temp_dir().join(format!("microclaw_webtest_{}", uuid::Uuid::new_v4()));
         std::fs::create_dir_all(&dir).unwrap();
         cfg.data_dir = dir.to_string_lossy().to_string();
@@ -2150,8 +2266,6 @@ mod tests {
         std::fs::create_dir_all(&runtime_dir).unwrap();
         let db = Arc::new(Database::new(&runtime_dir).unwrap());
         let memory_backend = Arc::new(crate::memory_backend::MemoryBackend::local_only(db.clone()));
-        let mut registry = ChannelRegistry::new();
-        registry.register(Arc::new(WebAdapter));
         let channel_registry = Arc::new(registry);
         let state = AppState {
             config: cfg.clone(),
@@ -2161,6 +2275,8 @@ mod tests {
             skills: SkillManager::from_skills_dir(&cfg.skills_data_dir()),
             hooks: Arc::new(crate::hooks::HookManager::for_tests()),
             llm,
+            llm_health: Arc::new(crate::llm::LlmHealthRegistry::new()),
+            llm_clients: Arc::new(crate::llm::LlmClientPool::new()),
             llm_provider_overrides: Arc::new(tokio::sync::RwLock::new(
                 std::collections::HashMap::new(),
             )),
@@ -2566,6 +2682,8 @@ mod tests {
                 default_model: Some("custom-model".to_string()),
                 models: vec!["custom-model".to_string()],
                 show_thinking: None,
+                thinking_budget_tokens: None,
+                fallback_providers: Vec::new(),
             },
         );
         let web_state = test_web_state_from_app_state(
@@ -2966,6 +3084,254 @@ mod tests {
             .unwrap_or(false));
     }
 
+    struct RedactingFilter;
+
+    #[async_trait::async_trait]
+    impl microclaw_channels::channel_adapter::OutboundMessageFilter for RedactingFilter {
+        async fn filter_outbound(
+            &self,
+            _channel: &str,
+            _chat_id: i64,
+            text: &str,
+        ) -> microclaw_channels::channel_adapter::OutboundDecision {
+            use microclaw_channels::channel_adapter::OutboundDecision;
+            if text.starts_with("Context cleared") {
+                OutboundDecision::Block("no resets".into())
+            } else {
+                OutboundDecision::Send(text.replace("llm", "[redacted]"))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_send_returns_filtered_text() {
+        let mut registry = ChannelRegistry::new();
+        registry.register(Arc::new(WebAdapter));
+        registry.set_outbound_filter(Arc::new(RedactingFilter));
+        let state = test_state_with_registry(Box::new(DummyLlm), test_config_template(), registry);
+        let db = state.db.clone();
+        let app = build_router(test_web_state_from_app_state(state, WebLimits::default()));
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/send")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"session_key": "main", "message": "hi"}).to_string(),
+            ))
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v["response"], "hello from [redacted]");
+        assert_eq!(v["blocked"], false);
+        let chat_id = v["chat_id"].as_i64().unwrap();
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/send")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"session_key": "main", "message": "/reset"}).to_string(),
+            ))
+            .unwrap();
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v["response"], "[Message blocked: no resets]");
+        assert_eq!(v["blocked"], true);
+
+        let stored = call_blocking(db, move |d| d.get_all_messages(chat_id))
+            .await
+            .unwrap();
+        assert!(stored.iter().all(|m| !m.content.contains("llm")));
+        assert!(stored
+            .iter()
+            .all(|m| !m.content.starts_with("Context cleared")));
+    }
+
+    #[tokio::test]
+    async fn test_api_memory_export_import_round_trip() {
+        let web_state = test_web_state(Box::new(DummyLlm), WebLimits::default());
+        let db = web_state.app_state.db.clone();
+        call_blocking(db.clone(), |d| {
+            d.insert_memory(None, "Team timezone is UTC+8", "KNOWLEDGE")
+        })
+        .await
+        .unwrap();
+        web_state
+            .app_state
+            .memory
+            .write_global_memory("- Prefer short answers\n")
+            .unwrap();
+        let memory_dir = web_state.app_state.memory.groups_dir().to_path_buf();
+
+        let app = build_router(web_state);
+        let req = Request::builder()
+            .method("GET")
+            .uri("/api/memory/export?scope=global")
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bundle = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(bundle.to_vec()).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.contains("Team timezone is UTC+8"));
+        assert!(text.contains(r#"{"kind":"agents_file","scope":{"type":"global"}"#));
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/memory/import?strategy=skip")
+            .body(Body::from(bundle.clone()))
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v["report"]["skipped"], 2);
+        assert_eq!(v["report"]["inserted"], 0);
+        assert_eq!(v["report"]["agents_files"], 0);
+
+        // Dedupe appends missing AGENTS.md lines; channel names that are not plain
+        // directory names are never written.
+        let extra = concat!(
+            r#"{"kind":"header","format":"microclaw-memory-bundle","version":2,"exported_at":"2024-01-01T00:00:00Z","memory_count":0,"edge_count":0,"agents_file_count":2}"#,
+            "\n",
+            r#"{"kind":"agents_file","scope":{"type":"global"},"content":"- Prefer short answers\n- Reply in English\n"}"#,
+            "\n",
+            r#"{"kind":"agents_file","scope":{"type":"bot","channel":"../escape"},"content":"x"}"#,
+            "\n",
+        );
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/memory/import?strategy=dedupe")
+            .body(Body::from(extra))
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v["report"]["agents_files"], 1);
+        assert_eq!(v["report"]["unmapped"], 1);
+        assert_eq!(
+            std::fs::read_to_string(memory_dir.join("AGENTS.md")).unwrap(),
+            "- Prefer short answers\n- Reply in English\n"
+        );
+        assert!(!memory_dir.join("..").join("escape").exists());
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/memory/import?strategy=merge")
+            .body(Body::from(bundle))
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/memory/import")
+            .body(Body::from("not a bundle"))
+            .unwrap();
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_api_memory_review_queue_approve_edit_reject() {
+        let web_state = test_web_state(Box::new(DummyLlm), WebLimits::default());
+        let db = web_state.app_state.db.clone();
+        let (first, second, third) = call_blocking(db.clone(), |d| {
+            Ok((
+                d.enqueue_memory_review(42, "insert", None, "User runs Arch", "PROFILE", 0.6)?,
+                d.enqueue_memory_review(42, "insert", None, "Standup is at 9", "EVENT", 0.5)?,
+                d.enqueue_memory_review(42, "insert", None, "Likes Vim", "PROFILE", 0.4)?,
+            ))
+        })
+        .await
+        .unwrap();
+
+        let app = build_router(web_state);
+        let req = Request::builder()
+            .method("GET")
+            .uri("/api/memory/reviews")
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v["reviews"].as_array().map(|a| a.len()), Some(3));
+
+        let req = Request::builder()
+            .method("POST")
+            .uri(format!("/api/memory/reviews/{first}/approve"))
+            .header("content-type", "application/json")
+            .body(Body::from(
+                r#"{"content":"User runs Arch Linux on a ThinkPad"}"#,
+            ))
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v["review"]["status"], "edited");
+        let memory_id = v["review"]["memory_id"].as_i64().unwrap();
+        let memory = call_blocking(db.clone(), move |d| d.get_memory_by_id(memory_id))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(memory.content, "User runs Arch Linux on a ThinkPad");
+        assert_eq!(memory.source, "reflector_reviewed");
+
+        let req = Request::builder()
+            .method("POST")
+            .uri(format!("/api/memory/reviews/{first}/reject"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let req = Request::builder()
+            .method("POST")
+            .uri(format!("/api/memory/reviews/{second}/approve"))
+            .body(Body::from("{not json"))
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = Request::builder()
+            .method("POST")
+            .uri(format!("/api/memory/reviews/{third}/reject"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let pending = call_blocking(db.clone(), |d| {
+            d.get_memory_reviews(None, Some("pending"), 10)
+        })
+        .await
+        .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, second);
+    }
+
     #[tokio::test]
     async fn test_read_endpoints_unknown_session_return_404_without_creating_chat() {
         let web_state = test_web_state(Box::new(DummyLlm), WebLimits::default());
@@ -3534,6 +3900,177 @@ mod tests {
         assert!(found);
     }
 
+    #[tokio::test]
+    async fn test_session_checkpoints_list_restore_and_fork() {
+        let mut cfg = test_config_template();
+        cfg.working_dir_isolation = WorkingDirIsolation::Chat;
+        let state = test_state_with_config(Box::new(DummyLlm), cfg);
+        let web_state = test_web_state_from_app_state(state.clone(), WebLimits::default());
+        let app = build_router(web_state);
+
+        let seed_req = Request::builder()
+            .method("POST")
+            .uri("/api/send")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                r#"{"session_key":"cp-main","sender_name":"u","message":"seed"}"#,
+            ))
+            .unwrap();
+        let seed_resp = app.clone().oneshot(seed_req).await.unwrap();
+        assert_eq!(seed_resp.status(), StatusCode::OK);
+        let chat_id = state
+            .db
+            .get_chat_id_by_channel_and_title("web", "cp-main")
+            .unwrap()
+            .unwrap();
+
+        let dir = crate::checkpoints::chat_working_dir(&state.config, "web", chat_id);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("notes.txt"), "v1").unwrap();
+        let checkpoint = crate::checkpoints::checkpoint_turn(&state, "web", chat_id)
+            .await
+            .unwrap();
+        std::fs::write(dir.join("notes.txt"), "v2").unwrap();
+
+        let list_req = Request::builder()
+            .method("GET")
+            .uri("/api/sessions/cp-main/checkpoints")
+            .body(Body::empty())
+            .unwrap();
+        let list_resp = app.clone().oneshot(list_req).await.unwrap();
+        assert_eq!(list_resp.status(), StatusCode::OK);
+        let list_body = axum::body::to_bytes(list_resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let list_json: serde_json::Value = serde_json::from_slice(&list_body).unwrap();
+        assert_eq!(
+            list_json["checkpoints"][0]["id"].as_str(),
+            Some(checkpoint.id.as_str())
+        );
+
+        let fork_req = Request::builder()
+            .method("POST")
+            .uri("/api/sessions/fork")
+            .header("content-type", "application/json")
+            .body(Body::from(format!(
+                r#"{{"source_session_key":"cp-main","target_session_key":"cp-fork","fork_point":{}}}"#,
+                checkpoint.message_count
+            )))
+            .unwrap();
+        let fork_resp = app.clone().oneshot(fork_req).await.unwrap();
+        assert_eq!(fork_resp.status(), StatusCode::OK);
+        let fork_body = axum::body::to_bytes(fork_resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let fork_json: serde_json::Value = serde_json::from_slice(&fork_body).unwrap();
+        assert_eq!(
+            fork_json["checkpoint_id"].as_str(),
+            Some(checkpoint.id.as_str())
+        );
+        let fork_dir = crate::checkpoints::chat_working_dir(
+            &state.config,
+            "web",
+            fork_json["target_chat_id"].as_i64().unwrap(),
+        );
+        assert_eq!(
+            std::fs::read_to_string(fork_dir.join("notes.txt")).unwrap(),
+            "v1"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("notes.txt")).unwrap(),
+            "v2"
+        );
+
+        let restore_req = Request::builder()
+            .method("POST")
+            .uri("/api/sessions/cp-main/checkpoints")
+            .header("content-type", "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        let restore_resp = app.clone().oneshot(restore_req).await.unwrap();
+        assert_eq!(restore_resp.status(), StatusCode::OK);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("notes.txt")).unwrap(),
+            "v1"
+        );
+
+        let missing_req = Request::builder()
+            .method("POST")
+            .uri("/api/sessions/cp-main/checkpoints")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"checkpoint_id":"missing"}"#))
+            .unwrap();
+        let missing_resp = app.oneshot(missing_req).await.unwrap();
+        assert_eq!(missing_resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_session_fork_past_pruned_checkpoint_restores_no_files() {
+        let mut cfg = test_config_template();
+        cfg.working_dir_isolation = WorkingDirIsolation::Chat;
+        cfg.checkpoints.max_per_chat = 1;
+        let state = test_state_with_config(Box::new(DummyLlm), cfg);
+        let web_state = test_web_state_from_app_state(state.clone(), WebLimits::default());
+        let app = build_router(web_state);
+
+        let send = |message: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/send")
+                .header("content-type", "application/json")
+                .body(Body::from(format!(
+                    r#"{{"session_key":"cp-pruned","sender_name":"u","message":"{message}"}}"#
+                )))
+                .unwrap()
+        };
+        let resp = app.clone().oneshot(send("seed")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let chat_id = state
+            .db
+            .get_chat_id_by_channel_and_title("web", "cp-pruned")
+            .unwrap()
+            .unwrap();
+
+        let dir = crate::checkpoints::chat_working_dir(&state.config, "web", chat_id);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("notes.txt"), "v1").unwrap();
+        let first = crate::checkpoints::checkpoint_turn(&state, "web", chat_id)
+            .await
+            .unwrap();
+        std::fs::write(dir.join("notes.txt"), "v2").unwrap();
+        let resp = app.clone().oneshot(send("more")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        // Keeping one checkpoint prunes the first, whose "v1" the fork point needs.
+        let second = crate::checkpoints::checkpoint_turn(&state, "web", chat_id)
+            .await
+            .unwrap();
+        assert!(second.message_count > first.message_count);
+
+        let fork_req = Request::builder()
+            .method("POST")
+            .uri("/api/sessions/fork")
+            .header("content-type", "application/json")
+            .body(Body::from(format!(
+                r#"{{"source_session_key":"cp-pruned","target_session_key":"cp-pruned-fork","fork_point":{}}}"#,
+                first.message_count
+            )))
+            .unwrap();
+        let fork_resp = app.oneshot(fork_req).await.unwrap();
+        assert_eq!(fork_resp.status(), StatusCode::OK);
+        let fork_body = axum::body::to_bytes(fork_resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let fork_json: serde_json::Value = serde_json::from_slice(&fork_body).unwrap();
+        assert!(fork_json["checkpoint_id"].is_null());
+        assert_eq!(fork_json["files_restored"], false);
+        let fork_dir = crate::checkpoints::chat_working_dir(
+            &state.config,
+            "web",
+            fork_json["target_chat_id"].as_i64().unwrap(),
+        );
+        assert!(!fork_dir.join("notes.txt").exists());
+    }
+
     #[tokio::test]
     async fn test_web_send_model_slash_command() {
         let web_state = test_web_state(Box::new(DummyLlm), WebLimits::default());
@@ -87,6 +87,7 @@ pub(super) async fn api_a2a_message(
             session_key: Some(session_key.clone()),
             sender_name: Some(sender_name),
             message,
+            response_schema: body.response_schema.clone(),
         },
     )
     .await?;
@@ -96,6 +97,7 @@ pub(super) async fn api_a2a_message(
         .and_then(|v| v.as_str())
         .unwrap_or_default()
         .to_string();
+    let structured = payload.get("structured").cloned();
     let resolved_session_key = payload
         .get("session_key")
         .and_then(|v| v.as_str())
@@ -119,5 +121,6 @@ pub(super) async fn api_a2a_message(
         agent_name: local_agent_name(&state.app_state.config),
         session_key: resolved_session_key,
         response,
+        structured,
     }))
 }
@@ -162,6 +162,7 @@ pub(super) async fn api_config_self_check(
         "read_file",
         "write_file",
         "edit_file",
+        "apply_patch",
         "glob",
         "grep",
     ]
@@ -0,0 +1,266 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::memory_service::{
+    decide_memory_review, export_memory_bundle, import_memory_bundle, MemoryReviewDecision,
+};
+use crate::web::{
+    audit_log, middleware::AuthScope, normalize_session_key, require_scope,
+    resolve_chat_id_for_session_key_read, WebState,
+};
+use microclaw_storage::db::{call_blocking, MemoryReview};
+use microclaw_storage::memory_bundle::{read_bundle, ExportOptions, MergeStrategy};
+
+/// Bundles with embeddings outgrow axum's 2 MiB default body limit quickly.
+pub(super) const IMPORT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct MemoryExportQuery {
+    /// `all` (default), `global` or `chat` (with `session_key`).
+    scope: Option<String>,
+    session_key: Option<String>,
+    include_archived: Option<bool>,
+    embeddings: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MemoryImportQuery {
+    strategy: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MemoryReviewsQuery {
+    /// `pending` (default), `approved`, `edited`, `rejected` or `all`.
+    status: Option<String>,
+    /// Only reviews of this session's chat; every chat when omitted.
+    session_key: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Optional body for approve: supplying `content` approves an edited fact.
+#[derive(Debug, Default, Deserialize)]
+pub struct MemoryReviewApproveRequest {
+    content: Option<String>,
+    category: Option<String>,
+}
+
+pub async fn api_memory_export(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Query(query): Query<MemoryExportQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    require_scope(&state, &headers, AuthScope::Admin).await?;
+
+    let scope = query
+        .scope
+        .as_deref()
+        .unwrap_or("all")
+        .trim()
+        .to_ascii_lowercase();
+    let mut options = ExportOptions {
+        include_archived: query.include_archived.unwrap_or(true),
+        include_embeddings: query.embeddings.unwrap_or(false),
+        ..Default::default()
+    };
+    match scope.as_str() {
+        "all" => {}
+        "global" => options.global_only = true,
+        "chat" => {
+            let session_key = normalize_session_key(query.session_key.as_deref());
+            options.chat_id =
+                Some(resolve_chat_id_for_session_key_read(&state, &session_key).await?);
+        }
+        other => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("unknown scope '{other}' (expected all, global or chat)"),
+            ))
+        }
+    }
+
+    let bundle = export_memory_bundle(state.app_state.db.clone(), &state.app_state.memory, options)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"microclaw-memories.jsonl\"",
+            ),
+        ],
+        bundle,
+    ))
+}
+
+pub async fn api_memory_import(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Query(query): Query<MemoryImportQuery>,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    require_scope(&state, &headers, AuthScope::Admin).await?;
+
+    let strategy = match query.strategy.as_deref() {
+        None => MergeStrategy::default(),
+        Some(raw) => MergeStrategy::parse(raw).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("unknown strategy '{raw}' (expected skip, overwrite or dedupe)"),
+            )
+        })?,
+    };
+    let records =
+        read_bundle(body.as_ref()).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let report = import_memory_bundle(
+        state.app_state.db.clone(),
+        &state.app_state.memory,
+        state.app_state.embedding.as_ref(),
+        records,
+        strategy,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(json!({
+        "ok": true,
+        "strategy": strategy.as_str(),
+        "report": report,
+    })))
+}
+
+fn review_json(r: &MemoryReview) -> serde_json::Value {
+    json!({
+        "id": r.id,
+        "chat_id": r.chat_id,
+        "run_id": r.run_id,
+        "action": r.action,
+        "target_memory_id": r.target_memory_id,
+        "content": r.content,
+        "category": r.category,
+        "confidence": r.confidence,
+        "status": r.status,
+        "memory_id": r.memory_id,
+        "created_at": r.created_at,
+        "decided_at": r.decided_at,
+        "decided_by": r.decided_by,
+    })
+}
+
+pub async fn api_memory_reviews(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Query(query): Query<MemoryReviewsQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    require_scope(&state, &headers, AuthScope::Read).await?;
+
+    let status = query
+        .status
+        .as_deref()
+        .unwrap_or("pending")
+        .trim()
+        .to_ascii_lowercase();
+    let status = match status.as_str() {
+        "all" => None,
+        "pending" | "approved" | "edited" | "rejected" => Some(status),
+        other => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "unknown status '{other}' (expected pending, approved, edited, rejected or all)"
+                ),
+            ))
+        }
+    };
+    let chat_id = match query.session_key.as_deref() {
+        Some(raw) if !raw.trim().is_empty() => {
+            let session_key = normalize_session_key(Some(raw));
+            Some(resolve_chat_id_for_session_key_read(&state, &session_key).await?)
+        }
+        _ => None,
+    };
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+    let reviews = call_blocking(state.app_state.db.clone(), move |db| {
+        db.get_memory_reviews(chat_id, status.as_deref(), limit)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(json!({
+        "ok": true,
+        "enabled": state.app_state.config.memory_review.enabled,
+        "auto_approve_confidence": state.app_state.config.memory_review.auto_approve_confidence,
+        "reviews": reviews.iter().map(review_json).collect::<Vec<_>>(),
+    })))
+}
+
+pub async fn api_memory_review_approve(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Path(id): Path<i64>,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    // An unparseable edit must not fall back to approving the original fact.
+    let body: MemoryReviewApproveRequest = if body.iter().all(u8::is_ascii_whitespace) {
+        MemoryReviewApproveRequest::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+    };
+    let decision = match body.content.as_deref() {
+        Some(content) => MemoryReviewDecision::edit(content, body.category.as_deref())
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?,
+        None => MemoryReviewDecision::Approve,
+    };
+    decide_review(headers, state, id, decision).await
+}
+
+pub async fn api_memory_review_reject(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Path(id): Path<i64>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    decide_review(headers, state, id, MemoryReviewDecision::Reject).await
+}
+
+async fn decide_review(
+    headers: HeaderMap,
+    state: WebState,
+    id: i64,
+    decision: MemoryReviewDecision,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let identity = require_scope(&state, &headers, AuthScope::Approvals).await?;
+
+    let decided_by = format!("web:{}", identity.actor);
+    let review = decide_memory_review(&state.app_state, id, decision, &decided_by)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let Some(review) = review else {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("review #{id} does not exist or was already decided"),
+        ));
+    };
+
+    audit_log(
+        &state,
+        "operator",
+        &identity.actor,
+        &format!("memory.review_{}", review.status),
+        Some(&id.to_string()),
+        "ok",
+        review.memory_id.map(|m| m.to_string()).as_deref(),
+    )
+    .await;
+
+    Ok(Json(json!({
+        "ok": true,
+        "review": review_json(&review),
+    })))
+}
@@ -261,6 +261,35 @@ pub(super) async fn api_sessions_fork(
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+    // With a shared working dir the fork already sees the source's files, and
+    // restoring an old checkpoint there would roll back the source too.
+    let config = &state.app_state.config;
+    let mut checkpoint_id = None;
+    let mut files_restored = false;
+    if body.restore_files.unwrap_or(true)
+        && config.checkpoints.enabled
+        && matches!(config.working_dir_isolation, WorkingDirIsolation::Chat)
+    {
+        let target_dir = crate::checkpoints::chat_working_dir(config, "web", target_chat_id);
+        match crate::checkpoints::restore_for_fork(
+            &state.app_state,
+            source_chat_id,
+            fork_point as i64,
+            target_dir,
+        )
+        .await
+        {
+            Ok(id) => {
+                checkpoint_id = id;
+                files_restored = true;
+            }
+            Err(e) => warn!(
+                "Failed to restore files for fork {} of {}: {}",
+                target_session_key, source_session_key, e
+            ),
+        }
+    }
+
     audit_log(
         &state,
         "operator",
@@ -277,7 +306,92 @@ pub(super) async fn api_sessions_fork(
         "source_chat_id": source_chat_id,
         "target_session_key": target_session_key,
         "target_chat_id": target_chat_id,
-        "fork_point": fork_point
+        "fork_point": fork_point,
+        "checkpoint_id": checkpoint_id,
+        "files_restored": files_restored
+    })))
+}
+
+pub(super) async fn api_session_checkpoints(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Path(session_key): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    metrics_http_inc(&state).await;
+    require_scope(&state, &headers, AuthScope::Read).await?;
+
+    let session_key = normalize_session_key(Some(&session_key));
+    let chat_id = resolve_chat_id_for_session_key_read(&state, &session_key).await?;
+    let limit = state.app_state.config.checkpoints.max_per_chat;
+    let records = call_blocking(state.app_state.db.clone(), move |db| {
+        db.list_checkpoints(chat_id, limit)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let checkpoints = records
+        .into_iter()
+        .map(|r| {
+            json!({
+                "id": r.id,
+                "kind": r.kind,
+                "working_dir": r.working_dir,
+                "message_count": r.message_count,
+                "created_at": r.created_at,
+                "restored_at": r.restored_at,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(Json(json!({
+        "ok": true,
+        "session_key": session_key,
+        "chat_id": chat_id,
+        "checkpoints": checkpoints,
+    })))
+}
+
+pub(super) async fn api_restore_session_checkpoint(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Path(session_key): Path<String>,
+    Json(body): Json<RestoreCheckpointRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    metrics_http_inc(&state).await;
+    let identity = require_scope(&state, &headers, AuthScope::Approvals).await?;
+
+    if matches!(
+        state.app_state.config.working_dir_isolation,
+        WorkingDirIsolation::Shared
+    ) {
+        return Err((
+            StatusCode::CONFLICT,
+            crate::checkpoints::SHARED_WORKING_DIR_UNDO_ERROR.into(),
+        ));
+    }
+    let session_key = normalize_session_key(Some(&session_key));
+    let chat_id = resolve_chat_id_for_session_key_read(&state, &session_key).await?;
+    let restored = crate::checkpoints::undo(&state.app_state, chat_id, body.checkpoint_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let Some((record, summary)) = restored else {
+        return Err((StatusCode::NOT_FOUND, "checkpoint not found".into()));
+    };
+
+    audit_log(
+        &state,
+        "operator",
+        &identity.actor,
+        "session.checkpoint_restore",
+        Some(&session_key),
+        "ok",
+        Some(&record.id),
+    )
+    .await;
+    Ok(Json(json!({
+        "ok": true,
+        "checkpoint_id": record.id,
+        "files_written": summary.written,
+        "files_removed": summary.removed,
     })))
 }
 
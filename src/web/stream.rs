@@ -90,6 +90,12 @@ async fn start_stream_run_internal(
             let state_for_events = state_for_task.clone();
             let run_id_for_events = run_id_for_task.clone();
             let run_history_limit = limits.run_history_limit;
+            // Deltas are raw model output; while an outbound filter may rewrite or
+            // block the reply, only the filtered text in "done" reaches the client.
+            let suppress_deltas = microclaw_channels::channel::outbound_filter_active(
+                &state_for_task.app_state.channel_registry,
+            )
+            .await;
             let forward = tokio::spawn(async move {
                 while let Some(evt) = evt_rx.recv().await {
                     match evt {
@@ -162,6 +168,8 @@ async fn start_stream_run_internal(
                                 )
                                 .await;
                         }
+                        AgentEvent::TextDelta { .. } | AgentEvent::ThinkingDelta { .. }
+                            if suppress_deltas => {}
                         AgentEvent::TextDelta { delta } => {
                             run_hub
                                 .publish(
@@ -172,6 +180,16 @@ async fn start_stream_run_internal(
                                 )
                                 .await;
                         }
+                        AgentEvent::ThinkingDelta { delta } => {
+                            run_hub
+                                .publish(
+                                    &run_id_for_events,
+                                    "thinking_delta",
+                                    json!({"delta": delta}).to_string(),
+                                    run_history_limit,
+                                )
+                                .await;
+                        }
                         AgentEvent::FinalResponse { .. } => {}
                     }
                 }
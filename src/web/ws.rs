@@ -518,6 +518,7 @@ async fn handle_request_frame(
                 session_key: Some(session_key.clone()),
                 sender_name: Some("ws-user".to_string()),
                 message: params.message,
+                response_schema: None,
             };
             let resp = match stream::start_stream_run_with_actor(
                 state.clone(),
@@ -620,7 +621,12 @@ async fn forward_run_event(
     evt: RunEvent,
 ) -> bool {
     let payload = match evt.event.as_str() {
-        "delta" => {
+        "delta" | "thinking_delta" => {
+            let kind = if evt.event == "thinking_delta" {
+                "thinking"
+            } else {
+                "text"
+            };
             let delta = serde_json::from_str::<serde_json::Value>(&evt.data)
                 .ok()
                 .and_then(|v| v.get("delta").and_then(|v| v.as_str()).map(str::to_string))
@@ -635,10 +641,7 @@ async fn forward_run_event(
                 state: "delta",
                 message: Some(ChatEventMessage {
                     role: "assistant",
-                    content: vec![ChatEventContent {
-                        kind: "text",
-                        text: delta,
-                    }],
+                    content: vec![ChatEventContent { kind, text: delta }],
                 }),
                 error_message: None,
             })
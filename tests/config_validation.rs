@@ -12,10 +12,14 @@ fn minimal_config() -> Config {
         model: String::new(),
         provider_presets: std::collections::HashMap::new(),
         llm_providers: std::collections::HashMap::new(),
+        llm_fallback_providers: Vec::new(),
+        llm_failover: microclaw::config::LlmFailoverConfig::default(),
+        llm_cassette: microclaw::config::LlmCassetteConfig::default(),
         llm_base_url: None,
         llm_user_agent: microclaw::http_client::default_llm_user_agent(),
         max_tokens: 8192,
         max_tool_iterations: 25,
+        max_parallel_tool_calls: 4,
         max_history_messages: 50,
         max_document_size_mb: 100,
         memory_token_budget: 1500,
@@ -25,6 +29,9 @@ fn minimal_config() -> Config {
         working_dir_isolation: WorkingDirIsolation::Chat,
         high_risk_tool_user_confirmation_required: true,
         sandbox: microclaw::config::SandboxConfig::default(),
+        bash_sessions: microclaw_tools::shell_session::ShellSessionConfig::default(),
+        checkpoints: microclaw_tools::checkpoint_store::CheckpointConfig::default(),
+        path_policy: microclaw_tools::path_guard::PathPolicyConfig::default(),
         openai_api_key: None,
         override_timezone: None,
         timezone: "UTC".into(),
@@ -32,6 +39,8 @@ fn minimal_config() -> Config {
         control_chat_ids: vec![],
         max_session_messages: 40,
         compact_keep_recent: 20,
+        context_window_tokens: None,
+        max_tool_result_tokens: 8000,
         default_tool_timeout_secs: 30,
         tool_timeout_overrides: std::collections::HashMap::new(),
         default_mcp_request_timeout_secs: 120,
@@ -41,6 +50,8 @@ fn minimal_config() -> Config {
         discord_no_mention: false,
         allow_group_slash_without_mention: false,
         show_thinking: false,
+        thinking_budget_tokens: None,
+        anthropic_prompt_cache: true,
         subagents: microclaw::config::SubagentConfig::default(),
         a2a: microclaw::config::A2AConfig::default(),
         openai_compat_body_overrides: std::collections::HashMap::new(),
@@ -58,14 +69,17 @@ fn minimal_config() -> Config {
             microclaw_tools::web_content_validation::WebContentValidationConfig::default(),
         web_fetch_url_validation: microclaw_tools::web_fetch::WebFetchUrlValidationConfig::default(
         ),
+        web_search: microclaw_tools::web_search::WebSearchConfig::default(),
         model_prices: vec![],
         embedding_provider: None,
         embedding_api_key: None,
         embedding_base_url: None,
         embedding_model: None,
         embedding_dim: None,
+        vector_index: None,
         reflector_enabled: true,
         reflector_interval_mins: 15,
+        memory_review: microclaw::config::MemoryReviewConfig::default(),
         soul_path: None,
         souls_dir: None,
         clawhub: microclaw::config::ClawHubConfig::default(),
@@ -0,0 +1,117 @@
+import React, { useCallback, useEffect, useState } from 'react'
+import { Badge, Button, Callout, Card, Flex, Text, TextArea } from '@radix-ui/themes'
+import { api } from '../lib/api'
+
+export type MemoryReview = {
+  id: number
+  chat_id: number
+  action: 'insert' | 'update' | 'supersede'
+  target_memory_id?: number | null
+  content: string
+  category: string
+  confidence: number
+  status: string
+  created_at: string
+}
+
+type MemoryReviewPanelProps = {
+  sessionKey: string
+  onDecided?: () => void
+}
+
+export function MemoryReviewPanel({ sessionKey, onDecided }: MemoryReviewPanelProps) {
+  const [reviews, setReviews] = useState<MemoryReview[]>([])
+  const [enabled, setEnabled] = useState(false)
+  const [error, setError] = useState('')
+  const [busyId, setBusyId] = useState<number | null>(null)
+  const [drafts, setDrafts] = useState<Record<number, string>>({})
+
+  const load = useCallback(async () => {
+    setError('')
+    try {
+      const query = new URLSearchParams({ status: 'pending', session_key: sessionKey })
+      const data = await api<{ enabled?: boolean; reviews?: MemoryReview[] }>(`/api/memory/reviews?${query.toString()}`)
+      setEnabled(Boolean(data.enabled))
+      setReviews(Array.isArray(data.reviews) ? data.reviews : [])
+      setDrafts({})
+    } catch (e) {
+      setError(e instanceof Error ? e.message : String(e))
+    }
+  }, [sessionKey])
+
+  useEffect(() => {
+    void load()
+  }, [load])
+
+  async function decide(review: MemoryReview, action: 'approve' | 'reject'): Promise<void> {
+    setBusyId(review.id)
+    setError('')
+    try {
+      const draft = drafts[review.id]
+      const edited = action === 'approve' && draft !== undefined && draft.trim() !== review.content
+      await api(`/api/memory/reviews/${review.id}/${action}`, {
+        method: 'POST',
+        body: edited ? JSON.stringify({ content: draft.trim() }) : undefined,
+      })
+      setReviews((prev) => prev.filter((r) => r.id !== review.id))
+      onDecided?.()
+    } catch (e) {
+      setError(e instanceof Error ? e.message : String(e))
+    } finally {
+      setBusyId(null)
+    }
+  }
+
+  if (!enabled && reviews.length === 0 && !error) return null
+
+  return (
+    <div className="space-y-3">
+      <Flex justify="between" align="center">
+        <Text size="2" weight="bold">Memory Review Queue</Text>
+        <Button size="1" variant="soft" onClick={() => void load()}>
+          Reload
+        </Button>
+      </Flex>
+      {error ? (
+        <Callout.Root color="red" size="1" variant="soft">
+          <Callout.Text>{error}</Callout.Text>
+        </Callout.Root>
+      ) : null}
+      {reviews.length === 0 ? (
+        <Text size="1" color="gray">No reflector memories waiting for review.</Text>
+      ) : (
+        reviews.map((review) => {
+          const draft = drafts[review.id] ?? review.content
+          const busy = busyId === review.id
+          return (
+            <Card key={review.id} className="p-3">
+              <Flex gap="2" align="center" wrap="wrap">
+                <Text size="1" color="gray">#{review.id}</Text>
+                <Badge color="gray">{review.category}</Badge>
+                <Badge color={review.action === 'insert' ? 'green' : 'blue'}>
+                  {review.action}
+                  {review.target_memory_id ? ` #${review.target_memory_id}` : ''}
+                </Badge>
+                <Text size="1" color="gray">confidence {(review.confidence * 100).toFixed(0)}%</Text>
+              </Flex>
+              <TextArea
+                className="mt-2"
+                size="2"
+                value={draft}
+                onChange={(e) => setDrafts((prev) => ({ ...prev, [review.id]: e.target.value }))}
+              />
+              <Flex gap="2" mt="2" justify="end">
+                <Button size="1" variant="soft" color="red" disabled={busy} onClick={() => void decide(review, 'reject')}>
+                  Reject
+                </Button>
+                <Button size="1" disabled={busy || !draft.trim()} onClick={() => void decide(review, 'approve')}>
+                  {draft.trim() !== review.content ? 'Save Edit' : 'Approve'}
+                </Button>
+              </Flex>
+            </Card>
+          )
+        })
+      )}
+    </div>
+  )
+}
@@ -1,5 +1,6 @@
 import React, { useMemo } from 'react'
 import { Button, Callout, Card, Dialog, Flex, Text } from '@radix-ui/themes'
+import { MemoryReviewPanel } from './memory-review-panel'
 
 export type MemoryObservability = {
   total: number
@@ -11,6 +12,10 @@ export type MemoryObservability = {
   reflector_inserted_24h: number
   reflector_updated_24h: number
   reflector_skipped_24h: number
+  reflector_queued_24h?: number
+  reflector_approved_24h?: number
+  reflector_rejected_24h?: number
+  review_pending?: number
   injection_events_24h: number
   injection_selected_24h: number
   injection_candidates_24h: number
@@ -274,6 +279,11 @@ export function UsagePanel(props: UsagePanelProps) {
                         <Text size="1" color="gray" className="mt-1 block">
                           +{fmtInt(usageMemory.reflector_inserted_24h)} / ~{fmtInt(usageMemory.reflector_updated_24h)} / -{fmtInt(usageMemory.reflector_skipped_24h)}
                         </Text>
+                        {usageMemory.reflector_queued_24h || usageMemory.review_pending ? (
+                          <Text size="1" color="gray" className="mt-1 block">
+                            review: {fmtInt(usageMemory.review_pending ?? 0)} pending / {fmtInt(usageMemory.reflector_approved_24h ?? 0)} approved / {fmtInt(usageMemory.reflector_rejected_24h ?? 0)} rejected
+                          </Text>
+                        ) : null}
                       </Card>
                       <Card className="p-3">
                         <Text size="1" color="gray" className="block">Injection Coverage 24h</Text>
@@ -299,6 +309,8 @@ export function UsagePanel(props: UsagePanelProps) {
                       <TrendRow title="Reflector Skips (7d)" subtitle="daily buckets" values={trend7d.skipped} color="#d97706" />
                       <TrendRow title="Injection Coverage (7d)" subtitle="selected/candidates %" values={trend7d.coverage} color="#7e22ce" />
                     </div>
+
+                    <MemoryReviewPanel sessionKey={usageSession || sessionKey} onDecided={onRefreshThis} />
                   </div>
                 ) : null}
 
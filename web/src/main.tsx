@@ -90,7 +90,12 @@ function collectThinkText(parts: readonly { type: string; text?: string }[] | un
   if (!Array.isArray(parts)) return ''
   const segments: string[] = []
   for (const part of parts) {
-    if (part.type !== 'text' || typeof part.text !== 'string') continue
+    if (typeof part.text !== 'string') continue
+    if (part.type === 'reasoning') {
+      if (part.text.trim()) segments.push(part.text.trim())
+      continue
+    }
+    if (part.type !== 'text') continue
     const extracted = extractThinkSegments(part.text)
     if (extracted.thinkSegments.length > 0) segments.push(...extracted.thinkSegments)
   }
@@ -1868,6 +1873,7 @@ function App() {
           }
 
           let assistantText = ''
+          let thinkingText = ''
           const toolState = new Map<
             string,
             {
@@ -1890,6 +1896,7 @@ function App() {
             }))
 
             return [
+              ...(thinkingText ? [{ type: 'reasoning' as const, text: thinkingText }] : []),
               ...(assistantText ? [{ type: 'text' as const, text: assistantText }] : []),
               ...toolParts,
             ]
@@ -1966,6 +1973,15 @@ function App() {
               continue
             }
 
+            if (event.event === 'thinking_delta') {
+              const delta = typeof data.delta === 'string' ? data.delta : ''
+              if (!delta) continue
+              thinkingText += delta
+              const content = makeContent()
+              if (content.length > 0) yield { content }
+              continue
+            }
+
             if (event.event === 'error') {
               const message = typeof data.error === 'string' ? data.error : 'stream error'
               throw new Error(message)